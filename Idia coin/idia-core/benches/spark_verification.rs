@@ -0,0 +1,89 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use idia_core::crypto::OneOfManyProof;
+use merlin::Transcript;
+use rand::rngs::OsRng;
+use sha2::Sha512;
+
+const SET_SIZE: usize = 256;
+// Spends per block
+const SPEND_COUNTS: [usize; 4] = [1, 4, 16, 64];
+
+struct Spend {
+    proof: OneOfManyProof,
+    offset: RistrettoPoint,
+    nullifier: [u8; 32],
+}
+
+fn transcript(nullifier: &[u8; 32]) -> Transcript {
+    let mut transcript = Transcript::new(b"spark-verification-bench");
+    transcript.append_message(b"nullifier", nullifier);
+    transcript
+}
+
+// Membership proofs over one anonymity set, shaped like Spark spends: each
+// shifts the set by its own offset and opens one member to zero
+fn spends(count: usize, h: &RistrettoPoint) -> (Vec<RistrettoPoint>, Vec<Spend>) {
+    let g = RISTRETTO_BASEPOINT_POINT;
+    let decoys = SET_SIZE - count;
+    let mut set: Vec<RistrettoPoint> = (0..decoys).map(|_| RistrettoPoint::random(&mut OsRng)).collect();
+
+    let members: Vec<(RistrettoPoint, Scalar)> = (0..count)
+        .map(|_| {
+            let offset = RistrettoPoint::random(&mut OsRng);
+            let blinding = Scalar::random(&mut OsRng);
+            (offset, blinding)
+        })
+        .collect();
+    set.extend(members.iter().map(|(offset, blinding)| offset + h * blinding));
+
+    let spends = members
+        .into_iter()
+        .enumerate()
+        .map(|(i, (offset, blinding))| {
+            let nullifier = Scalar::random(&mut OsRng).to_bytes();
+            let proof = OneOfManyProof::prove(&mut transcript(&nullifier), &set, &offset, decoys + i, blinding, &g, h)
+                .unwrap();
+            Spend { proof, offset, nullifier }
+        })
+        .collect();
+    (set, spends)
+}
+
+fn bench_spend_verification(c: &mut Criterion) {
+    let g = RISTRETTO_BASEPOINT_POINT;
+    let h = RistrettoPoint::hash_from_bytes::<Sha512>(b"spark-verification-bench-h");
+    let (set, spends) = spends(*SPEND_COUNTS.last().unwrap(), &h);
+
+    let mut group = c.benchmark_group("spark_verify_batch");
+    group.sample_size(10);
+    for count in SPEND_COUNTS {
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            b.iter(|| {
+                let proofs = spends[..count]
+                    .iter()
+                    .map(|spend| (&spend.proof, transcript(&spend.nullifier), spend.offset));
+                assert!(OneOfManyProof::verify_batch(proofs, &set, &g, &h));
+            });
+        });
+    }
+    group.finish();
+
+    // Baseline: checking the same spends one proof at a time
+    let mut group = c.benchmark_group("spark_verify_individually");
+    group.sample_size(10);
+    for count in SPEND_COUNTS {
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            b.iter(|| {
+                for spend in &spends[..count] {
+                    assert!(spend.proof.verify(transcript(&spend.nullifier), &set, &spend.offset, &g, &h));
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_spend_verification);
+criterion_main!(benches);
@@ -0,0 +1,289 @@
+//! Layered configuration loading
+//!
+//! Settings are read from serde defaults, then each TOML file in order, then
+//! environment variables, each layer overriding the one before. A variable
+//! `IDIA_NETWORK__USE_TOR=true` sets `use_tor` in the `[network]` table;
+//! values are parsed as TOML, so `["a", "b"]` gives an array, and anything
+//! that doesn't parse is taken as a string. Variables without a `__` are
+//! left alone.
+//!
+//! Sections this crate doesn't know, such as `[compliance]` or `[rpc]`, are
+//! kept as raw tables for the crate that owns them to read with
+//! [`IdiaConfig::section`].
+
+use crate::metrics::MetricsConfig;
+use crate::network::{DandelionConfig, NetworkConfig};
+use crate::storage::{StorageBackend, StorageConfig};
+use crate::wallet::WalletConfig;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Configuration error types
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Invalid {field}: {reason}")]
+    Invalid { field: String, reason: String },
+}
+
+impl ConfigError {
+    /// An invalid setting, named by its dotted path
+    pub fn invalid(field: &str, reason: impl Into<String>) -> Self {
+        Self::Invalid {
+            field: field.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Settings for everything in `idia-core`, plus the raw sections of other crates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdiaConfig {
+    /// `[network]`
+    pub network: NetworkConfig,
+    /// `[dandelion]`
+    pub dandelion: DandelionConfig,
+    /// `[wallet]`
+    pub wallet: WalletConfig,
+    /// `[metrics]`
+    pub metrics: MetricsConfig,
+    /// `[storage]`
+    pub storage: StorageConfig,
+    /// Every other section, by name
+    #[serde(flatten)]
+    pub sections: Table,
+}
+
+impl IdiaConfig {
+    /// Deserialize the section called `name`, if present
+    pub fn section<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, ConfigError> {
+        match self.sections.get(name) {
+            Some(value) => Ok(Some(value.clone().try_into()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Check the core sections for values that can't work
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.network.use_tor && self.network.tor_proxy.is_none() {
+            return Err(ConfigError::invalid("network.tor_proxy", "required when use_tor is set"));
+        }
+        if self.network.listen_addresses.is_empty() {
+            return Err(ConfigError::invalid("network.listen_addresses", "must not be empty"));
+        }
+        let fluff = self.dandelion.fluff_probability;
+        if !(fluff > 0.0 && fluff <= 1.0) {
+            return Err(ConfigError::invalid("dandelion.fluff_probability", "must be in (0, 1]"));
+        }
+        if self.dandelion.stem_timeout.is_zero() {
+            return Err(ConfigError::invalid("dandelion.stem_timeout", "must be positive"));
+        }
+        if self.wallet.ring_size < 2 {
+            return Err(ConfigError::invalid("wallet.ring_size", "must be at least 2"));
+        }
+        if self.metrics.token.as_deref() == Some("") {
+            return Err(ConfigError::invalid("metrics.token", "must not be empty; omit it to disable auth"));
+        }
+        if self.storage.backend == StorageBackend::Rocksdb && self.storage.path.as_os_str().is_empty() {
+            return Err(ConfigError::invalid("storage.path", "required for the rocksdb backend"));
+        }
+        Ok(())
+    }
+}
+
+/// Builds an [`IdiaConfig`] from files and the environment
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    files: Vec<PathBuf>,
+    env_prefix: Option<String>,
+}
+
+impl ConfigLoader {
+    /// A loader with no layers beyond the defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a TOML file layer; files added later override earlier ones
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.files.push(path.into());
+        self
+    }
+
+    /// Apply `<prefix>_SECTION__KEY` environment variables last
+    pub fn env(mut self, prefix: &str) -> Self {
+        self.env_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Merge the layers into one table without interpreting it
+    pub fn load_table(&self) -> Result<Table, ConfigError> {
+        let mut table = Table::new();
+        for path in &self.files {
+            merge(&mut table, read_file(path)?);
+        }
+        if let Some(prefix) = &self.env_prefix {
+            apply_env(&mut table, prefix, std::env::vars());
+        }
+        Ok(table)
+    }
+
+    /// Load and validate the configuration
+    pub fn load(&self) -> Result<IdiaConfig, ConfigError> {
+        let config: IdiaConfig = Value::Table(self.load_table()?).try_into()?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn read_file(path: &Path) -> Result<Table, ConfigError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(contents.parse()?)
+}
+
+/// Overlay `layer` onto `base`, merging tables key by key
+pub fn merge(base: &mut Table, layer: Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(overlay)) => merge(existing, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Apply `<prefix>_SECTION__KEY=value` overrides from `vars` to `table`
+pub fn apply_env(table: &mut Table, prefix: &str, vars: impl IntoIterator<Item = (String, String)>) {
+    let prefix = format!("{}_", prefix);
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let keys: Vec<String> = path.split("__").map(str::to_ascii_lowercase).collect();
+        if keys.len() < 2 || keys.iter().any(String::is_empty) {
+            continue;
+        }
+
+        let value = format!("value = {}", raw)
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut parsed| parsed.remove("value"))
+            .unwrap_or(Value::String(raw));
+        let mut target = &mut *table;
+        for key in &keys[..keys.len() - 1] {
+            let entry = target.entry(key.clone()).or_insert_with(|| Value::Table(Table::new()));
+            if !entry.is_table() {
+                *entry = Value::Table(Table::new());
+            }
+            target = entry.as_table_mut().expect("just made a table");
+        }
+        target.insert(keys[keys.len() - 1].clone(), value);
+    }
+}
+
+/// Serde adapter storing a `Duration` as whole seconds
+pub mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_secs(u64::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("base.toml");
+        let local = dir.path().join("local.toml");
+        std::fs::write(&base, "[network]\nuse_dandelion = false\nbootstrap_nodes = [\"a\"]\n[dandelion]\nstem_timeout = 60\n").unwrap();
+        std::fs::write(&local, "[network]\nbootstrap_nodes = [\"b\"]\n").unwrap();
+
+        let mut table = ConfigLoader::new().file(&base).file(&local).load_table().unwrap();
+        apply_env(&mut table, "IDIA", vars(&[("IDIA_DANDELION__FLUFF_PROBABILITY", "0.5")]));
+        let config: IdiaConfig = Value::Table(table).try_into().unwrap();
+
+        assert!(!config.network.use_dandelion);
+        assert_eq!(config.network.bootstrap_nodes, vec!["b".to_string()]);
+        assert_eq!(config.dandelion.stem_timeout, Duration::from_secs(60));
+        assert_eq!(config.dandelion.fluff_probability, 0.5);
+        // Untouched settings keep their defaults
+        assert_eq!(config.wallet.ring_size, WalletConfig::default().ring_size);
+    }
+
+    #[test]
+    fn test_env_values() {
+        let mut table = Table::new();
+        apply_env(
+            &mut table,
+            "IDIA",
+            vars(&[
+                ("IDIA_NETWORK__USE_TOR", "true"),
+                ("IDIA_NETWORK__TOR_PROXY", "127.0.0.1:9050"),
+                ("IDIA_RPC__TOKEN", "secret"),
+                ("IDIA_RPC_TOKEN", "ignored"),
+                ("OTHER_NETWORK__USE_TOR", "false"),
+            ]),
+        );
+        let config: IdiaConfig = Value::Table(table).try_into().unwrap();
+
+        assert!(config.network.use_tor);
+        assert_eq!(config.network.tor_proxy.as_deref(), Some("127.0.0.1:9050"));
+        assert_eq!(config.sections.len(), 1);
+        #[derive(Deserialize)]
+        struct Rpc {
+            token: String,
+        }
+        assert_eq!(config.section::<Rpc>("rpc").unwrap().unwrap().token, "secret");
+        assert!(config.section::<Rpc>("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(IdiaConfig::default().validate().is_ok());
+
+        let mut config = IdiaConfig::default();
+        config.network.use_tor = true;
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid { field, .. }) if field == "network.tor_proxy"));
+
+        let mut config = IdiaConfig::default();
+        config.dandelion.fluff_probability = 0.0;
+        assert!(config.validate().is_err());
+
+        let mut config = IdiaConfig::default();
+        config.wallet.ring_size = 1;
+        assert!(config.validate().is_err());
+
+        let mut config = IdiaConfig::default();
+        config.storage.path = PathBuf::new();
+        assert!(config.validate().is_err());
+        config.storage.backend = StorageBackend::Memory;
+        assert!(config.validate().is_ok());
+    }
+}
@@ -0,0 +1,258 @@
+//! Schnorr signatures and Schnorr adaptor signatures over Ristretto
+//!
+//! An adaptor signature (pre-signature) is a signature encrypted under an
+//! adaptor point `T = t·G`. Anyone can check that it will become a valid
+//! signature once adapted with `t`, and whoever sees both the pre-signature
+//! and the final signature learns `t`. Atomic swaps use this in place of a
+//! hashlock: publishing a claim signature reveals the secret on its own.
+
+use super::*;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use merlin::Transcript;
+
+/// Fiat-Shamir challenge binding the nonce point, the public key and the message
+fn challenge(nonce: &RistrettoPoint, public_key: &RistrettoPoint, message: &[u8]) -> Scalar {
+    let mut transcript = Transcript::new(b"idia-schnorr");
+    transcript.append_message(b"R", nonce.compress().as_bytes());
+    transcript.append_message(b"P", public_key.compress().as_bytes());
+    transcript.append_message(b"m", message);
+    let mut bytes = [0u8; 64];
+    transcript.challenge_bytes(b"e", &mut bytes);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+/// A Schnorr signature `(R, s)` with `s·G = R + e·P`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchnorrSignature {
+    pub nonce: CompressedRistretto,
+    pub s: Scalar,
+}
+
+impl SchnorrSignature {
+    /// Sign `message` with `secret_key`
+    pub fn sign(secret_key: &Scalar, message: &[u8]) -> Self {
+        let public_key = RISTRETTO_BASEPOINT_POINT * secret_key;
+        let k = Scalar::random(&mut OsRng);
+        let nonce = RISTRETTO_BASEPOINT_POINT * k;
+        let e = challenge(&nonce, &public_key, message);
+        Self {
+            nonce: nonce.compress(),
+            s: k + e * secret_key,
+        }
+    }
+
+    /// Verify against `public_key`
+    pub fn verify(&self, public_key: &RistrettoPoint, message: &[u8]) -> bool {
+        let Some(nonce) = self.nonce.decompress() else {
+            return false;
+        };
+        let e = challenge(&nonce, public_key, message);
+        RISTRETTO_BASEPOINT_POINT * self.s == nonce + public_key * e
+    }
+
+    /// Serialize as nonce then scalar, 64 bytes
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.nonce.as_bytes());
+        bytes[32..].copy_from_slice(self.s.as_bytes());
+        bytes
+    }
+
+    /// Parse the 64-byte encoding, rejecting non-canonical scalars
+    pub fn from_bytes(bytes: &[u8; 64]) -> Result<Self, CryptoError> {
+        let nonce = CompressedRistretto::from_slice(&bytes[..32]).map_err(|_| CryptoError::InvalidKey)?;
+        let s = Option::from(Scalar::from_canonical_bytes(bytes[32..].try_into().expect("32 bytes")))
+            .ok_or(CryptoError::SignatureVerification)?;
+        Ok(Self { nonce, s })
+    }
+}
+
+/// A Schnorr signature encrypted under an adaptor point `T`.
+///
+/// `nonce` is the final signature's nonce `R = k·G + T`; `s_hat = k + e·x`
+/// misses exactly `t` from a valid signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptorSignature {
+    pub nonce: CompressedRistretto,
+    pub s_hat: Scalar,
+}
+
+impl AdaptorSignature {
+    /// Pre-sign `message` with `secret_key`, encrypted under `adaptor_point`
+    pub fn presign(secret_key: &Scalar, message: &[u8], adaptor_point: &RistrettoPoint) -> Self {
+        let public_key = RISTRETTO_BASEPOINT_POINT * secret_key;
+        let k = Scalar::random(&mut OsRng);
+        let nonce = RISTRETTO_BASEPOINT_POINT * k + adaptor_point;
+        let e = challenge(&nonce, &public_key, message);
+        Self {
+            nonce: nonce.compress(),
+            s_hat: k + e * secret_key,
+        }
+    }
+
+    /// Check that adapting with the discrete log of `adaptor_point` yields a
+    /// valid signature by `public_key` on `message`
+    pub fn verify(&self, public_key: &RistrettoPoint, message: &[u8], adaptor_point: &RistrettoPoint) -> bool {
+        let Some(nonce) = self.nonce.decompress() else {
+            return false;
+        };
+        let e = challenge(&nonce, public_key, message);
+        RISTRETTO_BASEPOINT_POINT * self.s_hat == nonce - adaptor_point + public_key * e
+    }
+
+    /// Complete the signature with the adaptor secret
+    pub fn adapt(&self, secret: &Scalar) -> SchnorrSignature {
+        SchnorrSignature {
+            nonce: self.nonce,
+            s: self.s_hat + secret,
+        }
+    }
+
+    /// Recover the adaptor secret from the completed signature, checking it
+    /// against `adaptor_point`
+    pub fn extract(&self, signature: &SchnorrSignature, adaptor_point: &RistrettoPoint) -> Option<Scalar> {
+        if signature.nonce != self.nonce {
+            return None;
+        }
+        let secret = signature.s - self.s_hat;
+        (RISTRETTO_BASEPOINT_POINT * secret == *adaptor_point).then_some(secret)
+    }
+}
+
+/// Two-party MuSig aggregate of Schnorr keys.
+///
+/// Signatures under it are ordinary [`SchnorrSignature`]s by `public_key`,
+/// so an output locked to it looks like any other. Each key is weighted by a
+/// hash of both keys, so neither party can pick a key cancelling the other's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JointKey {
+    pub public_key: RistrettoPoint,
+    keys: [RistrettoPoint; 2],
+    coefficients: [Scalar; 2],
+}
+
+impl JointKey {
+    pub fn new(a: &RistrettoPoint, b: &RistrettoPoint) -> Self {
+        let mut keys = [*a, *b];
+        keys.sort_by_key(|key| key.compress().to_bytes());
+        let coefficients = keys.map(|key| {
+            let mut transcript = Transcript::new(b"idia-musig");
+            transcript.append_message(b"P0", keys[0].compress().as_bytes());
+            transcript.append_message(b"P1", keys[1].compress().as_bytes());
+            transcript.append_message(b"P", key.compress().as_bytes());
+            let mut bytes = [0u8; 64];
+            transcript.challenge_bytes(b"a", &mut bytes);
+            Scalar::from_bytes_mod_order_wide(&bytes)
+        });
+        Self {
+            public_key: keys[0] * coefficients[0] + keys[1] * coefficients[1],
+            keys,
+            coefficients,
+        }
+    }
+
+    fn coefficient(&self, public_key: &RistrettoPoint) -> Option<Scalar> {
+        self.keys
+            .iter()
+            .position(|key| key == public_key)
+            .map(|i| self.coefficients[i])
+    }
+
+    /// Our share of the signature on `message` with nonce point `nonce`, the
+    /// sum of both parties' nonce points and the adaptor point, if any
+    pub fn partial_sign(&self, secret_key: &Scalar, secret_nonce: &Scalar, nonce: &RistrettoPoint, message: &[u8]) -> Option<Scalar> {
+        let a = self.coefficient(&(RISTRETTO_BASEPOINT_POINT * secret_key))?;
+        let e = challenge(nonce, &self.public_key, message);
+        Some(secret_nonce + e * a * secret_key)
+    }
+
+    /// Check the other party's share against their key and nonce point
+    pub fn verify_partial(
+        &self,
+        public_key: &RistrettoPoint,
+        public_nonce: &RistrettoPoint,
+        nonce: &RistrettoPoint,
+        message: &[u8],
+        partial: &Scalar,
+    ) -> bool {
+        let Some(a) = self.coefficient(public_key) else {
+            return false;
+        };
+        let e = challenge(nonce, &self.public_key, message);
+        RISTRETTO_BASEPOINT_POINT * partial == public_nonce + public_key * (e * a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schnorr_signature() {
+        let secret = Scalar::random(&mut OsRng);
+        let public = RISTRETTO_BASEPOINT_POINT * secret;
+
+        let sig = SchnorrSignature::sign(&secret, b"message");
+        assert!(sig.verify(&public, b"message"));
+        assert!(!sig.verify(&public, b"other message"));
+        assert_eq!(SchnorrSignature::from_bytes(&sig.to_bytes()).unwrap(), sig);
+    }
+
+    #[test]
+    fn test_adaptor_signature_round_trip() {
+        let secret = Scalar::random(&mut OsRng);
+        let public = RISTRETTO_BASEPOINT_POINT * secret;
+        let t = Scalar::random(&mut OsRng);
+        let adaptor_point = RISTRETTO_BASEPOINT_POINT * t;
+
+        let presig = AdaptorSignature::presign(&secret, b"claim", &adaptor_point);
+        assert!(presig.verify(&public, b"claim", &adaptor_point));
+        // A pre-signature is not a signature until adapted
+        assert!(!SchnorrSignature { nonce: presig.nonce, s: presig.s_hat }.verify(&public, b"claim"));
+
+        let sig = presig.adapt(&t);
+        assert!(sig.verify(&public, b"claim"));
+        assert_eq!(presig.extract(&sig, &adaptor_point), Some(t));
+    }
+
+    #[test]
+    fn test_adaptor_wrong_secret() {
+        let secret = Scalar::random(&mut OsRng);
+        let public = RISTRETTO_BASEPOINT_POINT * secret;
+        let adaptor_point = RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng);
+
+        let presig = AdaptorSignature::presign(&secret, b"claim", &adaptor_point);
+        let sig = presig.adapt(&Scalar::random(&mut OsRng));
+        assert!(!sig.verify(&public, b"claim"));
+        assert_eq!(presig.extract(&sig, &adaptor_point), None);
+    }
+
+    #[test]
+    fn test_joint_key_adaptor_signature() {
+        let keys = [Scalar::random(&mut OsRng), Scalar::random(&mut OsRng)];
+        let publics = keys.map(|key| RISTRETTO_BASEPOINT_POINT * key);
+        let joint = JointKey::new(&publics[0], &publics[1]);
+        assert_eq!(JointKey::new(&publics[1], &publics[0]), joint);
+
+        let t = Scalar::random(&mut OsRng);
+        let adaptor_point = RISTRETTO_BASEPOINT_POINT * t;
+        let secret_nonces = [Scalar::random(&mut OsRng), Scalar::random(&mut OsRng)];
+        let public_nonces = secret_nonces.map(|k| RISTRETTO_BASEPOINT_POINT * k);
+        let nonce = public_nonces[0] + public_nonces[1] + adaptor_point;
+
+        let partials = [0, 1].map(|i| joint.partial_sign(&keys[i], &secret_nonces[i], &nonce, b"claim").unwrap());
+        assert!(joint.verify_partial(&publics[0], &public_nonces[0], &nonce, b"claim", &partials[0]));
+        assert!(!joint.verify_partial(&publics[1], &public_nonces[1], &nonce, b"claim", &partials[0]));
+        assert!(joint.partial_sign(&Scalar::random(&mut OsRng), &secret_nonces[0], &nonce, b"claim").is_none());
+
+        let presig = AdaptorSignature {
+            nonce: nonce.compress(),
+            s_hat: partials[0] + partials[1],
+        };
+        assert!(presig.verify(&joint.public_key, b"claim", &adaptor_point));
+        let sig = presig.adapt(&t);
+        assert!(sig.verify(&joint.public_key, b"claim"));
+        assert!(!sig.verify(&publics[0], b"claim"));
+        assert_eq!(presig.extract(&sig, &adaptor_point), Some(t));
+    }
+}
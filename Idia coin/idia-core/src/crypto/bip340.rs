@@ -0,0 +1,361 @@
+//! BIP340 Schnorr signatures over secp256k1, with adaptor signatures and a
+//! two-party joint key
+//!
+//! Taproot key-path spends verify these signatures as they are, so the
+//! Bitcoin leg of a scriptless swap can be an ordinary single-key output.
+//! The key is a MuSig aggregate of both parties' keys, tweaked the way BIP86
+//! tweaks a key-path-only output, and each spend carries one signature the
+//! parties produce together. Each party contributes a single nonce per
+//! signature, which is safe because swap keys are fresh and sign nothing but
+//! one claim and one refund.
+
+use super::CryptoError;
+use k256::elliptic_curve::group::GroupEncoding;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::{AffineCoordinates, DecompressPoint};
+use k256::elliptic_curve::subtle::Choice;
+use k256::elliptic_curve::{Field, PrimeField};
+use k256::{AffinePoint, U256};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+pub use k256::{ProjectivePoint as SecpPoint, Scalar as SecpScalar};
+
+/// BIP340's tagged hash, `SHA256(SHA256(tag) || SHA256(tag) || data)`
+fn tagged_hash(tag: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let tag = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag);
+    hasher.update(tag);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn reduce(bytes: [u8; 32]) -> SecpScalar {
+    <SecpScalar as Reduce<U256>>::reduce_bytes(&bytes.into())
+}
+
+fn has_even_y(point: &SecpPoint) -> bool {
+    !bool::from(point.to_affine().y_is_odd())
+}
+
+fn negate_if(value: SecpScalar, negate: bool) -> SecpScalar {
+    if negate { -value } else { value }
+}
+
+/// The x coordinate, all that an x-only key or a signature's nonce keeps
+pub(crate) fn x_only(point: &SecpPoint) -> [u8; 32] {
+    point.to_affine().x().into()
+}
+
+/// The point with x coordinate `x` and even y
+pub(crate) fn lift_x(x: &[u8; 32]) -> Option<SecpPoint> {
+    Option::<AffinePoint>::from(AffinePoint::decompress(&(*x).into(), Choice::from(0))).map(SecpPoint::from)
+}
+
+fn challenge(nonce: &SecpPoint, public_key: &[u8; 32], message: &[u8]) -> SecpScalar {
+    reduce(tagged_hash("BIP0340/challenge", &[&x_only(nonce)[..], &public_key[..], message]))
+}
+
+/// A fresh secret nonce and its public point
+pub fn secp_nonce() -> (SecpScalar, SecpPoint) {
+    let k = SecpScalar::random(&mut OsRng);
+    (k, SecpPoint::GENERATOR * k)
+}
+
+/// A BIP340 signature, as a taproot key-path witness carries it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bip340Signature {
+    /// x coordinate of the nonce point, whose y is even
+    pub nonce_x: [u8; 32],
+    pub s: SecpScalar,
+}
+
+impl Bip340Signature {
+    /// Verify against the x-only `public_key`
+    pub fn verify(&self, public_key: &[u8; 32], message: &[u8]) -> bool {
+        let Some(key) = lift_x(public_key) else {
+            return false;
+        };
+        let e = reduce(tagged_hash(
+            "BIP0340/challenge",
+            &[&self.nonce_x[..], &public_key[..], message],
+        ));
+        let nonce = SecpPoint::GENERATOR * self.s - key * e;
+        nonce != SecpPoint::IDENTITY && has_even_y(&nonce) && x_only(&nonce) == self.nonce_x
+    }
+
+    /// Serialize as BIP340 does: nonce x then `s`, big-endian, 64 bytes
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.nonce_x);
+        bytes[32..].copy_from_slice(&self.s.to_bytes());
+        bytes
+    }
+
+    /// Parse the 64-byte encoding, rejecting an `s` outside the group order
+    pub fn from_bytes(bytes: &[u8; 64]) -> Result<Self, CryptoError> {
+        let nonce_x = bytes[..32].try_into().expect("32 bytes");
+        let s: [u8; 32] = bytes[32..].try_into().expect("32 bytes");
+        let s = Option::from(SecpScalar::from_repr(s.into())).ok_or(CryptoError::SignatureVerification)?;
+        Ok(Self { nonce_x, s })
+    }
+}
+
+/// A BIP340 signature encrypted under an adaptor point `T`.
+///
+/// `nonce` is the full nonce point `R`, adaptor point included, before
+/// BIP340 makes its y even. When `R`'s y is odd the signature uses `-R`, so
+/// the adaptor secret enters `s` negated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bip340AdaptorSignature {
+    pub nonce: SecpPoint,
+    pub s_hat: SecpScalar,
+}
+
+impl Bip340AdaptorSignature {
+    fn negated(&self) -> bool {
+        !has_even_y(&self.nonce)
+    }
+
+    /// Check that adapting with the discrete log of `adaptor_point` yields a
+    /// valid signature by the x-only `public_key` on `message`
+    pub fn verify(&self, public_key: &[u8; 32], message: &[u8], adaptor_point: &SecpPoint) -> bool {
+        let Some(key) = lift_x(public_key) else {
+            return false;
+        };
+        if self.nonce == SecpPoint::IDENTITY {
+            return false;
+        }
+        let e = challenge(&self.nonce, public_key, message);
+        let nonce = self.nonce - adaptor_point;
+        let nonce = if self.negated() { -nonce } else { nonce };
+        SecpPoint::GENERATOR * self.s_hat == nonce + key * e
+    }
+
+    /// Complete the signature with the adaptor secret
+    pub fn adapt(&self, secret: &SecpScalar) -> Bip340Signature {
+        Bip340Signature {
+            nonce_x: x_only(&self.nonce),
+            s: self.s_hat + negate_if(*secret, self.negated()),
+        }
+    }
+
+    /// Recover the adaptor secret from the completed signature, checking it
+    /// against `adaptor_point`
+    pub fn extract(&self, signature: &Bip340Signature, adaptor_point: &SecpPoint) -> Option<SecpScalar> {
+        if signature.nonce_x != x_only(&self.nonce) {
+            return None;
+        }
+        let secret = negate_if(signature.s - self.s_hat, self.negated());
+        (SecpPoint::GENERATOR * secret == *adaptor_point).then_some(secret)
+    }
+}
+
+/// Two-party MuSig aggregate of secp256k1 keys with the BIP86 taproot tweak
+/// applied, so `output_key` is what a key-path-only taproot output commits to.
+///
+/// Partial signatures follow BIP327: each signer folds the output key's and
+/// the internal key's parities into its share, and the tweak's share is
+/// added when the partials are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bip340JointKey {
+    pub output_key: SecpPoint,
+    keys: [SecpPoint; 2],
+    coefficients: [SecpScalar; 2],
+    internal_negated: bool,
+    tweak: SecpScalar,
+}
+
+impl Bip340JointKey {
+    pub fn new(a: &SecpPoint, b: &SecpPoint) -> Self {
+        let mut keys = [*a, *b];
+        keys.sort_by_key(|key| key.to_bytes().to_vec());
+        let list = tagged_hash("KeyAgg list", &[&keys[0].to_bytes()[..], &keys[1].to_bytes()[..]]);
+        let coefficients = keys.map(|key| reduce(tagged_hash("KeyAgg coefficient", &[&list[..], &key.to_bytes()[..]])));
+        let internal = keys[0] * coefficients[0] + keys[1] * coefficients[1];
+        let internal_negated = !has_even_y(&internal);
+        let tweak = reduce(tagged_hash("TapTweak", &[&x_only(&internal)[..]]));
+        let even_internal = if internal_negated { -internal } else { internal };
+        Self {
+            output_key: even_internal + SecpPoint::GENERATOR * tweak,
+            keys,
+            coefficients,
+            internal_negated,
+            tweak,
+        }
+    }
+
+    /// The x-only output key, as the taproot output and verifiers see it
+    pub fn output_key_x(&self) -> [u8; 32] {
+        x_only(&self.output_key)
+    }
+
+    fn output_negated(&self) -> bool {
+        !has_even_y(&self.output_key)
+    }
+
+    fn coefficient(&self, public_key: &SecpPoint) -> Option<SecpScalar> {
+        self.keys
+            .iter()
+            .position(|key| key == public_key)
+            .map(|i| self.coefficients[i])
+    }
+
+    /// Our share of the signature on `message` with nonce point `nonce`, the
+    /// sum of both parties' nonce points and the adaptor point, if any
+    pub fn partial_sign(
+        &self,
+        secret_key: &SecpScalar,
+        secret_nonce: &SecpScalar,
+        nonce: &SecpPoint,
+        message: &[u8],
+    ) -> Option<SecpScalar> {
+        let a = self.coefficient(&(SecpPoint::GENERATOR * secret_key))?;
+        let e = challenge(nonce, &self.output_key_x(), message);
+        let k = negate_if(*secret_nonce, !has_even_y(nonce));
+        let d = negate_if(*secret_key, self.output_negated() != self.internal_negated);
+        Some(k + e * a * d)
+    }
+
+    /// Check the other party's share against their key and nonce point
+    pub fn verify_partial(
+        &self,
+        public_key: &SecpPoint,
+        public_nonce: &SecpPoint,
+        nonce: &SecpPoint,
+        message: &[u8],
+        partial: &SecpScalar,
+    ) -> bool {
+        let Some(a) = self.coefficient(public_key) else {
+            return false;
+        };
+        let e = challenge(nonce, &self.output_key_x(), message);
+        let public_nonce = if has_even_y(nonce) { *public_nonce } else { -*public_nonce };
+        let public_key = if self.output_negated() != self.internal_negated { -*public_key } else { *public_key };
+        SecpPoint::GENERATOR * partial == public_nonce + public_key * (e * a)
+    }
+
+    /// Sum both shares into the pre-signature for `nonce`; with no adaptor
+    /// point, adapting it with zero gives the final signature
+    pub fn combine(&self, nonce: &SecpPoint, message: &[u8], partials: [SecpScalar; 2]) -> Bip340AdaptorSignature {
+        let e = challenge(nonce, &self.output_key_x(), message);
+        Bip340AdaptorSignature {
+            nonce: *nonce,
+            s_hat: partials[0] + partials[1] + e * negate_if(self.tweak, self.output_negated()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        bytes
+    }
+
+    struct Signer {
+        key: SecpScalar,
+        nonce: (SecpScalar, SecpPoint),
+    }
+
+    impl Signer {
+        fn new() -> Self {
+            Self {
+                key: SecpScalar::random(&mut OsRng),
+                nonce: secp_nonce(),
+            }
+        }
+
+        fn public_key(&self) -> SecpPoint {
+            SecpPoint::GENERATOR * self.key
+        }
+    }
+
+    fn joint_presign(
+        a: &Signer,
+        b: &Signer,
+        message: &[u8],
+        adaptor_point: &SecpPoint,
+    ) -> (Bip340JointKey, Bip340AdaptorSignature) {
+        let joint = Bip340JointKey::new(&a.public_key(), &b.public_key());
+        let nonce = a.nonce.1 + b.nonce.1 + adaptor_point;
+        let partials = [a, b].map(|signer| {
+            let partial = joint.partial_sign(&signer.key, &signer.nonce.0, &nonce, message).unwrap();
+            assert!(joint.verify_partial(&signer.public_key(), &signer.nonce.1, &nonce, message, &partial));
+            partial
+        });
+        (joint, joint.combine(&nonce, message, partials))
+    }
+
+    #[test]
+    fn test_bip340_test_vector() {
+        // BIP340 test vector 0
+        let public_key = from_hex("F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9");
+        let signature = Bip340Signature::from_bytes(&from_hex(
+            "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA8215\
+             25F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0",
+        ))
+        .unwrap();
+        assert!(signature.verify(&public_key, &[0u8; 32]));
+        assert!(!signature.verify(&public_key, &[1u8; 32]));
+        assert_eq!(x_only(&(SecpPoint::GENERATOR * SecpScalar::from(3u64))), public_key);
+    }
+
+    #[test]
+    fn test_joint_signature() {
+        for _ in 0..8 {
+            let (a, b) = (Signer::new(), Signer::new());
+            let (joint, presig) = joint_presign(&a, &b, b"refund", &SecpPoint::IDENTITY);
+            let signature = presig.adapt(&SecpScalar::ZERO);
+            assert!(signature.verify(&joint.output_key_x(), b"refund"));
+            assert!(!signature.verify(&joint.output_key_x(), b"claim"));
+            assert_eq!(Bip340Signature::from_bytes(&signature.to_bytes()).unwrap(), signature);
+            // Order of the keys does not matter
+            assert_eq!(Bip340JointKey::new(&b.public_key(), &a.public_key()), joint);
+        }
+    }
+
+    #[test]
+    fn test_adaptor_round_trip() {
+        // Repeat so both nonce and key parities come up
+        for _ in 0..8 {
+            let (a, b) = (Signer::new(), Signer::new());
+            let t = SecpScalar::random(&mut OsRng);
+            let adaptor_point = SecpPoint::GENERATOR * t;
+            let (joint, presig) = joint_presign(&a, &b, b"claim", &adaptor_point);
+            let key = joint.output_key_x();
+
+            assert!(presig.verify(&key, b"claim", &adaptor_point));
+            assert!(!presig.verify(&key, b"refund", &adaptor_point));
+            assert!(!presig.adapt(&SecpScalar::ZERO).verify(&key, b"claim"));
+
+            let signature = presig.adapt(&t);
+            assert!(signature.verify(&key, b"claim"));
+            assert_eq!(presig.extract(&signature, &adaptor_point), Some(t));
+
+            let wrong = presig.adapt(&(t + SecpScalar::ONE));
+            assert!(!wrong.verify(&key, b"claim"));
+            assert_eq!(presig.extract(&wrong, &adaptor_point), None);
+        }
+    }
+
+    #[test]
+    fn test_rejects_partial_from_outsider() {
+        let (a, b, outsider) = (Signer::new(), Signer::new(), Signer::new());
+        let joint = Bip340JointKey::new(&a.public_key(), &b.public_key());
+        let nonce = a.nonce.1 + b.nonce.1;
+        assert!(joint.partial_sign(&outsider.key, &outsider.nonce.0, &nonce, b"claim").is_none());
+
+        let partial = joint.partial_sign(&a.key, &a.nonce.0, &nonce, b"claim").unwrap();
+        assert!(!joint.verify_partial(&b.public_key(), &b.nonce.1, &nonce, b"claim", &partial));
+        assert!(!joint.verify_partial(&a.public_key(), &a.nonce.1, &nonce, b"refund", &partial));
+    }
+}
@@ -0,0 +1,318 @@
+//! Proof that a Ristretto point and a secp256k1 point share a discrete log
+//!
+//! A scriptless swap between Idia and Bitcoin locks each leg under an
+//! adaptor point on that chain's curve. The participant must know both
+//! points hide the same secret before locking, or the initiator could claim
+//! the Bitcoin leg with a secret that opens nothing on Idia. As in MRL-0010,
+//! the secret is committed bit by bit in both groups, and for each bit a
+//! two-member ring signature spanning both groups under one challenge shows
+//! the two commitments hold the same bit. Challenges stay below 2^250 and
+//! secrets below 2^252, so each is the same integer in both groups.
+
+use super::bip340::{lift_x, SecpPoint, SecpScalar};
+use super::*;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::traits::Identity;
+use k256::elliptic_curve::group::GroupEncoding;
+use k256::elliptic_curve::{Field, PrimeField};
+use rand::RngCore;
+use sha2::Sha512;
+
+/// Bits of the secret the proof covers
+pub const DLEQ_BITS: usize = 252;
+
+/// x coordinate of BIP341's point with no known discrete log
+const SECP_H_X: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// A random secret below 2^252, usable as a scalar on both curves
+pub fn cross_group_secret() -> Scalar {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[31] &= 0x0f;
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+/// The same integer as a secp256k1 scalar, if it is below 2^252
+pub fn to_secp_scalar(secret: &Scalar) -> Option<SecpScalar> {
+    let mut bytes = secret.to_bytes();
+    if bytes[31] & 0xf0 != 0 {
+        return None;
+    }
+    bytes.reverse();
+    Option::from(SecpScalar::from_repr(bytes.into()))
+}
+
+/// The same integer as a Ristretto scalar, if it is below 2^252
+pub fn to_ristretto_scalar(secret: &SecpScalar) -> Option<Scalar> {
+    let mut bytes: [u8; 32] = secret.to_bytes().into();
+    if bytes[0] & 0xf0 != 0 {
+        return None;
+    }
+    bytes.reverse();
+    Some(Scalar::from_bytes_mod_order(bytes))
+}
+
+/// Second generators for the bit commitments
+struct Generators {
+    h: RistrettoPoint,
+    secp_h: SecpPoint,
+}
+
+impl Generators {
+    fn new() -> Self {
+        Self {
+            h: RistrettoPoint::hash_from_bytes::<Sha512>(b"idia-dleq-h"),
+            secp_h: lift_x(&SECP_H_X).expect("BIP341 H is on the curve"),
+        }
+    }
+
+    /// Nonces of the ring member claiming the commitments hold `bit`,
+    /// recomputed from its challenge and responses
+    fn member_nonces(
+        &self,
+        bit: u64,
+        commitments: (&RistrettoPoint, &SecpPoint),
+        challenge: &[u8; 32],
+        responses: (&Scalar, &SecpScalar),
+    ) -> (RistrettoPoint, SecpPoint) {
+        let ristretto = self.h * responses.0
+            - (commitments.0 - RISTRETTO_BASEPOINT_POINT * Scalar::from(bit)) * Scalar::from_bytes_mod_order(*challenge);
+        let secp = self.secp_h * responses.1
+            - (*commitments.1 - SecpPoint::GENERATOR * SecpScalar::from(bit)) * secp_challenge(challenge);
+        (ristretto, secp)
+    }
+}
+
+fn secp_challenge(challenge: &[u8; 32]) -> SecpScalar {
+    let mut bytes = *challenge;
+    bytes.reverse();
+    Option::from(SecpScalar::from_repr(bytes.into())).expect("challenges are below the group order")
+}
+
+/// Hash binding both public points, so a proof cannot be replayed for others
+fn statement(ristretto_point: &RistrettoPoint, secp_point: &SecpPoint) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"idia-dleq");
+    hasher.update(ristretto_point.compress().as_bytes());
+    hasher.update(secp_point.to_bytes());
+    hasher.finalize().into()
+}
+
+/// Ring challenge for bit `index`, little-endian and below 2^250
+fn ring_challenge(
+    statement: &[u8; 32],
+    index: usize,
+    commitments: (&RistrettoPoint, &SecpPoint),
+    nonces: (&RistrettoPoint, &SecpPoint),
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(statement);
+    hasher.update((index as u64).to_le_bytes());
+    hasher.update(commitments.0.compress().as_bytes());
+    hasher.update(commitments.1.to_bytes());
+    hasher.update(nonces.0.compress().as_bytes());
+    hasher.update(nonces.1.to_bytes());
+    let mut challenge: [u8; 32] = hasher.finalize().into();
+    challenge[31] &= 0x03;
+    challenge
+}
+
+/// Per-bit blinding factors whose sum weighted by 2^i is zero, so the
+/// weighted commitments add up to the bare public point
+fn ristretto_blindings() -> Vec<Scalar> {
+    let mut blindings: Vec<Scalar> = (1..DLEQ_BITS).map(|_| Scalar::random(&mut OsRng)).collect();
+    let (mut weight, mut sum) = (Scalar::ONE, Scalar::ZERO);
+    for blinding in &blindings {
+        sum += weight * blinding;
+        weight += weight;
+    }
+    blindings.push(-sum * weight.invert());
+    blindings
+}
+
+fn secp_blindings() -> Vec<SecpScalar> {
+    let mut blindings: Vec<SecpScalar> = (1..DLEQ_BITS).map(|_| SecpScalar::random(&mut OsRng)).collect();
+    let (mut weight, mut sum) = (SecpScalar::ONE, SecpScalar::ZERO);
+    for blinding in &blindings {
+        sum += weight * blinding;
+        weight += weight;
+    }
+    blindings.push(-sum * weight.invert().unwrap());
+    blindings
+}
+
+/// Proof that `x·G` on Ristretto and `x·G` on secp256k1 share `x`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossGroupDleq {
+    /// Per bit `b·G + r·H` on Ristretto
+    pub ristretto_commitments: Vec<RistrettoPoint>,
+    /// Per bit `b·G + s·H` on secp256k1
+    pub secp_commitments: Vec<SecpPoint>,
+    /// Per bit, the ring's challenge to the member claiming a zero bit
+    pub challenges: Vec<[u8; 32]>,
+    pub ristretto_responses: Vec<[Scalar; 2]>,
+    pub secp_responses: Vec<[SecpScalar; 2]>,
+}
+
+impl CrossGroupDleq {
+    /// Prove for `secret`, which must be below 2^252. Returns the secret's
+    /// point on each curve along with the proof.
+    pub fn prove(secret: &Scalar) -> Option<(RistrettoPoint, SecpPoint, Self)> {
+        let ristretto_point = RISTRETTO_BASEPOINT_POINT * secret;
+        let secp_point = SecpPoint::GENERATOR * to_secp_scalar(secret)?;
+        let statement = statement(&ristretto_point, &secp_point);
+        let generators = Generators::new();
+        let bits = secret.to_bytes();
+        let (blindings, secp_blindings) = (ristretto_blindings(), secp_blindings());
+
+        let mut proof = Self {
+            ristretto_commitments: Vec::with_capacity(DLEQ_BITS),
+            secp_commitments: Vec::with_capacity(DLEQ_BITS),
+            challenges: Vec::with_capacity(DLEQ_BITS),
+            ristretto_responses: Vec::with_capacity(DLEQ_BITS),
+            secp_responses: Vec::with_capacity(DLEQ_BITS),
+        };
+        for i in 0..DLEQ_BITS {
+            let bit = (bits[i / 8] >> (i % 8)) & 1;
+            let (real, other) = (bit as usize, 1 - bit as usize);
+            let commitment = RISTRETTO_BASEPOINT_POINT * Scalar::from(bit) + generators.h * blindings[i];
+            let secp_commitment = SecpPoint::GENERATOR * SecpScalar::from(bit as u64) + generators.secp_h * secp_blindings[i];
+            let commitments = (&commitment, &secp_commitment);
+
+            // Start the ring after the real member, simulate the other one,
+            // then close the ring with the real blindings
+            let (k, secp_k) = (Scalar::random(&mut OsRng), SecpScalar::random(&mut OsRng));
+            let mut challenges = [[0u8; 32]; 2];
+            let mut responses = [Scalar::ZERO; 2];
+            let mut secp_responses = [SecpScalar::ZERO; 2];
+            challenges[other] = ring_challenge(&statement, i, commitments, (&(generators.h * k), &(generators.secp_h * secp_k)));
+            responses[other] = Scalar::random(&mut OsRng);
+            secp_responses[other] = SecpScalar::random(&mut OsRng);
+            let nonces = generators.member_nonces(
+                other as u64,
+                commitments,
+                &challenges[other],
+                (&responses[other], &secp_responses[other]),
+            );
+            challenges[real] = ring_challenge(&statement, i, commitments, (&nonces.0, &nonces.1));
+            responses[real] = k + Scalar::from_bytes_mod_order(challenges[real]) * blindings[i];
+            secp_responses[real] = secp_k + secp_challenge(&challenges[real]) * secp_blindings[i];
+
+            proof.ristretto_commitments.push(commitment);
+            proof.secp_commitments.push(secp_commitment);
+            proof.challenges.push(challenges[0]);
+            proof.ristretto_responses.push(responses);
+            proof.secp_responses.push(secp_responses);
+        }
+        Some((ristretto_point, secp_point, proof))
+    }
+
+    pub fn verify(&self, ristretto_point: &RistrettoPoint, secp_point: &SecpPoint) -> bool {
+        let lengths = [
+            self.ristretto_commitments.len(),
+            self.secp_commitments.len(),
+            self.challenges.len(),
+            self.ristretto_responses.len(),
+            self.secp_responses.len(),
+        ];
+        if lengths.iter().any(|len| *len != DLEQ_BITS) {
+            return false;
+        }
+        let statement = statement(ristretto_point, secp_point);
+        let generators = Generators::new();
+
+        let (mut sum, mut secp_sum) = (RistrettoPoint::identity(), SecpPoint::IDENTITY);
+        let (mut weight, mut secp_weight) = (Scalar::ONE, SecpScalar::ONE);
+        for i in 0..DLEQ_BITS {
+            let commitments = (&self.ristretto_commitments[i], &self.secp_commitments[i]);
+            if self.challenges[i][31] & !0x03 != 0 {
+                return false;
+            }
+            let mut challenge = self.challenges[i];
+            for bit in 0..2 {
+                let nonces = generators.member_nonces(
+                    bit as u64,
+                    commitments,
+                    &challenge,
+                    (&self.ristretto_responses[i][bit], &self.secp_responses[i][bit]),
+                );
+                challenge = ring_challenge(&statement, i, commitments, (&nonces.0, &nonces.1));
+            }
+            if challenge != self.challenges[i] {
+                return false;
+            }
+            sum += commitments.0 * weight;
+            secp_sum += *commitments.1 * secp_weight;
+            weight += weight;
+            secp_weight += secp_weight;
+        }
+        sum == *ristretto_point && secp_sum == *secp_point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dleq_round_trip() {
+        let secret = cross_group_secret();
+        let (point, secp_point, proof) = CrossGroupDleq::prove(&secret).unwrap();
+        assert_eq!(point, RISTRETTO_BASEPOINT_POINT * secret);
+        assert_eq!(secp_point, SecpPoint::GENERATOR * to_secp_scalar(&secret).unwrap());
+        assert!(proof.verify(&point, &secp_point));
+    }
+
+    #[test]
+    fn test_scalar_conversion() {
+        let secret = cross_group_secret();
+        assert_eq!(to_ristretto_scalar(&to_secp_scalar(&secret).unwrap()), Some(secret));
+        assert_eq!(to_secp_scalar(&Scalar::from(7u64)), Some(SecpScalar::from(7u64)));
+        // The Ristretto group order is above 2^252
+        assert_eq!(to_secp_scalar(&-Scalar::ONE), None);
+        assert!(CrossGroupDleq::prove(&-Scalar::ONE).is_none());
+    }
+
+    #[test]
+    fn test_dleq_rejects_other_points() {
+        let secret = cross_group_secret();
+        let (point, secp_point, proof) = CrossGroupDleq::prove(&secret).unwrap();
+
+        let other = to_secp_scalar(&cross_group_secret()).unwrap();
+        assert!(!proof.verify(&point, &(SecpPoint::GENERATOR * other)));
+        assert!(!proof.verify(&(point + RISTRETTO_BASEPOINT_POINT), &secp_point));
+    }
+
+    #[test]
+    fn test_dleq_rejects_tampering() {
+        let (point, secp_point, proof) = CrossGroupDleq::prove(&cross_group_secret()).unwrap();
+
+        let mut tampered = proof.clone();
+        tampered.ristretto_responses[3][0] += Scalar::ONE;
+        assert!(!tampered.verify(&point, &secp_point));
+
+        // Moving weight between two bits keeps the sums but breaks the rings
+        let mut tampered = proof.clone();
+        tampered.secp_commitments[0] += SecpPoint::GENERATOR + SecpPoint::GENERATOR;
+        tampered.secp_commitments[1] -= SecpPoint::GENERATOR;
+        assert!(!tampered.verify(&point, &secp_point));
+
+        let mut tampered = proof;
+        tampered.challenges.pop();
+        assert!(!tampered.verify(&point, &secp_point));
+    }
+
+    #[test]
+    fn test_dleq_rejects_mismatched_secrets() {
+        // Prove for one secret, then swap in the secp side of another
+        let (point, _, proof) = CrossGroupDleq::prove(&cross_group_secret()).unwrap();
+        let (_, secp_point, other) = CrossGroupDleq::prove(&cross_group_secret()).unwrap();
+        let mut mixed = proof;
+        mixed.secp_commitments = other.secp_commitments;
+        mixed.secp_responses = other.secp_responses;
+        assert!(!mixed.verify(&point, &secp_point));
+    }
+}
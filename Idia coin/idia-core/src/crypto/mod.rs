@@ -4,11 +4,21 @@ mod pedersen;
 mod ring_signature;
 mod stealth_address;
 mod bulletproof;
+mod range_attestation;
+mod adaptor;
+mod one_of_many;
+mod bip340;
+mod dleq;
 
 pub use pedersen::*;
 pub use ring_signature::*;
 pub use stealth_address::*;
 pub use bulletproof::*;
+pub use range_attestation::*;
+pub use adaptor::*;
+pub use one_of_many::*;
+pub use bip340::*;
+pub use dleq::*;
 
 use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
 use curve25519_dalek::scalar::Scalar;
@@ -0,0 +1,417 @@
+//! Groth-Kohlweiss one-of-many proofs
+//!
+//! Binary (n = 2) variant as in Bootle et al.: proves knowledge of an index
+//! `l` and blinding `r` with `set[l] - offset = r·H`, without revealing `l`.
+//! Proof size is logarithmic in the set, which is padded to a power of two by
+//! repeating its last element. Proofs share the set and differ only in their
+//! offset, so a batch verifies with one multiscalar multiplication in which
+//! each set point appears once.
+
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::traits::{Identity, VartimeMultiscalarMul};
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use merlin::Transcript;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
+
+/// A membership proof for one element of an anonymity set
+#[derive(Debug, Clone)]
+pub struct OneOfManyProof {
+    /// Per index bit j: commitments to l_j, a_j and l_j·a_j
+    pub bit_commitments: Vec<RistrettoPoint>,
+    pub mask_commitments: Vec<RistrettoPoint>,
+    pub product_commitments: Vec<RistrettoPoint>,
+    /// Per degree k < m: Σ_i p_{i,k}·(set[i] - offset) + ρ_k·H
+    pub degree_commitments: Vec<RistrettoPoint>,
+    pub f: Vec<Scalar>,
+    pub z_a: Vec<Scalar>,
+    pub z_b: Vec<Scalar>,
+    pub z_d: Scalar,
+}
+
+fn bits_for(len: usize) -> usize {
+    len.max(2).next_power_of_two().trailing_zeros() as usize
+}
+
+fn padded(set: &[RistrettoPoint], m: usize) -> impl Iterator<Item = RistrettoPoint> + '_ {
+    let last = *set.last().expect("non-empty set");
+    set.iter().copied().chain(std::iter::repeat(last)).take(1 << m)
+}
+
+fn commit(g: &RistrettoPoint, h: &RistrettoPoint, value: Scalar, blinding: Scalar) -> RistrettoPoint {
+    g * value + h * blinding
+}
+
+/// Binds proofs to the set without compressing it once per proof
+fn set_digest(set: &[RistrettoPoint]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update((set.len() as u64).to_le_bytes());
+    // Doubling is injective in a prime-order group, and batches the inversions
+    for point in RistrettoPoint::double_and_compress_batch(set) {
+        hasher.update(point.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn challenge(transcript: &mut Transcript, set_digest: &[u8; 64], offset: &RistrettoPoint, proof: &OneOfManyProof) -> Scalar {
+    transcript.append_message(b"dom-sep", b"one-of-many");
+    transcript.append_message(b"set", set_digest);
+    transcript.append_message(b"offset", offset.compress().as_bytes());
+    for (label, points) in [
+        (b"B".as_slice(), &proof.bit_commitments),
+        (b"A".as_slice(), &proof.mask_commitments),
+        (b"C".as_slice(), &proof.product_commitments),
+        (b"D".as_slice(), &proof.degree_commitments),
+    ] {
+        for point in points {
+            transcript.append_message(label, point.compress().as_bytes());
+        }
+    }
+    let mut bytes = [0u8; 64];
+    transcript.challenge_bytes(b"x", &mut bytes);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+/// p_i(x) = Π_j (bit j of i ? f_j : x - f_j) for every padded index i, built
+/// one bit at a time in 2^m multiplications
+fn index_polynomials(f: &[Scalar], x: Scalar) -> Vec<Scalar> {
+    let mut products = vec![Scalar::ONE];
+    for f_j in f {
+        let zero = x - f_j;
+        let high: Vec<Scalar> = products.iter().map(|p| p * f_j).collect();
+        products.iter_mut().for_each(|p| *p *= zero);
+        products.extend(high);
+    }
+    products
+}
+
+impl OneOfManyProof {
+    /// `g` and `h` are the value and blinding generators of the commitments
+    pub fn prove(
+        transcript: &mut Transcript,
+        set: &[RistrettoPoint],
+        offset: &RistrettoPoint,
+        index: usize,
+        blinding: Scalar,
+        g: &RistrettoPoint,
+        h: &RistrettoPoint,
+    ) -> Option<Self> {
+        if index >= set.len() {
+            return None;
+        }
+        let m = bits_for(set.len());
+        let mut rng = OsRng;
+        let bits: Vec<Scalar> = (0..m).map(|j| Scalar::from(((index >> j) & 1) as u64)).collect();
+        let r: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+        let a: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+        let s: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+        let t: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+        let rho: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+
+        // Coefficients of p_i(x) = Π_j f_{j,i_j}(x), low degree first, where
+        // f_{j,1}(x) = l_j·x + a_j and f_{j,0}(x) = (1 - l_j)·x - a_j
+        let mut degree_sums = vec![RistrettoPoint::identity(); m];
+        for (i, point) in padded(set, m).enumerate() {
+            let point = point - offset;
+            let mut coefficients = vec![Scalar::ONE];
+            for j in 0..m {
+                let (constant, linear) = if (i >> j) & 1 == 1 {
+                    (a[j], bits[j])
+                } else {
+                    (-a[j], Scalar::ONE - bits[j])
+                };
+                let mut next = vec![Scalar::ZERO; coefficients.len() + 1];
+                for (k, c) in coefficients.iter().enumerate() {
+                    next[k] += c * constant;
+                    next[k + 1] += c * linear;
+                }
+                coefficients = next;
+            }
+            for k in 0..m {
+                degree_sums[k] += point * coefficients[k];
+            }
+        }
+
+        let mut proof = Self {
+            bit_commitments: (0..m).map(|j| commit(g, h, bits[j], r[j])).collect(),
+            mask_commitments: (0..m).map(|j| commit(g, h, a[j], s[j])).collect(),
+            product_commitments: (0..m).map(|j| commit(g, h, bits[j] * a[j], t[j])).collect(),
+            degree_commitments: (0..m).map(|k| degree_sums[k] + h * rho[k]).collect(),
+            f: Vec::new(),
+            z_a: Vec::new(),
+            z_b: Vec::new(),
+            z_d: Scalar::ZERO,
+        };
+        let x = challenge(transcript, &set_digest(set), offset, &proof);
+
+        proof.f = (0..m).map(|j| bits[j] * x + a[j]).collect();
+        proof.z_a = (0..m).map(|j| r[j] * x + s[j]).collect();
+        proof.z_b = (0..m).map(|j| r[j] * (x - proof.f[j]) + t[j]).collect();
+        let mut x_power = Scalar::ONE;
+        let mut rho_sum = Scalar::ZERO;
+        for rho_k in &rho {
+            rho_sum += rho_k * x_power;
+            x_power *= x;
+        }
+        proof.z_d = blinding * x_power - rho_sum;
+        Some(proof)
+    }
+
+    pub fn verify(
+        &self,
+        transcript: Transcript,
+        set: &[RistrettoPoint],
+        offset: &RistrettoPoint,
+        g: &RistrettoPoint,
+        h: &RistrettoPoint,
+    ) -> bool {
+        Self::verify_batch([(self, transcript, *offset)], set, g, h)
+    }
+
+    /// Each proof comes with its transcript and offset. Every equation is
+    /// scaled by a fresh random weight and all of them are summed into one
+    /// multiscalar multiplication: coefficients on the set, G and H are
+    /// shared, and since Σ_i p_i(x) = x^m each offset is a single term.
+    pub fn verify_batch<'a>(
+        proofs: impl IntoIterator<Item = (&'a Self, Transcript, RistrettoPoint)>,
+        set: &[RistrettoPoint],
+        g: &RistrettoPoint,
+        h: &RistrettoPoint,
+    ) -> bool {
+        if set.is_empty() {
+            return false;
+        }
+        let m = bits_for(set.len());
+        let digest = set_digest(set);
+        let mut rng = OsRng;
+
+        let mut set_scalars = vec![Scalar::ZERO; set.len()];
+        let mut g_scalar = Scalar::ZERO;
+        let mut h_scalar = Scalar::ZERO;
+        let mut scalars = Vec::new();
+        let mut points = Vec::new();
+
+        for (proof, mut transcript, offset) in proofs {
+            let lengths = [
+                proof.bit_commitments.len(),
+                proof.mask_commitments.len(),
+                proof.product_commitments.len(),
+                proof.degree_commitments.len(),
+                proof.f.len(),
+                proof.z_a.len(),
+                proof.z_b.len(),
+            ];
+            if lengths.iter().any(|len| *len != m) {
+                return false;
+            }
+            let x = challenge(&mut transcript, &digest, &offset, proof);
+
+            for j in 0..m {
+                // B^x·A = Com(f; z_a) and B^(x - f)·C = Com(0; z_b) force l_j to be a bit
+                let (u, v) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+                scalars.extend([u * x + v * (x - proof.f[j]), u, v]);
+                points.extend([
+                    proof.bit_commitments[j],
+                    proof.mask_commitments[j],
+                    proof.product_commitments[j],
+                ]);
+                g_scalar -= u * proof.f[j];
+                h_scalar -= u * proof.z_a[j] + v * proof.z_b[j];
+            }
+
+            // Σ_i p_i(x)·(set[i] - offset) - Σ_k x^k·D_k = Com(0; z_d);
+            // padding repeats the last point, so its coefficients land there
+            let w = Scalar::random(&mut rng);
+            let last = set.len() - 1;
+            for (i, p) in index_polynomials(&proof.f, x).into_iter().enumerate() {
+                set_scalars[i.min(last)] += w * p;
+            }
+            let mut x_power = Scalar::ONE;
+            for d in &proof.degree_commitments {
+                scalars.push(-w * x_power);
+                points.push(*d);
+                x_power *= x;
+            }
+            scalars.push(-w * x_power);
+            points.push(offset);
+            h_scalar -= w * proof.z_d;
+        }
+
+        scalars.extend(set_scalars);
+        points.extend_from_slice(set);
+        scalars.extend([g_scalar, h_scalar]);
+        points.extend([*g, *h]);
+        RistrettoPoint::vartime_multiscalar_mul(scalars, points) == RistrettoPoint::identity()
+    }
+
+    /// 4m compressed points then 3m + 1 scalars, 32 bytes each
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let points = [
+            &self.bit_commitments,
+            &self.mask_commitments,
+            &self.product_commitments,
+            &self.degree_commitments,
+        ];
+        let scalars = [&self.f, &self.z_a, &self.z_b];
+        let mut bytes = Vec::with_capacity((7 * self.f.len() + 1) * 32);
+        for point in points.into_iter().flatten() {
+            bytes.extend_from_slice(point.compress().as_bytes());
+        }
+        for scalar in scalars.into_iter().flatten().chain([&self.z_d]) {
+            bytes.extend_from_slice(scalar.as_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() % 32 != 0 || (bytes.len() / 32) % 7 != 1 {
+            return None;
+        }
+        let m = bytes.len() / 32 / 7;
+        let mut chunks = bytes.chunks_exact(32).map(|chunk| <[u8; 32]>::try_from(chunk).unwrap());
+        let mut points = |n: usize| -> Option<Vec<RistrettoPoint>> {
+            (0..n)
+                .map(|_| CompressedRistretto(chunks.next()?).decompress())
+                .collect()
+        };
+        let (bit_commitments, mask_commitments, product_commitments, degree_commitments) =
+            (points(m)?, points(m)?, points(m)?, points(m)?);
+        let mut scalars = |n: usize| -> Option<Vec<Scalar>> {
+            (0..n)
+                .map(|_| Option::from(Scalar::from_canonical_bytes(chunks.next()?)))
+                .collect()
+        };
+        let (f, z_a, z_b) = (scalars(m)?, scalars(m)?, scalars(m)?);
+        let z_d = scalars(1)?.pop()?;
+        Some(Self {
+            bit_commitments,
+            mask_commitments,
+            product_commitments,
+            degree_commitments,
+            f,
+            z_a,
+            z_b,
+            z_d,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+
+    struct Fixture {
+        set: Vec<RistrettoPoint>,
+        values: Vec<Scalar>,
+        blindings: Vec<Scalar>,
+        h: RistrettoPoint,
+    }
+
+    // Five commitments, so the set needs padding
+    fn fixture() -> Fixture {
+        let h = RistrettoPoint::hash_from_bytes::<Sha512>(b"one-of-many-test-h");
+        let values: Vec<Scalar> = (0..5).map(|_| Scalar::random(&mut OsRng)).collect();
+        let blindings: Vec<Scalar> = (0..5).map(|_| Scalar::random(&mut OsRng)).collect();
+        let set = values
+            .iter()
+            .zip(&blindings)
+            .map(|(value, blinding)| commit(&RISTRETTO_BASEPOINT_POINT, &h, *value, *blinding))
+            .collect();
+        Fixture { set, values, blindings, h }
+    }
+
+    fn prove(fixture: &Fixture, index: usize) -> (RistrettoPoint, OneOfManyProof) {
+        let offset = RISTRETTO_BASEPOINT_POINT * fixture.values[index];
+        let proof = OneOfManyProof::prove(
+            &mut Transcript::new(b"test"),
+            &fixture.set,
+            &offset,
+            index,
+            fixture.blindings[index],
+            &RISTRETTO_BASEPOINT_POINT,
+            &fixture.h,
+        )
+        .unwrap();
+        (offset, proof)
+    }
+
+    #[test]
+    fn test_proof_round_trip() {
+        let fixture = fixture();
+        for index in 0..fixture.set.len() {
+            let (offset, proof) = prove(&fixture, index);
+            assert!(proof.verify(Transcript::new(b"test"), &fixture.set, &offset, &RISTRETTO_BASEPOINT_POINT, &fixture.h));
+
+            let decoded = OneOfManyProof::from_bytes(&proof.to_bytes()).unwrap();
+            assert!(decoded.verify(Transcript::new(b"test"), &fixture.set, &offset, &RISTRETTO_BASEPOINT_POINT, &fixture.h));
+        }
+    }
+
+    #[test]
+    fn test_batch_verification() {
+        let fixture = fixture();
+        let proofs: Vec<_> = [0, 2, 4].into_iter().map(|index| prove(&fixture, index)).collect();
+        let batch = || proofs.iter().map(|(offset, proof)| (proof, Transcript::new(b"test"), *offset));
+        assert!(OneOfManyProof::verify_batch(batch(), &fixture.set, &RISTRETTO_BASEPOINT_POINT, &fixture.h));
+
+        // One bad offset fails the whole batch
+        let mut bad: Vec<_> = batch().collect();
+        bad[1].2 += RISTRETTO_BASEPOINT_POINT;
+        assert!(!OneOfManyProof::verify_batch(bad, &fixture.set, &RISTRETTO_BASEPOINT_POINT, &fixture.h));
+    }
+
+    #[test]
+    fn test_rejects_tampering() {
+        let fixture = fixture();
+        let (offset, proof) = prove(&fixture, 3);
+        let verify = |proof: &OneOfManyProof, set: &[RistrettoPoint], offset: &RistrettoPoint, label: &'static [u8]| {
+            proof.verify(Transcript::new(label), set, offset, &RISTRETTO_BASEPOINT_POINT, &fixture.h)
+        };
+
+        assert!(!verify(&proof, &fixture.set, &(offset + fixture.h), b"test"));
+        assert!(!verify(&proof, &fixture.set, &offset, b"other"));
+        assert!(!verify(&proof, &fixture.set[..4], &offset, b"test"));
+
+        let mut tampered = proof.clone();
+        tampered.f[0] += Scalar::ONE;
+        assert!(!verify(&tampered, &fixture.set, &offset, b"test"));
+
+        let mut tampered = proof.clone();
+        tampered.z_d += Scalar::ONE;
+        assert!(!verify(&tampered, &fixture.set, &offset, b"test"));
+
+        let mut tampered = proof;
+        tampered.degree_commitments.pop();
+        assert!(!verify(&tampered, &fixture.set, &offset, b"test"));
+    }
+
+    #[test]
+    fn test_rejects_non_member() {
+        let fixture = fixture();
+        // An offset opening none of the set cannot be proven
+        let offset = RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng);
+        let proof = OneOfManyProof::prove(
+            &mut Transcript::new(b"test"),
+            &fixture.set,
+            &offset,
+            1,
+            fixture.blindings[1],
+            &RISTRETTO_BASEPOINT_POINT,
+            &fixture.h,
+        )
+        .unwrap();
+        assert!(!proof.verify(Transcript::new(b"test"), &fixture.set, &offset, &RISTRETTO_BASEPOINT_POINT, &fixture.h));
+        assert!(OneOfManyProof::prove(
+            &mut Transcript::new(b"test"),
+            &fixture.set,
+            &offset,
+            5,
+            Scalar::ONE,
+            &RISTRETTO_BASEPOINT_POINT,
+            &fixture.h,
+        )
+        .is_none());
+        assert!(OneOfManyProof::from_bytes(&[0u8; 64]).is_none());
+    }
+}
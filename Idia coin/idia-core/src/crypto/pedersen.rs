@@ -1,6 +1,7 @@
 //! Pedersen commitment implementation for confidential transactions
 
 use super::*;
+use bulletproofs::PedersenGens;
 use merlin::Transcript;
 
 /// A Pedersen commitment of the form `value * G + blinding * H`
@@ -35,6 +36,14 @@ impl PedersenCommitment {
         let p2 = other.0.decompress().ok_or(CryptoError::InvalidCommitment)?;
         Ok(Self((p1 + p2).compress()))
     }
+
+    /// Bulletproofs generators matching this commitment scheme, for proofs over existing commitments
+    pub fn generators() -> PedersenGens {
+        PedersenGens {
+            B: RISTRETTO_BASEPOINT_POINT,
+            B_blinding: RistrettoPoint::hash_from_bytes::<Sha256>(b"Idia_H"),
+        }
+    }
 }
 
 // Constants for commitment calculation
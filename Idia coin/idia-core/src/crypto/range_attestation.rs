@@ -0,0 +1,118 @@
+//! Range attestations for selective disclosure of amounts
+
+use super::*;
+use bulletproofs::{BulletproofGens, RangeProof};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use merlin::Transcript;
+
+/// Bit width of each proven range
+const ATTESTATION_BITS: usize = 64;
+
+/// Proof that a committed amount is strictly below a public threshold.
+///
+/// The amount stays hidden: the proof shows that both `amount` and
+/// `threshold - 1 - amount` lie in `[0, 2^64)`, which is only possible when
+/// `amount < threshold`.
+#[derive(Debug, Clone)]
+pub struct RangeAttestation {
+    /// Threshold the amount is proven to be below
+    pub threshold: u64,
+    proof: RangeProof,
+}
+
+impl RangeAttestation {
+    /// Attest that the amount behind `commitment = amount * G + blinding * H` is below `threshold`
+    pub fn prove(amount: u64, blinding: &Scalar, threshold: u64) -> Result<Self, CryptoError> {
+        if amount >= threshold {
+            return Err(CryptoError::InvalidAmount);
+        }
+
+        let pc_gens = PedersenCommitment::generators();
+        let bp_gens = BulletproofGens::new(ATTESTATION_BITS, 2);
+        let mut transcript = Self::transcript(threshold);
+        let (proof, _) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[amount, threshold - 1 - amount],
+            &[*blinding, -blinding],
+            ATTESTATION_BITS,
+        )
+        .map_err(|_| CryptoError::RangeProofVerification)?;
+
+        Ok(Self { threshold, proof })
+    }
+
+    /// Verify the attestation against an output's commitment
+    pub fn verify(&self, commitment: &PedersenCommitment) -> Result<bool, CryptoError> {
+        if self.threshold == 0 {
+            return Ok(false);
+        }
+        let point = commitment.0.decompress().ok_or(CryptoError::InvalidCommitment)?;
+
+        // Commitment to `threshold - 1 - amount` under the negated blinding factor
+        let complement = RISTRETTO_BASEPOINT_POINT * Scalar::from(self.threshold - 1) - point;
+
+        let pc_gens = PedersenCommitment::generators();
+        let bp_gens = BulletproofGens::new(ATTESTATION_BITS, 2);
+        let mut transcript = Self::transcript(self.threshold);
+        Ok(self
+            .proof
+            .verify_multiple(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &[commitment.0, complement.compress()],
+                ATTESTATION_BITS,
+            )
+            .is_ok())
+    }
+
+    /// Serialize the proof, excluding the threshold
+    pub fn proof_bytes(&self) -> Vec<u8> {
+        self.proof.to_bytes()
+    }
+
+    /// Rebuild an attestation from a threshold and serialized proof
+    pub fn from_bytes(threshold: u64, proof: &[u8]) -> Result<Self, CryptoError> {
+        let proof = RangeProof::from_bytes(proof).map_err(|_| CryptoError::RangeProofVerification)?;
+        Ok(Self { threshold, proof })
+    }
+
+    fn transcript(threshold: u64) -> Transcript {
+        let mut transcript = Transcript::new(b"idia-range-attestation");
+        transcript.append_u64(b"threshold", threshold);
+        transcript
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attestation_below_threshold() {
+        let (commitment, blinding) = PedersenCommitment::new(9_999);
+        let attestation = RangeAttestation::prove(9_999, &blinding, 10_000).unwrap();
+        assert!(attestation.verify(&commitment).unwrap());
+
+        let restored = RangeAttestation::from_bytes(10_000, &attestation.proof_bytes()).unwrap();
+        assert!(restored.verify(&commitment).unwrap());
+    }
+
+    #[test]
+    fn test_attestation_rejects_amount_at_threshold() {
+        let (_, blinding) = PedersenCommitment::new(10_000);
+        assert!(RangeAttestation::prove(10_000, &blinding, 10_000).is_err());
+    }
+
+    #[test]
+    fn test_attestation_bound_to_threshold() {
+        let (commitment, blinding) = PedersenCommitment::new(500);
+        let attestation = RangeAttestation::prove(500, &blinding, 1_000).unwrap();
+
+        // Claiming a tighter threshold with the same proof must fail
+        let forged = RangeAttestation::from_bytes(501, &attestation.proof_bytes()).unwrap();
+        assert!(!forged.verify(&commitment).unwrap());
+    }
+}
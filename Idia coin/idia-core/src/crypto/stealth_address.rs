@@ -17,6 +17,26 @@ pub struct SpendKey {
     pub spend_public: RistrettoPoint,
 }
 
+/// View access limited to the outputs of specific transactions.
+///
+/// Holds the per-output shared secrets rather than the private view key, so
+/// the holder can recognise those outputs and nothing else.
+#[derive(Debug, Clone)]
+pub struct ScopedViewKey {
+    /// Transaction public keys paired with their shared secrets
+    pub shared_secrets: Vec<(RistrettoPoint, RistrettoPoint)>,
+}
+
+impl ScopedViewKey {
+    /// Check if a one-time public key is covered by this key and belongs to `spend_public`
+    pub fn scan_one_time_key(&self, spend_public: &RistrettoPoint, R: &RistrettoPoint, P: &RistrettoPoint) -> bool {
+        self.shared_secrets
+            .iter()
+            .filter(|(tx_pubkey, _)| tx_pubkey == R)
+            .any(|(_, shared_secret)| P == &(spend_public + (shared_secret * RISTRETTO_BASEPOINT_POINT)))
+    }
+}
+
 /// A complete stealth address
 #[derive(Debug, Clone)]
 pub struct StealthAddress {
@@ -62,6 +82,85 @@ impl StealthAddress {
         let shared_secret = self.view_key.view_private * R;
         self.spend_key.spend_private + shared_secret
     }
+
+    /// Deterministic subaddress `index` of the same wallet; index 0 is this address.
+    ///
+    /// Each subaddress is a key pair of its own derived from the private view
+    /// key, so payments to different subaddresses cannot be linked.
+    pub fn derive_subaddress(&self, index: u32) -> StealthAddress {
+        if index == 0 {
+            return self.clone();
+        }
+        let derive = |domain: &[u8]| {
+            let mut input = domain.to_vec();
+            input.extend_from_slice(self.view_key.view_private.as_bytes());
+            input.extend_from_slice(&index.to_le_bytes());
+            Scalar::hash_from_bytes::<sha2::Sha512>(&input)
+        };
+
+        let view_private = derive(b"idia-subaddress-view");
+        let spend_private = self.spend_key.spend_private + derive(b"idia-subaddress-spend");
+        Self {
+            view_key: ViewKey { view_private, view_public: RISTRETTO_BASEPOINT_POINT * view_private },
+            spend_key: SpendKey { spend_private, spend_public: RISTRETTO_BASEPOINT_POINT * spend_private },
+        }
+    }
+
+    /// Public view key followed by public spend key, as handed to payers
+    pub fn to_public_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.view_key.view_public.compress().as_bytes());
+        bytes[32..].copy_from_slice(self.spend_key.spend_public.compress().as_bytes());
+        bytes
+    }
+
+    /// Someone else's address from its public keys.
+    ///
+    /// The private halves are zero: the address can be paid, not scanned.
+    pub fn from_public_bytes(bytes: &[u8; 64]) -> Result<Self, CryptoError> {
+        let point = |range: std::ops::Range<usize>| {
+            CompressedRistretto::from_slice(&bytes[range])
+                .ok()
+                .and_then(|c| c.decompress())
+                .ok_or(CryptoError::InvalidKey)
+        };
+        Ok(Self {
+            view_key: ViewKey { view_private: Scalar::ZERO, view_public: point(0..32)? },
+            spend_key: SpendKey { spend_private: Scalar::ZERO, spend_public: point(32..64)? },
+        })
+    }
+
+    /// Private view key followed by private spend key, for backing up a wallet
+    pub fn to_secret_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.view_key.view_private.as_bytes());
+        bytes[32..].copy_from_slice(self.spend_key.spend_private.as_bytes());
+        bytes
+    }
+
+    /// Restore an address from `to_secret_bytes`, rejecting non-canonical scalars
+    pub fn from_secret_bytes(bytes: &[u8; 64]) -> Result<Self, CryptoError> {
+        let scalar = |range: std::ops::Range<usize>| {
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(bytes[range].try_into().expect("32 bytes")))
+                .ok_or(CryptoError::InvalidKey)
+        };
+        let view_private = scalar(0..32)?;
+        let spend_private = scalar(32..64)?;
+        Ok(Self {
+            view_key: ViewKey { view_private, view_public: RISTRETTO_BASEPOINT_POINT * view_private },
+            spend_key: SpendKey { spend_private, spend_public: RISTRETTO_BASEPOINT_POINT * spend_private },
+        })
+    }
+
+    /// Derive a view key that only covers outputs with the given transaction public keys
+    pub fn scoped_view_key(&self, tx_pubkeys: &[RistrettoPoint]) -> ScopedViewKey {
+        ScopedViewKey {
+            shared_secrets: tx_pubkeys
+                .iter()
+                .map(|R| (*R, self.view_key.view_private * R))
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -85,4 +184,44 @@ mod tests {
         let derived_pubkey = RISTRETTO_BASEPOINT_POINT * private_key;
         assert_eq!(derived_pubkey, P);
     }
+
+    #[test]
+    fn test_secret_bytes() {
+        let address = StealthAddress::new();
+        let restored = StealthAddress::from_secret_bytes(&address.to_secret_bytes()).unwrap();
+        assert_eq!(restored.to_public_bytes(), address.to_public_bytes());
+        assert_eq!(restored.derive_subaddress(3).to_public_bytes(), address.derive_subaddress(3).to_public_bytes());
+
+        assert!(StealthAddress::from_secret_bytes(&[0xff; 64]).is_err());
+    }
+
+    #[test]
+    fn test_scoped_view_key() {
+        let recipient = StealthAddress::new();
+        let mut rng = OsRng;
+        let (R1, P1) = recipient.generate_one_time_key(Scalar::random(&mut rng));
+        let (R2, P2) = recipient.generate_one_time_key(Scalar::random(&mut rng));
+
+        let scoped = recipient.scoped_view_key(&[R1]);
+        let spend_public = recipient.spend_key.spend_public;
+
+        // Covers the disclosed output only
+        assert!(scoped.scan_one_time_key(&spend_public, &R1, &P1));
+        assert!(!scoped.scan_one_time_key(&spend_public, &R2, &P2));
+    }
+
+    #[test]
+    fn test_subaddress() {
+        let wallet = StealthAddress::new();
+        let subaddress = wallet.derive_subaddress(1);
+        assert_eq!(wallet.derive_subaddress(1).to_public_bytes(), subaddress.to_public_bytes());
+        assert_ne!(wallet.derive_subaddress(2).to_public_bytes(), subaddress.to_public_bytes());
+
+        // A payer only knows the public keys, but the wallet can still find and spend the output
+        let payee = StealthAddress::from_public_bytes(&subaddress.to_public_bytes()).unwrap();
+        let (R, P) = payee.generate_one_time_key(Scalar::random(&mut OsRng));
+        assert!(subaddress.scan_one_time_key(&R, &P));
+        assert!(!wallet.scan_one_time_key(&R, &P));
+        assert_eq!(RISTRETTO_BASEPOINT_POINT * subaddress.derive_private_key(&R), P);
+    }
 }
\ No newline at end of file
@@ -10,6 +10,7 @@ pub use metrics::*;
 
 use crate::types::{Block, Transaction, Hash};
 use crate::crypto::StealthAddress;
+use crate::storage::{ColumnStore, StorageError};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -26,12 +27,20 @@ pub enum ExplorerError {
     StorageError(String),
 }
 
+impl From<StorageError> for ExplorerError {
+    fn from(e: StorageError) -> Self {
+        Self::StorageError(e.to_string())
+    }
+}
+
 /// Main explorer structure
 pub struct Explorer {
     /// Block storage
     store: Arc<RwLock<BlockStore>>,
     /// View-key authorized views
     views: Arc<RwLock<ViewManager>>,
+    /// Spark view keys and the notes they have found
+    spark_views: Arc<RwLock<SparkViews>>,
     /// Privacy-preserving metrics
     metrics: Arc<RwLock<MetricsAggregator>>,
 }
@@ -42,18 +51,39 @@ impl Explorer {
         Self {
             store: Arc::new(RwLock::new(BlockStore::new())),
             views: Arc::new(RwLock::new(ViewManager::new())),
+            spark_views: Arc::new(RwLock::new(SparkViews::new())),
             metrics: Arc::new(RwLock::new(MetricsAggregator::new())),
         }
     }
 
-    /// Add a new block to the explorer
+    /// Open an explorer over the blocks already in `store`, rebuilding its
+    /// metrics from them
+    pub fn with_store(store: Arc<dyn ColumnStore>) -> Result<Self, ExplorerError> {
+        let store = BlockStore::open(store)?;
+        let mut metrics = MetricsAggregator::new();
+        for block in store.blocks_by_height()? {
+            metrics.process_block(&block);
+        }
+        Ok(Self {
+            store: Arc::new(RwLock::new(store)),
+            views: Arc::new(RwLock::new(ViewManager::new())),
+            spark_views: Arc::new(RwLock::new(SparkViews::new())),
+            metrics: Arc::new(RwLock::new(metrics)),
+        })
+    }
+
+    /// Add a new block to the explorer; blocks already stored are skipped
     pub async fn add_block(&self, block: Block) -> Result<(), ExplorerError> {
         let mut store = self.store.write().await;
-        store.add_block(block.clone())?;
+        if !store.add_block(block.clone())? {
+            return Ok(());
+        }
 
         let mut metrics = self.metrics.write().await;
         metrics.process_block(&block);
 
+        self.spark_views.write().await.process_block(&block, None);
+
         Ok(())
     }
 
@@ -90,6 +120,59 @@ impl Explorer {
         Ok(())
     }
 
+    /// Register a Spark view key, scanning the stored chain for its notes.
+    ///
+    /// Returns the key's fingerprint, which names it in later queries.
+    pub async fn register_spark_view_key(&self, viewer: Box<dyn SparkViewer>) -> Result<String, ExplorerError> {
+        let store = self.store.read().await;
+        let blocks = store.blocks_by_height()?;
+        let mut spark_views = self.spark_views.write().await;
+        let fingerprint = spark_views.register(viewer);
+        for block in &blocks {
+            spark_views.process_block(block, Some(&fingerprint));
+        }
+        Ok(fingerprint)
+    }
+
+    /// Get the Spark notes received by a registered view key
+    pub async fn get_spark_notes(&self, fingerprint: &str) -> Result<Vec<SparkNoteView>, ExplorerError> {
+        self.spark_views
+            .read()
+            .await
+            .notes(fingerprint)
+            .map(<[SparkNoteView]>::to_vec)
+            .ok_or(ExplorerError::InvalidViewKey)
+    }
+
+    /// Drop a Spark view key and everything found with it
+    pub async fn revoke_spark_view_key(&self, fingerprint: &str) -> Result<(), ExplorerError> {
+        if self.spark_views.write().await.unregister(fingerprint) {
+            Ok(())
+        } else {
+            Err(ExplorerError::InvalidViewKey)
+        }
+    }
+
+    /// Get supply information: issuance, burns and the supply left
+    pub async fn get_supply_info(&self) -> SupplyInfo {
+        self.store.read().await.get_supply_info()
+    }
+
+    /// Record the issuance totals tracked by the token economics
+    pub async fn record_issuance(&self, issuance: IssuanceTotals) -> Result<(), ExplorerError> {
+        self.store.write().await.set_issuance(issuance)
+    }
+
+    /// Record treasury activity at `height`
+    pub async fn record_treasury_activity(&self, height: u64, activity: TreasuryActivity) -> Result<(), ExplorerError> {
+        self.store.write().await.add_treasury_record(TreasuryRecord { height, activity })
+    }
+
+    /// Treasury activity, oldest first
+    pub async fn get_treasury_activity(&self) -> Result<Vec<TreasuryRecord>, ExplorerError> {
+        self.store.read().await.treasury_records()
+    }
+
     /// Get privacy-preserving metrics
     pub async fn get_metrics(&self) -> NetworkMetrics {
         self.metrics.read().await.get_metrics()
@@ -1,7 +1,8 @@
 //! Block storage implementation
 
 use super::*;
-use std::collections::HashMap;
+use crate::storage::{columns, decode, get_value, ColumnStore, MemoryStore, WriteBatch};
+use serde::{Deserialize, Serialize};
 
 /// Block information (public view)
 #[derive(Debug, Clone)]
@@ -46,47 +47,153 @@ pub struct OutputView {
     pub stealth_pubkey: String,
 }
 
-/// Block storage
+/// Supply information: issuance reported by the token economics, burns
+/// derived from on-chain burn outputs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SupplyInfo {
+    /// Supply at genesis
+    pub initial_supply: u64,
+    /// Total emitted since genesis
+    pub total_emitted: u64,
+    /// Total amount provably burned
+    pub total_burned: u64,
+    /// Number of burn outputs seen
+    pub burn_count: u64,
+    /// Stake destroyed by slashing, which leaves no output on chain
+    pub total_slashed: u64,
+    /// Supply in existence: everything minted less burned and slashed
+    pub total_supply: u64,
+}
+
+/// Issuance totals, as tracked outside the chain by the token economics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuanceTotals {
+    /// Supply at genesis
+    pub initial_supply: u64,
+    /// Total emitted since genesis
+    pub total_emitted: u64,
+    /// Total destroyed by slashing
+    pub total_slashed: u64,
+}
+
+/// Burn totals, persisted under the supply key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BurnTotals {
+    total_burned: u64,
+    burn_count: u64,
+}
+
+/// Treasury activity shown by the explorer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TreasuryActivity {
+    /// A spend was approved for payment in installments
+    SpendScheduled {
+        proposal_id: [u8; 32],
+        total_amount: u64,
+        installments: u32,
+    },
+    /// A spend was approved as a vested grant
+    GrantCreated {
+        proposal_id: [u8; 32],
+        grant_id: u64,
+        total_amount: u64,
+    },
+    /// A spend was reserved for release milestone by milestone
+    MilestonesReserved {
+        proposal_id: [u8; 32],
+        total_amount: u64,
+        milestones: u64,
+    },
+    /// Funds left the treasury
+    Disbursed {
+        proposal_id: [u8; 32],
+        recipient: String,
+        amount: u64,
+        purpose: String,
+    },
+}
+
+/// Treasury activity with the height it happened at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreasuryRecord {
+    /// Block height
+    pub height: u64,
+    /// What happened
+    pub activity: TreasuryActivity,
+}
+
+/// Key of the burn totals in the explorer meta column
+const SUPPLY_KEY: &[u8] = b"supply";
+
+/// Key of the issuance totals in the explorer meta column
+const ISSUANCE_KEY: &[u8] = b"issuance";
+
+/// Block storage, kept in the explorer columns of a shared store
 pub struct BlockStore {
-    /// Blocks by hash
-    blocks: HashMap<Hash, Block>,
-    /// Block height mapping
-    heights: HashMap<u64, Hash>,
-    /// Transactions by hash
-    transactions: HashMap<Hash, (Hash, usize)>, // (block_hash, tx_index)
+    /// Blocks, heights and transaction positions
+    store: Arc<dyn ColumnStore>,
+    /// Burn totals, as last written
+    burns: BurnTotals,
+    /// Issuance totals, as last written
+    issuance: IssuanceTotals,
+    /// Number of treasury records stored
+    treasury_records: u64,
 }
 
 impl BlockStore {
-    /// Create a new block store
+    /// Create a new block store kept in memory
     pub fn new() -> Self {
         Self {
-            blocks: HashMap::new(),
-            heights: HashMap::new(),
-            transactions: HashMap::new(),
+            store: Arc::new(MemoryStore::new()),
+            burns: BurnTotals::default(),
+            issuance: IssuanceTotals::default(),
+            treasury_records: 0,
         }
     }
 
-    /// Add a block to storage
-    pub fn add_block(&mut self, block: Block) -> Result<(), ExplorerError> {
+    /// Open the block store kept in `store`
+    pub fn open(store: Arc<dyn ColumnStore>) -> Result<Self, ExplorerError> {
+        let burns = get_value(store.as_ref(), columns::EXPLORER_META, SUPPLY_KEY)?.unwrap_or_default();
+        let issuance = get_value(store.as_ref(), columns::EXPLORER_META, ISSUANCE_KEY)?.unwrap_or_default();
+        let treasury_records = store.iter_prefix(columns::EXPLORER_TREASURY, &[])?.count() as u64;
+        Ok(Self { store, burns, issuance, treasury_records })
+    }
+
+    /// Add a block to storage, returning false if it was already stored
+    pub fn add_block(&mut self, block: Block) -> Result<bool, ExplorerError> {
         let block_hash = block.hash();
-        
+        if self.store.get(columns::EXPLORER_BLOCKS, &block_hash)?.is_some() {
+            return Ok(false);
+        }
+        let mut batch = WriteBatch::new();
+        let mut burns = self.burns.clone();
+
         // Index transactions
         for (idx, tx) in block.transactions.iter().enumerate() {
-            let tx_hash = tx.hash();
-            self.transactions.insert(tx_hash, (block_hash, idx));
+            let position: (Hash, u64) = (block_hash, idx as u64);
+            batch.put_value(columns::EXPLORER_TRANSACTIONS, tx.hash().to_vec(), &position)?;
+            burns.burn_count += tx.burns.len() as u64;
         }
+        burns.total_burned += block.burned_amount();
 
-        // Store block
-        self.heights.insert(block.header.height, block_hash);
-        self.blocks.insert(block_hash, block);
+        // Store block and totals together
+        batch.put(columns::EXPLORER_HEIGHTS, block.header.height.to_be_bytes(), block_hash.to_vec());
+        batch.put_value(columns::EXPLORER_BLOCKS, block_hash.to_vec(), &block)?;
+        batch.put_value(columns::EXPLORER_META, SUPPLY_KEY.to_vec(), &burns)?;
+        self.store.write(batch)?;
+        self.burns = burns;
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// Load a stored block
+    fn block(&self, hash: &Hash) -> Result<Block, ExplorerError> {
+        get_value(self.store.as_ref(), columns::EXPLORER_BLOCKS, hash)?.ok_or(ExplorerError::BlockNotFound)
     }
 
     /// Get basic block information
     pub fn get_block_info(&self, hash: &Hash) -> Result<BlockInfo, ExplorerError> {
-        let block = self.blocks.get(hash)
-            .ok_or(ExplorerError::BlockNotFound)?;
+        let block = self.block(hash)?;
 
         Ok(BlockInfo {
             hash: *hash,
@@ -101,13 +208,14 @@ impl BlockStore {
         &self,
         tx_hash: &Hash,
     ) -> Result<Option<TransactionView>, ExplorerError> {
-        let (block_hash, tx_idx) = self.transactions.get(tx_hash)
-            .ok_or(ExplorerError::TransactionNotFound)?;
+        let (block_hash, tx_idx): (Hash, u64) =
+            get_value(self.store.as_ref(), columns::EXPLORER_TRANSACTIONS, tx_hash)?
+                .ok_or(ExplorerError::TransactionNotFound)?;
 
-        let block = self.blocks.get(block_hash)
-            .ok_or(ExplorerError::BlockNotFound)?;
+        let block = self.block(&block_hash)?;
 
-        let tx = &block.transactions[*tx_idx];
+        let tx = block.transactions.get(tx_idx as usize)
+            .ok_or(ExplorerError::TransactionNotFound)?;
 
         Ok(Some(TransactionView {
             hash: *tx_hash,
@@ -120,13 +228,65 @@ impl BlockStore {
         }))
     }
 
+    /// Get supply information
+    pub fn get_supply_info(&self) -> SupplyInfo {
+        let minted = self.issuance.initial_supply.saturating_add(self.issuance.total_emitted);
+        let destroyed = self.burns.total_burned.saturating_add(self.issuance.total_slashed);
+        SupplyInfo {
+            initial_supply: self.issuance.initial_supply,
+            total_emitted: self.issuance.total_emitted,
+            total_burned: self.burns.total_burned,
+            burn_count: self.burns.burn_count,
+            total_slashed: self.issuance.total_slashed,
+            total_supply: minted.saturating_sub(destroyed),
+        }
+    }
+
+    /// Replace the issuance totals
+    pub fn set_issuance(&mut self, issuance: IssuanceTotals) -> Result<(), ExplorerError> {
+        let mut batch = WriteBatch::new();
+        batch.put_value(columns::EXPLORER_META, ISSUANCE_KEY.to_vec(), &issuance)?;
+        self.store.write(batch)?;
+        self.issuance = issuance;
+        Ok(())
+    }
+
+    /// Append a treasury record
+    pub fn add_treasury_record(&mut self, record: TreasuryRecord) -> Result<(), ExplorerError> {
+        let mut batch = WriteBatch::new();
+        batch.put_value(columns::EXPLORER_TREASURY, self.treasury_records.to_be_bytes().to_vec(), &record)?;
+        self.store.write(batch)?;
+        self.treasury_records += 1;
+        Ok(())
+    }
+
+    /// Treasury records, oldest first
+    pub fn treasury_records(&self) -> Result<Vec<TreasuryRecord>, ExplorerError> {
+        let mut records = Vec::new();
+        for entry in self.store.iter_prefix(columns::EXPLORER_TREASURY, &[])? {
+            let (_, value) = entry?;
+            records.push(decode(&value)?);
+        }
+        Ok(records)
+    }
+
+    /// All stored blocks, lowest height first
+    pub fn blocks_by_height(&self) -> Result<Vec<Block>, ExplorerError> {
+        let mut blocks = Vec::new();
+        for entry in self.store.iter_prefix(columns::EXPLORER_HEIGHTS, &[])? {
+            let (_, hash) = entry?;
+            let hash: Hash = hash.try_into().map_err(|_| ExplorerError::StorageError("malformed height index".into()))?;
+            blocks.push(self.block(&hash)?);
+        }
+        Ok(blocks)
+    }
+
     /// Get block by height
     pub fn get_block_by_height(&self, height: u64) -> Result<Block, ExplorerError> {
-        let hash = self.heights.get(&height)
+        let hash = self.store.get(columns::EXPLORER_HEIGHTS, &height.to_be_bytes())?
             .ok_or(ExplorerError::BlockNotFound)?;
-        
-        self.blocks.get(hash)
-            .cloned()
-            .ok_or(ExplorerError::BlockNotFound)
+        let hash: Hash = hash.try_into().map_err(|_| ExplorerError::StorageError("malformed height index".into()))?;
+
+        self.block(&hash)
     }
 }
\ No newline at end of file
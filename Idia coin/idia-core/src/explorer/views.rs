@@ -1,6 +1,7 @@
 //! View key management for transaction privacy
 
 use super::*;
+use crate::types::SparkMint;
 use std::collections::{HashMap, HashSet};
 
 /// View key manager
@@ -44,9 +45,135 @@ impl ViewManager {
     }
 }
 
+/// A Spark note opened by a view key
+#[derive(Debug, Clone)]
+pub struct ViewedNote {
+    /// Note value
+    pub value: u64,
+    /// Index of the address the note was paid to
+    pub address_index: u64,
+    /// Memo attached by the sender
+    pub memo: Vec<u8>,
+    /// Nullifier, only for keys allowed to see spends
+    pub nullifier: Option<[u8; 32]>,
+}
+
+/// Opens Spark notes on behalf of a view key holder.
+///
+/// Implemented by the privacy module, which knows the key hierarchy. A
+/// viewer never has spend capability.
+pub trait SparkViewer: Send + Sync {
+    /// Identifier of the view key, safe to show and to use as a handle
+    fn fingerprint(&self) -> String;
+
+    /// Open a mint if it pays one of the key's addresses
+    fn view_mint(&self, mint: &SparkMint) -> Option<ViewedNote>;
+}
+
+/// A received Spark note as shown by the explorer
+#[derive(Debug, Clone)]
+pub struct SparkNoteView {
+    /// Hash of the minting transaction
+    pub tx_hash: Hash,
+    /// Height of the minting block
+    pub height: u64,
+    /// Note commitment
+    pub commitment: [u8; 32],
+    /// Note value
+    pub value: u64,
+    /// Index of the address the note was paid to
+    pub address_index: u64,
+    /// Memo attached by the sender
+    pub memo: Vec<u8>,
+    /// Height of the spending block, when spends are visible to the key
+    pub spent_at: Option<u64>,
+}
+
+/// Notes found for one registered view key
+struct SparkViewSession {
+    viewer: Box<dyn SparkViewer>,
+    notes: Vec<SparkNoteView>,
+    /// Index into `notes` by nullifier, for keys that see spends
+    by_nullifier: HashMap<[u8; 32], usize>,
+}
+
+/// Spark view keys registered with the explorer
+pub struct SparkViews {
+    sessions: HashMap<String, SparkViewSession>,
+}
+
+impl SparkViews {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Register a view key, returning its fingerprint as the handle
+    pub fn register(&mut self, viewer: Box<dyn SparkViewer>) -> String {
+        let fingerprint = viewer.fingerprint();
+        self.sessions.insert(
+            fingerprint.clone(),
+            SparkViewSession {
+                viewer,
+                notes: Vec::new(),
+                by_nullifier: HashMap::new(),
+            },
+        );
+        fingerprint
+    }
+
+    /// Forget a view key
+    pub fn unregister(&mut self, fingerprint: &str) -> bool {
+        self.sessions.remove(fingerprint).is_some()
+    }
+
+    /// Scan a block for every registered key, or only `fingerprint` when
+    /// catching a newly registered key up
+    pub fn process_block(&mut self, block: &Block, fingerprint: Option<&str>) {
+        let height = block.header.height;
+        for (id, session) in self.sessions.iter_mut() {
+            if fingerprint.is_some_and(|f| f != id) {
+                continue;
+            }
+            for tx in &block.transactions {
+                for mint in &tx.spark_mints {
+                    let Some(note) = session.viewer.view_mint(mint) else {
+                        continue;
+                    };
+                    if let Some(nullifier) = note.nullifier {
+                        session.by_nullifier.insert(nullifier, session.notes.len());
+                    }
+                    session.notes.push(SparkNoteView {
+                        tx_hash: tx.hash(),
+                        height,
+                        commitment: mint.commitment,
+                        value: note.value,
+                        address_index: note.address_index,
+                        memo: note.memo,
+                        spent_at: None,
+                    });
+                }
+            }
+            for nullifier in block.spark_nullifiers() {
+                if let Some(&index) = session.by_nullifier.get(nullifier) {
+                    session.notes[index].spent_at = Some(height);
+                }
+            }
+        }
+    }
+
+    /// Notes received by a registered key
+    pub fn notes(&self, fingerprint: &str) -> Option<&[SparkNoteView]> {
+        self.sessions.get(fingerprint).map(|session| session.notes.as_slice())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::SparkRedeem;
 
     #[test]
     fn test_view_authorization() {
@@ -65,4 +192,51 @@ mod tests {
         manager.revoke(&view_key, &tx_hash);
         assert!(!manager.is_authorized(&view_key, &tx_hash));
     }
+
+    struct TestViewer {
+        sees_spends: bool,
+    }
+
+    impl SparkViewer for TestViewer {
+        fn fingerprint(&self) -> String {
+            format!("test-{}", self.sees_spends)
+        }
+
+        // Opens mints whose commitment starts with 1
+        fn view_mint(&self, mint: &SparkMint) -> Option<ViewedNote> {
+            (mint.commitment[0] == 1).then(|| ViewedNote {
+                value: mint.value,
+                address_index: 0,
+                memo: Vec::new(),
+                nullifier: self.sees_spends.then_some([mint.value as u8; 32]),
+            })
+        }
+    }
+
+    fn block(height: u64, tx: Transaction) -> Block {
+        Block::new([0; 32], height, 0, vec![tx])
+    }
+
+    #[test]
+    fn test_spark_views() {
+        let mut views = SparkViews::new();
+        let incoming = views.register(Box::new(TestViewer { sees_spends: false }));
+        let full = views.register(Box::new(TestViewer { sees_spends: true }));
+
+        let mint = |value: u64, tag: u8| SparkMint { value, commitment: [tag; 32], proof: vec![0; 96], encrypted_note: Vec::new() };
+        let tx = Transaction::new(vec![], vec![], 0).with_spark_mints(vec![mint(7, 1), mint(9, 2)]);
+        views.process_block(&block(1, tx), None);
+        assert_eq!(views.notes(&incoming).unwrap().len(), 1);
+        assert_eq!(views.notes(&full).unwrap()[0].value, 7);
+
+        let redeem = SparkRedeem { nullifier: [7; 32], epoch: 0, value: 7, output_index: 0, proof: vec![0; 32] };
+        let tx = Transaction::new(vec![], vec![], 0).with_spark_redeems(vec![redeem]);
+        views.process_block(&block(2, tx), None);
+        // Only the full view key sees the note spent
+        assert_eq!(views.notes(&incoming).unwrap()[0].spent_at, None);
+        assert_eq!(views.notes(&full).unwrap()[0].spent_at, Some(2));
+
+        assert!(views.unregister(&incoming));
+        assert!(views.notes(&incoming).is_none());
+    }
 }
\ No newline at end of file
@@ -3,8 +3,13 @@
 //! This library implements the core functionality of the Idia privacy coin,
 //! including cryptographic primitives, network layer, and wallet functionality.
 
+pub mod config;
 pub mod crypto;
+pub mod explorer;
+pub mod metrics;
 pub mod network;
+pub mod shutdown;
+pub mod storage;
 pub mod wallet;
 pub mod types;
 
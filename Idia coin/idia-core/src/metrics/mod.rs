@@ -0,0 +1,112 @@
+//! Prometheus metrics for the node's subsystems
+//!
+//! Metrics live in the default registry, alongside any registered by crates
+//! built on this one, and are served in the text format by [`serve`].
+
+mod server;
+
+pub use server::*;
+
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge, Encoder, Histogram,
+    IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+
+lazy_static! {
+    // Network
+    pub static ref PEERS_CONNECTED: IntGauge = register_int_gauge!(
+        "idia_peers_connected",
+        "Peers currently connected"
+    ).unwrap();
+
+    pub static ref GOSSIP_RECEIVED: IntCounterVec = register_int_counter_vec!(
+        "idia_gossip_messages_received_total",
+        "Gossip messages received, by topic",
+        &["topic"]
+    ).unwrap();
+
+    pub static ref GOSSIP_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "idia_gossip_messages_rejected_total",
+        "Gossip messages rejected by validation, by topic",
+        &["topic"]
+    ).unwrap();
+
+    pub static ref BROADCASTS: IntCounterVec = register_int_counter_vec!(
+        "idia_broadcasts_total",
+        "Messages published to gossip, by topic",
+        &["topic"]
+    ).unwrap();
+
+    pub static ref BROADCAST_FAILURES: IntCounter = register_int_counter!(
+        "idia_broadcast_failures_total",
+        "Messages that could not be published"
+    ).unwrap();
+
+    // Mempool
+    pub static ref MEMPOOL_TRANSACTIONS: IntGauge = register_int_gauge!(
+        "idia_mempool_transactions",
+        "Transactions waiting in the mempool"
+    ).unwrap();
+
+    pub static ref MEMPOOL_REJECTED: IntCounter = register_int_counter!(
+        "idia_mempool_rejected_total",
+        "Transactions refused by the mempool"
+    ).unwrap();
+
+    pub static ref MEMPOOL_EVICTIONS: IntCounter = register_int_counter!(
+        "idia_mempool_evictions_total",
+        "Transactions evicted from a full mempool for a higher fee rate"
+    ).unwrap();
+
+    // Chain
+    pub static ref CHAIN_HEIGHT: IntGauge = register_int_gauge!(
+        "idia_chain_height",
+        "Height of the best chain tip"
+    ).unwrap();
+
+    pub static ref BLOCKS_CONNECTED: IntCounter = register_int_counter!(
+        "idia_blocks_connected_total",
+        "Blocks connected to the best chain"
+    ).unwrap();
+
+    pub static ref BLOCKS_REJECTED: IntCounter = register_int_counter!(
+        "idia_blocks_rejected_total",
+        "Blocks that failed validation"
+    ).unwrap();
+
+    pub static ref BLOCK_VALIDATION_SECONDS: Histogram = register_histogram!(
+        "idia_block_validation_seconds",
+        "Time to validate and connect a block",
+        vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]
+    ).unwrap();
+
+    // Wallet
+    pub static ref WALLET_BALANCE: IntGauge = register_int_gauge!(
+        "idia_wallet_balance",
+        "Spendable ring-CT balance of the wallet"
+    ).unwrap();
+
+    pub static ref WALLET_POOL_BALANCE: IntGauge = register_int_gauge!(
+        "idia_wallet_pool_balance",
+        "Spark pool balance of the wallet"
+    ).unwrap();
+
+    pub static ref WALLET_UNSPENT_OUTPUTS: IntGauge = register_int_gauge!(
+        "idia_wallet_unspent_outputs",
+        "Unspent outputs owned by the wallet"
+    ).unwrap();
+
+    pub static ref WALLET_SYNCED_HEIGHT: IntGauge = register_int_gauge!(
+        "idia_wallet_synced_height",
+        "Last block processed by the wallet"
+    ).unwrap();
+}
+
+/// Every registered metric in the Prometheus text format
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    // Encoding to a Vec cannot fail for well-formed metric families
+    let _ = TextEncoder::new().encode(&prometheus::gather(), &mut buffer);
+    String::from_utf8(buffer).unwrap_or_default()
+}
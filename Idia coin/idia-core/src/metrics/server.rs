@@ -0,0 +1,97 @@
+//! HTTP endpoint serving metrics to Prometheus
+
+use super::render;
+use crate::shutdown::ShutdownToken;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Metrics endpoint configuration, the `[metrics]` section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Whether to serve metrics at all
+    pub enabled: bool,
+    /// Address to listen on
+    pub bind: SocketAddr,
+    /// Bearer token required of scrapers, if set
+    pub token: Option<String>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: SocketAddr::from(([127, 0, 0, 1], 9101)),
+            token: None,
+        }
+    }
+}
+
+/// Compare without leaking how long a matching prefix was
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Routes serving `GET /metrics`
+pub fn metrics_routes(token: Option<String>) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(Arc::new(token))
+}
+
+/// Serve metrics until shutdown or until the listener fails
+pub async fn serve(config: &MetricsConfig, shutdown: ShutdownToken) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(config.bind).await?;
+    tracing::info!(bind = %config.bind, "Metrics listening");
+    axum::serve(listener, metrics_routes(config.token.clone()))
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .await
+}
+
+async fn metrics(State(token): State<Arc<Option<String>>>, headers: HeaderMap) -> Response {
+    if let Some(token) = token.as_deref() {
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if !given.is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes())) {
+            return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")]).into_response();
+        }
+    }
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], render()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::CHAIN_HEIGHT;
+
+    #[tokio::test]
+    async fn test_metrics_auth() {
+        CHAIN_HEIGHT.set(CHAIN_HEIGHT.get());
+
+        let mut headers = HeaderMap::new();
+        let response = metrics(State(Arc::new(None)), headers.clone()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let token = Arc::new(Some("secret".to_string()));
+        let response = metrics(State(token.clone()), headers.clone()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let response = metrics(State(token), headers).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_render() {
+        CHAIN_HEIGHT.set(CHAIN_HEIGHT.get());
+        assert!(render().contains("idia_chain_height"));
+    }
+}
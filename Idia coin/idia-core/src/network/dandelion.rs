@@ -38,11 +38,13 @@ pub struct DandelionHandler {
 }
 
 /// Dandelion++ configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DandelionConfig {
     /// Probability of entering fluff phase
     pub fluff_probability: f64,
-    /// Maximum time in stem phase
+    /// Maximum time in stem phase, in seconds in config files
+    #[serde(with = "crate::config::duration_secs")]
     pub stem_timeout: Duration,
 }
 
@@ -2,10 +2,12 @@
 
 mod p2p;
 mod dandelion;
+mod peers;
 mod tor;
 
 pub use p2p::*;
 pub use dandelion::*;
+pub use peers::*;
 pub use tor::*;
 
 use crate::types::{Transaction, Block};
@@ -19,11 +21,13 @@ use libp2p::{
     PeerId,
     Transport,
 };
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use tokio::sync::mpsc;
 
 /// Network configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NetworkConfig {
     /// Enable Tor SOCKS5 proxy
     pub use_tor: bool,
@@ -35,4 +39,16 @@ pub struct NetworkConfig {
     pub bootstrap_nodes: Vec<String>,
     /// Enable Dandelion++
     pub use_dandelion: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            use_tor: false,
+            tor_proxy: None,
+            listen_addresses: vec!["/ip4/0.0.0.0/tcp/8080".to_string()],
+            bootstrap_nodes: Vec::new(),
+            use_dandelion: true,
+        }
+    }
 }
\ No newline at end of file
@@ -1,17 +1,49 @@
 //! Core P2P networking implementation
 
 use super::*;
+use crate::metrics::{BROADCASTS, BROADCAST_FAILURES, GOSSIP_RECEIVED, GOSSIP_REJECTED, PEERS_CONNECTED};
 use libp2p::{
+    futures::StreamExt,
     gossipsub::{
-        Gossipsub, GossipsubConfig, GossipsubConfigBuilder,
-        MessageAuthenticity, ValidationMode,
+        Gossipsub, GossipsubConfig, GossipsubConfigBuilder, GossipsubEvent, GossipsubMessage,
+        IdentTopic, MessageAcceptance, MessageAuthenticity, MessageId, ValidationMode,
     },
-    swarm::SwarmBuilder,
+    swarm::{SwarmBuilder, SwarmEvent},
     Multiaddr,
     Swarm,
 };
+use crate::shutdown::ShutdownToken;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
+/// Seconds since the unix epoch
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Gossip topic carrying encoded rollup batches
+pub const ROLLUP_BATCH_TOPIC: &str = "rollup-batches";
+/// Gossip topic carrying swap offers and their negotiation
+pub const SWAP_ORDERS_TOPIC: &str = "swap-orders";
+
+/// Topics the service subscribes to
+const TOPICS: [&str; 5] = ["transactions", "blocks", "governance", ROLLUP_BATCH_TOPIC, SWAP_ORDERS_TOPIC];
+
+/// Decode a gossiped payload into the event for its topic
+fn decode_gossip(topic: &str, data: Vec<u8>) -> Option<NetworkEvent> {
+    match topic {
+        "transactions" => bincode::deserialize(&data).ok().map(NetworkEvent::Transaction),
+        "blocks" => bincode::deserialize(&data).ok().map(NetworkEvent::Block),
+        "governance" => Some(NetworkEvent::Governance(data)),
+        ROLLUP_BATCH_TOPIC => Some(NetworkEvent::RollupBatch(data)),
+        SWAP_ORDERS_TOPIC => Some(NetworkEvent::SwapOrder(data)),
+        _ => None,
+    }
+}
+
 /// P2P network events
 #[derive(Debug)]
 pub enum NetworkEvent {
@@ -19,30 +51,97 @@ pub enum NetworkEvent {
     Transaction(Transaction),
     /// New block received
     Block(Block),
+    /// Governance committee message, opaque to the network layer
+    Governance(Vec<u8>),
+    /// Encoded rollup batch, opaque to the network layer
+    RollupBatch(Vec<u8>),
+    /// Encoded swap order book message, opaque to the network layer
+    SwapOrder(Vec<u8>),
     /// New peer connected
     PeerConnected(PeerId),
     /// Peer disconnected
     PeerDisconnected(PeerId),
 }
 
+/// Requests from the rest of the node to the network
+#[derive(Debug)]
+pub enum NetworkCommand {
+    /// Gossip a transaction
+    BroadcastTransaction(Transaction),
+    /// Gossip a block
+    BroadcastBlock(Block),
+    /// Gossip an encoded rollup batch
+    BroadcastRollupBatch(Vec<u8>),
+    /// Gossip an encoded swap order book message
+    BroadcastSwapOrder(Vec<u8>),
+}
+
+/// Outcome of application-level validation of a gossiped payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GossipAcceptance {
+    /// Valid; deliver locally and keep propagating
+    Accept,
+    /// Not useful (e.g. already seen) but not the sender's fault
+    Ignore,
+    /// Invalid; drop it and do not propagate
+    Reject,
+}
+
+impl From<GossipAcceptance> for MessageAcceptance {
+    fn from(acceptance: GossipAcceptance) -> Self {
+        match acceptance {
+            GossipAcceptance::Accept => MessageAcceptance::Accept,
+            GossipAcceptance::Ignore => MessageAcceptance::Ignore,
+            GossipAcceptance::Reject => MessageAcceptance::Reject,
+        }
+    }
+}
+
+/// Validates payloads of one topic before they reach the rest of the node
+pub trait GossipValidator: Send + Sync {
+    /// Check an encoded payload
+    fn validate(&self, payload: &[u8]) -> GossipAcceptance;
+}
+
 /// P2P network service
 pub struct P2PService {
     /// libp2p swarm
     swarm: Swarm<IdiaNetworkBehaviour>,
     /// Event channel sender
     event_sender: mpsc::Sender<NetworkEvent>,
-    /// Event channel receiver
-    event_receiver: mpsc::Receiver<NetworkEvent>,
+    /// Event channel receiver, until taken by the node
+    event_receiver: Option<mpsc::Receiver<NetworkEvent>>,
+    /// Command channel sender, cloned out to the node
+    command_sender: mpsc::Sender<NetworkCommand>,
+    /// Command channel receiver
+    command_receiver: mpsc::Receiver<NetworkCommand>,
+    /// Application validators by topic
+    validators: HashMap<String, Arc<dyn GossipValidator>>,
+    /// Peers seen across restarts, if persisted
+    peers: Option<PeerStore>,
 }
 
 /// Custom network behaviour
 #[derive(NetworkBehaviour)]
-#[behaviour(out_event = "NetworkEvent")]
+#[behaviour(out_event = "IdiaBehaviourEvent")]
 pub struct IdiaNetworkBehaviour {
     /// Gossipsub for p2p message propagation
     gossipsub: Gossipsub,
 }
 
+/// Events raised by [`IdiaNetworkBehaviour`]
+#[derive(Debug)]
+pub enum IdiaBehaviourEvent {
+    /// Gossipsub event
+    Gossipsub(GossipsubEvent),
+}
+
+impl From<GossipsubEvent> for IdiaBehaviourEvent {
+    fn from(event: GossipsubEvent) -> Self {
+        IdiaBehaviourEvent::Gossipsub(event)
+    }
+}
+
 impl P2PService {
     /// Create a new P2P service
     pub async fn new(config: NetworkConfig) -> Result<Self, Box<dyn Error>> {
@@ -50,9 +149,11 @@ impl P2PService {
         let keypair = identity::Keypair::generate_ed25519();
         let peer_id = PeerId::from(keypair.public());
 
-        // Set up gossipsub
+        // Set up gossipsub. Messages are held until validated, so only
+        // payloads we accept are forwarded to other peers
         let gossipsub_config = GossipsubConfigBuilder::default()
             .validation_mode(ValidationMode::Strict)
+            .validate_messages()
             .message_id_fn(|message| {
                 // Custom message ID function
                 let mut hasher = Sha256::new();
@@ -62,10 +163,13 @@ impl P2PService {
             .build()
             .expect("Valid gossipsub config");
 
-        let gossipsub = Gossipsub::new(
+        let mut gossipsub = Gossipsub::new(
             MessageAuthenticity::Signed(keypair.clone()),
             gossipsub_config,
         )?;
+        for topic in TOPICS {
+            gossipsub.subscribe(&IdentTopic::new(topic))?;
+        }
 
         // Create transport
         let noise_keys = noise::Keypair::<noise::X25519Spec>::new()
@@ -96,16 +200,49 @@ impl P2PService {
 
         // Create event channels
         let (tx, rx) = mpsc::channel(100);
+        let (command_tx, command_rx) = mpsc::channel(100);
 
         Ok(Self {
             swarm,
             event_sender: tx,
-            event_receiver: rx,
+            event_receiver: Some(rx),
+            command_sender: command_tx,
+            command_receiver: command_rx,
+            validators: HashMap::new(),
+            peers: None,
         })
     }
 
+    /// Take the receiver of events for the rest of the node; only the first
+    /// call gets it
+    pub fn take_events(&mut self) -> Option<mpsc::Receiver<NetworkEvent>> {
+        self.event_receiver.take()
+    }
+
+    /// Sender of commands, handled while the service runs
+    pub fn commands(&self) -> mpsc::Sender<NetworkCommand> {
+        self.command_sender.clone()
+    }
+
+    /// Register the validator for a topic's payloads
+    pub fn set_validator(&mut self, topic: &str, validator: Arc<dyn GossipValidator>) {
+        self.validators.insert(topic.to_string(), validator);
+    }
+
+    /// Remember connected peers in `peers`
+    pub fn set_peer_store(&mut self, peers: PeerStore) {
+        self.peers = Some(peers);
+    }
+
+    /// Run a topic's validator, accepting when none is registered
+    fn validate(&self, topic: &str, payload: &[u8]) -> GossipAcceptance {
+        self.validators
+            .get(topic)
+            .map_or(GossipAcceptance::Accept, |validator| validator.validate(payload))
+    }
+
     /// Start the P2P service
-    pub async fn run(&mut self) {
+    pub async fn run(&mut self, shutdown: ShutdownToken) {
         loop {
             tokio::select! {
                 event = self.swarm.next() => {
@@ -113,60 +250,208 @@ impl P2PService {
                         self.handle_swarm_event(event).await;
                     }
                 }
+                Some(command) = self.command_receiver.recv() => {
+                    self.handle_command(command).await;
+                }
                 _ = tokio::time::sleep(Duration::from_secs(60)) => {
                     // Periodic maintenance
                     self.maintain().await;
                 }
+                _ = shutdown.wait() => {
+                    // Publish what was queued before the stop, so accepted
+                    // blocks and transactions still reach peers
+                    while let Ok(command) = self.command_receiver.try_recv() {
+                        self.handle_command(command).await;
+                    }
+                    tracing::info!("Network service stopped");
+                    return;
+                }
             }
         }
     }
 
     /// Handle swarm events
-    async fn handle_swarm_event(&mut self, event: NetworkEvent) {
+    async fn handle_swarm_event<E>(&mut self, event: SwarmEvent<IdiaBehaviourEvent, E>) {
+        match event {
+            SwarmEvent::Behaviour(IdiaBehaviourEvent::Gossipsub(GossipsubEvent::Message {
+                propagation_source,
+                message_id,
+                message,
+            })) => self.handle_gossip(propagation_source, message_id, message).await,
+            SwarmEvent::ConnectionEstablished { peer_id, num_established, .. } if num_established.get() == 1 => {
+                self.deliver(NetworkEvent::PeerConnected(peer_id)).await;
+            }
+            SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. } => {
+                self.deliver(NetworkEvent::PeerDisconnected(peer_id)).await;
+            }
+            _ => {}
+        }
+    }
+
+    /// Validate a gossiped message, deliver it if accepted and report the
+    /// outcome to gossipsub, which forwards accepted messages and penalizes
+    /// the peers that sent rejected ones
+    async fn handle_gossip(&mut self, source: PeerId, id: MessageId, message: GossipsubMessage) {
+        let topic = message.topic.as_str();
+        let acceptance = match self.validate(topic, &message.data) {
+            GossipAcceptance::Accept => match decode_gossip(topic, message.data) {
+                Some(event) => {
+                    self.deliver(event).await;
+                    GossipAcceptance::Accept
+                }
+                None => GossipAcceptance::Reject,
+            },
+            other => other,
+        };
+        if acceptance == GossipAcceptance::Reject {
+            GOSSIP_REJECTED.with_label_values(&[topic]).inc();
+            tracing::warn!(topic, peer = %source, "Rejected invalid gossip");
+        }
+        if let Err(e) = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(&id, &source, acceptance.into())
+        {
+            tracing::warn!(error = %e, "Failed to report gossip validation");
+        }
+    }
+
+    /// Pass an accepted event to the rest of the node
+    async fn deliver(&mut self, event: NetworkEvent) {
         match event {
             NetworkEvent::Transaction(tx) => {
-                // Handle new transaction
+                GOSSIP_RECEIVED.with_label_values(&["transactions"]).inc();
+                tracing::debug!(tx = %hex::encode(tx.hash()), "Received transaction from gossip");
                 if let Err(e) = self.event_sender.send(NetworkEvent::Transaction(tx)).await {
-                    log::error!("Failed to send transaction event: {}", e);
+                    tracing::error!(error = %e, "Failed to send transaction event");
                 }
             }
             NetworkEvent::Block(block) => {
-                // Handle new block
+                GOSSIP_RECEIVED.with_label_values(&["blocks"]).inc();
+                tracing::debug!(height = block.header.height, "Received block from gossip");
                 if let Err(e) = self.event_sender.send(NetworkEvent::Block(block)).await {
-                    log::error!("Failed to send block event: {}", e);
+                    tracing::error!(error = %e, "Failed to send block event");
+                }
+            }
+            NetworkEvent::Governance(payload) => {
+                GOSSIP_RECEIVED.with_label_values(&["governance"]).inc();
+                if let Err(e) = self.event_sender.send(NetworkEvent::Governance(payload)).await {
+                    tracing::error!("Failed to send governance event: {}", e);
+                }
+            }
+            NetworkEvent::RollupBatch(payload) => {
+                GOSSIP_RECEIVED.with_label_values(&[ROLLUP_BATCH_TOPIC]).inc();
+                if let Err(e) = self.event_sender.send(NetworkEvent::RollupBatch(payload)).await {
+                    tracing::error!("Failed to send rollup batch event: {}", e);
+                }
+            }
+            NetworkEvent::SwapOrder(payload) => {
+                GOSSIP_RECEIVED.with_label_values(&[SWAP_ORDERS_TOPIC]).inc();
+                if let Err(e) = self.event_sender.send(NetworkEvent::SwapOrder(payload)).await {
+                    tracing::error!("Failed to send swap order event: {}", e);
                 }
             }
             NetworkEvent::PeerConnected(peer_id) => {
-                log::info!("Peer connected: {}", peer_id);
+                PEERS_CONNECTED.inc();
+                tracing::info!(peer = %peer_id, "Peer connected");
+                if let Some(peers) = &self.peers {
+                    if let Err(e) = peers.record_connected(&peer_id, unix_now()) {
+                        tracing::warn!(error = %e, "Failed to record peer");
+                    }
+                }
             }
             NetworkEvent::PeerDisconnected(peer_id) => {
-                log::info!("Peer disconnected: {}", peer_id);
+                PEERS_CONNECTED.dec();
+                tracing::info!(peer = %peer_id, "Peer disconnected");
+                if let Some(peers) = &self.peers {
+                    if let Err(e) = peers.record_disconnected(&peer_id, unix_now()) {
+                        tracing::warn!(error = %e, "Failed to record peer");
+                    }
+                }
             }
         }
     }
 
+    /// Handle a command from the node
+    async fn handle_command(&mut self, command: NetworkCommand) {
+        let result = match command {
+            NetworkCommand::BroadcastTransaction(tx) => self.broadcast_transaction(tx).await,
+            NetworkCommand::BroadcastBlock(block) => self.broadcast_block(block).await,
+            NetworkCommand::BroadcastRollupBatch(payload) => self.broadcast_rollup_batch(payload).await,
+            NetworkCommand::BroadcastSwapOrder(payload) => self.broadcast_swap_order(payload).await,
+        };
+        if let Err(e) = result {
+            BROADCAST_FAILURES.inc();
+            tracing::error!(error = %e, "Failed to broadcast");
+        }
+    }
+
     /// Periodic maintenance
     async fn maintain(&mut self) {
         // Cleanup, reconnect to peers, etc.
+        if let Some(peers) = &self.peers {
+            match peers.prune(unix_now()) {
+                Ok(0) => {}
+                Ok(pruned) => tracing::debug!(pruned, "Forgot stale peers"),
+                Err(e) => tracing::warn!(error = %e, "Failed to prune peer store"),
+            }
+        }
     }
 
     /// Broadcast a transaction to the network
+    #[tracing::instrument(name = "relay_transaction", skip_all, fields(tx = %hex::encode(tx.hash())))]
     pub async fn broadcast_transaction(&mut self, tx: Transaction) -> Result<(), Box<dyn Error>> {
         let encoded = bincode::serialize(&tx)?;
         self.swarm.behaviour_mut().gossipsub.publish(
             "transactions".into(),
             encoded,
         )?;
+        BROADCASTS.with_label_values(&["transactions"]).inc();
+        tracing::debug!("Published transaction");
         Ok(())
     }
 
     /// Broadcast a block to the network
+    #[tracing::instrument(name = "relay_block", skip_all, fields(height = block.header.height))]
     pub async fn broadcast_block(&mut self, block: Block) -> Result<(), Box<dyn Error>> {
         let encoded = bincode::serialize(&block)?;
         self.swarm.behaviour_mut().gossipsub.publish(
             "blocks".into(),
             encoded,
         )?;
+        BROADCASTS.with_label_values(&["blocks"]).inc();
+        tracing::debug!("Published block");
+        Ok(())
+    }
+
+    /// Broadcast an encoded governance committee message
+    pub async fn broadcast_governance(&mut self, payload: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        self.swarm.behaviour_mut().gossipsub.publish(
+            "governance".into(),
+            payload,
+        )?;
+        BROADCASTS.with_label_values(&["governance"]).inc();
+        Ok(())
+    }
+
+    /// Broadcast an encoded rollup batch
+    pub async fn broadcast_rollup_batch(&mut self, payload: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        self.swarm.behaviour_mut().gossipsub.publish(
+            ROLLUP_BATCH_TOPIC.into(),
+            payload,
+        )?;
+        BROADCASTS.with_label_values(&[ROLLUP_BATCH_TOPIC]).inc();
+        Ok(())
+    }
+
+    /// Broadcast an encoded swap order book message
+    pub async fn broadcast_swap_order(&mut self, payload: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        self.swarm.behaviour_mut().gossipsub.publish(
+            SWAP_ORDERS_TOPIC.into(),
+            payload,
+        )?;
+        BROADCASTS.with_label_values(&[SWAP_ORDERS_TOPIC]).inc();
         Ok(())
     }
 }
\ No newline at end of file
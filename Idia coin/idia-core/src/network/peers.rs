@@ -0,0 +1,115 @@
+//! Persistent record of the peers the node has connected to
+
+use super::*;
+use crate::storage::{decode, encode, KvStore, StorageError};
+
+/// How long a peer is remembered after it was last seen, in seconds
+pub const PEER_RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// What is remembered about a peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    /// Unix time of the first connection
+    pub first_seen: u64,
+    /// Unix time the peer last connected or disconnected
+    pub last_seen: u64,
+    /// Number of connections made
+    pub connections: u64,
+}
+
+/// Peers by ID, kept in a single keyspace so they survive restarts
+pub struct PeerStore {
+    store: Box<dyn KvStore>,
+}
+
+impl PeerStore {
+    /// Keep peers in `store`
+    pub fn new(store: impl KvStore + 'static) -> Self {
+        Self { store: Box::new(store) }
+    }
+
+    /// Get what is known about a peer
+    pub fn get(&self, peer: &PeerId) -> Result<Option<PeerRecord>, StorageError> {
+        self.store.get(&peer.to_bytes())?.map(|bytes| decode(&bytes)).transpose()
+    }
+
+    /// Note a connection to `peer` at unix time `now`
+    pub fn record_connected(&self, peer: &PeerId, now: u64) -> Result<PeerRecord, StorageError> {
+        let record = match self.get(peer)? {
+            Some(record) => PeerRecord {
+                last_seen: now,
+                connections: record.connections + 1,
+                ..record
+            },
+            None => PeerRecord {
+                first_seen: now,
+                last_seen: now,
+                connections: 1,
+            },
+        };
+        self.store.put(&peer.to_bytes(), &encode(&record)?)?;
+        Ok(record)
+    }
+
+    /// Note that `peer` disconnected at unix time `now`
+    pub fn record_disconnected(&self, peer: &PeerId, now: u64) -> Result<(), StorageError> {
+        if let Some(record) = self.get(peer)? {
+            let record = PeerRecord { last_seen: now, ..record };
+            self.store.put(&peer.to_bytes(), &encode(&record)?)?;
+        }
+        Ok(())
+    }
+
+    /// Every remembered peer
+    pub fn peers(&self) -> Result<Vec<(PeerId, PeerRecord)>, StorageError> {
+        let mut peers = Vec::new();
+        for entry in self.store.iter_prefix(&[])? {
+            let (key, value) = entry?;
+            match PeerId::from_bytes(&key) {
+                Ok(peer) => peers.push((peer, decode(&value)?)),
+                Err(_) => tracing::warn!("Skipping malformed peer ID in peer store"),
+            }
+        }
+        Ok(peers)
+    }
+
+    /// Forget peers not seen for [`PEER_RETENTION_SECS`], returning how
+    /// many were dropped
+    pub fn prune(&self, now: u64) -> Result<usize, StorageError> {
+        let stale: Vec<PeerId> = self
+            .peers()?
+            .into_iter()
+            .filter(|(_, record)| record.last_seen.saturating_add(PEER_RETENTION_SECS) < now)
+            .map(|(peer, _)| peer)
+            .collect();
+        for peer in &stale {
+            self.store.delete(&peer.to_bytes())?;
+        }
+        Ok(stale.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{columns, Column, MemoryStore};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_record_and_prune() {
+        let store = PeerStore::new(Column::new(Arc::new(MemoryStore::new()), columns::PEERS));
+        let old = PeerId::random();
+        let recent = PeerId::random();
+
+        store.record_connected(&old, 100).unwrap();
+        store.record_disconnected(&old, 200).unwrap();
+        let record = store.record_connected(&recent, 150).unwrap();
+        assert_eq!(store.record_connected(&recent, 300).unwrap().connections, record.connections + 1);
+        assert_eq!(store.get(&old).unwrap().unwrap().last_seen, 200);
+        assert_eq!(store.peers().unwrap().len(), 2);
+
+        assert_eq!(store.prune(250 + PEER_RETENTION_SECS).unwrap(), 1);
+        assert!(store.get(&old).unwrap().is_none());
+        assert!(store.get(&recent).unwrap().is_some());
+    }
+}
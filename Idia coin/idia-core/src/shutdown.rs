@@ -0,0 +1,122 @@
+//! Cooperative shutdown of long-running tasks
+//!
+//! A [`Shutdown`] hands out [`ShutdownToken`]s. Tasks select on
+//! [`ShutdownToken::wait`] alongside their work, flush whatever they hold
+//! and return, rather than being dropped mid-write.
+
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Starts shutdown for every token it has handed out
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    /// Create a shutdown that has not been triggered
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self { sender: Arc::new(sender) }
+    }
+
+    /// Token observing this shutdown
+    pub fn token(&self) -> ShutdownToken {
+        ShutdownToken {
+            receiver: self.sender.subscribe(),
+        }
+    }
+
+    /// Begin shutdown; later calls do nothing
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    /// Whether shutdown has begun
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tells a task when to stop
+#[derive(Debug, Clone)]
+pub struct ShutdownToken {
+    receiver: watch::Receiver<bool>,
+}
+
+impl ShutdownToken {
+    /// Whether shutdown has begun
+    pub fn is_shutdown(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Resolve once shutdown begins. Never resolves if every [`Shutdown`]
+    /// is dropped without triggering.
+    pub async fn wait(&self) {
+        let mut receiver = self.receiver.clone();
+        if receiver.wait_for(|stopping| *stopping).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Resolve on SIGINT, or on SIGTERM where there is one
+pub async fn os_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => tracing::info!("Interrupted, shutting down"),
+                    _ = terminate.recv() => tracing::info!("Terminated, shutting down"),
+                }
+                return;
+            }
+            Err(e) => tracing::warn!(error = %e, "Cannot listen for SIGTERM"),
+        }
+    }
+    if tokio::signal::ctrl_c().await.is_ok() {
+        tracing::info!("Interrupted, shutting down");
+    } else {
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_trigger_wakes_tokens() {
+        let shutdown = Shutdown::new();
+        let token = shutdown.token();
+        let waiting = tokio::spawn({
+            let token = token.clone();
+            async move { token.wait().await }
+        });
+        assert!(!token.is_shutdown());
+
+        shutdown.trigger();
+        waiting.await.unwrap();
+        assert!(token.is_shutdown());
+        assert!(shutdown.is_triggered());
+        // Tokens taken after the fact see it too
+        shutdown.token().wait().await;
+    }
+
+    #[tokio::test]
+    async fn test_dropped_shutdown_never_fires() {
+        let token = Shutdown::new().token();
+        let waited = tokio::time::timeout(Duration::from_millis(20), token.wait()).await;
+        assert!(waited.is_err());
+        assert!(!token.is_shutdown());
+    }
+}
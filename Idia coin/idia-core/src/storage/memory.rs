@@ -0,0 +1,86 @@
+//! In-memory storage backend
+
+use super::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+/// Store kept in memory, for tests and nodes that need nothing on disk.
+///
+/// Columns are created on first write.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    columns: RwLock<HashMap<String, BTreeMap<Vec<u8>, Vec<u8>>>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ColumnStore for MemoryStore {
+    fn get(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        let columns = self.columns.read().unwrap();
+        Ok(columns.get(column).and_then(|entries| entries.get(key).cloned()))
+    }
+
+    fn iter_prefix<'a>(&'a self, column: &str, prefix: &[u8]) -> Result<KvIter<'a>, StorageError> {
+        // Copied out so the lock isn't held while the caller iterates
+        let columns = self.columns.read().unwrap();
+        let entries: Vec<_> = columns
+            .get(column)
+            .map(|entries| {
+                entries
+                    .range(prefix.to_vec()..)
+                    .take_while(|(key, _)| key.starts_with(prefix))
+                    .map(|(key, value)| Ok((key.clone(), value.clone())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut columns = self.columns.write().unwrap();
+        for op in batch.into_ops() {
+            match op {
+                BatchOp::Put { column, key, value } => {
+                    columns.entry(column.to_string()).or_default().insert(key, value);
+                }
+                BatchOp::Delete { column, key } => {
+                    if let Some(entries) = columns.get_mut(column) {
+                        entries.remove(&key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_and_prefix_iteration() {
+        let store = MemoryStore::new();
+        let mut batch = WriteBatch::new();
+        batch.put(columns::CHAIN_HEIGHTS, 2u64.to_be_bytes(), b"two".to_vec());
+        batch.put(columns::CHAIN_HEIGHTS, 1u64.to_be_bytes(), b"one".to_vec());
+        batch.put(columns::CHAIN_HEIGHTS, 256u64.to_be_bytes(), b"big".to_vec());
+        batch.delete(columns::CHAIN_HEIGHTS, 2u64.to_be_bytes());
+        store.write(batch).unwrap();
+
+        let all: Vec<_> = store
+            .iter_prefix(columns::CHAIN_HEIGHTS, &[])
+            .unwrap()
+            .map(|entry| entry.unwrap().1)
+            .collect();
+        assert_eq!(all, vec![b"one".to_vec(), b"big".to_vec()]);
+
+        let low: Vec<_> = store.iter_prefix(columns::CHAIN_HEIGHTS, &[0; 7]).unwrap().collect();
+        assert_eq!(low.len(), 1);
+    }
+}
@@ -0,0 +1,318 @@
+//! Persistent key-value storage shared by the chain, explorer, wallet and
+//! peer store
+//!
+//! A [`ColumnStore`] holds named columns of byte keys and values, sorted by
+//! key. Writes go through a [`WriteBatch`] that lands atomically, so a
+//! component that touches several columns never leaves them half updated.
+//! [`Column`] narrows a store to one column for components that only need a
+//! single [`KvStore`].
+//!
+//! Integer keys are big-endian so that iteration follows numeric order.
+
+mod memory;
+mod rocks;
+
+pub use memory::*;
+pub use rocks::*;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Storage error types
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Unknown column {0}")]
+    UnknownColumn(String),
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+    #[error("Encoding error: {0}")]
+    Codec(#[from] bincode::Error),
+}
+
+/// Columns of the node database, each owned by one component
+pub mod columns {
+    /// Best chain blocks by hash, dropped once pruned
+    pub const CHAIN_BLOCKS: &str = "chain_blocks";
+    /// Best chain block headers by hash, kept when blocks are pruned
+    pub const CHAIN_HEADERS: &str = "chain_headers";
+    /// Best chain block hashes by height
+    pub const CHAIN_HEIGHTS: &str = "chain_heights";
+    /// Block hash and position of each best chain transaction
+    pub const CHAIN_TRANSACTIONS: &str = "chain_transactions";
+    /// Chain counters
+    pub const CHAIN_META: &str = "chain_meta";
+    /// Outputs created on the best chain, by transaction hash and index
+    pub const UTXOS: &str = "utxos";
+    /// Range proofs of best chain outputs, dropped once pruned
+    pub const RANGE_PROOFS: &str = "range_proofs";
+    /// Height at which each key image was spent
+    pub const KEY_IMAGES: &str = "key_images";
+    /// Explorer blocks by hash
+    pub const EXPLORER_BLOCKS: &str = "explorer_blocks";
+    /// Explorer block hashes by height
+    pub const EXPLORER_HEIGHTS: &str = "explorer_heights";
+    /// Block hash and position of each indexed transaction
+    pub const EXPLORER_TRANSACTIONS: &str = "explorer_transactions";
+    /// Explorer totals
+    pub const EXPLORER_META: &str = "explorer_meta";
+    /// Treasury activity by sequence number
+    pub const EXPLORER_TREASURY: &str = "explorer_treasury";
+    /// Wallet outputs not yet spent
+    pub const WALLET_OUTPUTS: &str = "wallet_outputs";
+    /// Outputs the wallet has spent, by key image
+    pub const WALLET_SPENT: &str = "wallet_spent";
+    /// Wallet transfers by sequence number
+    pub const WALLET_TRANSFERS: &str = "wallet_transfers";
+    /// Wallet counters
+    pub const WALLET_META: &str = "wallet_meta";
+    /// Known peers by peer ID
+    pub const PEERS: &str = "peers";
+
+    /// Every column, for backends that must declare them up front
+    pub const ALL: &[&str] = &[
+        CHAIN_BLOCKS,
+        CHAIN_HEADERS,
+        CHAIN_HEIGHTS,
+        CHAIN_TRANSACTIONS,
+        CHAIN_META,
+        UTXOS,
+        RANGE_PROOFS,
+        KEY_IMAGES,
+        EXPLORER_BLOCKS,
+        EXPLORER_HEIGHTS,
+        EXPLORER_TRANSACTIONS,
+        EXPLORER_META,
+        EXPLORER_TREASURY,
+        WALLET_OUTPUTS,
+        WALLET_SPENT,
+        WALLET_TRANSFERS,
+        WALLET_META,
+        PEERS,
+    ];
+}
+
+/// Key and value pair read from a store
+pub type KvPair = (Vec<u8>, Vec<u8>);
+
+/// Entries in key order
+pub type KvIter<'a> = Box<dyn Iterator<Item = Result<KvPair, StorageError>> + 'a>;
+
+/// One write in a [`WriteBatch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOp {
+    /// Set `key` in `column` to `value`
+    Put {
+        column: &'static str,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    /// Remove `key` from `column`
+    Delete { column: &'static str, key: Vec<u8> },
+}
+
+/// Writes applied together or not at all
+#[derive(Debug, Clone, Default)]
+pub struct WriteBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    /// Create an empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `key` in `column`
+    pub fn put(&mut self, column: &'static str, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        self.ops.push(BatchOp::Put {
+            column,
+            key: key.into(),
+            value: value.into(),
+        });
+    }
+
+    /// Set `key` in `column` to the encoding of `value`
+    pub fn put_value<T: Serialize>(
+        &mut self,
+        column: &'static str,
+        key: impl Into<Vec<u8>>,
+        value: &T,
+    ) -> Result<(), StorageError> {
+        self.put(column, key, encode(value)?);
+        Ok(())
+    }
+
+    /// Remove `key` from `column`
+    pub fn delete(&mut self, column: &'static str, key: impl Into<Vec<u8>>) {
+        self.ops.push(BatchOp::Delete { column, key: key.into() });
+    }
+
+    /// Number of writes
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether the batch writes nothing
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// The writes, in the order they were added
+    pub fn into_ops(self) -> Vec<BatchOp> {
+        self.ops
+    }
+}
+
+/// Named columns of sorted keys, written atomically in batches
+pub trait ColumnStore: Send + Sync {
+    /// Read `key` from `column`
+    fn get(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Entries of `column` whose keys start with `prefix`, in key order
+    fn iter_prefix<'a>(&'a self, column: &str, prefix: &[u8]) -> Result<KvIter<'a>, StorageError>;
+
+    /// Apply every write in `batch`, or none if any fails
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError>;
+
+    /// Make written data durable
+    fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+/// A single keyspace
+pub trait KvStore: Send + Sync {
+    /// Read `key`
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Set `key` to `value`
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), StorageError>;
+
+    /// Remove `key`
+    fn delete(&self, key: &[u8]) -> Result<(), StorageError>;
+
+    /// Entries whose keys start with `prefix`, in key order
+    fn iter_prefix(&self, prefix: &[u8]) -> Result<KvIter<'_>, StorageError>;
+}
+
+/// One column of a shared [`ColumnStore`], used as a [`KvStore`]
+#[derive(Clone)]
+pub struct Column {
+    store: Arc<dyn ColumnStore>,
+    name: &'static str,
+}
+
+impl Column {
+    /// Narrow `store` to the column `name`
+    pub fn new(store: Arc<dyn ColumnStore>, name: &'static str) -> Self {
+        Self { store, name }
+    }
+}
+
+impl KvStore for Column {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        self.store.get(self.name, key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        let mut batch = WriteBatch::new();
+        batch.put(self.name, key, value);
+        self.store.write(batch)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<(), StorageError> {
+        let mut batch = WriteBatch::new();
+        batch.delete(self.name, key);
+        self.store.write(batch)
+    }
+
+    fn iter_prefix(&self, prefix: &[u8]) -> Result<KvIter<'_>, StorageError> {
+        self.store.iter_prefix(self.name, prefix)
+    }
+}
+
+/// Read and decode `key` from `column`
+pub fn get_value<T: DeserializeOwned>(
+    store: &dyn ColumnStore,
+    column: &str,
+    key: &[u8],
+) -> Result<Option<T>, StorageError> {
+    store.get(column, key)?.map(|bytes| decode(&bytes)).transpose()
+}
+
+/// Encode a stored value
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, StorageError> {
+    Ok(bincode::serialize(value)?)
+}
+
+/// Decode a stored value
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, StorageError> {
+    Ok(bincode::deserialize(bytes)?)
+}
+
+/// Storage backend to open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Kept in memory and lost on exit
+    Memory,
+    /// RocksDB database on disk
+    Rocksdb,
+}
+
+/// Storage configuration, the `[storage]` section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Backend to store data in
+    pub backend: StorageBackend,
+    /// Database directory, for on-disk backends
+    pub path: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::Rocksdb,
+            path: PathBuf::from("./data/db"),
+        }
+    }
+}
+
+/// Open the configured store with every column in [`columns::ALL`]
+pub fn open(config: &StorageConfig) -> Result<Arc<dyn ColumnStore>, StorageError> {
+    Ok(match config.backend {
+        StorageBackend::Memory => Arc::new(MemoryStore::new()),
+        StorageBackend::Rocksdb => Arc::new(RocksStore::open(&config.path, columns::ALL)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_store_as_kv_store() {
+        let store: Arc<dyn ColumnStore> = Arc::new(MemoryStore::new());
+        let peers = Column::new(store.clone(), columns::PEERS);
+        peers.put(b"a", b"1").unwrap();
+        assert_eq!(peers.get(b"a").unwrap(), Some(b"1".to_vec()));
+        // Columns don't see each other's keys
+        assert_eq!(store.get(columns::UTXOS, b"a").unwrap(), None);
+
+        peers.delete(b"a").unwrap();
+        assert_eq!(peers.get(b"a").unwrap(), None);
+    }
+
+    #[test]
+    fn test_values_round_trip() {
+        let store = MemoryStore::new();
+        let mut batch = WriteBatch::new();
+        batch.put_value(columns::WALLET_META, b"height".to_vec(), &42u64).unwrap();
+        store.write(batch).unwrap();
+        assert_eq!(get_value::<u64>(&store, columns::WALLET_META, b"height").unwrap(), Some(42));
+        assert_eq!(get_value::<u64>(&store, columns::WALLET_META, b"missing").unwrap(), None);
+    }
+}
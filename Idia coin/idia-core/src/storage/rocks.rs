@@ -0,0 +1,106 @@
+//! RocksDB storage backend
+
+use super::*;
+use rocksdb::{ColumnFamily, Direction, IteratorMode, Options, DB};
+use std::path::Path;
+
+/// Store backed by a RocksDB database, one column family per column
+pub struct RocksStore {
+    db: DB,
+    columns: Vec<String>,
+}
+
+impl RocksStore {
+    /// Open or create the database at `path` with the given columns
+    pub fn open(path: &Path, columns: &[&str]) -> Result<Self, StorageError> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let db = DB::open_cf(&options, path, columns).map_err(backend)?;
+        tracing::info!(path = %path.display(), "Opened database");
+        Ok(Self {
+            db,
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+        })
+    }
+
+    fn column(&self, name: &str) -> Result<&ColumnFamily, StorageError> {
+        self.db
+            .cf_handle(name)
+            .ok_or_else(|| StorageError::UnknownColumn(name.to_string()))
+    }
+}
+
+fn backend(e: rocksdb::Error) -> StorageError {
+    StorageError::Backend(e.into_string())
+}
+
+impl ColumnStore for RocksStore {
+    fn get(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        self.db.get_cf(self.column(column)?, key).map_err(backend)
+    }
+
+    fn iter_prefix<'a>(&'a self, column: &str, prefix: &[u8]) -> Result<KvIter<'a>, StorageError> {
+        let prefix = prefix.to_vec();
+        let entries = self
+            .db
+            .iterator_cf(self.column(column)?, IteratorMode::From(&prefix, Direction::Forward))
+            .map(|entry| entry.map(|(key, value)| (key.into_vec(), value.into_vec())).map_err(backend))
+            .take_while(move |entry| entry.as_ref().map_or(true, |(key, _)| key.starts_with(&prefix)));
+        Ok(Box::new(entries))
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut rocks_batch = rocksdb::WriteBatch::default();
+        for op in batch.into_ops() {
+            match op {
+                BatchOp::Put { column, key, value } => rocks_batch.put_cf(self.column(column)?, key, value),
+                BatchOp::Delete { column, key } => rocks_batch.delete_cf(self.column(column)?, key),
+            }
+        }
+        self.db.write(rocks_batch).map_err(backend)
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
+        for column in &self.columns {
+            self.db.flush_cf(self.column(column)?).map_err(backend)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reopen_keeps_data() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let store = RocksStore::open(dir.path(), columns::ALL).unwrap();
+            let mut batch = WriteBatch::new();
+            batch.put(columns::PEERS, b"peer-a".to_vec(), b"1".to_vec());
+            batch.put(columns::PEERS, b"peer-b".to_vec(), b"2".to_vec());
+            batch.put(columns::UTXOS, b"peer-c".to_vec(), b"3".to_vec());
+            store.write(batch).unwrap();
+            store.flush().unwrap();
+        }
+
+        let store = RocksStore::open(dir.path(), columns::ALL).unwrap();
+        let peers: Vec<_> = store
+            .iter_prefix(columns::PEERS, b"peer-")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(peers, vec![b"peer-a".to_vec(), b"peer-b".to_vec()]);
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksStore::open(dir.path(), &[columns::PEERS]).unwrap();
+        let mut batch = WriteBatch::new();
+        batch.put(columns::UTXOS, b"k".to_vec(), b"v".to_vec());
+        assert!(matches!(store.write(batch), Err(StorageError::UnknownColumn(_))));
+    }
+}
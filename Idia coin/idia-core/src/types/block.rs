@@ -90,6 +90,77 @@ impl Block {
         hashes[0]
     }
 
+    /// Sibling hashes from the transaction at `index` up to the merkle root,
+    /// using the same odd-level duplication as the root calculation
+    pub fn merkle_branch(&self, index: usize) -> Option<Vec<Hash>> {
+        if index >= self.transactions.len() {
+            return None;
+        }
+
+        let mut hashes: Vec<Hash> = self.transactions.iter()
+            .map(|tx| tx.hash())
+            .collect();
+        let mut position = index;
+        let mut branch = Vec::new();
+
+        while hashes.len() > 1 {
+            if hashes.len() % 2 != 0 {
+                hashes.push(hashes.last().unwrap().clone());
+            }
+            branch.push(hashes[position ^ 1]);
+
+            hashes = hashes.chunks(2)
+                .map(|chunk| {
+                    let mut hasher = Sha256::new();
+                    hasher.update(&chunk[0]);
+                    hasher.update(&chunk[1]);
+                    hasher.finalize().into()
+                })
+                .collect();
+            position /= 2;
+        }
+
+        Some(branch)
+    }
+
+    /// Check that `leaf` sits at `index` under `root` given its merkle branch
+    pub fn verify_merkle_branch(leaf: Hash, index: usize, branch: &[Hash], root: Hash) -> bool {
+        let mut hash = leaf;
+        let mut position = index;
+
+        for sibling in branch {
+            let mut hasher = Sha256::new();
+            if position % 2 == 0 {
+                hasher.update(&hash);
+                hasher.update(sibling);
+            } else {
+                hasher.update(sibling);
+                hasher.update(&hash);
+            }
+            hash = hasher.finalize().into();
+            position /= 2;
+        }
+
+        position == 0 && hash == root
+    }
+
+    /// Total amount destroyed by burn outputs in this block
+    pub fn burned_amount(&self) -> u64 {
+        self.transactions.iter().map(|tx| tx.burned_amount()).sum()
+    }
+
+    /// All governance records in this block, in transaction order
+    pub fn governance_records(&self) -> impl Iterator<Item = &GovernanceRecord> {
+        self.transactions.iter().flat_map(|tx| tx.governance.iter())
+    }
+
+    /// Serial numbers of all Spark notes spent in this block
+    pub fn spark_nullifiers(&self) -> impl Iterator<Item = &[u8; 32]> {
+        self.transactions
+            .iter()
+            .flat_map(|tx| tx.spark_redeems.iter().map(|r| &r.nullifier))
+    }
+
     /// Get the block hash
     pub fn hash(&self) -> Hash {
         hash_of(&self.header)
@@ -109,6 +180,11 @@ impl Block {
             }
         }
 
+        // A Spark note can only be redeemed once per block
+        if !unique_nullifiers(self.transactions.iter().flat_map(|tx| &tx.spark_redeems)) {
+            return Ok(false);
+        }
+
         // Verify proof of work
         // TODO: Implement proper PoW verification
         
@@ -153,4 +229,27 @@ mod tests {
             Block::calculate_merkle_root(&block.transactions)
         );
     }
+
+    #[test]
+    fn test_merkle_branch() {
+        let recipient = crate::crypto::StealthAddress::new();
+        let transactions: Vec<Transaction> = (0..5)
+            .map(|fee| {
+                let (output, _) = Output::new(100, &recipient).unwrap();
+                Transaction::new(vec![], vec![output], fee)
+            })
+            .collect();
+        let block = Block::new([0; 32], 1, 1, transactions);
+        let root = block.header.merkle_root;
+
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let branch = block.merkle_branch(index).unwrap();
+            assert!(Block::verify_merkle_branch(tx.hash(), index, &branch, root));
+            // The same branch must not place the transaction at its sibling's index
+            if index ^ 1 < block.transactions.len() {
+                assert!(!Block::verify_merkle_branch(tx.hash(), index ^ 1, &branch, root));
+            }
+        }
+        assert!(block.merkle_branch(5).is_none());
+    }
 }
\ No newline at end of file
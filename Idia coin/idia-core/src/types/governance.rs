@@ -0,0 +1,130 @@
+//! Governance records carried by transactions
+
+use super::*;
+
+/// Length of a compressed BLS12-381 signature share
+pub const SIGNATURE_SHARE_LEN: usize = 96;
+
+/// Length of a compressed BLS12-381 signature
+pub const SIGNATURE_LEN: usize = 96;
+
+/// Maximum size of a canonically encoded proposal
+pub const MAX_PROPOSAL_PAYLOAD: usize = 64 * 1024;
+
+/// A governance action published on-chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GovernanceRecord {
+    /// Submission of a new proposal
+    ProposalSubmission {
+        /// SHA-256 of the canonical proposal encoding
+        proposal_id: Hash,
+        /// Canonical proposal encoding
+        payload: Vec<u8>,
+    },
+    /// Publication of a committee member's signature share on a proposal
+    SignatureShare {
+        /// Proposal being signed
+        proposal_id: Hash,
+        /// Committee index of the signer
+        node_index: u32,
+        /// Compressed signature share over the proposal ID
+        share: Vec<u8>,
+    },
+    /// A committee member's share of a veto against an approved proposal
+    VetoShare {
+        /// Proposal being vetoed
+        proposal_id: Hash,
+        /// Committee index of the signer
+        node_index: u32,
+        /// Compressed signature share over the veto message
+        share: Vec<u8>,
+    },
+    /// Veto by the bootstrap guardian key
+    GuardianVeto {
+        /// Proposal being vetoed
+        proposal_id: Hash,
+        /// Compressed guardian signature over the veto message
+        signature: Vec<u8>,
+    },
+}
+
+impl GovernanceRecord {
+    /// Proposal this record refers to
+    pub fn proposal_id(&self) -> &Hash {
+        match self {
+            GovernanceRecord::ProposalSubmission { proposal_id, .. } => proposal_id,
+            GovernanceRecord::SignatureShare { proposal_id, .. } => proposal_id,
+            GovernanceRecord::VetoShare { proposal_id, .. } => proposal_id,
+            GovernanceRecord::GuardianVeto { proposal_id, .. } => proposal_id,
+        }
+    }
+
+    /// Context-free validity checks applied by consensus.
+    ///
+    /// Signature shares are verified cryptographically by the governance
+    /// module, which knows the committee's public key set.
+    pub fn verify(&self) -> bool {
+        match self {
+            GovernanceRecord::ProposalSubmission { proposal_id, payload } => {
+                !payload.is_empty()
+                    && payload.len() <= MAX_PROPOSAL_PAYLOAD
+                    && Sha256::digest(payload).as_slice() == proposal_id
+            }
+            GovernanceRecord::SignatureShare { share, .. } => share.len() == SIGNATURE_SHARE_LEN,
+            GovernanceRecord::VetoShare { share, .. } => share.len() == SIGNATURE_SHARE_LEN,
+            GovernanceRecord::GuardianVeto { signature, .. } => signature.len() == SIGNATURE_LEN,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submission_binds_to_payload() {
+        let payload = b"proposal".to_vec();
+        let proposal_id: Hash = Sha256::digest(&payload).into();
+
+        let record = GovernanceRecord::ProposalSubmission { proposal_id, payload };
+        assert!(record.verify());
+
+        let forged = GovernanceRecord::ProposalSubmission {
+            proposal_id,
+            payload: b"other proposal".to_vec(),
+        };
+        assert!(!forged.verify());
+    }
+
+    #[test]
+    fn test_signature_share_length() {
+        let record = GovernanceRecord::SignatureShare {
+            proposal_id: [0; 32],
+            node_index: 1,
+            share: vec![0; SIGNATURE_SHARE_LEN],
+        };
+        assert!(record.verify());
+
+        let truncated = GovernanceRecord::SignatureShare {
+            proposal_id: [0; 32],
+            node_index: 1,
+            share: vec![0; 10],
+        };
+        assert!(!truncated.verify());
+    }
+
+    #[test]
+    fn test_guardian_veto_length() {
+        let record = GovernanceRecord::GuardianVeto {
+            proposal_id: [0; 32],
+            signature: vec![0; SIGNATURE_LEN],
+        };
+        assert!(record.verify());
+
+        let truncated = GovernanceRecord::GuardianVeto {
+            proposal_id: [0; 32],
+            signature: vec![0; SIGNATURE_LEN - 1],
+        };
+        assert!(!truncated.verify());
+    }
+}
@@ -1,10 +1,14 @@
 //! Core types for the Idia blockchain
 
 mod block;
+mod governance;
+mod spark;
 mod transaction;
 mod utxo;
 
 pub use block::*;
+pub use governance::*;
+pub use spark::*;
 pub use transaction::*;
 pub use utxo::*;
 
@@ -21,4 +25,4 @@ pub fn hash_of<T: Serialize>(data: &T) -> Hash {
     let mut hasher = Sha256::new();
     hasher.update(serialized);
     hasher.finalize().into()
-}
\ No newline at end of file
+}
@@ -0,0 +1,123 @@
+//! Transfers between ring-CT outputs and the Spark (Lelantus) note pool
+
+use super::*;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use std::collections::HashSet;
+
+/// Maximum size of a Spark proof carried by a transaction
+pub const MAX_SPARK_PROOF: usize = 16 * 1024;
+
+/// Maximum size of a note encrypted to its recipient
+pub const MAX_ENCRYPTED_NOTE: usize = 512;
+
+/// Moves a public amount out of ring-CT inputs into a new Spark note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SparkMint {
+    /// Amount paid into the pool by the transaction's inputs
+    pub value: u64,
+    /// Commitment of the new note
+    pub commitment: [u8; 32],
+    /// Proof that the commitment opens to `value`
+    pub proof: Vec<u8>,
+    /// Note opening encrypted to the recipient's incoming view key
+    #[serde(default)]
+    pub encrypted_note: Vec<u8>,
+}
+
+/// Spends a Spark note into one of the transaction's ring-CT outputs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SparkRedeem {
+    /// Serial number of the spent note
+    pub nullifier: [u8; 32],
+    /// Anonymity set epoch the note is proven to belong to
+    #[serde(default)]
+    pub epoch: u64,
+    /// Value of the spent note, public so the pool's supply can be audited
+    pub value: u64,
+    /// Index of the output in this transaction receiving the value
+    pub output_index: u32,
+    /// Proof that the nullifier belongs to a note in the pool, bound to the output
+    pub proof: Vec<u8>,
+}
+
+impl SparkMint {
+    /// Context-free validity checks applied by consensus.
+    ///
+    /// The opening proof is verified by the privacy module, which knows the
+    /// Spark generators.
+    pub fn verify(&self) -> bool {
+        self.value > 0
+            && CompressedRistretto(self.commitment).decompress().is_some()
+            && !self.proof.is_empty()
+            && self.proof.len() <= MAX_SPARK_PROOF
+            && self.encrypted_note.len() <= MAX_ENCRYPTED_NOTE
+    }
+}
+
+impl SparkRedeem {
+    /// Context-free validity checks applied by consensus.
+    ///
+    /// Membership is verified by the privacy module against the note pool,
+    /// and the nullifier against the spent set.
+    pub fn verify(&self, outputs: usize) -> bool {
+        self.value > 0
+            && (self.output_index as usize) < outputs
+            && !self.proof.is_empty()
+            && self.proof.len() <= MAX_SPARK_PROOF
+    }
+}
+
+/// Check that no nullifier is redeemed twice within the given redeems
+pub fn unique_nullifiers<'a>(redeems: impl IntoIterator<Item = &'a SparkRedeem>) -> bool {
+    let mut seen = HashSet::new();
+    redeems.into_iter().all(|redeem| seen.insert(redeem.nullifier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+
+    fn redeem(nullifier: u8) -> SparkRedeem {
+        SparkRedeem {
+            nullifier: [nullifier; 32],
+            epoch: 0,
+            value: 10,
+            output_index: 0,
+            proof: vec![0; 32],
+        }
+    }
+
+    #[test]
+    fn test_mint_verification() {
+        let mint = SparkMint {
+            value: 10,
+            commitment: RISTRETTO_BASEPOINT_POINT.compress().to_bytes(),
+            proof: vec![0; 96],
+            encrypted_note: vec![0; 205],
+        };
+        assert!(mint.verify());
+
+        let mut empty = mint.clone();
+        empty.value = 0;
+        assert!(!empty.verify());
+
+        let mut oversized = mint.clone();
+        oversized.proof = vec![0; MAX_SPARK_PROOF + 1];
+        assert!(!oversized.verify());
+
+        let mut oversized = mint;
+        oversized.encrypted_note = vec![0; MAX_ENCRYPTED_NOTE + 1];
+        assert!(!oversized.verify());
+    }
+
+    #[test]
+    fn test_redeem_verification() {
+        assert!(redeem(1).verify(1));
+        // Must pay into an output of the same transaction
+        assert!(!redeem(1).verify(0));
+
+        assert!(unique_nullifiers(&[redeem(1), redeem(2)]));
+        assert!(!unique_nullifiers(&[redeem(1), redeem(1)]));
+    }
+}
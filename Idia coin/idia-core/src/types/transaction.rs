@@ -4,6 +4,9 @@ use super::*;
 use crate::crypto::{RingSignature, KeyImage};
 use std::collections::HashSet;
 
+/// Maximum size of a transaction's auxiliary data
+pub const MAX_TX_EXTRA: usize = 4096;
+
 /// A transaction input, which spends a previous output
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Input {
@@ -24,6 +27,21 @@ pub struct Transaction {
     pub inputs: Vec<Input>,
     /// Transaction outputs
     pub outputs: Vec<Output>,
+    /// Provably unspendable burn outputs
+    #[serde(default)]
+    pub burns: Vec<BurnOutput>,
+    /// Governance records published by this transaction
+    #[serde(default)]
+    pub governance: Vec<GovernanceRecord>,
+    /// Amounts moved from the inputs into new Spark notes
+    #[serde(default)]
+    pub spark_mints: Vec<SparkMint>,
+    /// Spark notes spent into this transaction's outputs
+    #[serde(default)]
+    pub spark_redeems: Vec<SparkRedeem>,
+    /// Auxiliary data such as encrypted travel-rule payloads
+    #[serde(default)]
+    pub extra: Vec<u8>,
     /// Transaction fee (committed to in input/output balance)
     pub fee: u64,
     /// Timestamp
@@ -41,6 +59,11 @@ impl Transaction {
             version: 1,
             inputs,
             outputs,
+            burns: Vec::new(),
+            governance: Vec::new(),
+            spark_mints: Vec::new(),
+            spark_redeems: Vec::new(),
+            extra: Vec::new(),
             fee,
             timestamp: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
@@ -49,11 +72,53 @@ impl Transaction {
         }
     }
 
+    /// Add burn outputs to the transaction
+    pub fn with_burns(mut self, burns: Vec<BurnOutput>) -> Self {
+        self.burns = burns;
+        self
+    }
+
+    /// Attach governance records to the transaction
+    pub fn with_governance(mut self, records: Vec<GovernanceRecord>) -> Self {
+        self.governance = records;
+        self
+    }
+
+    /// Move part of the inputs into the Spark pool
+    pub fn with_spark_mints(mut self, mints: Vec<SparkMint>) -> Self {
+        self.spark_mints = mints;
+        self
+    }
+
+    /// Fund outputs from spent Spark notes
+    pub fn with_spark_redeems(mut self, redeems: Vec<SparkRedeem>) -> Self {
+        self.spark_redeems = redeems;
+        self
+    }
+
+    /// Attach auxiliary data to the transaction
+    pub fn with_extra(mut self, extra: Vec<u8>) -> Self {
+        self.extra = extra;
+        self
+    }
+
     /// Get the transaction hash
     pub fn hash(&self) -> Hash {
         hash_of(self)
     }
 
+    /// Total amount destroyed by this transaction's burn outputs
+    pub fn burned_amount(&self) -> u64 {
+        self.burns.iter().map(|b| b.amount).sum()
+    }
+
+    /// Net amount moved into the Spark pool (negative when redeeming)
+    pub fn spark_pool_delta(&self) -> i128 {
+        let minted: i128 = self.spark_mints.iter().map(|m| m.value as i128).sum();
+        let redeemed: i128 = self.spark_redeems.iter().map(|r| r.value as i128).sum();
+        minted - redeemed
+    }
+
     /// Verify the entire transaction
     pub fn verify(&self) -> Result<bool, CryptoError> {
         // Verify each output's range proof
@@ -63,6 +128,32 @@ impl Transaction {
             }
         }
 
+        // Verify burn outputs are provably unspendable
+        for burn in &self.burns {
+            if !burn.verify() {
+                return Ok(false);
+            }
+        }
+
+        // Verify governance records are well-formed
+        for record in &self.governance {
+            if !record.verify() {
+                return Ok(false);
+            }
+        }
+
+        // Verify Spark migrations are well-formed
+        if !self.spark_mints.iter().all(SparkMint::verify)
+            || !self.spark_redeems.iter().all(|r| r.verify(self.outputs.len()))
+            || !unique_nullifiers(&self.spark_redeems)
+        {
+            return Ok(false);
+        }
+
+        if self.extra.len() > MAX_TX_EXTRA {
+            return Ok(false);
+        }
+
         // Verify ring signatures
         for input in &self.inputs {
             // TODO: Implement full ring signature verification
@@ -78,7 +169,7 @@ impl Transaction {
         }
 
         // TODO: Verify input/output balance using Pedersen commitments
-        // sum(input_commitments) = sum(output_commitments) + fee_commitment
+        // sum(input_commitments) + redeemed·G = sum(output_commitments) + (fee + minted)·G
 
         Ok(true)
     }
@@ -106,4 +197,13 @@ mod tests {
         assert!(tx.timestamp > 0);
         assert!(!tx.hash().iter().all(|&x| x == 0));
     }
+
+    #[test]
+    fn test_extra_size_limit() {
+        let tx = Transaction::new(vec![], vec![], 1).with_extra(vec![0; MAX_TX_EXTRA]);
+        assert!(tx.verify().unwrap());
+
+        let oversized = Transaction::new(vec![], vec![], 1).with_extra(vec![0; MAX_TX_EXTRA + 1]);
+        assert!(!oversized.verify().unwrap());
+    }
 }
\ No newline at end of file
@@ -17,6 +17,17 @@ pub struct Output {
     pub tx_pubkey: RistrettoPoint,
 }
 
+/// A provably unspendable output that destroys a publicly known amount
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BurnOutput {
+    /// Amount destroyed (public so that supply can be audited)
+    pub amount: u64,
+    /// Pedersen commitment to the amount with a zero blinding factor
+    pub commitment: PedersenCommitment,
+    /// Unspendable one-time key the output is locked to
+    pub stealth_pubkey: RistrettoPoint,
+}
+
 /// Reference to a previous output
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputReference {
@@ -54,6 +65,28 @@ impl Output {
     }
 }
 
+impl BurnOutput {
+    /// Create a burn output destroying the given amount
+    pub fn new(amount: u64) -> Self {
+        Self {
+            amount,
+            commitment: PedersenCommitment::with_blinding(amount, Scalar::zero()),
+            stealth_pubkey: Self::unspendable_key(),
+        }
+    }
+
+    /// Hash-to-point key with no known discrete log, so nobody can sign for it
+    pub fn unspendable_key() -> RistrettoPoint {
+        RistrettoPoint::hash_from_bytes::<Sha256>(b"Idia_burn")
+    }
+
+    /// Verify the output is locked to the unspendable key and commits to its stated amount
+    pub fn verify(&self) -> bool {
+        self.stealth_pubkey == Self::unspendable_key()
+            && self.commitment.verify(self.amount, Scalar::zero())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,4 +99,20 @@ mod tests {
         let (output, _r) = Output::new(amount, &recipient).unwrap();
         assert!(output.verify().unwrap());
     }
+
+    #[test]
+    fn test_burn_output_verification() {
+        let burn = BurnOutput::new(500);
+        assert!(burn.verify());
+
+        // Claiming a different amount than committed must fail
+        let mut inflated = burn.clone();
+        inflated.amount = 1000;
+        assert!(!inflated.verify());
+
+        // Redirecting the output to a spendable key must fail
+        let mut redirected = burn;
+        redirected.stealth_pubkey = StealthAddress::new().spend_key.spend_public;
+        assert!(!redirected.verify());
+    }
 }
\ No newline at end of file
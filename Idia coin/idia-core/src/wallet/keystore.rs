@@ -5,6 +5,7 @@ use crate::crypto::StealthAddress;
 use std::fs;
 use std::io::{Read, Write};
 use rand::rngs::OsRng;
+use rand::RngCore;
 use sha2::{Sha256, Digest};
 use aes_gcm::{
     aead::{Aead, KeyInit},
@@ -51,6 +52,28 @@ impl KeyStore {
         })
     }
 
+    /// Create a key store holding existing keys, for restoring a wallet from
+    /// backup. Fails if `data_dir` already has a wallet.
+    pub fn restore(data_dir: &PathBuf, stealth_address: StealthAddress) -> Result<Self, WalletError> {
+        fs::create_dir_all(data_dir)
+            .map_err(|e| WalletError::KeyStoreError(e.to_string()))?;
+
+        let key_file = data_dir.join("wallet.key");
+        if key_file.exists() {
+            return Err(WalletError::KeyStoreError("wallet already exists".to_string()));
+        }
+
+        let mut encryption_key = [0u8; 32];
+        OsRng.fill_bytes(&mut encryption_key);
+        Self::save_keys(&key_file, &stealth_address, &encryption_key)?;
+
+        Ok(Self {
+            data_dir: data_dir.to_owned(),
+            stealth_address,
+            encryption_key,
+        })
+    }
+
     /// Load keys from file
     fn load_keys(path: &PathBuf) -> Result<(StealthAddress, [u8; 32]), WalletError> {
         let mut file = fs::File::open(path)
@@ -113,18 +136,25 @@ impl KeyStore {
         Ok(self.stealth_address.clone())
     }
 
-    /// Encrypt data for storage
+    /// Encrypt data for storage, as a random nonce followed by the ciphertext
     pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, WalletError> {
         let cipher = Aes256Gcm::new(self.encryption_key.as_slice().into());
-        let nonce = Nonce::from_slice(&Sha256::digest(data)[..12]);
-        
-        cipher
-            .encrypt(nonce, data)
-            .map_err(|e| WalletError::KeyStoreError(e.to_string()))
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), data)
+            .map_err(|e| WalletError::KeyStoreError(e.to_string()))?;
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(ciphertext);
+        Ok(encrypted)
     }
 
     /// Decrypt stored data
     pub fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, WalletError> {
+        if encrypted.len() < 12 {
+            return Err(WalletError::KeyStoreError("ciphertext too short".to_string()));
+        }
         let cipher = Aes256Gcm::new(self.encryption_key.as_slice().into());
         let nonce = Nonce::from_slice(&encrypted[..12]);
         
@@ -160,4 +190,27 @@ mod tests {
         
         assert_eq!(data.as_slice(), decrypted.as_slice());
     }
+
+    #[test]
+    fn test_keystore_encryption_is_randomized() {
+        let dir = tempdir().unwrap();
+        let keystore = KeyStore::new(&dir.path().to_path_buf()).unwrap();
+
+        // Equal plaintexts must not be recognizable on disk
+        assert_ne!(keystore.encrypt(b"secret").unwrap(), keystore.encrypt(b"secret").unwrap());
+    }
+
+    #[test]
+    fn test_keystore_restore() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_path_buf();
+        let address = StealthAddress::new();
+        KeyStore::restore(&path, address.clone()).unwrap();
+
+        // Reopening loads the restored keys rather than generating new ones
+        let reopened = KeyStore::new(&path).unwrap().get_stealth_address().unwrap();
+        assert_eq!(reopened.to_secret_bytes(), address.to_secret_bytes());
+
+        assert!(KeyStore::restore(&path, StealthAddress::new()).is_err());
+    }
 }
\ No newline at end of file
@@ -2,14 +2,22 @@
 
 mod keystore;
 mod scanner;
+mod spark;
 mod transaction_builder;
+mod transfers;
 
 pub use keystore::*;
 pub use scanner::*;
+pub use spark::*;
 pub use transaction_builder::*;
+pub use transfers::*;
 
-use crate::crypto::{StealthAddress, KeyImage};
-use crate::types::{Transaction, Output, Input, OutputReference};
+use crate::crypto::{StealthAddress, KeyImage, SchnorrSignature};
+use crate::metrics;
+use crate::storage::{columns, decode, encode, get_value, ColumnStore, MemoryStore, StorageError, WriteBatch};
+use crate::types::{Block, Hash, Transaction, Output, Input, OutputReference};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -28,8 +36,25 @@ pub enum WalletError {
     ScannerError(String),
     #[error("Transaction building error: {0}")]
     TransactionBuildError(String),
+    #[error("Spark pool not configured")]
+    SparkUnavailable,
+    #[error("Spark error: {0}")]
+    SparkError(String),
+    #[error("Storage error: {0}")]
+    StorageError(String),
 }
 
+impl From<StorageError> for WalletError {
+    fn from(e: StorageError) -> Self {
+        Self::StorageError(e.to_string())
+    }
+}
+
+/// Key of the subaddress count in the wallet meta column
+const ADDRESS_COUNT_KEY: &[u8] = b"address_count";
+/// Key of the last processed height in the wallet meta column
+const SYNCED_HEIGHT_KEY: &[u8] = b"synced_height";
+
 /// Wallet state
 #[derive(Debug)]
 pub struct WalletState {
@@ -39,10 +64,21 @@ pub struct WalletState {
     spent_key_images: HashMap<KeyImage, OutputReference>,
     /// Total balance
     balance: u64,
+    /// Unspent Spark notes owned by this wallet, by nullifier
+    pool_notes: HashMap<[u8; 32], PoolNote>,
+    /// Total value of unspent Spark notes
+    pool_balance: u64,
+    /// Addresses scanned for, by subaddress index
+    addresses: Vec<StealthAddress>,
+    /// Transfers in and out, oldest first
+    transfers: Vec<Transfer>,
+    /// Height of the last block processed
+    synced_height: Option<u64>,
 }
 
 /// Wallet configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WalletConfig {
     /// Wallet data directory
     pub data_dir: PathBuf,
@@ -52,8 +88,19 @@ pub struct WalletConfig {
     pub ring_size: usize,
 }
 
+impl Default for WalletConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("./wallet"),
+            network: NetworkType::Mainnet,
+            ring_size: 11,
+        }
+    }
+}
+
 /// Network type
-#[derive(Debug, Clone, Copy, EqualsPartial)]
+#[derive(Debug, Clone, Copy, EqualsPartial, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NetworkType {
     Mainnet,
     Testnet,
@@ -71,12 +118,28 @@ pub struct Wallet {
     scanner: OutputScanner,
     /// Transaction builder
     tx_builder: TransactionBuilder,
+    /// Spark keys and note pool, if the wallet uses Spark
+    spark: Option<Box<dyn SparkBackend>>,
+    /// Where outputs, transfers and subaddresses are persisted
+    store: Arc<dyn ColumnStore>,
 }
 
 impl Wallet {
     /// Create a new wallet
     pub async fn new(config: WalletConfig) -> Result<Self, WalletError> {
         let keystore = KeyStore::new(&config.data_dir)?;
+        Self::with_keystore(config, keystore)
+    }
+
+    /// Restore a wallet from backed-up keys into an empty data directory.
+    ///
+    /// Outputs are found again by processing blocks from genesis.
+    pub async fn restore(config: WalletConfig, address: StealthAddress) -> Result<Self, WalletError> {
+        let keystore = KeyStore::restore(&config.data_dir, address)?;
+        Self::with_keystore(config, keystore)
+    }
+
+    fn with_keystore(config: WalletConfig, keystore: KeyStore) -> Result<Self, WalletError> {
         let scanner = OutputScanner::new();
         let tx_builder = TransactionBuilder::new(config.ring_size);
 
@@ -84,6 +147,11 @@ impl Wallet {
             unspent_outputs: HashMap::new(),
             spent_key_images: HashMap::new(),
             balance: 0,
+            pool_notes: HashMap::new(),
+            pool_balance: 0,
+            addresses: vec![keystore.get_stealth_address()?],
+            transfers: Vec::new(),
+            synced_height: None,
         }));
 
         Ok(Self {
@@ -92,19 +160,136 @@ impl Wallet {
             keystore,
             scanner,
             tx_builder,
+            spark: None,
+            store: Arc::new(MemoryStore::new()),
         })
     }
 
+    /// Persist wallet state in `store`, first loading whatever an earlier
+    /// run left there.
+    ///
+    /// Outputs, spent key images, transfers, the subaddress count and the
+    /// last processed height are kept; Spark notes are left to the Spark
+    /// backend.
+    pub async fn with_store(mut self, store: Arc<dyn ColumnStore>) -> Result<Self, WalletError> {
+        {
+            let mut state = self.state.write().await;
+            let primary = self.keystore.get_stealth_address()?;
+            let address_count: u32 = get_value(store.as_ref(), columns::WALLET_META, ADDRESS_COUNT_KEY)?.unwrap_or(1);
+            for index in state.addresses.len() as u32..address_count {
+                state.addresses.push(primary.derive_subaddress(index));
+            }
+
+            for entry in store.iter_prefix(columns::WALLET_OUTPUTS, &[])? {
+                let (key, value) = entry?;
+                let output: Output = decode(&value)?;
+                state.balance += output.amount;
+                state.unspent_outputs.insert(decode(&key)?, output);
+            }
+            for entry in store.iter_prefix(columns::WALLET_SPENT, &[])? {
+                let (key, value) = entry?;
+                let image = key
+                    .try_into()
+                    .map_err(|_| WalletError::StorageError("malformed key image".to_string()))?;
+                state.spent_key_images.insert(KeyImage(CompressedRistretto(image)), decode(&value)?);
+            }
+            for entry in store.iter_prefix(columns::WALLET_TRANSFERS, &[])? {
+                state.transfers.push(decode(&entry?.1)?);
+            }
+            state.synced_height = get_value(store.as_ref(), columns::WALLET_META, SYNCED_HEIGHT_KEY)?;
+            metrics::WALLET_BALANCE.set(state.balance as i64);
+            metrics::WALLET_UNSPENT_OUTPUTS.set(state.unspent_outputs.len() as i64);
+        }
+        self.store = store;
+        Ok(self)
+    }
+
+    /// Enable Spark pool operations using the given backend
+    pub fn with_spark(mut self, backend: impl SparkBackend + 'static) -> Self {
+        self.spark = Some(Box::new(backend));
+        self
+    }
+
     /// Get the wallet's stealth address
     pub fn get_address(&self) -> Result<StealthAddress, WalletError> {
         self.keystore.get_stealth_address()
     }
 
+    /// Derive the next subaddress and start scanning for it
+    pub async fn create_address(&self) -> Result<(u32, StealthAddress), WalletError> {
+        let mut state = self.state.write().await;
+        let index = state.addresses.len() as u32;
+        let address = self.keystore.get_stealth_address()?.derive_subaddress(index);
+        let mut batch = WriteBatch::new();
+        batch.put_value(columns::WALLET_META, ADDRESS_COUNT_KEY, &(index + 1))?;
+        self.store.write(batch)?;
+        state.addresses.push(address.clone());
+        Ok((index, address))
+    }
+
+    /// Get a subaddress already created
+    pub async fn get_address_at(&self, index: u32) -> Option<StealthAddress> {
+        self.state.read().await.addresses.get(index as usize).cloned()
+    }
+
+    /// Number of addresses scanned for, including the primary address
+    pub async fn address_count(&self) -> u32 {
+        self.state.read().await.addresses.len() as u32
+    }
+
+    /// Height of the last block processed, if any
+    pub async fn synced_height(&self) -> Option<u64> {
+        self.state.read().await.synced_height
+    }
+
+    /// Transfers in and out of the wallet, oldest first
+    pub async fn get_transfers(&self) -> Vec<Transfer> {
+        self.state.read().await.transfers.clone()
+    }
+
+    /// Key images of the unspent outputs, so a view-only copy of the wallet
+    /// can tell which of them have been spent
+    pub async fn export_key_images(&self) -> Vec<(OutputReference, KeyImage)> {
+        self.state
+            .read()
+            .await
+            .unspent_outputs
+            .iter()
+            .map(|(outref, output)| (outref.clone(), Self::key_image(output)))
+            .collect()
+    }
+
+    /// Sign a message with the primary address's spend key
+    pub fn sign_message(&self, message: &[u8]) -> Result<SchnorrSignature, WalletError> {
+        let address = self.keystore.get_stealth_address()?;
+        Ok(SchnorrSignature::sign(&address.spend_key.spend_private, message))
+    }
+
+    /// Check a message signature against an address's spend key
+    pub fn verify_message(address: &StealthAddress, message: &[u8], signature: &SchnorrSignature) -> bool {
+        signature.verify(&address.spend_key.spend_public, message)
+    }
+
+    /// Key image an input spending `output` reveals, as built by the
+    /// transaction builder
+    fn key_image(output: &Output) -> KeyImage {
+        KeyImage(output.stealth_pubkey.compress())
+    }
+
     /// Get the current balance
     pub async fn get_balance(&self) -> u64 {
         self.state.read().await.balance
     }
 
+    /// Get the balance split between ring-CT outputs and Spark notes
+    pub async fn get_balances(&self) -> Balance {
+        let state = self.state.read().await;
+        Balance {
+            transparent: state.balance,
+            pool: state.pool_balance,
+        }
+    }
+
     /// Create a new transaction
     pub async fn create_transaction(
         &self,
@@ -131,36 +316,146 @@ impl Wallet {
             .map_err(|e| WalletError::TransactionBuildError(e.to_string()))
     }
 
+    /// Create a transaction moving `amount` of ring-CT funds into a new
+    /// Spark note owned by this wallet
+    pub async fn mint_to_pool(&mut self, amount: u64, fee: u64) -> Result<Transaction, WalletError> {
+        let state = self.state.read().await;
+        if amount == 0 {
+            return Err(WalletError::InvalidAmount);
+        }
+        if amount + fee > state.balance {
+            return Err(WalletError::InsufficientFunds);
+        }
+
+        let spark = self.spark.as_mut().ok_or(WalletError::SparkUnavailable)?;
+        let mint = spark.mint(amount)?;
+        self.tx_builder
+            .build_spark_mint(&self.keystore, &state.unspent_outputs, mint, fee)
+    }
+
+    /// Create a transaction paying `amount` out of the wallet's Spark notes
+    /// to a ring-CT output, minting any change back into a new note
+    pub async fn spend_from_pool(
+        &mut self,
+        recipient: &StealthAddress,
+        amount: u64,
+        fee: u64,
+    ) -> Result<Transaction, WalletError> {
+        let state = self.state.read().await;
+        if amount == 0 {
+            return Err(WalletError::InvalidAmount);
+        }
+        if amount + fee > state.pool_balance {
+            return Err(WalletError::InsufficientFunds);
+        }
+
+        let spark = self.spark.as_mut().ok_or(WalletError::SparkUnavailable)?;
+        self.tx_builder
+            .build_pool_spend(&state.pool_notes, recipient, amount, fee, spark.as_mut())
+    }
+
     /// Process a new block
+    #[tracing::instrument(name = "wallet_process_block", skip_all, fields(height = block.header.height))]
     pub async fn process_block(&mut self, block: &Block) -> Result<(), WalletError> {
         let mut state = self.state.write().await;
-        
+        // Blocks already reflected in persisted state would count twice
+        if state.synced_height.is_some_and(|synced| block.header.height <= synced) {
+            return Ok(());
+        }
+        // Everything the block changes is persisted together
+        let mut batch = WriteBatch::new();
+
         // Scan for our outputs
         for tx in &block.transactions {
-            if let Some(new_outputs) = self.scanner.scan_transaction(
-                tx,
-                &self.keystore.get_stealth_address()?,
-            )? {
-                // Add new outputs
-                for (outref, output) in new_outputs {
-                    state.balance += output.amount;
-                    state.unspent_outputs.insert(outref, output);
+            let tx_hash = tx.hash();
+            let mut received = Vec::new();
+            for (index, address) in state.addresses.iter().enumerate() {
+                if let Some(new_outputs) = self.scanner.scan_transaction(tx, address)? {
+                    received.extend(new_outputs.into_iter().map(|owned| (index as u32, owned)));
                 }
             }
 
-            // Mark spent outputs
+            // Mark spent outputs, recognised by the key images our inputs reveal
+            let mut spent_amount = 0u64;
             for input in &tx.inputs {
-                if let Some(outref) = state.spent_key_images.insert(
-                    input.key_image.clone(),
-                    input.ring[0].clone(), // Assuming first ring member is real
-                ) {
-                    if let Some(output) = state.unspent_outputs.remove(&outref) {
-                        state.balance -= output.amount;
-                    }
+                let spent = state
+                    .unspent_outputs
+                    .iter()
+                    .find(|(_, output)| Self::key_image(output).0 == input.key_image.0)
+                    .map(|(outref, _)| outref.clone());
+                if let Some(outref) = spent {
+                    let output = state.unspent_outputs.remove(&outref).unwrap();
+                    state.balance -= output.amount;
+                    spent_amount += output.amount;
+                    batch.delete(columns::WALLET_OUTPUTS, encode(&outref)?);
+                    batch.put_value(columns::WALLET_SPENT, input.key_image.0.to_bytes(), &outref)?;
+                    state.spent_key_images.insert(input.key_image.clone(), outref);
+                }
+            }
+
+            // Add new outputs
+            let mut received_amount = 0u64;
+            for (address_index, (outref, output)) in received {
+                let amount = output.amount;
+                received_amount += amount;
+                state.balance += amount;
+                batch.put_value(columns::WALLET_OUTPUTS, encode(&outref)?, &output)?;
+                state.unspent_outputs.insert(outref, output);
+                // What comes back in a transaction we paid from is change
+                if spent_amount == 0 {
+                    let transfer = Transfer {
+                        tx_hash,
+                        height: block.header.height,
+                        direction: TransferDirection::In,
+                        amount,
+                        fee: 0,
+                        address_index: Some(address_index),
+                    };
+                    Self::record_transfer(&mut state, &mut batch, transfer)?;
+                }
+            }
+            if spent_amount > 0 {
+                let transfer = Transfer {
+                    tx_hash,
+                    height: block.header.height,
+                    direction: TransferDirection::Out,
+                    amount: spent_amount.saturating_sub(received_amount + tx.fee),
+                    fee: tx.fee,
+                    address_index: None,
+                };
+                Self::record_transfer(&mut state, &mut batch, transfer)?;
+            }
+        }
+
+        // Pick up notes minted to us before dropping spent ones, so a note
+        // minted and spent in the same block is never counted
+        if let Some(spark) = self.spark.as_mut() {
+            for note in spark.scan_block(block) {
+                state.pool_balance += note.value;
+                state.pool_notes.insert(note.nullifier, note);
+            }
+            for nullifier in block.spark_nullifiers() {
+                if let Some(note) = state.pool_notes.remove(nullifier) {
+                    state.pool_balance -= note.value;
                 }
             }
         }
 
+        batch.put_value(columns::WALLET_META, SYNCED_HEIGHT_KEY, &block.header.height)?;
+        self.store.write(batch)?;
+        state.synced_height = Some(block.header.height);
+
+        metrics::WALLET_BALANCE.set(state.balance as i64);
+        metrics::WALLET_POOL_BALANCE.set(state.pool_balance as i64);
+        metrics::WALLET_UNSPENT_OUTPUTS.set(state.unspent_outputs.len() as i64);
+        metrics::WALLET_SYNCED_HEIGHT.set(block.header.height as i64);
+        Ok(())
+    }
+
+    /// Append a transfer, staging it under the next sequence number
+    fn record_transfer(state: &mut WalletState, batch: &mut WriteBatch, transfer: Transfer) -> Result<(), WalletError> {
+        batch.put_value(columns::WALLET_TRANSFERS, (state.transfers.len() as u64).to_be_bytes(), &transfer)?;
+        state.transfers.push(transfer);
         Ok(())
     }
 }
\ No newline at end of file
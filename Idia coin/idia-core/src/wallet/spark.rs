@@ -0,0 +1,38 @@
+//! Spark note pool support for the wallet
+
+use super::*;
+use crate::types::{SparkMint, SparkRedeem};
+
+/// A Spark note owned by the wallet, as far as the wallet needs to know it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolNote {
+    /// Serial number revealed when the note is spent
+    pub nullifier: [u8; 32],
+    /// Note value
+    pub value: u64,
+    /// Height of the block that minted the note
+    pub height: u64,
+}
+
+/// Spark operations the wallet delegates to the privacy module, which holds
+/// the Spark keys and the note pool
+pub trait SparkBackend: Send + Sync {
+    /// Create a mint record for a new note of `value` paid to this wallet
+    fn mint(&mut self, value: u64) -> Result<SparkMint, WalletError>;
+
+    /// Create a redeem record spending the note with `nullifier` into
+    /// `output`, which the transaction carries at `output_index`
+    fn redeem(&mut self, nullifier: &[u8; 32], output: &Output, output_index: u32) -> Result<SparkRedeem, WalletError>;
+
+    /// Trial-decrypt a block's mints, returning the notes paid to this wallet
+    fn scan_block(&mut self, block: &Block) -> Vec<PoolNote>;
+}
+
+/// Wallet balance split by where the funds are held
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Balance {
+    /// Unspent ring-CT outputs
+    pub transparent: u64,
+    /// Unspent Spark notes
+    pub pool: u64,
+}
@@ -2,6 +2,7 @@
 
 use super::*;
 use crate::crypto::{KeyImage, RingSignature, StealthAddress};
+use crate::types::{SparkMint, SparkRedeem};
 use rand::{seq::IteratorRandom, thread_rng};
 
 /// Transaction builder for constructing new transactions
@@ -25,8 +26,102 @@ impl TransactionBuilder {
         amount: u64,
         fee: u64,
     ) -> Result<Transaction, WalletError> {
-        let total_needed = amount + fee;
+        let mut outputs = Vec::new();
         
+        // Payment output
+        let (payment_output, _) = Output::new(amount, recipient)?;
+        outputs.push(payment_output);
+
+        let inputs = self.fund(keystore, available_outputs, amount + fee, &mut outputs)?;
+        Ok(Transaction::new(inputs, outputs, fee))
+    }
+
+    /// Build a transaction moving ring-CT funds into a Spark note
+    pub fn build_spark_mint(
+        &self,
+        keystore: &KeyStore,
+        available_outputs: &HashMap<OutputReference, Output>,
+        mint: SparkMint,
+        fee: u64,
+    ) -> Result<Transaction, WalletError> {
+        let mut outputs = Vec::new();
+        let inputs = self.fund(keystore, available_outputs, mint.value + fee, &mut outputs)?;
+        Ok(Transaction::new(inputs, outputs, fee).with_spark_mints(vec![mint]))
+    }
+
+    /// Build a transaction redeeming a Spark note of `value` to a ring-CT output.
+    ///
+    /// The redeem proof is bound to the output it pays, so `prove` is handed
+    /// the output once it exists.
+    pub fn build_spark_redeem(
+        &self,
+        recipient: &StealthAddress,
+        value: u64,
+        fee: u64,
+        prove: impl FnOnce(&Output) -> Result<SparkRedeem, WalletError>,
+    ) -> Result<Transaction, WalletError> {
+        let amount = value.checked_sub(fee).ok_or(WalletError::InsufficientFunds)?;
+        let (output, _) = Output::new(amount, recipient)?;
+        let redeem = prove(&output)?;
+        Ok(Transaction::new(Vec::new(), vec![output], fee).with_spark_redeems(vec![redeem]))
+    }
+
+    /// Build a transaction paying `amount` out of Spark notes to a ring-CT output.
+    ///
+    /// Notes are redeemed whole, so whatever the selected notes hold beyond
+    /// `amount + fee` is minted back to the wallet as a change note.
+    pub fn build_pool_spend(
+        &self,
+        available_notes: &HashMap<[u8; 32], PoolNote>,
+        recipient: &StealthAddress,
+        amount: u64,
+        fee: u64,
+        spark: &mut dyn SparkBackend,
+    ) -> Result<Transaction, WalletError> {
+        let total_needed = amount.checked_add(fee).ok_or(WalletError::InvalidAmount)?;
+
+        // Largest notes first, so as few membership proofs as possible are needed
+        let mut notes: Vec<&PoolNote> = available_notes.values().collect();
+        notes.sort_by(|a, b| b.value.cmp(&a.value));
+        let mut selected_amount = 0u64;
+        let mut selected_notes = Vec::new();
+        for note in notes {
+            if selected_amount >= total_needed {
+                break;
+            }
+            selected_notes.push(note);
+            selected_amount += note.value;
+        }
+
+        if selected_amount < total_needed {
+            return Err(WalletError::InsufficientFunds);
+        }
+
+        let (output, _) = Output::new(amount, recipient)?;
+        let redeems = selected_notes
+            .iter()
+            .map(|note| spark.redeem(&note.nullifier, &output, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Change note if needed
+        let mut mints = Vec::new();
+        if selected_amount > total_needed {
+            mints.push(spark.mint(selected_amount - total_needed)?);
+        }
+
+        Ok(Transaction::new(Vec::new(), vec![output], fee)
+            .with_spark_redeems(redeems)
+            .with_spark_mints(mints))
+    }
+
+    /// Select and sign inputs covering `total_needed`, adding a change output
+    fn fund(
+        &self,
+        keystore: &KeyStore,
+        available_outputs: &HashMap<OutputReference, Output>,
+        total_needed: u64,
+        outputs: &mut Vec<Output>,
+    ) -> Result<Vec<Input>, WalletError> {
         // Select inputs
         let mut selected_amount = 0u64;
         let mut selected_inputs = Vec::new();
@@ -44,13 +139,6 @@ impl TransactionBuilder {
             return Err(WalletError::InsufficientFunds);
         }
 
-        // Create outputs
-        let mut outputs = Vec::new();
-        
-        // Payment output
-        let (payment_output, _) = Output::new(amount, recipient)?;
-        outputs.push(payment_output);
-
         // Change output if needed
         if selected_amount > total_needed {
             let change_amount = selected_amount - total_needed;
@@ -85,7 +173,7 @@ impl TransactionBuilder {
             });
         }
 
-        Ok(Transaction::new(inputs, outputs, fee))
+        Ok(inputs)
     }
 
     /// Select decoy outputs for ring signatures
@@ -147,4 +235,81 @@ mod tests {
         assert_eq!(tx.outputs.len(), 2); // payment + change
         assert_eq!(tx.fee, 1);
     }
+
+    #[test]
+    fn test_spark_migration_building() {
+        let dir = tempdir().unwrap();
+        let keystore = KeyStore::new(&dir.path().to_path_buf()).unwrap();
+
+        let mut available_outputs = HashMap::new();
+        let (output, _) = Output::new(1000, &keystore.get_stealth_address().unwrap()).unwrap();
+        available_outputs.insert(OutputReference { tx_hash: [0; 32], output_index: 0 }, output);
+
+        let builder = TransactionBuilder::new(11);
+        let mint = SparkMint { value: 600, commitment: [0; 32], proof: vec![0; 96], encrypted_note: Vec::new() };
+        let tx = builder.build_spark_mint(&keystore, &available_outputs, mint, 1).unwrap();
+        assert_eq!(tx.inputs.len(), 1);
+        assert_eq!(tx.outputs.len(), 1); // change only
+        assert_eq!(tx.spark_mints.len(), 1);
+
+        let recipient = StealthAddress::new();
+        let tx = builder
+            .build_spark_redeem(&recipient, 600, 1, |output| {
+                Ok(SparkRedeem {
+                    nullifier: [1; 32],
+                    epoch: 0,
+                    value: 600,
+                    output_index: 0,
+                    proof: output.stealth_pubkey.compress().to_bytes().to_vec(),
+                })
+            })
+            .unwrap();
+        assert!(tx.inputs.is_empty());
+        assert_eq!(tx.outputs.len(), 1);
+        assert_eq!(tx.spark_redeems[0].value, 600);
+
+        // The fee has to come out of the note
+        assert!(builder.build_spark_redeem(&recipient, 1, 2, |_| unreachable!()).is_err());
+    }
+
+    struct TestSpark;
+
+    impl SparkBackend for TestSpark {
+        fn mint(&mut self, value: u64) -> Result<SparkMint, WalletError> {
+            Ok(SparkMint { value, commitment: [0; 32], proof: vec![0; 96], encrypted_note: Vec::new() })
+        }
+
+        fn redeem(&mut self, nullifier: &[u8; 32], _: &Output, output_index: u32) -> Result<SparkRedeem, WalletError> {
+            Ok(SparkRedeem { nullifier: *nullifier, epoch: 0, value: nullifier[0] as u64 * 100, output_index, proof: vec![0; 32] })
+        }
+
+        fn scan_block(&mut self, _: &Block) -> Vec<PoolNote> {
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn test_pool_spend_building() {
+        let mut notes = HashMap::new();
+        for value in [1u8, 2, 5] {
+            let note = PoolNote { nullifier: [value; 32], value: value as u64 * 100, height: 1 };
+            notes.insert(note.nullifier, note);
+        }
+
+        let builder = TransactionBuilder::new(11);
+        let recipient = StealthAddress::new();
+        let tx = builder.build_pool_spend(&notes, &recipient, 550, 10, &mut TestSpark).unwrap();
+        // 500 + 200 redeemed, 140 minted back as change
+        assert_eq!(tx.spark_redeems.len(), 2);
+        assert_eq!(tx.outputs.len(), 1);
+        assert_eq!(tx.spark_mints[0].value, 140);
+        assert_eq!(tx.spark_pool_delta(), -700 + 140);
+
+        // An exact match needs no change note
+        let tx = builder.build_pool_spend(&notes, &recipient, 490, 10, &mut TestSpark).unwrap();
+        assert_eq!(tx.spark_redeems.len(), 1);
+        assert!(tx.spark_mints.is_empty());
+
+        assert!(builder.build_pool_spend(&notes, &recipient, 800, 1, &mut TestSpark).is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,30 @@
+//! Wallet transfer history
+
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// Which way a transfer moved funds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferDirection {
+    /// Paid to one of the wallet's addresses
+    In,
+    /// Paid out of the wallet's outputs
+    Out,
+}
+
+/// A transaction as it affected the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transfer {
+    /// Transaction hash
+    pub tx_hash: Hash,
+    /// Height of the block holding the transaction
+    pub height: u64,
+    /// Direction of the transfer
+    pub direction: TransferDirection,
+    /// Amount received, or sent not counting change and fee
+    pub amount: u64,
+    /// Fee paid, for outgoing transfers
+    pub fee: u64,
+    /// Subaddress receiving an incoming transfer
+    pub address_index: Option<u32>,
+}
@@ -0,0 +1,208 @@
+//! `idia-cli`: drives the wallet RPC daemon and queries the node from the
+//! command line
+
+use clap::{Parser, Subcommand};
+use idia_node::config::{self, RpcSection, WalletRpcSection};
+use idia_node::node_client::NodeClient;
+use serde_json::{json, Value};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "idia-cli", version, about = "Command-line client for the Idia node and wallet daemons")]
+struct Cli {
+    /// TOML configuration file the daemons use; URLs and tokens not given
+    /// below are taken from its `[wallet_rpc]` and `[rpc]` sections
+    #[arg(long, env = "IDIA_CONFIG")]
+    config: Option<PathBuf>,
+    /// Wallet RPC daemon URL
+    #[arg(long, env = "IDIA_WALLET_URL")]
+    wallet_url: Option<String>,
+    /// Wallet RPC bearer token
+    #[arg(long, env = "IDIA_WALLET_RPC_TOKEN", hide_env_values = true)]
+    wallet_token: Option<String>,
+    /// Node JSON-RPC URL
+    #[arg(long, env = "IDIA_NODE_URL")]
+    node_url: Option<String>,
+    /// Node RPC bearer token
+    #[arg(long, env = "IDIA_RPC_TOKEN", hide_env_values = true)]
+    node_token: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a wallet and print its keys for backup
+    Create { name: String },
+    /// Restore a wallet from backed-up keys
+    Restore {
+        name: String,
+        /// Keys printed by `create` or `keys`, hex
+        #[arg(long)]
+        keys: String,
+        /// Height to rescan from; must be at least a pruned node's lowest block
+        #[arg(long, default_value_t = 0)]
+        restore_height: u64,
+    },
+    /// Open an existing wallet
+    Open { name: String },
+    /// Close the open wallet
+    Close,
+    /// Show the open wallet's balance
+    Balance,
+    /// List the open wallet's addresses, or create one with --new
+    Address {
+        /// Label for a new subaddress
+        #[arg(long)]
+        new: Option<String>,
+    },
+    /// Print the open wallet's secret keys
+    Keys,
+    /// Send funds
+    Transfer {
+        address: String,
+        amount: u64,
+        #[arg(long, default_value_t = 1000)]
+        fee: u64,
+    },
+    /// Send the whole balance less the fee
+    Sweep {
+        address: String,
+        #[arg(long, default_value_t = 1000)]
+        fee: u64,
+    },
+    /// Show incoming and outgoing transfers
+    Transfers,
+    /// Query the node
+    #[command(subcommand)]
+    Node(NodeCommand),
+}
+
+#[derive(Subcommand)]
+enum NodeCommand {
+    /// Chain height, tip, difficulty and mempool size
+    Info,
+    /// Block by height, or by hash with --hash
+    Block {
+        height: Option<u64>,
+        #[arg(long, conflicts_with = "height")]
+        hash: Option<String>,
+    },
+    /// Transaction by hash
+    Transaction { hash: String },
+    /// Fee rate expected to confirm within the target
+    FeeEstimate {
+        #[arg(long, default_value_t = 1)]
+        target_blocks: u64,
+    },
+    /// Show the node's log filter, or replace it with --set
+    LogFilter {
+        /// Filter directives, e.g. `info,idia_node::rpc=debug`
+        #[arg(long)]
+        set: Option<String>,
+    },
+}
+
+/// Posts to the wallet RPC daemon's endpoints
+struct WalletClient {
+    url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl WalletClient {
+    async fn post(&self, endpoint: &str, body: Value) -> Result<Value, String> {
+        let mut request = self.client.post(format!("{}/{}", self.url.trim_end_matches('/'), endpoint)).json(&body);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        let body: Value = response.json().await.map_err(|e| format!("{} ({})", e, status))?;
+        if !status.is_success() {
+            return Err(body["error"].as_str().map_or_else(|| status.to_string(), str::to_string));
+        }
+        Ok(body)
+    }
+}
+
+// Where to connect to a server listening on `bind`
+fn local_url(bind: SocketAddr) -> String {
+    let ip = if bind.ip().is_unspecified() { IpAddr::V4(Ipv4Addr::LOCALHOST) } else { bind.ip() };
+    format!("http://{}", SocketAddr::new(ip, bind.port()))
+}
+
+async fn run(cli: Cli) -> Result<Value, String> {
+    let config = config::load(cli.config.as_deref()).map_err(|e| e.to_string())?;
+    let wallet_rpc = WalletRpcSection::from_config(&config).map_err(|e| e.to_string())?;
+    let rpc = RpcSection::from_config(&config).map_err(|e| e.to_string())?;
+
+    let wallet = WalletClient {
+        url: cli.wallet_url.unwrap_or_else(|| local_url(wallet_rpc.bind)),
+        token: cli.wallet_token.or(wallet_rpc.token),
+        client: reqwest::Client::new(),
+    };
+
+    match cli.command {
+        Command::Create { name } => {
+            wallet.post("create_wallet", json!({ "name": name })).await?;
+            wallet.post("get_keys", json!({})).await
+        }
+        Command::Restore { name, keys, restore_height } => {
+            wallet
+                .post("restore_wallet", json!({ "name": name, "keys": keys, "restore_height": restore_height }))
+                .await
+        }
+        Command::Open { name } => wallet.post("open_wallet", json!({ "name": name })).await,
+        Command::Close => wallet.post("close_wallet", json!({})).await,
+        Command::Balance => wallet.post("get_balance", json!({})).await,
+        Command::Address { new: Some(label) } => wallet.post("create_address", json!({ "label": label })).await,
+        Command::Address { new: None } => wallet.post("get_addresses", json!({})).await,
+        Command::Keys => wallet.post("get_keys", json!({})).await,
+        Command::Transfer { address, amount, fee } => {
+            wallet
+                .post("transfer", json!({ "address": address, "amount": amount, "fee": fee }))
+                .await
+        }
+        Command::Sweep { address, fee } => wallet.post("sweep", json!({ "address": address, "fee": fee })).await,
+        Command::Transfers => wallet.post("get_transfers", json!({})).await,
+        Command::Node(command) => {
+            let node_url = cli.node_url.unwrap_or_else(|| format!("{}/json_rpc", local_url(rpc.bind)));
+            let node = NodeClient::new(node_url, cli.node_token.or(rpc.token));
+            let (method, params) = match command {
+                NodeCommand::Info => ("get_info", Value::Null),
+                NodeCommand::Block { hash: Some(hash), .. } => ("get_block", json!({ "hash": hash })),
+                NodeCommand::Block { height, hash: None } => {
+                    let height = match height {
+                        Some(height) => height,
+                        None => node.get_info().await.map_err(|e| e.to_string())?.height,
+                    };
+                    ("get_block_by_height", json!({ "height": height }))
+                }
+                NodeCommand::Transaction { hash } => ("get_transaction", json!({ "hash": hash })),
+                NodeCommand::FeeEstimate { target_blocks } => {
+                    ("get_fee_estimate", json!({ "target_blocks": target_blocks }))
+                }
+                NodeCommand::LogFilter { set: Some(filter) } => ("set_log_filter", json!({ "filter": filter })),
+                NodeCommand::LogFilter { set: None } => ("get_log_filter", Value::Null),
+            };
+            node.call(method, params).await.map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(result) => {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Wallet RPC daemon: serves `WalletService` over HTTP against a running node
+
+use clap::Parser;
+use idia_core::shutdown::{self, Shutdown};
+use idia_node::config::{self, WalletRpcSection};
+use idia_node::node_client::NodeClient;
+use idia_node::telemetry::{self, LoggingSection};
+use idia_node::wallet_rpc::{self, WalletRpcState};
+use idia_node::wallet_service::{WalletService, WalletServiceError};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(name = "idia-wallet-rpc", version, about = "Idia wallet RPC daemon")]
+struct Args {
+    /// TOML configuration file
+    #[arg(long, env = "IDIA_CONFIG")]
+    config: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let config = config::load(args.config.as_deref())?;
+    let section = WalletRpcSection::from_config(&config)?;
+    telemetry::init(&LoggingSection::from_config(&config)?.filter)?;
+
+    let node = NodeClient::new(section.node_url.clone(), section.node_token.clone());
+    let service = Arc::new(WalletService::new(section.service_config(&config), node));
+
+    let shutdown = Shutdown::new();
+    let syncing = service.clone();
+    let sync_interval = section.sync_interval;
+    let token = shutdown.token();
+    let sync = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(sync_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = token.wait() => break,
+            }
+            if let Err(e) = syncing.sync().await {
+                tracing::warn!(error = %e, "Wallet sync failed");
+            }
+        }
+    });
+
+    let state = WalletRpcState::new(service.clone(), section.server_config());
+    let server = wallet_rpc::serve(state, shutdown.token());
+    tokio::pin!(server);
+    let served = tokio::select! {
+        served = &mut server => served,
+        _ = shutdown::os_signal() => {
+            shutdown.trigger();
+            // In-flight requests get the grace period to finish
+            tokio::time::timeout(section.shutdown_timeout, &mut server).await.unwrap_or(Ok(()))
+        }
+    };
+    shutdown.trigger();
+
+    // Let a running sync finish, then persist the open wallet
+    let flush = async {
+        let _ = sync.await;
+        match service.close_wallet().await {
+            Ok(()) | Err(WalletServiceError::NoWalletOpen) => Ok(()),
+            Err(e) => Err(e),
+        }
+    };
+    match tokio::time::timeout(section.shutdown_timeout, flush).await {
+        Ok(Ok(())) => tracing::info!("Wallet RPC stopped"),
+        Ok(Err(e)) => tracing::error!(error = %e, "Failed to save wallet on shutdown"),
+        Err(_) => tracing::warn!(timeout = ?section.shutdown_timeout, "Gave up waiting to save wallet"),
+    }
+    served?;
+    Ok(())
+}
@@ -0,0 +1,488 @@
+//! Chain state for the node, kept in the shared store
+
+use crate::utxo::UtxoSet;
+use idia_core::metrics;
+use idia_core::storage::{columns, get_value, ColumnStore, MemoryStore, StorageError, WriteBatch};
+use idia_core::{Block, BlockHeader, CryptoError, Hash, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Timestamp of the genesis block, fixed so every node derives the same hash
+pub const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+
+/// Fewest recent blocks a pruned node keeps whole, so the explorer indexer
+/// and wallets scanning the tip still find the blocks they need
+pub const MIN_PRUNE_WINDOW: u64 = 288;
+
+/// Most heights pruned in one write when catching up on a backlog
+const PRUNE_BATCH_BLOCKS: u64 = 1000;
+
+/// Key of the lowest unpruned height in the chain meta column
+const PRUNED_BELOW_KEY: &[u8] = b"pruned_below";
+
+/// Layout of the chain columns. Version 1 kept whole outputs in the UTXO
+/// set and no headers apart from blocks; it is upgraded on open.
+const SCHEMA_VERSION: u32 = 2;
+
+/// Key of the schema version in the chain meta column
+const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+
+/// Chain error types
+#[derive(Debug, thiserror::Error)]
+pub enum ChainError {
+    #[error("Block does not extend the tip at height {0}")]
+    NotTip(u64),
+    #[error("Block is invalid")]
+    InvalidBlock,
+    #[error("Block does not meet difficulty {0}")]
+    InsufficientWork(u32),
+    #[error("Key image already spent")]
+    DoubleSpend,
+    #[error("Chain database has schema version {0}, newer than this node supports")]
+    UnsupportedSchema(u32),
+    #[error("Crypto error: {0}")]
+    Crypto(#[from] CryptoError),
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// The genesis block
+pub fn genesis_block() -> Block {
+    let mut block = Block::new([0; 32], 0, 0, Vec::new());
+    block.header.timestamp = GENESIS_TIMESTAMP;
+    block
+}
+
+/// Whether `hash` has at least `difficulty` leading zero bits
+pub fn meets_difficulty(hash: &Hash, difficulty: u32) -> bool {
+    let mut zeros = 0;
+    for byte in hash {
+        zeros += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    zeros >= difficulty
+}
+
+/// What is kept of every best chain block, pruned or not
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderEntry {
+    /// The block header
+    pub header: BlockHeader,
+    /// Number of outputs the block created, for decoy selection
+    pub output_count: u64,
+}
+
+impl HeaderEntry {
+    fn new(block: &Block) -> Self {
+        Self {
+            header: block.header.clone(),
+            output_count: block.transactions.iter().map(|tx| tx.outputs.len() as u64).sum(),
+        }
+    }
+}
+
+/// Key images spent by a transaction's inputs
+pub fn key_images(tx: &Transaction) -> impl Iterator<Item = [u8; 32]> + '_ {
+    tx.inputs.iter().map(|input| input.key_image.0.to_bytes())
+}
+
+/// The best chain and its UTXO set.
+///
+/// Blocks, transaction positions and the UTXO set live in the store and are
+/// written in one batch per block; only the best chain's hashes are kept in
+/// memory. Only blocks extending the tip are accepted; competing branches
+/// are dropped rather than tracked for reorganisation.
+///
+/// With pruning on, blocks that fall out of the retention window lose their
+/// bodies, transaction positions and range proofs. Headers, key images and
+/// the UTXO set are kept for every block.
+pub struct ChainState {
+    /// Blocks, transaction positions and the UTXO set
+    store: Arc<dyn ColumnStore>,
+    /// Outputs created and key images spent
+    utxos: UtxoSet,
+    /// Best chain block hashes by height
+    best_chain: Vec<Hash>,
+    /// Difficulty required of new blocks
+    difficulty: u32,
+    /// Number of recent blocks kept whole, if pruning
+    prune_window: Option<u64>,
+    /// Heights below this have been pruned
+    pruned_below: u64,
+}
+
+impl ChainState {
+    /// Create a chain kept in memory, holding only the genesis block
+    pub fn new(difficulty: u32) -> Self {
+        Self::open(Arc::new(MemoryStore::new()), difficulty).expect("in-memory store does not fail")
+    }
+
+    /// Open the chain kept in `store`, starting it with the genesis block
+    /// if the store is empty
+    pub fn open(store: Arc<dyn ColumnStore>, difficulty: u32) -> Result<Self, ChainError> {
+        let mut best_chain = Vec::new();
+        for entry in store.iter_prefix(columns::CHAIN_HEIGHTS, &[])? {
+            let (_, hash) = entry?;
+            let hash = hash
+                .try_into()
+                .map_err(|_| StorageError::Backend("malformed chain height index".to_string()))?;
+            best_chain.push(hash);
+        }
+
+        let pruned_below = get_value(store.as_ref(), columns::CHAIN_META, PRUNED_BELOW_KEY)?.unwrap_or(0);
+        let version: Option<u32> = get_value(store.as_ref(), columns::CHAIN_META, SCHEMA_VERSION_KEY)?;
+        if let Some(version) = version.filter(|version| *version > SCHEMA_VERSION) {
+            return Err(ChainError::UnsupportedSchema(version));
+        }
+
+        let mut chain = Self {
+            utxos: UtxoSet::new(store.clone()),
+            store,
+            best_chain,
+            difficulty,
+            prune_window: None,
+            pruned_below,
+        };
+        if chain.best_chain.is_empty() {
+            chain.store_block(genesis_block())?;
+        } else {
+            if version.is_none() {
+                chain.upgrade_from_v1()?;
+            }
+            tracing::info!(height = chain.height(), tip = %hex::encode(chain.tip()), "Loaded chain");
+        }
+        if version != Some(SCHEMA_VERSION) {
+            let mut batch = WriteBatch::new();
+            batch.put_value(columns::CHAIN_META, SCHEMA_VERSION_KEY.to_vec(), &SCHEMA_VERSION)?;
+            chain.store.write(batch)?;
+        }
+        metrics::CHAIN_HEIGHT.set(chain.height() as i64);
+        Ok(chain)
+    }
+
+    /// Keep only the latest `window` blocks whole, pruning any older ones
+    /// now and the rest as they fall out of the window
+    pub fn with_pruning(mut self, window: u64) -> Result<Self, ChainError> {
+        self.prune_window = Some(window);
+        let target = self.prune_target(self.height());
+        if self.pruned_below < target {
+            tracing::info!(from = self.pruned_below, to = target, "Pruning old blocks");
+        }
+        while self.pruned_below < target {
+            let to = target.min(self.pruned_below + PRUNE_BATCH_BLOCKS);
+            let mut batch = WriteBatch::new();
+            self.stage_pruning(&mut batch, to)?;
+            self.store.write(batch)?;
+            self.pruned_below = to;
+        }
+        Ok(self)
+    }
+
+    /// Rebuild the headers and UTXO set entries of a version 1 database from
+    /// its blocks. Version 1 never pruned, so every block is still there;
+    /// the upgrade is redone from the start if it is interrupted.
+    fn upgrade_from_v1(&self) -> Result<(), ChainError> {
+        tracing::info!(height = self.height(), "Upgrading chain database to schema version {}", SCHEMA_VERSION);
+        let mut batch = WriteBatch::new();
+        for (height, hash) in self.best_chain.iter().enumerate() {
+            let block: Block = get_value(self.store.as_ref(), columns::CHAIN_BLOCKS, hash)?.ok_or_else(|| {
+                StorageError::Backend(format!("block at height {} is missing", height))
+            })?;
+            batch.put_value(columns::CHAIN_HEADERS, hash.to_vec(), &HeaderEntry::new(&block))?;
+            self.utxos.apply_block(&mut batch, &block)?;
+            if (height as u64 + 1) % PRUNE_BATCH_BLOCKS == 0 {
+                self.store.write(std::mem::take(&mut batch))?;
+            }
+        }
+        self.store.write(batch)?;
+        Ok(())
+    }
+
+    /// Lowest height to keep whole with `tip` at the top of the chain
+    fn prune_target(&self, tip: u64) -> u64 {
+        match self.prune_window {
+            Some(window) => (tip + 1).saturating_sub(window).max(self.pruned_below),
+            None => self.pruned_below,
+        }
+    }
+
+    /// Stage pruning every height from `pruned_below` up to `to`
+    fn stage_pruning(&self, batch: &mut WriteBatch, to: u64) -> Result<(), ChainError> {
+        if to <= self.pruned_below {
+            return Ok(());
+        }
+        for height in self.pruned_below..to {
+            let hash = self.best_chain[height as usize];
+            if let Some(block) = get_value::<Block>(self.store.as_ref(), columns::CHAIN_BLOCKS, &hash)? {
+                for tx in &block.transactions {
+                    batch.delete(columns::CHAIN_TRANSACTIONS, tx.hash().to_vec());
+                }
+                self.utxos.prune_block(batch, &block);
+            }
+            batch.delete(columns::CHAIN_BLOCKS, hash.to_vec());
+        }
+        batch.put_value(columns::CHAIN_META, PRUNED_BELOW_KEY.to_vec(), &to)?;
+        Ok(())
+    }
+
+    /// Read a value, logging rather than returning storage failures
+    fn load<T: serde::de::DeserializeOwned>(&self, column: &str, key: &[u8]) -> Option<T> {
+        get_value(self.store.as_ref(), column, key).unwrap_or_else(|e| {
+            tracing::error!(column, error = %e, "Failed to read chain state");
+            None
+        })
+    }
+
+    /// Height of the tip
+    pub fn height(&self) -> u64 {
+        self.best_chain.len() as u64 - 1
+    }
+
+    /// Hash of the tip
+    pub fn tip(&self) -> Hash {
+        *self.best_chain.last().unwrap()
+    }
+
+    /// Difficulty required of the next block
+    pub fn next_difficulty(&self) -> u32 {
+        self.difficulty
+    }
+
+    /// Heights below this have been pruned, keeping only their headers
+    pub fn pruned_below(&self) -> u64 {
+        self.pruned_below
+    }
+
+    /// Get a block on the best chain by hash, unless it has been pruned
+    pub fn block(&self, hash: &Hash) -> Option<Block> {
+        self.load(columns::CHAIN_BLOCKS, hash)
+    }
+
+    /// Get the header of a block on the best chain by hash
+    pub fn header(&self, hash: &Hash) -> Option<HeaderEntry> {
+        self.load(columns::CHAIN_HEADERS, hash)
+    }
+
+    /// Get the header of the best chain block at `height`
+    pub fn header_at(&self, height: u64) -> Option<HeaderEntry> {
+        self.hash_at(height).and_then(|hash| self.header(&hash))
+    }
+
+    /// Hash of the best chain block at `height`
+    pub fn hash_at(&self, height: u64) -> Option<Hash> {
+        self.best_chain.get(height as usize).copied()
+    }
+
+    /// Get the best chain block at `height`, unless it has been pruned
+    pub fn block_at(&self, height: u64) -> Option<Block> {
+        self.hash_at(height).and_then(|hash| self.block(&hash))
+    }
+
+    /// Get a best chain transaction with the block holding it
+    pub fn transaction(&self, hash: &Hash) -> Option<(Transaction, Block)> {
+        let (block_hash, index): (Hash, u64) = self.load(columns::CHAIN_TRANSACTIONS, hash)?;
+        let block = self.block(&block_hash)?;
+        let tx = block.transactions.get(index as usize)?.clone();
+        Some((tx, block))
+    }
+
+    /// The UTXO set of the best chain
+    pub fn utxos(&self) -> &UtxoSet {
+        &self.utxos
+    }
+
+    /// Whether a key image is spent on the best chain. A key image that
+    /// can't be looked up counts as spent, so nothing is accepted twice.
+    pub fn is_spent(&self, key_image: &[u8; 32]) -> bool {
+        match self.utxos.spent_at(key_image) {
+            Ok(height) => height.is_some(),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to read key image");
+                true
+            }
+        }
+    }
+
+    /// Validate a transaction against the chain, without its block context
+    pub fn check_transaction(&self, tx: &Transaction) -> Result<(), ChainError> {
+        if !tx.verify()? {
+            return Err(ChainError::InvalidBlock);
+        }
+        if key_images(tx).any(|image| self.is_spent(&image)) {
+            return Err(ChainError::DoubleSpend);
+        }
+        Ok(())
+    }
+
+    /// Validate and append a block extending the tip
+    #[tracing::instrument(name = "validate_block", skip_all, fields(height = block.header.height))]
+    pub fn connect_block(&mut self, block: Block) -> Result<Hash, ChainError> {
+        let timer = metrics::BLOCK_VALIDATION_SECONDS.start_timer();
+        let result = self.try_connect_block(block);
+        timer.observe_duration();
+        match &result {
+            Ok(_) => {
+                metrics::BLOCKS_CONNECTED.inc();
+                metrics::CHAIN_HEIGHT.set(self.height() as i64);
+            }
+            Err(_) => metrics::BLOCKS_REJECTED.inc(),
+        }
+        result
+    }
+
+    fn try_connect_block(&mut self, block: Block) -> Result<Hash, ChainError> {
+        if block.header.prev_hash != self.tip() || block.header.height != self.height() + 1 {
+            return Err(ChainError::NotTip(self.height()));
+        }
+        let hash = block.hash();
+        if block.header.difficulty < self.difficulty || !meets_difficulty(&hash, block.header.difficulty) {
+            return Err(ChainError::InsufficientWork(self.difficulty));
+        }
+        if !block.verify()? {
+            return Err(ChainError::InvalidBlock);
+        }
+
+        let mut spent = HashSet::new();
+        for tx in &block.transactions {
+            for image in key_images(tx) {
+                if self.is_spent(&image) || !spent.insert(image) {
+                    return Err(ChainError::DoubleSpend);
+                }
+            }
+        }
+
+        self.store_block(block)
+    }
+
+    /// Write a validated block, its transaction positions and its UTXO set
+    /// changes in one batch with any pruning it causes, then make it the tip
+    fn store_block(&mut self, block: Block) -> Result<Hash, ChainError> {
+        let hash = block.hash();
+        let header = HeaderEntry::new(&block);
+        let mut batch = WriteBatch::new();
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let position: (Hash, u64) = (hash, index as u64);
+            batch.put_value(columns::CHAIN_TRANSACTIONS, tx.hash().to_vec(), &position)?;
+        }
+        self.utxos.apply_block(&mut batch, &block)?;
+        batch.put(columns::CHAIN_HEIGHTS, block.header.height.to_be_bytes(), hash.to_vec());
+        batch.put_value(columns::CHAIN_HEADERS, hash.to_vec(), &header)?;
+        batch.put_value(columns::CHAIN_BLOCKS, hash.to_vec(), &block)?;
+        let pruned_below = self.prune_target(block.header.height);
+        self.stage_pruning(&mut batch, pruned_below)?;
+        self.store.write(batch)?;
+
+        self.best_chain.push(hash);
+        self.pruned_below = pruned_below;
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utxo::output_key;
+    use idia_core::{Output, OutputReference, StealthAddress};
+
+    #[test]
+    fn test_connect_block() {
+        let mut chain = ChainState::new(0);
+        assert_eq!(chain.height(), 0);
+        assert_eq!(chain.tip(), genesis_block().hash());
+
+        let block = Block::new(chain.tip(), 1, 0, Vec::new());
+        let hash = chain.connect_block(block.clone()).unwrap();
+        assert_eq!(chain.height(), 1);
+        assert_eq!(chain.block_at(1).unwrap().hash(), hash);
+
+        // The same block no longer extends the tip
+        assert!(matches!(chain.connect_block(block), Err(ChainError::NotTip(1))));
+    }
+
+    #[test]
+    fn test_reopen_from_store() {
+        let store: Arc<dyn ColumnStore> = Arc::new(MemoryStore::new());
+        let mut chain = ChainState::open(store.clone(), 0).unwrap();
+        let block = Block::new(chain.tip(), 1, 0, Vec::new());
+        let hash = chain.connect_block(block).unwrap();
+
+        let chain = ChainState::open(store, 0).unwrap();
+        assert_eq!(chain.height(), 1);
+        assert_eq!(chain.tip(), hash);
+        assert_eq!(chain.block_at(0).unwrap().hash(), genesis_block().hash());
+    }
+
+    #[test]
+    fn test_pruning() {
+        let store: Arc<dyn ColumnStore> = Arc::new(MemoryStore::new());
+        let mut chain = ChainState::open(store.clone(), 0).unwrap();
+        for height in 1..=5 {
+            chain.connect_block(Block::new(chain.tip(), height, 0, Vec::new())).unwrap();
+        }
+
+        // Turning pruning on drops the backlog at once
+        let mut chain = chain.with_pruning(3).unwrap();
+        assert_eq!(chain.pruned_below(), 3);
+        assert!(chain.block_at(2).is_none());
+        assert_eq!(chain.header_at(2).unwrap().header.height, 2);
+        assert!(chain.block_at(3).is_some());
+
+        // and each new block pushes the window along
+        chain.connect_block(Block::new(chain.tip(), 6, 0, Vec::new())).unwrap();
+        assert_eq!(chain.pruned_below(), 4);
+        assert!(chain.block_at(3).is_none());
+
+        let chain = ChainState::open(store, 0).unwrap();
+        assert_eq!(chain.pruned_below(), 4);
+        assert_eq!(chain.height(), 6);
+    }
+
+    #[test]
+    fn test_upgrade_from_v1() {
+        // Lay the chain out as version 1 did: blocks and whole outputs only
+        let store: Arc<dyn ColumnStore> = Arc::new(MemoryStore::new());
+        let genesis = genesis_block();
+        let (output, _) = Output::new(100, &StealthAddress::new()).unwrap();
+        let block = Block::new(genesis.hash(), 1, 0, vec![Transaction::new(vec![], vec![output.clone()], 1)]);
+        let outref = OutputReference {
+            tx_hash: block.transactions[0].hash(),
+            output_index: 0,
+        };
+        let mut batch = WriteBatch::new();
+        for block in [&genesis, &block] {
+            batch.put(columns::CHAIN_HEIGHTS, block.header.height.to_be_bytes(), block.hash().to_vec());
+            batch.put_value(columns::CHAIN_BLOCKS, block.hash().to_vec(), block).unwrap();
+        }
+        batch.put_value(columns::UTXOS, output_key(&outref), &output).unwrap();
+        store.write(batch).unwrap();
+
+        let chain = ChainState::open(store.clone(), 0).unwrap();
+        assert_eq!(chain.height(), 1);
+        assert_eq!(chain.header_at(1).unwrap().output_count, 1);
+        let entry = chain.utxos().output(&outref).unwrap().unwrap();
+        assert_eq!(entry.height, 1);
+        assert!(chain.utxos().range_proof(&outref).unwrap().is_some());
+
+        // A database from a newer node is refused
+        let mut batch = WriteBatch::new();
+        batch.put_value(columns::CHAIN_META, SCHEMA_VERSION_KEY.to_vec(), &(SCHEMA_VERSION + 1)).unwrap();
+        store.write(batch).unwrap();
+        assert!(matches!(ChainState::open(store, 0), Err(ChainError::UnsupportedSchema(3))));
+    }
+
+    #[test]
+    fn test_difficulty() {
+        assert!(meets_difficulty(&[0; 32], 256));
+        let mut hash = [0u8; 32];
+        hash[1] = 0b0001_0000;
+        assert!(meets_difficulty(&hash, 11));
+        assert!(!meets_difficulty(&hash, 12));
+
+        let mut chain = ChainState::new(250);
+        let block = Block::new(chain.tip(), 1, 250, Vec::new());
+        assert!(matches!(chain.connect_block(block), Err(ChainError::InsufficientWork(250))));
+    }
+}
@@ -0,0 +1,282 @@
+//! Node sections of the layered configuration
+//!
+//! The core `[network]`, `[dandelion]` and `[wallet]` sections are read by
+//! `idia_core::config`; this module adds `[node]`, `[rpc]` and `[wallet_rpc]`.
+
+use crate::chain::MIN_PRUNE_WINDOW;
+use crate::node::NodeConfig;
+use crate::rpc::RpcConfig;
+use crate::supervisor::DEFAULT_SHUTDOWN_TIMEOUT;
+use crate::wallet_rpc::WalletRpcConfig;
+use crate::wallet_service::WalletServiceConfig;
+use idia_core::config::{duration_secs, ConfigError, ConfigLoader, IdiaConfig};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Prefix of environment variables overriding config files
+pub const ENV_PREFIX: &str = "IDIA";
+
+/// Load the configuration the binaries share: defaults, then `path` if
+/// given, then `IDIA_SECTION__KEY` environment variables
+pub fn load(path: Option<&Path>) -> Result<IdiaConfig, ConfigError> {
+    let mut loader = ConfigLoader::new();
+    if let Some(path) = path {
+        loader = loader.file(path);
+    }
+    loader.env(ENV_PREFIX).load()
+}
+
+/// `[node]`: what the daemon runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeSection {
+    /// Whether to mine blocks
+    pub mine: bool,
+    /// Difficulty required of blocks, in leading zero bits
+    pub difficulty: u32,
+    /// Maximum number of pooled transactions
+    pub mempool_size: usize,
+    /// Maximum number of transactions per mined block
+    pub max_block_transactions: usize,
+    /// Keep the `[wallet]` wallet in sync with the chain
+    pub sync_wallet: bool,
+    /// How long each task may take to stop on shutdown, in seconds
+    #[serde(with = "duration_secs")]
+    pub shutdown_timeout: Duration,
+    /// Keep only this many recent blocks whole, pruning older bodies and
+    /// range proofs; unset keeps everything
+    pub prune_window: Option<u64>,
+}
+
+impl Default for NodeSection {
+    fn default() -> Self {
+        Self {
+            mine: false,
+            difficulty: 16,
+            mempool_size: 5000,
+            max_block_transactions: 1000,
+            sync_wallet: false,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            prune_window: None,
+        }
+    }
+}
+
+/// `[rpc]`: the node's JSON-RPC server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcSection {
+    /// Whether to serve RPC at all
+    pub enabled: bool,
+    /// Address to listen on
+    pub bind: SocketAddr,
+    /// Bearer token required of every request, if set
+    pub token: Option<String>,
+    /// Maximum number of calls in one batch
+    pub max_batch: usize,
+}
+
+impl Default for RpcSection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            bind: SocketAddr::from(([127, 0, 0, 1], 8081)),
+            token: None,
+            max_batch: 100,
+        }
+    }
+}
+
+/// `[wallet_rpc]`: the wallet daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WalletRpcSection {
+    /// Address to listen on
+    pub bind: SocketAddr,
+    /// Bearer token required of every request, if set
+    pub token: Option<String>,
+    /// Directory holding one subdirectory per wallet
+    pub wallets_dir: PathBuf,
+    /// Node JSON-RPC endpoint to sync from and submit to
+    pub node_url: String,
+    /// Bearer token for the node, if it requires one
+    pub node_token: Option<String>,
+    /// How often the open wallet catches up with the node, in seconds
+    #[serde(with = "duration_secs")]
+    pub sync_interval: Duration,
+    /// How long to wait for in-flight requests and the wallet flush on
+    /// shutdown, in seconds
+    #[serde(with = "duration_secs")]
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for WalletRpcSection {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([127, 0, 0, 1], 8082)),
+            token: None,
+            wallets_dir: PathBuf::from("./wallets"),
+            node_url: "http://127.0.0.1:8081/json_rpc".to_string(),
+            node_token: None,
+            sync_interval: Duration::from_secs(10),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+        }
+    }
+}
+
+impl NodeSection {
+    /// The `[node]` section, or defaults
+    pub fn from_config(config: &IdiaConfig) -> Result<Self, ConfigError> {
+        let section: Self = config.section("node")?.unwrap_or_default();
+        if section.mempool_size == 0 {
+            return Err(ConfigError::invalid("node.mempool_size", "must be positive"));
+        }
+        if section.max_block_transactions == 0 {
+            return Err(ConfigError::invalid("node.max_block_transactions", "must be positive"));
+        }
+        if section.prune_window.is_some_and(|window| window < MIN_PRUNE_WINDOW) {
+            return Err(ConfigError::invalid(
+                "node.prune_window",
+                format!("must be at least {}", MIN_PRUNE_WINDOW),
+            ));
+        }
+        Ok(section)
+    }
+}
+
+impl RpcSection {
+    /// The `[rpc]` section, or defaults
+    pub fn from_config(config: &IdiaConfig) -> Result<Self, ConfigError> {
+        let section: Self = config.section("rpc")?.unwrap_or_default();
+        if section.max_batch == 0 {
+            return Err(ConfigError::invalid("rpc.max_batch", "must be positive"));
+        }
+        if section.token.as_deref() == Some("") {
+            return Err(ConfigError::invalid("rpc.token", "must not be empty; omit it to disable auth"));
+        }
+        Ok(section)
+    }
+}
+
+impl WalletRpcSection {
+    /// The `[wallet_rpc]` section, or defaults
+    pub fn from_config(config: &IdiaConfig) -> Result<Self, ConfigError> {
+        let section: Self = config.section("wallet_rpc")?.unwrap_or_default();
+        if section.sync_interval.is_zero() {
+            return Err(ConfigError::invalid("wallet_rpc.sync_interval", "must be positive"));
+        }
+        if section.token.as_deref() == Some("") {
+            return Err(ConfigError::invalid("wallet_rpc.token", "must not be empty; omit it to disable auth"));
+        }
+        Ok(section)
+    }
+
+    /// Server settings for the wallet daemon
+    pub fn server_config(&self) -> WalletRpcConfig {
+        WalletRpcConfig {
+            bind: self.bind,
+            token: self.token.clone(),
+        }
+    }
+
+    /// Service settings, taking network and ring size from `[wallet]`
+    pub fn service_config(&self, config: &IdiaConfig) -> WalletServiceConfig {
+        WalletServiceConfig {
+            wallets_dir: self.wallets_dir.clone(),
+            network: config.wallet.network,
+            ring_size: config.wallet.ring_size,
+        }
+    }
+}
+
+impl NodeConfig {
+    /// Node settings from the layered configuration
+    pub fn from_config(config: &IdiaConfig) -> Result<Self, ConfigError> {
+        let node = NodeSection::from_config(config)?;
+        let rpc = RpcSection::from_config(config)?;
+        Ok(Self {
+            network: config.network.clone(),
+            wallet: node.sync_wallet.then(|| config.wallet.clone()),
+            rpc: rpc.enabled.then(|| RpcConfig {
+                bind: rpc.bind,
+                token: rpc.token,
+                max_batch: rpc.max_batch,
+            }),
+            metrics: config.metrics.enabled.then(|| config.metrics.clone()),
+            storage: config.storage.clone(),
+            prune_window: node.prune_window,
+            mine: node.mine,
+            difficulty: node.difficulty,
+            mempool_size: node.mempool_size,
+            max_block_transactions: node.max_block_transactions,
+            shutdown_timeout: node.shutdown_timeout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use idia_core::config::ConfigLoader;
+
+    fn load(contents: &str) -> IdiaConfig {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("idia.toml");
+        std::fs::write(&path, contents).unwrap();
+        ConfigLoader::new().file(path).load().unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = NodeConfig::from_config(&IdiaConfig::default()).unwrap();
+        assert!(!config.mine);
+        assert!(config.wallet.is_none());
+        assert!(config.metrics.is_none());
+        assert_eq!(config.rpc.unwrap().bind, RpcSection::default().bind);
+    }
+
+    #[test]
+    fn test_sections() {
+        let config = load(
+            r#"
+            [node]
+            mine = true
+            sync_wallet = true
+            shutdown_timeout = 3
+            prune_window = 1000
+
+            [rpc]
+            enabled = false
+
+            [wallet]
+            ring_size = 16
+
+            [wallet_rpc]
+            bind = "0.0.0.0:9000"
+            sync_interval = 30
+            "#,
+        );
+        let node = NodeConfig::from_config(&config).unwrap();
+        assert!(node.mine);
+        assert_eq!(node.shutdown_timeout, Duration::from_secs(3));
+        assert_eq!(node.prune_window, Some(1000));
+        assert!(node.rpc.is_none());
+        assert_eq!(node.wallet.unwrap().ring_size, 16);
+
+        let wallet_rpc = WalletRpcSection::from_config(&config).unwrap();
+        assert_eq!(wallet_rpc.server_config().bind.port(), 9000);
+        assert_eq!(wallet_rpc.sync_interval, Duration::from_secs(30));
+        assert_eq!(wallet_rpc.service_config(&config).ring_size, 16);
+    }
+
+    #[test]
+    fn test_invalid_sections() {
+        assert!(NodeConfig::from_config(&load("[node]\nmempool_size = 0\n")).is_err());
+        assert!(NodeConfig::from_config(&load("[node]\nprune_window = 10\n")).is_err());
+        assert!(NodeConfig::from_config(&load("[rpc]\nmax_batch = 0\n")).is_err());
+        // Typos are reported rather than silently ignored
+        assert!(NodeConfig::from_config(&load("[node]\nmin = true\n")).is_err());
+    }
+}
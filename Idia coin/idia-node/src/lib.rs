@@ -0,0 +1,19 @@
+//! Idia Node - the full node daemon
+//!
+//! Runs the network, chain, mempool, miner, explorer and wallet from
+//! `idia-core` as supervised tasks of a single process.
+
+pub mod chain;
+pub mod config;
+pub mod mempool;
+pub mod miner;
+pub mod node;
+pub mod node_client;
+pub mod rpc;
+pub mod supervisor;
+pub mod telemetry;
+pub mod utxo;
+pub mod wallet_rpc;
+pub mod wallet_service;
+
+pub use node::{Node, NodeConfig, NodeContext, NodeError};
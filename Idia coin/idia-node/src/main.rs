@@ -0,0 +1,53 @@
+//! `idia-node` daemon entry point
+
+use clap::Parser;
+use idia_node::telemetry::{self, LoggingSection};
+use idia_node::{config, Node, NodeConfig};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "idia-node", version, about = "Idia full node")]
+struct Args {
+    /// TOML configuration file
+    #[arg(long, env = "IDIA_CONFIG")]
+    config: Option<PathBuf>,
+    /// Mine blocks, whatever the config says
+    #[arg(long)]
+    mine: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let config = config::load(args.config.as_deref()).and_then(|config| {
+        let logging = LoggingSection::from_config(&config)?;
+        Ok((NodeConfig::from_config(&config)?, logging))
+    });
+    let config = match config {
+        Ok((config, logging)) => {
+            if let Err(e) = telemetry::init(&logging.filter) {
+                eprintln!("Failed to start logging: {}", e);
+            }
+            NodeConfig {
+                mine: config.mine || args.mine,
+                ..config
+            }
+        }
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let node = match Node::new(config).await {
+        Ok(node) => node,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to create node");
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = node.run().await {
+        tracing::error!(error = %e, "Node stopped");
+        std::process::exit(1);
+    }
+}
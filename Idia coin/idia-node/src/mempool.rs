@@ -0,0 +1,412 @@
+//! Pool of transactions waiting to be mined
+
+use crate::chain::{key_images, ChainState};
+use async_trait::async_trait;
+use idia_core::metrics;
+use idia_core::{Block, Hash, Transaction};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Lowest fee rate, per 1000 bytes, the fee estimate ever suggests
+pub const MIN_FEE_RATE: u64 = 10;
+
+/// Mempool error types
+#[derive(Debug, thiserror::Error)]
+pub enum MempoolError {
+    #[error("Transaction already in the pool")]
+    Duplicate,
+    #[error("Transaction spends a key image already spent in the pool")]
+    Conflict,
+    #[error("Mempool is full")]
+    Full,
+    #[error("Fee rate too low for a full pool")]
+    FeeTooLow,
+    #[error("Ring of {size} members below the minimum of {min}")]
+    RingTooSmall { size: usize, min: usize },
+    #[error("Refused by admission policy: {0}")]
+    Refused(String),
+}
+
+/// Node-local rule, on top of consensus validity, for which transactions
+/// this node pools and relays
+#[async_trait]
+pub trait AdmissionPolicy: Send + Sync {
+    /// The reason `tx` is refused, if it is
+    async fn admit(&self, tx: &Transaction) -> Result<(), String>;
+}
+
+/// Block inclusion order under a burned base fee: highest effective tip
+/// first, then highest maximum fee
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeePriority {
+    /// What the block producer keeps per byte once the base fee is burned
+    pub tip_per_byte: u64,
+    /// Most the sender pays per byte, base fee included
+    pub max_fee_per_byte: u64,
+}
+
+impl FeePriority {
+    /// Priority of a transaction bidding `max_fee_per_byte`, of which at most
+    /// `max_priority_fee_per_byte` goes to the producer. None if it can't
+    /// cover `base_fee` and so can't be mined in the next block.
+    pub fn new(base_fee: u64, max_fee_per_byte: u64, max_priority_fee_per_byte: u64) -> Option<Self> {
+        let headroom = max_fee_per_byte.checked_sub(base_fee)?;
+        Some(Self {
+            tip_per_byte: headroom.min(max_priority_fee_per_byte),
+            max_fee_per_byte,
+        })
+    }
+}
+
+impl Ord for FeePriority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.tip_per_byte
+            .cmp(&other.tip_per_byte)
+            .then(self.max_fee_per_byte.cmp(&other.max_fee_per_byte))
+    }
+}
+
+impl PartialOrd for FeePriority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A pooled transaction with its fee rate
+#[derive(Debug, Clone)]
+pub struct MempoolEntry {
+    /// The transaction
+    pub tx: Transaction,
+    /// Serialized size in bytes
+    pub size: usize,
+    /// Fee per 1000 bytes
+    pub fee_rate: u64,
+}
+
+impl MempoolEntry {
+    fn new(tx: Transaction) -> Self {
+        let size = bincode::serialized_size(&tx).unwrap_or(u64::MAX) as usize;
+        let fee_rate = tx.fee.saturating_mul(1000) / size.max(1) as u64;
+        Self { tx, size, fee_rate }
+    }
+
+    /// Priority over `base_fee`. A transaction carries a single fee, so all
+    /// of it above the base fee is its tip.
+    pub fn priority(&self, base_fee: u64) -> Option<FeePriority> {
+        let fee_per_byte = self.tx.fee / self.size.max(1) as u64;
+        FeePriority::new(base_fee, fee_per_byte, fee_per_byte)
+    }
+}
+
+/// Transactions validated against the chain and waiting for a block
+pub struct Mempool {
+    /// Entries by transaction hash
+    entries: HashMap<Hash, MempoolEntry>,
+    /// Transaction spending each pooled key image
+    key_images: HashMap<[u8; 32], Hash>,
+    /// Maximum number of transactions held
+    max_size: usize,
+    /// Base fee per byte the next block burns
+    base_fee: u64,
+    /// Fewest ring members an input may have
+    min_ring_size: usize,
+    /// Most bytes of transactions a block template holds
+    max_block_size: usize,
+    /// Operator policy every new transaction must pass, if any
+    admission: Option<Arc<dyn AdmissionPolicy>>,
+}
+
+impl Mempool {
+    /// Create an empty mempool holding at most `max_size` transactions
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            key_images: HashMap::new(),
+            max_size,
+            base_fee: 0,
+            min_ring_size: 0,
+            max_block_size: usize::MAX,
+            admission: None,
+        }
+    }
+
+    /// Set the policy new transactions must pass before `insert`
+    pub fn set_admission_policy(&mut self, policy: Arc<dyn AdmissionPolicy>) {
+        self.admission = Some(policy);
+    }
+
+    /// Run the admission policy on `tx`.
+    ///
+    /// Policies are async, so this is separate from `insert` and should be
+    /// awaited before the chain is locked for validation.
+    pub async fn admit(&self, tx: &Transaction) -> Result<(), MempoolError> {
+        let Some(policy) = &self.admission else {
+            return Ok(());
+        };
+        policy.admit(tx).await.map_err(|reason| {
+            metrics::MEMPOOL_REJECTED.inc();
+            MempoolError::Refused(reason)
+        })
+    }
+
+    /// Set the fewest ring members an input may have; pooled transactions
+    /// below it are dropped
+    pub fn set_min_ring_size(&mut self, min_ring_size: usize) {
+        self.min_ring_size = min_ring_size;
+        let small: Vec<Hash> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| self.small_ring(&entry.tx).is_some())
+            .map(|(hash, _)| *hash)
+            .collect();
+        for hash in small {
+            self.remove(&hash);
+        }
+    }
+
+    /// Set the most bytes of transactions `select` puts in a block
+    pub fn set_max_block_size(&mut self, max_block_size: usize) {
+        self.max_block_size = max_block_size;
+    }
+
+    /// Size of the first ring in `tx` below the minimum
+    fn small_ring(&self, tx: &Transaction) -> Option<usize> {
+        tx.inputs.iter().map(|input| input.ring.len()).find(|size| *size < self.min_ring_size)
+    }
+
+    /// Set the base fee per byte of the next block, as the fee market moves
+    /// it after each block; transactions below it wait in the pool
+    pub fn set_base_fee(&mut self, base_fee: u64) {
+        self.base_fee = base_fee;
+    }
+
+    /// Number of pooled transactions
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the pool is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get a pooled transaction
+    pub fn get(&self, hash: &Hash) -> Option<&Transaction> {
+        self.entries.get(hash).map(|entry| &entry.tx)
+    }
+
+    /// Add a transaction already validated against `chain`.
+    ///
+    /// When the pool is full the lowest fee rate entry is evicted to make
+    /// room, if the new transaction pays more.
+    pub fn insert(&mut self, tx: Transaction, chain: &ChainState) -> Result<Hash, MempoolError> {
+        let result = self.try_insert(tx, chain);
+        if result.is_err() {
+            metrics::MEMPOOL_REJECTED.inc();
+        }
+        result
+    }
+
+    fn try_insert(&mut self, tx: Transaction, chain: &ChainState) -> Result<Hash, MempoolError> {
+        let hash = tx.hash();
+        if self.entries.contains_key(&hash) {
+            return Err(MempoolError::Duplicate);
+        }
+        if let Some(size) = self.small_ring(&tx) {
+            return Err(MempoolError::RingTooSmall { size, min: self.min_ring_size });
+        }
+        let images: Vec<[u8; 32]> = key_images(&tx).collect();
+        if images.iter().any(|image| self.key_images.contains_key(image) || chain.is_spent(image)) {
+            return Err(MempoolError::Conflict);
+        }
+
+        let entry = MempoolEntry::new(tx);
+        if self.entries.len() >= self.max_size {
+            let (&lowest, lowest_rate) = self
+                .entries
+                .iter()
+                .map(|(hash, entry)| (hash, entry.fee_rate))
+                .min_by_key(|(_, rate)| *rate)
+                .ok_or(MempoolError::Full)?;
+            if lowest_rate >= entry.fee_rate {
+                return Err(MempoolError::FeeTooLow);
+            }
+            self.remove(&lowest);
+            metrics::MEMPOOL_EVICTIONS.inc();
+        }
+
+        for image in images {
+            self.key_images.insert(image, hash);
+        }
+        self.entries.insert(hash, entry);
+        metrics::MEMPOOL_TRANSACTIONS.set(self.entries.len() as i64);
+        Ok(hash)
+    }
+
+    /// Remove a transaction
+    pub fn remove(&mut self, hash: &Hash) -> Option<Transaction> {
+        let entry = self.entries.remove(hash)?;
+        for image in key_images(&entry.tx) {
+            self.key_images.remove(&image);
+        }
+        metrics::MEMPOOL_TRANSACTIONS.set(self.entries.len() as i64);
+        Some(entry.tx)
+    }
+
+    /// Drop transactions mined in `block` or conflicting with it
+    pub fn remove_block(&mut self, block: &Block) {
+        for tx in &block.transactions {
+            self.remove(&tx.hash());
+            for image in key_images(tx) {
+                if let Some(conflict) = self.key_images.get(&image).copied() {
+                    self.remove(&conflict);
+                }
+            }
+        }
+    }
+
+    /// Up to `max` transactions that cover the base fee and fit in the block
+    /// size, highest priority first; the fee rate breaks ties lost to
+    /// per-byte rounding
+    pub fn select(&self, max: usize) -> Vec<Transaction> {
+        let mut entries: Vec<(FeePriority, &MempoolEntry)> = self
+            .entries
+            .values()
+            .filter_map(|entry| Some((entry.priority(self.base_fee)?, entry)))
+            .collect();
+        entries.sort_by(|(a, a_entry), (b, b_entry)| b.cmp(a).then(b_entry.fee_rate.cmp(&a_entry.fee_rate)));
+
+        let mut space = self.max_block_size;
+        entries
+            .into_iter()
+            .filter(|(_, entry)| match space.checked_sub(entry.size) {
+                Some(left) => {
+                    space = left;
+                    true
+                }
+                None => false,
+            })
+            .take(max)
+            .map(|(_, entry)| entry.tx.clone())
+            .collect()
+    }
+
+    /// Fee rate per 1000 bytes likely to be mined within `target_blocks`
+    /// blocks of `block_capacity` transactions: just above whatever the pool
+    /// would fill those blocks with
+    pub fn estimate_fee_rate(&self, target_blocks: u64, block_capacity: usize) -> u64 {
+        let mut rates: Vec<u64> = self.entries.values().map(|entry| entry.fee_rate).collect();
+        rates.sort_unstable_by(|a, b| b.cmp(a));
+        let capacity = (target_blocks.max(1) as usize).saturating_mul(block_capacity);
+        match rates.get(capacity.saturating_sub(1)) {
+            Some(rate) if rates.len() >= capacity => (rate + 1).max(MIN_FEE_RATE),
+            _ => MIN_FEE_RATE,
+        }
+    }
+
+    /// All pooled entries, in no particular order
+    pub fn entries(&self) -> impl Iterator<Item = &MempoolEntry> {
+        self.entries.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_ordering_and_eviction() {
+        let chain = ChainState::new(0);
+        let mut mempool = Mempool::new(2);
+
+        let low = mempool.insert(Transaction::new(vec![], vec![], 1), &chain).unwrap();
+        let high = mempool.insert(Transaction::new(vec![], vec![], 100), &chain).unwrap();
+        assert_eq!(mempool.select(1)[0].hash(), high);
+
+        // A full pool takes a better paying transaction in place of the worst
+        mempool.insert(Transaction::new(vec![], vec![], 50), &chain).unwrap();
+        assert!(mempool.get(&low).is_none());
+        assert_eq!(mempool.len(), 2);
+
+        assert!(matches!(
+            mempool.insert(Transaction::new(vec![], vec![], 2), &chain),
+            Err(MempoolError::FeeTooLow)
+        ));
+    }
+
+    #[test]
+    fn test_base_fee_ordering() {
+        let chain = ChainState::new(0);
+        let mut mempool = Mempool::new(10);
+        let size = MempoolEntry::new(Transaction::new(vec![], vec![], 0)).size as u64;
+
+        let cheap = mempool.insert(Transaction::new(vec![], vec![], 5 * size), &chain).unwrap();
+        let tipping = mempool.insert(Transaction::new(vec![], vec![], 20 * size), &chain).unwrap();
+        let generous = mempool.insert(Transaction::new(vec![], vec![], 30 * size), &chain).unwrap();
+
+        // Under a base fee of 10 per byte the cheap one waits in the pool
+        mempool.set_base_fee(10);
+        let selected: Vec<Hash> = mempool.select(10).iter().map(|tx| tx.hash()).collect();
+        assert_eq!(selected, vec![generous, tipping]);
+        assert!(mempool.get(&cheap).is_some());
+
+        let entry = mempool.entries().find(|entry| entry.tx.hash() == tipping).unwrap();
+        assert_eq!(entry.priority(10).unwrap().tip_per_byte, 10);
+        assert!(entry.priority(21).is_none());
+    }
+
+    #[test]
+    fn test_block_size_limit() {
+        let chain = ChainState::new(0);
+        let mut mempool = Mempool::new(10);
+        for fee in [10, 20, 30] {
+            mempool.insert(Transaction::new(vec![], vec![], fee), &chain).unwrap();
+        }
+        let size = mempool.entries().next().unwrap().size;
+
+        mempool.set_max_block_size(2 * size + 1);
+        let selected = mempool.select(10);
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[0].fee, 30);
+    }
+
+    #[test]
+    fn test_fee_estimate() {
+        let chain = ChainState::new(0);
+        let mut mempool = Mempool::new(10);
+        assert_eq!(mempool.estimate_fee_rate(1, 1), MIN_FEE_RATE);
+
+        for fee in [1_000, 2_000, 3_000] {
+            mempool.insert(Transaction::new(vec![], vec![], fee), &chain).unwrap();
+        }
+        // One slot in the next block: outbid the best paying transaction
+        let best = mempool.entries().map(|entry| entry.fee_rate).max().unwrap();
+        assert_eq!(mempool.estimate_fee_rate(1, 1), best + 1);
+        // Room for everything pooled within three blocks
+        assert_eq!(mempool.estimate_fee_rate(3, 2), MIN_FEE_RATE);
+    }
+
+    struct MinimumFee(u64);
+
+    #[async_trait]
+    impl AdmissionPolicy for MinimumFee {
+        async fn admit(&self, tx: &Transaction) -> Result<(), String> {
+            if tx.fee < self.0 {
+                return Err(format!("fee {} below {}", tx.fee, self.0));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admission_policy_refuses() {
+        let mut mempool = Mempool::new(10);
+        let cheap = Transaction::new(vec![], vec![], 5);
+        mempool.admit(&cheap).await.unwrap();
+
+        mempool.set_admission_policy(Arc::new(MinimumFee(100)));
+        assert!(matches!(mempool.admit(&cheap).await, Err(MempoolError::Refused(_))));
+        mempool.admit(&Transaction::new(vec![], vec![], 100)).await.unwrap();
+    }
+}
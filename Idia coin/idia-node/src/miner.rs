@@ -0,0 +1,59 @@
+//! Proof-of-work block production
+
+use crate::chain::{meets_difficulty, ChainState};
+use crate::mempool::Mempool;
+use idia_core::Block;
+
+/// Builds block templates from the mempool and searches for a valid nonce
+pub struct Miner {
+    /// Maximum number of transactions per block
+    max_block_transactions: usize,
+    /// Nonces tried before checking whether the tip moved
+    attempts_per_round: u64,
+}
+
+impl Miner {
+    /// Create a new miner
+    pub fn new(max_block_transactions: usize) -> Self {
+        Self {
+            max_block_transactions,
+            attempts_per_round: 100_000,
+        }
+    }
+
+    /// A block extending the tip with the best paying pooled transactions
+    pub fn template(&self, chain: &ChainState, mempool: &Mempool) -> Block {
+        Block::new(
+            chain.tip(),
+            chain.height() + 1,
+            chain.next_difficulty(),
+            mempool.select(self.max_block_transactions),
+        )
+    }
+
+    /// Try one round of nonces, returning whether the block is now solved
+    pub fn solve(&self, block: &mut Block) -> bool {
+        for _ in 0..self.attempts_per_round {
+            if meets_difficulty(&block.hash(), block.header.difficulty) {
+                return true;
+            }
+            block.header.nonce = block.header.nonce.wrapping_add(1);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mine_block() {
+        let mut chain = ChainState::new(8);
+        let miner = Miner::new(100);
+        let mut block = miner.template(&chain, &Mempool::new(10));
+        while !miner.solve(&mut block) {}
+        assert!(chain.connect_block(block).is_ok());
+        assert_eq!(chain.height(), 1);
+    }
+}
@@ -0,0 +1,523 @@
+//! The node daemon: wires network, chain, mempool, miner, explorer and wallet
+
+use crate::chain::{ChainError, ChainState};
+use crate::mempool::{Mempool, MempoolError};
+use crate::miner::Miner;
+use crate::rpc::{self, RpcConfig, RpcState};
+use crate::supervisor::{RestartPolicy, Supervisor, TaskResult};
+use idia_core::explorer::{Explorer, ExplorerError};
+use idia_core::metrics::{self, MetricsConfig};
+use idia_core::shutdown::{self, Shutdown, ShutdownToken};
+use idia_core::storage::{self, columns, Column, ColumnStore, StorageConfig, StorageError};
+use idia_core::{
+    Block, GossipValidator, Hash, NetworkCommand, NetworkConfig, NetworkEvent, P2PService, PeerStore,
+    Transaction, Wallet, WalletConfig, WalletError, ROLLUP_BATCH_TOPIC, SWAP_ORDERS_TOPIC,
+};
+use axum::Router;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, Mutex, RwLock};
+
+/// Node error types
+#[derive(Debug, thiserror::Error)]
+pub enum NodeError {
+    #[error("Chain error: {0}")]
+    Chain(#[from] ChainError),
+    #[error("Mempool error: {0}")]
+    Mempool(#[from] MempoolError),
+    #[error("Explorer error: {0}")]
+    Explorer(#[from] ExplorerError),
+    #[error("Wallet error: {0}")]
+    Wallet(#[from] WalletError),
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("Network error: {0}")]
+    Network(String),
+}
+
+/// Node configuration
+#[derive(Debug, Clone)]
+pub struct NodeConfig {
+    /// Network configuration
+    pub network: NetworkConfig,
+    /// Wallet to keep in sync with the chain, if any
+    pub wallet: Option<WalletConfig>,
+    /// JSON-RPC server, if enabled
+    pub rpc: Option<RpcConfig>,
+    /// Prometheus metrics endpoint, if enabled
+    pub metrics: Option<MetricsConfig>,
+    /// Database holding the chain, explorer, wallet and peers
+    pub storage: StorageConfig,
+    /// Number of recent blocks to keep whole, pruning older ones, if set
+    pub prune_window: Option<u64>,
+    /// Whether to mine blocks
+    pub mine: bool,
+    /// Difficulty required of blocks, in leading zero bits
+    pub difficulty: u32,
+    /// Maximum number of pooled transactions
+    pub mempool_size: usize,
+    /// Maximum number of transactions per mined block
+    pub max_block_transactions: usize,
+    /// How long each task may take to flush and stop once shutdown begins
+    pub shutdown_timeout: Duration,
+}
+
+/// Shared handles to the node's components, cheap to clone into tasks
+#[derive(Clone)]
+pub struct NodeContext {
+    /// Best chain
+    pub chain: Arc<RwLock<ChainState>>,
+    /// Transactions waiting for a block
+    pub mempool: Arc<RwLock<Mempool>>,
+    /// Privacy-preserving explorer
+    pub explorer: Arc<Explorer>,
+    /// Wallet kept in sync with the chain
+    pub wallet: Option<Arc<RwLock<Wallet>>>,
+    /// Commands to the network, whichever run of the network task is live
+    network: mpsc::Sender<NetworkCommand>,
+    /// Connected blocks waiting for the explorer indexer
+    indexer: mpsc::Sender<Block>,
+}
+
+impl NodeContext {
+    /// Connect a block to the chain and pass it to every component.
+    ///
+    /// `relay` gossips it on, for blocks that did not come from the network.
+    #[tracing::instrument(skip_all, fields(height = block.header.height, relay))]
+    pub async fn submit_block(&self, block: Block, relay: bool) -> Result<Hash, NodeError> {
+        let hash = self.chain.write().await.connect_block(block.clone())?;
+        self.mempool.write().await.remove_block(&block);
+        if let Some(wallet) = &self.wallet {
+            wallet.write().await.process_block(&block).await?;
+        }
+        if relay {
+            self.relay(NetworkCommand::BroadcastBlock(block.clone())).await;
+        }
+        if self.indexer.send(block).await.is_err() {
+            tracing::warn!("Explorer indexer is down, not indexing");
+        }
+        tracing::debug!(hash = %hex::encode(hash), "Connected block");
+        Ok(hash)
+    }
+
+    /// Validate a transaction and add it to the mempool
+    #[tracing::instrument(skip_all, fields(tx = %hex::encode(tx.hash()), relay))]
+    pub async fn submit_transaction(&self, tx: Transaction, relay: bool) -> Result<Hash, NodeError> {
+        self.mempool.read().await.admit(&tx).await?;
+        let hash = {
+            let chain = self.chain.read().await;
+            chain.check_transaction(&tx)?;
+            self.mempool.write().await.insert(tx.clone(), &chain)?
+        };
+        if relay {
+            self.relay(NetworkCommand::BroadcastTransaction(tx)).await;
+        }
+        Ok(hash)
+    }
+
+    /// Commands to the network, for services built on the node to gossip
+    /// their own messages
+    pub fn network(&self) -> mpsc::Sender<NetworkCommand> {
+        self.network.clone()
+    }
+
+    async fn relay(&self, command: NetworkCommand) {
+        if self.network.send(command).await.is_err() {
+            tracing::warn!("Network is down, not relaying");
+        }
+    }
+}
+
+/// The node daemon
+pub struct Node {
+    /// Node configuration
+    config: NodeConfig,
+    /// Shared component handles
+    context: NodeContext,
+    /// Database the components persist to
+    store: Arc<dyn ColumnStore>,
+    /// Runs and restarts the node's tasks
+    supervisor: Supervisor,
+    /// Network events, read by the dispatcher across network restarts
+    events: (mpsc::Sender<NetworkEvent>, Arc<Mutex<mpsc::Receiver<NetworkEvent>>>),
+    /// Network commands, read by whichever run of the network task is live
+    commands: Arc<Mutex<mpsc::Receiver<NetworkCommand>>>,
+    /// Connected blocks, read by whichever run of the indexer task is live
+    blocks: Arc<Mutex<mpsc::Receiver<Block>>>,
+    /// True while the network task is up
+    network_ready: watch::Sender<bool>,
+    /// Further HTTP routes for the RPC server
+    rpc_routes: Router,
+    /// Validators of gossip topics served by services built on the node
+    validators: HashMap<String, Arc<dyn GossipValidator>>,
+    /// Where accepted payloads of those topics go
+    gossip: HashMap<String, mpsc::Sender<Vec<u8>>>,
+}
+
+impl Node {
+    /// Create the node's components, without starting anything
+    pub async fn new(config: NodeConfig) -> Result<Self, NodeError> {
+        let store = storage::open(&config.storage)?;
+        let wallet = match &config.wallet {
+            Some(wallet_config) => {
+                let wallet = Wallet::new(wallet_config.clone()).await?.with_store(store.clone()).await?;
+                Some(Arc::new(RwLock::new(wallet)))
+            }
+            None => None,
+        };
+        let (command_tx, command_rx) = mpsc::channel(100);
+        let (event_tx, event_rx) = mpsc::channel(100);
+        let (block_tx, block_rx) = mpsc::channel(100);
+        let (network_ready, _) = watch::channel(false);
+
+        let mut chain = ChainState::open(store.clone(), config.difficulty)?;
+        if let Some(window) = config.prune_window {
+            chain = chain.with_pruning(window)?;
+        }
+
+        let context = NodeContext {
+            chain: Arc::new(RwLock::new(chain)),
+            mempool: Arc::new(RwLock::new(Mempool::new(config.mempool_size))),
+            explorer: Arc::new(Explorer::with_store(store.clone())?),
+            wallet,
+            network: command_tx,
+            indexer: block_tx,
+        };
+
+        Ok(Self {
+            supervisor: Supervisor::new().with_shutdown_timeout(config.shutdown_timeout),
+            config,
+            context,
+            store,
+            events: (event_tx, Arc::new(Mutex::new(event_rx))),
+            commands: Arc::new(Mutex::new(command_rx)),
+            blocks: Arc::new(Mutex::new(block_rx)),
+            network_ready,
+            rpc_routes: Router::new(),
+            validators: HashMap::new(),
+            gossip: HashMap::new(),
+        })
+    }
+
+    /// Validate `topic`'s gossip with `validator` before it is relayed, and
+    /// hand accepted payloads to `handler`, for services built on the node
+    /// such as rollups and swaps
+    pub fn with_gossip(
+        mut self,
+        topic: &str,
+        validator: Arc<dyn GossipValidator>,
+        handler: mpsc::Sender<Vec<u8>>,
+    ) -> Self {
+        self.validators.insert(topic.to_string(), validator);
+        self.gossip.insert(topic.to_string(), handler);
+        self
+    }
+
+    /// Serve `routes` from the RPC server, behind its bearer token, for
+    /// services built on the node such as governance
+    pub fn with_rpc_routes(mut self, routes: Router) -> Self {
+        self.rpc_routes = self.rpc_routes.merge(routes);
+        self
+    }
+
+    /// Shared component handles
+    pub fn context(&self) -> NodeContext {
+        self.context.clone()
+    }
+
+    /// Start the node's tasks in dependency order: the explorer catches up
+    /// with the chain and starts indexing, then the network comes up, then
+    /// inbound events are dispatched and RPC is served, and mining only
+    /// begins once the network is ready.
+    pub async fn start(&mut self) -> Result<(), NodeError> {
+        self.catch_up_explorer().await?;
+
+        let backoff = Duration::from_secs(1);
+        let explorer = self.context.explorer.clone();
+        let blocks = self.blocks.clone();
+        self.supervisor.spawn("explorer", RestartPolicy::Always { backoff }, false, move |token| {
+            index_blocks(explorer.clone(), blocks.clone(), token)
+        });
+
+        let network = self.config.network.clone();
+        let events = self.events.0.clone();
+        let commands = self.commands.clone();
+        let ready = self.network_ready.clone();
+        let store = self.store.clone();
+        let validators = self.validators.clone();
+        self.supervisor.spawn("network", RestartPolicy::Always { backoff }, true, move |token| {
+            let peers = PeerStore::new(Column::new(store.clone(), columns::PEERS));
+            run_network(
+                network.clone(),
+                peers,
+                validators.clone(),
+                events.clone(),
+                commands.clone(),
+                ready.clone(),
+                token,
+            )
+        });
+
+        let context = self.context.clone();
+        let events = self.events.1.clone();
+        let gossip = self.gossip.clone();
+        self.supervisor.spawn("dispatcher", RestartPolicy::Always { backoff }, true, move |token| {
+            dispatch_events(context.clone(), gossip.clone(), events.clone(), token)
+        });
+
+        if let Some(rpc_config) = &self.config.rpc {
+            let state = RpcState::new(self.context.clone(), rpc_config.clone(), self.config.max_block_transactions)
+                .with_routes(self.rpc_routes.clone());
+            let policy = RestartPolicy::OnFailure { max_restarts: 5, backoff };
+            self.supervisor.spawn("rpc", policy, false, move |token| {
+                let state = state.clone();
+                async move { Ok(rpc::serve(state, token).await?) }
+            });
+        }
+
+        if let Some(metrics_config) = &self.config.metrics {
+            let metrics_config = metrics_config.clone();
+            let policy = RestartPolicy::OnFailure { max_restarts: 5, backoff };
+            self.supervisor.spawn("metrics", policy, false, move |token| {
+                let metrics_config = metrics_config.clone();
+                async move { Ok(metrics::serve(&metrics_config, token).await?) }
+            });
+        }
+
+        if self.config.mine {
+            let context = self.context.clone();
+            let ready = self.network_ready.subscribe();
+            let max_block_transactions = self.config.max_block_transactions;
+            let policy = RestartPolicy::OnFailure { max_restarts: 10, backoff };
+            self.supervisor.spawn("miner", policy, false, move |token| {
+                mine(context.clone(), Miner::new(max_block_transactions), ready.clone(), token)
+            });
+        }
+        Ok(())
+    }
+
+    /// Run until SIGINT, SIGTERM or a critical task giving up, then stop
+    /// every task, each flushing within the shutdown timeout
+    pub async fn run(mut self) -> Result<(), NodeError> {
+        self.start().await?;
+        tokio::select! {
+            _ = shutdown::os_signal() => {}
+            _ = self.supervisor.wait() => {}
+        }
+        self.supervisor.shutdown();
+        self.supervisor.join().await;
+        self.store.flush()?;
+        tracing::info!("Node stopped");
+        Ok(())
+    }
+
+    /// Index blocks the chain connected but the explorer never saw, such as
+    /// those connected just before an unclean exit. Pruned blocks can't be
+    /// indexed and are skipped.
+    async fn catch_up_explorer(&self) -> Result<(), NodeError> {
+        let chain = self.context.chain.read().await;
+        let mut from = chain.height() + 1;
+        while from > 0 {
+            let hash = chain.hash_at(from - 1).ok_or_else(|| missing_block(from - 1))?;
+            if self.context.explorer.get_block_info(&hash).await.is_ok() {
+                break;
+            }
+            from -= 1;
+        }
+        if from < chain.pruned_below() {
+            tracing::warn!(from, to = chain.pruned_below() - 1, "Explorer is missing pruned blocks");
+            from = chain.pruned_below();
+        }
+        for height in from..=chain.height() {
+            let block = chain.block_at(height).ok_or_else(|| missing_block(height))?;
+            self.context.explorer.add_block(block).await?;
+        }
+        if from <= chain.height() {
+            tracing::info!(from, to = chain.height(), "Caught explorer up with the chain");
+        }
+        Ok(())
+    }
+}
+
+fn missing_block(height: u64) -> StorageError {
+    StorageError::Backend(format!("best chain block {} is missing", height))
+}
+
+/// One run of the network service, forwarding events and commands through
+/// the node's long-lived channels
+async fn run_network(
+    config: NetworkConfig,
+    peers: PeerStore,
+    validators: HashMap<String, Arc<dyn GossipValidator>>,
+    events: mpsc::Sender<NetworkEvent>,
+    commands: Arc<Mutex<mpsc::Receiver<NetworkCommand>>>,
+    ready: watch::Sender<bool>,
+    shutdown: ShutdownToken,
+) -> TaskResult {
+    let mut service = P2PService::new(config).await.map_err(|e| e.to_string())?;
+    service.set_peer_store(peers);
+    for (topic, validator) in validators {
+        service.set_validator(&topic, validator);
+    }
+    let mut inbound = service.take_events().ok_or("network events already taken")?;
+    let outbound = service.commands();
+    let mut commands = commands.lock().await;
+    ready.send_replace(true);
+
+    // Stopped only once our queued commands are handed over
+    let stop = Shutdown::new();
+    let result: TaskResult = {
+        let run = service.run(stop.token());
+        tokio::pin!(run);
+        loop {
+            tokio::select! {
+                _ = &mut run => break Err("network service stopped".into()),
+                _ = shutdown.wait() => {
+                    while let Ok(command) = commands.try_recv() {
+                        let _ = outbound.send(command).await;
+                    }
+                    stop.trigger();
+                    (&mut run).await;
+                    break Ok(());
+                }
+                Some(event) = inbound.recv() => {
+                    if events.send(event).await.is_err() {
+                        break Ok(());
+                    }
+                }
+                Some(command) = commands.recv() => {
+                    let _ = outbound.send(command).await;
+                }
+            }
+        }
+    };
+    ready.send_replace(false);
+    result
+}
+
+/// Index connected blocks into the explorer, working through blocks
+/// already queued before stopping
+async fn index_blocks(
+    explorer: Arc<Explorer>,
+    blocks: Arc<Mutex<mpsc::Receiver<Block>>>,
+    shutdown: ShutdownToken,
+) -> TaskResult {
+    let mut blocks = blocks.lock().await;
+    loop {
+        let block = tokio::select! {
+            block = blocks.recv() => match block {
+                Some(block) => block,
+                None => return Ok(()),
+            },
+            _ = shutdown.wait() => break,
+        };
+        index_block(&explorer, block).await;
+    }
+    while let Ok(block) = blocks.try_recv() {
+        index_block(&explorer, block).await;
+    }
+    Ok(())
+}
+
+async fn index_block(explorer: &Explorer, block: Block) {
+    let height = block.header.height;
+    if let Err(e) = explorer.add_block(block).await {
+        tracing::warn!(height, error = %e, "Failed to index block");
+    }
+}
+
+/// Apply inbound blocks and transactions, and hand other gossip to the
+/// services that registered its topic
+async fn dispatch_events(
+    context: NodeContext,
+    gossip: HashMap<String, mpsc::Sender<Vec<u8>>>,
+    events: Arc<Mutex<mpsc::Receiver<NetworkEvent>>>,
+    shutdown: ShutdownToken,
+) -> TaskResult {
+    let mut events = events.lock().await;
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => return Ok(()),
+            },
+            _ = shutdown.wait() => return Ok(()),
+        };
+        match event {
+            NetworkEvent::Block(block) => {
+                let height = block.header.height;
+                if let Err(e) = context.submit_block(block, false).await {
+                    tracing::debug!(height, error = %e, "Ignoring block");
+                }
+            }
+            NetworkEvent::Transaction(tx) => {
+                if let Err(e) = context.submit_transaction(tx, false).await {
+                    tracing::debug!(error = %e, "Ignoring transaction");
+                }
+            }
+            NetworkEvent::Governance(payload) => forward(&gossip, "governance", payload).await,
+            NetworkEvent::RollupBatch(payload) => forward(&gossip, ROLLUP_BATCH_TOPIC, payload).await,
+            NetworkEvent::SwapOrder(payload) => forward(&gossip, SWAP_ORDERS_TOPIC, payload).await,
+            _ => {}
+        }
+    }
+}
+
+/// Hand a gossiped payload to its topic's service, if one registered
+async fn forward(gossip: &HashMap<String, mpsc::Sender<Vec<u8>>>, topic: &str, payload: Vec<u8>) {
+    if let Some(handler) = gossip.get(topic) {
+        if handler.send(payload).await.is_err() {
+            tracing::warn!(topic, "Gossip handler is down, dropping message");
+        }
+    }
+}
+
+/// Mine on the tip, starting over whenever it moves
+async fn mine(
+    context: NodeContext,
+    miner: Miner,
+    mut ready: watch::Receiver<bool>,
+    shutdown: ShutdownToken,
+) -> TaskResult {
+    let miner = Arc::new(miner);
+    loop {
+        tokio::select! {
+            up = ready.wait_for(|up| *up) => {
+                up?;
+            }
+            _ = shutdown.wait() => return Ok(()),
+        }
+        let mut block = {
+            let chain = context.chain.read().await;
+            let mempool = context.mempool.read().await;
+            miner.template(&chain, &mempool)
+        };
+
+        loop {
+            let round = miner.clone();
+            let (solved, candidate) = tokio::task::spawn_blocking(move || {
+                let mut block = block;
+                let solved = round.solve(&mut block);
+                (solved, block)
+            })
+            .await?;
+            block = candidate;
+
+            if shutdown.is_shutdown() {
+                return Ok(());
+            }
+            if context.chain.read().await.tip() != block.header.prev_hash {
+                break;
+            }
+            if solved {
+                let height = block.header.height;
+                match context.submit_block(block, true).await {
+                    Ok(hash) => tracing::info!(hash = %hex::encode(hash), height, "Mined block"),
+                    Err(e) => tracing::warn!(height, error = %e, "Mined block rejected"),
+                }
+                break;
+            }
+        }
+    }
+}
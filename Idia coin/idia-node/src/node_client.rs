@@ -0,0 +1,104 @@
+//! Client for the node's JSON-RPC server
+
+use idia_core::{Block, Hash, Transaction};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Node client error types
+#[derive(Debug, thiserror::Error)]
+pub enum NodeClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Node returned error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("Malformed node response: {0}")]
+    Malformed(String),
+}
+
+/// Summary of the node's chain
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeInfo {
+    /// Height of the tip
+    pub height: u64,
+    /// Hash of the tip, hex
+    pub top_block_hash: String,
+    /// Lowest height whose block the node still serves; 0 unless it prunes
+    #[serde(default)]
+    pub pruned_below: u64,
+}
+
+#[derive(Deserialize)]
+struct BlockResponse {
+    block: Block,
+}
+
+/// Calls the node's JSON-RPC methods over HTTP
+pub struct NodeClient {
+    /// URL of the node's `/json_rpc` endpoint
+    url: String,
+    /// Bearer token, if the node requires one
+    token: Option<String>,
+    /// HTTP client
+    client: reqwest::Client,
+    /// Next request ID
+    next_id: AtomicU64,
+}
+
+impl NodeClient {
+    /// Create a client for the node at `url`
+    pub fn new(url: String, token: Option<String>) -> Self {
+        Self {
+            url,
+            token,
+            client: reqwest::Client::new(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Call a method, decoding its result
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, NodeClientError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut request = self
+            .client
+            .post(&self.url)
+            .json(&json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": id }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let mut response: Value = request.send().await?.error_for_status()?.json().await?;
+        if let Some(error) = response.get("error") {
+            return Err(NodeClientError::Rpc {
+                code: error["code"].as_i64().unwrap_or_default(),
+                message: error["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        serde_json::from_value(response["result"].take()).map_err(|e| NodeClientError::Malformed(e.to_string()))
+    }
+
+    /// Get the node's chain summary
+    pub async fn get_info(&self) -> Result<NodeInfo, NodeClientError> {
+        self.call("get_info", Value::Null).await
+    }
+
+    /// Get the best chain block at `height`
+    pub async fn get_block_by_height(&self, height: u64) -> Result<Block, NodeClientError> {
+        let response: BlockResponse = self.call("get_block_by_height", json!({ "height": height })).await?;
+        Ok(response.block)
+    }
+
+    /// Submit a transaction for the mempool and relay
+    pub async fn send_raw_transaction(&self, tx: &Transaction) -> Result<Hash, NodeClientError> {
+        let encoded = bincode::serialize(tx).map_err(|e| NodeClientError::Malformed(e.to_string()))?;
+        let response: Value = self
+            .call("send_raw_transaction", json!({ "tx_as_hex": hex::encode(encoded) }))
+            .await?;
+        response["tx_hash"]
+            .as_str()
+            .and_then(|hash| hex::decode(hash).ok())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| NodeClientError::Malformed("missing tx_hash".to_string()))
+    }
+}
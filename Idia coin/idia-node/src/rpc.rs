@@ -0,0 +1,522 @@
+//! JSON-RPC 2.0 server for wallets and exchanges
+
+use crate::node::{NodeContext, NodeError};
+use crate::telemetry::{self, TelemetryError, REQUEST_ID_HEADER};
+use axum::body::Bytes;
+use axum::extract::{Request as HttpRequest, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use idia_core::shutdown::ShutdownToken;
+use idia_core::{Hash, Transaction, PROTOCOL_VERSION};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use tracing::Instrument;
+
+/// Parse error
+pub const PARSE_ERROR: i64 = -32700;
+/// Invalid request object
+pub const INVALID_REQUEST: i64 = -32600;
+/// Unknown method
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters
+pub const INVALID_PARAMS: i64 = -32602;
+/// Internal error
+pub const INTERNAL_ERROR: i64 = -32603;
+/// Requested block or transaction is unknown
+pub const NOT_FOUND: i64 = -32001;
+/// Submitted transaction was rejected
+pub const REJECTED: i64 = -32002;
+
+/// RPC server configuration
+#[derive(Debug, Clone)]
+pub struct RpcConfig {
+    /// Address to listen on
+    pub bind: SocketAddr,
+    /// Bearer token required of every request, if set
+    pub token: Option<String>,
+    /// Maximum number of calls in one batch
+    pub max_batch: usize,
+}
+
+/// A JSON-RPC error object
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcError {
+    /// Error code
+    pub code: i64,
+    /// Human-readable message
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<NodeError> for RpcError {
+    fn from(e: NodeError) -> Self {
+        Self::new(REJECTED, e.to_string())
+    }
+}
+
+impl From<TelemetryError> for RpcError {
+    fn from(e: TelemetryError) -> Self {
+        match e {
+            TelemetryError::InvalidFilter(_) => Self::new(INVALID_PARAMS, e.to_string()),
+            _ => Self::new(INTERNAL_ERROR, e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    /// Absent for notifications, which get no response
+    id: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+    id: Value,
+}
+
+impl RpcResponse {
+    fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0",
+            result,
+            error,
+            id,
+        }
+    }
+}
+
+/// State shared by RPC handlers
+#[derive(Clone)]
+pub struct RpcState {
+    /// Node components
+    context: NodeContext,
+    /// Server configuration
+    config: RpcConfig,
+    /// Transactions per block, for fee estimation
+    block_capacity: usize,
+    /// Further HTTP routes served alongside JSON-RPC
+    routes: Router,
+}
+
+impl RpcState {
+    /// Create RPC state over the node's components
+    pub fn new(context: NodeContext, config: RpcConfig, block_capacity: usize) -> Self {
+        Self {
+            context,
+            config,
+            block_capacity,
+            routes: Router::new(),
+        }
+    }
+
+    /// Serve `routes` next to `/json_rpc`, behind the same bearer token
+    pub fn with_routes(mut self, routes: Router) -> Self {
+        self.routes = self.routes.merge(routes);
+        self
+    }
+}
+
+/// Routes for the RPC server: calls are POSTed to `/json_rpc`, next to any
+/// routes added with [`RpcState::with_routes`]
+pub fn create_rpc_routes(state: RpcState) -> Router {
+    let token = state.config.token.clone();
+    let routes = state.routes.clone().layer(middleware::from_fn_with_state(token, require_token));
+    Router::new().route("/json_rpc", post(json_rpc)).with_state(state).merge(routes)
+}
+
+/// Serve RPC until shutdown or until the listener fails
+pub async fn serve(state: RpcState, shutdown: ShutdownToken) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(state.config.bind).await?;
+    tracing::info!(bind = %state.config.bind, "JSON-RPC listening");
+    axum::serve(listener, create_rpc_routes(state))
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .await
+}
+
+/// Compare without leaking how long a matching prefix was
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub(crate) fn authorized(headers: &HeaderMap, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+}
+
+async fn require_token(State(token): State<Option<String>>, request: HttpRequest, next: Next) -> Response {
+    if authorized(request.headers(), token.as_deref()) {
+        return next.run(request).await;
+    }
+    tracing::warn!(path = %request.uri().path(), "Unauthorized HTTP request");
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")]).into_response()
+}
+
+async fn json_rpc(State(state): State<RpcState>, headers: HeaderMap, body: Bytes) -> Response {
+    let request_id = telemetry::request_id(&headers);
+    let span = tracing::info_span!("rpc_request", request_id = %request_id);
+    let mut response = async {
+        if !authorized(&headers, state.config.token.as_deref()) {
+            tracing::warn!("Unauthorized RPC request");
+            return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")]).into_response();
+        }
+        match handle_payload(&state, &body).await {
+            Some(response) => Json(response).into_response(),
+            // Only notifications: nothing to say
+            None => StatusCode::NO_CONTENT.into_response(),
+        }
+    }
+    .instrument(span)
+    .await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Handle a single call or a batch, returning `None` when every call was a
+/// notification
+pub async fn handle_payload(state: &RpcState, body: &[u8]) -> Option<Value> {
+    let payload: Value = match serde_json::from_slice(body) {
+        Ok(payload) => payload,
+        Err(e) => return Some(error_response(PARSE_ERROR, e.to_string())),
+    };
+
+    match payload {
+        Value::Array(calls) => {
+            if calls.is_empty() {
+                return Some(error_response(INVALID_REQUEST, "empty batch"));
+            }
+            if calls.len() > state.config.max_batch {
+                return Some(error_response(
+                    INVALID_REQUEST,
+                    format!("batch of {} calls exceeds {}", calls.len(), state.config.max_batch),
+                ));
+            }
+            let mut responses = Vec::new();
+            for call in calls {
+                if let Some(response) = handle_call(state, call).await {
+                    responses.push(response);
+                }
+            }
+            (!responses.is_empty()).then(|| Value::Array(responses))
+        }
+        call => handle_call(state, call).await,
+    }
+}
+
+fn error_response(code: i64, message: impl Into<String>) -> Value {
+    json!(RpcResponse::new(Value::Null, Err(RpcError::new(code, message))))
+}
+
+async fn handle_call(state: &RpcState, call: Value) -> Option<Value> {
+    let request: Request = match serde_json::from_value(call) {
+        Ok(request) => request,
+        Err(e) => return Some(error_response(INVALID_REQUEST, e.to_string())),
+    };
+    if request.jsonrpc != "2.0" {
+        return Some(json!(RpcResponse::new(
+            request.id.unwrap_or(Value::Null),
+            Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""))
+        )));
+    }
+
+    let span = tracing::debug_span!("rpc_call", method = %request.method);
+    let outcome = async {
+        let outcome = dispatch(state, &request.method, request.params).await;
+        match &outcome {
+            Ok(_) => tracing::debug!("Call succeeded"),
+            Err(e) => tracing::debug!(code = e.code, error = %e.message, "Call failed"),
+        }
+        outcome
+    }
+    .instrument(span)
+    .await;
+    request.id.map(|id| json!(RpcResponse::new(id, outcome)))
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn parse_hash(hex_hash: &str) -> Result<Hash, RpcError> {
+    hex::decode(hex_hash)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "hash must be 32 bytes of hex"))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))
+}
+
+#[derive(Deserialize)]
+struct HashParams {
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct HeightParams {
+    height: u64,
+}
+
+#[derive(Deserialize)]
+struct RawTransactionParams {
+    /// Hex of the bincode-encoded transaction
+    tx_as_hex: String,
+}
+
+#[derive(Deserialize)]
+struct DistributionParams {
+    from_height: u64,
+    to_height: Option<u64>,
+    #[serde(default)]
+    cumulative: bool,
+}
+
+#[derive(Deserialize)]
+struct FeeEstimateParams {
+    #[serde(default = "default_target_blocks")]
+    target_blocks: u64,
+}
+
+fn default_target_blocks() -> u64 {
+    1
+}
+
+#[derive(Deserialize)]
+struct LogFilterParams {
+    /// Filter directives, e.g. `info,idia_node::rpc=debug`
+    filter: String,
+}
+
+async fn dispatch(state: &RpcState, method: &str, raw: Value) -> Result<Value, RpcError> {
+    let context = &state.context;
+    match method {
+        "get_info" => {
+            let chain = context.chain.read().await;
+            let mempool = context.mempool.read().await;
+            Ok(json!({
+                "version": PROTOCOL_VERSION,
+                "height": chain.height(),
+                "top_block_hash": hex::encode(chain.tip()),
+                "pruned_below": chain.pruned_below(),
+                "difficulty": chain.next_difficulty(),
+                "tx_pool_size": mempool.len(),
+            }))
+        }
+        "get_block" => {
+            let HashParams { hash } = params(raw)?;
+            let chain = context.chain.read().await;
+            let block_hash = parse_hash(&hash)?;
+            let block = chain.block(&block_hash).ok_or_else(|| match chain.header(&block_hash) {
+                Some(_) => RpcError::new(NOT_FOUND, "block has been pruned"),
+                None => RpcError::new(NOT_FOUND, "block not found"),
+            })?;
+            Ok(json!({ "hash": hash.to_lowercase(), "block": to_json(&block)? }))
+        }
+        "get_block_by_height" => {
+            let HeightParams { height } = params(raw)?;
+            let chain = context.chain.read().await;
+            let block = chain.block_at(height).ok_or_else(|| {
+                if height < chain.pruned_below() {
+                    RpcError::new(NOT_FOUND, "block has been pruned")
+                } else {
+                    RpcError::new(NOT_FOUND, "no block at that height")
+                }
+            })?;
+            Ok(json!({ "hash": hex::encode(block.hash()), "block": to_json(&block)? }))
+        }
+        "get_transaction" => {
+            let HashParams { hash } = params(raw)?;
+            let hash = parse_hash(&hash)?;
+            if let Some(tx) = context.mempool.read().await.get(&hash) {
+                return Ok(json!({ "tx": to_json(tx)?, "in_pool": true }));
+            }
+            let chain = context.chain.read().await;
+            let (tx, block) = chain
+                .transaction(&hash)
+                .ok_or_else(|| RpcError::new(NOT_FOUND, "transaction not found"))?;
+            Ok(json!({
+                "tx": to_json(&tx)?,
+                "in_pool": false,
+                "block_hash": hex::encode(block.hash()),
+                "block_height": block.header.height,
+            }))
+        }
+        "send_raw_transaction" => {
+            let RawTransactionParams { tx_as_hex } = params(raw)?;
+            let tx: Transaction = hex::decode(&tx_as_hex)
+                .ok()
+                .and_then(|bytes| bincode::deserialize(&bytes).ok())
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "tx_as_hex is not an encoded transaction"))?;
+            let hash = context.submit_transaction(tx, true).await?;
+            Ok(json!({ "tx_hash": hex::encode(hash) }))
+        }
+        "get_output_distribution" => {
+            // Outputs created per block, which wallets sample decoys from
+            let DistributionParams { from_height, to_height, cumulative } = params(raw)?;
+            let chain = context.chain.read().await;
+            let to_height = to_height.unwrap_or(chain.height()).min(chain.height());
+            let mut total = 0u64;
+            let mut distribution = Vec::new();
+            for height in from_height..=to_height {
+                // Headers outlive pruning, so this covers the whole chain
+                let outputs = chain.header_at(height).map_or(0, |entry| entry.output_count);
+                total += outputs;
+                distribution.push(if cumulative { total } else { outputs });
+            }
+            Ok(json!({ "start_height": from_height, "distribution": distribution }))
+        }
+        "get_fee_estimate" => {
+            let FeeEstimateParams { target_blocks } = params(raw)?;
+            let fee_rate = context
+                .mempool
+                .read()
+                .await
+                .estimate_fee_rate(target_blocks, state.block_capacity);
+            Ok(json!({ "fee_per_kb": fee_rate, "target_blocks": target_blocks }))
+        }
+        "get_log_filter" => {
+            let filter = telemetry::log_filter().ok_or(TelemetryError::NotInitialized)?;
+            Ok(json!({ "filter": filter }))
+        }
+        "set_log_filter" => {
+            let LogFilterParams { filter } = params(raw)?;
+            telemetry::set_log_filter(&filter)?;
+            Ok(json!({ "filter": filter }))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method {}", method))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{Node, NodeConfig};
+    use idia_core::storage::{StorageBackend, StorageConfig};
+    use idia_core::NetworkConfig;
+    use std::time::Duration;
+
+    async fn state() -> RpcState {
+        let config = NodeConfig {
+            network: NetworkConfig {
+                use_tor: false,
+                tor_proxy: None,
+                listen_addresses: Vec::new(),
+                bootstrap_nodes: Vec::new(),
+                use_dandelion: false,
+            },
+            wallet: None,
+            rpc: None,
+            metrics: None,
+            storage: StorageConfig {
+                backend: StorageBackend::Memory,
+                ..StorageConfig::default()
+            },
+            prune_window: None,
+            mine: false,
+            difficulty: 0,
+            mempool_size: 10,
+            max_block_transactions: 10,
+            shutdown_timeout: Duration::from_secs(1),
+        };
+        let node = Node::new(config).await.unwrap();
+        let rpc = RpcConfig {
+            bind: "127.0.0.1:0".parse().unwrap(),
+            token: None,
+            max_batch: 2,
+        };
+        RpcState::new(node.context(), rpc, 10)
+    }
+
+    #[tokio::test]
+    async fn test_single_and_batch_calls() {
+        let state = state().await;
+
+        let response = handle_payload(&state, br#"{"jsonrpc":"2.0","method":"get_info","id":1}"#).await.unwrap();
+        assert_eq!(response["result"]["height"], 0);
+        assert_eq!(response["result"]["pruned_below"], 0);
+        assert_eq!(response["id"], 1);
+
+        let response = handle_payload(
+            &state,
+            br#"[{"jsonrpc":"2.0","method":"get_block_by_height","params":{"height":0},"id":"a"},
+                {"jsonrpc":"2.0","method":"get_info"}]"#,
+        )
+        .await
+        .unwrap();
+        // The notification gets no response
+        assert_eq!(response.as_array().unwrap().len(), 1);
+        assert_eq!(response[0]["id"], "a");
+
+        let notification = br#"{"jsonrpc":"2.0","method":"get_info"}"#;
+        assert!(handle_payload(&state, notification).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let state = state().await;
+        let code = |response: Value| response["error"]["code"].as_i64().unwrap();
+
+        assert_eq!(code(handle_payload(&state, b"{").await.unwrap()), PARSE_ERROR);
+        assert_eq!(code(handle_payload(&state, b"[]").await.unwrap()), INVALID_REQUEST);
+        let oversized = br#"[{"jsonrpc":"2.0","method":"get_info","id":1},{"jsonrpc":"2.0","method":"get_info","id":2},{"jsonrpc":"2.0","method":"get_info","id":3}]"#;
+        assert_eq!(code(handle_payload(&state, oversized).await.unwrap()), INVALID_REQUEST);
+
+        let call = |body: &'static [u8]| handle_payload(&state, body);
+        assert_eq!(code(call(br#"{"jsonrpc":"2.0","method":"nope","id":1}"#).await.unwrap()), METHOD_NOT_FOUND);
+        assert_eq!(code(call(br#"{"jsonrpc":"2.0","method":"get_block","id":1}"#).await.unwrap()), INVALID_PARAMS);
+        assert_eq!(
+            code(call(br#"{"jsonrpc":"2.0","method":"get_block_by_height","params":{"height":5},"id":1}"#).await.unwrap()),
+            NOT_FOUND
+        );
+        assert_eq!(
+            code(call(br#"{"jsonrpc":"2.0","method":"set_log_filter","params":{"filter":"idia=loud"},"id":1}"#).await.unwrap()),
+            INVALID_PARAMS
+        );
+    }
+
+    #[test]
+    fn test_authorization() {
+        let mut headers = HeaderMap::new();
+        assert!(authorized(&headers, None));
+        assert!(!authorized(&headers, Some("secret")));
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert!(authorized(&headers, Some("secret")));
+        assert!(!authorized(&headers, Some("other")));
+    }
+}
@@ -0,0 +1,228 @@
+//! Supervision of the node's long-running tasks
+
+use idia_core::shutdown::{Shutdown, ShutdownToken};
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+/// Result of one run of a supervised task
+pub type TaskResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// Longest wait between restarts, however often a task has failed
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Default time a task gets to wind down once shutdown begins
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What to do when a supervised task stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Run once
+    Never,
+    /// Restart after a failure, at most `max_restarts` times
+    OnFailure { max_restarts: u32, backoff: Duration },
+    /// Restart whenever the task stops, whether or not it failed
+    Always { backoff: Duration },
+}
+
+impl RestartPolicy {
+    /// Delay before restart number `restarts`, doubling each time
+    fn delay(&self, restarts: u32) -> Duration {
+        let backoff = match self {
+            Self::Never => return Duration::ZERO,
+            Self::OnFailure { backoff, .. } | Self::Always { backoff } => *backoff,
+        };
+        backoff.saturating_mul(1 << restarts.min(16)).min(MAX_BACKOFF)
+    }
+}
+
+/// Runs tasks under restart policies and stops them all on shutdown.
+///
+/// Each task is handed a [`ShutdownToken`] and, once shutdown begins, gets
+/// up to the shutdown timeout to flush its state and return before it is
+/// dropped. A critical task that stops for good brings the whole node down,
+/// since the node is not useful without it.
+pub struct Supervisor {
+    /// Triggered once shutdown has begun
+    shutdown: Shutdown,
+    /// How long a task may take to stop after shutdown begins
+    shutdown_timeout: Duration,
+    /// Running tasks by name
+    tasks: Vec<(String, JoinHandle<()>)>,
+}
+
+impl Supervisor {
+    /// Create a supervisor with no tasks
+    pub fn new() -> Self {
+        Self {
+            shutdown: Shutdown::new(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Set how long each task may take to stop after shutdown begins
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Token that fires when shutdown begins
+    pub fn shutdown_signal(&self) -> ShutdownToken {
+        self.shutdown.token()
+    }
+
+    /// Begin shutdown: running tasks are told to stop and none restart
+    pub fn shutdown(&self) {
+        self.shutdown.trigger();
+    }
+
+    /// Spawn a task, calling `task` again for each restart with a token
+    /// that fires on shutdown
+    pub fn spawn<F, Fut>(&mut self, name: &str, policy: RestartPolicy, critical: bool, mut task: F)
+    where
+        F: FnMut(ShutdownToken) -> Fut + Send + 'static,
+        Fut: Future<Output = TaskResult> + Send + 'static,
+    {
+        let name = name.to_string();
+        let shutdown = self.shutdown.clone();
+        let token = self.shutdown.token();
+        let timeout = self.shutdown_timeout;
+        // Everything a task logs carries its name
+        let span = tracing::info_span!("task", name = %name);
+
+        let handle = tokio::spawn(async move {
+            let mut restarts = 0u32;
+            while !token.is_shutdown() {
+                tracing::info!("Starting task");
+                let run = task(token.clone());
+                tokio::pin!(run);
+                let result = tokio::select! {
+                    result = &mut run => result,
+                    // Give the task its grace period to flush and return
+                    _ = token.wait() => match tokio::time::timeout(timeout, &mut run).await {
+                        Ok(result) => result,
+                        Err(_) => Err(format!("did not stop within {:?}", timeout).into()),
+                    },
+                };
+                if token.is_shutdown() {
+                    match result {
+                        Ok(()) => tracing::info!("Task stopped"),
+                        Err(e) => tracing::warn!(error = %e, "Task stopped uncleanly"),
+                    }
+                    break;
+                }
+
+                let restart = match (&result, policy) {
+                    (_, RestartPolicy::Always { .. }) => true,
+                    (Err(_), RestartPolicy::OnFailure { max_restarts, .. }) => restarts < max_restarts,
+                    _ => false,
+                };
+                match result {
+                    Ok(()) => tracing::info!("Task finished"),
+                    Err(e) => tracing::error!(error = %e, "Task failed"),
+                }
+                if !restart {
+                    break;
+                }
+
+                let delay = policy.delay(restarts);
+                restarts += 1;
+                tracing::warn!(?delay, restarts, "Restarting task");
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = token.wait() => break,
+                }
+            }
+
+            if critical && !shutdown.is_triggered() {
+                tracing::error!("Critical task stopped, shutting down");
+                shutdown.trigger();
+            }
+        }
+        .instrument(span));
+        self.tasks.push((name, handle));
+    }
+
+    /// Wait until shutdown begins, from `shutdown` or a critical task stopping
+    pub async fn wait(&self) {
+        self.shutdown.token().wait().await;
+    }
+
+    /// Wait for every task to stop after shutdown
+    pub async fn join(self) {
+        for (name, handle) in self.tasks {
+            if let Err(e) = handle.await {
+                tracing::error!(task = %name, error = %e, "Task panicked");
+            }
+        }
+    }
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_restart_on_failure() {
+        let mut supervisor = Supervisor::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        let policy = RestartPolicy::OnFailure { max_restarts: 2, backoff: Duration::from_millis(1) };
+        supervisor.spawn("flaky", policy, true, move |_| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Err("boom".into())
+            }
+        });
+
+        // Gives up after two restarts and, being critical, shuts the node down
+        supervisor.wait().await;
+        supervisor.join().await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_tasks() {
+        let mut supervisor = Supervisor::new().with_shutdown_timeout(Duration::from_millis(10));
+        supervisor.spawn("forever", RestartPolicy::Never, false, |_| async {
+            std::future::pending::<()>().await;
+            Ok(())
+        });
+        supervisor.shutdown();
+        supervisor.join().await;
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_lets_tasks_flush() {
+        let mut supervisor = Supervisor::new();
+        let flushed = Arc::new(AtomicU32::new(0));
+        let started = Arc::new(tokio::sync::Notify::new());
+        let (counter, running) = (flushed.clone(), started.clone());
+        supervisor.spawn("store", RestartPolicy::Always { backoff: Duration::ZERO }, true, move |token| {
+            let (counter, running) = (counter.clone(), running.clone());
+            async move {
+                running.notify_one();
+                token.wait().await;
+                tokio::task::yield_now().await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        started.notified().await;
+        supervisor.shutdown();
+        supervisor.join().await;
+        // Flushed once and, with shutdown underway, not restarted
+        assert_eq!(flushed.load(Ordering::SeqCst), 1);
+    }
+}
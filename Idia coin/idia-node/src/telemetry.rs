@@ -0,0 +1,137 @@
+//! Tracing setup: the subscriber, a per-module filter that can be changed
+//! while running, and request IDs for the RPC servers
+
+use axum::http::HeaderMap;
+use idia_core::config::{ConfigError, IdiaConfig};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+/// Header carrying a request's ID, echoed back in the response
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Telemetry error types
+#[derive(Debug, thiserror::Error)]
+pub enum TelemetryError {
+    #[error("Invalid log filter: {0}")]
+    InvalidFilter(String),
+    #[error("Logging is not initialized")]
+    NotInitialized,
+    #[error("Failed to install subscriber: {0}")]
+    Install(String),
+}
+
+/// `[logging]`: what gets logged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingSection {
+    /// Filter directives, e.g. `info,idia_node::rpc=debug,idia_core::network=warn`
+    pub filter: String,
+}
+
+impl Default for LoggingSection {
+    fn default() -> Self {
+        Self {
+            filter: "info".to_string(),
+        }
+    }
+}
+
+impl LoggingSection {
+    /// The `[logging]` section, or defaults
+    pub fn from_config(config: &IdiaConfig) -> Result<Self, ConfigError> {
+        let section: Self = config.section("logging")?.unwrap_or_default();
+        if let Err(e) = EnvFilter::try_new(&section.filter) {
+            return Err(ConfigError::invalid("logging.filter", e.to_string()));
+        }
+        Ok(section)
+    }
+}
+
+struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    directives: Mutex<String>,
+}
+
+static LOG_FILTER: OnceLock<LogFilter> = OnceLock::new();
+
+/// Install the global subscriber, logging what `filter` allows
+pub fn init(filter: &str) -> Result<(), TelemetryError> {
+    let env_filter = EnvFilter::try_new(filter).map_err(|e| TelemetryError::InvalidFilter(e.to_string()))?;
+    let (filter_layer, handle) = reload::Layer::new(env_filter);
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(fmt::layer())
+        .try_init()
+        .map_err(|e| TelemetryError::Install(e.to_string()))?;
+    let _ = LOG_FILTER.set(LogFilter {
+        handle,
+        directives: Mutex::new(filter.to_string()),
+    });
+    Ok(())
+}
+
+/// The filter in force, if `init` has run
+pub fn log_filter() -> Option<String> {
+    LOG_FILTER.get().map(|filter| filter.directives.lock().unwrap().clone())
+}
+
+/// Replace the filter without restarting
+pub fn set_log_filter(filter: &str) -> Result<(), TelemetryError> {
+    let env_filter = EnvFilter::try_new(filter).map_err(|e| TelemetryError::InvalidFilter(e.to_string()))?;
+    let current = LOG_FILTER.get().ok_or(TelemetryError::NotInitialized)?;
+    current
+        .handle
+        .reload(env_filter)
+        .map_err(|_| TelemetryError::NotInitialized)?;
+    *current.directives.lock().unwrap() = filter.to_string();
+    tracing::info!(filter, "Log filter changed");
+    Ok(())
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The caller's request ID if it sent a usable one, else a fresh one
+pub fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 64 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:x}-{}", std::process::id(), NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_request_id() {
+        let mut headers = HeaderMap::new();
+        let first = request_id(&headers);
+        assert_ne!(first, request_id(&headers));
+
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("client-42"));
+        assert_eq!(request_id(&headers), "client-42");
+
+        // Anything that could garble a log line is replaced
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("two words"));
+        assert_ne!(request_id(&headers), "two words");
+    }
+
+    #[test]
+    fn test_logging_section() {
+        assert_eq!(LoggingSection::from_config(&IdiaConfig::default()).unwrap().filter, "info");
+
+        let mut config = IdiaConfig::default();
+        config.sections.insert(
+            "logging".to_string(),
+            toml::Value::Table(toml::toml! { filter = "info,idia_node::rpc=nonsense" }),
+        );
+        assert!(LoggingSection::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! The UTXO set: outputs created on the best chain and the key images spent
+
+use crate::chain::key_images;
+use idia_core::storage::{columns, get_value, ColumnStore, StorageError, WriteBatch};
+use idia_core::{Block, Output, OutputReference, PedersenCommitment, RangeProofWrapper};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Store key of an output: the transaction hash, then the big-endian index,
+/// so a transaction's outputs sit together in order
+pub fn output_key(outref: &OutputReference) -> Vec<u8> {
+    let mut key = outref.tx_hash.to_vec();
+    key.extend_from_slice(&outref.output_index.to_be_bytes());
+    key
+}
+
+/// An output in the UTXO set. Its range proof is kept apart, since it is
+/// only needed to validate the output and is dropped when pruning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UtxoEntry {
+    /// Pedersen commitment to the amount
+    pub commitment: PedersenCommitment,
+    /// One-time public key, compressed
+    pub stealth_pubkey: [u8; 32],
+    /// Transaction public key, compressed
+    pub tx_pubkey: [u8; 32],
+    /// Height of the block that created the output
+    pub height: u64,
+}
+
+impl UtxoEntry {
+    fn new(output: &Output, height: u64) -> Self {
+        Self {
+            commitment: output.commitment.clone(),
+            stealth_pubkey: output.stealth_pubkey.compress().to_bytes(),
+            tx_pubkey: output.tx_pubkey.compress().to_bytes(),
+            height,
+        }
+    }
+}
+
+/// References to the outputs of every transaction in `block`
+fn block_outputs(block: &Block) -> impl Iterator<Item = (OutputReference, &Output)> + '_ {
+    block.transactions.iter().flat_map(|tx| {
+        let tx_hash = tx.hash();
+        tx.outputs.iter().enumerate().map(move |(index, output)| {
+            let outref = OutputReference {
+                tx_hash,
+                output_index: index as u32,
+            };
+            (outref, output)
+        })
+    })
+}
+
+/// Outputs and spent key images of the best chain, kept in a shared store.
+///
+/// Ring signatures hide which output an input spends, so outputs stay in
+/// the set once created and spending is tracked by key image instead.
+#[derive(Clone)]
+pub struct UtxoSet {
+    store: Arc<dyn ColumnStore>,
+}
+
+impl UtxoSet {
+    /// The UTXO set kept in `store`
+    pub fn new(store: Arc<dyn ColumnStore>) -> Self {
+        Self { store }
+    }
+
+    /// Stage the outputs `block` creates and the key images it spends, to
+    /// be written with the rest of the block
+    pub fn apply_block(&self, batch: &mut WriteBatch, block: &Block) -> Result<(), StorageError> {
+        let height = block.header.height;
+        for (outref, output) in block_outputs(block) {
+            let key = output_key(&outref);
+            batch.put_value(columns::UTXOS, key.clone(), &UtxoEntry::new(output, height))?;
+            batch.put_value(columns::RANGE_PROOFS, key, &output.range_proof)?;
+        }
+        for tx in &block.transactions {
+            for image in key_images(tx) {
+                batch.put_value(columns::KEY_IMAGES, image.to_vec(), &height)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stage dropping the range proofs of the outputs `block` created.
+    ///
+    /// Which of them are spent can't be told, so every proof goes once the
+    /// block leaves the retention window; the proofs were checked when the
+    /// block was connected and nothing reads them after that.
+    pub fn prune_block(&self, batch: &mut WriteBatch, block: &Block) {
+        for (outref, _) in block_outputs(block) {
+            batch.delete(columns::RANGE_PROOFS, output_key(&outref));
+        }
+    }
+
+    /// Get an output created on the best chain
+    pub fn output(&self, outref: &OutputReference) -> Result<Option<UtxoEntry>, StorageError> {
+        get_value(self.store.as_ref(), columns::UTXOS, &output_key(outref))
+    }
+
+    /// Get the range proof of an output, unless it has been pruned
+    pub fn range_proof(&self, outref: &OutputReference) -> Result<Option<RangeProofWrapper>, StorageError> {
+        get_value(self.store.as_ref(), columns::RANGE_PROOFS, &output_key(outref))
+    }
+
+    /// Height of the block that spent a key image, if it is spent
+    pub fn spent_at(&self, key_image: &[u8; 32]) -> Result<Option<u64>, StorageError> {
+        get_value(self.store.as_ref(), columns::KEY_IMAGES, key_image)
+    }
+}
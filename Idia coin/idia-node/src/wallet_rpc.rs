@@ -0,0 +1,277 @@
+//! HTTP/JSON wallet server, separate from the node RPC so custody
+//! automation never needs node credentials
+
+use crate::rpc::authorized;
+use crate::telemetry::{self, REQUEST_ID_HEADER};
+use crate::wallet_service::{WalletService, WalletServiceError};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use idia_core::shutdown::ShutdownToken;
+use idia_core::TransferDirection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::Instrument;
+
+/// Wallet RPC server configuration
+#[derive(Debug, Clone)]
+pub struct WalletRpcConfig {
+    /// Address to listen on
+    pub bind: SocketAddr,
+    /// Bearer token required of every request, if set
+    pub token: Option<String>,
+}
+
+/// State shared by wallet RPC handlers
+#[derive(Clone)]
+pub struct WalletRpcState {
+    service: Arc<WalletService>,
+    config: WalletRpcConfig,
+}
+
+impl WalletRpcState {
+    /// Create state over a wallet service
+    pub fn new(service: Arc<WalletService>, config: WalletRpcConfig) -> Self {
+        Self { service, config }
+    }
+}
+
+impl IntoResponse for WalletServiceError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            WalletServiceError::NoWalletOpen | WalletServiceError::WalletExists(_) | WalletServiceError::NodePruned(_) => {
+                StatusCode::CONFLICT
+            }
+            WalletServiceError::InvalidName
+            | WalletServiceError::InvalidKeys
+            | WalletServiceError::InvalidAddress
+            | WalletServiceError::InvalidSignature => StatusCode::BAD_REQUEST,
+            WalletServiceError::Node(_) => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+type WalletResult = Result<Json<Value>, WalletServiceError>;
+
+#[derive(Debug, Deserialize)]
+struct OpenWalletRequest {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RestoreWalletRequest {
+    name: String,
+    keys: String,
+    #[serde(default)]
+    restore_height: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateAddressRequest {
+    #[serde(default)]
+    label: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransferRequest {
+    address: String,
+    amount: u64,
+    fee: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SweepRequest {
+    address: String,
+    fee: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignRequest {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyRequest {
+    address: String,
+    message: String,
+    signature: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TransferView {
+    tx_hash: String,
+    height: u64,
+    direction: TransferDirection,
+    amount: u64,
+    fee: u64,
+    address_index: Option<u32>,
+}
+
+/// Routes for the wallet server: one POST endpoint per operation
+pub fn create_wallet_routes(state: WalletRpcState) -> Router {
+    Router::new()
+        .route("/open_wallet", post(open_wallet))
+        .route("/create_wallet", post(create_wallet))
+        .route("/restore_wallet", post(restore_wallet))
+        .route("/close_wallet", post(close_wallet))
+        .route("/get_balance", post(get_balance))
+        .route("/get_keys", post(get_keys))
+        .route("/create_address", post(create_address))
+        .route("/get_addresses", post(get_addresses))
+        .route("/transfer", post(transfer))
+        .route("/sweep", post(sweep))
+        .route("/get_transfers", post(get_transfers))
+        .route("/export_key_images", post(export_key_images))
+        .route("/sign", post(sign))
+        .route("/verify", post(verify))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .layer(middleware::from_fn(trace_request))
+        .with_state(state)
+}
+
+/// Serve the wallet RPC until shutdown or until the listener fails
+pub async fn serve(state: WalletRpcState, shutdown: ShutdownToken) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(state.config.bind).await?;
+    tracing::info!(bind = %state.config.bind, "Wallet RPC listening");
+    axum::serve(listener, create_wallet_routes(state))
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .await
+}
+
+// Runs each request in a span carrying its ID, and echoes the ID back
+async fn trace_request(request: Request, next: Next) -> Response {
+    let request_id = telemetry::request_id(request.headers());
+    let span = tracing::info_span!("wallet_rpc_request", request_id = %request_id, path = %request.uri().path());
+    let mut response = async {
+        let response = next.run(request).await;
+        tracing::debug!(status = response.status().as_u16(), "Request handled");
+        response
+    }
+    .instrument(span)
+    .await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+async fn require_token(State(state): State<WalletRpcState>, headers: HeaderMap, request: Request, next: Next) -> Response {
+    if !authorized(&headers, state.config.token.as_deref()) {
+        tracing::warn!("Unauthorized wallet RPC request");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+async fn open_wallet(State(state): State<WalletRpcState>, Json(request): Json<OpenWalletRequest>) -> WalletResult {
+    state.service.open_wallet(&request.name).await?;
+    Ok(Json(json!({})))
+}
+
+async fn create_wallet(State(state): State<WalletRpcState>, Json(request): Json<OpenWalletRequest>) -> WalletResult {
+    state.service.create_wallet(&request.name).await?;
+    Ok(Json(json!({})))
+}
+
+async fn restore_wallet(State(state): State<WalletRpcState>, Json(request): Json<RestoreWalletRequest>) -> WalletResult {
+    state
+        .service
+        .restore_wallet(&request.name, &request.keys, request.restore_height)
+        .await?;
+    Ok(Json(json!({})))
+}
+
+async fn get_balance(State(state): State<WalletRpcState>) -> WalletResult {
+    let balance = state.service.get_balance().await?;
+    Ok(Json(json!({ "balance": balance.transparent, "pool_balance": balance.pool })))
+}
+
+async fn get_keys(State(state): State<WalletRpcState>) -> WalletResult {
+    Ok(Json(json!({ "keys": state.service.get_keys().await? })))
+}
+
+async fn close_wallet(State(state): State<WalletRpcState>) -> WalletResult {
+    state.service.close_wallet().await?;
+    Ok(Json(json!({})))
+}
+
+async fn create_address(State(state): State<WalletRpcState>, Json(request): Json<CreateAddressRequest>) -> WalletResult {
+    let (index, address) = state.service.create_address(&request.label).await?;
+    Ok(Json(json!({ "address_index": index, "address": address })))
+}
+
+async fn get_addresses(State(state): State<WalletRpcState>) -> WalletResult {
+    let addresses: Vec<Value> = state
+        .service
+        .get_addresses()
+        .await?
+        .into_iter()
+        .map(|(index, label, address)| json!({ "address_index": index, "label": label, "address": address }))
+        .collect();
+    Ok(Json(json!({ "addresses": addresses })))
+}
+
+async fn transfer(State(state): State<WalletRpcState>, Json(request): Json<TransferRequest>) -> WalletResult {
+    let hash = state.service.transfer(&request.address, request.amount, request.fee).await?;
+    Ok(Json(json!({ "tx_hash": hex::encode(hash) })))
+}
+
+async fn sweep(State(state): State<WalletRpcState>, Json(request): Json<SweepRequest>) -> WalletResult {
+    let (hash, amount) = state.service.sweep(&request.address, request.fee).await?;
+    Ok(Json(json!({ "tx_hash": hex::encode(hash), "amount": amount })))
+}
+
+async fn get_transfers(State(state): State<WalletRpcState>) -> WalletResult {
+    let transfers: Vec<TransferView> = state
+        .service
+        .get_transfers()
+        .await?
+        .into_iter()
+        .map(|transfer| TransferView {
+            tx_hash: hex::encode(transfer.tx_hash),
+            height: transfer.height,
+            direction: transfer.direction,
+            amount: transfer.amount,
+            fee: transfer.fee,
+            address_index: transfer.address_index,
+        })
+        .collect();
+    Ok(Json(json!({ "transfers": transfers })))
+}
+
+async fn export_key_images(State(state): State<WalletRpcState>) -> WalletResult {
+    let key_images: Vec<Value> = state
+        .service
+        .export_key_images()
+        .await?
+        .into_iter()
+        .map(|(output, key_image)| {
+            json!({
+                "tx_hash": hex::encode(output.tx_hash),
+                "output_index": output.output_index,
+                "key_image": hex::encode(key_image.0.as_bytes()),
+            })
+        })
+        .collect();
+    Ok(Json(json!({ "key_images": key_images })))
+}
+
+async fn sign(State(state): State<WalletRpcState>, Json(request): Json<SignRequest>) -> WalletResult {
+    let signature = state.service.sign(request.message.as_bytes()).await?;
+    Ok(Json(json!({ "signature": signature })))
+}
+
+async fn verify(State(state): State<WalletRpcState>, Json(request): Json<VerifyRequest>) -> WalletResult {
+    let good = state
+        .service
+        .verify(&request.address, request.message.as_bytes(), &request.signature)?;
+    Ok(Json(json!({ "good": good })))
+}
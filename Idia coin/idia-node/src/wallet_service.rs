@@ -0,0 +1,388 @@
+//! Façade over one open wallet at a time, used by the wallet RPC server
+
+use crate::node_client::{NodeClient, NodeClientError};
+use idia_core::storage::{columns, ColumnStore, RocksStore, StorageError};
+use idia_core::{
+    Balance, Hash, KeyImage, NetworkType, OutputReference, SchnorrSignature, StealthAddress, Transfer, Wallet, WalletConfig,
+    WalletError,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Columns of each wallet's own database
+const WALLET_COLUMNS: &[&str] = &[
+    columns::WALLET_OUTPUTS,
+    columns::WALLET_SPENT,
+    columns::WALLET_TRANSFERS,
+    columns::WALLET_META,
+];
+
+/// Wallet service error types
+#[derive(Debug, thiserror::Error)]
+pub enum WalletServiceError {
+    #[error("No wallet is open")]
+    NoWalletOpen,
+    #[error("Invalid wallet name")]
+    InvalidName,
+    #[error("Wallet {0} already exists")]
+    WalletExists(String),
+    #[error("Invalid wallet keys")]
+    InvalidKeys,
+    #[error("Invalid address")]
+    InvalidAddress,
+    #[error("Invalid signature encoding")]
+    InvalidSignature,
+    #[error("Node is pruned below height {0}; restore the wallet with a restore height of at least {0}")]
+    NodePruned(u64),
+    #[error("Wallet error: {0}")]
+    Wallet(#[from] WalletError),
+    #[error("Node error: {0}")]
+    Node(#[from] NodeClientError),
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Wallet service configuration
+#[derive(Debug, Clone)]
+pub struct WalletServiceConfig {
+    /// Directory holding one subdirectory per wallet
+    pub wallets_dir: PathBuf,
+    /// Network the wallets are for
+    pub network: NetworkType,
+    /// Ring size for transactions
+    pub ring_size: usize,
+}
+
+/// Encode an address's public keys for payers
+pub fn encode_address(address: &StealthAddress) -> String {
+    hex::encode(address.to_public_bytes())
+}
+
+/// Decode an address produced by `encode_address`
+pub fn decode_address(encoded: &str) -> Result<StealthAddress, WalletServiceError> {
+    let bytes: [u8; 64] = hex::decode(encoded)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(WalletServiceError::InvalidAddress)?;
+    StealthAddress::from_public_bytes(&bytes).map_err(|_| WalletServiceError::InvalidAddress)
+}
+
+/// What the service keeps alongside a wallet's keys
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WalletMeta {
+    /// Label of each subaddress, by index
+    labels: Vec<String>,
+    /// Height the wallet's history starts at; earlier blocks are not scanned
+    #[serde(default)]
+    restore_height: u64,
+}
+
+struct OpenWallet {
+    name: String,
+    wallet: Wallet,
+    meta: WalletMeta,
+    /// Database holding the wallet's outputs and transfers
+    store: Arc<dyn ColumnStore>,
+    /// Last block processed; wallets resume from here when reopened
+    synced_height: Option<u64>,
+}
+
+impl OpenWallet {
+    fn meta_path(wallets_dir: &Path, name: &str) -> PathBuf {
+        wallets_dir.join(name).join("meta.json")
+    }
+
+    fn state_path(wallets_dir: &Path, name: &str) -> PathBuf {
+        wallets_dir.join(name).join("state")
+    }
+
+    fn save_meta(&self, wallets_dir: &Path) -> Result<(), WalletServiceError> {
+        let path = Self::meta_path(wallets_dir, &self.name);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.meta)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Opens, closes and operates on wallets for custody automation. One wallet
+/// is open at a time; opening another closes it.
+pub struct WalletService {
+    config: WalletServiceConfig,
+    node: NodeClient,
+    open: RwLock<Option<OpenWallet>>,
+}
+
+impl WalletService {
+    /// Create a service with no wallet open
+    pub fn new(config: WalletServiceConfig, node: NodeClient) -> Self {
+        Self {
+            config,
+            node,
+            open: RwLock::new(None),
+        }
+    }
+
+    fn wallet_config(&self, name: &str) -> Result<WalletConfig, WalletServiceError> {
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(WalletServiceError::InvalidName);
+        }
+        Ok(WalletConfig {
+            data_dir: self.config.wallets_dir.join(name),
+            network: self.config.network,
+            ring_size: self.config.ring_size,
+        })
+    }
+
+    fn ensure_new(&self, config: &WalletConfig, name: &str) -> Result<(), WalletServiceError> {
+        if config.data_dir.join("wallet.key").exists() {
+            return Err(WalletServiceError::WalletExists(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Open the wallet called `name`, creating it if it does not exist
+    pub async fn open_wallet(&self, name: &str) -> Result<(), WalletServiceError> {
+        let config = self.wallet_config(name)?;
+        self.install(name, Wallet::new(config).await?, None).await
+    }
+
+    /// Create and open a new wallet, failing if `name` is taken. Nothing can
+    /// have been paid to it yet, so it starts scanning at the node's tip.
+    pub async fn create_wallet(&self, name: &str) -> Result<(), WalletServiceError> {
+        let config = self.wallet_config(name)?;
+        self.ensure_new(&config, name)?;
+        let restore_height = self.node.get_info().await?.height;
+        self.install(name, Wallet::new(config).await?, Some(restore_height)).await
+    }
+
+    /// Restore a wallet from keys exported by `get_keys` and open it. Its
+    /// history is rebuilt as the service syncs from `restore_height`, which
+    /// must not be below the blocks a pruned node has dropped.
+    pub async fn restore_wallet(&self, name: &str, keys: &str, restore_height: u64) -> Result<(), WalletServiceError> {
+        let address = hex::decode(keys)
+            .ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .and_then(|bytes| StealthAddress::from_secret_bytes(&bytes).ok())
+            .ok_or(WalletServiceError::InvalidKeys)?;
+        let config = self.wallet_config(name)?;
+        self.ensure_new(&config, name)?;
+        let pruned_below = self.node.get_info().await?.pruned_below;
+        sync_start(None, restore_height, pruned_below)?;
+        self.install(name, Wallet::restore(config, address).await?, Some(restore_height)).await
+    }
+
+    // Replaces whatever wallet is open with `wallet`, loading its state.
+    // The open wallet is closed first, as it may hold the same database.
+    async fn install(&self, name: &str, wallet: Wallet, restore_height: Option<u64>) -> Result<(), WalletServiceError> {
+        self.close_wallet().await.or_else(|e| match e {
+            WalletServiceError::NoWalletOpen => Ok(()),
+            e => Err(e),
+        })?;
+
+        let state_path = OpenWallet::state_path(&self.config.wallets_dir, name);
+        let store: Arc<dyn ColumnStore> = Arc::new(RocksStore::open(&state_path, WALLET_COLUMNS)?);
+        let wallet = wallet.with_store(store.clone()).await?;
+
+        let meta_path = OpenWallet::meta_path(&self.config.wallets_dir, name);
+        let mut meta: WalletMeta = match std::fs::read(&meta_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => WalletMeta {
+                labels: vec!["primary".to_string()],
+                restore_height: 0,
+            },
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(restore_height) = restore_height {
+            meta.restore_height = restore_height;
+        }
+        // Subaddresses are deterministic, so only their number is kept
+        while wallet.address_count().await < meta.labels.len() as u32 {
+            wallet.create_address().await?;
+        }
+
+        let open = OpenWallet {
+            name: name.to_string(),
+            synced_height: wallet.synced_height().await,
+            wallet,
+            meta,
+            store,
+        };
+        open.save_meta(&self.config.wallets_dir)?;
+        tracing::info!(wallet = name, "Opened wallet");
+        *self.open.write().await = Some(open);
+        Ok(())
+    }
+
+    /// Close the open wallet
+    pub async fn close_wallet(&self) -> Result<(), WalletServiceError> {
+        let open = self.open.write().await.take().ok_or(WalletServiceError::NoWalletOpen)?;
+        open.save_meta(&self.config.wallets_dir)?;
+        open.store.flush()?;
+        tracing::info!(wallet = %open.name, "Closed wallet");
+        Ok(())
+    }
+
+    /// Create a labelled subaddress, returning its index and encoding
+    pub async fn create_address(&self, label: &str) -> Result<(u32, String), WalletServiceError> {
+        let mut guard = self.open.write().await;
+        let open = guard.as_mut().ok_or(WalletServiceError::NoWalletOpen)?;
+        let (index, address) = open.wallet.create_address().await?;
+        open.meta.labels.push(label.to_string());
+        open.save_meta(&self.config.wallets_dir)?;
+        Ok((index, encode_address(&address)))
+    }
+
+    /// Labelled subaddresses of the open wallet
+    pub async fn get_addresses(&self) -> Result<Vec<(u32, String, String)>, WalletServiceError> {
+        let guard = self.open.read().await;
+        let open = guard.as_ref().ok_or(WalletServiceError::NoWalletOpen)?;
+        let mut addresses = Vec::new();
+        for (index, label) in open.meta.labels.iter().enumerate() {
+            if let Some(address) = open.wallet.get_address_at(index as u32).await {
+                addresses.push((index as u32, label.clone(), encode_address(&address)));
+            }
+        }
+        Ok(addresses)
+    }
+
+    /// Spendable and Spark pool balance of the open wallet
+    pub async fn get_balance(&self) -> Result<Balance, WalletServiceError> {
+        let guard = self.open.read().await;
+        let open = guard.as_ref().ok_or(WalletServiceError::NoWalletOpen)?;
+        Ok(open.wallet.get_balances().await)
+    }
+
+    /// Secret keys of the open wallet, hex encoded, for backup and `restore_wallet`
+    pub async fn get_keys(&self) -> Result<String, WalletServiceError> {
+        let guard = self.open.read().await;
+        let open = guard.as_ref().ok_or(WalletServiceError::NoWalletOpen)?;
+        Ok(hex::encode(open.wallet.get_address()?.to_secret_bytes()))
+    }
+
+    /// Pay `amount` to an encoded address and submit the transaction to the node
+    pub async fn transfer(&self, destination: &str, amount: u64, fee: u64) -> Result<Hash, WalletServiceError> {
+        let recipient = decode_address(destination)?;
+        let guard = self.open.read().await;
+        let open = guard.as_ref().ok_or(WalletServiceError::NoWalletOpen)?;
+        let tx = open.wallet.create_transaction(&recipient, amount, fee).await?;
+        let hash = self.node.send_raw_transaction(&tx).await?;
+        tracing::info!(wallet = %open.name, amount, tx = %hex::encode(hash), "Sent transfer");
+        Ok(hash)
+    }
+
+    /// Send the whole spendable balance less `fee` to an encoded address
+    pub async fn sweep(&self, destination: &str, fee: u64) -> Result<(Hash, u64), WalletServiceError> {
+        let balance = self.get_balance().await?.transparent;
+        let amount = balance.checked_sub(fee).filter(|amount| *amount > 0).ok_or(WalletError::InsufficientFunds)?;
+        let hash = self.transfer(destination, amount, fee).await?;
+        Ok((hash, amount))
+    }
+
+    /// Transfers in and out of the open wallet
+    pub async fn get_transfers(&self) -> Result<Vec<Transfer>, WalletServiceError> {
+        let guard = self.open.read().await;
+        let open = guard.as_ref().ok_or(WalletServiceError::NoWalletOpen)?;
+        Ok(open.wallet.get_transfers().await)
+    }
+
+    /// Key images of the open wallet's unspent outputs
+    pub async fn export_key_images(&self) -> Result<Vec<(OutputReference, KeyImage)>, WalletServiceError> {
+        let guard = self.open.read().await;
+        let open = guard.as_ref().ok_or(WalletServiceError::NoWalletOpen)?;
+        Ok(open.wallet.export_key_images().await)
+    }
+
+    /// Sign a message with the open wallet's primary address
+    pub async fn sign(&self, message: &[u8]) -> Result<String, WalletServiceError> {
+        let guard = self.open.read().await;
+        let open = guard.as_ref().ok_or(WalletServiceError::NoWalletOpen)?;
+        Ok(hex::encode(open.wallet.sign_message(message)?.to_bytes()))
+    }
+
+    /// Check a signature made by `sign`; needs no open wallet
+    pub fn verify(&self, address: &str, message: &[u8], signature: &str) -> Result<bool, WalletServiceError> {
+        let address = decode_address(address)?;
+        let signature = hex::decode(signature)
+            .ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .and_then(|bytes| SchnorrSignature::from_bytes(&bytes).ok())
+            .ok_or(WalletServiceError::InvalidSignature)?;
+        Ok(Wallet::verify_message(&address, message, &signature))
+    }
+
+    /// Process blocks the node has that the open wallet has not seen,
+    /// returning the height synced to
+    #[tracing::instrument(name = "wallet_sync", skip_all, fields(wallet, tip))]
+    pub async fn sync(&self) -> Result<Option<u64>, WalletServiceError> {
+        let info = self.node.get_info().await?;
+        let tip = info.height;
+        let mut guard = self.open.write().await;
+        let Some(open) = guard.as_mut() else {
+            return Ok(None);
+        };
+        let span = tracing::Span::current();
+        span.record("wallet", open.name.as_str());
+        span.record("tip", tip);
+
+        let from = sync_start(open.synced_height, open.meta.restore_height, info.pruned_below)?;
+        for height in from..=tip {
+            let block = self.node.get_block_by_height(height).await?;
+            open.wallet.process_block(&block).await?;
+            open.synced_height = Some(height);
+        }
+        if from <= tip {
+            tracing::debug!(from, "Synced wallet");
+        }
+        Ok(open.synced_height)
+    }
+}
+
+// First height the wallet still has to scan. Blocks the node has pruned are
+// never skipped, since outputs in them would be missed for good.
+fn sync_start(synced_height: Option<u64>, restore_height: u64, pruned_below: u64) -> Result<u64, WalletServiceError> {
+    let from = synced_height.map_or(restore_height, |height| height + 1);
+    if from < pruned_below {
+        return Err(WalletServiceError::NodePruned(pruned_below));
+    }
+    Ok(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_encoding() {
+        let address = StealthAddress::new();
+        let decoded = decode_address(&encode_address(&address)).unwrap();
+        assert_eq!(decoded.to_public_bytes(), address.to_public_bytes());
+
+        assert!(matches!(decode_address("00"), Err(WalletServiceError::InvalidAddress)));
+        assert!(matches!(decode_address("not hex"), Err(WalletServiceError::InvalidAddress)));
+    }
+
+    #[test]
+    fn test_sync_start_on_pruned_node() {
+        // An archive node serves everything from genesis
+        assert_eq!(sync_start(None, 0, 0).unwrap(), 0);
+        assert_eq!(sync_start(Some(9), 0, 0).unwrap(), 10);
+
+        // A wallet restored from genesis can't sync past a pruned node's gap
+        assert!(matches!(sync_start(None, 0, 100), Err(WalletServiceError::NodePruned(100))));
+        assert_eq!(sync_start(None, 100, 100).unwrap(), 100);
+        assert_eq!(sync_start(None, 150, 100).unwrap(), 150);
+
+        // Nor can one that fell behind the retention window
+        assert!(matches!(sync_start(Some(50), 0, 100), Err(WalletServiceError::NodePruned(100))));
+        assert_eq!(sync_start(Some(99), 0, 100).unwrap(), 100);
+        assert_eq!(sync_start(Some(120), 100, 100).unwrap(), 121);
+    }
+}
@@ -0,0 +1,58 @@
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::health::{BridgeHealth, BridgeHealthReport};
+use super::ledger::{BackingReport, WrappedLedger};
+
+#[derive(Clone)]
+pub struct BridgeApiState {
+    ledger: Arc<RwLock<WrappedLedger>>,
+    health: Option<Arc<BridgeHealth>>,
+}
+
+impl BridgeApiState {
+    pub fn new(ledger: Arc<RwLock<WrappedLedger>>) -> Self {
+        Self { ledger, health: None }
+    }
+
+    pub fn with_health(mut self, health: Arc<BridgeHealth>) -> Self {
+        self.health = Some(health);
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackingResponse {
+    chains: Vec<BackingReport>,
+    // First violated invariant, if any
+    violation: Option<String>,
+}
+
+pub fn create_bridge_routes(state: BridgeApiState) -> Router {
+    Router::new()
+        .route("/bridge/backing", get(backing))
+        .route("/bridge/health", get(health))
+        .with_state(state)
+}
+
+async fn backing(State(state): State<BridgeApiState>) -> Result<Json<BackingResponse>, StatusCode> {
+    let ledger = state.ledger.read().await;
+    Ok(Json(BackingResponse {
+        chains: ledger.report(),
+        violation: ledger.check_invariants().err().map(|e| e.to_string()),
+    }))
+}
+
+// 503 while unhealthy so load balancers and probes can act on the status alone
+async fn health(State(state): State<BridgeApiState>) -> Result<(StatusCode, Json<BridgeHealthReport>), StatusCode> {
+    let health = state.health.ok_or(StatusCode::NOT_FOUND)?;
+    let report = health.report().await;
+    let status = if report.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok((status, Json(report)))
+}
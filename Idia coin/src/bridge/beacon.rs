@@ -0,0 +1,671 @@
+use blst::min_pk::{PublicKey, Signature};
+use blst::BLST_ERROR;
+use ethers::types::{Bloom, Bytes, H160, H256, U256};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use super::types::BridgeError;
+
+const SLOTS_PER_EPOCH: u64 = 32;
+const SLOTS_PER_PERIOD: u64 = SLOTS_PER_EPOCH * 256;
+const SYNC_COMMITTEE_SIZE: usize = 512;
+const DOMAIN_SYNC_COMMITTEE: [u8; 4] = [7, 0, 0, 0];
+const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+// Position of the execution payload header in a block body
+const EXECUTION_PAYLOAD_GINDEX: u64 = 25;
+
+// Positions of the light client proofs in the beacon state, which Electra grew
+struct StateGindices {
+    finalized_root: u64,
+    current_sync_committee: u64,
+    next_sync_committee: u64,
+}
+
+const DENEB_GINDICES: StateGindices = StateGindices {
+    finalized_root: 105,
+    current_sync_committee: 54,
+    next_sync_committee: 55,
+};
+
+const ELECTRA_GINDICES: StateGindices = StateGindices {
+    finalized_root: 169,
+    current_sync_committee: 86,
+    next_sync_committee: 87,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ForkVersion {
+    pub epoch: u64,
+    pub version: [u8; 4],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeaconConfig {
+    pub beacon_url: String,
+    pub genesis_validators_root: H256,
+    // Forks from Deneb on, oldest first; updates from before the first are refused
+    pub forks: Vec<ForkVersion>,
+    pub electra_epoch: u64,
+    // Subjective initialization: a finalized beacon block root trusted out of band
+    pub checkpoint_root: H256,
+}
+
+impl BeaconConfig {
+    pub fn mainnet(beacon_url: String, checkpoint_root: H256) -> Self {
+        Self {
+            beacon_url,
+            genesis_validators_root: "4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+                .parse()
+                .expect("valid genesis validators root"),
+            forks: vec![
+                ForkVersion { epoch: 269568, version: [4, 0, 0, 0] },
+                ForkVersion { epoch: 364032, version: [5, 0, 0, 0] },
+                ForkVersion { epoch: 411392, version: [6, 0, 0, 0] },
+            ],
+            electra_epoch: 364032,
+            checkpoint_root,
+        }
+    }
+
+    fn fork_version(&self, epoch: u64) -> Result<[u8; 4], BridgeError> {
+        self.forks
+            .iter()
+            .rev()
+            .find(|fork| fork.epoch <= epoch)
+            .map(|fork| fork.version)
+            .ok_or_else(|| BridgeError::Rpc(format!("epoch {} predates the configured forks", epoch)))
+    }
+
+    fn gindices(&self, slot: u64) -> Result<&'static StateGindices, BridgeError> {
+        let epoch = slot / SLOTS_PER_EPOCH;
+        self.fork_version(epoch)?;
+        Ok(if epoch >= self.electra_epoch { &ELECTRA_GINDICES } else { &DENEB_GINDICES })
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BeaconBlockHeader {
+    #[serde(deserialize_with = "quoted")]
+    pub slot: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub proposer_index: u64,
+    pub parent_root: H256,
+    pub state_root: H256,
+    pub body_root: H256,
+}
+
+impl BeaconBlockHeader {
+    pub fn hash_tree_root(&self) -> H256 {
+        merkleize(
+            vec![
+                uint64(self.slot),
+                uint64(self.proposer_index),
+                self.parent_root,
+                self.state_root,
+                self.body_root,
+            ],
+            8,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ExecutionPayloadHeader {
+    parent_hash: H256,
+    fee_recipient: H160,
+    state_root: H256,
+    receipts_root: H256,
+    logs_bloom: Bloom,
+    prev_randao: H256,
+    #[serde(deserialize_with = "quoted")]
+    block_number: u64,
+    #[serde(deserialize_with = "quoted")]
+    gas_limit: u64,
+    #[serde(deserialize_with = "quoted")]
+    gas_used: u64,
+    #[serde(deserialize_with = "quoted")]
+    timestamp: u64,
+    extra_data: Bytes,
+    #[serde(deserialize_with = "quoted_u256")]
+    base_fee_per_gas: U256,
+    block_hash: H256,
+    transactions_root: H256,
+    withdrawals_root: H256,
+    #[serde(deserialize_with = "quoted")]
+    blob_gas_used: u64,
+    #[serde(deserialize_with = "quoted")]
+    excess_blob_gas: u64,
+}
+
+impl ExecutionPayloadHeader {
+    fn hash_tree_root(&self) -> H256 {
+        let mut base_fee = [0u8; 32];
+        self.base_fee_per_gas.to_little_endian(&mut base_fee);
+        let extra_data = mix_in_length(merkleize(pack(&self.extra_data), 1), self.extra_data.len());
+        merkleize(
+            vec![
+                self.parent_hash,
+                pack(self.fee_recipient.as_bytes())[0],
+                self.state_root,
+                self.receipts_root,
+                merkleize(pack(self.logs_bloom.as_bytes()), 8),
+                self.prev_randao,
+                uint64(self.block_number),
+                uint64(self.gas_limit),
+                uint64(self.gas_used),
+                uint64(self.timestamp),
+                extra_data,
+                H256(base_fee),
+                self.block_hash,
+                self.transactions_root,
+                self.withdrawals_root,
+                uint64(self.blob_gas_used),
+                uint64(self.excess_blob_gas),
+            ],
+            32,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct LightClientHeader {
+    beacon: BeaconBlockHeader,
+    execution: ExecutionPayloadHeader,
+    execution_branch: Vec<H256>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SyncCommitteeData {
+    pubkeys: Vec<Bytes>,
+    aggregate_pubkey: Bytes,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SyncAggregate {
+    sync_committee_bits: Bytes,
+    sync_committee_signature: Bytes,
+}
+
+#[derive(Debug, Deserialize)]
+struct Bootstrap {
+    header: LightClientHeader,
+    current_sync_committee: SyncCommitteeData,
+    current_sync_committee_branch: Vec<H256>,
+}
+
+// A period update, or with no next committee a finality update
+#[derive(Debug, Deserialize)]
+struct LightClientUpdate {
+    attested_header: LightClientHeader,
+    #[serde(default)]
+    next_sync_committee: Option<SyncCommitteeData>,
+    #[serde(default)]
+    next_sync_committee_branch: Vec<H256>,
+    finalized_header: LightClientHeader,
+    finality_branch: Vec<H256>,
+    sync_aggregate: SyncAggregate,
+    #[serde(deserialize_with = "quoted")]
+    signature_slot: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Versioned<T> {
+    data: T,
+}
+
+struct SyncCommittee {
+    pubkeys: Vec<PublicKey>,
+    root: H256,
+}
+
+impl SyncCommittee {
+    fn parse(data: &SyncCommitteeData) -> Result<Self, BridgeError> {
+        if data.pubkeys.len() != SYNC_COMMITTEE_SIZE
+            || data.aggregate_pubkey.len() != 48
+            || data.pubkeys.iter().any(|pk| pk.len() != 48)
+        {
+            return Err(BridgeError::Rpc("malformed sync committee".to_string()));
+        }
+        let pubkeys_root = merkleize(data.pubkeys.iter().map(|pk| merkleize(pack(pk), 2)).collect(), SYNC_COMMITTEE_SIZE);
+        let root = merkleize(vec![pubkeys_root, merkleize(pack(&data.aggregate_pubkey), 2)], 2);
+        let pubkeys = data
+            .pubkeys
+            .iter()
+            .map(|pk| PublicKey::key_validate(pk))
+            .collect::<Result<_, _>>()
+            .map_err(|e| BridgeError::Rpc(format!("invalid sync committee key: {:?}", e)))?;
+        Ok(Self { pubkeys, root })
+    }
+}
+
+// The execution block inside a finalized beacon block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FinalizedBlock {
+    pub number: u64,
+    pub hash: H256,
+}
+
+struct Store {
+    finalized: BeaconBlockHeader,
+    execution: FinalizedBlock,
+    current: SyncCommittee,
+    next: Option<SyncCommittee>,
+}
+
+// Beacon chain light client (Deneb onwards): follows finality through updates
+// signed by a supermajority of the sync committee, rotating committees each
+// period, starting from a checkpoint block root the operator trusts. The
+// beacon node serving the updates is trusted for nothing.
+pub struct BeaconLightClient {
+    http: reqwest::Client,
+    config: BeaconConfig,
+    store: RwLock<Store>,
+}
+
+impl BeaconLightClient {
+    pub async fn bootstrap(config: BeaconConfig) -> Result<Self, BridgeError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(http_error)?;
+        let path = format!("/eth/v1/beacon/light_client/bootstrap/{:?}", config.checkpoint_root);
+        let bootstrap: Versioned<Bootstrap> = get(&http, &config, &path).await?;
+        let bootstrap = bootstrap.data;
+
+        let header = &bootstrap.header.beacon;
+        if header.hash_tree_root() != config.checkpoint_root {
+            return Err(BridgeError::Rpc("bootstrap header does not match the checkpoint".to_string()));
+        }
+        let current = SyncCommittee::parse(&bootstrap.current_sync_committee)?;
+        let gindex = config.gindices(header.slot)?.current_sync_committee;
+        if !verify_branch(current.root, &bootstrap.current_sync_committee_branch, gindex, header.state_root) {
+            return Err(BridgeError::Rpc("bootstrap sync committee is not in the checkpoint state".to_string()));
+        }
+        let execution = verify_execution(&bootstrap.header)?;
+
+        Ok(Self {
+            http,
+            config,
+            store: RwLock::new(Store {
+                finalized: bootstrap.header.beacon,
+                execution,
+                current,
+                next: None,
+            }),
+        })
+    }
+
+    pub async fn finalized(&self) -> FinalizedBlock {
+        self.store.read().await.execution
+    }
+
+    // Fetches the next committee when it is unknown, then the latest finality update
+    pub async fn sync(&self) -> Result<FinalizedBlock, BridgeError> {
+        let (period, has_next) = {
+            let store = self.store.read().await;
+            (store.finalized.slot / SLOTS_PER_PERIOD, store.next.is_some())
+        };
+        if !has_next {
+            let path = format!("/eth/v1/beacon/light_client/updates?start_period={}&count=1", period);
+            let updates: Vec<Versioned<LightClientUpdate>> = get(&self.http, &self.config, &path).await?;
+            if let Some(update) = updates.into_iter().next() {
+                self.apply(&mut *self.store.write().await, update.data)?;
+            }
+        }
+
+        let update: Versioned<LightClientUpdate> =
+            get(&self.http, &self.config, "/eth/v1/beacon/light_client/finality_update").await?;
+        let mut store = self.store.write().await;
+        self.apply(&mut store, update.data)?;
+        Ok(store.execution)
+    }
+
+    fn apply(&self, store: &mut Store, update: LightClientUpdate) -> Result<(), BridgeError> {
+        let attested = &update.attested_header.beacon;
+        let finalized = &update.finalized_header.beacon;
+        let learns_next = update.next_sync_committee.is_some() && store.next.is_none();
+        if finalized.slot <= store.finalized.slot && !learns_next {
+            return Ok(());
+        }
+        if update.signature_slot <= attested.slot || attested.slot < finalized.slot {
+            return Err(BridgeError::Rpc("update slots are out of order".to_string()));
+        }
+
+        let store_period = store.finalized.slot / SLOTS_PER_PERIOD;
+        let signature_period = update.signature_slot / SLOTS_PER_PERIOD;
+        let committee = if signature_period == store_period {
+            &store.current
+        } else if signature_period == store_period + 1 {
+            store
+                .next
+                .as_ref()
+                .ok_or_else(|| BridgeError::Rpc("update is signed by a committee not yet known".to_string()))?
+        } else {
+            return Err(BridgeError::Rpc(format!("update skips from period {} to {}", store_period, signature_period)));
+        };
+
+        let gindices = self.config.gindices(attested.slot)?;
+        if !verify_branch(finalized.hash_tree_root(), &update.finality_branch, gindices.finalized_root, attested.state_root) {
+            return Err(BridgeError::Rpc("finalized header is not in the attested state".to_string()));
+        }
+        let execution = verify_execution(&update.finalized_header)?;
+
+        // Only the committee following ours is worth keeping
+        let next = match &update.next_sync_committee {
+            Some(data) if learns_next && attested.slot / SLOTS_PER_PERIOD == store_period => {
+                let next = SyncCommittee::parse(data)?;
+                let gindex = gindices.next_sync_committee;
+                if !verify_branch(next.root, &update.next_sync_committee_branch, gindex, attested.state_root) {
+                    return Err(BridgeError::Rpc("next sync committee is not in the attested state".to_string()));
+                }
+                Some(next)
+            }
+            _ => None,
+        };
+
+        self.verify_signature(committee, &update)?;
+
+        if next.is_some() {
+            store.next = next;
+        }
+        if finalized.slot > store.finalized.slot {
+            match finalized.slot / SLOTS_PER_PERIOD - store_period {
+                0 => {}
+                1 => {
+                    store.current = store
+                        .next
+                        .take()
+                        .ok_or_else(|| BridgeError::Rpc("finality moved past a committee not yet known".to_string()))?;
+                }
+                _ => return Err(BridgeError::Rpc("finality skipped a sync committee period".to_string())),
+            }
+            store.finalized = finalized.clone();
+            store.execution = execution;
+        }
+        Ok(())
+    }
+
+    // At least two thirds of the committee must have signed the attested header
+    fn verify_signature(&self, committee: &SyncCommittee, update: &LightClientUpdate) -> Result<(), BridgeError> {
+        let bits = &update.sync_aggregate.sync_committee_bits;
+        if bits.len() != SYNC_COMMITTEE_SIZE / 8 {
+            return Err(BridgeError::Rpc("malformed sync committee bits".to_string()));
+        }
+        let participants: Vec<&PublicKey> = committee
+            .pubkeys
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| bits[i / 8] >> (i % 8) & 1 == 1)
+            .map(|(_, pk)| pk)
+            .collect();
+        if participants.len() * 3 < SYNC_COMMITTEE_SIZE * 2 {
+            return Err(BridgeError::Rpc(format!(
+                "only {} of {} sync committee members signed",
+                participants.len(),
+                SYNC_COMMITTEE_SIZE
+            )));
+        }
+
+        // The fork is the one of the slot before the signature was included
+        let epoch = update.signature_slot.saturating_sub(1) / SLOTS_PER_EPOCH;
+        let mut version = [0u8; 32];
+        version[..4].copy_from_slice(&self.config.fork_version(epoch)?);
+        let fork_data_root = hash_pair(&version, self.config.genesis_validators_root.as_bytes());
+        let mut domain = [0u8; 32];
+        domain[..4].copy_from_slice(&DOMAIN_SYNC_COMMITTEE);
+        domain[4..].copy_from_slice(&fork_data_root[..28]);
+        let signing_root = hash_pair(update.attested_header.beacon.hash_tree_root().as_bytes(), &domain);
+
+        let signature = Signature::from_bytes(&update.sync_aggregate.sync_committee_signature)
+            .map_err(|e| BridgeError::Rpc(format!("malformed sync committee signature: {:?}", e)))?;
+        if signature.fast_aggregate_verify(true, signing_root.as_bytes(), BLS_DST, &participants) != BLST_ERROR::BLST_SUCCESS {
+            return Err(BridgeError::Rpc("update is not signed by the sync committee".to_string()));
+        }
+        Ok(())
+    }
+}
+
+fn verify_execution(header: &LightClientHeader) -> Result<FinalizedBlock, BridgeError> {
+    let execution = &header.execution;
+    if execution.extra_data.len() > 32
+        || !verify_branch(
+            execution.hash_tree_root(),
+            &header.execution_branch,
+            EXECUTION_PAYLOAD_GINDEX,
+            header.beacon.body_root,
+        )
+    {
+        return Err(BridgeError::Rpc("execution header is not in the beacon block".to_string()));
+    }
+    Ok(FinalizedBlock {
+        number: execution.block_number,
+        hash: execution.block_hash,
+    })
+}
+
+async fn get<T: DeserializeOwned>(http: &reqwest::Client, config: &BeaconConfig, path: &str) -> Result<T, BridgeError> {
+    let url = format!("{}{}", config.beacon_url.trim_end_matches('/'), path);
+    http.get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(http_error)?
+        .json()
+        .await
+        .map_err(http_error)
+}
+
+fn hash_pair(left: &[u8], right: &[u8]) -> H256 {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    H256::from_slice(&hasher.finalize())
+}
+
+// SSZ merkleization of `chunks`, padded with zero chunks to `limit`
+fn merkleize(mut chunks: Vec<H256>, limit: usize) -> H256 {
+    chunks.resize(limit.next_power_of_two(), H256::zero());
+    while chunks.len() > 1 {
+        chunks = chunks
+            .chunks(2)
+            .map(|pair| hash_pair(pair[0].as_bytes(), pair[1].as_bytes()))
+            .collect();
+    }
+    chunks[0]
+}
+
+fn mix_in_length(root: H256, length: usize) -> H256 {
+    hash_pair(root.as_bytes(), uint64(length as u64).as_bytes())
+}
+
+fn pack(bytes: &[u8]) -> Vec<H256> {
+    bytes
+        .chunks(32)
+        .map(|part| {
+            let mut chunk = [0u8; 32];
+            chunk[..part.len()].copy_from_slice(part);
+            H256(chunk)
+        })
+        .collect()
+}
+
+fn uint64(value: u64) -> H256 {
+    let mut chunk = [0u8; 32];
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    H256(chunk)
+}
+
+// Root reached from `leaf` at generalized index `gindex` through `branch`
+fn branch_root(leaf: H256, branch: &[H256], gindex: u64) -> Option<H256> {
+    let depth = gindex.checked_ilog2()? as usize;
+    if branch.len() != depth {
+        return None;
+    }
+    let mut node = leaf;
+    for (level, sibling) in branch.iter().enumerate() {
+        node = if (gindex >> level) & 1 == 1 {
+            hash_pair(sibling.as_bytes(), node.as_bytes())
+        } else {
+            hash_pair(node.as_bytes(), sibling.as_bytes())
+        };
+    }
+    Some(node)
+}
+
+fn verify_branch(leaf: H256, branch: &[H256], gindex: u64, root: H256) -> bool {
+    branch_root(leaf, branch, gindex) == Some(root)
+}
+
+fn quoted<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+}
+
+fn quoted_u256<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    U256::from_dec_str(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+fn http_error(e: reqwest::Error) -> BridgeError {
+    BridgeError::Rpc(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blst::min_pk::{AggregateSignature, SecretKey};
+
+    const SLOT: u64 = 364032 * SLOTS_PER_EPOCH + 100;
+
+    fn keys() -> Vec<SecretKey> {
+        (0..4u8).map(|i| SecretKey::key_gen(&[i + 1; 32], &[]).unwrap()).collect()
+    }
+
+    fn committee(keys: &[SecretKey]) -> SyncCommittee {
+        let pubkeys: Vec<Bytes> = (0..SYNC_COMMITTEE_SIZE)
+            .map(|i| Bytes::from(keys[i % keys.len()].sk_to_pk().to_bytes().to_vec()))
+            .collect();
+        let aggregate_pubkey = pubkeys[0].clone();
+        SyncCommittee::parse(&SyncCommitteeData { pubkeys, aggregate_pubkey }).unwrap()
+    }
+
+    fn client() -> BeaconLightClient {
+        let keys = keys();
+        let store = Store {
+            finalized: BeaconBlockHeader { slot: SLOT, ..Default::default() },
+            execution: FinalizedBlock { number: 1, hash: H256::zero() },
+            current: committee(&keys),
+            next: None,
+        };
+        BeaconLightClient {
+            http: reqwest::Client::new(),
+            config: BeaconConfig::mainnet(String::new(), H256::zero()),
+            store: RwLock::new(store),
+        }
+    }
+
+    // A finality update with consistent proofs, signed by `signers` of the committee
+    fn update(client: &BeaconLightClient, signers: usize) -> LightClientUpdate {
+        let execution = ExecutionPayloadHeader {
+            block_number: 42,
+            block_hash: H256::repeat_byte(7),
+            ..Default::default()
+        };
+        let execution_branch = vec![H256::repeat_byte(1); 4];
+        let finalized = BeaconBlockHeader {
+            slot: SLOT + 64,
+            body_root: branch_root(execution.hash_tree_root(), &execution_branch, EXECUTION_PAYLOAD_GINDEX).unwrap(),
+            ..Default::default()
+        };
+        let finality_branch = vec![H256::repeat_byte(2); 7];
+        let attested = BeaconBlockHeader {
+            slot: SLOT + 128,
+            state_root: branch_root(finalized.hash_tree_root(), &finality_branch, ELECTRA_GINDICES.finalized_root)
+                .unwrap(),
+            ..Default::default()
+        };
+
+        let mut update = LightClientUpdate {
+            attested_header: LightClientHeader { beacon: attested, ..Default::default() },
+            next_sync_committee: None,
+            next_sync_committee_branch: Vec::new(),
+            finalized_header: LightClientHeader {
+                beacon: finalized,
+                execution,
+                execution_branch,
+            },
+            finality_branch,
+            sync_aggregate: SyncAggregate {
+                sync_committee_bits: Bytes::new(),
+                sync_committee_signature: Bytes::new(),
+            },
+            signature_slot: SLOT + 129,
+        };
+        sign(client, &mut update, signers);
+        update
+    }
+
+    fn sign(client: &BeaconLightClient, update: &mut LightClientUpdate, signers: usize) {
+        let keys = keys();
+        let mut bits = vec![0u8; SYNC_COMMITTEE_SIZE / 8];
+        for i in 0..signers {
+            bits[i / 8] |= 1 << (i % 8);
+        }
+
+        let mut version = [0u8; 32];
+        version[..4].copy_from_slice(&client.config.fork_version(update.signature_slot / SLOTS_PER_EPOCH).unwrap());
+        let fork_data_root = hash_pair(&version, client.config.genesis_validators_root.as_bytes());
+        let mut domain = [0u8; 32];
+        domain[..4].copy_from_slice(&DOMAIN_SYNC_COMMITTEE);
+        domain[4..].copy_from_slice(&fork_data_root[..28]);
+        let message = hash_pair(update.attested_header.beacon.hash_tree_root().as_bytes(), &domain);
+
+        let signatures: Vec<Signature> = (0..signers)
+            .map(|i| keys[i % keys.len()].sign(message.as_bytes(), BLS_DST, &[]))
+            .collect();
+        let refs: Vec<&Signature> = signatures.iter().collect();
+        let signature = AggregateSignature::aggregate(&refs, true).unwrap().to_signature();
+        update.sync_aggregate = SyncAggregate {
+            sync_committee_bits: Bytes::from(bits),
+            sync_committee_signature: Bytes::from(signature.to_bytes().to_vec()),
+        };
+    }
+
+    #[test]
+    fn test_empty_header_root() {
+        // Zero hash of depth 3, as every SSZ implementation computes it
+        let expected = "c78009fdf07fc56a11f122370658a353aaa542ed63e44c4bc15ff4cd105ab33c";
+        assert_eq!(hex::encode(BeaconBlockHeader::default().hash_tree_root()), expected);
+    }
+
+    #[tokio::test]
+    async fn test_signed_finality_update_applies() {
+        let client = client();
+        let update = update(&client, SYNC_COMMITTEE_SIZE);
+        client.apply(&mut *client.store.write().await, update).unwrap();
+        assert_eq!(
+            client.finalized().await,
+            FinalizedBlock { number: 42, hash: H256::repeat_byte(7) }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_forged_updates() {
+        let client = client();
+
+        let mut tampered = update(&client, SYNC_COMMITTEE_SIZE);
+        tampered.finalized_header.execution.block_hash = H256::repeat_byte(8);
+        assert!(client.apply(&mut *client.store.write().await, tampered).is_err());
+
+        let mut resigned = update(&client, SYNC_COMMITTEE_SIZE);
+        resigned.attested_header.beacon.proposer_index = 1;
+        assert!(client.apply(&mut *client.store.write().await, resigned).is_err());
+
+        let minority = update(&client, SYNC_COMMITTEE_SIZE * 2 / 3 - 1);
+        assert!(client.apply(&mut *client.store.write().await, minority).is_err());
+
+        assert_eq!(client.finalized().await.number, 1);
+    }
+}
@@ -0,0 +1,641 @@
+use async_trait::async_trait;
+use bitcoin::block::Header;
+use bitcoin::blockdata::opcodes::all::{
+    OP_CHECKSIG, OP_CSV, OP_DROP, OP_ELSE, OP_ENDIF, OP_EQUALVERIFY, OP_IF, OP_SHA256,
+};
+use bitcoin::blockdata::script::{Builder, ScriptBuf};
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::merkle_tree::MerkleBlock;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::{
+    absolute, transaction, Address, Amount, CompactTarget, Network, OutPoint, PublicKey, Sequence,
+    Target, Transaction, TxIn, TxOut, Txid, Witness,
+};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use super::federation::verify_approval;
+use super::manager::ChainAdapter;
+use super::types::{BridgeError, CrossChainProof, ProofPayload, TxHash};
+
+// Upper bound on the size of a one-in one-out P2WSH HTLC spend, used for fees
+const HTLC_SPEND_VSIZE: u64 = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitcoinBridgeConfig {
+    pub rpc_url: String,
+    pub rpc_user: String,
+    pub rpc_password: String,
+    pub network: Network,
+    // Blocks, including the one holding the lock, an SPV proof must cover and
+    // our node's best chain must have on top of the lock
+    pub min_confirmations: usize,
+    pub htlc_timeout_blocks: u16,
+    // Easiest proof-of-work accepted in a proof header, as compact `bits`
+    pub max_target_bits: u32,
+    // sat/vB
+    pub fee_rate: u64,
+}
+
+impl Default for BitcoinBridgeConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: "http://127.0.0.1:8332".to_string(),
+            rpc_user: String::new(),
+            rpc_password: String::new(),
+            network: Network::Bitcoin,
+            min_confirmations: 6,
+            htlc_timeout_blocks: 144,
+            max_target_bits: 0x1d00ffff,
+            fee_rate: 10,
+        }
+    }
+}
+
+// Pays `recipient` on presentation of the SHA-256 preimage of `payment_hash`,
+// or back to `refund` once the output is `timeout_blocks` deep
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Htlc {
+    pub payment_hash: [u8; 32],
+    pub recipient: PublicKey,
+    pub refund: PublicKey,
+    pub timeout_blocks: u16,
+}
+
+impl Htlc {
+    pub fn witness_script(&self) -> ScriptBuf {
+        Builder::new()
+            .push_opcode(OP_IF)
+            .push_opcode(OP_SHA256)
+            .push_slice(self.payment_hash)
+            .push_opcode(OP_EQUALVERIFY)
+            .push_key(&self.recipient)
+            .push_opcode(OP_ELSE)
+            .push_int(self.timeout_blocks as i64)
+            .push_opcode(OP_CSV)
+            .push_opcode(OP_DROP)
+            .push_key(&self.refund)
+            .push_opcode(OP_ENDIF)
+            .push_opcode(OP_CHECKSIG)
+            .into_script()
+    }
+
+    pub fn script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2wsh(&self.witness_script().wscript_hash())
+    }
+
+    pub fn address(&self, network: Network) -> Address {
+        Address::p2wsh(&self.witness_script(), network)
+    }
+}
+
+// The lock transaction, the merkle block (`gettxoutproof`) placing it in a
+// block, and the consensus-encoded headers built on top of that block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitcoinSpvProof {
+    pub raw_tx: Vec<u8>,
+    pub output_index: u32,
+    pub merkle_block: Vec<u8>,
+    pub confirmations: Vec<Vec<u8>>,
+    pub htlc: Htlc,
+    pub preimage: Option<[u8; 32]>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockStoreError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingLock {
+    htlc: Htlc,
+    preimage: [u8; 32],
+    // None until the funding transaction has been broadcast
+    lock_tx: Option<TxHash>,
+    output_index: u32,
+    amount: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LockRecord {
+    payment_hash: [u8; 32],
+    // None once the lock has been refunded
+    lock: Option<PendingLock>,
+}
+
+// Our open HTLC locks, preimages included, so they can still be revealed,
+// proven or refunded after a restart. A lock is written before it is funded,
+// so no funded HTLC is ever missing its preimage. Every change is appended as
+// a JSON line and synced; the last line for a payment hash wins.
+pub struct LockStore {
+    path: PathBuf,
+    locks: HashMap<[u8; 32], PendingLock>,
+}
+
+impl LockStore {
+    pub async fn open(path: PathBuf) -> Result<Self, LockStoreError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut store = Self {
+            path,
+            locks: HashMap::new(),
+        };
+        let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str::<LockRecord>(line) {
+                Ok(record) => store.apply(record),
+                // A crash mid-append leaves a partial last line; anything earlier is real corruption
+                Err(e) if index + 1 == lines.len() => {
+                    tracing::warn!("Dropping torn last record of {}: {}", store.path.display(), e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        store.compact().await?;
+
+        let unfunded = store.locks.values().filter(|l| l.lock_tx.is_none()).count();
+        if unfunded > 0 {
+            tracing::warn!("{} HTLC locks were prepared but their funding transaction is unknown", unfunded);
+        }
+        Ok(store)
+    }
+
+    fn get(&self, lock_tx: TxHash) -> Option<&PendingLock> {
+        self.locks.values().find(|lock| lock.lock_tx == Some(lock_tx))
+    }
+
+    async fn put(&mut self, lock: PendingLock) -> Result<(), LockStoreError> {
+        let record = LockRecord {
+            payment_hash: lock.htlc.payment_hash,
+            lock: Some(lock),
+        };
+        self.append(record).await
+    }
+
+    async fn remove(&mut self, payment_hash: [u8; 32]) -> Result<(), LockStoreError> {
+        self.append(LockRecord { payment_hash, lock: None }).await
+    }
+
+    fn apply(&mut self, record: LockRecord) {
+        match record.lock {
+            Some(lock) => self.locks.insert(record.payment_hash, lock),
+            None => self.locks.remove(&record.payment_hash),
+        };
+    }
+
+    async fn append(&mut self, record: LockRecord) -> Result<(), LockStoreError> {
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        self.apply(record);
+        Ok(())
+    }
+
+    async fn compact(&self) -> Result<(), LockStoreError> {
+        let mut contents = Vec::new();
+        for (payment_hash, lock) in &self.locks {
+            let record = LockRecord {
+                payment_hash: *payment_hash,
+                lock: Some(lock.clone()),
+            };
+            contents.extend(serde_json::to_vec(&record)?);
+            contents.push(b'\n');
+        }
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, contents).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+// Locks fund a P2WSH HTLC payable to the recipient's key, refundable to the
+// bridge; releases claim an HTLC payable to the bridge with its preimage.
+// Lock transactions are trusted through SPV proofs, and only once the block
+// they prove is buried in the best chain our own node follows.
+pub struct BitcoinBridge {
+    config: BitcoinBridgeConfig,
+    rpc: Client,
+    secret_key: SecretKey,
+    public_key: PublicKey,
+    locks: RwLock<LockStore>,
+    // Federation group key; releases are signed here, so this is where the
+    // federation's approval is enforced
+    federation_key: Option<threshold_crypto::PublicKey>,
+}
+
+impl BitcoinBridge {
+    pub fn new(config: BitcoinBridgeConfig, secret_key: SecretKey, locks: LockStore) -> Result<Self, BridgeError> {
+        let rpc = Client::new(
+            &config.rpc_url,
+            Auth::UserPass(config.rpc_user.clone(), config.rpc_password.clone()),
+        )
+        .map_err(rpc_error)?;
+        let public_key = PublicKey::new(secret_key.public_key(&Secp256k1::signing_only()));
+
+        Ok(Self {
+            config,
+            rpc,
+            secret_key,
+            public_key,
+            locks: RwLock::new(locks),
+            federation_key: None,
+        })
+    }
+
+    pub fn with_federation_key(mut self, federation_key: threshold_crypto::PublicKey) -> Self {
+        self.federation_key = Some(federation_key);
+        self
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    // Revealed to the recipient once the other leg of the transfer has settled
+    pub async fn preimage(&self, lock_tx: TxHash) -> Option<[u8; 32]> {
+        self.locks.read().await.get(lock_tx).map(|lock| lock.preimage)
+    }
+
+    // Builds the SPV proof for one of our own locks once it is deep enough
+    pub async fn spv_proof(&self, lock_tx: TxHash) -> Result<BitcoinSpvProof, BridgeError> {
+        let lock = self
+            .locks
+            .read()
+            .await
+            .get(lock_tx)
+            .cloned()
+            .ok_or(BridgeError::UnknownLock(lock_tx))?;
+        let txid = to_txid(lock_tx);
+
+        let raw_tx = self.rpc.get_raw_transaction_hex(&txid, None).map_err(rpc_error)?;
+        let merkle_block = self.rpc.get_tx_out_proof(&[txid], None).map_err(rpc_error)?;
+        let block: MerkleBlock = deserialize(&merkle_block).map_err(malformed)?;
+        let info = self
+            .rpc
+            .get_block_header_info(&block.header.block_hash())
+            .map_err(rpc_error)?;
+        if info.confirmations < self.config.min_confirmations as i32 {
+            return Err(BridgeError::Rpc(format!(
+                "lock {} has {} of {} confirmations",
+                txid, info.confirmations, self.config.min_confirmations
+            )));
+        }
+
+        let mut confirmations = Vec::new();
+        for height in info.height + 1..info.height + self.config.min_confirmations {
+            let hash = self.rpc.get_block_hash(height as u64).map_err(rpc_error)?;
+            let header = self.rpc.get_block_header(&hash).map_err(rpc_error)?;
+            confirmations.push(serialize(&header));
+        }
+
+        Ok(BitcoinSpvProof {
+            raw_tx: hex::decode(&raw_tx).map_err(|e| BridgeError::Rpc(e.to_string()))?,
+            output_index: lock.output_index,
+            merkle_block,
+            confirmations,
+            htlc: lock.htlc,
+            preimage: None,
+        })
+    }
+
+    // Takes one of our locks back after its timeout has passed
+    pub async fn refund(&self, lock_tx: TxHash, address: &str) -> Result<TxHash, BridgeError> {
+        let lock = self
+            .locks
+            .read()
+            .await
+            .get(lock_tx)
+            .cloned()
+            .ok_or(BridgeError::UnknownLock(lock_tx))?;
+        let destination = self.parse_address(address)?;
+        let outpoint = OutPoint::new(to_txid(lock_tx), lock.output_index);
+
+        let txid = self.spend_htlc(
+            &lock.htlc,
+            outpoint,
+            lock.amount,
+            destination,
+            Sequence::from_height(lock.htlc.timeout_blocks),
+            None,
+        )?;
+        self.locks.write().await.remove(lock.htlc.payment_hash).await?;
+        Ok(txid)
+    }
+
+    fn verify_spv(&self, proof: &CrossChainProof, spv: &BitcoinSpvProof) -> Result<(), BridgeError> {
+        let tx: Transaction = deserialize(&spv.raw_tx).map_err(malformed)?;
+        let txid = tx.compute_txid();
+        if to_tx_hash(txid) != proof.lock_tx {
+            return Err(BridgeError::MalformedProof("transaction does not match lock_tx".to_string()));
+        }
+
+        // Inclusion: the partial merkle tree commits to the header's root and matches our tx
+        let block: MerkleBlock = deserialize(&spv.merkle_block).map_err(malformed)?;
+        let mut matches = Vec::new();
+        let mut indexes = Vec::new();
+        let root = block
+            .extract_matches(&mut matches, &mut indexes)
+            .map_err(|e| BridgeError::MalformedProof(e.to_string()))?;
+        if root != block.header.merkle_root || !matches.contains(&txid) {
+            return Err(BridgeError::InvalidProof);
+        }
+
+        // Work: every header is linked, meets its own target, and that target is
+        // no easier than the configured floor
+        let max_target = Target::from_compact(CompactTarget::from_consensus(self.config.max_target_bits));
+        let mut previous = block.header;
+        self.check_work(&previous, max_target)?;
+        for encoded in &spv.confirmations {
+            let header: Header = deserialize(encoded).map_err(malformed)?;
+            if header.prev_blockhash != previous.block_hash() {
+                return Err(BridgeError::InvalidProof);
+            }
+            self.check_work(&header, max_target)?;
+            previous = header;
+        }
+        if 1 + spv.confirmations.len() < self.config.min_confirmations {
+            return Err(BridgeError::InvalidProof);
+        }
+
+        // Anchor: headers that link up and meet the floor could still come from a
+        // fork mined in private, so the block must also sit on the best chain our
+        // node follows, buried at least `min_confirmations` deep there. Stale
+        // blocks report -1 confirmations.
+        let info = match self.rpc.get_block_header_info(&block.header.block_hash()) {
+            Ok(info) => info,
+            // RPC_INVALID_ADDRESS_OR_KEY: our node has never seen the block
+            Err(bitcoincore_rpc::Error::JsonRpc(bitcoincore_rpc::jsonrpc::Error::Rpc(e))) if e.code == -5 => {
+                return Err(BridgeError::InvalidProof);
+            }
+            Err(e) => return Err(rpc_error(e)),
+        };
+        if info.confirmations < self.config.min_confirmations as i32 {
+            return Err(BridgeError::InvalidProof);
+        }
+
+        let output = tx
+            .output
+            .get(spv.output_index as usize)
+            .ok_or_else(|| BridgeError::MalformedProof("output index out of range".to_string()))?;
+        if output.script_pubkey != spv.htlc.script_pubkey() || output.value.to_sat() != proof.amount {
+            return Err(BridgeError::InvalidProof);
+        }
+
+        if let Some(preimage) = spv.preimage {
+            if sha256::Hash::hash(&preimage).to_byte_array() != spv.htlc.payment_hash {
+                return Err(BridgeError::InvalidProof);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_work(&self, header: &Header, max_target: Target) -> Result<(), BridgeError> {
+        let target = header.target();
+        if target > max_target || header.validate_pow(target).is_err() {
+            return Err(BridgeError::InvalidProof);
+        }
+        Ok(())
+    }
+
+    fn spend_htlc(
+        &self,
+        htlc: &Htlc,
+        outpoint: OutPoint,
+        amount: u64,
+        destination: Address,
+        sequence: Sequence,
+        preimage: Option<[u8; 32]>,
+    ) -> Result<TxHash, BridgeError> {
+        let fee = self.config.fee_rate * HTLC_SPEND_VSIZE;
+        if amount <= fee {
+            return Err(BridgeError::Rpc(format!("{} sat does not cover the {} sat fee", amount, fee)));
+        }
+
+        let mut tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(amount - fee),
+                script_pubkey: destination.script_pubkey(),
+            }],
+        };
+
+        let script = htlc.witness_script();
+        let sighash = SighashCache::new(&tx)
+            .p2wsh_signature_hash(0, &script, Amount::from_sat(amount), EcdsaSighashType::All)
+            .map_err(|e| BridgeError::Signing(e.to_string()))?;
+        let signature = Secp256k1::signing_only()
+            .sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), &self.secret_key);
+        let signature = bitcoin::ecdsa::Signature::sighash_all(signature);
+
+        // Claim path selects OP_IF with the preimage, refund path OP_ELSE with an empty push
+        let mut witness = Witness::new();
+        witness.push(signature.to_vec());
+        match preimage {
+            Some(preimage) => {
+                witness.push(preimage);
+                witness.push([1u8]);
+            }
+            None => witness.push([]),
+        }
+        witness.push(script.as_bytes());
+        tx.input[0].witness = witness;
+
+        let txid = self.rpc.send_raw_transaction(&tx).map_err(rpc_error)?;
+        Ok(to_tx_hash(txid))
+    }
+
+    fn parse_address(&self, address: &str) -> Result<Address, BridgeError> {
+        Address::from_str(address)
+            .and_then(|a| a.require_network(self.config.network))
+            .map_err(|e| BridgeError::InvalidRecipient(format!("{}: {}", address, e)))
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for BitcoinBridge {
+    async fn verify_proof(&self, proof: &CrossChainProof) -> Result<bool, BridgeError> {
+        let ProofPayload::Bitcoin(spv) = &proof.payload else {
+            return Ok(false);
+        };
+        match self.verify_spv(proof, spv) {
+            Ok(()) => Ok(true),
+            Err(BridgeError::InvalidProof) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // `recipient` is the hex-encoded public key allowed to claim the HTLC
+    async fn lock_assets(&self, amount: u64, recipient: &str) -> Result<TxHash, BridgeError> {
+        let recipient = PublicKey::from_str(recipient)
+            .map_err(|e| BridgeError::InvalidRecipient(format!("{}: {}", recipient, e)))?;
+
+        let mut preimage = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut preimage);
+        let htlc = Htlc {
+            payment_hash: sha256::Hash::hash(&preimage).to_byte_array(),
+            recipient,
+            refund: self.public_key,
+            timeout_blocks: self.config.htlc_timeout_blocks,
+        };
+        let mut lock = PendingLock {
+            htlc: htlc.clone(),
+            preimage,
+            lock_tx: None,
+            output_index: 0,
+            amount,
+        };
+        // Persist the preimage and refund path before any funds move
+        self.locks.write().await.put(lock.clone()).await?;
+
+        let txid = self
+            .rpc
+            .send_to_address(
+                &htlc.address(self.config.network),
+                Amount::from_sat(amount),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .map_err(rpc_error)?;
+
+        // The wallet picks the output order, so find ours
+        let tx = self.rpc.get_raw_transaction(&txid, None).map_err(rpc_error)?;
+        let script_pubkey = htlc.script_pubkey();
+        let output_index = tx
+            .output
+            .iter()
+            .position(|o| o.script_pubkey == script_pubkey)
+            .ok_or_else(|| BridgeError::Rpc(format!("{} has no HTLC output", txid)))?;
+
+        let lock_tx = to_tx_hash(txid);
+        lock.lock_tx = Some(lock_tx);
+        lock.output_index = output_index as u32;
+        self.locks.write().await.put(lock).await?;
+        tracing::info!("Locked {} sat in HTLC {}:{}", amount, txid, output_index);
+        Ok(lock_tx)
+    }
+
+    // Claims an HTLC locked to the bridge key and pays it to `proof.recipient`
+    async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError> {
+        let ProofPayload::Bitcoin(spv) = &proof.payload else {
+            return Err(BridgeError::MalformedProof("expected a Bitcoin SPV proof".to_string()));
+        };
+        if let Some(federation_key) = &self.federation_key {
+            verify_approval(federation_key, proof)?;
+        }
+        self.verify_spv(proof, spv)?;
+        if spv.htlc.recipient != self.public_key {
+            return Err(BridgeError::InvalidRecipient("HTLC is not payable to the bridge".to_string()));
+        }
+        let preimage = spv
+            .preimage
+            .ok_or_else(|| BridgeError::MalformedProof("missing preimage".to_string()))?;
+        let destination = self.parse_address(&proof.recipient)?;
+
+        self.spend_htlc(
+            &spv.htlc,
+            OutPoint::new(to_txid(proof.lock_tx), spv.output_index),
+            proof.amount,
+            destination,
+            Sequence::ENABLE_RBF_NO_LOCKTIME,
+            Some(preimage),
+        )
+    }
+}
+
+// Internal byte order, as in the consensus encoding rather than the reversed display form
+fn to_tx_hash(txid: Txid) -> TxHash {
+    TxHash::from(txid.to_byte_array())
+}
+
+fn to_txid(hash: TxHash) -> Txid {
+    Txid::from_byte_array(hash.0)
+}
+
+fn rpc_error(e: bitcoincore_rpc::Error) -> BridgeError {
+    BridgeError::Rpc(e.to_string())
+}
+
+fn malformed(e: bitcoin::consensus::encode::Error) -> BridgeError {
+    BridgeError::MalformedProof(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(seed: u8, lock_tx: Option<TxHash>) -> PendingLock {
+        let key = PublicKey::from_str("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        let preimage = [seed; 32];
+        PendingLock {
+            htlc: Htlc {
+                payment_hash: sha256::Hash::hash(&preimage).to_byte_array(),
+                recipient: key,
+                refund: key,
+                timeout_blocks: 144,
+            },
+            preimage,
+            lock_tx,
+            output_index: 1,
+            amount: 50_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_locks_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("btc_locks.jsonl");
+        let funded = TxHash::repeat_byte(1);
+        {
+            let mut store = LockStore::open(path.clone()).await.unwrap();
+            store.put(pending(1, None)).await.unwrap();
+            store.put(pending(1, Some(funded))).await.unwrap();
+            store.put(pending(2, Some(TxHash::repeat_byte(2)))).await.unwrap();
+            store.remove(pending(2, None).htlc.payment_hash).await.unwrap();
+        }
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(&path).await.unwrap();
+        file.write_all(br#"{"payment_hash":[3,"#).await.unwrap();
+        drop(file);
+
+        let store = LockStore::open(path).await.unwrap();
+        assert_eq!(store.locks.len(), 1);
+        let lock = store.get(funded).unwrap();
+        assert_eq!(lock.preimage, [1; 32]);
+        assert_eq!(lock.output_index, 1);
+        assert!(store.get(TxHash::repeat_byte(2)).is_none());
+    }
+}
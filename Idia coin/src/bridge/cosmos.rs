@@ -0,0 +1,395 @@
+use async_trait::async_trait;
+use cosmrs::crypto::secp256k1::SigningKey;
+use cosmrs::proto::cosmos::auth::v1beta1::{BaseAccount, QueryAccountRequest, QueryAccountResponse};
+use cosmrs::tx::{Body, Fee, SignDoc, SignerInfo};
+use cosmrs::{AccountId, Any, Coin};
+use ics23::{CommitmentProof, HostFunctionsManager};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tendermint::block::signed_header::SignedHeader;
+use tendermint::block::Height;
+use tendermint::validator::Set as ValidatorSet;
+use tendermint::Time;
+use tendermint_light_client_verifier::options::Options;
+use tendermint_light_client_verifier::types::{TrustThreshold, TrustedBlockState, UntrustedBlockState};
+use tendermint_light_client_verifier::{ProdVerifier, Verdict, Verifier};
+use tendermint_rpc::{Client, HttpClient, Paging};
+use tokio::sync::RwLock;
+
+use super::manager::ChainAdapter;
+use super::types::{BridgeError, ChainId, CrossChainProof, ProofPayload, TxHash};
+
+// Store of the bridge module and the key prefix its lock records live under
+const BRIDGE_STORE: &[u8] = b"bridge";
+const LOCK_KEY_PREFIX: &str = "lock/";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CosmosBridgeConfig {
+    pub rpc_url: String,
+    pub chain_id: String,
+    // Subjective initialization: a header the operator trusts out of band
+    pub trusted_height: u64,
+    pub trusted_hash: String,
+    pub trusting_period_secs: u64,
+    pub fee_denom: String,
+    pub fee_amount: u128,
+    pub gas_limit: u64,
+}
+
+// Cosmos SDK messages of the bridge module
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgLock {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(uint64, tag = "2")]
+    pub amount: u64,
+    #[prost(string, tag = "3")]
+    pub recipient: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgRelease {
+    #[prost(string, tag = "1")]
+    pub relayer: String,
+    #[prost(string, tag = "2")]
+    pub source_chain: String,
+    #[prost(bytes = "vec", tag = "3")]
+    pub lock_tx: Vec<u8>,
+    #[prost(uint64, tag = "4")]
+    pub amount: u64,
+    #[prost(string, tag = "5")]
+    pub recipient: String,
+    #[prost(uint64, tag = "6")]
+    pub nonce: u64,
+    #[prost(bytes = "vec", tag = "7")]
+    pub proof: Vec<u8>,
+    // Federation signature, checked by the module against the group key it holds
+    #[prost(bytes = "vec", tag = "8")]
+    pub approval: Vec<u8>,
+}
+
+// A lock record as the bridge module stores it under `lock/{nonce}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LockRecord {
+    amount: u64,
+    recipient: String,
+    destination: String,
+}
+
+// ICS-23 proofs of a lock record at `height`: first the IAVL proof within the
+// bridge store, then the proof of that store's root in the multistore. The
+// state at `height` is committed to by the app hash of header `height + 1`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CosmosLockProof {
+    pub height: u64,
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub store_proof: Vec<u8>,
+    pub multistore_proof: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+struct LightBlock {
+    signed_header: SignedHeader,
+    validators: ValidatorSet,
+    next_validators: ValidatorSet,
+}
+
+// Tendermint light client: headers are accepted only when enough of a
+// trusted validator set signed them, bisecting when the jump is too large
+pub struct TendermintLightClient {
+    rpc: HttpClient,
+    chain_id: tendermint::chain::Id,
+    options: Options,
+    trusted: RwLock<BTreeMap<u64, LightBlock>>,
+}
+
+impl TendermintLightClient {
+    pub async fn new(config: &CosmosBridgeConfig) -> Result<Self, BridgeError> {
+        let rpc = HttpClient::new(config.rpc_url.as_str()).map_err(rpc_error)?;
+        let chain_id = config
+            .chain_id
+            .parse()
+            .map_err(|e: tendermint::Error| BridgeError::Rpc(e.to_string()))?;
+        let options = Options {
+            trust_threshold: TrustThreshold::ONE_THIRD,
+            trusting_period: Duration::from_secs(config.trusting_period_secs),
+            clock_drift: Duration::from_secs(10),
+        };
+
+        let client = Self {
+            rpc,
+            chain_id,
+            options,
+            trusted: RwLock::new(BTreeMap::new()),
+        };
+        let block = client.fetch(config.trusted_height).await?;
+        let hash = block.signed_header.header.hash().to_string();
+        if !hash.eq_ignore_ascii_case(&config.trusted_hash) {
+            return Err(BridgeError::Rpc(format!(
+                "header {} hashes to {}, not the trusted {}",
+                config.trusted_height, hash, config.trusted_hash
+            )));
+        }
+        client.trusted.write().await.insert(config.trusted_height, block);
+        Ok(client)
+    }
+
+    // App hash at `height`, verifying the header first if we do not hold it
+    pub async fn app_hash(&self, height: u64) -> Result<Vec<u8>, BridgeError> {
+        if let Some(block) = self.trusted.read().await.get(&height) {
+            return Ok(block.signed_header.header.app_hash.as_bytes().to_vec());
+        }
+        let block = self.verify_to(height).await?;
+        Ok(block.signed_header.header.app_hash.as_bytes().to_vec())
+    }
+
+    async fn verify_to(&self, target: u64) -> Result<LightBlock, BridgeError> {
+        let mut pending = vec![self.fetch(target).await?];
+
+        while let Some(untrusted) = pending.last().cloned() {
+            let height = untrusted.signed_header.header.height.value();
+            let trusted = {
+                let trusted = self.trusted.read().await;
+                trusted
+                    .range(..height)
+                    .next_back()
+                    .map(|(_, b)| b.clone())
+                    .ok_or_else(|| BridgeError::Rpc(format!("no trusted header below {}", height)))?
+            };
+
+            match self.verify_step(&trusted, &untrusted) {
+                Verdict::Success => {
+                    self.trusted.write().await.insert(height, untrusted);
+                    pending.pop();
+                }
+                Verdict::NotEnoughTrust(_) => {
+                    let from = trusted.signed_header.header.height.value();
+                    let midpoint = from + (height - from) / 2;
+                    if midpoint == from {
+                        return Err(BridgeError::InvalidProof);
+                    }
+                    pending.push(self.fetch(midpoint).await?);
+                }
+                Verdict::Invalid(e) => {
+                    return Err(BridgeError::Rpc(format!("header {} rejected: {}", height, e)));
+                }
+            }
+        }
+
+        self.trusted
+            .read()
+            .await
+            .get(&target)
+            .cloned()
+            .ok_or(BridgeError::InvalidProof)
+    }
+
+    fn verify_step(&self, trusted: &LightBlock, untrusted: &LightBlock) -> Verdict {
+        let header = &trusted.signed_header.header;
+        let trusted_state = TrustedBlockState {
+            chain_id: &self.chain_id,
+            header_time: header.time,
+            height: header.height,
+            next_validators: &trusted.next_validators,
+            next_validators_hash: header.next_validators_hash,
+        };
+        let untrusted_state = UntrustedBlockState {
+            signed_header: &untrusted.signed_header,
+            validators: &untrusted.validators,
+            next_validators: Some(&untrusted.next_validators),
+        };
+        ProdVerifier::default().verify_update_header(untrusted_state, trusted_state, &self.options, Time::now())
+    }
+
+    async fn fetch(&self, height: u64) -> Result<LightBlock, BridgeError> {
+        let at = Height::try_from(height).map_err(|e| BridgeError::Rpc(e.to_string()))?;
+        let next = Height::try_from(height + 1).map_err(|e| BridgeError::Rpc(e.to_string()))?;
+
+        let signed_header = self.rpc.commit(at).await.map_err(rpc_error)?.signed_header;
+        let validators = self.rpc.validators(at, Paging::All).await.map_err(rpc_error)?.validators;
+        let next_validators = self.rpc.validators(next, Paging::All).await.map_err(rpc_error)?.validators;
+
+        Ok(LightBlock {
+            signed_header,
+            validators: ValidatorSet::without_proposer(validators),
+            next_validators: ValidatorSet::without_proposer(next_validators),
+        })
+    }
+}
+
+pub struct CosmosBridge {
+    config: CosmosBridgeConfig,
+    rpc: HttpClient,
+    light_client: TendermintLightClient,
+    signing_key: SigningKey,
+    account: AccountId,
+}
+
+impl CosmosBridge {
+    pub async fn new(config: CosmosBridgeConfig, signing_key: SigningKey, prefix: &str) -> Result<Self, BridgeError> {
+        let rpc = HttpClient::new(config.rpc_url.as_str()).map_err(rpc_error)?;
+        let light_client = TendermintLightClient::new(&config).await?;
+        let account = signing_key
+            .public_key()
+            .account_id(prefix)
+            .map_err(|e| BridgeError::Signing(e.to_string()))?;
+
+        Ok(Self {
+            config,
+            rpc,
+            light_client,
+            signing_key,
+            account,
+        })
+    }
+
+    fn verify_lock(&self, proof: &CrossChainProof, lock: &CosmosLockProof, app_hash: &[u8]) -> Result<bool, BridgeError> {
+        let decode = |bytes: &[u8]| {
+            CommitmentProof::decode(bytes).map_err(|e| BridgeError::MalformedProof(e.to_string()))
+        };
+        let store_proof = decode(&lock.store_proof)?;
+        let multistore_proof = decode(&lock.multistore_proof)?;
+
+        if lock.key != format!("{}{}", LOCK_KEY_PREFIX, proof.nonce).into_bytes() {
+            return Ok(false);
+        }
+
+        // The record's existence proof yields the bridge store root, which the
+        // multistore proof must in turn place under the app hash
+        let ics23::commitment_proof::Proof::Exist(existence) = store_proof.proof.as_ref().ok_or(BridgeError::InvalidProof)?
+        else {
+            return Ok(false);
+        };
+        let store_root = ics23::calculate_existence_root::<HostFunctionsManager>(existence)
+            .map_err(|e| BridgeError::MalformedProof(e.to_string()))?;
+        if !ics23::verify_membership::<HostFunctionsManager>(
+            &store_proof,
+            &ics23::iavl_spec(),
+            &store_root,
+            &lock.key,
+            &lock.value,
+        ) || !ics23::verify_membership::<HostFunctionsManager>(
+            &multistore_proof,
+            &ics23::tendermint_spec(),
+            &app_hash.to_vec(),
+            BRIDGE_STORE,
+            &store_root,
+        ) {
+            return Ok(false);
+        }
+
+        let record: LockRecord =
+            serde_json::from_slice(&lock.value).map_err(|e| BridgeError::MalformedProof(e.to_string()))?;
+        Ok(record.amount == proof.amount
+            && record.recipient == proof.recipient
+            && record.destination == proof.destination_chain.to_string())
+    }
+
+    async fn broadcast(&self, message: Any) -> Result<TxHash, BridgeError> {
+        let account = self.base_account().await?;
+        let body = Body::new(vec![message], "", 0u32);
+        let fee = Fee::from_amount_and_gas(
+            Coin {
+                denom: self.config.fee_denom.parse().map_err(signing_error)?,
+                amount: self.config.fee_amount,
+            },
+            self.config.gas_limit,
+        );
+        let auth_info = SignerInfo::single_direct(Some(self.signing_key.public_key()), account.sequence).auth_info(fee);
+        let chain_id = self.config.chain_id.parse().map_err(signing_error)?;
+        let tx = SignDoc::new(&body, &auth_info, &chain_id, account.account_number)
+            .map_err(signing_error)?
+            .sign(&self.signing_key)
+            .map_err(signing_error)?;
+        let bytes = tx.to_bytes().map_err(signing_error)?;
+
+        let response = self.rpc.broadcast_tx_commit(bytes.clone()).await.map_err(rpc_error)?;
+        if response.check_tx.code.is_err() || response.tx_result.code.is_err() {
+            return Err(BridgeError::Rpc(format!(
+                "transaction rejected: {} {}",
+                response.check_tx.log, response.tx_result.log
+            )));
+        }
+        Ok(TxHash::from_slice(&Sha256::digest(&bytes)))
+    }
+
+    async fn base_account(&self) -> Result<BaseAccount, BridgeError> {
+        let request = QueryAccountRequest {
+            address: self.account.to_string(),
+        };
+        let response = self
+            .rpc
+            .abci_query(
+                Some("/cosmos.auth.v1beta1.Query/Account".to_string()),
+                request.encode_to_vec(),
+                None,
+                false,
+            )
+            .await
+            .map_err(rpc_error)?;
+        let account = QueryAccountResponse::decode(response.value.as_slice())
+            .map_err(|e| BridgeError::Rpc(e.to_string()))?
+            .account
+            .ok_or_else(|| BridgeError::Rpc(format!("account {} not found", self.account)))?;
+        BaseAccount::decode(account.value.as_slice()).map_err(|e| BridgeError::Rpc(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for CosmosBridge {
+    async fn verify_proof(&self, proof: &CrossChainProof) -> Result<bool, BridgeError> {
+        let ProofPayload::Cosmos(lock) = &proof.payload else {
+            return Ok(false);
+        };
+        if proof.source_chain != ChainId::Cosmos {
+            return Ok(false);
+        }
+        let app_hash = self.light_client.app_hash(lock.height + 1).await?;
+        self.verify_lock(proof, lock, &app_hash)
+    }
+
+    async fn lock_assets(&self, amount: u64, recipient: &str) -> Result<TxHash, BridgeError> {
+        let message = MsgLock {
+            sender: self.account.to_string(),
+            amount,
+            recipient: recipient.to_string(),
+        };
+        self.broadcast(Any {
+            type_url: "/idia.bridge.v1.MsgLock".to_string(),
+            value: message.encode_to_vec(),
+        })
+        .await
+    }
+
+    // The module re-verifies the proof through its own light client of the
+    // source chain, and the federation's approval; we only relay them
+    async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError> {
+        let message = MsgRelease {
+            relayer: self.account.to_string(),
+            source_chain: proof.source_chain.to_string(),
+            lock_tx: proof.lock_tx.as_bytes().to_vec(),
+            amount: proof.amount,
+            recipient: proof.recipient.clone(),
+            nonce: proof.nonce,
+            proof: bincode::serialize(&proof.payload).map_err(|e| BridgeError::MalformedProof(e.to_string()))?,
+            approval: proof.approval.clone().ok_or(BridgeError::QuorumNotReached)?,
+        };
+        self.broadcast(Any {
+            type_url: "/idia.bridge.v1.MsgRelease".to_string(),
+            value: message.encode_to_vec(),
+        })
+        .await
+    }
+}
+
+fn rpc_error(e: tendermint_rpc::Error) -> BridgeError {
+    BridgeError::Rpc(e.to_string())
+}
+
+fn signing_error(e: impl std::fmt::Display) -> BridgeError {
+    BridgeError::Signing(e.to_string())
+}
@@ -0,0 +1,475 @@
+use ethers::abi::{self, ParamType, Token};
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use ethers::utils::rlp::{self, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use super::beacon::BeaconLightClient;
+use super::types::{BridgeError, CrossChainProof};
+
+// Emitted by the bridge contract's `lock`; the sender is indexed
+const LOCK_EVENT: &str = "Locked(address,uint256,string,uint256)";
+
+// Finalized headers kept for proof verification, about a day of blocks. Also
+// the furthest a sync walks back, since anything older would be pruned.
+const HEADER_RETENTION: u64 = 8192;
+
+// A lock event located by its receipt, with the receipts-trie nodes from the
+// block's receipts root down to that receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EthereumReceiptProof {
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub receipt_index: u64,
+    pub log_index: usize,
+    pub receipt: Vec<u8>,
+    pub nodes: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+struct TrustedHeader {
+    hash: H256,
+    receipts_root: H256,
+}
+
+// Follows the execution chain below the finalized block that the beacon light
+// client verified from sync committee signatures. The execution node only
+// serves headers: each is re-hashed locally and must be the parent of one
+// already trusted, starting from the beacon-verified block hash.
+pub struct EthereumLightClient {
+    provider: Provider<Http>,
+    beacon: BeaconLightClient,
+    headers: RwLock<BTreeMap<u64, TrustedHeader>>,
+}
+
+impl EthereumLightClient {
+    pub fn new(provider: Provider<Http>, beacon: BeaconLightClient) -> Self {
+        Self {
+            provider,
+            beacon,
+            headers: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub async fn finalized(&self) -> u64 {
+        self.headers.read().await.keys().next_back().copied().unwrap_or_default()
+    }
+
+    pub async fn sync(&self) -> Result<u64, BridgeError> {
+        let finalized = self.beacon.sync().await?;
+        let tip = self.headers.read().await.iter().next_back().map(|(n, h)| (*n, h.hash));
+        if let Some((number, _)) = tip.filter(|(number, _)| finalized.number <= *number) {
+            return Ok(number);
+        }
+
+        // Walk back from the finalized block until it links onto what we hold,
+        // or at most a retention window
+        let floor = finalized.number.saturating_sub(HEADER_RETENTION - 1);
+        let mut expected = finalized.hash;
+        let mut number = finalized.number;
+        let mut batch = Vec::new();
+        loop {
+            let block = self.fetch(expected.into()).await?;
+            if block_number(&block)? != number {
+                return Err(BridgeError::Rpc(format!("header {:?} is not at height {}", expected, number)));
+            }
+            let hash = verify_header(&block)?;
+            if hash != expected {
+                return Err(BridgeError::Rpc(format!("header {} does not match its child", number)));
+            }
+            batch.push((
+                number,
+                TrustedHeader {
+                    hash,
+                    receipts_root: block.receipts_root,
+                },
+            ));
+
+            if let Some((tip, tip_hash)) = tip.filter(|(tip, _)| number == tip + 1) {
+                if block.parent_hash != tip_hash {
+                    return Err(BridgeError::Rpc(format!(
+                        "finalized chain does not descend from header {}",
+                        tip
+                    )));
+                }
+                break;
+            }
+            if number == floor {
+                break;
+            }
+            expected = block.parent_hash;
+            number -= 1;
+        }
+
+        let mut headers = self.headers.write().await;
+        // Past a gap wider than the window nothing held is still worth keeping
+        if tip.is_none_or(|(tip, _)| number > tip + 1) {
+            headers.clear();
+        }
+        headers.extend(batch);
+        if finalized.number > HEADER_RETENTION {
+            *headers = headers.split_off(&(finalized.number - HEADER_RETENTION));
+        }
+        Ok(finalized.number)
+    }
+
+    pub fn spawn_sync(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                match self.sync().await {
+                    Ok(finalized) => tracing::debug!("Ethereum light client finalized at {}", finalized),
+                    Err(e) => tracing::warn!("Ethereum header sync failed: {}", e),
+                }
+            }
+        })
+    }
+
+    // Collects the receipts of the lock's block and proves the lock's receipt
+    pub async fn receipt_proof(&self, lock_tx: H256, log_index: usize) -> Result<EthereumReceiptProof, BridgeError> {
+        let receipt = self
+            .provider
+            .get_transaction_receipt(lock_tx)
+            .await
+            .map_err(rpc_error)?
+            .ok_or_else(|| BridgeError::Rpc(format!("no receipt for {:?}", lock_tx)))?;
+        let block_number = receipt
+            .block_number
+            .ok_or_else(|| BridgeError::Rpc(format!("{:?} is pending", lock_tx)))?;
+        let block_hash = receipt.block_hash.unwrap_or_default();
+
+        let receipts = self
+            .provider
+            .get_block_receipts(block_number)
+            .await
+            .map_err(rpc_error)?;
+        let items: Vec<(Vec<u8>, Vec<u8>)> = receipts
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (nibbles(&rlp::encode(&(i as u64))), encode_receipt(r)))
+            .collect();
+
+        let receipt_index = receipt.transaction_index.as_u64();
+        let key = nibbles(&rlp::encode(&receipt_index));
+        let mut sorted = items.clone();
+        sorted.sort();
+        let mut nodes = Vec::new();
+        build_node(&sorted, 0, &key, &mut nodes);
+        nodes.reverse();
+
+        Ok(EthereumReceiptProof {
+            block_number: block_number.as_u64(),
+            block_hash,
+            receipt_index,
+            log_index,
+            receipt: items[receipt_index as usize].1.clone(),
+            nodes,
+        })
+    }
+
+    // Accepts the proof only if its receipt is committed to by a finalized header
+    // we verified and carries a matching lock event from `contract`
+    pub async fn verify(
+        &self,
+        proof: &CrossChainProof,
+        receipt_proof: &EthereumReceiptProof,
+        contract: Address,
+    ) -> Result<bool, BridgeError> {
+        let receipts_root = match self.headers.read().await.get(&receipt_proof.block_number) {
+            Some(header) if header.hash == receipt_proof.block_hash => header.receipts_root,
+            _ => return Ok(false),
+        };
+
+        let key = rlp::encode(&receipt_proof.receipt_index);
+        match verify_trie_proof(receipts_root, &key, &receipt_proof.nodes)? {
+            Some(value) if value == receipt_proof.receipt => {}
+            _ => return Ok(false),
+        }
+
+        let Some((amount, recipient, nonce)) = lock_event(&receipt_proof.receipt, receipt_proof.log_index, contract)?
+        else {
+            return Ok(false);
+        };
+        Ok(amount == U256::from(proof.amount) && recipient == proof.recipient && nonce == U256::from(proof.nonce))
+    }
+
+    async fn fetch(&self, id: BlockId) -> Result<Block<H256>, BridgeError> {
+        self.provider
+            .get_block(id)
+            .await
+            .map_err(rpc_error)?
+            .ok_or_else(|| BridgeError::Rpc(format!("block {:?} not found", id)))
+    }
+}
+
+fn block_number(block: &Block<H256>) -> Result<u64, BridgeError> {
+    block
+        .number
+        .map(|n| n.as_u64())
+        .ok_or_else(|| BridgeError::Rpc("block without a number".to_string()))
+}
+
+// Re-derives the block hash from the header fields and checks the node's claim
+fn verify_header(block: &Block<H256>) -> Result<H256, BridgeError> {
+    let mut stream = RlpStream::new();
+    stream.begin_unbounded_list();
+    stream.append(&block.parent_hash);
+    stream.append(&block.uncles_hash);
+    stream.append(&block.author.unwrap_or_default());
+    stream.append(&block.state_root);
+    stream.append(&block.transactions_root);
+    stream.append(&block.receipts_root);
+    stream.append(&block.logs_bloom.unwrap_or_default());
+    stream.append(&block.difficulty);
+    stream.append(&block.number.unwrap_or_default());
+    stream.append(&block.gas_limit);
+    stream.append(&block.gas_used);
+    stream.append(&block.timestamp);
+    stream.append(&block.extra_data.as_ref());
+    stream.append(&block.mix_hash.unwrap_or_default());
+    stream.append(&block.nonce.unwrap_or_default());
+    // Fork-dependent trailing fields, present from London, Shanghai and Cancun on
+    if let Some(base_fee) = block.base_fee_per_gas {
+        stream.append(&base_fee);
+    }
+    if let Some(withdrawals_root) = block.withdrawals_root {
+        stream.append(&withdrawals_root);
+    }
+    if let (Some(blob_gas_used), Some(excess_blob_gas)) = (block.blob_gas_used, block.excess_blob_gas) {
+        stream.append(&blob_gas_used);
+        stream.append(&excess_blob_gas);
+    }
+    if let Some(parent_beacon_block_root) = block.parent_beacon_block_root {
+        stream.append(&parent_beacon_block_root);
+    }
+    stream.finalize_unbounded_list();
+
+    let hash = H256::from(keccak256(stream.out()));
+    if block.hash != Some(hash) {
+        return Err(BridgeError::Rpc(format!(
+            "header {} does not hash to {:?}",
+            block.number.unwrap_or_default(),
+            block.hash
+        )));
+    }
+    Ok(hash)
+}
+
+// Receipts-trie leaf: typed receipts are prefixed with their transaction type
+fn encode_receipt(receipt: &TransactionReceipt) -> Vec<u8> {
+    let encoded = rlp::encode(receipt).to_vec();
+    match receipt.transaction_type.map(|t| t.as_u64()) {
+        Some(t) if t > 0 => [vec![t as u8], encoded].concat(),
+        _ => encoded,
+    }
+}
+
+// Decodes `(amount, recipient, nonce)` from the receipt's log at `log_index`,
+// if the transaction succeeded and the log is our contract's lock event
+fn lock_event(receipt: &[u8], log_index: usize, contract: Address) -> Result<Option<(U256, String, U256)>, BridgeError> {
+    let body = match receipt.first() {
+        Some(&t) if t < 0x7f => &receipt[1..],
+        _ => receipt,
+    };
+    let receipt = Rlp::new(body);
+    if receipt.val_at::<u64>(0).map_err(decoder_error)? != 1 {
+        return Ok(None);
+    }
+
+    let logs = receipt.at(3).map_err(decoder_error)?;
+    if log_index >= logs.item_count().map_err(decoder_error)? {
+        return Ok(None);
+    }
+    let log = logs.at(log_index).map_err(decoder_error)?;
+    let address: Address = log.val_at(0).map_err(decoder_error)?;
+    let topics: Vec<H256> = log.list_at(1).map_err(decoder_error)?;
+    let data: Vec<u8> = log.val_at(2).map_err(decoder_error)?;
+    if address != contract || topics.first() != Some(&H256::from(keccak256(LOCK_EVENT))) {
+        return Ok(None);
+    }
+
+    let tokens = abi::decode(&[ParamType::Uint(256), ParamType::String, ParamType::Uint(256)], &data)
+        .map_err(|e| BridgeError::MalformedProof(e.to_string()))?;
+    match tokens.as_slice() {
+        [Token::Uint(amount), Token::String(recipient), Token::Uint(nonce)] => {
+            Ok(Some((*amount, recipient.clone(), *nonce)))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Walks a Merkle-Patricia proof from `root` along `key`, returning the value
+// stored there, or None if the proof shows the key is absent
+fn verify_trie_proof(root: H256, key: &[u8], nodes: &[Vec<u8>]) -> Result<Option<Vec<u8>>, BridgeError> {
+    let by_hash: std::collections::HashMap<H256, &[u8]> = nodes
+        .iter()
+        .map(|node| (H256::from(keccak256(node)), node.as_slice()))
+        .collect();
+    let path = nibbles(key);
+    let mut position = 0;
+    let mut node: &[u8] = by_hash
+        .get(&root)
+        .ok_or_else(|| BridgeError::MalformedProof("root node missing".to_string()))?;
+
+    loop {
+        let rlp = Rlp::new(node);
+        match rlp.item_count().map_err(decoder_error)? {
+            17 => {
+                if position == path.len() {
+                    let value: Vec<u8> = rlp.val_at(16).map_err(decoder_error)?;
+                    return Ok((!value.is_empty()).then_some(value));
+                }
+                let child = rlp.at(path[position] as usize).map_err(decoder_error)?;
+                position += 1;
+                match resolve(&child, &by_hash)? {
+                    Some(next) => node = next,
+                    None => return Ok(None),
+                }
+            }
+            2 => {
+                let encoded_path: Vec<u8> = rlp.val_at(0).map_err(decoder_error)?;
+                let (segment, is_leaf) = decode_hex_prefix(&encoded_path)?;
+                if !path[position..].starts_with(&segment) {
+                    return Ok(None);
+                }
+                position += segment.len();
+                if is_leaf {
+                    if position != path.len() {
+                        return Ok(None);
+                    }
+                    return Ok(Some(rlp.val_at(1).map_err(decoder_error)?));
+                }
+                let child = rlp.at(1).map_err(decoder_error)?;
+                match resolve(&child, &by_hash)? {
+                    Some(next) => node = next,
+                    None => return Ok(None),
+                }
+            }
+            _ => return Err(BridgeError::MalformedProof("invalid trie node".to_string())),
+        }
+    }
+}
+
+// A child reference is its hash, or the node itself when under 32 bytes
+fn resolve<'a>(
+    child: &Rlp<'a>,
+    by_hash: &std::collections::HashMap<H256, &'a [u8]>,
+) -> Result<Option<&'a [u8]>, BridgeError> {
+    if child.is_list() {
+        return Ok(Some(child.as_raw()));
+    }
+    let data = child.data().map_err(decoder_error)?;
+    if data.is_empty() {
+        return Ok(None);
+    }
+    if data.len() != 32 {
+        return Err(BridgeError::MalformedProof("invalid child reference".to_string()));
+    }
+    by_hash
+        .get(&H256::from_slice(data))
+        .copied()
+        .map(Some)
+        .ok_or_else(|| BridgeError::MalformedProof("proof is missing a node".to_string()))
+}
+
+// Encodes the trie over `items` (sorted, sharing their first `depth` nibbles),
+// collecting every node on the path to `target` into `proof`
+fn build_node(items: &[(Vec<u8>, Vec<u8>)], depth: usize, target: &[u8], proof: &mut Vec<Vec<u8>>) -> Vec<u8> {
+    let encoded = if let [(key, value)] = items {
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&encode_hex_prefix(&key[depth..], true));
+        stream.append(value);
+        stream.out().to_vec()
+    } else {
+        let first = &items[0].0;
+        let shared = (depth..first.len())
+            .take_while(|&i| items.iter().all(|(k, _)| k.get(i) == Some(&first[i])))
+            .count();
+
+        if shared > 0 {
+            let mut stream = RlpStream::new_list(2);
+            stream.append(&encode_hex_prefix(&first[depth..depth + shared], false));
+            append_child(&mut stream, build_node(items, depth + shared, target, proof));
+            stream.out().to_vec()
+        } else {
+            let mut stream = RlpStream::new_list(17);
+            for nibble in 0..16u8 {
+                let children: Vec<_> = items
+                    .iter()
+                    .filter(|(k, _)| k.get(depth) == Some(&nibble))
+                    .cloned()
+                    .collect();
+                if children.is_empty() {
+                    stream.append_empty_data();
+                } else {
+                    append_child(&mut stream, build_node(&children, depth + 1, target, proof));
+                }
+            }
+            match items.iter().find(|(k, _)| k.len() == depth) {
+                Some((_, value)) => stream.append(value),
+                None => stream.append_empty_data(),
+            };
+            stream.out().to_vec()
+        }
+    };
+
+    if items.iter().any(|(k, _)| k == target) {
+        proof.push(encoded.clone());
+    }
+    encoded
+}
+
+fn append_child(stream: &mut RlpStream, encoded: Vec<u8>) {
+    if encoded.len() < 32 {
+        stream.append_raw(&encoded, 1);
+    } else {
+        stream.append(&H256::from(keccak256(&encoded)));
+    }
+}
+
+fn nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+fn encode_hex_prefix(path: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut encoded = Vec::with_capacity(path.len() / 2 + 1);
+    let rest = if path.len() % 2 == 1 {
+        encoded.push(((flag + 1) << 4) | path[0]);
+        &path[1..]
+    } else {
+        encoded.push(flag << 4);
+        path
+    };
+    encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    encoded
+}
+
+fn decode_hex_prefix(encoded: &[u8]) -> Result<(Vec<u8>, bool), BridgeError> {
+    let first = *encoded
+        .first()
+        .ok_or_else(|| BridgeError::MalformedProof("empty trie path".to_string()))?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(BridgeError::MalformedProof("invalid trie path flag".to_string()));
+    }
+    let mut path = Vec::new();
+    if flag & 1 == 1 {
+        path.push(first & 0x0f);
+    }
+    path.extend(nibbles(&encoded[1..]));
+    Ok((path, flag & 2 == 2))
+}
+
+fn rpc_error(e: ProviderError) -> BridgeError {
+    BridgeError::Rpc(e.to_string())
+}
+
+fn decoder_error(e: rlp::DecoderError) -> BridgeError {
+    BridgeError::MalformedProof(e.to_string())
+}
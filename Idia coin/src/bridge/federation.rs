@@ -0,0 +1,424 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use threshold_crypto::{PublicKey, PublicKeySet, SecretKeyShare, Signature, SignatureShare};
+use tokio::sync::RwLock;
+
+use super::manager::ChainAdapter;
+use super::types::{BridgeError, CrossChainProof, TxHash};
+use super::velocity::VelocityMonitor;
+
+pub type ReleaseId = [u8; 32];
+
+// A release request no quorum has formed for by then is dropped
+const PENDING_TTL_HOURS: i64 = 24;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FederationError {
+    #[error("Unknown operator {0}")]
+    UnknownOperator(u32),
+    #[error("Invalid signature share from operator {0}")]
+    InvalidShare(u32),
+    #[error("Unknown release")]
+    UnknownRelease,
+    #[error("Combined signature does not verify")]
+    InvalidSignature,
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+}
+
+// What operators sign: the transfer fields plus a hash of the full proof, so a
+// quorum approves exactly one release of one lock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseRequest {
+    pub proof: CrossChainProof,
+}
+
+impl ReleaseRequest {
+    const DOMAIN_TAG: &'static [u8] = b"idia-bridge-release-v1";
+
+    pub fn new(proof: CrossChainProof) -> Self {
+        Self { proof }
+    }
+
+    pub fn message(&self) -> Result<Vec<u8>, FederationError> {
+        let proof = &self.proof;
+        let mut buf = Vec::new();
+        buf.extend_from_slice(Self::DOMAIN_TAG);
+        buf.extend_from_slice(proof.source_chain.to_string().as_bytes());
+        buf.push(0);
+        buf.extend_from_slice(proof.destination_chain.to_string().as_bytes());
+        buf.push(0);
+        buf.extend_from_slice(proof.lock_tx.as_bytes());
+        buf.extend_from_slice(&proof.amount.to_le_bytes());
+        buf.extend_from_slice(&(proof.recipient.len() as u32).to_le_bytes());
+        buf.extend_from_slice(proof.recipient.as_bytes());
+        buf.extend_from_slice(&proof.nonce.to_le_bytes());
+        buf.extend_from_slice(&Sha256::digest(bincode::serialize(&proof.payload)?));
+        Ok(buf)
+    }
+
+    pub fn id(&self) -> Result<ReleaseId, FederationError> {
+        Ok(Sha256::digest(self.message()?).into())
+    }
+}
+
+// Exchanged between bridge operators over the federation's gossip channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FederationMessage {
+    // Broadcast by the operator that first observed the lock
+    Propose { proposer: u32, request: ReleaseRequest },
+    // An operator's share, sent only after it verified the proof itself
+    Share { release_id: ReleaseId, signer: u32, share: SignatureShare },
+    // Broadcast once shares combine into the federation signature
+    Finalized { release_id: ReleaseId, signature: Signature },
+    // Sent periodically so idle operators still show as live
+    Heartbeat { signer: u32, at: DateTime<Utc>, share: SignatureShare },
+}
+
+impl FederationMessage {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FederationError> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FederationError> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingRelease {
+    pub request: ReleaseRequest,
+    pub shares: BTreeMap<u32, SignatureShare>,
+    pub signature: Option<Signature>,
+    pub created_at: DateTime<Utc>,
+}
+
+// One operator's view of the federation: its key share and a quorum tracker
+// per pending release. Keys come from the governance DKG.
+pub struct Federation {
+    public_key_set: PublicKeySet,
+    secret_key_share: SecretKeyShare,
+    node_index: u32,
+    size: u32,
+    pending: HashMap<ReleaseId, PendingRelease>,
+    last_seen: HashMap<u32, DateTime<Utc>>,
+}
+
+impl Federation {
+    pub fn new(public_key_set: PublicKeySet, secret_key_share: SecretKeyShare, node_index: u32, size: u32) -> Self {
+        Self {
+            public_key_set,
+            secret_key_share,
+            node_index,
+            size,
+            pending: HashMap::new(),
+            last_seen: HashMap::new(),
+        }
+    }
+
+    // combine_signatures needs threshold + 1 shares
+    pub fn quorum(&self) -> usize {
+        self.public_key_set.threshold() + 1
+    }
+
+    pub fn pending(&self, release_id: &ReleaseId) -> Option<&PendingRelease> {
+        self.pending.get(release_id)
+    }
+
+    pub fn signature(&self, release_id: &ReleaseId) -> Option<&Signature> {
+        self.pending.get(release_id).and_then(|p| p.signature.as_ref())
+    }
+
+    // Valid shares received so far; operators keep sending theirs after the
+    // quorum formed, which is what extra confirmations count on
+    pub fn confirmations(&self, release_id: &ReleaseId) -> usize {
+        self.pending.get(release_id).map_or(0, |p| p.shares.len())
+    }
+
+    // When each operator was last heard from with a valid signature share
+    pub fn last_seen(&self) -> Vec<(u32, Option<DateTime<Utc>>)> {
+        (0..self.size).map(|i| (i, self.last_seen.get(&i).copied())).collect()
+    }
+
+    pub fn heartbeat(&mut self) -> FederationMessage {
+        let at = Utc::now();
+        self.last_seen.insert(self.node_index, at);
+        FederationMessage::Heartbeat {
+            signer: self.node_index,
+            at,
+            share: self.secret_key_share.sign(heartbeat_message(at)),
+        }
+    }
+
+    pub fn propose(&mut self, request: ReleaseRequest) -> Result<FederationMessage, FederationError> {
+        self.track(request.clone())?;
+        Ok(FederationMessage::Propose {
+            proposer: self.node_index,
+            request,
+        })
+    }
+
+    // Call only after verifying `request.proof` against the source chain
+    pub fn approve(&mut self, request: ReleaseRequest) -> Result<Vec<FederationMessage>, FederationError> {
+        let release_id = self.track(request.clone())?;
+        let share = self.secret_key_share.sign(request.message()?);
+        let mut messages = vec![FederationMessage::Share {
+            release_id,
+            signer: self.node_index,
+            share: share.clone(),
+        }];
+        messages.extend(self.add_share(release_id, self.node_index, share)?);
+        Ok(messages)
+    }
+
+    // Proposals are only tracked; approving them is the caller's decision
+    pub fn handle_message(&mut self, message: FederationMessage) -> Result<Option<FederationMessage>, FederationError> {
+        match message {
+            FederationMessage::Propose { proposer, request } => {
+                if proposer >= self.size {
+                    return Err(FederationError::UnknownOperator(proposer));
+                }
+                self.track(request)?;
+                Ok(None)
+            }
+            FederationMessage::Share { release_id, signer, share } => self.add_share(release_id, signer, share),
+            FederationMessage::Finalized { release_id, signature } => {
+                let pending = self.pending.get_mut(&release_id).ok_or(FederationError::UnknownRelease)?;
+                if !self.public_key_set.public_key().verify(&signature, pending.request.message()?) {
+                    return Err(FederationError::InvalidSignature);
+                }
+                pending.signature = Some(signature);
+                Ok(None)
+            }
+            FederationMessage::Heartbeat { signer, at, share } => {
+                if signer >= self.size {
+                    return Err(FederationError::UnknownOperator(signer));
+                }
+                if !self.public_key_set.public_key_share(signer as u64).verify(&share, heartbeat_message(at)) {
+                    return Err(FederationError::InvalidShare(signer));
+                }
+                // A replayed heartbeat carries its old timestamp and cannot refresh liveness
+                let seen = self.last_seen.entry(signer).or_insert(at);
+                *seen = (*seen).max(at.min(Utc::now()));
+                Ok(None)
+            }
+        }
+    }
+
+    // Drops expired releases that never reached quorum
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - Duration::hours(PENDING_TTL_HOURS);
+        self.pending.retain(|_, p| p.signature.is_some() || p.created_at >= cutoff);
+    }
+
+    pub fn remove(&mut self, release_id: &ReleaseId) -> Option<PendingRelease> {
+        self.pending.remove(release_id)
+    }
+
+    fn track(&mut self, request: ReleaseRequest) -> Result<ReleaseId, FederationError> {
+        let release_id = request.id()?;
+        self.pending.entry(release_id).or_insert_with(|| PendingRelease {
+            request,
+            shares: BTreeMap::new(),
+            signature: None,
+            created_at: Utc::now(),
+        });
+        Ok(release_id)
+    }
+
+    fn add_share(
+        &mut self,
+        release_id: ReleaseId,
+        signer: u32,
+        share: SignatureShare,
+    ) -> Result<Option<FederationMessage>, FederationError> {
+        if signer >= self.size {
+            return Err(FederationError::UnknownOperator(signer));
+        }
+        let quorum = self.quorum();
+        let pending = self.pending.get_mut(&release_id).ok_or(FederationError::UnknownRelease)?;
+        let message = pending.request.message()?;
+        if !self.public_key_set.public_key_share(signer as u64).verify(&share, &message) {
+            return Err(FederationError::InvalidShare(signer));
+        }
+        pending.shares.insert(signer, share);
+        self.last_seen.insert(signer, Utc::now());
+
+        if pending.signature.is_some() || pending.shares.len() < quorum {
+            return Ok(None);
+        }
+        let shares = pending.shares.iter().map(|(&i, s)| (i as u64, s));
+        let signature = self
+            .public_key_set
+            .combine_signatures(shares)
+            .map_err(|_| FederationError::InvalidSignature)?;
+        if !self.public_key_set.public_key().verify(&signature, &message) {
+            return Err(FederationError::InvalidSignature);
+        }
+        pending.signature = Some(signature.clone());
+        tracing::info!("Federation quorum reached for release of {:?}", pending.request.proof.lock_tx);
+        Ok(Some(FederationMessage::Finalized { release_id, signature }))
+    }
+}
+
+// For destinations that enforce releases themselves: the approval attached to
+// `proof` must be the federation's signature over exactly this release
+pub fn verify_approval(group_key: &PublicKey, proof: &CrossChainProof) -> Result<(), BridgeError> {
+    let approval = proof.approval.as_deref().ok_or(BridgeError::QuorumNotReached)?;
+    let bytes: [u8; 96] = approval
+        .try_into()
+        .map_err(|_| BridgeError::MalformedProof("approval is not a signature".to_string()))?;
+    let signature = Signature::from_bytes(bytes).map_err(|e| BridgeError::MalformedProof(e.to_string()))?;
+    let message = ReleaseRequest::new(proof.clone())
+        .message()
+        .map_err(|e| BridgeError::MalformedProof(e.to_string()))?;
+    if !group_key.verify(&signature, message) {
+        return Err(BridgeError::QuorumNotReached);
+    }
+    Ok(())
+}
+
+fn heartbeat_message(at: DateTime<Utc>) -> Vec<u8> {
+    let mut buf = b"idia-bridge-heartbeat-v1".to_vec();
+    buf.extend_from_slice(&at.timestamp().to_le_bytes());
+    buf
+}
+
+// Wraps a destination adapter so it refuses any release the federation has
+// not signed off on, and hands the federation signature to the adapter in
+// `proof.approval` so the destination can check it too
+pub struct FederatedAdapter<A> {
+    inner: A,
+    federation: Arc<RwLock<Federation>>,
+    velocity: Option<Arc<VelocityMonitor>>,
+}
+
+impl<A> FederatedAdapter<A> {
+    pub fn new(inner: A, federation: Arc<RwLock<Federation>>) -> Self {
+        Self {
+            inner,
+            federation,
+            velocity: None,
+        }
+    }
+
+    // While a velocity alarm on the destination is active, releases wait for
+    // shares beyond the quorum
+    pub fn with_velocity(mut self, velocity: Arc<VelocityMonitor>) -> Self {
+        self.velocity = Some(velocity);
+        self
+    }
+}
+
+#[async_trait]
+impl<A: ChainAdapter + Send + Sync> ChainAdapter for FederatedAdapter<A> {
+    async fn verify_proof(&self, proof: &CrossChainProof) -> Result<bool, BridgeError> {
+        self.inner.verify_proof(proof).await
+    }
+
+    async fn lock_assets(&self, amount: u64, recipient: &str) -> Result<TxHash, BridgeError> {
+        self.inner.lock_assets(amount, recipient).await
+    }
+
+    async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError> {
+        let release_id = ReleaseRequest::new(proof.clone())
+            .id()
+            .map_err(|e| BridgeError::MalformedProof(e.to_string()))?;
+        let signature = {
+            let federation = self.federation.read().await;
+            let signature = federation.signature(&release_id).cloned().ok_or(BridgeError::QuorumNotReached)?;
+            if let Some(velocity) = &self.velocity {
+                let extra = velocity.extra_confirmations(proof.destination_chain, Utc::now()).await;
+                if federation.confirmations(&release_id) < federation.quorum() + extra {
+                    return Err(BridgeError::QuorumNotReached);
+                }
+            }
+            signature
+        };
+        let mut approved = proof.clone();
+        approved.approval = Some(signature.to_bytes().to_vec());
+        let release_tx = self.inner.release_assets(&approved).await?;
+        self.federation.write().await.remove(&release_id);
+        Ok(release_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::types::{ChainId, ProofPayload};
+    use std::sync::Mutex;
+    use threshold_crypto::SecretKeySet;
+
+    // Destination that enforces the approval the way a contract or module would
+    struct Destination {
+        group_key: PublicKey,
+        released: Mutex<Vec<CrossChainProof>>,
+    }
+
+    #[async_trait]
+    impl ChainAdapter for Destination {
+        async fn verify_proof(&self, _proof: &CrossChainProof) -> Result<bool, BridgeError> {
+            Ok(true)
+        }
+
+        async fn lock_assets(&self, _amount: u64, _recipient: &str) -> Result<TxHash, BridgeError> {
+            Ok(TxHash::zero())
+        }
+
+        async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError> {
+            verify_approval(&self.group_key, proof)?;
+            self.released.lock().unwrap().push(proof.clone());
+            Ok(TxHash::repeat_byte(1))
+        }
+    }
+
+    fn proof(nonce: u64) -> CrossChainProof {
+        CrossChainProof {
+            source_chain: ChainId::Idia,
+            destination_chain: ChainId::Bitcoin,
+            lock_tx: TxHash::repeat_byte(9),
+            amount: 1_000,
+            recipient: "recipient".to_string(),
+            nonce,
+            payload: ProofPayload::Opaque(vec![1, 2, 3]),
+            approval: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_release_carries_verifiable_approval() {
+        let keys = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut operators: Vec<Federation> = (0..3)
+            .map(|i| Federation::new(keys.public_keys(), keys.secret_key_share(i), i as u32, 3))
+            .collect();
+
+        let request = ReleaseRequest::new(proof(1));
+        operators[0].approve(request.clone()).unwrap();
+        let messages = operators[1].approve(request).unwrap();
+        for message in messages {
+            operators[0].handle_message(message).ok();
+        }
+
+        let destination = Destination {
+            group_key: keys.public_keys().public_key(),
+            released: Mutex::new(Vec::new()),
+        };
+        let adapter = FederatedAdapter::new(destination, Arc::new(RwLock::new(operators.remove(0))));
+
+        // No quorum formed for this one, so it never reaches the destination
+        assert!(matches!(adapter.release_assets(&proof(2)).await, Err(BridgeError::QuorumNotReached)));
+        adapter.release_assets(&proof(1)).await.unwrap();
+        let released = adapter.inner.released.lock().unwrap().clone();
+        assert_eq!(released.len(), 1);
+
+        // The approval covers exactly this release
+        let mut altered = released[0].clone();
+        altered.amount += 1;
+        assert!(verify_approval(&keys.public_keys().public_key(), &altered).is_err());
+    }
+}
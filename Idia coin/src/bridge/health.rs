@@ -0,0 +1,240 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use ethers::prelude::{Http, Middleware, Provider};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::metrics::bridge::{
+    BRIDGE_FAILED_OPERATIONS, BRIDGE_PAUSED, BRIDGE_PENDING_OPERATIONS, BRIDGE_RPC_UP, BRIDGE_SIGNERS_LIVE,
+    BRIDGE_SIGNER_QUORUM, BRIDGE_SYNCED_HEAD,
+};
+
+use super::federation::Federation;
+use super::operations::{OperationState, OperationStore};
+use super::pause::BridgePause;
+use super::types::{BridgeError, ChainId};
+
+// Operators silent for longer than this count as down
+const SIGNER_LIVENESS_SECS: i64 = 300;
+
+// Asks a chain's RPC endpoint for its current head
+#[async_trait]
+pub trait ChainProbe: Send + Sync {
+    async fn head(&self) -> Result<u64, BridgeError>;
+}
+
+#[async_trait]
+impl ChainProbe for Provider<Http> {
+    async fn head(&self) -> Result<u64, BridgeError> {
+        let number = self.get_block_number().await.map_err(|e| BridgeError::Rpc(e.to_string()))?;
+        Ok(number.as_u64())
+    }
+}
+
+#[async_trait]
+impl ChainProbe for solana_client::nonblocking::rpc_client::RpcClient {
+    async fn head(&self) -> Result<u64, BridgeError> {
+        self.get_slot().await.map_err(|e| BridgeError::Rpc(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl ChainProbe for tendermint_rpc::HttpClient {
+    async fn head(&self) -> Result<u64, BridgeError> {
+        use tendermint_rpc::Client;
+        let block = self.latest_block().await.map_err(|e| BridgeError::Rpc(e.to_string()))?;
+        Ok(block.block.header.height.value())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChainHealth {
+    pub rpc_reachable: bool,
+    pub head: Option<u64>,
+    // When `head` was last read successfully
+    pub synced_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub pending_operations: usize,
+    pub failed_operations: usize,
+    pub paused: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignerStatus {
+    pub index: u32,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub live: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FederationHealth {
+    pub quorum: usize,
+    pub live: usize,
+    pub signers: Vec<SignerStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeHealthReport {
+    // Every probed RPC answers and enough signers are live to form a quorum
+    pub healthy: bool,
+    pub chains: BTreeMap<String, ChainHealth>,
+    pub federation: Option<FederationHealth>,
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+// Periodically probes every adapter's chain and summarizes the operation
+// store and federation, keeping the Prometheus gauges in step
+pub struct BridgeHealth {
+    probes: HashMap<ChainId, Arc<dyn ChainProbe>>,
+    operations: Arc<RwLock<OperationStore>>,
+    federation: Option<Arc<RwLock<Federation>>>,
+    pause: Option<Arc<BridgePause>>,
+    report: RwLock<BridgeHealthReport>,
+}
+
+impl BridgeHealth {
+    pub fn new(operations: Arc<RwLock<OperationStore>>) -> Self {
+        Self {
+            probes: HashMap::new(),
+            operations,
+            federation: None,
+            pause: None,
+            report: RwLock::new(BridgeHealthReport {
+                healthy: false,
+                chains: BTreeMap::new(),
+                federation: None,
+                checked_at: None,
+            }),
+        }
+    }
+
+    pub fn with_probe(mut self, chain: ChainId, probe: Arc<dyn ChainProbe>) -> Self {
+        self.probes.insert(chain, probe);
+        self
+    }
+
+    pub fn with_federation(mut self, federation: Arc<RwLock<Federation>>) -> Self {
+        self.federation = Some(federation);
+        self
+    }
+
+    pub fn with_pause(mut self, pause: Arc<BridgePause>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    pub async fn report(&self) -> BridgeHealthReport {
+        self.report.read().await.clone()
+    }
+
+    pub async fn check(&self) -> BridgeHealthReport {
+        let now = Utc::now();
+        let previous = self.report.read().await.chains.clone();
+        let mut chains: HashMap<ChainId, ChainHealth> = HashMap::new();
+
+        for (chain, probe) in &self.probes {
+            let mut health = previous.get(&chain.to_string()).cloned().unwrap_or_default();
+            match probe.head().await {
+                Ok(head) => {
+                    health.rpc_reachable = true;
+                    health.head = Some(head);
+                    health.synced_at = Some(now);
+                    health.last_error = None;
+                }
+                Err(e) => {
+                    health.rpc_reachable = false;
+                    health.last_error = Some(e.to_string());
+                }
+            }
+            chains.insert(*chain, health);
+        }
+
+        for operation in self.operations.read().await.list(None) {
+            let health = chains.entry(operation.to_chain).or_default();
+            match operation.state {
+                OperationState::Failed | OperationState::Refunding => health.failed_operations += 1,
+                state if !state.is_terminal() => health.pending_operations += 1,
+                _ => {}
+            }
+        }
+
+        if let Some(pause) = &self.pause {
+            let (all, paused) = pause.paused().await;
+            for (chain, health) in chains.iter_mut() {
+                health.paused = all
+                    .as_ref()
+                    .or_else(|| paused.get(chain))
+                    .map(|record| record.reason.clone());
+            }
+        }
+
+        let federation = match &self.federation {
+            Some(federation) => Some(federation_health(&*federation.read().await, now)),
+            None => None,
+        };
+
+        for (chain, health) in &chains {
+            let label = chain.to_string();
+            BRIDGE_PENDING_OPERATIONS.with_label_values(&[&label]).set(health.pending_operations as i64);
+            BRIDGE_FAILED_OPERATIONS.with_label_values(&[&label]).set(health.failed_operations as i64);
+            BRIDGE_PAUSED.with_label_values(&[&label]).set(health.paused.is_some() as i64);
+            if self.probes.contains_key(chain) {
+                BRIDGE_RPC_UP.with_label_values(&[&label]).set(health.rpc_reachable as i64);
+            }
+            if let Some(head) = health.head {
+                BRIDGE_SYNCED_HEAD.with_label_values(&[&label]).set(head as i64);
+            }
+        }
+        if let Some(federation) = &federation {
+            BRIDGE_SIGNERS_LIVE.set(federation.live as i64);
+            BRIDGE_SIGNER_QUORUM.set(federation.quorum as i64);
+        }
+
+        let healthy = self
+            .probes
+            .keys()
+            .all(|chain| chains.get(chain).is_some_and(|h| h.rpc_reachable))
+            && federation.as_ref().map_or(true, |f| f.live >= f.quorum);
+        let report = BridgeHealthReport {
+            healthy,
+            chains: chains.into_iter().map(|(chain, health)| (chain.to_string(), health)).collect(),
+            federation,
+            checked_at: Some(now),
+        };
+        *self.report.write().await = report.clone();
+        report
+    }
+
+    pub fn spawn(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let report = self.check().await;
+                if !report.healthy {
+                    tracing::warn!("Bridge health check failed");
+                }
+            }
+        })
+    }
+}
+
+fn federation_health(federation: &Federation, now: DateTime<Utc>) -> FederationHealth {
+    let cutoff = now - Duration::seconds(SIGNER_LIVENESS_SECS);
+    let signers: Vec<SignerStatus> = federation
+        .last_seen()
+        .into_iter()
+        .map(|(index, last_seen)| SignerStatus {
+            index,
+            last_seen,
+            live: last_seen.is_some_and(|at| at >= cutoff),
+        })
+        .collect();
+    FederationHealth {
+        quorum: federation.quorum(),
+        live: signers.iter().filter(|s| s.live).count(),
+        signers,
+    }
+}
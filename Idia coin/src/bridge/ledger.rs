@@ -0,0 +1,260 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::manager::ChainAdapter;
+use super::types::{BridgeError, ChainId, CrossChainProof, TxHash};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LedgerError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Backing invariant violated on {chain}: {reason}")]
+    InvariantViolated { chain: ChainId, reason: String },
+}
+
+// Native IDIA held against one foreign chain, and the wrapped IDIA issued there
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ChainLedger {
+    pub locked: u64,
+    pub unlocked: u64,
+    pub minted: u64,
+    pub burned: u64,
+}
+
+impl ChainLedger {
+    pub fn backing(&self) -> u64 {
+        self.locked.saturating_sub(self.unlocked)
+    }
+
+    pub fn wrapped_supply(&self) -> u64 {
+        self.minted.saturating_sub(self.burned)
+    }
+
+    // None while nothing is outstanding
+    pub fn backing_ratio(&self) -> Option<f64> {
+        let supply = self.wrapped_supply();
+        (supply > 0).then(|| self.backing() as f64 / supply as f64)
+    }
+
+    fn violation(&self) -> Option<String> {
+        if self.unlocked > self.locked {
+            Some(format!("unlocked {} exceeds locked {}", self.unlocked, self.locked))
+        } else if self.burned > self.minted {
+            Some(format!("burned {} exceeds minted {}", self.burned, self.minted))
+        } else if self.wrapped_supply() > self.backing() {
+            Some(format!(
+                "wrapped supply {} exceeds backing {}",
+                self.wrapped_supply(),
+                self.backing()
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackingReport {
+    pub chain: ChainId,
+    #[serde(flatten)]
+    pub ledger: ChainLedger,
+    pub backing: u64,
+    pub wrapped_supply: u64,
+    pub backing_ratio: Option<f64>,
+}
+
+// Outbound transfers lock native IDIA and mint wrapped IDIA on the foreign
+// chain; inbound transfers burn it there and unlock the native coins. Every
+// chain's wrapped supply must stay fully backed.
+pub struct WrappedLedger {
+    path: PathBuf,
+    chains: BTreeMap<ChainId, ChainLedger>,
+}
+
+impl WrappedLedger {
+    pub async fn open(path: PathBuf) -> Result<Self, LedgerError> {
+        let chains = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, chains })
+    }
+
+    pub fn chain(&self, chain: ChainId) -> ChainLedger {
+        self.chains.get(&chain).copied().unwrap_or_default()
+    }
+
+    pub async fn record_locked(&mut self, chain: ChainId, amount: u64) -> Result<(), LedgerError> {
+        self.record(chain, |l| l.locked += amount).await
+    }
+
+    pub async fn record_minted(&mut self, chain: ChainId, amount: u64) -> Result<(), LedgerError> {
+        self.record(chain, |l| l.minted += amount).await
+    }
+
+    pub async fn record_burned(&mut self, chain: ChainId, amount: u64) -> Result<(), LedgerError> {
+        self.record(chain, |l| l.burned += amount).await
+    }
+
+    pub async fn record_unlocked(&mut self, chain: ChainId, amount: u64) -> Result<(), LedgerError> {
+        self.record(chain, |l| l.unlocked += amount).await
+    }
+
+    // Both sides of a completed transfer at once: native IDIA leaving locks
+    // then mints on the foreign chain; coming back, the wrapped coins are
+    // burned there and the native ones unlocked. Between two foreign chains
+    // both happen, so the native backing follows the wrapped coins.
+    pub async fn record_transfer(&mut self, from_chain: ChainId, to_chain: ChainId, amount: u64) -> Result<(), LedgerError> {
+        match (from_chain, to_chain) {
+            (ChainId::Idia, ChainId::Idia) => Ok(()),
+            (ChainId::Idia, chain) => {
+                self.record(chain, |l| {
+                    l.locked += amount;
+                    l.minted += amount;
+                })
+                .await
+            }
+            (chain, ChainId::Idia) => {
+                self.record(chain, |l| {
+                    l.burned += amount;
+                    l.unlocked += amount;
+                })
+                .await
+            }
+            (from_chain, to_chain) => {
+                // Both movements happened, so record the second even if the first violates
+                let debited = self
+                    .record(from_chain, |l| {
+                        l.burned += amount;
+                        l.unlocked += amount;
+                    })
+                    .await;
+                let credited = self
+                    .record(to_chain, |l| {
+                        l.locked += amount;
+                        l.minted += amount;
+                    })
+                    .await;
+                debited.and(credited)
+            }
+        }
+    }
+
+    pub fn check_invariants(&self) -> Result<(), LedgerError> {
+        for (chain, ledger) in &self.chains {
+            if let Some(reason) = ledger.violation() {
+                return Err(LedgerError::InvariantViolated { chain: *chain, reason });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn report(&self) -> Vec<BackingReport> {
+        self.chains
+            .iter()
+            .map(|(chain, ledger)| BackingReport {
+                chain: *chain,
+                ledger: *ledger,
+                backing: ledger.backing(),
+                wrapped_supply: ledger.wrapped_supply(),
+                backing_ratio: ledger.backing_ratio(),
+            })
+            .collect()
+    }
+
+    // Movements already happened on chain, so they are persisted before the
+    // invariant is checked; a violation is for the caller to raise
+    async fn record(&mut self, chain: ChainId, apply: impl FnOnce(&mut ChainLedger)) -> Result<(), LedgerError> {
+        let ledger = self.chains.entry(chain).or_default();
+        apply(ledger);
+        let violation = ledger.violation();
+        self.save().await?;
+
+        match violation {
+            Some(reason) => Err(LedgerError::InvariantViolated { chain, reason }),
+            None => Ok(()),
+        }
+    }
+
+    async fn save(&self) -> Result<(), LedgerError> {
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(&self.chains)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+// Wraps a destination adapter so every release lands in the ledger, whether
+// the manager, the relayer or an optimistic claim submits it. Place it inside
+// the ReplayGuard so only the release that wins the claim is recorded.
+pub struct LedgerRecorder<A> {
+    inner: A,
+    ledger: Arc<RwLock<WrappedLedger>>,
+}
+
+impl<A> LedgerRecorder<A> {
+    pub fn new(inner: A, ledger: Arc<RwLock<WrappedLedger>>) -> Self {
+        Self { inner, ledger }
+    }
+}
+
+#[async_trait]
+impl<A: ChainAdapter + Send + Sync> ChainAdapter for LedgerRecorder<A> {
+    async fn verify_proof(&self, proof: &CrossChainProof) -> Result<bool, BridgeError> {
+        self.inner.verify_proof(proof).await
+    }
+
+    async fn lock_assets(&self, amount: u64, recipient: &str) -> Result<TxHash, BridgeError> {
+        self.inner.lock_assets(amount, recipient).await
+    }
+
+    async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError> {
+        let release_tx = self.inner.release_assets(proof).await?;
+        // The release already went out, so a ledger failure is raised but not returned
+        let recorded = self.ledger.write().await
+            .record_transfer(proof.source_chain, proof.destination_chain, proof.amount)
+            .await;
+        if let Err(e) = recorded {
+            tracing::error!("Wrapped IDIA ledger: {}", e);
+        }
+        Ok(release_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_foreign_to_foreign_moves_backing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.json");
+        let mut ledger = WrappedLedger::open(path.clone()).await.unwrap();
+
+        ledger.record_transfer(ChainId::Idia, ChainId::Ethereum, 1_000).await.unwrap();
+        ledger.record_transfer(ChainId::Ethereum, ChainId::Cosmos, 400).await.unwrap();
+
+        let ethereum = ledger.chain(ChainId::Ethereum);
+        assert_eq!(ethereum.wrapped_supply(), 600);
+        assert_eq!(ethereum.backing(), 600);
+        let cosmos = ledger.chain(ChainId::Cosmos);
+        assert_eq!(cosmos.wrapped_supply(), 400);
+        assert_eq!(cosmos.backing(), 400);
+        ledger.check_invariants().unwrap();
+
+        // More than was ever minted there cannot leave, but the credit is still recorded
+        let overdrawn = ledger.record_transfer(ChainId::Cosmos, ChainId::Ethereum, 500).await;
+        assert!(matches!(overdrawn, Err(LedgerError::InvariantViolated { chain: ChainId::Cosmos, .. })));
+        assert_eq!(ledger.chain(ChainId::Ethereum).wrapped_supply(), 1_100);
+
+        let reopened = WrappedLedger::open(path).await.unwrap();
+        assert_eq!(reopened.chain(ChainId::Cosmos).burned, 900);
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::types::{BridgeError, ChainId};
+
+// Bounds and fees for transfers to one destination chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainLimits {
+    pub min_amount: u64,
+    pub max_amount: u64,
+    // Total that may be sent to the chain per epoch
+    pub epoch_cap: u64,
+    pub flat_fee: u64,
+    // Basis points of the transferred amount, on top of the flat fee
+    pub fee_bps: u32,
+}
+
+impl ChainLimits {
+    pub fn fee(&self, amount: u64) -> u64 {
+        let proportional = (amount as u128 * self.fee_bps as u128 / 10_000) as u64;
+        self.flat_fee.saturating_add(proportional)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeLimitsConfig {
+    pub epoch_secs: i64,
+    // Destinations without an entry are not limited and pay no fee
+    pub chains: HashMap<ChainId, ChainLimits>,
+}
+
+impl Default for BridgeLimitsConfig {
+    fn default() -> Self {
+        Self {
+            epoch_secs: 86_400,
+            chains: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FeeQuote {
+    pub amount: u64,
+    pub fee: u64,
+    // What the recipient receives
+    pub net_amount: u64,
+}
+
+// Enforces the configured limits and keeps each destination's usage in the
+// current epoch. Volume is reserved when a transfer starts and handed back if
+// it fails before anything was locked.
+pub struct BridgeLimits {
+    config: BridgeLimitsConfig,
+    epoch_start: DateTime<Utc>,
+    used: HashMap<ChainId, u64>,
+}
+
+impl BridgeLimits {
+    pub fn new(config: BridgeLimitsConfig) -> Self {
+        Self {
+            config,
+            epoch_start: Utc::now(),
+            used: HashMap::new(),
+        }
+    }
+
+    pub fn quote(&self, to_chain: ChainId, amount: u64) -> Result<FeeQuote, BridgeError> {
+        let Some(limits) = self.config.chains.get(&to_chain) else {
+            return Ok(FeeQuote {
+                amount,
+                fee: 0,
+                net_amount: amount,
+            });
+        };
+
+        if amount < limits.min_amount {
+            return Err(BridgeError::BelowMinimum {
+                chain: to_chain,
+                amount,
+                min: limits.min_amount,
+            });
+        }
+        if amount > limits.max_amount {
+            return Err(BridgeError::AboveMaximum {
+                chain: to_chain,
+                amount,
+                max: limits.max_amount,
+            });
+        }
+        let fee = limits.fee(amount);
+        if fee >= amount {
+            return Err(BridgeError::BelowMinimum {
+                chain: to_chain,
+                amount,
+                min: fee + 1,
+            });
+        }
+
+        Ok(FeeQuote {
+            amount,
+            fee,
+            net_amount: amount - fee,
+        })
+    }
+
+    pub fn reserve(&mut self, to_chain: ChainId, amount: u64, now: DateTime<Utc>) -> Result<FeeQuote, BridgeError> {
+        let quote = self.quote(to_chain, amount)?;
+        self.roll_epoch(now);
+
+        if let Some(limits) = self.config.chains.get(&to_chain) {
+            let used = self.used.entry(to_chain).or_default();
+            let remaining = limits.epoch_cap.saturating_sub(*used);
+            if amount > remaining {
+                return Err(BridgeError::EpochCapExceeded {
+                    chain: to_chain,
+                    amount,
+                    remaining,
+                });
+            }
+            *used += amount;
+        }
+        Ok(quote)
+    }
+
+    pub fn unreserve(&mut self, to_chain: ChainId, amount: u64) {
+        if let Some(used) = self.used.get_mut(&to_chain) {
+            *used = used.saturating_sub(amount);
+        }
+    }
+
+    pub fn used(&self, to_chain: ChainId) -> u64 {
+        self.used.get(&to_chain).copied().unwrap_or_default()
+    }
+
+    fn roll_epoch(&mut self, now: DateTime<Utc>) {
+        if now - self.epoch_start >= Duration::seconds(self.config.epoch_secs) {
+            self.epoch_start = now;
+            self.used.clear();
+        }
+    }
+}
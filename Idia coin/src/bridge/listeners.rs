@@ -0,0 +1,287 @@
+use ethers::abi::{self, ParamType, Token};
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+
+use super::operations::{OperationState, OperationStore, Transition};
+use super::relayer::LockEvent;
+use super::types::{BridgeError, ChainId, TxHash};
+
+const LOCK_EVENT: &str = "Locked(address,uint256,string,uint256)";
+// The indexed lock id is the source chain's lock transaction
+const RELEASE_EVENT: &str = "Released(bytes32,address,uint256)";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub enum Observation {
+    Locked(LockEvent),
+    Released {
+        chain: ChainId,
+        lock_tx: TxHash,
+        release_tx: TxHash,
+    },
+}
+
+// Where listeners report: the operation state machine, and the relayer's
+// queue so an inbound lock gets proven and released without manual submission
+pub struct ObservationSink {
+    operations: Arc<RwLock<OperationStore>>,
+    relayer: Option<mpsc::Sender<LockEvent>>,
+}
+
+impl ObservationSink {
+    pub fn new(operations: Arc<RwLock<OperationStore>>) -> Self {
+        Self {
+            operations,
+            relayer: None,
+        }
+    }
+
+    pub fn with_relayer(mut self, relayer: mpsc::Sender<LockEvent>) -> Self {
+        self.relayer = Some(relayer);
+        self
+    }
+
+    pub async fn observe(&self, observation: Observation) -> Result<(), BridgeError> {
+        match observation {
+            Observation::Locked(event) => {
+                let (id, created) = self
+                    .operations
+                    .write()
+                    .await
+                    .record_inbound(
+                        event.source,
+                        event.destination,
+                        event.amount,
+                        event.recipient.clone(),
+                        event.lock_tx,
+                    )
+                    .await?;
+                if !created {
+                    return Ok(());
+                }
+                tracing::info!("Observed lock {:?} on {} as operation {}", event.lock_tx, event.source, id);
+                if let Some(relayer) = &self.relayer {
+                    if relayer.send(event).await.is_err() {
+                        tracing::warn!("Relayer queue closed; operation {} needs manual relaying", id);
+                    }
+                }
+            }
+            Observation::Released { chain, lock_tx, release_tx } => {
+                let mut operations = self.operations.write().await;
+                let Some(operation) = operations.find_by_lock(lock_tx) else {
+                    tracing::warn!("Release {:?} on {} matches no known lock", release_tx, chain);
+                    return Ok(());
+                };
+                if operation.state != OperationState::Proven {
+                    return Ok(());
+                }
+                let id = operation.id;
+                operations
+                    .transition(
+                        id,
+                        OperationState::Released,
+                        Transition {
+                            release_tx: Some(release_tx),
+                            reason: Some(format!("release observed on {}", chain)),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Follows the bridge contract's logs over a websocket subscription. After a
+// reconnect it backfills from the last block it saw, so no event is missed;
+// finality is left to the proof step.
+pub struct EthereumListener {
+    provider: Provider<Ws>,
+    contract: Address,
+    next_block: u64,
+}
+
+impl EthereumListener {
+    pub fn new(provider: Provider<Ws>, contract: Address, from_block: u64) -> Self {
+        Self {
+            provider,
+            contract,
+            next_block: from_block,
+        }
+    }
+
+    pub fn spawn(mut self, sink: Arc<ObservationSink>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.run(&sink).await {
+                    tracing::warn!("Ethereum log subscription dropped: {}", e);
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        })
+    }
+
+    async fn run(&mut self, sink: &ObservationSink) -> Result<(), BridgeError> {
+        let filter = Filter::new().address(self.contract).topic0(vec![
+            H256::from(keccak256(LOCK_EVENT)),
+            H256::from(keccak256(RELEASE_EVENT)),
+        ]);
+
+        let backfill = self
+            .provider
+            .get_logs(&filter.clone().from_block(self.next_block))
+            .await
+            .map_err(|e| BridgeError::Rpc(e.to_string()))?;
+        for log in backfill {
+            self.handle(log, sink).await;
+        }
+
+        let mut stream = self
+            .provider
+            .subscribe_logs(&filter)
+            .await
+            .map_err(|e| BridgeError::Rpc(e.to_string()))?;
+        while let Some(log) = stream.next().await {
+            self.handle(log, sink).await;
+        }
+        Err(BridgeError::Rpc("subscription closed".to_string()))
+    }
+
+    async fn handle(&mut self, log: Log, sink: &ObservationSink) {
+        if log.removed == Some(true) {
+            return;
+        }
+        if let Some(number) = log.block_number {
+            self.next_block = self.next_block.max(number.as_u64());
+        }
+
+        match decode_log(&log) {
+            Ok(Some(observation)) => {
+                if let Err(e) = sink.observe(observation).await {
+                    tracing::error!("Failed to record Ethereum bridge event: {}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Undecodable bridge log in {:?}: {}", log.transaction_hash, e),
+        }
+    }
+}
+
+fn decode_log(log: &Log) -> Result<Option<Observation>, BridgeError> {
+    let (Some(topic), Some(tx_hash)) = (log.topics.first(), log.transaction_hash) else {
+        return Ok(None);
+    };
+    let malformed = |e: abi::Error| BridgeError::MalformedProof(e.to_string());
+
+    if *topic == H256::from(keccak256(LOCK_EVENT)) {
+        let tokens = abi::decode(&[ParamType::Uint(256), ParamType::String, ParamType::Uint(256)], &log.data)
+            .map_err(malformed)?;
+        let [Token::Uint(amount), Token::String(recipient), Token::Uint(nonce)] = tokens.as_slice() else {
+            return Ok(None);
+        };
+        if *amount > U256::from(u64::MAX) || *nonce > U256::from(u64::MAX) {
+            return Err(BridgeError::MalformedProof("amount or nonce out of range".to_string()));
+        }
+        return Ok(Some(Observation::Locked(LockEvent {
+            source: ChainId::Ethereum,
+            destination: ChainId::Idia,
+            lock_tx: tx_hash,
+            amount: amount.as_u64(),
+            recipient: recipient.clone(),
+            nonce: nonce.as_u64(),
+        })));
+    }
+
+    if *topic == H256::from(keccak256(RELEASE_EVENT)) {
+        if let Some(lock_tx) = log.topics.get(1) {
+            return Ok(Some(Observation::Released {
+                chain: ChainId::Ethereum,
+                lock_tx: *lock_tx,
+                release_tx: tx_hash,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+// Layout of the bridge program's lock accounts after the 8-byte account discriminator
+#[derive(Debug, Deserialize)]
+struct LockAccount {
+    amount: u64,
+    nonce: u64,
+    recipient: String,
+}
+
+// Polls the bridge program's lock accounts; each new account is one lock,
+// identified by the account address
+pub struct SolanaListener {
+    client: RpcClient,
+    program_id: Pubkey,
+    seen: HashSet<Pubkey>,
+}
+
+impl SolanaListener {
+    pub fn new(client: RpcClient, program_id: Pubkey) -> Self {
+        Self {
+            client,
+            program_id,
+            seen: HashSet::new(),
+        }
+    }
+
+    pub fn spawn(mut self, sink: Arc<ObservationSink>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.poll(&sink).await {
+                    tracing::warn!("Solana lock account poll failed: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn poll(&mut self, sink: &ObservationSink) -> Result<(), BridgeError> {
+        let accounts = self
+            .client
+            .get_program_accounts(&self.program_id)
+            .await
+            .map_err(|e| BridgeError::Rpc(e.to_string()))?;
+        let discriminator = &Sha256::digest(b"account:LockAccount")[..8];
+
+        for (address, account) in accounts {
+            if self.seen.contains(&address) || !account.data.starts_with(discriminator) {
+                continue;
+            }
+            let lock: LockAccount = match bincode::deserialize(&account.data[8..]) {
+                Ok(lock) => lock,
+                Err(e) => {
+                    tracing::warn!("Undecodable lock account {}: {}", address, e);
+                    continue;
+                }
+            };
+
+            let observation = Observation::Locked(LockEvent {
+                source: ChainId::Solana,
+                destination: ChainId::Idia,
+                lock_tx: TxHash::from(address.to_bytes()),
+                amount: lock.amount,
+                recipient: lock.recipient,
+                nonce: lock.nonce,
+            });
+            sink.observe(observation).await?;
+            self.seen.insert(address);
+        }
+        Ok(())
+    }
+}
@@ -2,6 +2,22 @@ use async_trait::async_trait;
 use ethers::prelude::*;
 use solana_client::rpc_client::RpcClient;
 use bitcoin::Network;
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::tokenomics::economics::Treasury;
+
+use super::ethereum::EthereumLightClient;
+use super::federation::{FederatedAdapter, Federation};
+use super::ledger::{LedgerRecorder, WrappedLedger};
+use super::limits::{BridgeLimits, BridgeLimitsConfig};
+use super::pause::BridgePause;
+use super::operations::{OperationRecord, OperationState, OperationStore, Transition};
+use super::proof::{ProofGenerator, StateVerifier};
+use super::replay::{ProcessedProofs, ReplayGuard};
+use super::types::{BridgeError, BridgeOperation, ChainId, CrossChainProof, ProofPayload};
+use super::velocity::{VelocityAction, VelocityMonitor};
 
 #[async_trait]
 pub trait ChainAdapter {
@@ -10,15 +26,69 @@ pub trait ChainAdapter {
     async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError>;
 }
 
+#[async_trait]
+impl<A: ChainAdapter + Send + Sync + ?Sized> ChainAdapter for Box<A> {
+    async fn verify_proof(&self, proof: &CrossChainProof) -> Result<bool, BridgeError> {
+        (**self).verify_proof(proof).await
+    }
+
+    async fn lock_assets(&self, amount: u64, recipient: &str) -> Result<TxHash, BridgeError> {
+        (**self).lock_assets(amount, recipient).await
+    }
+
+    async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError> {
+        (**self).release_assets(proof).await
+    }
+}
+
+// Shared state every destination adapter is wrapped with, so no release goes
+// out without a federation quorum, twice, or unrecorded
+pub struct ReleaseGuards {
+    pub processed: Arc<RwLock<ProcessedProofs>>,
+    pub ledger: Arc<RwLock<WrappedLedger>>,
+    pub federation: Arc<RwLock<Federation>>,
+    pub velocity: Option<Arc<VelocityMonitor>>,
+}
+
+impl ReleaseGuards {
+    // Outermost first: ReplayGuard, LedgerRecorder, FederatedAdapter, then the chain
+    pub fn wrap(&self, adapter: Box<dyn ChainAdapter + Send + Sync>) -> Box<dyn ChainAdapter + Send + Sync> {
+        let mut federated = FederatedAdapter::new(adapter, self.federation.clone());
+        if let Some(velocity) = &self.velocity {
+            federated = federated.with_velocity(velocity.clone());
+        }
+        let recorded = LedgerRecorder::new(federated, self.ledger.clone());
+        Box::new(ReplayGuard::new(recorded, self.processed.clone()))
+    }
+}
+
 pub struct EthereumBridge {
     contract: ethers::Contract,
     provider: Provider<Http>,
     wallet: LocalWallet,
+    light_client: Option<Arc<EthereumLightClient>>,
+}
+
+impl EthereumBridge {
+    pub fn with_light_client(mut self, light_client: Arc<EthereumLightClient>) -> Self {
+        self.light_client = Some(light_client);
+        self
+    }
 }
 
 #[async_trait]
 impl ChainAdapter for EthereumBridge {
     async fn verify_proof(&self, proof: &CrossChainProof) -> Result<bool, BridgeError> {
+        // With a light client the receipt must check out locally before the contract is asked
+        if let Some(light_client) = &self.light_client {
+            let ProofPayload::Ethereum(receipt) = &proof.payload else {
+                return Ok(false);
+            };
+            if !light_client.verify(proof, receipt, self.contract.address()).await? {
+                return Ok(false);
+            }
+        }
+
         let valid = self.contract
             .method("verifyProof", proof.to_eth_format())?
             .call()
@@ -36,7 +106,7 @@ impl ChainAdapter for EthereumBridge {
 
     async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError> {
         let tx = self.contract
-            .method("release", proof.to_eth_format())?
+            .method("release", proof.to_eth_release_format()?)?
             .send()
             .await?;
         Ok(tx.tx_hash())
@@ -91,12 +161,67 @@ impl ChainAdapter for PolkadotBridge {
 }
 
 pub struct BridgeManager {
-    bridges: HashMap<ChainId, Box<dyn ChainAdapter>>,
+    bridges: HashMap<ChainId, Box<dyn ChainAdapter + Send + Sync>>,
     state_verifier: StateVerifier,
     proof_generator: ProofGenerator,
+    operations: Arc<RwLock<OperationStore>>,
+    limits: RwLock<BridgeLimits>,
+    treasury: Option<Arc<RwLock<Treasury>>>,
+    pause: Option<Arc<BridgePause>>,
+    velocity: Option<Arc<VelocityMonitor>>,
 }
 
 impl BridgeManager {
+    // Every adapter is wrapped in `guards`; there is no way to register an
+    // unguarded one
+    pub fn new(
+        adapters: Vec<(ChainId, Box<dyn ChainAdapter + Send + Sync>)>,
+        guards: &ReleaseGuards,
+        state_verifier: StateVerifier,
+        proof_generator: ProofGenerator,
+        operations: Arc<RwLock<OperationStore>>,
+    ) -> Self {
+        let bridges = adapters
+            .into_iter()
+            .map(|(chain, adapter)| (chain, guards.wrap(adapter)))
+            .collect();
+        Self {
+            bridges,
+            state_verifier,
+            proof_generator,
+            operations,
+            limits: RwLock::new(BridgeLimits::new(BridgeLimitsConfig::default())),
+            treasury: None,
+            pause: None,
+            velocity: guards.velocity.clone(),
+        }
+    }
+
+    pub fn with_limits(mut self, config: BridgeLimitsConfig) -> Self {
+        self.limits = RwLock::new(BridgeLimits::new(config));
+        self
+    }
+
+    // Bridge fees are paid into the treasury once the source lock lands
+    pub fn with_treasury(mut self, treasury: Arc<RwLock<Treasury>>) -> Self {
+        self.treasury = Some(treasury);
+        self
+    }
+
+    pub fn with_pause(mut self, pause: Arc<BridgePause>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    pub fn with_velocity(mut self, velocity: Arc<VelocityMonitor>) -> Self {
+        self.velocity = Some(velocity);
+        self
+    }
+
+    pub async fn operation(&self, id: u64) -> Option<OperationRecord> {
+        self.operations.read().await.get(id).cloned()
+    }
+
     pub async fn bridge_assets(
         &self,
         from_chain: ChainId,
@@ -110,29 +235,110 @@ impl BridgeManager {
         let dest = self.bridges.get(&to_chain)
             .ok_or(BridgeError::ChainNotSupported(to_chain))?;
 
-        // Lock assets on source chain
-        let lock_tx = source.lock_assets(amount, recipient).await?;
+        if let Some(pause) = &self.pause {
+            pause.check(from_chain).await?;
+            pause.check(to_chain).await?;
+        }
 
-        // Generate cross-chain proof
-        let proof = self.proof_generator
-            .generate_proof(from_chain, to_chain, lock_tx)
-            .await?;
+        // The fee is withheld from what gets locked for the recipient
+        let quote = self.limits.write().await.reserve(to_chain, amount, Utc::now())?;
+        if let Some(velocity) = &self.velocity {
+            let alarm = velocity.record(to_chain, recipient, quote.net_amount, Utc::now()).await;
+            if let Some(alarm) = alarm.filter(|a| a.action == VelocityAction::Pause) {
+                self.limits.write().await.unreserve(to_chain, amount);
+                return Err(BridgeError::Paused {
+                    chain: to_chain,
+                    reason: alarm.reason(),
+                });
+            }
+        }
+        let id = match self.operations.write().await
+            .create(from_chain, to_chain, amount, recipient.to_string())
+            .await
+        {
+            Ok(id) => id,
+            Err(e) => {
+                self.limits.write().await.unreserve(to_chain, amount);
+                return Err(e.into());
+            }
+        };
+
+        // Lock assets on source chain
+        let lock_tx = match source.lock_assets(quote.net_amount, recipient).await {
+            Ok(lock_tx) => lock_tx,
+            Err(e) => {
+                self.limits.write().await.unreserve(to_chain, amount);
+                return Err(self.fail(id, OperationState::Failed, e).await);
+            }
+        };
+        if quote.fee > 0 {
+            if let Some(treasury) = &self.treasury {
+                treasury.write().await.add_funds(quote.fee);
+            }
+        }
+        self.advance(id, OperationState::SourceLocked, Transition {
+            lock_tx: Some(lock_tx),
+            ..Default::default()
+        }).await?;
 
-        // Verify proof validity
-        if !self.state_verifier.verify_proof(&proof).await? {
-            return Err(BridgeError::InvalidProof);
+        // Once assets are locked, any failure leaves the operation refunding
+        let proof = match self.proof_generator.generate_proof(from_chain, to_chain, lock_tx).await {
+            Ok(proof) => proof,
+            Err(e) => return Err(self.fail(id, OperationState::Refunding, e).await),
+        };
+        // Idia locks are checked against our own chain, others by the source adapter
+        let verified = if from_chain == ChainId::Idia {
+            self.state_verifier.verify_proof(&proof).await
+        } else {
+            source.verify_proof(&proof).await
+        };
+        match verified {
+            Ok(true) => {}
+            Ok(false) => return Err(self.fail(id, OperationState::Refunding, BridgeError::InvalidProof).await),
+            Err(e) => return Err(self.fail(id, OperationState::Refunding, e).await),
         }
+        self.advance(id, OperationState::Proven, Transition {
+            proof: Some(proof.clone()),
+            ..Default::default()
+        }).await?;
 
         // Release assets on destination chain
-        let release_tx = dest.release_assets(&proof).await?;
+        let release_tx = match dest.release_assets(&proof).await {
+            Ok(release_tx) => release_tx,
+            Err(e) => return Err(self.fail(id, OperationState::Refunding, e).await),
+        };
+        // Finalized by the timeout sweep once the release has had time to settle
+        self.advance(id, OperationState::Released, Transition {
+            release_tx: Some(release_tx),
+            ..Default::default()
+        }).await?;
 
         Ok(BridgeOperation {
+            id,
             from_chain,
             to_chain,
             amount,
+            fee: quote.fee,
             lock_tx,
             release_tx,
             proof,
         })
     }
+
+    async fn advance(&self, id: u64, state: OperationState, update: Transition) -> Result<(), BridgeError> {
+        self.operations.write().await.transition(id, state, update).await?;
+        Ok(())
+    }
+
+    // Records why the operation stopped and hands the original error back
+    async fn fail(&self, id: u64, state: OperationState, error: BridgeError) -> BridgeError {
+        let update = Transition {
+            reason: Some(error.to_string()),
+            ..Default::default()
+        };
+        if let Err(e) = self.operations.write().await.transition(id, state, update).await {
+            tracing::error!("Failed to record bridge operation {} as {:?}: {}", id, state, e);
+        }
+        error
+    }
 }
\ No newline at end of file
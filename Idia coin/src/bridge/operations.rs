@@ -0,0 +1,338 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::metrics::bridge::BRIDGE_OPERATION_TRANSITIONS;
+
+use super::types::{ChainId, CrossChainProof, TxHash};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OperationError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Operation {0} not found")]
+    NotFound(u64),
+    #[error("Operation {id} cannot move from {from:?} to {to:?}")]
+    InvalidTransition { id: u64, from: OperationState, to: OperationState },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationState {
+    Initiated,
+    SourceLocked,
+    Proven,
+    Released,
+    Finalized,
+    Failed,
+    Refunding,
+}
+
+impl OperationState {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OperationState::Finalized | OperationState::Failed)
+    }
+
+    fn can_move_to(&self, next: OperationState) -> bool {
+        use OperationState::*;
+        matches!(
+            (self, next),
+            (Initiated, SourceLocked)
+                | (Initiated, Failed)
+                | (SourceLocked, Proven)
+                | (SourceLocked, Refunding)
+                | (Proven, Released)
+                | (Proven, Refunding)
+                | (Released, Finalized)
+                | (Refunding, Failed)
+        )
+    }
+}
+
+// How long an operation may sit in each state before the timeout sweep moves it on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationTimeouts {
+    pub initiated_secs: i64,
+    pub source_locked_secs: i64,
+    pub proven_secs: i64,
+    // Released operations are finalized once the release is this old
+    pub finality_secs: i64,
+}
+
+impl Default for OperationTimeouts {
+    fn default() -> Self {
+        Self {
+            initiated_secs: 600,
+            source_locked_secs: 3600,
+            proven_secs: 3600,
+            finality_secs: 1800,
+        }
+    }
+}
+
+impl OperationTimeouts {
+    fn for_state(&self, state: OperationState) -> Option<Duration> {
+        match state {
+            OperationState::Initiated => Some(Duration::seconds(self.initiated_secs)),
+            OperationState::SourceLocked => Some(Duration::seconds(self.source_locked_secs)),
+            OperationState::Proven => Some(Duration::seconds(self.proven_secs)),
+            OperationState::Released => Some(Duration::seconds(self.finality_secs)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateChange {
+    pub state: OperationState,
+    pub at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationRecord {
+    pub id: u64,
+    pub from_chain: ChainId,
+    pub to_chain: ChainId,
+    pub amount: u64,
+    pub recipient: String,
+    pub state: OperationState,
+    pub lock_tx: Option<TxHash>,
+    pub proof: Option<CrossChainProof>,
+    pub release_tx: Option<TxHash>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    // When the timeout sweep acts on the current state, if it ever does
+    pub deadline: Option<DateTime<Utc>>,
+    pub history: Vec<StateChange>,
+}
+
+// Updates to an operation alongside a state change
+#[derive(Debug, Default)]
+pub struct Transition {
+    pub lock_tx: Option<TxHash>,
+    pub proof: Option<CrossChainProof>,
+    pub release_tx: Option<TxHash>,
+    pub reason: Option<String>,
+}
+
+pub struct OperationStore {
+    path: PathBuf,
+    operations: BTreeMap<u64, OperationRecord>,
+    next_id: u64,
+    timeouts: OperationTimeouts,
+}
+
+impl OperationStore {
+    pub async fn open(path: PathBuf, timeouts: OperationTimeouts) -> Result<Self, OperationError> {
+        let operations: Vec<OperationRecord> = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let next_id = operations.iter().map(|o| o.id + 1).max().unwrap_or(1);
+
+        Ok(Self {
+            path,
+            operations: operations.into_iter().map(|o| (o.id, o)).collect(),
+            next_id,
+            timeouts,
+        })
+    }
+
+    pub fn get(&self, id: u64) -> Option<&OperationRecord> {
+        self.operations.get(&id)
+    }
+
+    pub fn list(&self, state: Option<OperationState>) -> Vec<&OperationRecord> {
+        self.operations
+            .values()
+            .filter(|o| state.map_or(true, |s| o.state == s))
+            .collect()
+    }
+
+    pub fn find_by_lock(&self, lock_tx: TxHash) -> Option<&OperationRecord> {
+        self.operations.values().find(|o| o.lock_tx == Some(lock_tx))
+    }
+
+    pub async fn create(
+        &mut self,
+        from_chain: ChainId,
+        to_chain: ChainId,
+        amount: u64,
+        recipient: String,
+    ) -> Result<u64, OperationError> {
+        let id = self.insert(from_chain, to_chain, amount, recipient);
+        self.save().await?;
+        Ok(id)
+    }
+
+    // Starts tracking a lock a listener saw on another chain. Returns the
+    // operation and whether it is new; a lock seen twice maps to one operation.
+    pub async fn record_inbound(
+        &mut self,
+        from_chain: ChainId,
+        to_chain: ChainId,
+        amount: u64,
+        recipient: String,
+        lock_tx: TxHash,
+    ) -> Result<(u64, bool), OperationError> {
+        if let Some(existing) = self.find_by_lock(lock_tx) {
+            return Ok((existing.id, false));
+        }
+
+        let id = self.insert(from_chain, to_chain, amount, recipient);
+        self.apply(
+            id,
+            OperationState::SourceLocked,
+            Transition {
+                lock_tx: Some(lock_tx),
+                reason: Some(format!("observed on {}", from_chain)),
+                ..Default::default()
+            },
+        )?;
+        self.save().await?;
+        Ok((id, true))
+    }
+
+    fn insert(&mut self, from_chain: ChainId, to_chain: ChainId, amount: u64, recipient: String) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let now = Utc::now();
+        let state = OperationState::Initiated;
+        self.operations.insert(
+            id,
+            OperationRecord {
+                id,
+                from_chain,
+                to_chain,
+                amount,
+                recipient,
+                state,
+                lock_tx: None,
+                proof: None,
+                release_tx: None,
+                created_at: now,
+                updated_at: now,
+                deadline: self.timeouts.for_state(state).map(|t| now + t),
+                history: vec![StateChange {
+                    state,
+                    at: now,
+                    reason: None,
+                }],
+            },
+        );
+        id
+    }
+
+    pub async fn transition(
+        &mut self,
+        id: u64,
+        next: OperationState,
+        update: Transition,
+    ) -> Result<&OperationRecord, OperationError> {
+        self.apply(id, next, update)?;
+        self.save().await?;
+        Ok(&self.operations[&id])
+    }
+
+    // Moves every operation whose deadline has passed: unlocked ones fail,
+    // locked but unreleased ones start refunding, and released ones finalize
+    pub async fn expire(&mut self, now: DateTime<Utc>) -> Result<Vec<(u64, OperationState)>, OperationError> {
+        let expired: Vec<(u64, OperationState)> = self
+            .operations
+            .values()
+            .filter(|o| o.deadline.map_or(false, |d| d <= now))
+            .map(|o| (o.id, o.state))
+            .collect();
+
+        let mut moved = Vec::new();
+        for (id, state) in expired {
+            let next = match state {
+                OperationState::Initiated => OperationState::Failed,
+                OperationState::SourceLocked | OperationState::Proven => OperationState::Refunding,
+                OperationState::Released => OperationState::Finalized,
+                _ => continue,
+            };
+            let reason = (next != OperationState::Finalized).then(|| format!("timed out in {:?}", state));
+            self.apply(id, next, Transition { reason, ..Default::default() })?;
+            moved.push((id, next));
+        }
+        if !moved.is_empty() {
+            self.save().await?;
+        }
+        Ok(moved)
+    }
+
+    fn apply(&mut self, id: u64, next: OperationState, update: Transition) -> Result<(), OperationError> {
+        let operation = self.operations.get_mut(&id).ok_or(OperationError::NotFound(id))?;
+        if !operation.state.can_move_to(next) {
+            return Err(OperationError::InvalidTransition {
+                id,
+                from: operation.state,
+                to: next,
+            });
+        }
+
+        let now = Utc::now();
+        if update.lock_tx.is_some() {
+            operation.lock_tx = update.lock_tx;
+        }
+        if update.proof.is_some() {
+            operation.proof = update.proof;
+        }
+        if update.release_tx.is_some() {
+            operation.release_tx = update.release_tx;
+        }
+        BRIDGE_OPERATION_TRANSITIONS
+            .with_label_values(&[
+                &operation.from_chain.to_string(),
+                &operation.to_chain.to_string(),
+                &format!("{:?}", next),
+            ])
+            .inc();
+        operation.state = next;
+        operation.updated_at = now;
+        operation.deadline = self.timeouts.for_state(next).map(|t| now + t);
+        operation.history.push(StateChange {
+            state: next,
+            at: now,
+            reason: update.reason,
+        });
+        Ok(())
+    }
+
+    async fn save(&self) -> Result<(), OperationError> {
+        let operations: Vec<&OperationRecord> = self.operations.values().collect();
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(&operations)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+pub fn spawn_timeouts(
+    store: Arc<RwLock<OperationStore>>,
+    interval: std::time::Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            match store.write().await.expire(Utc::now()).await {
+                Ok(moved) => {
+                    for (id, state) in moved {
+                        tracing::info!("Bridge operation {} timed out into {:?}", id, state);
+                    }
+                }
+                Err(e) => tracing::error!("Bridge operation timeout sweep failed: {}", e),
+            }
+        }
+    })
+}
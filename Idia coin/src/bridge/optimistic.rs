@@ -0,0 +1,391 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::tokenomics::economics::Treasury;
+
+use super::manager::ChainAdapter;
+use super::pause::BridgePause;
+use super::types::{BridgeError, ChainId, CrossChainProof, TxHash};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OptimisticError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Unknown claim {0}")]
+    UnknownClaim(u64),
+    #[error("Relayer {relayer} has {available} unbonded, {required} required")]
+    InsufficientBond { relayer: String, available: u64, required: u64 },
+    #[error("Lock {nonce} from {chain} is already claimed")]
+    AlreadyClaimed { chain: ChainId, nonce: u64 },
+    #[error("Challenge window of claim {0} has closed")]
+    ChallengeWindowClosed(u64),
+    #[error("Claim {0} is no longer pending")]
+    ClaimNotPending(u64),
+    #[error("Fraud proof rejected: {0}")]
+    FraudProofRejected(String),
+    #[error("Bridge error: {0}")]
+    Bridge(#[from] BridgeError),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimisticConfig {
+    pub challenge_window_secs: i64,
+    // Bond a relayer puts at stake on each claim
+    pub claim_bond: u64,
+    // Share of a slashed bond paid to the challenger; the rest goes to the treasury
+    pub challenger_reward_bps: u32,
+}
+
+impl Default for OptimisticConfig {
+    fn default() -> Self {
+        Self {
+            challenge_window_secs: 3_600,
+            claim_bond: 0,
+            challenger_reward_bps: 5_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimStatus {
+    Pending,
+    Released,
+    // A fraud proof showed the lock proof invalid and the bond was slashed
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimisticClaim {
+    pub id: u64,
+    pub proof: CrossChainProof,
+    pub relayer: String,
+    pub bond: u64,
+    pub posted_at: DateTime<Utc>,
+    pub challenge_deadline: DateTime<Utc>,
+    pub status: ClaimStatus,
+    pub release_tx: Option<TxHash>,
+    pub challenger: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RelayerBond {
+    pub total: u64,
+    // Backing claims still inside their challenge window
+    pub locked: u64,
+}
+
+impl RelayerBond {
+    pub fn available(&self) -> u64 {
+        self.total.saturating_sub(self.locked)
+    }
+}
+
+// A challenge that the claimed lock proof does not verify on its source chain.
+// The source adapter re-checks the proof itself, so the challenger needs no
+// evidence beyond naming the claim.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FraudProof {
+    pub claim_id: u64,
+    pub challenger: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChallengeOutcome {
+    pub claim_id: u64,
+    pub relayer: String,
+    pub slashed: u64,
+    pub challenger_reward: u64,
+    pub treasury_share: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OptimisticState {
+    next_id: u64,
+    claims: BTreeMap<u64, OptimisticClaim>,
+    bonds: HashMap<String, RelayerBond>,
+}
+
+// Alternative to verify-then-release: a bonded relayer claims a lock, and the
+// release goes out once the challenge window passes unchallenged. A valid
+// fraud proof cancels the release and slashes the relayer's bond.
+pub struct OptimisticBridge {
+    path: PathBuf,
+    config: OptimisticConfig,
+    state: RwLock<OptimisticState>,
+    sources: HashMap<ChainId, Arc<dyn ChainAdapter + Send + Sync>>,
+    destinations: HashMap<ChainId, Arc<dyn ChainAdapter + Send + Sync>>,
+    treasury: Option<Arc<RwLock<Treasury>>>,
+    pause: Option<Arc<BridgePause>>,
+}
+
+impl OptimisticBridge {
+    pub async fn open(path: PathBuf, config: OptimisticConfig) -> Result<Self, OptimisticError> {
+        let state = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => OptimisticState::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            config,
+            state: RwLock::new(state),
+            sources: HashMap::new(),
+            destinations: HashMap::new(),
+            treasury: None,
+            pause: None,
+        })
+    }
+
+    // Adapter of the chain locks happen on, used to check fraud proofs
+    pub fn with_source(mut self, chain: ChainId, adapter: Arc<dyn ChainAdapter + Send + Sync>) -> Self {
+        self.sources.insert(chain, adapter);
+        self
+    }
+
+    pub fn with_destination(mut self, chain: ChainId, adapter: Arc<dyn ChainAdapter + Send + Sync>) -> Self {
+        self.destinations.insert(chain, adapter);
+        self
+    }
+
+    pub fn with_treasury(mut self, treasury: Arc<RwLock<Treasury>>) -> Self {
+        self.treasury = Some(treasury);
+        self
+    }
+
+    pub fn with_pause(mut self, pause: Arc<BridgePause>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    pub async fn claim(&self, id: u64) -> Option<OptimisticClaim> {
+        self.state.read().await.claims.get(&id).cloned()
+    }
+
+    pub async fn bond(&self, relayer: &str) -> RelayerBond {
+        self.state.read().await.bonds.get(relayer).copied().unwrap_or_default()
+    }
+
+    pub async fn deposit_bond(&self, relayer: &str, amount: u64) -> Result<RelayerBond, OptimisticError> {
+        let mut state = self.state.write().await;
+        let bond = state.bonds.entry(relayer.to_string()).or_default();
+        bond.total += amount;
+        let bond = *bond;
+        self.save(&state).await?;
+        Ok(bond)
+    }
+
+    pub async fn withdraw_bond(&self, relayer: &str, amount: u64) -> Result<RelayerBond, OptimisticError> {
+        let mut state = self.state.write().await;
+        let bond = state.bonds.entry(relayer.to_string()).or_default();
+        if amount > bond.available() {
+            return Err(OptimisticError::InsufficientBond {
+                relayer: relayer.to_string(),
+                available: bond.available(),
+                required: amount,
+            });
+        }
+        bond.total -= amount;
+        let bond = *bond;
+        self.save(&state).await?;
+        Ok(bond)
+    }
+
+    // Posts a claim for a proven lock; the bond stays locked until the claim
+    // is released or slashed
+    pub async fn submit_claim(&self, relayer: &str, proof: CrossChainProof) -> Result<u64, OptimisticError> {
+        if let Some(pause) = &self.pause {
+            pause.check(proof.source_chain).await?;
+            pause.check(proof.destination_chain).await?;
+        }
+
+        let mut state = self.state.write().await;
+        let duplicate = state.claims.values().any(|c| {
+            c.status != ClaimStatus::Rejected
+                && c.proof.source_chain == proof.source_chain
+                && c.proof.destination_chain == proof.destination_chain
+                && c.proof.nonce == proof.nonce
+        });
+        if duplicate {
+            return Err(OptimisticError::AlreadyClaimed {
+                chain: proof.source_chain,
+                nonce: proof.nonce,
+            });
+        }
+
+        let required = self.config.claim_bond;
+        let bond = state.bonds.entry(relayer.to_string()).or_default();
+        if bond.available() < required {
+            return Err(OptimisticError::InsufficientBond {
+                relayer: relayer.to_string(),
+                available: bond.available(),
+                required,
+            });
+        }
+        bond.locked += required;
+
+        let id = state.next_id;
+        state.next_id += 1;
+        let now = Utc::now();
+        state.claims.insert(
+            id,
+            OptimisticClaim {
+                id,
+                proof,
+                relayer: relayer.to_string(),
+                bond: required,
+                posted_at: now,
+                challenge_deadline: now + Duration::seconds(self.config.challenge_window_secs),
+                status: ClaimStatus::Pending,
+                release_tx: None,
+                challenger: None,
+            },
+        );
+        self.save(&state).await?;
+        tracing::info!("Relayer {} posted optimistic claim {}", relayer, id);
+        Ok(id)
+    }
+
+    pub async fn challenge(&self, fraud: FraudProof) -> Result<ChallengeOutcome, OptimisticError> {
+        let claim = self
+            .claim(fraud.claim_id)
+            .await
+            .ok_or(OptimisticError::UnknownClaim(fraud.claim_id))?;
+        if claim.status != ClaimStatus::Pending {
+            return Err(OptimisticError::ClaimNotPending(claim.id));
+        }
+        if Utc::now() >= claim.challenge_deadline {
+            return Err(OptimisticError::ChallengeWindowClosed(claim.id));
+        }
+
+        // Verified without holding the state lock; the claim is re-read below
+        let source = self
+            .sources
+            .get(&claim.proof.source_chain)
+            .ok_or(BridgeError::ChainNotSupported(claim.proof.source_chain))?;
+        match source.verify_proof(&claim.proof).await {
+            Ok(false) | Err(BridgeError::InvalidProof | BridgeError::MalformedProof(_)) => {}
+            Ok(true) => return Err(OptimisticError::FraudProofRejected("lock proof verifies".to_string())),
+            // An unreachable source chain proves nothing either way
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut state = self.state.write().await;
+        let claim = state
+            .claims
+            .get_mut(&fraud.claim_id)
+            .ok_or(OptimisticError::UnknownClaim(fraud.claim_id))?;
+        if claim.status != ClaimStatus::Pending {
+            return Err(OptimisticError::ClaimNotPending(claim.id));
+        }
+        claim.status = ClaimStatus::Rejected;
+        claim.challenger = Some(fraud.challenger.clone());
+        let (relayer, slashed) = (claim.relayer.clone(), claim.bond);
+
+        let bond = state.bonds.entry(relayer.clone()).or_default();
+        bond.locked = bond.locked.saturating_sub(slashed);
+        bond.total = bond.total.saturating_sub(slashed);
+
+        // The challenger's reward lands in their bond account, withdrawable at once
+        let challenger_reward = (slashed as u128 * self.config.challenger_reward_bps as u128 / 10_000) as u64;
+        let treasury_share = slashed - challenger_reward;
+        state.bonds.entry(fraud.challenger.clone()).or_default().total += challenger_reward;
+        self.save(&state).await?;
+        drop(state);
+
+        if treasury_share > 0 {
+            if let Some(treasury) = &self.treasury {
+                treasury.write().await.add_funds(treasury_share);
+            }
+        }
+        tracing::warn!(
+            "Optimistic claim {} by {} proven fraudulent by {}; slashed {}",
+            fraud.claim_id,
+            relayer,
+            fraud.challenger,
+            slashed
+        );
+        Ok(ChallengeOutcome {
+            claim_id: fraud.claim_id,
+            relayer,
+            slashed,
+            challenger_reward,
+            treasury_share,
+        })
+    }
+
+    // Releases every claim whose challenge window passed unchallenged
+    pub async fn release_due(&self, now: DateTime<Utc>) -> Result<Vec<u64>, OptimisticError> {
+        let due: Vec<OptimisticClaim> = self
+            .state
+            .read()
+            .await
+            .claims
+            .values()
+            .filter(|c| c.status == ClaimStatus::Pending && c.challenge_deadline <= now)
+            .cloned()
+            .collect();
+
+        let mut released = Vec::new();
+        for claim in due {
+            if let Some(pause) = &self.pause {
+                if pause.check(claim.proof.destination_chain).await.is_err() {
+                    continue;
+                }
+            }
+            let Some(destination) = self.destinations.get(&claim.proof.destination_chain) else {
+                tracing::warn!("No adapter for {} to release claim {}", claim.proof.destination_chain, claim.id);
+                continue;
+            };
+            let release_tx = match destination.release_assets(&claim.proof).await {
+                Ok(release_tx) => release_tx,
+                Err(e) => {
+                    tracing::warn!("Release of optimistic claim {} failed, retrying: {}", claim.id, e);
+                    continue;
+                }
+            };
+
+            let mut state = self.state.write().await;
+            if let Some(stored) = state.claims.get_mut(&claim.id) {
+                stored.status = ClaimStatus::Released;
+                stored.release_tx = Some(release_tx);
+            }
+            if let Some(bond) = state.bonds.get_mut(&claim.relayer) {
+                bond.locked = bond.locked.saturating_sub(claim.bond);
+            }
+            self.save(&state).await?;
+            released.push(claim.id);
+        }
+        Ok(released)
+    }
+
+    pub fn spawn(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                match self.release_due(Utc::now()).await {
+                    Ok(released) => {
+                        for id in released {
+                            tracing::info!("Optimistic claim {} released after its challenge window", id);
+                        }
+                    }
+                    Err(e) => tracing::error!("Optimistic release sweep failed: {}", e),
+                }
+            }
+        })
+    }
+
+    async fn save(&self, state: &OptimisticState) -> Result<(), OptimisticError> {
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(state)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
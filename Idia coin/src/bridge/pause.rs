@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tokio::sync::RwLock;
+
+use crate::governance::threshold::{GovernanceEvent, ThresholdGovernance};
+
+use super::types::{BridgeError, ChainId};
+
+// Governance names this target to halt every chain at once
+pub const ALL_CHAINS: &str = "*";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PauseRecord {
+    pub reason: String,
+    pub since: DateTime<Utc>,
+}
+
+// Emergency halt per chain adapter, flipped by executed `BridgePause`
+// proposals. A paused chain accepts no new transfers and no releases.
+#[derive(Default)]
+pub struct BridgePause {
+    all: RwLock<Option<PauseRecord>>,
+    chains: RwLock<HashMap<ChainId, PauseRecord>>,
+}
+
+impl BridgePause {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Restores the switch from governance state when a node starts
+    pub async fn from_governance(governance: &ThresholdGovernance) -> Self {
+        let pause = Self::new();
+        for (chain, reason) in governance.bridge_pauses() {
+            pause.set(chain, true, reason).await;
+        }
+        pause
+    }
+
+    // Feed with `ThresholdGovernance::drain_events`; other events are ignored
+    pub async fn apply(&self, event: &GovernanceEvent) {
+        if let GovernanceEvent::BridgePauseChanged { chain, paused, reason, .. } = event {
+            self.set(chain, *paused, reason).await;
+        }
+    }
+
+    // Automatic halt, e.g. by a velocity alarm; only governance resumes the chain
+    pub async fn trip(&self, chain: ChainId, reason: &str) {
+        let record = PauseRecord {
+            reason: reason.to_string(),
+            since: Utc::now(),
+        };
+        self.chains.write().await.entry(chain).or_insert(record);
+        tracing::warn!("Bridge transfers on {} paused automatically: {}", chain, reason);
+    }
+
+    pub async fn check(&self, chain: ChainId) -> Result<(), BridgeError> {
+        let record = match self.all.read().await.clone() {
+            Some(record) => Some(record),
+            None => self.chains.read().await.get(&chain).cloned(),
+        };
+        match record {
+            Some(record) => Err(BridgeError::Paused {
+                chain,
+                reason: record.reason,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn paused(&self) -> (Option<PauseRecord>, HashMap<ChainId, PauseRecord>) {
+        (self.all.read().await.clone(), self.chains.read().await.clone())
+    }
+
+    async fn set(&self, target: &str, paused: bool, reason: &str) {
+        let record = paused.then(|| PauseRecord {
+            reason: reason.to_string(),
+            since: Utc::now(),
+        });
+
+        if target == ALL_CHAINS {
+            *self.all.write().await = record;
+        } else {
+            let Ok(chain) = ChainId::from_str(target) else {
+                tracing::warn!("Bridge pause names unknown chain {}", target);
+                return;
+            };
+            let mut chains = self.chains.write().await;
+            match record {
+                Some(record) => chains.insert(chain, record),
+                None => chains.remove(&chain),
+            };
+        }
+
+        if paused {
+            tracing::warn!("Bridge transfers on {} paused by governance: {}", target, reason);
+        } else {
+            tracing::info!("Bridge transfers on {} resumed by governance", target);
+        }
+    }
+}
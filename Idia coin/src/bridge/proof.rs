@@ -0,0 +1,251 @@
+use async_trait::async_trait;
+use idia_core::{hash_of, Block, BlockHeader, Hash, Transaction};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::relayer::{LockEvent, ProofSource};
+use super::types::{BridgeError, ChainId, CrossChainProof, ProofPayload, TxHash};
+
+// Prefix of the `extra` payload marking an Idia transaction as a bridge lock
+const LOCK_MEMO_TAG: &[u8] = b"idia-bridge-lock-v1";
+
+// Where locked IDIA should be released; the locked amount is the
+// transaction's public burn total
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockMemo {
+    pub destination: ChainId,
+    pub recipient: String,
+    pub nonce: u64,
+}
+
+impl LockMemo {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut extra = LOCK_MEMO_TAG.to_vec();
+        extra.extend(bincode::serialize(self).unwrap_or_default());
+        extra
+    }
+
+    pub fn decode(extra: &[u8]) -> Option<Self> {
+        extra
+            .strip_prefix(LOCK_MEMO_TAG)
+            .and_then(|memo| bincode::deserialize(memo).ok())
+    }
+}
+
+// Block heights whose hashes are trusted out of band, shipped with the node
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IdiaCheckpoint {
+    pub height: u64,
+    pub hash: Hash,
+}
+
+// The lock transaction, its merkle branch in its block, and every header from
+// just after a checkpoint through the confirmations on top of the block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdiaInclusionProof {
+    pub transaction: Transaction,
+    pub tx_index: usize,
+    pub branch: Vec<Hash>,
+    pub checkpoint: IdiaCheckpoint,
+    pub headers: Vec<BlockHeader>,
+    pub block_height: u64,
+}
+
+// Read access to the local Idia chain
+#[async_trait]
+pub trait IdiaChain: Send + Sync {
+    async fn tip_height(&self) -> Result<u64, BridgeError>;
+    async fn block_at(&self, height: u64) -> Result<Block, BridgeError>;
+    // Height of the block containing the transaction, if it is on the main chain
+    async fn locate(&self, tx_hash: &Hash) -> Result<Option<u64>, BridgeError>;
+}
+
+pub struct ProofGenerator {
+    chain: Arc<dyn IdiaChain>,
+    checkpoints: Vec<IdiaCheckpoint>,
+    confirmations: u64,
+}
+
+impl ProofGenerator {
+    pub fn new(chain: Arc<dyn IdiaChain>, mut checkpoints: Vec<IdiaCheckpoint>, confirmations: u64) -> Self {
+        checkpoints.sort_by_key(|c| c.height);
+        Self {
+            chain,
+            checkpoints,
+            confirmations,
+        }
+    }
+
+    pub async fn generate_proof(
+        &self,
+        from_chain: ChainId,
+        to_chain: ChainId,
+        lock_tx: TxHash,
+    ) -> Result<CrossChainProof, BridgeError> {
+        if from_chain != ChainId::Idia {
+            return Err(BridgeError::ChainNotSupported(from_chain));
+        }
+
+        let tx_hash: Hash = lock_tx.0;
+        let height = self
+            .chain
+            .locate(&tx_hash)
+            .await?
+            .ok_or(BridgeError::UnknownLock(lock_tx))?;
+        let tip = self.chain.tip_height().await?;
+        if tip < height + self.confirmations {
+            return Err(BridgeError::Rpc(format!(
+                "lock at height {} has {} of {} confirmations",
+                height,
+                tip - height,
+                self.confirmations
+            )));
+        }
+
+        let block = self.chain.block_at(height).await?;
+        let tx_index = block
+            .transactions
+            .iter()
+            .position(|tx| tx.hash() == tx_hash)
+            .ok_or(BridgeError::UnknownLock(lock_tx))?;
+        let transaction = block.transactions[tx_index].clone();
+        let memo = LockMemo::decode(&transaction.extra)
+            .ok_or_else(|| BridgeError::MalformedProof("transaction is not a bridge lock".to_string()))?;
+        if memo.destination != to_chain {
+            return Err(BridgeError::MalformedProof(format!(
+                "lock is bound for {}, not {}",
+                memo.destination, to_chain
+            )));
+        }
+        let branch = block
+            .merkle_branch(tx_index)
+            .ok_or(BridgeError::UnknownLock(lock_tx))?;
+
+        let checkpoint = *self
+            .checkpoints
+            .iter()
+            .rev()
+            .find(|c| c.height < height)
+            .ok_or_else(|| BridgeError::Rpc(format!("no checkpoint below height {}", height)))?;
+        let mut headers = Vec::new();
+        for h in checkpoint.height + 1..=height + self.confirmations {
+            headers.push(self.chain.block_at(h).await?.header);
+        }
+
+        Ok(CrossChainProof {
+            source_chain: ChainId::Idia,
+            destination_chain: to_chain,
+            lock_tx,
+            amount: transaction.burned_amount(),
+            recipient: memo.recipient,
+            nonce: memo.nonce,
+            payload: ProofPayload::Idia(IdiaInclusionProof {
+                transaction,
+                tx_index,
+                branch,
+                checkpoint,
+                headers,
+                block_height: height,
+            }),
+            approval: None,
+        })
+    }
+}
+
+#[async_trait]
+impl ProofSource for ProofGenerator {
+    async fn prove(&self, event: &LockEvent) -> Result<CrossChainProof, BridgeError> {
+        self.generate_proof(event.source, event.destination, event.lock_tx).await
+    }
+}
+
+// Checks Idia inclusion proofs. Consensus does not enforce proof of work yet
+// (see `Block::verify`), so a header chain only proves linkage to a trusted
+// checkpoint; with a local chain attached, the proven block must also be the
+// one our own node has at that height.
+pub struct StateVerifier {
+    checkpoints: Vec<IdiaCheckpoint>,
+    confirmations: u64,
+    chain: Option<Arc<dyn IdiaChain>>,
+}
+
+impl StateVerifier {
+    pub fn new(checkpoints: Vec<IdiaCheckpoint>, confirmations: u64) -> Self {
+        Self {
+            checkpoints,
+            confirmations,
+            chain: None,
+        }
+    }
+
+    pub fn with_chain(mut self, chain: Arc<dyn IdiaChain>) -> Self {
+        self.chain = Some(chain);
+        self
+    }
+
+    pub async fn verify_proof(&self, proof: &CrossChainProof) -> Result<bool, BridgeError> {
+        let ProofPayload::Idia(inclusion) = &proof.payload else {
+            return Ok(false);
+        };
+        if proof.source_chain != ChainId::Idia {
+            return Ok(false);
+        }
+        let Some(block_hash) = self.verify_inclusion(proof, inclusion) else {
+            return Ok(false);
+        };
+
+        if let Some(chain) = &self.chain {
+            let local = chain.block_at(inclusion.block_height).await?;
+            if local.hash() != block_hash {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Returns the hash of the block holding the lock if the proof holds together
+    fn verify_inclusion(&self, proof: &CrossChainProof, inclusion: &IdiaInclusionProof) -> Option<Hash> {
+        let transaction = &inclusion.transaction;
+        let tx_hash = transaction.hash();
+        if tx_hash != proof.lock_tx.0 {
+            return None;
+        }
+
+        let memo = LockMemo::decode(&transaction.extra)?;
+        if memo.destination != proof.destination_chain
+            || memo.recipient != proof.recipient
+            || memo.nonce != proof.nonce
+            || transaction.burned_amount() != proof.amount
+            || !transaction.burns.iter().all(|burn| burn.verify())
+        {
+            return None;
+        }
+
+        let checkpoint = self
+            .checkpoints
+            .iter()
+            .find(|c| c.height == inclusion.checkpoint.height && c.hash == inclusion.checkpoint.hash)?;
+
+        // Headers must run contiguously from the checkpoint
+        let mut previous = checkpoint.hash;
+        let mut block_hash = None;
+        for (offset, header) in inclusion.headers.iter().enumerate() {
+            if header.prev_hash != previous || header.height != checkpoint.height + 1 + offset as u64 {
+                return None;
+            }
+            previous = hash_of(header);
+            if header.height == inclusion.block_height {
+                if !Block::verify_merkle_branch(tx_hash, inclusion.tx_index, &inclusion.branch, header.merkle_root) {
+                    return None;
+                }
+                block_hash = Some(previous);
+            }
+        }
+
+        let last = inclusion.headers.last()?.height;
+        if last < inclusion.block_height + self.confirmations {
+            return None;
+        }
+        block_hash
+    }
+}
@@ -0,0 +1,400 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, RwLock};
+
+use super::manager::ChainAdapter;
+use super::operations::{OperationState, OperationStore, Transition};
+use super::optimistic::{OptimisticBridge, OptimisticError};
+use super::pause::BridgePause;
+use super::types::{BridgeError, ChainId, CrossChainProof, TxHash};
+use super::velocity::VelocityMonitor;
+
+const BASE_BACKOFF_SECS: i64 = 5;
+const MAX_BACKOFF_SECS: i64 = 600;
+const MAX_ATTEMPTS: u32 = 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RelayerError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+// A lock observed on a source chain that should be released on `destination`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockEvent {
+    pub source: ChainId,
+    pub destination: ChainId,
+    pub lock_tx: TxHash,
+    pub amount: u64,
+    pub recipient: String,
+    pub nonce: u64,
+}
+
+impl LockEvent {
+    pub fn id(&self) -> String {
+        format!("{}:{:?}", self.source, self.lock_tx)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayStage {
+    Observed,
+    Proven,
+    // Written before the release is submitted, so a crash mid-submission is
+    // resumed as a resubmission, which the destination must reject if it landed
+    Releasing,
+    Released,
+    // Handed to the optimistic bridge, which releases after the challenge window
+    Claimed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayRecord {
+    pub event: LockEvent,
+    pub stage: RelayStage,
+    pub proof: Option<CrossChainProof>,
+    pub release_tx: Option<TxHash>,
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RelayRecord {
+    fn is_pending(&self) -> bool {
+        !matches!(self.stage, RelayStage::Released | RelayStage::Claimed | RelayStage::Failed)
+    }
+}
+
+// Produces the proof of a lock on one source chain, once it is final there
+#[async_trait]
+pub trait ProofSource: Send + Sync {
+    async fn prove(&self, event: &LockEvent) -> Result<CrossChainProof, BridgeError>;
+}
+
+// Every state change is appended as a JSON line; the last line for an
+// operation wins when the log is replayed
+pub struct RelayStore {
+    path: PathBuf,
+    records: HashMap<String, RelayRecord>,
+}
+
+impl RelayStore {
+    pub async fn open(path: PathBuf) -> Result<Self, RelayerError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut records = HashMap::new();
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            let record: RelayRecord = serde_json::from_str(line)?;
+            records.insert(record.event.id(), record);
+        }
+
+        let store = Self { path, records };
+        store.compact().await?;
+        Ok(store)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&RelayRecord> {
+        self.records.get(id)
+    }
+
+    pub fn pending(&self) -> Vec<&RelayRecord> {
+        self.records.values().filter(|r| r.is_pending()).collect()
+    }
+
+    async fn put(&mut self, record: RelayRecord) -> Result<(), RelayerError> {
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        self.records.insert(record.event.id(), record);
+        Ok(())
+    }
+
+    async fn compact(&self) -> Result<(), RelayerError> {
+        let mut contents = Vec::new();
+        for record in self.records.values() {
+            contents.extend(serde_json::to_vec(record)?);
+            contents.push(b'\n');
+        }
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, contents).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+// Drives observed locks through proof generation and release. Progress is
+// persisted after every step, so a restarted relayer picks up where it stopped.
+pub struct Relayer {
+    store: RwLock<RelayStore>,
+    provers: HashMap<ChainId, Arc<dyn ProofSource>>,
+    adapters: HashMap<ChainId, Arc<dyn ChainAdapter + Send + Sync>>,
+    operations: Option<Arc<RwLock<OperationStore>>>,
+    pause: Option<Arc<BridgePause>>,
+    velocity: Option<Arc<VelocityMonitor>>,
+    // The bridge and the bond account claims are posted under
+    optimistic: Option<(Arc<OptimisticBridge>, String)>,
+}
+
+impl Relayer {
+    pub fn new(store: RelayStore) -> Self {
+        Self {
+            store: RwLock::new(store),
+            provers: HashMap::new(),
+            adapters: HashMap::new(),
+            operations: None,
+            pause: None,
+            velocity: None,
+            optimistic: None,
+        }
+    }
+
+    // Posts proven locks as bonded optimistic claims instead of releasing them
+    pub fn with_optimistic(mut self, bridge: Arc<OptimisticBridge>, relayer: String) -> Self {
+        self.optimistic = Some((bridge, relayer));
+        self
+    }
+
+    pub fn with_velocity(mut self, velocity: Arc<VelocityMonitor>) -> Self {
+        self.velocity = Some(velocity);
+        self
+    }
+
+    pub fn with_pause(mut self, pause: Arc<BridgePause>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    // Mirrors relay progress onto the bridge operation for the same lock
+    pub fn with_operations(mut self, operations: Arc<RwLock<OperationStore>>) -> Self {
+        self.operations = Some(operations);
+        self
+    }
+
+    pub fn with_prover(mut self, chain: ChainId, prover: Arc<dyn ProofSource>) -> Self {
+        self.provers.insert(chain, prover);
+        self
+    }
+
+    // Destination adapters are shared with the manager, wrapped in the same
+    // ReplayGuard and LedgerRecorder, so both paths are guarded and accounted
+    pub fn with_adapter(mut self, chain: ChainId, adapter: Arc<dyn ChainAdapter + Send + Sync>) -> Self {
+        self.adapters.insert(chain, adapter);
+        self
+    }
+
+    pub async fn get(&self, id: &str) -> Option<RelayRecord> {
+        self.store.read().await.get(id).cloned()
+    }
+
+    // Records a newly seen lock; seeing the same lock again is a no-op
+    pub async fn observe(&self, event: LockEvent) -> Result<(), RelayerError> {
+        let mut store = self.store.write().await;
+        if store.get(&event.id()).is_some() {
+            return Ok(());
+        }
+        let now = Utc::now();
+        store
+            .put(RelayRecord {
+                event,
+                stage: RelayStage::Observed,
+                proof: None,
+                release_tx: None,
+                attempts: 0,
+                next_attempt: now,
+                last_error: None,
+                updated_at: now,
+            })
+            .await
+    }
+
+    // Advances every pending operation whose backoff has elapsed
+    pub async fn process_due(&self) -> Result<(), RelayerError> {
+        let now = Utc::now();
+        let due: Vec<RelayRecord> = self
+            .store
+            .read()
+            .await
+            .pending()
+            .into_iter()
+            .filter(|r| r.next_attempt <= now)
+            .cloned()
+            .collect();
+
+        for record in due {
+            let id = record.event.id();
+            let updated = match self.advance(record.clone()).await {
+                Ok(updated) => updated,
+                Err(e) => self.retry_later(record, e),
+            };
+            if updated.stage == RelayStage::Failed {
+                tracing::error!("Relay of {} failed: {}", id, updated.last_error.as_deref().unwrap_or(""));
+            }
+            self.store.write().await.put(updated).await?;
+        }
+        Ok(())
+    }
+
+    pub fn spawn(self: Arc<Self>, mut events: mpsc::Receiver<LockEvent>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    Some(event) = events.recv() => {
+                        let id = event.id();
+                        if let Err(e) = self.observe(event).await {
+                            tracing::error!("Failed to record lock {}: {}", id, e);
+                        }
+                    }
+                    _ = interval.tick() => {
+                        if let Err(e) = self.process_due().await {
+                            tracing::error!("Relayer failed to persist progress: {}", e);
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    // Runs the operation forward as far as it will go, persisting each stage
+    async fn advance(&self, mut record: RelayRecord) -> Result<RelayRecord, BridgeError> {
+        let event = record.event.clone();
+
+        if record.stage == RelayStage::Observed {
+            let prover = self
+                .provers
+                .get(&event.source)
+                .ok_or(BridgeError::ChainNotSupported(event.source))?;
+            record.proof = Some(prover.prove(&event).await?);
+            record = self.checkpoint(record, RelayStage::Proven).await?;
+            self.mirror(&event, OperationState::Proven, Transition {
+                proof: record.proof.clone(),
+                ..Default::default()
+            })
+            .await;
+        }
+
+        if matches!(record.stage, RelayStage::Proven | RelayStage::Releasing) {
+            // Counted once per lock: a retried release comes back with attempts > 0.
+            // Done before the pause check so a tripped alarm holds this release too.
+            if record.stage == RelayStage::Proven && record.attempts == 0 {
+                if let Some(velocity) = &self.velocity {
+                    velocity.record(event.destination, &event.recipient, event.amount, Utc::now()).await;
+                }
+            }
+            if let Some(pause) = &self.pause {
+                pause.check(event.source).await?;
+                pause.check(event.destination).await?;
+            }
+            if let Some((bridge, relayer)) = &self.optimistic {
+                let proof = record.proof.clone().ok_or(BridgeError::InvalidProof)?;
+                let claim_id = bridge.submit_claim(relayer, proof).await.map_err(|e| match e {
+                    OptimisticError::Bridge(e) => e,
+                    e => BridgeError::Rpc(format!("optimistic claim: {}", e)),
+                })?;
+                record.stage = RelayStage::Claimed;
+                record.last_error = None;
+                record.updated_at = Utc::now();
+                tracing::info!("Claimed {} optimistically as claim {}", event.id(), claim_id);
+                return Ok(record);
+            }
+            let adapter = self
+                .adapters
+                .get(&event.destination)
+                .ok_or(BridgeError::ChainNotSupported(event.destination))?;
+            let proof = record.proof.clone().ok_or(BridgeError::InvalidProof)?;
+            if !adapter.verify_proof(&proof).await? {
+                return Err(BridgeError::InvalidProof);
+            }
+
+            record = self.checkpoint(record, RelayStage::Releasing).await?;
+            record.release_tx = Some(adapter.release_assets(&proof).await?);
+            record.stage = RelayStage::Released;
+            record.last_error = None;
+            record.updated_at = Utc::now();
+            tracing::info!("Released {} on {}", event.id(), event.destination);
+            self.mirror(&event, OperationState::Released, Transition {
+                release_tx: record.release_tx,
+                ..Default::default()
+            })
+            .await;
+        }
+        Ok(record)
+    }
+
+    async fn mirror(&self, event: &LockEvent, state: OperationState, update: Transition) {
+        let Some(operations) = &self.operations else {
+            return;
+        };
+        let mut operations = operations.write().await;
+        let Some(id) = operations.find_by_lock(event.lock_tx).map(|o| o.id) else {
+            return;
+        };
+        if let Err(e) = operations.transition(id, state, update).await {
+            tracing::warn!("Bridge operation {} not moved to {:?}: {}", id, state, e);
+        }
+    }
+
+    async fn checkpoint(&self, mut record: RelayRecord, stage: RelayStage) -> Result<RelayRecord, BridgeError> {
+        record.stage = stage;
+        record.attempts = 0;
+        record.updated_at = Utc::now();
+        self.store
+            .write()
+            .await
+            .put(record.clone())
+            .await
+            .map_err(|e| BridgeError::Rpc(format!("relay store: {}", e)))?;
+        Ok(record)
+    }
+
+    // RPC failures and releases still collecting federation signatures back off
+    // exponentially; a paused chain waits however long the pause lasts, and
+    // anything else cannot succeed on retry
+    fn retry_later(&self, mut record: RelayRecord, error: BridgeError) -> RelayRecord {
+        let now = Utc::now();
+        record.attempts += 1;
+        record.last_error = Some(error.to_string());
+        record.updated_at = now;
+
+        let transient = matches!(error, BridgeError::Rpc(_) | BridgeError::QuorumNotReached);
+        let paused = matches!(error, BridgeError::Paused { .. });
+        if paused || (transient && record.attempts < MAX_ATTEMPTS) {
+            let delay = (BASE_BACKOFF_SECS << record.attempts.min(16)).min(MAX_BACKOFF_SECS);
+            record.next_attempt = now + chrono::Duration::seconds(delay);
+            tracing::warn!(
+                "Relay of {} attempt {} failed, retrying in {}s: {}",
+                record.event.id(),
+                record.attempts,
+                delay,
+                error
+            );
+        } else {
+            record.stage = RelayStage::Failed;
+        }
+        record
+    }
+}
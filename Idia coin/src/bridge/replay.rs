@@ -0,0 +1,311 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use crate::metrics::bridge::BRIDGE_REPLAYS_REJECTED;
+
+use super::manager::ChainAdapter;
+use super::types::{BridgeError, ChainId, CrossChainProof, TxHash};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofStatus {
+    // Written before the release is submitted; if the node dies before the
+    // outcome is known the proof stays blocked until an operator clears it
+    Claimed,
+    Released,
+    // The release was refused before anything was submitted, so it may be retried
+    Abandoned,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessedProof {
+    pub source_chain: ChainId,
+    pub destination_chain: ChainId,
+    pub nonce: u64,
+    pub lock_tx: TxHash,
+    pub status: ProofStatus,
+    pub release_tx: Option<TxHash>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ProcessedProof {
+    // A lock is released once, whichever destination a proof names; lock
+    // hashes are only unique on the chain that mined them
+    pub fn key(source_chain: ChainId, lock_tx: TxHash) -> String {
+        format!("{}:{:?}", source_chain, lock_tx)
+    }
+
+    fn id(&self) -> String {
+        Self::key(self.source_chain, self.lock_tx)
+    }
+}
+
+// Proofs that have released, or may have released, funds on each destination.
+// Every change is appended as a JSON line and synced before the release goes
+// out; the last line for a proof wins when the log is replayed.
+pub struct ProcessedProofs {
+    path: PathBuf,
+    proofs: HashMap<String, ProcessedProof>,
+    nonces: HashMap<(ChainId, u64), String>,
+}
+
+impl ProcessedProofs {
+    pub async fn open(path: PathBuf) -> Result<Self, ReplayError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut store = Self {
+            path,
+            proofs: HashMap::new(),
+            nonces: HashMap::new(),
+        };
+        let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(proof) => store.index(proof),
+                // A crash mid-append leaves a partial last line; anything earlier is real corruption
+                Err(e) if index + 1 == lines.len() => {
+                    tracing::warn!("Dropping torn last record of {}: {}", store.path.display(), e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        store.compact().await?;
+
+        let claimed = store.proofs.values().filter(|p| p.status == ProofStatus::Claimed).count();
+        if claimed > 0 {
+            tracing::warn!("{} bridge releases have an unknown outcome and stay blocked until cleared", claimed);
+        }
+        Ok(store)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ProcessedProof> {
+        self.proofs.get(key)
+    }
+
+    // The earlier proof this one would replay, matched by lock or by the
+    // source bridge's nonce, which still identifies a lock that a reorg
+    // re-mined under a different hash
+    pub fn conflict(&self, proof: &CrossChainProof) -> Option<&ProcessedProof> {
+        let by_lock = self.proofs.get(&ProcessedProof::key(proof.source_chain, proof.lock_tx));
+        let by_nonce = self
+            .nonces
+            .get(&(proof.source_chain, proof.nonce))
+            .and_then(|key| self.proofs.get(key));
+        by_lock
+            .into_iter()
+            .chain(by_nonce)
+            .find(|p| p.status != ProofStatus::Abandoned)
+    }
+
+    pub async fn claim(&mut self, proof: &CrossChainProof) -> Result<(), BridgeError> {
+        if let Some(existing) = self.conflict(proof) {
+            BRIDGE_REPLAYS_REJECTED
+                .with_label_values(&[&proof.destination_chain.to_string()])
+                .inc();
+            return Err(BridgeError::AlreadyProcessed {
+                chain: existing.source_chain,
+                nonce: existing.nonce,
+            });
+        }
+        self.put(ProcessedProof {
+            source_chain: proof.source_chain,
+            destination_chain: proof.destination_chain,
+            nonce: proof.nonce,
+            lock_tx: proof.lock_tx,
+            status: ProofStatus::Claimed,
+            release_tx: None,
+            updated_at: Utc::now(),
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub async fn settle(&mut self, key: &str, status: ProofStatus, release_tx: Option<TxHash>) -> Result<(), ReplayError> {
+        let Some(mut proof) = self.proofs.get(key).cloned() else {
+            return Ok(());
+        };
+        proof.status = status;
+        proof.release_tx = release_tx;
+        proof.updated_at = Utc::now();
+        self.put(proof).await
+    }
+
+    // For an operator who has confirmed on the destination chain that a
+    // claimed release never landed
+    pub async fn clear(&mut self, key: &str) -> Result<(), ReplayError> {
+        match self.proofs.get(key) {
+            Some(proof) if proof.status == ProofStatus::Claimed => self.settle(key, ProofStatus::Abandoned, None).await,
+            _ => Ok(()),
+        }
+    }
+
+    fn index(&mut self, proof: ProcessedProof) {
+        let id = proof.id();
+        self.nonces.insert((proof.source_chain, proof.nonce), id.clone());
+        self.proofs.insert(id, proof);
+    }
+
+    async fn put(&mut self, proof: ProcessedProof) -> Result<(), ReplayError> {
+        let mut line = serde_json::to_vec(&proof)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        self.index(proof);
+        Ok(())
+    }
+
+    async fn compact(&self) -> Result<(), ReplayError> {
+        let mut contents = Vec::new();
+        for proof in self.proofs.values() {
+            contents.extend(serde_json::to_vec(proof)?);
+            contents.push(b'\n');
+        }
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, contents).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+// Wraps a destination adapter so no proof releases twice, whoever submits it.
+// Place it outermost so refusals from wrapped adapters free the claim again.
+pub struct ReplayGuard<A> {
+    inner: A,
+    processed: Arc<RwLock<ProcessedProofs>>,
+}
+
+impl<A> ReplayGuard<A> {
+    pub fn new(inner: A, processed: Arc<RwLock<ProcessedProofs>>) -> Self {
+        Self { inner, processed }
+    }
+}
+
+#[async_trait]
+impl<A: ChainAdapter + Send + Sync> ChainAdapter for ReplayGuard<A> {
+    async fn verify_proof(&self, proof: &CrossChainProof) -> Result<bool, BridgeError> {
+        self.inner.verify_proof(proof).await
+    }
+
+    async fn lock_assets(&self, amount: u64, recipient: &str) -> Result<TxHash, BridgeError> {
+        self.inner.lock_assets(amount, recipient).await
+    }
+
+    async fn release_assets(&self, proof: &CrossChainProof) -> Result<TxHash, BridgeError> {
+        let key = ProcessedProof::key(proof.source_chain, proof.lock_tx);
+        // Held across the release so two submitters cannot both pass the check
+        let mut processed = self.processed.write().await;
+        processed.claim(proof).await?;
+
+        match self.inner.release_assets(proof).await {
+            Ok(release_tx) => {
+                if let Err(e) = processed.settle(&key, ProofStatus::Released, Some(release_tx)).await {
+                    // The claim alone already blocks a replay
+                    tracing::error!("Failed to record release of {}: {}", key, e);
+                }
+                Ok(release_tx)
+            }
+            // Refused before anything was submitted: the proof may be used again
+            Err(e) if refused(&e) => {
+                processed.settle(&key, ProofStatus::Abandoned, None).await?;
+                Err(e)
+            }
+            Err(e) => {
+                tracing::warn!("Release of {} has an unknown outcome and stays claimed: {}", key, e);
+                Err(e)
+            }
+        }
+    }
+}
+
+fn refused(error: &BridgeError) -> bool {
+    matches!(
+        error,
+        BridgeError::QuorumNotReached
+            | BridgeError::Paused { .. }
+            | BridgeError::InvalidProof
+            | BridgeError::MalformedProof(_)
+            | BridgeError::InvalidRecipient(_)
+            | BridgeError::ChainNotSupported(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::types::ProofPayload;
+
+    fn proof(destination_chain: ChainId, lock_tx: TxHash, nonce: u64) -> CrossChainProof {
+        CrossChainProof {
+            source_chain: ChainId::Ethereum,
+            destination_chain,
+            lock_tx,
+            amount: 1_000,
+            recipient: "recipient".to_string(),
+            nonce,
+            payload: ProofPayload::Opaque(Vec::new()),
+            approval: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lock_releases_once_across_destinations() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut processed = ProcessedProofs::open(dir.path().join("processed.jsonl")).await.unwrap();
+        let lock_tx = TxHash::repeat_byte(1);
+        processed.claim(&proof(ChainId::Idia, lock_tx, 1)).await.unwrap();
+
+        // Same lock named for another destination, or under another nonce
+        assert!(processed.claim(&proof(ChainId::Cosmos, lock_tx, 1)).await.is_err());
+        assert!(processed.claim(&proof(ChainId::Idia, lock_tx, 2)).await.is_err());
+        // Same nonce re-mined under another hash
+        assert!(processed.claim(&proof(ChainId::Idia, TxHash::repeat_byte(2), 1)).await.is_err());
+
+        let key = ProcessedProof::key(ChainId::Ethereum, lock_tx);
+        processed.settle(&key, ProofStatus::Abandoned, None).await.unwrap();
+        processed.claim(&proof(ChainId::Cosmos, lock_tx, 1)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reopen_after_partial_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("processed.jsonl");
+        let lock_tx = TxHash::repeat_byte(1);
+        {
+            let mut processed = ProcessedProofs::open(path.clone()).await.unwrap();
+            processed.claim(&proof(ChainId::Idia, lock_tx, 1)).await.unwrap();
+        }
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(&path).await.unwrap();
+        file.write_all(br#"{"source_chain":"ethe"#).await.unwrap();
+        drop(file);
+
+        let processed = ProcessedProofs::open(path).await.unwrap();
+        let claimed = processed.get(&ProcessedProof::key(ChainId::Ethereum, lock_tx)).unwrap();
+        assert_eq!(claimed.status, ProofStatus::Claimed);
+    }
+}
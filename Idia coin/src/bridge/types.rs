@@ -0,0 +1,148 @@
+use ethers::types::{Bytes, H256, U256};
+use serde::{Deserialize, Serialize};
+
+pub type TxHash = H256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainId {
+    Idia,
+    Bitcoin,
+    Ethereum,
+    Solana,
+    Polkadot,
+    Cosmos,
+}
+
+impl std::str::FromStr for ChainId {
+    type Err = BridgeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "idia" => Ok(ChainId::Idia),
+            "bitcoin" => Ok(ChainId::Bitcoin),
+            "ethereum" => Ok(ChainId::Ethereum),
+            "solana" => Ok(ChainId::Solana),
+            "polkadot" => Ok(ChainId::Polkadot),
+            "cosmos" => Ok(ChainId::Cosmos),
+            other => Err(BridgeError::UnknownChain(other.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for ChainId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ChainId::Idia => "idia",
+            ChainId::Bitcoin => "bitcoin",
+            ChainId::Ethereum => "ethereum",
+            ChainId::Solana => "solana",
+            ChainId::Polkadot => "polkadot",
+            ChainId::Cosmos => "cosmos",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BridgeError {
+    #[error("Chain not supported: {0}")]
+    ChainNotSupported(ChainId),
+    #[error("Unknown chain: {0}")]
+    UnknownChain(String),
+    #[error("Bridge transfers on {chain} are paused: {reason}")]
+    Paused { chain: ChainId, reason: String },
+    #[error("Invalid cross-chain proof")]
+    InvalidProof,
+    #[error("Malformed proof: {0}")]
+    MalformedProof(String),
+    #[error("Invalid recipient: {0}")]
+    InvalidRecipient(String),
+    #[error("Unknown lock transaction: {0:?}")]
+    UnknownLock(TxHash),
+    #[error("Chain RPC error: {0}")]
+    Rpc(String),
+    #[error("Contract error: {0}")]
+    Contract(String),
+    #[error("Signing error: {0}")]
+    Signing(String),
+    #[error("Release has not been signed by a federation quorum")]
+    QuorumNotReached,
+    #[error("Transfer of {amount} to {chain} is below the minimum of {min}")]
+    BelowMinimum { chain: ChainId, amount: u64, min: u64 },
+    #[error("Transfer of {amount} to {chain} is above the maximum of {max}")]
+    AboveMaximum { chain: ChainId, amount: u64, max: u64 },
+    #[error("Transfer of {amount} to {chain} exceeds the epoch cap, {remaining} remaining")]
+    EpochCapExceeded { chain: ChainId, amount: u64, remaining: u64 },
+    #[error("Operation store error: {0}")]
+    Operation(#[from] super::operations::OperationError),
+    #[error("Lock {nonce} from {chain} has already been released")]
+    AlreadyProcessed { chain: ChainId, nonce: u64 },
+    #[error("Processed proof store error: {0}")]
+    Replay(#[from] super::replay::ReplayError),
+    #[error("HTLC lock store error: {0}")]
+    Locks(#[from] super::btc::LockStoreError),
+}
+
+// Evidence that assets were locked on the source chain, in the form the
+// source chain's adapter knows how to check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProofPayload {
+    Idia(super::proof::IdiaInclusionProof),
+    Bitcoin(super::btc::BitcoinSpvProof),
+    Ethereum(super::ethereum::EthereumReceiptProof),
+    Cosmos(super::cosmos::CosmosLockProof),
+    Opaque(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossChainProof {
+    pub source_chain: ChainId,
+    pub destination_chain: ChainId,
+    pub lock_tx: TxHash,
+    pub amount: u64,
+    pub recipient: String,
+    pub nonce: u64,
+    pub payload: ProofPayload,
+    // Federation signature over the `ReleaseRequest` message, attached by
+    // `FederatedAdapter` for the destination to check when it releases
+    #[serde(default)]
+    pub approval: Option<Vec<u8>>,
+}
+
+impl CrossChainProof {
+    // Argument tuple of the bridge contract's `verifyProof` and `release`
+    pub fn to_eth_format(&self) -> (H256, U256, String, U256, Bytes) {
+        let payload = match &self.payload {
+            ProofPayload::Opaque(bytes) => bytes.clone(),
+            other => bincode::serialize(other).unwrap_or_default(),
+        };
+        (
+            self.lock_tx,
+            U256::from(self.amount),
+            self.recipient.clone(),
+            U256::from(self.nonce),
+            Bytes::from(payload),
+        )
+    }
+
+    // Arguments of the bridge contract's `release`, which checks the
+    // federation signature against the group key it was deployed with
+    pub fn to_eth_release_format(&self) -> Result<(H256, U256, String, U256, Bytes, Bytes), BridgeError> {
+        let approval = self.approval.clone().ok_or(BridgeError::QuorumNotReached)?;
+        let (lock_tx, amount, recipient, nonce, payload) = self.to_eth_format();
+        Ok((lock_tx, amount, recipient, nonce, payload, Bytes::from(approval)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeOperation {
+    pub id: u64,
+    pub from_chain: ChainId,
+    pub to_chain: ChainId,
+    pub amount: u64,
+    pub fee: u64,
+    pub lock_tx: TxHash,
+    pub release_tx: TxHash,
+    pub proof: CrossChainProof,
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::metrics::bridge::BRIDGE_VELOCITY_ALARMS;
+
+use super::pause::BridgePause;
+use super::types::ChainId;
+
+// What happens when a chain's release volume outruns its bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VelocityAction {
+    // Halts the chain until governance resumes it
+    Pause,
+    // Releases need this many federation shares beyond the quorum until the
+    // window has passed without another alarm
+    ExtraConfirmations(usize),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VelocityBounds {
+    pub window_secs: i64,
+    // Total released on the chain within the window
+    pub chain_volume: u64,
+    // Total released to any single recipient within the window
+    pub recipient_volume: u64,
+    pub action: VelocityAction,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VelocityConfig {
+    // Chains without an entry are not monitored
+    pub chains: HashMap<ChainId, VelocityBounds>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VelocityAlarm {
+    pub chain: ChainId,
+    // Set when a single recipient tripped the alarm
+    pub recipient: Option<String>,
+    pub volume: u64,
+    pub bound: u64,
+    pub window_secs: i64,
+    pub action: VelocityAction,
+    pub at: DateTime<Utc>,
+}
+
+impl VelocityAlarm {
+    pub fn reason(&self) -> String {
+        match &self.recipient {
+            Some(recipient) => format!(
+                "{} released to {} within {}s, bound is {}",
+                self.volume, recipient, self.window_secs, self.bound
+            ),
+            None => format!(
+                "{} released within {}s, bound is {}",
+                self.volume, self.window_secs, self.bound
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Transfer {
+    at: DateTime<Utc>,
+    recipient: String,
+    amount: u64,
+}
+
+// Rolling-window release volume per destination chain and per recipient.
+// A compromised key drains as fast as it can; bounding the rate caps what it
+// gets before the chain halts or the federation asks for more signers.
+pub struct VelocityMonitor {
+    config: VelocityConfig,
+    transfers: RwLock<HashMap<ChainId, VecDeque<Transfer>>>,
+    escalated: RwLock<HashMap<ChainId, (usize, DateTime<Utc>)>>,
+    alarms: RwLock<VecDeque<VelocityAlarm>>,
+    pause: Option<Arc<BridgePause>>,
+}
+
+impl VelocityMonitor {
+    const MAX_ALARMS: usize = 100;
+
+    pub fn new(config: VelocityConfig) -> Self {
+        Self {
+            config,
+            transfers: RwLock::new(HashMap::new()),
+            escalated: RwLock::new(HashMap::new()),
+            alarms: RwLock::new(VecDeque::new()),
+            pause: None,
+        }
+    }
+
+    // Needed for `VelocityAction::Pause`; without it such alarms are only logged
+    pub fn with_pause(mut self, pause: Arc<BridgePause>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    // Counts a release towards the window and raises an alarm if it crossed a bound
+    pub async fn record(&self, chain: ChainId, recipient: &str, amount: u64, now: DateTime<Utc>) -> Option<VelocityAlarm> {
+        let bounds = self.config.chains.get(&chain)?;
+
+        let alarm = {
+            let mut transfers = self.transfers.write().await;
+            let window = transfers.entry(chain).or_default();
+            let cutoff = now - Duration::seconds(bounds.window_secs);
+            while window.front().is_some_and(|t| t.at < cutoff) {
+                window.pop_front();
+            }
+            window.push_back(Transfer {
+                at: now,
+                recipient: recipient.to_string(),
+                amount,
+            });
+
+            let chain_volume: u64 = window.iter().map(|t| t.amount).sum();
+            let recipient_volume: u64 = window
+                .iter()
+                .filter(|t| t.recipient == recipient)
+                .map(|t| t.amount)
+                .sum();
+
+            let raise = |recipient: Option<String>, volume, bound| VelocityAlarm {
+                chain,
+                recipient,
+                volume,
+                bound,
+                window_secs: bounds.window_secs,
+                action: bounds.action,
+                at: now,
+            };
+            if recipient_volume > bounds.recipient_volume {
+                raise(Some(recipient.to_string()), recipient_volume, bounds.recipient_volume)
+            } else if chain_volume > bounds.chain_volume {
+                raise(None, chain_volume, bounds.chain_volume)
+            } else {
+                return None;
+            }
+        };
+
+        tracing::warn!("Bridge velocity alarm on {}: {}", chain, alarm.reason());
+        BRIDGE_VELOCITY_ALARMS.with_label_values(&[&chain.to_string()]).inc();
+        match alarm.action {
+            VelocityAction::Pause => match &self.pause {
+                Some(pause) => pause.trip(chain, &alarm.reason()).await,
+                None => tracing::error!("Velocity alarm on {} wants a pause but no pause switch is wired", chain),
+            },
+            VelocityAction::ExtraConfirmations(extra) => {
+                self.escalated.write().await.insert(chain, (extra, now));
+            }
+        }
+
+        let mut alarms = self.alarms.write().await;
+        if alarms.len() == Self::MAX_ALARMS {
+            alarms.pop_front();
+        }
+        alarms.push_back(alarm.clone());
+        Some(alarm)
+    }
+
+    // Shares a release to `chain` needs on top of the federation quorum
+    pub async fn extra_confirmations(&self, chain: ChainId, now: DateTime<Utc>) -> usize {
+        let Some(bounds) = self.config.chains.get(&chain) else {
+            return 0;
+        };
+        match self.escalated.read().await.get(&chain) {
+            Some((extra, since)) if now - *since < Duration::seconds(bounds.window_secs) => *extra,
+            _ => 0,
+        }
+    }
+
+    pub async fn alarms(&self) -> Vec<VelocityAlarm> {
+        self.alarms.read().await.iter().cloned().collect()
+    }
+}
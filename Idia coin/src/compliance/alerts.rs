@@ -0,0 +1,202 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::audit::{AuditError, AuditEvent, ComplianceAuditLog};
+use super::checks::{CheckResult, ComplianceCheckType, TransactionCheck};
+use super::reporter::{AlertComment, AlertType, ComplianceAlert, ResolutionStatus};
+use super::webhooks::{WebhookDispatcher, WebhookEventKind};
+use crate::metrics::compliance::HIGH_RISK_TRANSACTIONS;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AlertError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Alert not found: {0}")]
+    NotFound(u64),
+    #[error("Audit log error: {0}")]
+    Audit(#[from] AuditError),
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AlertFilter {
+    pub status: Option<ResolutionStatus>,
+    pub assignee: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+// Alerts persisted as a JSON array, rewritten on every change
+pub struct AlertStore {
+    path: PathBuf,
+    alerts: BTreeMap<u64, ComplianceAlert>,
+    next_id: u64,
+    audit: Option<Arc<ComplianceAuditLog>>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+}
+
+impl AlertStore {
+    pub async fn open(path: PathBuf) -> Result<Self, AlertError> {
+        let alerts: Vec<ComplianceAlert> = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let next_id = alerts.iter().map(|a| a.id + 1).max().unwrap_or(1);
+
+        let store = Self {
+            path,
+            alerts: alerts.into_iter().map(|a| (a.id, a)).collect(),
+            next_id,
+            audit: None,
+            webhooks: None,
+        };
+        store.update_gauge();
+        Ok(store)
+    }
+
+    pub fn with_audit_log(mut self, audit: Arc<ComplianceAuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    pub async fn raise(
+        &mut self,
+        alert_type: AlertType,
+        description: String,
+        transaction_id: Option<String>,
+    ) -> Result<u64, AlertError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let now = Utc::now();
+        self.alerts.insert(
+            id,
+            ComplianceAlert {
+                id,
+                timestamp: now,
+                alert_type,
+                description,
+                resolution_status: ResolutionStatus::Open,
+                transaction_id,
+                assignee: None,
+                comments: Vec::new(),
+                updated_at: now,
+            },
+        );
+        self.save().await?;
+
+        if let Some(webhooks) = &self.webhooks {
+            match serde_json::to_value(&self.alerts[&id]) {
+                Ok(data) => webhooks.notify(WebhookEventKind::AlertRaised, data).await,
+                Err(e) => tracing::error!("Failed to serialize alert {}: {}", id, e),
+            }
+        }
+        Ok(id)
+    }
+
+    // One alert per check that did not pass
+    pub async fn raise_from_check(&mut self, check: &TransactionCheck) -> Result<Vec<u64>, AlertError> {
+        let mut raised = Vec::new();
+        for result in &check.checks {
+            let reason = match &result.result {
+                CheckResult::Pass => continue,
+                CheckResult::Fail(reason) | CheckResult::Warning(reason) => reason.clone(),
+                CheckResult::RequiresReview => result.details.clone(),
+            };
+            let alert_type = match result.check_type {
+                ComplianceCheckType::AmountRange | ComplianceCheckType::VolumeLimit => AlertType::LargeTransaction,
+                ComplianceCheckType::PatternAnalysis => AlertType::AnomalousPattern,
+                ComplianceCheckType::RingSignatureValidation | ComplianceCheckType::StealthAddressFormat => {
+                    AlertType::PrivacyFeatureFailure
+                }
+                _ => AlertType::ComplianceCheckFailure,
+            };
+            let description = format!("{:?}: {}", result.check_type, reason);
+            raised.push(self.raise(alert_type, description, Some(check.transaction_id.clone())).await?);
+        }
+        Ok(raised)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ComplianceAlert> {
+        self.alerts.get(&id)
+    }
+
+    pub fn list(&self, filter: &AlertFilter) -> Vec<&ComplianceAlert> {
+        self.alerts
+            .values()
+            .filter(|a| filter.status.map_or(true, |s| a.resolution_status == s))
+            .filter(|a| filter.assignee.as_ref().map_or(true, |who| a.assignee.as_ref() == Some(who)))
+            .filter(|a| filter.since.map_or(true, |since| a.updated_at >= since))
+            .collect()
+    }
+
+    // Alerts worth including in a report: anything unresolved, plus whatever changed since `since`
+    pub fn for_report(&self, since: DateTime<Utc>) -> Vec<ComplianceAlert> {
+        self.alerts
+            .values()
+            .filter(|a| a.is_open() || a.updated_at >= since)
+            .cloned()
+            .collect()
+    }
+
+    pub async fn set_status(&mut self, id: u64, status: ResolutionStatus) -> Result<(), AlertError> {
+        let alert = self.alerts.get_mut(&id).ok_or(AlertError::NotFound(id))?;
+        if alert.resolution_status == status {
+            return Ok(());
+        }
+        let from = alert.resolution_status;
+
+        if let Some(audit) = &self.audit {
+            audit
+                .append(AuditEvent::AlertStatusChanged {
+                    alert_id: id,
+                    from,
+                    to: status,
+                })
+                .await?;
+        }
+        alert.resolution_status = status;
+        alert.updated_at = Utc::now();
+        self.save().await
+    }
+
+    pub async fn assign(&mut self, id: u64, assignee: Option<String>) -> Result<(), AlertError> {
+        let alert = self.alerts.get_mut(&id).ok_or(AlertError::NotFound(id))?;
+        alert.assignee = assignee;
+        alert.updated_at = Utc::now();
+        self.save().await
+    }
+
+    pub async fn comment(&mut self, id: u64, author: String, text: String) -> Result<(), AlertError> {
+        let alert = self.alerts.get_mut(&id).ok_or(AlertError::NotFound(id))?;
+        let now = Utc::now();
+        alert.comments.push(AlertComment {
+            author,
+            timestamp: now,
+            text,
+        });
+        alert.updated_at = now;
+        self.save().await
+    }
+
+    async fn save(&self) -> Result<(), AlertError> {
+        let alerts: Vec<&ComplianceAlert> = self.alerts.values().collect();
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(&alerts)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        self.update_gauge();
+        Ok(())
+    }
+
+    fn update_gauge(&self) {
+        HIGH_RISK_TRANSACTIONS.set(self.alerts.values().filter(|a| a.is_open()).count() as i64);
+    }
+}
@@ -1,16 +1,212 @@
 use axum::{
-    routing::{get, post},
+    routing::{get, patch, post, put},
     Router,
     Json,
-    extract::State,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use super::alerts::{AlertError, AlertFilter, AlertStore};
+use super::audit::ComplianceAuditLog;
+use super::authorization::{
+    parse_hex, AuthorizationError, DisclosureScope, IssuedViewKey, TransactionSource, ViewKeyAuthorization,
+    ViewKeyIssuer,
+};
+use super::cases::{Case, CaseError, CaseStatus, CaseStore};
+use super::checks::{ComplianceChecker, ComplianceConfig};
+use super::config::{ConfigChange, ConfigError};
+use super::escrow::{EscrowError, EscrowStore, EscrowedViewKey, PartialDerivation};
+use super::results::{CheckQuery, CheckStore};
+use super::sar::{ReportingEntity, SarError, SuspiciousActivityReport};
+use super::sealing::SealingError;
+use super::travel_rule::{EncryptedTravelRulePayload, IdentityPayload, TravelRuleAttachment, TravelRuleError};
+use super::webhooks::{Delivery, DeliveryState, WebhookConfig, WebhookDispatcher};
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::Scalar;
+use idia_core::{Hash, RangeAttestation};
+
 #[derive(Clone)]
 pub struct ComplianceState {
     reporter: Arc<RwLock<crate::compliance::reporter::ComplianceReporter>>,
+    issuer: Arc<ViewKeyIssuer>,
+    audit: Arc<ComplianceAuditLog>,
+    transactions: Arc<dyn TransactionSource>,
+    alerts: Arc<RwLock<AlertStore>>,
+    cases: Arc<RwLock<CaseStore>>,
+    checker: Arc<ComplianceChecker>,
+    // This node's VASP key for opening travel-rule payloads addressed to it
+    travel_rule_key: Option<Scalar>,
+    reporting_entity: Option<ReportingEntity>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+    results: Option<Arc<RwLock<CheckStore>>>,
+    escrows: Option<Arc<RwLock<EscrowStore>>>,
+}
+
+impl ComplianceState {
+    pub fn new(
+        reporter: Arc<RwLock<crate::compliance::reporter::ComplianceReporter>>,
+        issuer: Arc<ViewKeyIssuer>,
+        audit: Arc<ComplianceAuditLog>,
+        transactions: Arc<dyn TransactionSource>,
+        alerts: Arc<RwLock<AlertStore>>,
+        cases: Arc<RwLock<CaseStore>>,
+        checker: Arc<ComplianceChecker>,
+    ) -> Self {
+        Self { reporter, issuer, audit, transactions, alerts, cases, checker, travel_rule_key: None, reporting_entity: None, webhooks: None, results: None, escrows: None }
+    }
+
+    pub fn with_travel_rule_key(mut self, key: Scalar) -> Self {
+        self.travel_rule_key = Some(key);
+        self
+    }
+
+    pub fn with_reporting_entity(mut self, entity: ReportingEntity) -> Self {
+        self.reporting_entity = Some(entity);
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    pub fn with_check_store(mut self, results: Arc<RwLock<CheckStore>>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    pub fn with_escrow_store(mut self, escrows: Arc<RwLock<EscrowStore>>) -> Self {
+        self.escrows = Some(escrows);
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertUpdate {
+    status: Option<crate::compliance::reporter::ResolutionStatus>,
+    assignee: Option<String>,
+    #[serde(default)]
+    unassign: bool,
+    comment: Option<AlertCommentRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertCommentRequest {
+    author: String,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CaseFilter {
+    status: Option<CaseStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewCaseRequest {
+    title: String,
+    #[serde(default)]
+    alert_ids: Vec<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CaseUpdate {
+    status: Option<CaseStatus>,
+    assignee: Option<String>,
+    #[serde(default)]
+    unassign: bool,
+    note: Option<AlertCommentRequest>,
+    #[serde(default)]
+    alert_ids: Vec<u64>,
+    #[serde(default)]
+    transaction_ids: Vec<String>,
+    // File names of exported reports in the reporter's data directory
+    #[serde(default)]
+    reports: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CaseExportRequest {
+    // Hex-encoded compressed Ristretto key of the receiving investigator
+    recipient: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SarFormat {
+    #[default]
+    Goaml,
+    Json,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SarRequest {
+    #[serde(default)]
+    format: SarFormat,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigUpdate {
+    // Operator responsible for the change, recorded in the audit log
+    changed_by: String,
+    config: ComplianceConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryFilter {
+    webhook_id: Option<String>,
+    state: Option<DeliveryState>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttestationRequest {
+    transaction_id: String,
+    output_index: u32,
+    threshold: u64,
+    // Hex-encoded range proof from `RangeAttestation::proof_bytes`
+    proof: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttestationResult {
+    transaction_id: String,
+    output_index: u32,
+    threshold: u64,
+    below_threshold: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TravelRuleDecodeRequest {
+    // Hex-encoded tx_extra of the transaction
+    tx_extra: String,
+    // Hex-encoded sealed payload, when the transaction only carries a reference
+    payload: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditStatus {
+    entries: u64,
+    head_hash: String,
+    valid: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnchorCheck {
+    // Sequence and head hash as published by `AuditAnchor`
+    sequence: u64,
+    head_hash: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnchorVerification {
+    sequence: u64,
+    valid: bool,
+    error: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -21,18 +217,49 @@ pub struct ComplianceStatus {
     compliance_checks_active: Vec<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct EscrowedViewKeyRequest {
+    #[serde(flatten)]
+    request: ViewKeyRequest,
+    // At least `threshold` custodians' derivations for the authorized outputs
+    derivations: Vec<PartialDerivation>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ViewKeyRequest {
     transaction_id: String,
     requesting_authority: String,
+    scope: DisclosureScope,
+    expires_at: DateTime<Utc>,
+    // Hex-encoded Ed25519 signature over (transaction_id, scope, expires_at)
     authorization_proof: String,
 }
 
-pub fn create_compliance_routes() -> Router {
+pub fn create_compliance_routes(state: ComplianceState) -> Router {
     Router::new()
         .route("/compliance/status", get(compliance_status))
         .route("/compliance/report", get(generate_report))
         .route("/compliance/view-key", post(request_view_key))
+        .route("/compliance/escrow", post(deposit_escrow))
+        .route("/compliance/escrow/:id", get(get_escrow))
+        .route("/compliance/escrow/:id/view-key", post(request_escrowed_view_key))
+        .route("/compliance/audit", get(audit_status))
+        .route("/compliance/audit/verify", post(verify_audit_anchor))
+        .route("/compliance/travel-rule/decode", post(decode_travel_rule))
+        .route("/compliance/attestations/verify", post(verify_attestation))
+        .route("/compliance/alerts", get(list_alerts))
+        .route("/compliance/alerts/:id", patch(update_alert))
+        .route("/compliance/cases", get(list_cases).post(create_case))
+        .route("/compliance/cases/:id", get(get_case).patch(update_case))
+        .route("/compliance/cases/:id/export", post(export_case))
+        .route("/compliance/cases/:id/sar", get(case_sar))
+        .route("/compliance/checks", get(query_checks))
+        .route("/compliance/checks/:transaction_id", get(transaction_checks))
+        .route("/compliance/config", get(get_config).put(update_config))
+        .route("/compliance/config/reload", post(reload_config))
+        .route("/compliance/webhooks", get(list_webhooks))
+        .route("/compliance/webhooks/deliveries", get(list_deliveries))
+        .with_state(state)
 }
 
 async fn compliance_status() -> Json<ComplianceStatus> {
@@ -60,20 +287,422 @@ async fn generate_report(
     Json(report)
 }
 
-async fn request_view_key(
-    Json(request): Json<ViewKeyRequest>
-) -> Json<serde_json::Value> {
-    // Validate authorization and generate view key
-    // This is a placeholder implementation
-    Json(serde_json::json!({
-        "status": "authorized",
-        "view_key": "dummy_view_key",
-        "valid_until": chrono::Utc::now() + chrono::Duration::hours(24),
-        "transaction_id": request.transaction_id,
-        "restrictions": {
-            "purpose": "law_enforcement",
-            "scope": "single_transaction",
-            "expires_in": "24h"
+async fn audit_status(
+    State(state): State<ComplianceState>
+) -> Json<AuditStatus> {
+    let (entries, head_hash) = state.audit.head().await;
+    let verification = state.audit.verify().await;
+    Json(AuditStatus {
+        entries,
+        head_hash,
+        valid: verification.is_ok(),
+        error: verification.err().map(|e| e.to_string()),
+    })
+}
+
+// Lets an auditor check the node's log against a head hash they fetched from
+// the anchor transaction, rather than trusting the node's own view of it
+async fn verify_audit_anchor(
+    State(state): State<ComplianceState>,
+    Json(check): Json<AnchorCheck>
+) -> Json<AnchorVerification> {
+    let verification = state.audit.verify_anchor(check.sequence, &check.head_hash).await;
+    Json(AnchorVerification {
+        sequence: check.sequence,
+        valid: verification.is_ok(),
+        error: verification.err().map(|e| e.to_string()),
+    })
+}
+
+async fn list_alerts(
+    State(state): State<ComplianceState>,
+    Query(filter): Query<AlertFilter>,
+) -> Json<Vec<crate::compliance::reporter::ComplianceAlert>> {
+    let alerts = state.alerts.read().await;
+    Json(alerts.list(&filter).into_iter().cloned().collect())
+}
+
+async fn update_alert(
+    State(state): State<ComplianceState>,
+    Path(id): Path<u64>,
+    Json(update): Json<AlertUpdate>,
+) -> Result<Json<crate::compliance::reporter::ComplianceAlert>, StatusCode> {
+    let mut alerts = state.alerts.write().await;
+    let result = async {
+        if update.unassign {
+            alerts.assign(id, None).await?;
+        } else if let Some(assignee) = update.assignee {
+            alerts.assign(id, Some(assignee)).await?;
+        }
+        if let Some(comment) = update.comment {
+            alerts.comment(id, comment.author, comment.text).await?;
+        }
+        if let Some(status) = update.status {
+            alerts.set_status(id, status).await?;
+        }
+        Ok::<_, AlertError>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => alerts.get(id).cloned().map(Json).ok_or(StatusCode::NOT_FOUND),
+        Err(AlertError::NotFound(_)) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to update alert {}: {}", id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
+    }
+}
+
+fn case_status(e: &CaseError) -> StatusCode {
+    match e {
+        CaseError::NotFound(_) => StatusCode::NOT_FOUND,
+        CaseError::UnknownAlert(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        CaseError::Closed(_) => StatusCode::CONFLICT,
+        CaseError::InvalidReport(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+async fn list_cases(
+    State(state): State<ComplianceState>,
+    Query(filter): Query<CaseFilter>,
+) -> Json<Vec<Case>> {
+    let cases = state.cases.read().await;
+    Json(cases.list(filter.status).into_iter().cloned().collect())
+}
+
+async fn create_case(
+    State(state): State<ComplianceState>,
+    Json(request): Json<NewCaseRequest>,
+) -> Result<Json<Case>, StatusCode> {
+    let alerts = state.alerts.read().await;
+    let mut cases = state.cases.write().await;
+    let id = cases
+        .open_case(request.title, &alerts, request.alert_ids)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to open case: {}", e);
+            case_status(&e)
+        })?;
+    cases.get(id).cloned().map(Json).ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_case(
+    State(state): State<ComplianceState>,
+    Path(id): Path<u64>,
+) -> Result<Json<Case>, StatusCode> {
+    let cases = state.cases.read().await;
+    cases.get(id).cloned().map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn update_case(
+    State(state): State<ComplianceState>,
+    Path(id): Path<u64>,
+    Json(update): Json<CaseUpdate>,
+) -> Result<Json<Case>, StatusCode> {
+    let alerts = state.alerts.read().await;
+    let reporter = state.reporter.read().await;
+    let mut cases = state.cases.write().await;
+    let result = async {
+        // Evidence goes in before any status change, so closing and linking in one request works
+        for alert_id in update.alert_ids {
+            cases.link_alert(id, &alerts, alert_id).await?;
+        }
+        for transaction_id in update.transaction_ids {
+            cases.link_transaction(id, transaction_id).await?;
+        }
+        for name in update.reports {
+            cases.link_report(id, &reporter, name).await?;
+        }
+        if update.unassign {
+            cases.assign(id, None).await?;
+        } else if let Some(assignee) = update.assignee {
+            cases.assign(id, Some(assignee)).await?;
+        }
+        if let Some(note) = update.note {
+            cases.add_note(id, note.author, note.text).await?;
+        }
+        if let Some(status) = update.status {
+            cases.set_status(id, status).await?;
+        }
+        Ok::<_, CaseError>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => cases.get(id).cloned().map(Json).ok_or(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to update case {}: {}", id, e);
+            Err(case_status(&e))
+        }
+    }
+}
+
+async fn export_case(
+    State(state): State<ComplianceState>,
+    Path(id): Path<u64>,
+    request: Option<Json<CaseExportRequest>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    // Exporting is audited, so it is a POST; an empty body exports unsealed
+    let Json(request) = request.unwrap_or_default();
+    let recipient = match &request.recipient {
+        Some(key) => Some(
+            parse_hex(key)
+                .and_then(|bytes| CompressedRistretto::from_slice(&bytes).ok())
+                .and_then(|point| point.decompress())
+                .ok_or(StatusCode::BAD_REQUEST)?,
+        ),
+        None => None,
+    };
+
+    let alerts = state.alerts.read().await;
+    let reporter = state.reporter.read().await;
+    let cases = state.cases.read().await;
+    let exported = async {
+        let bundle = cases.bundle(id, &alerts, &reporter, &state.checker, &state.audit).await?;
+        cases.export(&bundle, recipient.as_ref()).await
+    }
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to export case {}: {}", id, e);
+        case_status(&e)
+    })?;
+
+    let content_type = if recipient.is_some() { "application/octet-stream" } else { "application/json" };
+    Ok(([(header::CONTENT_TYPE, content_type)], exported))
+}
+
+async fn case_sar(
+    State(state): State<ComplianceState>,
+    Path(id): Path<u64>,
+    Query(request): Query<SarRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let entity = state.reporting_entity.clone().ok_or(StatusCode::NOT_IMPLEMENTED)?;
+
+    let alerts = state.alerts.read().await;
+    let reporter = state.reporter.read().await;
+    let cases = state.cases.read().await;
+    let bundle = cases
+        .bundle(id, &alerts, &reporter, &state.checker, &state.audit)
+        .await
+        .map_err(|e| case_status(&e))?;
+    let report = SuspiciousActivityReport::from_case(&bundle, entity).map_err(|e| match e {
+        SarError::NothingToReport => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    match request.format {
+        SarFormat::Goaml => Ok(([(header::CONTENT_TYPE, "application/xml")], report.to_goaml_xml())),
+        SarFormat::Json => {
+            let json = report.to_json().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            Ok(([(header::CONTENT_TYPE, "application/json")], json))
+        }
+    }
+}
+
+async fn query_checks(
+    State(state): State<ComplianceState>,
+    Query(query): Query<CheckQuery>,
+) -> Result<Json<Vec<crate::compliance::checks::TransactionCheck>>, StatusCode> {
+    let results = state.results.as_ref().ok_or(StatusCode::NOT_IMPLEMENTED)?.read().await;
+    Ok(Json(results.query(&query).into_iter().cloned().collect()))
+}
+
+async fn transaction_checks(
+    State(state): State<ComplianceState>,
+    Path(transaction_id): Path<String>,
+) -> Result<Json<Vec<crate::compliance::checks::TransactionCheck>>, StatusCode> {
+    let results = state.results.as_ref().ok_or(StatusCode::NOT_IMPLEMENTED)?.read().await;
+    let checks = results.for_transaction(&transaction_id);
+    if checks.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(checks.into_iter().cloned().collect()))
+}
+
+fn config_failure(e: ConfigError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match e {
+        ConfigError::Invalid { .. }
+        | ConfigError::Toml(_)
+        | ConfigError::Json(_)
+        | ConfigError::UnsupportedFormat(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        ConfigError::NoConfigFile => StatusCode::CONFLICT,
+        ConfigError::Io(_) | ConfigError::Audit(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(serde_json::json!({ "reason": e.to_string() })))
+}
+
+async fn get_config(
+    State(state): State<ComplianceState>
+) -> Json<ComplianceConfig> {
+    Json(state.checker.config().await)
+}
+
+async fn update_config(
+    State(state): State<ComplianceState>,
+    Json(update): Json<ConfigUpdate>,
+) -> Result<Json<Vec<ConfigChange>>, (StatusCode, Json<serde_json::Value>)> {
+    if update.changed_by.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(serde_json::json!({ "reason": "changed_by is required" }))));
+    }
+    state.checker.update_config(update.config, &update.changed_by).await.map(Json).map_err(config_failure)
+}
+
+async fn reload_config(
+    State(state): State<ComplianceState>
+) -> Result<Json<Vec<ConfigChange>>, (StatusCode, Json<serde_json::Value>)> {
+    state.checker.reload_config().await.map(Json).map_err(config_failure)
+}
+
+async fn list_webhooks(
+    State(state): State<ComplianceState>
+) -> Json<Vec<WebhookConfig>> {
+    Json(state.webhooks.as_ref().map(|w| w.webhooks().to_vec()).unwrap_or_default())
+}
+
+async fn list_deliveries(
+    State(state): State<ComplianceState>,
+    Query(filter): Query<DeliveryFilter>,
+) -> Json<Vec<Delivery>> {
+    let Some(webhooks) = &state.webhooks else {
+        return Json(Vec::new());
+    };
+    Json(webhooks.deliveries(filter.webhook_id.as_deref(), filter.state).await)
+}
+
+async fn verify_attestation(
+    State(state): State<ComplianceState>,
+    Json(request): Json<AttestationRequest>
+) -> Result<Json<AttestationResult>, StatusCode> {
+    let hash: Hash = parse_hex(&request.transaction_id)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let proof = parse_hex(&request.proof).ok_or(StatusCode::BAD_REQUEST)?;
+    let attestation = RangeAttestation::from_bytes(request.threshold, &proof).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    // Verify against the commitment on chain, not one supplied by the prover
+    let tx = state.transactions.transaction(&hash).await.ok_or(StatusCode::NOT_FOUND)?;
+    let output = tx.outputs.get(request.output_index as usize).ok_or(StatusCode::NOT_FOUND)?;
+    let below_threshold = attestation.verify(&output.commitment).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    Ok(Json(AttestationResult {
+        transaction_id: request.transaction_id,
+        output_index: request.output_index,
+        threshold: request.threshold,
+        below_threshold,
     }))
+}
+
+async fn decode_travel_rule(
+    State(state): State<ComplianceState>,
+    Json(request): Json<TravelRuleDecodeRequest>
+) -> Result<Json<IdentityPayload>, (StatusCode, Json<serde_json::Value>)> {
+    let failure = |status: StatusCode, reason: String| (status, Json(serde_json::json!({ "reason": reason })));
+
+    let key = state.travel_rule_key.as_ref()
+        .ok_or_else(|| failure(StatusCode::NOT_IMPLEMENTED, "No travel-rule key configured".to_string()))?;
+    let extra = parse_hex(&request.tx_extra)
+        .ok_or_else(|| failure(StatusCode::BAD_REQUEST, "tx_extra is not hex".to_string()))?;
+    let fetched = match &request.payload {
+        Some(payload) => Some(parse_hex(payload)
+            .ok_or_else(|| failure(StatusCode::BAD_REQUEST, "payload is not hex".to_string()))?),
+        None => None,
+    };
+
+    let decoded = TravelRuleAttachment::from_tx_extra(&extra).and_then(|attachment| {
+        attachment
+            .ok_or(TravelRuleError::MalformedExtra)?
+            .resolve(fetched.as_deref())
+            .and_then(|sealed: EncryptedTravelRulePayload| sealed.open(key))
+    });
+
+    decoded.map(Json).map_err(|e| {
+        let status = match e {
+            TravelRuleError::Sealing(SealingError::Decryption) => StatusCode::FORBIDDEN,
+            TravelRuleError::MissingPayload(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        failure(status, e.to_string())
+    })
+}
+
+impl ViewKeyRequest {
+    fn authorization(self) -> ViewKeyAuthorization {
+        ViewKeyAuthorization {
+            transaction_id: self.transaction_id,
+            authority: self.requesting_authority,
+            scope: self.scope,
+            expires_at: self.expires_at,
+            // Undecodable proofs still go through the issuer so the refusal is audited
+            signature: parse_hex(&self.authorization_proof).unwrap_or_default(),
+        }
+    }
+}
+
+fn view_key_denied(e: AuthorizationError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match e {
+        AuthorizationError::UnknownAuthority(_)
+        | AuthorizationError::InvalidSignature
+        | AuthorizationError::Expired(_) => StatusCode::FORBIDDEN,
+        AuthorizationError::MalformedProof(_)
+        | AuthorizationError::ExpiryTooFar(_)
+        | AuthorizationError::OutputOutOfRange(_) => StatusCode::BAD_REQUEST,
+        AuthorizationError::TransactionNotFound(_) => StatusCode::NOT_FOUND,
+        AuthorizationError::Escrow(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        AuthorizationError::Audit(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(serde_json::json!({ "status": "denied", "reason": e.to_string() })))
+}
+
+async fn request_view_key(
+    State(state): State<ComplianceState>,
+    Json(request): Json<ViewKeyRequest>
+) -> Result<Json<IssuedViewKey>, (StatusCode, Json<serde_json::Value>)> {
+    let authorization = request.authorization();
+    state.issuer.issue(&authorization).await.map(Json).map_err(view_key_denied)
+}
+
+async fn deposit_escrow(
+    State(state): State<ComplianceState>,
+    Json(escrow): Json<EscrowedViewKey>
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let escrows = state.escrows.as_ref()
+        .ok_or((StatusCode::NOT_IMPLEMENTED, Json(serde_json::json!({ "reason": "No escrow store configured" }))))?;
+    let id = escrows.write().await.deposit(escrow).await.map_err(|e| {
+        let status = match e {
+            EscrowError::InvalidThreshold { .. } | EscrowError::Malformed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(serde_json::json!({ "reason": e.to_string() })))
+    })?;
+    Ok(Json(serde_json::json!({ "escrow_id": id })))
+}
+
+async fn get_escrow(
+    State(state): State<ComplianceState>,
+    Path(id): Path<String>,
+) -> Result<Json<EscrowedViewKey>, StatusCode> {
+    let escrows = state.escrows.as_ref().ok_or(StatusCode::NOT_IMPLEMENTED)?.read().await;
+    escrows.get(&id).cloned().map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn request_escrowed_view_key(
+    State(state): State<ComplianceState>,
+    Path(id): Path<String>,
+    Json(request): Json<EscrowedViewKeyRequest>
+) -> Result<Json<IssuedViewKey>, (StatusCode, Json<serde_json::Value>)> {
+    let failure = |status: StatusCode, reason: &str| (status, Json(serde_json::json!({ "status": "denied", "reason": reason })));
+    let escrows = state.escrows.as_ref()
+        .ok_or_else(|| failure(StatusCode::NOT_IMPLEMENTED, "No escrow store configured"))?
+        .read()
+        .await;
+    let escrow = escrows.get(&id).ok_or_else(|| failure(StatusCode::NOT_FOUND, "Unknown escrow"))?;
+
+    let authorization = request.request.authorization();
+    state.issuer
+        .issue_escrowed(&authorization, escrow, &request.derivations)
+        .await
+        .map(Json)
+        .map_err(view_key_denied)
 }
\ No newline at end of file
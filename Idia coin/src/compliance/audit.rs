@@ -0,0 +1,361 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use super::authorization::DisclosureScope;
+use super::cases::CaseStatus;
+use super::config::ConfigChange;
+use super::reporter::ResolutionStatus;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Audit chain broken at entry {0}")]
+    BrokenChain(u64),
+    #[error("Partial record at entry {0}")]
+    TornRecord(u64),
+    #[error("Anchored head for entry {0} does not match the log")]
+    AnchorMismatch(u64),
+    #[error("Anchoring failed: {0}")]
+    Anchor(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    ViewKeyDisclosure {
+        transaction_id: String,
+        authority: String,
+        scope: DisclosureScope,
+        expires_at: DateTime<Utc>,
+        outputs: Vec<u32>,
+    },
+    ViewKeyDenied {
+        transaction_id: String,
+        authority: String,
+        reason: String,
+    },
+    ReportGenerated {
+        node_id: String,
+        path: String,
+        sha256: String,
+    },
+    ReportDeleted {
+        path: String,
+    },
+    AlertStatusChanged {
+        alert_id: u64,
+        from: ResolutionStatus,
+        to: ResolutionStatus,
+    },
+    CaseOpened {
+        case_id: u64,
+        title: String,
+    },
+    CaseStatusChanged {
+        case_id: u64,
+        from: CaseStatus,
+        to: CaseStatus,
+    },
+    CaseExported {
+        case_id: u64,
+        sealed: bool,
+        sha256: String,
+    },
+    EscrowShareUsed {
+        escrow_id: String,
+        custodian: String,
+        transaction_id: String,
+        authority: String,
+        granted: bool,
+    },
+    ConfigChanged {
+        changed_by: String,
+        changes: Vec<ConfigChange>,
+    },
+    HeadAnchored {
+        sequence: u64,
+        head_hash: String,
+        transaction_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub event: AuditEvent,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn compute_hash(
+        sequence: u64,
+        timestamp: &DateTime<Utc>,
+        event: &AuditEvent,
+        prev_hash: &str,
+    ) -> Result<String, AuditError> {
+        let mut hasher = Sha256::new();
+        hasher.update(prev_hash.as_bytes());
+        hasher.update(sequence.to_le_bytes());
+        hasher.update(timestamp.to_rfc3339().as_bytes());
+        hasher.update(serde_json::to_vec(event)?);
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+// Publishes the log head somewhere it can't be rewritten, e.g. a chain transaction
+#[async_trait]
+pub trait AuditAnchor: Send + Sync {
+    async fn anchor(&self, sequence: u64, head_hash: &str) -> Result<String, AuditError>;
+}
+
+struct ChainHead {
+    next_sequence: u64,
+    hash: String,
+}
+
+// Append-only JSON-lines log of regulatory actions taken by this node. Each
+// record hashes its predecessor, so anyone holding an anchored head hash can
+// detect a rewritten or truncated history.
+pub struct ComplianceAuditLog {
+    path: PathBuf,
+    head: Mutex<ChainHead>,
+}
+
+impl ComplianceAuditLog {
+    pub async fn open(path: PathBuf) -> Result<Self, AuditError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let contents = read_log(&path).await?;
+        let (records, torn_at) = parse_records(&contents)?;
+        if let Some(len) = torn_at {
+            // Cut the partial record off so the next append starts on a clean line
+            tracing::warn!("Dropping torn last record of {}", path.display());
+            let file = tokio::fs::OpenOptions::new().write(true).open(&path).await?;
+            file.set_len(len).await?;
+            file.sync_data().await?;
+        }
+        Self::verify_records(&records)?;
+        let head = ChainHead {
+            next_sequence: records.len() as u64,
+            hash: records.last().map_or_else(genesis_hash, |r| r.hash.clone()),
+        };
+
+        Ok(Self {
+            path,
+            head: Mutex::new(head),
+        })
+    }
+
+    pub async fn append(&self, event: AuditEvent) -> Result<AuditRecord, AuditError> {
+        // Holding the head lock serializes writers, so lines never interleave
+        let mut head = self.head.lock().await;
+        let timestamp = Utc::now();
+        let hash = AuditRecord::compute_hash(head.next_sequence, &timestamp, &event, &head.hash)?;
+        let record = AuditRecord {
+            sequence: head.next_sequence,
+            timestamp,
+            event,
+            prev_hash: head.hash.clone(),
+            hash,
+        };
+
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+
+        head.next_sequence += 1;
+        head.hash = record.hash.clone();
+        Ok(record)
+    }
+
+    pub async fn head(&self) -> (u64, String) {
+        let head = self.head.lock().await;
+        (head.next_sequence, head.hash.clone())
+    }
+
+    pub async fn records(&self) -> Result<Vec<AuditRecord>, AuditError> {
+        Self::read_records(&self.path).await
+    }
+
+    // Re-reads the file so edits made behind the node's back are caught;
+    // holds the head so no append is half-written while reading
+    pub async fn verify(&self) -> Result<u64, AuditError> {
+        let _head = self.head.lock().await;
+        Self::verify_file(&self.path).await
+    }
+
+    pub async fn verify_file(path: &Path) -> Result<u64, AuditError> {
+        let records = Self::read_records(path).await?;
+        Self::verify_records(&records)?;
+        Ok(records.len() as u64)
+    }
+
+    // Checks the log against a head hash published at `sequence`
+    pub async fn verify_anchor(&self, sequence: u64, head_hash: &str) -> Result<(), AuditError> {
+        let records = {
+            let _head = self.head.lock().await;
+            self.records().await?
+        };
+        Self::verify_records(&records)?;
+        match records.get(sequence as usize) {
+            Some(record) if record.hash == head_hash => Ok(()),
+            _ => Err(AuditError::AnchorMismatch(sequence)),
+        }
+    }
+
+    pub fn spawn_anchoring(self: Arc<Self>, anchor: Arc<dyn AuditAnchor>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            let mut last_anchored = None;
+            loop {
+                interval.tick().await;
+                let (next_sequence, head_hash) = self.head().await;
+                // Nothing new since the last anchor; the anchor record itself doesn't count
+                if next_sequence == 0 || last_anchored == Some(next_sequence - 1) {
+                    continue;
+                }
+
+                let sequence = next_sequence - 1;
+                match anchor.anchor(sequence, &head_hash).await {
+                    Ok(transaction_id) => {
+                        let event = AuditEvent::HeadAnchored {
+                            sequence,
+                            head_hash,
+                            transaction_id,
+                        };
+                        match self.append(event).await {
+                            Ok(record) => last_anchored = Some(record.sequence),
+                            Err(e) => tracing::error!("Failed to record audit anchor: {}", e),
+                        }
+                    }
+                    Err(e) => tracing::error!("Audit log anchoring failed: {}", e),
+                }
+            }
+        })
+    }
+
+    // Outside of `open` a torn record isn't repaired, only reported
+    async fn read_records(path: &Path) -> Result<Vec<AuditRecord>, AuditError> {
+        match parse_records(&read_log(path).await?)? {
+            (records, None) => Ok(records),
+            (records, Some(_)) => Err(AuditError::TornRecord(records.len() as u64)),
+        }
+    }
+
+    fn verify_records(records: &[AuditRecord]) -> Result<(), AuditError> {
+        let mut prev_hash = genesis_hash();
+        for (index, record) in records.iter().enumerate() {
+            let expected = AuditRecord::compute_hash(record.sequence, &record.timestamp, &record.event, &prev_hash)?;
+            if record.sequence != index as u64 || record.prev_hash != prev_hash || record.hash != expected {
+                return Err(AuditError::BrokenChain(index as u64));
+            }
+            prev_hash = record.hash.clone();
+        }
+        Ok(())
+    }
+}
+
+fn genesis_hash() -> String {
+    hex::encode([0u8; 32])
+}
+
+async fn read_log(path: &Path) -> Result<String, AuditError> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// A crash mid-append leaves a partial last line: one that doesn't parse or
+// lacks its newline. Returns the whole records and, if the tail is torn, the
+// length of the log up to it. A bad line anywhere earlier is real corruption.
+fn parse_records(contents: &str) -> Result<(Vec<AuditRecord>, Option<u64>), AuditError> {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    let mut lines = contents.split_inclusive('\n').peekable();
+    while let Some(line) = lines.next() {
+        let start = offset;
+        offset += line.len();
+        let last = lines.peek().is_none();
+        if last && !line.ends_with('\n') {
+            return Ok((records, Some(start as u64)));
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(_) if last => return Ok((records, Some(start as u64))),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok((records, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deleted(path: &str) -> AuditEvent {
+        AuditEvent::ReportDeleted { path: path.to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_reopen_after_partial_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        {
+            let log = ComplianceAuditLog::open(path.clone()).await.unwrap();
+            log.append(deleted("a")).await.unwrap();
+            log.append(deleted("b")).await.unwrap();
+        }
+
+        // Simulate a crash partway through writing a third record
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(&path).await.unwrap();
+        file.write_all(br#"{"sequence":2,"timest"#).await.unwrap();
+        drop(file);
+        assert!(matches!(ComplianceAuditLog::verify_file(&path).await, Err(AuditError::TornRecord(2))));
+
+        let log = ComplianceAuditLog::open(path.clone()).await.unwrap();
+        assert_eq!(log.head().await.0, 2);
+        log.append(deleted("c")).await.unwrap();
+        assert_eq!(log.verify().await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_detects_rewritten_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let log = ComplianceAuditLog::open(path.clone()).await.unwrap();
+        log.append(deleted("a")).await.unwrap();
+        let (_, head_hash) = log.head().await;
+        log.append(deleted("b")).await.unwrap();
+        log.verify_anchor(0, &head_hash).await.unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        tokio::fs::write(&path, contents.replacen("\"a\"", "\"x\"", 1)).await.unwrap();
+        assert!(matches!(log.verify().await, Err(AuditError::BrokenChain(0))));
+        assert!(ComplianceAuditLog::open(path).await.is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use idia_core::{Hash, ScopedViewKey, StealthAddress, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::audit::{AuditError, AuditEvent, ComplianceAuditLog};
+use super::escrow::{EscrowedViewKey, PartialDerivation};
+use super::webhooks::{WebhookDispatcher, WebhookEventKind};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthorizationError {
+    #[error("Unknown authority: {0}")]
+    UnknownAuthority(String),
+    #[error("Malformed authorization proof: {0}")]
+    MalformedProof(String),
+    #[error("Authorization signature does not verify")]
+    InvalidSignature,
+    #[error("Authorization expired at {0}")]
+    Expired(DateTime<Utc>),
+    #[error("Authorization validity exceeds {0} days")]
+    ExpiryTooFar(i64),
+    #[error("Transaction not found: {0}")]
+    TransactionNotFound(String),
+    #[error("Output {0} is not part of the transaction")]
+    OutputOutOfRange(u32),
+    #[error("Escrowed key derivation failed: {0}")]
+    Escrow(String),
+    #[error("Audit log error: {0}")]
+    Audit(#[from] AuditError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DisclosureScope {
+    Transaction,
+    Outputs { indices: Vec<u32> },
+}
+
+impl DisclosureScope {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        match self {
+            DisclosureScope::Transaction => vec![0],
+            DisclosureScope::Outputs { indices } => {
+                let mut bytes = vec![1];
+                bytes.extend_from_slice(&(indices.len() as u32).to_le_bytes());
+                for index in indices {
+                    bytes.extend_from_slice(&index.to_le_bytes());
+                }
+                bytes
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AuthorityRegistry {
+    authorities: HashMap<String, VerifyingKey>,
+}
+
+impl AuthorityRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, authority: &str, key: VerifyingKey) {
+        self.authorities.insert(authority.to_string(), key);
+    }
+
+    pub fn revoke(&mut self, authority: &str) -> bool {
+        self.authorities.remove(authority).is_some()
+    }
+
+    pub fn key(&self, authority: &str) -> Option<&VerifyingKey> {
+        self.authorities.get(authority)
+    }
+}
+
+// An authority's signed request to see one transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewKeyAuthorization {
+    pub transaction_id: String,
+    pub authority: String,
+    pub scope: DisclosureScope,
+    pub expires_at: DateTime<Utc>,
+    pub signature: Vec<u8>,
+}
+
+impl ViewKeyAuthorization {
+    // Authorities can't sign open-ended disclosures
+    pub const MAX_VALIDITY_DAYS: i64 = 30;
+
+    pub fn message(transaction_id: &str, scope: &DisclosureScope, expires_at: DateTime<Utc>) -> Vec<u8> {
+        let mut message = b"idia-view-key-authorization-v1".to_vec();
+        message.extend_from_slice(&(transaction_id.len() as u32).to_le_bytes());
+        message.extend_from_slice(transaction_id.as_bytes());
+        message.extend_from_slice(&scope.canonical_bytes());
+        message.extend_from_slice(&expires_at.timestamp().to_le_bytes());
+        message
+    }
+
+    pub fn verify(&self, registry: &AuthorityRegistry, now: DateTime<Utc>) -> Result<(), AuthorizationError> {
+        let key = registry
+            .key(&self.authority)
+            .ok_or_else(|| AuthorizationError::UnknownAuthority(self.authority.clone()))?;
+
+        if self.expires_at <= now {
+            return Err(AuthorizationError::Expired(self.expires_at));
+        }
+        if self.expires_at - now > Duration::days(Self::MAX_VALIDITY_DAYS) {
+            return Err(AuthorizationError::ExpiryTooFar(Self::MAX_VALIDITY_DAYS));
+        }
+
+        let signature = Signature::from_slice(&self.signature)
+            .map_err(|e| AuthorizationError::MalformedProof(e.to_string()))?;
+        let message = Self::message(&self.transaction_id, &self.scope, self.expires_at);
+        key.verify(&message, &signature)
+            .map_err(|_| AuthorizationError::InvalidSignature)
+    }
+}
+
+#[async_trait]
+pub trait TransactionSource: Send + Sync {
+    async fn transaction(&self, hash: &Hash) -> Option<Transaction>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisclosedOutput {
+    pub index: u32,
+    pub tx_pubkey: String,
+    pub shared_secret: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedViewKey {
+    pub transaction_id: String,
+    pub authority: String,
+    pub scope: DisclosureScope,
+    pub expires_at: DateTime<Utc>,
+    pub outputs: Vec<DisclosedOutput>,
+}
+
+pub struct ViewKeyIssuer {
+    address: StealthAddress,
+    registry: Arc<RwLock<AuthorityRegistry>>,
+    transactions: Arc<dyn TransactionSource>,
+    audit: Arc<ComplianceAuditLog>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+}
+
+impl ViewKeyIssuer {
+    pub fn new(
+        address: StealthAddress,
+        registry: Arc<RwLock<AuthorityRegistry>>,
+        transactions: Arc<dyn TransactionSource>,
+        audit: Arc<ComplianceAuditLog>,
+    ) -> Self {
+        Self {
+            address,
+            registry,
+            transactions,
+            audit,
+            webhooks: None,
+        }
+    }
+
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    pub fn registry(&self) -> Arc<RwLock<AuthorityRegistry>> {
+        self.registry.clone()
+    }
+
+    // Every decision is written to the audit log, including refusals
+    pub async fn issue(&self, authorization: &ViewKeyAuthorization) -> Result<IssuedViewKey, AuthorizationError> {
+        let result = self
+            .authorize(authorization, |tx_pubkeys| Ok(self.address.scoped_view_key(tx_pubkeys)))
+            .await;
+        self.record(authorization, result).await
+    }
+
+    // For wallets whose view key is escrowed: the shared secrets come from the
+    // custodians' derivations, which each custodian only produces after checking
+    // the same authorization itself
+    pub async fn issue_escrowed(
+        &self,
+        authorization: &ViewKeyAuthorization,
+        escrow: &EscrowedViewKey,
+        derivations: &[PartialDerivation],
+    ) -> Result<IssuedViewKey, AuthorizationError> {
+        let result = self
+            .authorize(authorization, |tx_pubkeys| {
+                escrow
+                    .combine(tx_pubkeys, derivations)
+                    .map_err(|e| AuthorizationError::Escrow(e.to_string()))
+            })
+            .await;
+        self.record(authorization, result).await
+    }
+
+    async fn record(
+        &self,
+        authorization: &ViewKeyAuthorization,
+        result: Result<IssuedViewKey, AuthorizationError>,
+    ) -> Result<IssuedViewKey, AuthorizationError> {
+        match result {
+            Ok(issued) => {
+                self.audit
+                    .append(AuditEvent::ViewKeyDisclosure {
+                        transaction_id: issued.transaction_id.clone(),
+                        authority: issued.authority.clone(),
+                        scope: issued.scope.clone(),
+                        expires_at: issued.expires_at,
+                        outputs: issued.outputs.iter().map(|o| o.index).collect(),
+                    })
+                    .await?;
+                // Subscribers learn that a disclosure happened, never the key material
+                if let Some(webhooks) = &self.webhooks {
+                    let data = serde_json::json!({
+                        "transaction_id": issued.transaction_id,
+                        "authority": issued.authority,
+                        "scope": issued.scope,
+                        "expires_at": issued.expires_at,
+                        "outputs": issued.outputs.iter().map(|o| o.index).collect::<Vec<_>>(),
+                    });
+                    webhooks.notify(WebhookEventKind::ViewKeyDisclosed, data).await;
+                }
+                Ok(issued)
+            }
+            Err(e) => {
+                self.audit
+                    .append(AuditEvent::ViewKeyDenied {
+                        transaction_id: authorization.transaction_id.clone(),
+                        authority: authorization.authority.clone(),
+                        reason: e.to_string(),
+                    })
+                    .await?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn authorize(
+        &self,
+        authorization: &ViewKeyAuthorization,
+        derive: impl FnOnce(&[RistrettoPoint]) -> Result<ScopedViewKey, AuthorizationError>,
+    ) -> Result<IssuedViewKey, AuthorizationError> {
+        authorization.verify(&*self.registry.read().await, Utc::now())?;
+
+        let (indices, tx_pubkeys) = scope_tx_pubkeys(&*self.transactions, authorization).await?;
+        let scoped = derive(&tx_pubkeys)?;
+        let outputs = indices
+            .into_iter()
+            .zip(scoped.shared_secrets)
+            .map(|(index, (tx_pubkey, shared_secret))| DisclosedOutput {
+                index,
+                tx_pubkey: hex::encode(tx_pubkey.compress().as_bytes()),
+                shared_secret: hex::encode(shared_secret.compress().as_bytes()),
+            })
+            .collect();
+
+        Ok(IssuedViewKey {
+            transaction_id: authorization.transaction_id.clone(),
+            authority: authorization.authority.clone(),
+            scope: authorization.scope.clone(),
+            expires_at: authorization.expires_at,
+            outputs,
+        })
+    }
+}
+
+// The output indices an authorization covers and their transaction public keys
+pub(crate) async fn scope_tx_pubkeys(
+    transactions: &dyn TransactionSource,
+    authorization: &ViewKeyAuthorization,
+) -> Result<(Vec<u32>, Vec<RistrettoPoint>), AuthorizationError> {
+    let hash = parse_hash(&authorization.transaction_id)
+        .ok_or_else(|| AuthorizationError::MalformedProof("transaction ID is not a hash".to_string()))?;
+    let tx = transactions
+        .transaction(&hash)
+        .await
+        .ok_or_else(|| AuthorizationError::TransactionNotFound(authorization.transaction_id.clone()))?;
+
+    let indices: Vec<u32> = match &authorization.scope {
+        DisclosureScope::Transaction => (0..tx.outputs.len() as u32).collect(),
+        DisclosureScope::Outputs { indices } => indices.clone(),
+    };
+    let mut tx_pubkeys = Vec::with_capacity(indices.len());
+    for &index in &indices {
+        let output = tx
+            .outputs
+            .get(index as usize)
+            .ok_or(AuthorizationError::OutputOutOfRange(index))?;
+        tx_pubkeys.push(output.tx_pubkey);
+    }
+    Ok((indices, tx_pubkeys))
+}
+
+pub(crate) fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    hex::decode(value).ok()
+}
+
+fn parse_hash(value: &str) -> Option<Hash> {
+    parse_hex(value)?.try_into().ok()
+}
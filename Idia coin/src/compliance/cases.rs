@@ -0,0 +1,345 @@
+use chrono::{DateTime, Utc};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::alerts::AlertStore;
+use super::audit::{AuditError, AuditEvent, AuditRecord, ComplianceAuditLog};
+use super::checks::ComplianceChecker;
+use super::reporter::{AlertComment, ComplianceAlert, ComplianceReporter};
+use super::sanctions::DisclosedEntity;
+use super::sealing::{SealedBox, SealingError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CaseError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Case not found: {0}")]
+    NotFound(u64),
+    #[error("Alert not found: {0}")]
+    UnknownAlert(u64),
+    #[error("Case {0} is closed")]
+    Closed(u64),
+    #[error("Not a report file name: {0}")]
+    InvalidReport(String),
+    #[error("Audit log error: {0}")]
+    Audit(#[from] AuditError),
+    #[error("Sealing error: {0}")]
+    Sealing(#[from] SealingError),
+    #[error("Encoding error: {0}")]
+    Encoding(#[from] bincode::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaseStatus {
+    Open,
+    UnderInvestigation,
+    Escalated,
+    Closed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Case {
+    pub id: u64,
+    pub title: String,
+    pub status: CaseStatus,
+    pub assignee: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub alert_ids: Vec<u64>,
+    pub transaction_ids: Vec<String>,
+    // File names of exported reports, resolved inside the reporter's data directory
+    #[serde(default, alias = "report_paths")]
+    pub reports: Vec<String>,
+    pub notes: Vec<AlertComment>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportEvidence {
+    pub name: String,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionEvidence {
+    pub transaction_id: String,
+    pub disclosed_parties: Vec<DisclosedEntity>,
+}
+
+// Everything an investigator needs to pick up a case, including the audit
+// records touching it so they can be checked against the anchored log
+#[derive(Debug, Serialize)]
+pub struct CaseBundle {
+    pub exported_at: DateTime<Utc>,
+    pub case: Case,
+    pub alerts: Vec<ComplianceAlert>,
+    pub transactions: Vec<TransactionEvidence>,
+    pub reports: Vec<ReportEvidence>,
+    pub audit_trail: Vec<AuditRecord>,
+    pub audit_head: String,
+}
+
+pub struct CaseStore {
+    path: PathBuf,
+    cases: BTreeMap<u64, Case>,
+    next_id: u64,
+    audit: Option<Arc<ComplianceAuditLog>>,
+}
+
+impl CaseStore {
+    const EXPORT_DOMAIN: &'static [u8] = b"idia-case-bundle-v1";
+
+    pub async fn open(path: PathBuf) -> Result<Self, CaseError> {
+        let mut cases: Vec<Case> = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        // Cases saved before reports were linked by name hold full paths
+        for case in &mut cases {
+            for report in &mut case.reports {
+                if let Some(name) = Path::new(report.as_str()).file_name().and_then(|n| n.to_str()) {
+                    *report = name.to_string();
+                }
+            }
+        }
+        let next_id = cases.iter().map(|c| c.id + 1).max().unwrap_or(1);
+
+        Ok(Self {
+            path,
+            cases: cases.into_iter().map(|c| (c.id, c)).collect(),
+            next_id,
+            audit: None,
+        })
+    }
+
+    pub fn with_audit_log(mut self, audit: Arc<ComplianceAuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub async fn open_case(&mut self, title: String, alerts: &AlertStore, alert_ids: Vec<u64>) -> Result<u64, CaseError> {
+        let mut transaction_ids = Vec::new();
+        for id in &alert_ids {
+            let alert = alerts.get(*id).ok_or(CaseError::UnknownAlert(*id))?;
+            if let Some(tx) = &alert.transaction_id {
+                if !transaction_ids.contains(tx) {
+                    transaction_ids.push(tx.clone());
+                }
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        if let Some(audit) = &self.audit {
+            audit.append(AuditEvent::CaseOpened { case_id: id, title: title.clone() }).await?;
+        }
+
+        let now = Utc::now();
+        self.cases.insert(
+            id,
+            Case {
+                id,
+                title,
+                status: CaseStatus::Open,
+                assignee: None,
+                created_at: now,
+                updated_at: now,
+                alert_ids,
+                transaction_ids,
+                reports: Vec::new(),
+                notes: Vec::new(),
+            },
+        );
+        self.save().await?;
+        Ok(id)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Case> {
+        self.cases.get(&id)
+    }
+
+    pub fn list(&self, status: Option<CaseStatus>) -> Vec<&Case> {
+        self.cases.values().filter(|c| status.map_or(true, |s| c.status == s)).collect()
+    }
+
+    pub async fn link_alert(&mut self, id: u64, alerts: &AlertStore, alert_id: u64) -> Result<(), CaseError> {
+        let alert = alerts.get(alert_id).ok_or(CaseError::UnknownAlert(alert_id))?;
+        let case = self.open_case_mut(id)?;
+        if !case.alert_ids.contains(&alert_id) {
+            case.alert_ids.push(alert_id);
+        }
+        if let Some(tx) = &alert.transaction_id {
+            if !case.transaction_ids.contains(tx) {
+                case.transaction_ids.push(tx.clone());
+            }
+        }
+        case.updated_at = Utc::now();
+        self.save().await
+    }
+
+    pub async fn link_transaction(&mut self, id: u64, transaction_id: String) -> Result<(), CaseError> {
+        let case = self.open_case_mut(id)?;
+        if !case.transaction_ids.contains(&transaction_id) {
+            case.transaction_ids.push(transaction_id);
+        }
+        case.updated_at = Utc::now();
+        self.save().await
+    }
+
+    pub async fn link_report(&mut self, id: u64, reporter: &ComplianceReporter, name: String) -> Result<(), CaseError> {
+        if reporter.report_path(&name).is_none() {
+            return Err(CaseError::InvalidReport(name));
+        }
+        let case = self.open_case_mut(id)?;
+        if !case.reports.contains(&name) {
+            case.reports.push(name);
+        }
+        case.updated_at = Utc::now();
+        self.save().await
+    }
+
+    pub async fn add_note(&mut self, id: u64, author: String, text: String) -> Result<(), CaseError> {
+        let case = self.cases.get_mut(&id).ok_or(CaseError::NotFound(id))?;
+        let now = Utc::now();
+        case.notes.push(AlertComment { author, timestamp: now, text });
+        case.updated_at = now;
+        self.save().await
+    }
+
+    pub async fn assign(&mut self, id: u64, assignee: Option<String>) -> Result<(), CaseError> {
+        let case = self.cases.get_mut(&id).ok_or(CaseError::NotFound(id))?;
+        case.assignee = assignee;
+        case.updated_at = Utc::now();
+        self.save().await
+    }
+
+    pub async fn set_status(&mut self, id: u64, status: CaseStatus) -> Result<(), CaseError> {
+        let case = self.cases.get_mut(&id).ok_or(CaseError::NotFound(id))?;
+        if case.status == status {
+            return Ok(());
+        }
+        if let Some(audit) = &self.audit {
+            audit
+                .append(AuditEvent::CaseStatusChanged {
+                    case_id: id,
+                    from: case.status,
+                    to: status,
+                })
+                .await?;
+        }
+        case.status = status;
+        case.updated_at = Utc::now();
+        self.save().await
+    }
+
+    pub async fn bundle(
+        &self,
+        id: u64,
+        alerts: &AlertStore,
+        reporter: &ComplianceReporter,
+        checker: &ComplianceChecker,
+        audit: &ComplianceAuditLog,
+    ) -> Result<CaseBundle, CaseError> {
+        let case = self.cases.get(&id).ok_or(CaseError::NotFound(id))?.clone();
+
+        let case_alerts: Vec<ComplianceAlert> = case.alert_ids.iter().filter_map(|a| alerts.get(*a).cloned()).collect();
+
+        let mut transactions = Vec::new();
+        for transaction_id in &case.transaction_ids {
+            transactions.push(TransactionEvidence {
+                transaction_id: transaction_id.clone(),
+                disclosed_parties: checker.disclosures_for(transaction_id).await,
+            });
+        }
+
+        let mut reports = Vec::new();
+        for name in &case.reports {
+            // A report deleted by retention is still listed, without a hash
+            let sha256 = match reporter.report_path(name) {
+                Some(path) => tokio::fs::read(path).await.ok().map(|bytes| hex::encode(Sha256::digest(bytes))),
+                None => None,
+            };
+            reports.push(ReportEvidence { name: name.clone(), sha256 });
+        }
+
+        let audit_trail = audit
+            .records()
+            .await?
+            .into_iter()
+            .filter(|record| Self::concerns(&case, &record.event))
+            .collect();
+        let (_, audit_head) = audit.head().await;
+
+        Ok(CaseBundle {
+            exported_at: Utc::now(),
+            case,
+            alerts: case_alerts,
+            transactions,
+            reports,
+            audit_trail,
+            audit_head,
+        })
+    }
+
+    // Serializes the bundle for handover, sealed to the investigator's key when
+    // one is given. The digest of exactly what left the node is audited.
+    pub async fn export(&self, bundle: &CaseBundle, recipient: Option<&RistrettoPoint>) -> Result<Vec<u8>, CaseError> {
+        let json = serde_json::to_vec_pretty(bundle)?;
+        let contents = match recipient {
+            Some(key) => bincode::serialize(&SealedBox::seal(&json, Self::EXPORT_DOMAIN, key)?)?,
+            None => json,
+        };
+
+        if let Some(audit) = &self.audit {
+            audit
+                .append(AuditEvent::CaseExported {
+                    case_id: bundle.case.id,
+                    sealed: recipient.is_some(),
+                    sha256: hex::encode(Sha256::digest(&contents)),
+                })
+                .await?;
+        }
+        Ok(contents)
+    }
+
+    fn concerns(case: &Case, event: &AuditEvent) -> bool {
+        match event {
+            AuditEvent::ViewKeyDisclosure { transaction_id, .. }
+            | AuditEvent::ViewKeyDenied { transaction_id, .. }
+            | AuditEvent::EscrowShareUsed { transaction_id, .. } => {
+                case.transaction_ids.contains(transaction_id)
+            }
+            AuditEvent::AlertStatusChanged { alert_id, .. } => case.alert_ids.contains(alert_id),
+            AuditEvent::CaseOpened { case_id, .. }
+            | AuditEvent::CaseStatusChanged { case_id, .. }
+            | AuditEvent::CaseExported { case_id, .. } => *case_id == case.id,
+            AuditEvent::ReportGenerated { path, .. } | AuditEvent::ReportDeleted { path } => {
+                let name = Path::new(path).file_name().and_then(|n| n.to_str());
+                name.is_some_and(|name| case.reports.iter().any(|r| r == name))
+            }
+            AuditEvent::ConfigChanged { .. } | AuditEvent::HeadAnchored { .. } => false,
+        }
+    }
+
+    fn open_case_mut(&mut self, id: u64) -> Result<&mut Case, CaseError> {
+        let case = self.cases.get_mut(&id).ok_or(CaseError::NotFound(id))?;
+        if case.status == CaseStatus::Closed {
+            return Err(CaseError::Closed(id));
+        }
+        Ok(case)
+    }
+
+    async fn save(&self) -> Result<(), CaseError> {
+        let cases: Vec<&Case> = self.cases.values().collect();
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(&cases)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
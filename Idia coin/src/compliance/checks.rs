@@ -1,21 +1,44 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::audit::{AuditEvent, ComplianceAuditLog};
+use super::config::{spawn_file_watch, ConfigChange, ConfigError};
+use super::geo::GeoProvider;
+use super::patterns::{DisclosedTransaction, PatternFinding, PatternStore};
+use super::rules::{CompliancePolicy, PolicyError, RuleCondition, Severity};
+use super::sanctions::{DisclosedEntity, SanctionsMatch, SanctionsScreener};
+use super::results::CheckStore;
+use super::risk::{RiskFactor, RiskScore};
+use super::volume::{VolumeError, VolumeTracker};
+use super::webhooks::{WebhookDispatcher, WebhookEventKind};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionCheck {
     pub transaction_id: String,
     pub timestamp: DateTime<Utc>,
     pub checks: Vec<ComplianceCheck>,
+    #[serde(default)]
+    pub risk: RiskScore,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComplianceCheck {
     pub check_type: ComplianceCheckType,
     pub result: CheckResult,
     pub details: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matches: Vec<SanctionsMatch>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<PatternFinding>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ComplianceCheckType {
     TransactionSize,
     RingSignatureValidation,
@@ -29,7 +52,7 @@ pub enum ComplianceCheckType {
     TimeBasedRestrictions,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CheckResult {
     Pass,
     Fail(String),
@@ -38,10 +61,24 @@ pub enum CheckResult {
 }
 
 pub struct ComplianceChecker {
-    config: ComplianceConfig,
+    config: Arc<RwLock<ComplianceConfig>>,
+    config_path: Option<PathBuf>,
+    audit: Option<Arc<ComplianceAuditLog>>,
+    sanctions: Option<Arc<SanctionsScreener>>,
+    // Counterparties revealed through view-key disclosures, by transaction ID
+    disclosures: Arc<RwLock<HashMap<String, Vec<DisclosedEntity>>>>,
+    patterns: Arc<RwLock<PatternStore>>,
+    policy: Arc<RwLock<CompliancePolicy>>,
+    policy_path: Option<PathBuf>,
+    geo: Option<Arc<dyn GeoProvider>>,
+    // Addresses of the peers each transaction was relayed from
+    connections: Arc<RwLock<HashMap<String, Vec<IpAddr>>>>,
+    volumes: Arc<RwLock<VolumeTracker>>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+    results: Option<Arc<RwLock<CheckStore>>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComplianceConfig {
     pub max_transaction_size: u64,
     pub min_ring_size: u32,
@@ -50,7 +87,7 @@ pub struct ComplianceConfig {
     pub high_risk_thresholds: HighRiskThresholds,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HighRiskThresholds {
     pub amount: f64,
     pub frequency: u32,
@@ -59,103 +96,536 @@ pub struct HighRiskThresholds {
 
 impl ComplianceChecker {
     pub fn new(config: ComplianceConfig) -> Self {
-        Self { config }
+        let patterns = PatternStore::new(&config.high_risk_thresholds);
+        let policy = CompliancePolicy::from_config(&config);
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            config_path: None,
+            audit: None,
+            sanctions: None,
+            disclosures: Arc::new(RwLock::new(HashMap::new())),
+            patterns: Arc::new(RwLock::new(patterns)),
+            policy: Arc::new(RwLock::new(policy)),
+            policy_path: None,
+            geo: None,
+            connections: Arc::new(RwLock::new(HashMap::new())),
+            volumes: Arc::new(RwLock::new(VolumeTracker::new())),
+            webhooks: None,
+            results: None,
+        }
+    }
+
+    pub fn with_volume_store(mut self, path: PathBuf) -> Result<Self, VolumeError> {
+        self.volumes = Arc::new(RwLock::new(VolumeTracker::open(path)?));
+        Ok(self)
+    }
+
+    pub fn with_policy_file(mut self, path: PathBuf) -> Result<Self, PolicyError> {
+        let policy = CompliancePolicy::load(&path)?;
+        self.policy = Arc::new(RwLock::new(policy));
+        self.policy_path = Some(path);
+        Ok(self)
+    }
+
+    // The file is watched by `spawn_config_reload`; without a policy file the
+    // default rules are derived from it
+    pub fn with_config_file(mut self, path: PathBuf) -> Result<Self, ConfigError> {
+        let config = ComplianceConfig::load(&path)?;
+        if self.policy_path.is_none() {
+            self.policy = Arc::new(RwLock::new(CompliancePolicy::from_config(&config)));
+        }
+        self.patterns = Arc::new(RwLock::new(PatternStore::new(&config.high_risk_thresholds)));
+        self.config = Arc::new(RwLock::new(config));
+        self.config_path = Some(path);
+        Ok(self)
+    }
+
+    pub fn with_audit_log(mut self, audit: Arc<ComplianceAuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub async fn config(&self) -> ComplianceConfig {
+        self.config.read().await.clone()
+    }
+
+    // Validates, audits and applies a new config; an invalid one leaves the current config in force
+    pub async fn update_config(&self, config: ComplianceConfig, changed_by: &str) -> Result<Vec<ConfigChange>, ConfigError> {
+        config.validate()?;
+        let mut current = self.config.write().await;
+        let changes = current.diff(&config)?;
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+
+        if let Some(audit) = &self.audit {
+            audit
+                .append(AuditEvent::ConfigChanged {
+                    changed_by: changed_by.to_string(),
+                    changes: changes.clone(),
+                })
+                .await?;
+        }
+        self.patterns.write().await.set_window(&config.high_risk_thresholds);
+        if self.policy_path.is_none() {
+            *self.policy.write().await = CompliancePolicy::from_config(&config);
+        }
+        *current = config;
+        Ok(changes)
+    }
+
+    pub async fn reload_config(&self) -> Result<Vec<ConfigChange>, ConfigError> {
+        let path = self.config_path.as_ref().ok_or(ConfigError::NoConfigFile)?;
+        let config = ComplianceConfig::load(path)?;
+        self.update_config(config, &format!("file:{}", path.display())).await
+    }
+
+    pub fn spawn_config_reload(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let Some(path) = self.config_path.clone() else {
+            return tokio::spawn(async {});
+        };
+        spawn_file_watch(path, interval, move || {
+            let checker = self.clone();
+            async move {
+                match checker.reload_config().await {
+                    Ok(changes) => tracing::info!("Compliance config reloaded: {} settings changed", changes.len()),
+                    Err(e) => tracing::error!("Compliance config reload failed, keeping previous config: {}", e),
+                }
+            }
+        })
+    }
+
+    pub async fn policy(&self) -> CompliancePolicy {
+        self.policy.read().await.clone()
+    }
+
+    // A policy that fails to parse or validate leaves the current one in force
+    pub async fn reload_policy(&self) -> Result<u32, PolicyError> {
+        let Some(path) = &self.policy_path else {
+            return Err(PolicyError::InvalidPolicy("no policy file configured".to_string()));
+        };
+        let policy = CompliancePolicy::load(path)?;
+        let version = policy.version;
+        *self.policy.write().await = policy;
+        Ok(version)
+    }
+
+    pub fn spawn_policy_reload(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let Some(path) = self.policy_path.clone() else {
+            return tokio::spawn(async {});
+        };
+        spawn_file_watch(path, interval, move || {
+            let checker = self.clone();
+            async move {
+                match checker.reload_policy().await {
+                    Ok(version) => tracing::info!("Compliance policy reloaded: version {}", version),
+                    Err(e) => tracing::error!("Compliance policy reload failed, keeping previous policy: {}", e),
+                }
+            }
+        })
+    }
+
+    pub fn with_sanctions(mut self, screener: Arc<SanctionsScreener>) -> Self {
+        self.sanctions = Some(screener);
+        self
+    }
+
+    pub fn with_geo_provider(mut self, provider: Arc<dyn GeoProvider>) -> Self {
+        self.geo = Some(provider);
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    pub fn with_check_store(mut self, results: Arc<RwLock<CheckStore>>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    pub async fn record_connection(&self, transaction_id: &str, peer: IpAddr) {
+        self.connections
+            .write()
+            .await
+            .entry(transaction_id.to_string())
+            .or_default()
+            .push(peer);
+    }
+
+    pub async fn record_disclosure(&self, transaction_id: &str, entity: DisclosedEntity) {
+        self.disclosures
+            .write()
+            .await
+            .entry(transaction_id.to_string())
+            .or_default()
+            .push(entity);
+    }
+
+    pub async fn disclosures_for(&self, transaction_id: &str) -> Vec<DisclosedEntity> {
+        self.disclosures.read().await.get(transaction_id).cloned().unwrap_or_default()
+    }
+
+    pub async fn record_disclosed_transaction(&self, tx: DisclosedTransaction) -> Result<(), VolumeError> {
+        self.volumes
+            .write()
+            .await
+            .record(&tx.entity, &tx.transaction_id, tx.amount, tx.timestamp)
+            .await?;
+        self.patterns.write().await.record(tx);
+        Ok(())
     }
 
     pub async fn check_transaction(&self, tx: &Transaction) -> TransactionCheck {
+        // Snapshot the policy so a reload mid-check can't mix rule sets
+        let policy = self.policy.read().await.clone();
+        let config = self.config.read().await.clone();
         let mut checks = Vec::new();
+        let mut factors = Vec::new();
+
+        for rule in policy.enabled_rules() {
+            let check = match &rule.condition {
+                RuleCondition::TransactionSize { max_bytes } => {
+                    self.check_transaction_size(tx, *max_bytes, rule.severity)
+                }
+                // A policy may be stricter than the governed protocol minimum, never looser
+                RuleCondition::RingSize { min } => {
+                    let min = (*min).max(config.min_ring_size);
+                    self.validate_ring_signatures(tx, min, rule.severity)
+                }
+                RuleCondition::AmountAbove { amount } => {
+                    self.check_amount_thresholds(tx, *amount, rule.severity).await
+                }
+                RuleCondition::PatternAnalysis => {
+                    self.analyze_patterns(tx, &config.high_risk_thresholds, rule.severity).await
+                }
+                RuleCondition::SanctionsList => self.screen_sanctions(tx, rule.severity).await,
+                RuleCondition::GeographicRestrictions { restricted } => {
+                    self.check_jurisdictions(tx, &config.restricted_jurisdictions, restricted, rule.severity).await
+                }
+                RuleCondition::VolumeLimit { daily, monthly } => {
+                    let daily = daily.unwrap_or(config.max_daily_volume);
+                    let monthly = monthly.unwrap_or(daily * 30.0);
+                    self.check_volume_limits(tx, daily, monthly, rule.severity).await
+                }
+            };
 
-        // Size check
-        checks.push(self.check_transaction_size(tx));
-        
-        // Ring signature validation
-        checks.push(self.validate_ring_signatures(tx));
-        
-        // Amount checks
-        checks.push(self.check_amount_thresholds(tx));
-        
-        // Pattern analysis
-        checks.push(self.analyze_patterns(tx).await);
-        
-        // Sanctions screening
-        checks.push(self.screen_sanctions(tx).await);
-
-        TransactionCheck {
+            let reason = match &check.result {
+                CheckResult::Pass => None,
+                CheckResult::Fail(reason) | CheckResult::Warning(reason) => Some(reason.clone()),
+                CheckResult::RequiresReview => Some(check.details.clone()),
+            };
+            if let Some(reason) = reason {
+                factors.push(RiskFactor {
+                    rule_id: rule.id.clone(),
+                    weight: rule.weight(),
+                    reason,
+                });
+            }
+            checks.push(check);
+        }
+
+        let result = TransactionCheck {
             transaction_id: tx.id.clone(),
             timestamp: Utc::now(),
             checks,
+            risk: RiskScore::from_factors(factors, &policy.bands),
+        };
+        if let Some(results) = &self.results {
+            if let Err(e) = results.write().await.record(tx, &result).await {
+                tracing::error!("Failed to store compliance check for {}: {}", result.transaction_id, e);
+            }
+        }
+        if let Some(webhooks) = &self.webhooks {
+            if result.checks.iter().any(|c| !matches!(c.result, CheckResult::Pass)) {
+                match serde_json::to_value(&result) {
+                    Ok(data) => webhooks.notify(WebhookEventKind::CheckFailed, data).await,
+                    Err(e) => tracing::error!("Failed to serialize check for {}: {}", result.transaction_id, e),
+                }
+            }
         }
+        result
     }
 
-    fn check_transaction_size(&self, tx: &Transaction) -> ComplianceCheck {
+    // Only the structural rules, for callers such as mempool admission that
+    // must not depend on deanonymized data; nothing is recorded or notified
+    pub async fn check_structure(&self, tx: &Transaction) -> Vec<(String, ComplianceCheck)> {
+        let policy = self.policy.read().await.clone();
+        let min_ring_size = self.config.read().await.min_ring_size;
+
+        policy
+            .enabled_rules()
+            .filter_map(|rule| {
+                let check = match &rule.condition {
+                    RuleCondition::TransactionSize { max_bytes } => {
+                        self.check_transaction_size(tx, *max_bytes, rule.severity)
+                    }
+                    RuleCondition::RingSize { min } => {
+                        self.validate_ring_signatures(tx, (*min).max(min_ring_size), rule.severity)
+                    }
+                    _ => return None,
+                };
+                Some((rule.id.clone(), check))
+            })
+            .collect()
+    }
+
+    fn check_transaction_size(&self, tx: &Transaction, max_bytes: u64, severity: Severity) -> ComplianceCheck {
         let size = tx.serialized_size();
-        if size > self.config.max_transaction_size {
+        if size > max_bytes {
             ComplianceCheck {
                 check_type: ComplianceCheckType::TransactionSize,
-                result: CheckResult::Fail(format!("Size {} exceeds maximum {}", 
-                    size, self.config.max_transaction_size)),
+                result: severity.result(format!("Size {} exceeds maximum {}", 
+                    size, max_bytes)),
                 details: "Transaction size exceeds regulatory limits".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
             }
         } else {
             ComplianceCheck {
                 check_type: ComplianceCheckType::TransactionSize,
                 result: CheckResult::Pass,
                 details: "Transaction size within limits".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
             }
         }
     }
 
-    fn validate_ring_signatures(&self, tx: &Transaction) -> ComplianceCheck {
-        if tx.ring_size() < self.config.min_ring_size {
+    fn validate_ring_signatures(&self, tx: &Transaction, min_ring_size: u32, severity: Severity) -> ComplianceCheck {
+        if tx.ring_size() < min_ring_size {
             ComplianceCheck {
                 check_type: ComplianceCheckType::RingSignatureValidation,
-                result: CheckResult::Fail(format!("Ring size {} below minimum {}", 
-                    tx.ring_size(), self.config.min_ring_size)),
+                result: severity.result(format!("Ring size {} below minimum {}", 
+                    tx.ring_size(), min_ring_size)),
                 details: "Insufficient ring size for privacy requirements".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
             }
         } else {
             ComplianceCheck {
                 check_type: ComplianceCheckType::RingSignatureValidation,
                 result: CheckResult::Pass,
                 details: "Ring signature requirements met".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
             }
         }
     }
 
-    async fn check_amount_thresholds(&self, tx: &Transaction) -> ComplianceCheck {
+    async fn check_amount_thresholds(&self, tx: &Transaction, threshold: f64, severity: Severity) -> ComplianceCheck {
         let amount = tx.amount();
-        if amount > self.config.high_risk_thresholds.amount {
+        if amount > threshold {
             ComplianceCheck {
                 check_type: ComplianceCheckType::AmountRange,
-                result: CheckResult::Warning(format!("Large transaction amount: {}", amount)),
+                result: severity.result(format!("Large transaction amount: {}", amount)),
                 details: "Transaction requires enhanced due diligence".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
             }
         } else {
             ComplianceCheck {
                 check_type: ComplianceCheckType::AmountRange,
                 result: CheckResult::Pass,
                 details: "Transaction amount within normal range".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
+            }
+        }
+    }
+
+    async fn analyze_patterns(&self, tx: &Transaction, thresholds: &HighRiskThresholds, severity: Severity) -> ComplianceCheck {
+        let now = Utc::now();
+
+        let mut store = self.patterns.write().await;
+        store.prune(now);
+
+        // Only parties whose activity was disclosed have a history to analyze
+        let entities = store.entities_for(&tx.id);
+        let findings: Vec<PatternFinding> = entities
+            .iter()
+            .flat_map(|entity| store.analyze(entity, thresholds, now))
+            .collect();
+
+        if findings.is_empty() {
+            ComplianceCheck {
+                check_type: ComplianceCheckType::PatternAnalysis,
+                result: CheckResult::Pass,
+                details: format!(
+                    "No suspicious patterns across {} disclosed parties in the last {} hours",
+                    entities.len(),
+                    thresholds.pattern_window_hours,
+                ),
+                matches: Vec::new(),
+                findings,
+            }
+        } else {
+            let details = findings
+                .iter()
+                .map(|f| format!("{:?} by {}: {}", f.pattern, f.entity, f.explanation))
+                .collect::<Vec<_>>()
+                .join("; ");
+            ComplianceCheck {
+                check_type: ComplianceCheckType::PatternAnalysis,
+                result: severity.result(details.clone()),
+                details,
+                matches: Vec::new(),
+                findings,
+            }
+        }
+    }
+
+    async fn check_jurisdictions(
+        &self,
+        tx: &Transaction,
+        configured: &[String],
+        extra_restricted: &[String],
+        severity: Severity,
+    ) -> ComplianceCheck {
+        let restricted: HashSet<String> = configured
+            .iter()
+            .chain(extra_restricted)
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        // (country, where it came from)
+        let mut located: Vec<(String, String)> = Vec::new();
+        let entities = self.disclosures.read().await.get(&tx.id).cloned().unwrap_or_default();
+        for entity in &entities {
+            if let Some(country) = &entity.country {
+                located.push((country.to_ascii_uppercase(), "declared counterparty country".to_string()));
+            }
+        }
+
+        let peers = self.connections.read().await.get(&tx.id).cloned().unwrap_or_default();
+        let lookups = entities.iter().flat_map(|e| &e.ip_addresses).map(|ip| (ip, "counterparty"))
+            .chain(peers.iter().map(|ip| (ip, "relaying peer")));
+        let mut unresolved = 0;
+        for (ip, role) in lookups {
+            match self.geo.as_ref().and_then(|geo| geo.country(*ip).map(|c| (c, geo.name()))) {
+                Some((country, source)) => located.push((country, format!("{} {} via {}", role, ip, source))),
+                None => unresolved += 1,
+            }
+        }
+
+        let hits: Vec<String> = located
+            .iter()
+            .filter(|(country, _)| restricted.contains(country))
+            .map(|(country, source)| format!("{} ({})", country, source))
+            .collect();
+
+        if !hits.is_empty() {
+            ComplianceCheck {
+                check_type: ComplianceCheckType::GeographicRestrictions,
+                result: severity.result(format!("Restricted jurisdiction: {}", hits.join(", "))),
+                details: "Transaction involves a restricted jurisdiction".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
+            }
+        } else if unresolved > 0 && self.geo.is_none() {
+            ComplianceCheck {
+                check_type: ComplianceCheckType::GeographicRestrictions,
+                result: CheckResult::Warning("No geolocation provider configured".to_string()),
+                details: format!("{} addresses could not be located", unresolved),
+                matches: Vec::new(),
+                findings: Vec::new(),
+            }
+        } else {
+            ComplianceCheck {
+                check_type: ComplianceCheckType::GeographicRestrictions,
+                result: CheckResult::Pass,
+                details: format!(
+                    "{} locations checked, {} addresses unresolved, none restricted",
+                    located.len(),
+                    unresolved,
+                ),
+                matches: Vec::new(),
+                findings: Vec::new(),
             }
         }
     }
 
-    async fn analyze_patterns(&self, tx: &Transaction) -> ComplianceCheck {
-        // Implementation for pattern analysis
-        // This would look at historical data and identify suspicious patterns
-        ComplianceCheck {
-            check_type: ComplianceCheckType::PatternAnalysis,
-            result: CheckResult::Pass,
-            details: "No suspicious patterns detected".to_string(),
+    async fn check_volume_limits(&self, tx: &Transaction, daily_limit: f64, monthly_limit: f64, severity: Severity) -> ComplianceCheck {
+        let volumes = self.volumes.read().await;
+        let now = Utc::now();
+        let entities = volumes.entities_for(&tx.id);
+
+        let mut exceeded = Vec::new();
+        for entity in &entities {
+            let volume = volumes.volume(entity, now);
+            if volume.daily > daily_limit {
+                exceeded.push(format!("{} 24h volume {:.2} exceeds {:.2}", entity, volume.daily, daily_limit));
+            }
+            if volume.monthly > monthly_limit {
+                exceeded.push(format!("{} 30d volume {:.2} exceeds {:.2}", entity, volume.monthly, monthly_limit));
+            }
+        }
+
+        if exceeded.is_empty() {
+            ComplianceCheck {
+                check_type: ComplianceCheckType::VolumeLimit,
+                result: CheckResult::Pass,
+                details: format!("{} disclosed parties within volume limits", entities.len()),
+                matches: Vec::new(),
+                findings: Vec::new(),
+            }
+        } else {
+            ComplianceCheck {
+                check_type: ComplianceCheckType::VolumeLimit,
+                result: severity.result(exceeded.join("; ")),
+                details: "Disclosed party exceeds its volume limit".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
+            }
         }
     }
 
-    async fn screen_sanctions(&self, tx: &Transaction) -> ComplianceCheck {
-        // Implementation for sanctions screening
-        // This would check against known sanctions lists
-        ComplianceCheck {
-            check_type: ComplianceCheckType::SanctionsList,
-            result: CheckResult::Pass,
-            details: "No sanctions list matches found".to_string(),
+    async fn screen_sanctions(&self, tx: &Transaction, severity: Severity) -> ComplianceCheck {
+        let Some(screener) = &self.sanctions else {
+            return ComplianceCheck {
+                check_type: ComplianceCheckType::SanctionsList,
+                result: CheckResult::Warning("No sanctions providers configured".to_string()),
+                details: "Sanctions screening unavailable".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
+            };
+        };
+        if screener.last_refresh().await.is_none() {
+            return ComplianceCheck {
+                check_type: ComplianceCheckType::SanctionsList,
+                result: CheckResult::RequiresReview,
+                details: "Sanctions lists not loaded yet".to_string(),
+                matches: Vec::new(),
+                findings: Vec::new(),
+            };
+        }
+
+        // Shielded transactions only expose counterparties that were disclosed via view keys
+        let entities = self.disclosures.read().await.get(&tx.id).cloned().unwrap_or_default();
+        let matches = screener.screen(&entities).await;
+
+        if matches.is_empty() {
+            ComplianceCheck {
+                check_type: ComplianceCheckType::SanctionsList,
+                result: CheckResult::Pass,
+                details: format!("No sanctions list matches across {} disclosed parties", entities.len()),
+                matches,
+                findings: Vec::new(),
+            }
+        } else {
+            let summary = matches
+                .iter()
+                .map(|m| format!("{} {} ({})", m.source, m.uid, m.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ComplianceCheck {
+                check_type: ComplianceCheckType::SanctionsList,
+                result: severity.result(format!("Sanctions match: {}", summary)),
+                details: "Disclosed counterparty appears on a sanctions list".to_string(),
+                matches,
+                findings: Vec::new(),
+            }
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+use idia_core::config::IdiaConfig;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::audit::AuditError;
+use super::checks::ComplianceConfig;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported config file format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Invalid {field}: {reason}")]
+    Invalid { field: String, reason: String },
+    #[error("No config file configured")]
+    NoConfigFile,
+    #[error("Audit log error: {0}")]
+    Audit(#[from] AuditError),
+    #[error("Config error: {0}")]
+    Layered(#[from] idia_core::config::ConfigError),
+}
+
+// One changed setting, addressed by its dotted path, e.g. `high_risk_thresholds.amount`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigChange {
+    pub field: String,
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+// Parses a `.toml` or `.json` file; any other extension goes to `unsupported`
+pub(crate) fn load_by_extension<T, E>(path: &Path, unsupported: impl FnOnce(String) -> E) -> Result<T, E>
+where
+    T: DeserializeOwned,
+    E: From<std::io::Error> + From<toml::de::Error> + From<serde_json::Error>,
+{
+    let contents = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => Ok(toml::from_str(&contents)?),
+        Some("json") => Ok(serde_json::from_str(&contents)?),
+        other => Err(unsupported(other.unwrap_or("").to_string())),
+    }
+}
+
+// Polls `path` every `interval` and runs `on_change` whenever its mtime moves
+pub(crate) fn spawn_file_watch<F, Fut>(path: PathBuf, interval: Duration, mut on_change: F) -> tokio::task::JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    tokio::spawn(async move {
+        let modified_at = |path: &Path| -> Option<SystemTime> { std::fs::metadata(path).and_then(|m| m.modified()).ok() };
+
+        let mut last_modified = modified_at(&path);
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            let modified = modified_at(&path);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+            on_change().await;
+        }
+    })
+}
+
+impl ComplianceConfig {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let config: Self = load_by_extension(path, ConfigError::UnsupportedFormat)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // The `[compliance]` section of the node's layered config, if it has one
+    pub fn from_config(config: &IdiaConfig) -> Result<Option<Self>, ConfigError> {
+        let Some(section) = config.section::<Self>("compliance")? else {
+            return Ok(None);
+        };
+        section.validate()?;
+        Ok(Some(section))
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |field: &str, reason: &str| {
+            Err(ConfigError::Invalid {
+                field: field.to_string(),
+                reason: reason.to_string(),
+            })
+        };
+
+        if self.max_transaction_size == 0 {
+            return invalid("max_transaction_size", "must be positive");
+        }
+        if self.min_ring_size < 2 {
+            return invalid("min_ring_size", "must be at least 2");
+        }
+        if !self.max_daily_volume.is_finite() || self.max_daily_volume <= 0.0 {
+            return invalid("max_daily_volume", "must be a positive amount");
+        }
+        if let Some(code) = self
+            .restricted_jurisdictions
+            .iter()
+            .find(|c| c.len() != 2 || !c.chars().all(|ch| ch.is_ascii_alphabetic()))
+        {
+            return invalid("restricted_jurisdictions", &format!("{} is not an ISO 3166 alpha-2 code", code));
+        }
+
+        let thresholds = &self.high_risk_thresholds;
+        if !thresholds.amount.is_finite() || thresholds.amount <= 0.0 {
+            return invalid("high_risk_thresholds.amount", "must be a positive amount");
+        }
+        if thresholds.frequency == 0 {
+            return invalid("high_risk_thresholds.frequency", "must be positive");
+        }
+        if thresholds.pattern_window_hours == 0 {
+            return invalid("high_risk_thresholds.pattern_window_hours", "must be positive");
+        }
+        Ok(())
+    }
+
+    pub fn diff(&self, other: &Self) -> Result<Vec<ConfigChange>, ConfigError> {
+        let mut changes = Vec::new();
+        diff_values("", &serde_json::to_value(self)?, &serde_json::to_value(other)?, &mut changes);
+        Ok(changes)
+    }
+}
+
+fn diff_values(path: &str, from: &serde_json::Value, to: &serde_json::Value, changes: &mut Vec<ConfigChange>) {
+    match (from, to) {
+        (serde_json::Value::Object(a), serde_json::Value::Object(b)) => {
+            for (key, value) in a {
+                let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                diff_values(&field, value, b.get(key).unwrap_or(&serde_json::Value::Null), changes);
+            }
+        }
+        _ if from != to => changes.push(ConfigChange {
+            field: path.to_string(),
+            from: from.clone(),
+            to: to.clone(),
+        }),
+        _ => {}
+    }
+}
@@ -0,0 +1,603 @@
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::Scalar;
+use idia_core::ScopedViewKey;
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::audit::{AuditError, AuditEvent, ComplianceAuditLog};
+use super::authorization::{
+    scope_tx_pubkeys, AuthorityRegistry, AuthorizationError, TransactionSource, ViewKeyAuthorization,
+};
+use super::sealing::{SealedBox, SealingError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum EscrowError {
+    #[error("Invalid threshold {threshold} for {custodians} custodians")]
+    InvalidThreshold { threshold: u32, custodians: usize },
+    #[error("Duplicate custodian {0}")]
+    DuplicateCustodian(String),
+    #[error("Invalid public key for custodian {0}")]
+    InvalidKey(String),
+    #[error("Malformed escrow: {0}")]
+    Malformed(String),
+    #[error("Share {0} does not match the escrow commitments")]
+    InvalidShare(u32),
+    #[error("Derivation from share {0} failed verification")]
+    InvalidDerivation(u32),
+    #[error("Need {needed} shares, got {got}")]
+    NotEnoughShares { needed: u32, got: usize },
+    #[error("Reconstructed key does not match the escrowed view key")]
+    KeyMismatch,
+    #[error("No share for custodian {0}")]
+    UnknownCustodian(String),
+    #[error("Authorization failed: {0}")]
+    Authorization(#[from] AuthorizationError),
+    #[error("Sealing error: {0}")]
+    Sealing(#[from] SealingError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Audit log error: {0}")]
+    Audit(#[from] AuditError),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Custodian {
+    pub id: String,
+    pub public_key: [u8; 32],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedShare {
+    // Evaluation point of the share, starting at 1
+    pub index: u32,
+    pub custodian: String,
+    pub sealed: SealedBox,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct KeyShare {
+    pub index: u32,
+    pub value: Scalar,
+}
+
+// A wallet's view key split t-of-n with Shamir sharing over the Ristretto
+// scalar field. Each share is sealed to its custodian, and Feldman commitments
+// to the polynomial let anyone check a share or derivation against the escrow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowedViewKey {
+    pub view_public: [u8; 32],
+    pub threshold: u32,
+    pub commitments: Vec<[u8; 32]>,
+    pub shares: Vec<EncryptedShare>,
+}
+
+impl EscrowedViewKey {
+    const SHARE_DOMAIN: &'static [u8] = b"idia-view-key-escrow-v1";
+
+    pub fn split(view_private: &Scalar, custodians: &[Custodian], threshold: u32) -> Result<Self, EscrowError> {
+        if threshold < 2 || threshold as usize > custodians.len() {
+            return Err(EscrowError::InvalidThreshold {
+                threshold,
+                custodians: custodians.len(),
+            });
+        }
+        let mut seen = HashSet::new();
+        for custodian in custodians {
+            if !seen.insert(custodian.id.as_str()) {
+                return Err(EscrowError::DuplicateCustodian(custodian.id.clone()));
+            }
+        }
+
+        let mut rng = OsRng;
+        let coefficients: Vec<Scalar> = std::iter::once(*view_private)
+            .chain((1..threshold).map(|_| Scalar::random(&mut rng)))
+            .collect();
+
+        let mut shares = Vec::with_capacity(custodians.len());
+        for (position, custodian) in custodians.iter().enumerate() {
+            let index = position as u32 + 1;
+            let recipient = decompress(&custodian.public_key)
+                .ok_or_else(|| EscrowError::InvalidKey(custodian.id.clone()))?;
+            let value = evaluate(&coefficients, index);
+            shares.push(EncryptedShare {
+                index,
+                custodian: custodian.id.clone(),
+                sealed: SealedBox::seal(value.as_bytes(), Self::SHARE_DOMAIN, &recipient)?,
+            });
+        }
+
+        Ok(Self {
+            view_public: (RISTRETTO_BASEPOINT_POINT * view_private).compress().to_bytes(),
+            threshold,
+            commitments: coefficients
+                .iter()
+                .map(|c| (RISTRETTO_BASEPOINT_POINT * c).compress().to_bytes())
+                .collect(),
+            shares,
+        })
+    }
+
+    // Escrows are looked up by the view public key they protect
+    pub fn id(&self) -> String {
+        hex::encode(self.view_public)
+    }
+
+    pub fn validate(&self) -> Result<(), EscrowError> {
+        if self.threshold < 2 || self.threshold as usize > self.shares.len() {
+            return Err(EscrowError::InvalidThreshold {
+                threshold: self.threshold,
+                custodians: self.shares.len(),
+            });
+        }
+        if self.commitments.len() != self.threshold as usize {
+            return Err(EscrowError::Malformed("one commitment per coefficient required".to_string()));
+        }
+        let commitments = self.commitment_points()?;
+        if decompress(&self.view_public) != Some(commitments[0]) {
+            return Err(EscrowError::Malformed("constant commitment is not the view public key".to_string()));
+        }
+        let indices: HashSet<u32> = self.shares.iter().map(|s| s.index).collect();
+        if indices.len() != self.shares.len() || indices.contains(&0) {
+            return Err(EscrowError::Malformed("share indices must be distinct and non-zero".to_string()));
+        }
+        Ok(())
+    }
+
+    // The custodian's own check on receiving a share
+    pub fn open_share(&self, custodian: &str, secret: &Scalar) -> Result<KeyShare, EscrowError> {
+        let encrypted = self
+            .shares
+            .iter()
+            .find(|s| s.custodian == custodian)
+            .ok_or_else(|| EscrowError::UnknownCustodian(custodian.to_string()))?;
+        let bytes: [u8; 32] = encrypted
+            .sealed
+            .open(Self::SHARE_DOMAIN, secret)?
+            .try_into()
+            .map_err(|_| EscrowError::InvalidShare(encrypted.index))?;
+        let value = Option::from(Scalar::from_canonical_bytes(bytes)).ok_or(EscrowError::InvalidShare(encrypted.index))?;
+
+        let share = KeyShare {
+            index: encrypted.index,
+            value,
+        };
+        if RISTRETTO_BASEPOINT_POINT * share.value != self.share_public(share.index)? {
+            return Err(EscrowError::InvalidShare(share.index));
+        }
+        Ok(share)
+    }
+
+    // Full recovery of the view key, e.g. when the wallet itself is lost.
+    // Regulatory access goes through `combine` instead and never rebuilds it.
+    pub fn reconstruct(&self, shares: &[KeyShare]) -> Result<Scalar, EscrowError> {
+        let shares = self.select(shares, |s| s.index)?;
+        for share in &shares {
+            if RISTRETTO_BASEPOINT_POINT * share.value != self.share_public(share.index)? {
+                return Err(EscrowError::InvalidShare(share.index));
+            }
+        }
+
+        let indices: Vec<u32> = shares.iter().map(|s| s.index).collect();
+        let view_private: Scalar = shares.iter().map(|s| lagrange_at_zero(s.index, &indices) * s.value).sum();
+        if Some(RISTRETTO_BASEPOINT_POINT * view_private) != decompress(&self.view_public) {
+            return Err(EscrowError::KeyMismatch);
+        }
+        Ok(view_private)
+    }
+
+    // Combines t custodians' derivations into the shared secrets view_private * R
+    // for each transaction public key, interpolating in the group
+    pub fn combine(
+        &self,
+        tx_pubkeys: &[RistrettoPoint],
+        derivations: &[PartialDerivation],
+    ) -> Result<ScopedViewKey, EscrowError> {
+        let derivations = self.select(derivations, |d| d.index)?;
+        for derivation in &derivations {
+            derivation.verify(&self.share_public(derivation.index)?, tx_pubkeys)?;
+        }
+
+        let indices: Vec<u32> = derivations.iter().map(|d| d.index).collect();
+        let mut shared_secrets = Vec::with_capacity(tx_pubkeys.len());
+        for (position, tx_pubkey) in tx_pubkeys.iter().enumerate() {
+            let mut secret = RistrettoPoint::default();
+            for derivation in &derivations {
+                let point = decompress(&derivation.points[position]).ok_or(EscrowError::InvalidDerivation(derivation.index))?;
+                secret += lagrange_at_zero(derivation.index, &indices) * point;
+            }
+            shared_secrets.push((*tx_pubkey, secret));
+        }
+        Ok(ScopedViewKey { shared_secrets })
+    }
+
+    fn commitment_points(&self) -> Result<Vec<RistrettoPoint>, EscrowError> {
+        self.commitments
+            .iter()
+            .map(|c| decompress(c).ok_or_else(|| EscrowError::Malformed("invalid commitment".to_string())))
+            .collect()
+    }
+
+    // share_index * G, from the commitments: sum of C_j * index^j
+    fn share_public(&self, index: u32) -> Result<RistrettoPoint, EscrowError> {
+        let x = Scalar::from(index as u64);
+        let mut power = Scalar::ONE;
+        let mut public = RistrettoPoint::default();
+        for commitment in self.commitment_points()? {
+            public += commitment * power;
+            power *= x;
+        }
+        Ok(public)
+    }
+
+    // The first `threshold` items with distinct indices that belong to this escrow
+    fn select<'a, T>(&self, items: &'a [T], index: impl Fn(&T) -> u32) -> Result<Vec<&'a T>, EscrowError> {
+        let known: HashSet<u32> = self.shares.iter().map(|s| s.index).collect();
+        let mut seen = HashSet::new();
+        let selected: Vec<&T> = items
+            .iter()
+            .filter(|item| known.contains(&index(item)) && seen.insert(index(item)))
+            .take(self.threshold as usize)
+            .collect();
+        if selected.len() < self.threshold as usize {
+            return Err(EscrowError::NotEnoughShares {
+                needed: self.threshold,
+                got: selected.len(),
+            });
+        }
+        Ok(selected)
+    }
+}
+
+// share * R for each transaction public key, with a proof per point that the
+// same share was used as in the escrow commitments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialDerivation {
+    pub index: u32,
+    pub points: Vec<[u8; 32]>,
+    pub proofs: Vec<DleqProof>,
+}
+
+impl PartialDerivation {
+    pub fn derive(share: &KeyShare, tx_pubkeys: &[RistrettoPoint]) -> Self {
+        let share_public = RISTRETTO_BASEPOINT_POINT * share.value;
+        let (points, proofs) = tx_pubkeys
+            .iter()
+            .map(|tx_pubkey| {
+                let point = share.value * tx_pubkey;
+                let proof = DleqProof::prove(&share.value, &share_public, tx_pubkey, &point);
+                (point.compress().to_bytes(), proof)
+            })
+            .unzip();
+        Self {
+            index: share.index,
+            points,
+            proofs,
+        }
+    }
+
+    fn verify(&self, share_public: &RistrettoPoint, tx_pubkeys: &[RistrettoPoint]) -> Result<(), EscrowError> {
+        let invalid = EscrowError::InvalidDerivation(self.index);
+        if self.points.len() != tx_pubkeys.len() || self.proofs.len() != tx_pubkeys.len() {
+            return Err(invalid);
+        }
+        for ((point, proof), tx_pubkey) in self.points.iter().zip(&self.proofs).zip(tx_pubkeys) {
+            let point = decompress(point).ok_or(EscrowError::InvalidDerivation(self.index))?;
+            if !proof.verify(share_public, tx_pubkey, &point) {
+                return Err(invalid);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Chaum-Pedersen proof that log_G(public) == log_base(derived)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DleqProof {
+    pub challenge: [u8; 32],
+    pub response: [u8; 32],
+}
+
+impl DleqProof {
+    fn prove(secret: &Scalar, public: &RistrettoPoint, base: &RistrettoPoint, derived: &RistrettoPoint) -> Self {
+        let k = Scalar::random(&mut OsRng);
+        let c = Self::challenge(public, base, derived, &(RISTRETTO_BASEPOINT_POINT * k), &(k * base));
+        Self {
+            challenge: c.to_bytes(),
+            response: (k - c * secret).to_bytes(),
+        }
+    }
+
+    fn verify(&self, public: &RistrettoPoint, base: &RistrettoPoint, derived: &RistrettoPoint) -> bool {
+        let (Some(c), Some(z)) = (
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(self.challenge)),
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(self.response)),
+        ) else {
+            return false;
+        };
+        let nonce_g = RISTRETTO_BASEPOINT_POINT * z + public * c;
+        let nonce_base = base * z + derived * c;
+        Self::challenge(public, base, derived, &nonce_g, &nonce_base) == c
+    }
+
+    fn challenge(
+        public: &RistrettoPoint,
+        base: &RistrettoPoint,
+        derived: &RistrettoPoint,
+        nonce_g: &RistrettoPoint,
+        nonce_base: &RistrettoPoint,
+    ) -> Scalar {
+        let mut hasher = Sha512::new();
+        hasher.update(b"idia-escrow-dleq-v1");
+        for point in [public, base, derived, nonce_g, nonce_base] {
+            hasher.update(point.compress().as_bytes());
+        }
+        Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+    }
+}
+
+// Runs at each custodian: checks the regulator's authorization independently
+// before contributing its share to a scoped derivation
+pub struct EscrowCustodian {
+    id: String,
+    secret: Scalar,
+    registry: Arc<RwLock<AuthorityRegistry>>,
+    transactions: Arc<dyn TransactionSource>,
+    audit: Arc<ComplianceAuditLog>,
+}
+
+impl EscrowCustodian {
+    pub fn new(
+        id: String,
+        secret: Scalar,
+        registry: Arc<RwLock<AuthorityRegistry>>,
+        transactions: Arc<dyn TransactionSource>,
+        audit: Arc<ComplianceAuditLog>,
+    ) -> Self {
+        Self {
+            id,
+            secret,
+            registry,
+            transactions,
+            audit,
+        }
+    }
+
+    pub async fn derive(
+        &self,
+        escrow: &EscrowedViewKey,
+        authorization: &ViewKeyAuthorization,
+    ) -> Result<PartialDerivation, EscrowError> {
+        let result = async {
+            authorization.verify(&*self.registry.read().await, chrono::Utc::now())?;
+            let (_, tx_pubkeys) = scope_tx_pubkeys(&*self.transactions, authorization).await?;
+            let share = escrow.open_share(&self.id, &self.secret)?;
+            Ok::<_, EscrowError>(PartialDerivation::derive(&share, &tx_pubkeys))
+        }
+        .await;
+
+        self.audit
+            .append(AuditEvent::EscrowShareUsed {
+                escrow_id: escrow.id(),
+                custodian: self.id.clone(),
+                transaction_id: authorization.transaction_id.clone(),
+                authority: authorization.authority.clone(),
+                granted: result.is_ok(),
+            })
+            .await?;
+        result
+    }
+}
+
+// Escrows deposited with this node, persisted as a JSON array
+pub struct EscrowStore {
+    path: PathBuf,
+    escrows: BTreeMap<String, EscrowedViewKey>,
+}
+
+impl EscrowStore {
+    pub async fn open(path: PathBuf) -> Result<Self, EscrowError> {
+        let escrows: Vec<EscrowedViewKey> = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            escrows: escrows.into_iter().map(|e| (e.id(), e)).collect(),
+        })
+    }
+
+    pub async fn deposit(&mut self, escrow: EscrowedViewKey) -> Result<String, EscrowError> {
+        escrow.validate()?;
+        let id = escrow.id();
+        self.escrows.insert(id.clone(), escrow);
+        self.save().await?;
+        Ok(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&EscrowedViewKey> {
+        self.escrows.get(id)
+    }
+
+    async fn save(&self) -> Result<(), EscrowError> {
+        let escrows: Vec<&EscrowedViewKey> = self.escrows.values().collect();
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(&escrows)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+// Coefficients lowest degree first
+fn evaluate(coefficients: &[Scalar], index: u32) -> Scalar {
+    let x = Scalar::from(index as u64);
+    coefficients.iter().rev().fold(Scalar::ZERO, |acc, c| acc * x + c)
+}
+
+fn lagrange_at_zero(index: u32, indices: &[u32]) -> Scalar {
+    let x_i = Scalar::from(index as u64);
+    indices
+        .iter()
+        .filter(|&&j| j != index)
+        .fold(Scalar::ONE, |acc, &j| {
+            let x_j = Scalar::from(j as u64);
+            acc * x_j * (x_j - x_i).invert()
+        })
+}
+
+fn decompress(bytes: &[u8; 32]) -> Option<RistrettoPoint> {
+    CompressedRistretto(*bytes).decompress()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixture {
+        view_private: Scalar,
+        secrets: Vec<Scalar>,
+        escrow: EscrowedViewKey,
+    }
+
+    // 3-of-5 escrow
+    fn fixture() -> Fixture {
+        let view_private = Scalar::random(&mut OsRng);
+        let secrets: Vec<Scalar> = (0..5).map(|_| Scalar::random(&mut OsRng)).collect();
+        let custodians: Vec<Custodian> = secrets
+            .iter()
+            .enumerate()
+            .map(|(i, secret)| Custodian {
+                id: format!("custodian-{}", i),
+                public_key: (RISTRETTO_BASEPOINT_POINT * secret).compress().to_bytes(),
+            })
+            .collect();
+        let escrow = EscrowedViewKey::split(&view_private, &custodians, 3).unwrap();
+        Fixture {
+            view_private,
+            secrets,
+            escrow,
+        }
+    }
+
+    fn open(fixture: &Fixture, custodians: &[usize]) -> Vec<KeyShare> {
+        custodians
+            .iter()
+            .map(|&i| fixture.escrow.open_share(&format!("custodian-{}", i), &fixture.secrets[i]).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_and_reconstruct() {
+        let fixture = fixture();
+        fixture.escrow.validate().unwrap();
+        assert_eq!(fixture.escrow.reconstruct(&open(&fixture, &[0, 2, 4])).unwrap(), fixture.view_private);
+        assert_eq!(fixture.escrow.reconstruct(&open(&fixture, &[3, 1, 2])).unwrap(), fixture.view_private);
+
+        assert!(matches!(
+            fixture.escrow.reconstruct(&open(&fixture, &[0, 1])),
+            Err(EscrowError::NotEnoughShares { needed: 3, got: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_combine_derives_shared_secrets() {
+        let fixture = fixture();
+        let tx_pubkeys: Vec<RistrettoPoint> =
+            (0..3).map(|_| RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng)).collect();
+        let derivations: Vec<PartialDerivation> = open(&fixture, &[1, 3, 4])
+            .iter()
+            .map(|share| PartialDerivation::derive(share, &tx_pubkeys))
+            .collect();
+
+        let scoped = fixture.escrow.combine(&tx_pubkeys, &derivations).unwrap();
+        for ((tx_pubkey, secret), expected) in scoped.shared_secrets.iter().zip(&tx_pubkeys) {
+            assert_eq!(tx_pubkey, expected);
+            assert_eq!(*secret, fixture.view_private * expected);
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_shares_and_derivations() {
+        let fixture = fixture();
+        assert!(fixture.escrow.open_share("custodian-0", &fixture.secrets[1]).is_err());
+        assert!(matches!(
+            fixture.escrow.open_share("nobody", &fixture.secrets[0]),
+            Err(EscrowError::UnknownCustodian(_))
+        ));
+
+        let mut shares = open(&fixture, &[0, 1, 2]);
+        shares[1].value += Scalar::ONE;
+        assert!(matches!(fixture.escrow.reconstruct(&shares), Err(EscrowError::InvalidShare(2))));
+
+        let tx_pubkeys = vec![RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng)];
+        let mut derivations: Vec<PartialDerivation> = open(&fixture, &[0, 1, 2])
+            .iter()
+            .map(|share| PartialDerivation::derive(share, &tx_pubkeys))
+            .collect();
+        derivations[0].points[0] = (RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng)).compress().to_bytes();
+        assert!(matches!(
+            fixture.escrow.combine(&tx_pubkeys, &derivations),
+            Err(EscrowError::InvalidDerivation(1))
+        ));
+
+        // A derivation for other transactions does not pass for these
+        let other = vec![RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng)];
+        let derivations: Vec<PartialDerivation> = open(&fixture, &[0, 1, 2])
+            .iter()
+            .map(|share| PartialDerivation::derive(share, &other))
+            .collect();
+        assert!(fixture.escrow.combine(&tx_pubkeys, &derivations).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_malformed_escrow() {
+        let fixture = fixture();
+        let custodian = Custodian {
+            id: "a".to_string(),
+            public_key: RISTRETTO_BASEPOINT_POINT.compress().to_bytes(),
+        };
+        assert!(matches!(
+            EscrowedViewKey::split(&fixture.view_private, &[custodian.clone(), custodian.clone()], 2),
+            Err(EscrowError::DuplicateCustodian(_))
+        ));
+        assert!(matches!(
+            EscrowedViewKey::split(&fixture.view_private, &[custodian], 2),
+            Err(EscrowError::InvalidThreshold { .. })
+        ));
+
+        let mut tampered = fixture.escrow.clone();
+        tampered.view_public = (RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng)).compress().to_bytes();
+        assert!(matches!(tampered.validate(), Err(EscrowError::Malformed(_))));
+
+        let mut tampered = fixture.escrow.clone();
+        tampered.shares[1].index = 1;
+        assert!(matches!(tampered.validate(), Err(EscrowError::Malformed(_))));
+
+        let mut tampered = fixture.escrow;
+        tampered.commitments.pop();
+        assert!(matches!(tampered.validate(), Err(EscrowError::Malformed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_store_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("escrows.json");
+        let fixture = fixture();
+
+        let mut store = EscrowStore::open(path.clone()).await.unwrap();
+        let id = store.deposit(fixture.escrow.clone()).await.unwrap();
+
+        let store = EscrowStore::open(path).await.unwrap();
+        let escrow = store.get(&id).unwrap();
+        assert_eq!(escrow.commitments, fixture.escrow.commitments);
+        assert_eq!(escrow.reconstruct(&open(&fixture, &[0, 1, 2])).unwrap(), fixture.view_private);
+    }
+}
@@ -0,0 +1,130 @@
+use std::net::IpAddr;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GeoError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Malformed CIDR list at line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+    #[error("GeoIP database error: {0}")]
+    Database(#[from] maxminddb::MaxMindDBError),
+}
+
+// Resolves an address to an ISO 3166-1 alpha-2 country code
+pub trait GeoProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn country(&self, ip: IpAddr) -> Option<String>;
+}
+
+// Reads a MaxMind GeoIP2/GeoLite2 Country database
+pub struct MaxMindProvider {
+    reader: maxminddb::Reader<Vec<u8>>,
+}
+
+impl MaxMindProvider {
+    pub fn open(path: &Path) -> Result<Self, GeoError> {
+        Ok(Self {
+            reader: maxminddb::Reader::open_readfile(path)?,
+        })
+    }
+}
+
+impl GeoProvider for MaxMindProvider {
+    fn name(&self) -> &str {
+        "MaxMind"
+    }
+
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        let record: maxminddb::geoip2::Country = self.reader.lookup(ip).ok()?;
+        record.country?.iso_code.map(str::to_string)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CidrBlock {
+    network: IpAddr,
+    prefix: u8,
+    country: String,
+}
+
+impl CidrBlock {
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(u32::from(network) as u128, u32::from(*ip) as u128, self.prefix, 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(network), u128::from(*ip), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(network: u128, ip: u128, prefix: u8, bits: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = (bits - prefix) as u32;
+    (network >> shift) == (ip >> shift)
+}
+
+// Static `cidr,country` lists, e.g. "203.0.113.0/24,KP". Blank lines and
+// lines starting with `#` are ignored; the most specific block wins.
+pub struct StaticCidrProvider {
+    blocks: Vec<CidrBlock>,
+}
+
+impl StaticCidrProvider {
+    pub fn load(path: &Path) -> Result<Self, GeoError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, GeoError> {
+        let mut blocks = Vec::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |reason: &str| GeoError::Parse {
+                line: index + 1,
+                reason: reason.to_string(),
+            };
+
+            let (cidr, country) = line.split_once(',').ok_or_else(|| error("expected cidr,country"))?;
+            let (network, prefix) = cidr.trim().split_once('/').ok_or_else(|| error("missing prefix length"))?;
+            let network: IpAddr = network.parse().map_err(|_| error("invalid network address"))?;
+            let prefix: u8 = prefix.parse().map_err(|_| error("invalid prefix length"))?;
+            let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+            if prefix > max_prefix {
+                return Err(error("prefix length too long"));
+            }
+
+            let country = country.trim().to_ascii_uppercase();
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(error("country must be an ISO 3166 alpha-2 code"));
+            }
+            blocks.push(CidrBlock { network, prefix, country });
+        }
+
+        // Most specific first, so the first hit is the longest prefix
+        blocks.sort_by(|a, b| b.prefix.cmp(&a.prefix));
+        Ok(Self { blocks })
+    }
+}
+
+impl GeoProvider for StaticCidrProvider {
+    fn name(&self) -> &str {
+        "static CIDR list"
+    }
+
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        self.blocks
+            .iter()
+            .find(|block| block.contains(&ip))
+            .map(|block| block.country.clone())
+    }
+}
@@ -0,0 +1,202 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use super::checks::HighRiskThresholds;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+// Metadata revealed for one side of a transaction through a view-key disclosure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisclosedTransaction {
+    pub transaction_id: String,
+    pub entity: String,
+    pub amount: f64,
+    pub direction: Direction,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatternKind {
+    Structuring,
+    RapidCycling,
+    VelocitySpike,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternFinding {
+    pub pattern: PatternKind,
+    pub entity: String,
+    pub explanation: String,
+    pub transaction_ids: Vec<String>,
+}
+
+pub struct PatternStore {
+    window: Duration,
+    by_entity: HashMap<String, VecDeque<DisclosedTransaction>>,
+}
+
+impl PatternStore {
+    // Amounts within this fraction below the reporting threshold count as structuring
+    const STRUCTURING_MARGIN: f64 = 0.1;
+    // Funds leaving again within this long of arriving count as a cycle
+    const CYCLE_WINDOW_MINUTES: i64 = 60;
+    // An outgoing amount this close to the incoming one is treated as pass-through
+    const CYCLE_AMOUNT_TOLERANCE: f64 = 0.1;
+    const MIN_CYCLES: usize = 2;
+    // Hourly activity this many times the window's average is a spike
+    const VELOCITY_MULTIPLIER: f64 = 3.0;
+
+    pub fn new(thresholds: &HighRiskThresholds) -> Self {
+        Self {
+            window: Duration::hours(thresholds.pattern_window_hours as i64),
+            by_entity: HashMap::new(),
+        }
+    }
+
+    // Histories already held are pruned to the new window on the next `prune`
+    pub fn set_window(&mut self, thresholds: &HighRiskThresholds) {
+        self.window = Duration::hours(thresholds.pattern_window_hours as i64);
+    }
+
+    pub fn record(&mut self, tx: DisclosedTransaction) {
+        let now = tx.timestamp;
+        let history = self.by_entity.entry(tx.entity.clone()).or_default();
+
+        // Keep each history sorted; disclosures may arrive out of order
+        let position = history.iter().rposition(|t| t.timestamp <= tx.timestamp).map_or(0, |i| i + 1);
+        history.insert(position, tx);
+        self.prune(now);
+    }
+
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - self.window;
+        for history in self.by_entity.values_mut() {
+            while history.front().map_or(false, |t| t.timestamp < cutoff) {
+                history.pop_front();
+            }
+        }
+        self.by_entity.retain(|_, history| !history.is_empty());
+    }
+
+    pub fn entities_for(&self, transaction_id: &str) -> Vec<String> {
+        self.by_entity
+            .iter()
+            .filter(|(_, history)| history.iter().any(|t| t.transaction_id == transaction_id))
+            .map(|(entity, _)| entity.clone())
+            .collect()
+    }
+
+    pub fn analyze(&self, entity: &str, thresholds: &HighRiskThresholds, now: DateTime<Utc>) -> Vec<PatternFinding> {
+        let Some(history) = self.by_entity.get(entity) else {
+            return Vec::new();
+        };
+
+        [
+            self.detect_structuring(entity, history, thresholds),
+            self.detect_cycling(entity, history),
+            self.detect_velocity(entity, history, thresholds, now),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn detect_structuring(
+        &self,
+        entity: &str,
+        history: &VecDeque<DisclosedTransaction>,
+        thresholds: &HighRiskThresholds,
+    ) -> Option<PatternFinding> {
+        let floor = thresholds.amount * (1.0 - Self::STRUCTURING_MARGIN);
+        let just_below: Vec<&DisclosedTransaction> = history
+            .iter()
+            .filter(|t| t.amount >= floor && t.amount < thresholds.amount)
+            .collect();
+
+        if just_below.len() < thresholds.frequency as usize {
+            return None;
+        }
+        Some(PatternFinding {
+            pattern: PatternKind::Structuring,
+            entity: entity.to_string(),
+            explanation: format!(
+                "{} transactions between {:.2} and the {:.2} reporting threshold within {} hours",
+                just_below.len(),
+                floor,
+                thresholds.amount,
+                self.window.num_hours(),
+            ),
+            transaction_ids: just_below.iter().map(|t| t.transaction_id.clone()).collect(),
+        })
+    }
+
+    fn detect_cycling(&self, entity: &str, history: &VecDeque<DisclosedTransaction>) -> Option<PatternFinding> {
+        let cycle_window = Duration::minutes(Self::CYCLE_WINDOW_MINUTES);
+        let mut cycles = Vec::new();
+
+        for incoming in history.iter().filter(|t| t.direction == Direction::Incoming) {
+            let passed_through = history.iter().find(|out| {
+                out.direction == Direction::Outgoing
+                    && out.timestamp >= incoming.timestamp
+                    && out.timestamp - incoming.timestamp <= cycle_window
+                    && (incoming.amount - out.amount).abs() <= incoming.amount * Self::CYCLE_AMOUNT_TOLERANCE
+            });
+            if let Some(outgoing) = passed_through {
+                cycles.push((incoming, outgoing));
+            }
+        }
+
+        if cycles.len() < Self::MIN_CYCLES {
+            return None;
+        }
+        Some(PatternFinding {
+            pattern: PatternKind::RapidCycling,
+            entity: entity.to_string(),
+            explanation: format!(
+                "{} deposits forwarded on at a similar amount within {} minutes",
+                cycles.len(),
+                Self::CYCLE_WINDOW_MINUTES,
+            ),
+            transaction_ids: cycles
+                .iter()
+                .flat_map(|(i, o)| [i.transaction_id.clone(), o.transaction_id.clone()])
+                .collect(),
+        })
+    }
+
+    fn detect_velocity(
+        &self,
+        entity: &str,
+        history: &VecDeque<DisclosedTransaction>,
+        thresholds: &HighRiskThresholds,
+        now: DateTime<Utc>,
+    ) -> Option<PatternFinding> {
+        let last_hour: Vec<&DisclosedTransaction> = history
+            .iter()
+            .filter(|t| now - t.timestamp <= Duration::hours(1))
+            .collect();
+
+        let window_hours = self.window.num_hours().max(1) as f64;
+        let hourly_average = history.len() as f64 / window_hours;
+        let limit = (hourly_average * Self::VELOCITY_MULTIPLIER).max(thresholds.frequency as f64);
+
+        if (last_hour.len() as f64) <= limit {
+            return None;
+        }
+        Some(PatternFinding {
+            pattern: PatternKind::VelocitySpike,
+            entity: entity.to_string(),
+            explanation: format!(
+                "{} transactions in the last hour against an average of {:.1} per hour",
+                last_hour.len(),
+                hourly_average,
+            ),
+            transaction_ids: last_hour.iter().map(|t| t.transaction_id.clone()).collect(),
+        })
+    }
+}
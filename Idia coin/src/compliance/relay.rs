@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use idia_core::Transaction;
+use idia_node::mempool::AdmissionPolicy;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::checks::{CheckResult, ComplianceChecker};
+use crate::metrics::compliance::TRANSACTIONS_REJECTED;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Transaction {transaction_id} refused by relay policy rule {rule}: {reason}")]
+pub struct RelayRejection {
+    pub transaction_id: String,
+    pub rule: String,
+    pub reason: String,
+}
+
+// Operator opt-in; a node that leaves this disabled relays everything consensus accepts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RelayPolicyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    // Structural rule ids to enforce; empty enforces all of them
+    #[serde(default)]
+    pub rules: Vec<String>,
+}
+
+// Consulted by the mempool before a transaction is accepted for relay, once
+// installed with `Mempool::set_admission_policy`. Only
+// the policy's structural rules (size, ring size) are evaluated, so admission
+// never depends on deanonymized data, and only rules with `block` severity
+// refuse a transaction.
+pub struct RelayPolicy {
+    config: RelayPolicyConfig,
+    checker: Arc<ComplianceChecker>,
+}
+
+impl RelayPolicy {
+    pub fn new(config: RelayPolicyConfig, checker: Arc<ComplianceChecker>) -> Self {
+        Self { config, checker }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub async fn admit(&self, tx: &Transaction) -> Result<(), RelayRejection> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        for (rule, check) in self.checker.check_structure(tx).await {
+            if !self.config.rules.is_empty() && !self.config.rules.contains(&rule) {
+                continue;
+            }
+            if let CheckResult::Fail(reason) = check.result {
+                let transaction_id = hex::encode(tx.hash());
+                TRANSACTIONS_REJECTED.inc();
+                tracing::debug!("Not relaying {}: rule {} failed: {}", transaction_id, rule, reason);
+                return Err(RelayRejection {
+                    transaction_id,
+                    rule,
+                    reason,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AdmissionPolicy for RelayPolicy {
+    async fn admit(&self, tx: &Transaction) -> Result<(), String> {
+        RelayPolicy::admit(self, tx).await.map_err(|e| e.to_string())
+    }
+}
@@ -1,6 +1,16 @@
-use std::path::PathBuf;
-use chrono::{DateTime, Utc};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use curve25519_dalek::ristretto::RistrettoPoint;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use super::alerts::AlertStore;
+use super::audit::{AuditEvent, ComplianceAuditLog};
+use super::results::CheckStore;
+use super::schedule::CronSchedule;
+use super::sealing::SealedBox;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ComplianceReport {
@@ -19,15 +29,33 @@ pub struct ComplianceMetrics {
     regulatory_requests_handled: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComplianceAlert {
-    timestamp: DateTime<Utc>,
-    alert_type: AlertType,
-    description: String,
-    resolution_status: ResolutionStatus,
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub alert_type: AlertType,
+    pub description: String,
+    pub resolution_status: ResolutionStatus,
+    pub transaction_id: Option<String>,
+    pub assignee: Option<String>,
+    pub comments: Vec<AlertComment>,
+    pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl ComplianceAlert {
+    pub fn is_open(&self) -> bool {
+        matches!(self.resolution_status, ResolutionStatus::Open | ResolutionStatus::InProgress)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertComment {
+    pub author: String,
+    pub timestamp: DateTime<Utc>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AlertType {
     LargeTransaction,
     AnomalousPattern,
@@ -35,7 +63,7 @@ pub enum AlertType {
     ComplianceCheckFailure,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ResolutionStatus {
     Open,
     InProgress,
@@ -43,14 +71,165 @@ pub enum ResolutionStatus {
     FalsePositive,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<u32>,
+    pub max_reports: Option<usize>,
+}
+
 pub struct ComplianceReporter {
     data_dir: PathBuf,
     node_id: String,
+    audit: Option<Arc<ComplianceAuditLog>>,
+    alerts: Option<Arc<RwLock<AlertStore>>>,
+    results: Option<Arc<RwLock<CheckStore>>>,
+    schedule: Option<CronSchedule>,
+    retention: RetentionPolicy,
+    // Regulator or auditor key that exported reports are encrypted to
+    encryption_key: Option<RistrettoPoint>,
 }
 
 impl ComplianceReporter {
+    const REPORT_PREFIX: &'static str = "compliance_report_";
+    const TIMESTAMP_FORMAT: &'static str = "%Y%m%d_%H%M%S";
+    const ENCRYPTION_DOMAIN: &'static [u8] = b"idia-compliance-report-v1";
+
     pub fn new(data_dir: PathBuf, node_id: String) -> Self {
-        Self { data_dir, node_id }
+        Self {
+            data_dir,
+            node_id,
+            audit: None,
+            alerts: None,
+            results: None,
+            schedule: None,
+            retention: RetentionPolicy::default(),
+            encryption_key: None,
+        }
+    }
+
+    pub fn with_audit_log(mut self, audit: Arc<ComplianceAuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub fn with_alert_store(mut self, alerts: Arc<RwLock<AlertStore>>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    pub fn with_check_store(mut self, results: Arc<RwLock<CheckStore>>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: CronSchedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn with_encryption_key(mut self, key: RistrettoPoint) -> Self {
+        self.encryption_key = Some(key);
+        self
+    }
+
+    pub fn spawn_scheduler(reporter: Arc<RwLock<Self>>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let next = {
+                    let reporter = reporter.read().await;
+                    match &reporter.schedule {
+                        Some(schedule) => schedule.next_after(Utc::now()),
+                        None => None,
+                    }
+                };
+                let Some(next) = next else {
+                    tracing::warn!("No upcoming compliance report run; scheduler stopped");
+                    return;
+                };
+                let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                let reporter = reporter.read().await;
+                match reporter.run_scheduled().await {
+                    Ok(path) => tracing::info!("Scheduled compliance report written to {}", path.display()),
+                    Err(e) => tracing::error!("Scheduled compliance report failed: {}", e),
+                }
+            }
+        })
+    }
+
+    async fn run_scheduled(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let report = self.generate_report().await?;
+        let path = self.export_report(report).await?;
+        self.apply_retention().await?;
+        Ok(path)
+    }
+
+    // Deletes exported reports that are too old or beyond the configured count, newest kept first
+    pub async fn apply_retention(&self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        let mut reports = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.data_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(timestamp) = Self::report_timestamp(&name) {
+                reports.push((timestamp, entry.path()));
+            }
+        }
+        reports.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let cutoff = self.retention.max_age_days.map(|days| Utc::now() - Duration::days(days as i64));
+        let keep = self.retention.max_reports.unwrap_or(usize::MAX);
+
+        let mut deleted = Vec::new();
+        for (index, (timestamp, path)) in reports.into_iter().enumerate() {
+            let expired = cutoff.map_or(false, |cutoff| timestamp < cutoff);
+            if !expired && index < keep {
+                continue;
+            }
+
+            tokio::fs::remove_file(&path).await?;
+            if let Some(audit) = &self.audit {
+                audit.append(AuditEvent::ReportDeleted {
+                    path: path.display().to_string(),
+                }).await?;
+            }
+            deleted.push(path);
+        }
+        Ok(deleted)
+    }
+
+    // Resolves the file name of an exported report inside the data directory.
+    // Anything else, including `..` and absolute paths, resolves to nothing.
+    pub fn report_path(&self, name: &str) -> Option<PathBuf> {
+        let mut components = Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) if Self::report_timestamp(name).is_some() => {
+                Some(self.data_dir.join(name))
+            }
+            _ => None,
+        }
+    }
+
+    fn report_timestamp(name: &str) -> Option<DateTime<Utc>> {
+        let stem = name.strip_prefix(Self::REPORT_PREFIX)?;
+        let stem = stem.strip_suffix(".json.enc").or_else(|| stem.strip_suffix(".json"))?;
+        NaiveDateTime::parse_from_str(stem, Self::TIMESTAMP_FORMAT)
+            .ok()
+            .map(|t| t.and_utc())
+    }
+
+    pub fn decrypt_report(
+        sealed: &[u8],
+        secret: &curve25519_dalek::Scalar,
+    ) -> Result<ComplianceReport, Box<dyn std::error::Error>> {
+        let sealed: SealedBox = bincode::deserialize(sealed)?;
+        let plaintext = sealed.open(Self::ENCRYPTION_DOMAIN, secret)?;
+        Ok(serde_json::from_slice(&plaintext)?)
     }
 
     pub async fn generate_report(&self) -> Result<ComplianceReport, Box<dyn std::error::Error>> {
@@ -68,31 +247,73 @@ impl ComplianceReporter {
         })
     }
 
+    // Covers the day before the report, matching the alerts it includes
     async fn collect_metrics(&self) -> Result<ComplianceMetrics, Box<dyn std::error::Error>> {
-        // Implementation for collecting metrics from the node
-        // This would interact with your node's metric collection system
+        let now = Utc::now();
+        let since = now - Duration::days(1);
+
+        let summary = match &self.results {
+            Some(results) => results.read().await.summary(since, now),
+            None => Default::default(),
+        };
+
+        let (mut view_key_disclosures, mut regulatory_requests_handled) = (0, 0);
+        if let Some(audit) = &self.audit {
+            for record in audit.records().await?.iter().filter(|r| r.timestamp >= since) {
+                match record.event {
+                    AuditEvent::ViewKeyDisclosure { .. } => {
+                        view_key_disclosures += 1;
+                        regulatory_requests_handled += 1;
+                    }
+                    AuditEvent::ViewKeyDenied { .. } => regulatory_requests_handled += 1,
+                    _ => {}
+                }
+            }
+        }
+
         Ok(ComplianceMetrics {
-            total_transactions: 0, // TODO: Implement actual metric collection
-            ring_signature_usage: 0.0,
-            stealth_address_usage: 0.0,
-            view_key_disclosures: 0,
-            regulatory_requests_handled: 0,
+            total_transactions: summary.total,
+            ring_signature_usage: summary.ring_signature_usage,
+            stealth_address_usage: summary.stealth_address_usage,
+            view_key_disclosures,
+            regulatory_requests_handled,
         })
     }
 
     async fn get_recent_alerts(&self) -> Result<Vec<ComplianceAlert>, Box<dyn std::error::Error>> {
-        // Implementation for getting recent alerts from your alerting system
-        Ok(Vec::new()) // TODO: Implement actual alert collection
+        let Some(alerts) = &self.alerts else {
+            return Ok(Vec::new());
+        };
+        Ok(alerts.read().await.for_report(Utc::now() - Duration::days(1)))
     }
 
     pub async fn export_report(&self, report: ComplianceReport) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let report_path = self.data_dir.join(format!(
-            "compliance_report_{}.json",
-            report.timestamp.format("%Y%m%d_%H%M%S")
-        ));
-
         let report_json = serde_json::to_string_pretty(&report)?;
-        tokio::fs::write(&report_path, report_json).await?;
+        let timestamp = report.timestamp.format(Self::TIMESTAMP_FORMAT);
+
+        // Encrypted reports never touch disk in plaintext
+        let (report_path, contents) = match &self.encryption_key {
+            Some(key) => {
+                let sealed = SealedBox::seal(report_json.as_bytes(), Self::ENCRYPTION_DOMAIN, key)?;
+                (
+                    self.data_dir.join(format!("{}{}.json.enc", Self::REPORT_PREFIX, timestamp)),
+                    bincode::serialize(&sealed)?,
+                )
+            }
+            None => (
+                self.data_dir.join(format!("{}{}.json", Self::REPORT_PREFIX, timestamp)),
+                report_json.into_bytes(),
+            ),
+        };
+        tokio::fs::write(&report_path, &contents).await?;
+
+        if let Some(audit) = &self.audit {
+            audit.append(AuditEvent::ReportGenerated {
+                node_id: self.node_id.clone(),
+                path: report_path.display().to_string(),
+                sha256: hex::encode(Sha256::digest(&contents)),
+            }).await?;
+        }
 
         Ok(report_path)
     }
@@ -0,0 +1,243 @@
+use chrono::{DateTime, Duration, Utc};
+use idia_core::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+use super::checks::{CheckResult, ComplianceCheckType, TransactionCheck};
+use super::risk::RiskDecision;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CheckStoreError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+// A check result plus the transaction facts reports aggregate over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRecord {
+    pub check: TransactionCheck,
+    pub ring_size: u32,
+    pub stealth_address: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CheckQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub decision: Option<RiskDecision>,
+    // Only results where this check did not pass
+    pub failed_check: Option<ComplianceCheckType>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckSummary {
+    pub total: u64,
+    pub passed: u64,
+    pub review: u64,
+    pub blocked: u64,
+    // Fractions of checked transactions
+    pub ring_signature_usage: f64,
+    pub stealth_address_usage: f64,
+}
+
+// Every `TransactionCheck`, appended as JSON lines and indexed in memory.
+// Records older than the retention window are dropped when the store is opened.
+pub struct CheckStore {
+    path: PathBuf,
+    records: Vec<CheckRecord>,
+    by_transaction: HashMap<String, Vec<usize>>,
+}
+
+impl CheckStore {
+    pub async fn open(path: PathBuf, retention_days: Option<u32>) -> Result<Self, CheckStoreError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
+        let mut records = Vec::new();
+        let mut torn = false;
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str::<CheckRecord>(line) {
+                Ok(record) => records.push(record),
+                // A crash mid-append leaves a partial last line; anything earlier is real corruption
+                Err(e) if index + 1 == lines.len() => {
+                    tracing::warn!("Dropping torn last record of {}: {}", path.display(), e);
+                    torn = true;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut store = Self {
+            path,
+            records,
+            by_transaction: HashMap::new(),
+        };
+        let mut rewrite = torn;
+        if let Some(days) = retention_days {
+            let cutoff = Utc::now() - Duration::days(days as i64);
+            let before = store.records.len();
+            store.records.retain(|r| r.check.timestamp >= cutoff);
+            rewrite |= store.records.len() != before;
+        }
+        if rewrite {
+            store.compact().await?;
+        }
+        store.reindex();
+        Ok(store)
+    }
+
+    pub async fn record(&mut self, tx: &Transaction, check: &TransactionCheck) -> Result<(), CheckStoreError> {
+        let record = CheckRecord {
+            check: check.clone(),
+            // The largest ring, since inputs may use different sizes
+            ring_size: tx.inputs.iter().map(|input| input.ring.len()).max().unwrap_or(0) as u32,
+            // Every regular output is paid to a one-time stealth key
+            stealth_address: !tx.outputs.is_empty(),
+        };
+
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+
+        self.by_transaction
+            .entry(check.transaction_id.clone())
+            .or_default()
+            .push(self.records.len());
+        self.records.push(record);
+        Ok(())
+    }
+
+    // All checks run against a transaction, oldest first
+    pub fn for_transaction(&self, transaction_id: &str) -> Vec<&TransactionCheck> {
+        self.by_transaction
+            .get(transaction_id)
+            .map(|indices| indices.iter().map(|&i| &self.records[i].check).collect())
+            .unwrap_or_default()
+    }
+
+    // Newest first
+    pub fn query(&self, query: &CheckQuery) -> Vec<&TransactionCheck> {
+        self.records
+            .iter()
+            .rev()
+            .map(|r| &r.check)
+            .filter(|c| query.from.map_or(true, |from| c.timestamp >= from))
+            .filter(|c| query.to.map_or(true, |to| c.timestamp < to))
+            .filter(|c| query.decision.map_or(true, |d| c.risk.decision == d))
+            .filter(|c| {
+                query.failed_check.as_ref().map_or(true, |failed| {
+                    c.checks
+                        .iter()
+                        .any(|check| check.check_type == *failed && !matches!(check.result, CheckResult::Pass))
+                })
+            })
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    pub fn summary(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> CheckSummary {
+        let mut summary = CheckSummary::default();
+        let mut ring_signatures = 0u64;
+        let mut stealth_addresses = 0u64;
+
+        for record in self.records.iter().filter(|r| r.check.timestamp >= from && r.check.timestamp < to) {
+            summary.total += 1;
+            match record.check.risk.decision {
+                RiskDecision::Pass => summary.passed += 1,
+                RiskDecision::Review => summary.review += 1,
+                RiskDecision::Block => summary.blocked += 1,
+            }
+            // A ring of one is a plain signature
+            if record.ring_size > 1 {
+                ring_signatures += 1;
+            }
+            if record.stealth_address {
+                stealth_addresses += 1;
+            }
+        }
+
+        if summary.total > 0 {
+            summary.ring_signature_usage = ring_signatures as f64 / summary.total as f64;
+            summary.stealth_address_usage = stealth_addresses as f64 / summary.total as f64;
+        }
+        summary
+    }
+
+    fn reindex(&mut self) {
+        self.by_transaction.clear();
+        for (index, record) in self.records.iter().enumerate() {
+            self.by_transaction
+                .entry(record.check.transaction_id.clone())
+                .or_default()
+                .push(index);
+        }
+    }
+
+    async fn compact(&self) -> Result<(), CheckStoreError> {
+        let mut contents = Vec::new();
+        for record in &self.records {
+            contents.extend(serde_json::to_vec(record)?);
+            contents.push(b'\n');
+        }
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, contents).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compliance::risk::RiskScore;
+
+    fn record(transaction_id: &str) -> CheckRecord {
+        CheckRecord {
+            check: TransactionCheck {
+                transaction_id: transaction_id.to_string(),
+                timestamp: Utc::now(),
+                checks: Vec::new(),
+                risk: RiskScore::default(),
+            },
+            ring_size: 11,
+            stealth_address: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_drops_torn_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checks.jsonl");
+        let mut contents = serde_json::to_vec(&record("a")).unwrap();
+        contents.push(b'\n');
+        contents.extend_from_slice(br#"{"check":{"transaction_id":"b","#);
+        std::fs::write(&path, contents).unwrap();
+
+        let store = CheckStore::open(path.clone(), None).await.unwrap();
+        assert_eq!(store.for_transaction("a").len(), 1);
+        assert!(store.for_transaction("b").is_empty());
+
+        // The torn line is gone from disk, so the next open is clean
+        let reopened = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(reopened.lines().count(), 1);
+        assert!(CheckStore::open(path, None).await.is_ok());
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use super::rules::Severity;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskDecision {
+    #[default]
+    Pass,
+    Review,
+    Block,
+}
+
+// Scores at or above `review` go to an analyst, at or above `block` are refused
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RiskBands {
+    pub review: f64,
+    pub block: f64,
+}
+
+impl Default for RiskBands {
+    fn default() -> Self {
+        Self {
+            review: 30.0,
+            block: 100.0,
+        }
+    }
+}
+
+impl RiskBands {
+    pub fn decide(&self, score: f64) -> RiskDecision {
+        if score >= self.block {
+            RiskDecision::Block
+        } else if score >= self.review {
+            RiskDecision::Review
+        } else {
+            RiskDecision::Pass
+        }
+    }
+}
+
+// Weight a rule contributes when none is configured, chosen so the default
+// bands reproduce the old outcomes: one blocking rule blocks, one review rule
+// needs review, and warnings only escalate in numbers
+pub fn default_weight(severity: Severity) -> f64 {
+    match severity {
+        Severity::Warning => 10.0,
+        Severity::Review => 30.0,
+        Severity::Block => 100.0,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskFactor {
+    pub rule_id: String,
+    pub weight: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RiskScore {
+    pub score: f64,
+    pub decision: RiskDecision,
+    pub factors: Vec<RiskFactor>,
+}
+
+impl RiskScore {
+    pub fn from_factors(factors: Vec<RiskFactor>, bands: &RiskBands) -> Self {
+        let score = factors.iter().map(|f| f.weight).sum();
+        Self {
+            score,
+            decision: bands.decide(score),
+            factors,
+        }
+    }
+}
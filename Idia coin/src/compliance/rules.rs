@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::checks::{CheckResult, ComplianceCheckType, ComplianceConfig};
+use super::config::load_by_extension;
+use super::risk::{default_weight, RiskBands};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported policy file format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Invalid rule {rule}: {reason}")]
+    InvalidRule { rule: String, reason: String },
+    #[error("Invalid policy: {0}")]
+    InvalidPolicy(String),
+}
+
+// What a failing rule does to the transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Review,
+    Block,
+}
+
+impl Severity {
+    pub fn result(&self, reason: String) -> CheckResult {
+        match self {
+            Severity::Warning => CheckResult::Warning(reason),
+            Severity::Review => CheckResult::RequiresReview,
+            Severity::Block => CheckResult::Fail(reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum RuleCondition {
+    TransactionSize { max_bytes: u64 },
+    RingSize { min: u32 },
+    AmountAbove { amount: f64 },
+    PatternAnalysis,
+    SanctionsList,
+    // Adds to the config's `restricted_jurisdictions`
+    GeographicRestrictions {
+        #[serde(default)]
+        restricted: Vec<String>,
+    },
+    // Daily limit defaults to the config's `max_daily_volume`, monthly to 30 times the daily one
+    VolumeLimit {
+        #[serde(default)]
+        daily: Option<f64>,
+        #[serde(default)]
+        monthly: Option<f64>,
+    },
+}
+
+impl RuleCondition {
+    pub fn check_type(&self) -> ComplianceCheckType {
+        match self {
+            RuleCondition::TransactionSize { .. } => ComplianceCheckType::TransactionSize,
+            RuleCondition::RingSize { .. } => ComplianceCheckType::RingSignatureValidation,
+            RuleCondition::AmountAbove { .. } => ComplianceCheckType::AmountRange,
+            RuleCondition::PatternAnalysis => ComplianceCheckType::PatternAnalysis,
+            RuleCondition::SanctionsList => ComplianceCheckType::SanctionsList,
+            RuleCondition::GeographicRestrictions { .. } => ComplianceCheckType::GeographicRestrictions,
+            RuleCondition::VolumeLimit { .. } => ComplianceCheckType::VolumeLimit,
+        }
+    }
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    pub severity: Severity,
+    // Contribution to the risk score when the rule fails; defaults by severity
+    #[serde(default)]
+    pub weight: Option<f64>,
+    #[serde(flatten)]
+    pub condition: RuleCondition,
+}
+
+impl Rule {
+    fn new(id: &str, severity: Severity, condition: RuleCondition) -> Self {
+        Self {
+            id: id.to_string(),
+            description: None,
+            enabled: true,
+            severity,
+            weight: None,
+            condition,
+        }
+    }
+
+    pub fn weight(&self) -> f64 {
+        self.weight.unwrap_or_else(|| default_weight(self.severity))
+    }
+
+    fn validate(&self) -> Result<(), PolicyError> {
+        let invalid = |reason: &str| PolicyError::InvalidRule {
+            rule: self.id.clone(),
+            reason: reason.to_string(),
+        };
+
+        if self.weight.map_or(false, |w| !w.is_finite() || w < 0.0) {
+            return Err(invalid("weight must be a non-negative number"));
+        }
+
+        match &self.condition {
+            RuleCondition::TransactionSize { max_bytes } if *max_bytes == 0 => {
+                Err(invalid("max_bytes must be positive"))
+            }
+            RuleCondition::RingSize { min } if *min < 2 => Err(invalid("min ring size must be at least 2")),
+            RuleCondition::AmountAbove { amount } if !amount.is_finite() || *amount <= 0.0 => {
+                Err(invalid("amount must be a positive number"))
+            }
+            RuleCondition::GeographicRestrictions { restricted }
+                if !restricted.iter().all(|c| c.len() == 2 && c.chars().all(|c| c.is_ascii_uppercase())) =>
+            {
+                Err(invalid("restricted jurisdictions must be ISO 3166 alpha-2 codes"))
+            }
+            RuleCondition::VolumeLimit { daily, monthly }
+                if [daily, monthly].into_iter().flatten().any(|v| !v.is_finite() || *v <= 0.0) =>
+            {
+                Err(invalid("volume limits must be positive numbers"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// A jurisdiction's set of checks, e.g.
+//
+//   jurisdiction = "EU"
+//   version = 3
+//
+//   [[rules]]
+//   id = "large-transfer"
+//   check = "amount_above"
+//   amount = 10000.0
+//   severity = "review"
+//   weight = 40.0
+//
+//   [bands]
+//   review = 30.0
+//   block = 100.0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompliancePolicy {
+    pub jurisdiction: String,
+    pub version: u32,
+    #[serde(default)]
+    pub bands: RiskBands,
+    pub rules: Vec<Rule>,
+}
+
+impl CompliancePolicy {
+    // Equivalent to the checks that used to be hard-coded in ComplianceChecker
+    pub fn from_config(config: &ComplianceConfig) -> Self {
+        Self {
+            jurisdiction: "default".to_string(),
+            version: 0,
+            bands: RiskBands::default(),
+            rules: vec![
+                Rule::new(
+                    "transaction-size",
+                    Severity::Block,
+                    RuleCondition::TransactionSize { max_bytes: config.max_transaction_size },
+                ),
+                Rule::new(
+                    "ring-size",
+                    Severity::Block,
+                    RuleCondition::RingSize { min: config.min_ring_size },
+                ),
+                Rule::new(
+                    "high-risk-amount",
+                    Severity::Warning,
+                    RuleCondition::AmountAbove { amount: config.high_risk_thresholds.amount },
+                ),
+                Rule::new("pattern-analysis", Severity::Review, RuleCondition::PatternAnalysis),
+                Rule::new("sanctions", Severity::Block, RuleCondition::SanctionsList),
+                Rule::new(
+                    "restricted-jurisdictions",
+                    Severity::Block,
+                    RuleCondition::GeographicRestrictions { restricted: Vec::new() },
+                ),
+                Rule::new(
+                    "volume-limit",
+                    Severity::Review,
+                    RuleCondition::VolumeLimit { daily: None, monthly: None },
+                ),
+            ],
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, PolicyError> {
+        let policy: Self = load_by_extension(path, PolicyError::UnsupportedFormat)?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn validate(&self) -> Result<(), PolicyError> {
+        if self.jurisdiction.trim().is_empty() {
+            return Err(PolicyError::InvalidPolicy("jurisdiction is required".to_string()));
+        }
+        if !(self.bands.review > 0.0 && self.bands.review <= self.bands.block && self.bands.block.is_finite()) {
+            return Err(PolicyError::InvalidPolicy("risk bands must satisfy 0 < review <= block".to_string()));
+        }
+        if self.rules.is_empty() {
+            return Err(PolicyError::InvalidPolicy("policy declares no rules".to_string()));
+        }
+
+        let mut ids = HashSet::new();
+        for rule in &self.rules {
+            if rule.id.trim().is_empty() {
+                return Err(PolicyError::InvalidPolicy("rule without an id".to_string()));
+            }
+            if !ids.insert(rule.id.as_str()) {
+                return Err(PolicyError::InvalidRule {
+                    rule: rule.id.clone(),
+                    reason: "duplicate rule id".to_string(),
+                });
+            }
+            rule.validate()?;
+        }
+        Ok(())
+    }
+
+    pub fn enabled_rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().filter(|rule| rule.enabled)
+    }
+}
@@ -0,0 +1,362 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SanctionsError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Malformed sanctions list at line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+    #[error("Provider {0} returned no entries")]
+    EmptyList(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SanctionedEntity {
+    pub source: String,
+    pub uid: String,
+    pub name: String,
+    pub programs: Vec<String>,
+    pub addresses: Vec<String>,
+    pub identifiers: Vec<String>,
+}
+
+// What a view-key disclosure revealed about a transaction's counterparty
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisclosedEntity {
+    pub name: Option<String>,
+    pub addresses: Vec<String>,
+    pub identifiers: Vec<String>,
+    // Declared country of residence or registration
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub ip_addresses: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MatchField {
+    Address,
+    Identifier,
+    Name,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SanctionsMatch {
+    pub source: String,
+    pub uid: String,
+    pub name: String,
+    pub programs: Vec<String>,
+    pub field: MatchField,
+    pub matched_value: String,
+}
+
+#[async_trait]
+pub trait SanctionsProvider: Send + Sync {
+    fn name(&self) -> &str;
+    async fn fetch(&self) -> Result<Vec<SanctionedEntity>, SanctionsError>;
+}
+
+// Parses the OFAC SDN list in its published CSV layout (sdn.csv):
+// ent_num, SDN_Name, SDN_Type, Program, Title, Call_Sign, Vess_type,
+// Tonnage, GRT, Vess_flag, Vess_owner, Remarks
+pub struct OfacSdnProvider {
+    path: PathBuf,
+}
+
+impl OfacSdnProvider {
+    const DIGITAL_CURRENCY_PREFIX: &'static str = "Digital Currency Address - ";
+    const ID_MARKERS: [&'static str; 4] = ["Passport ", "National ID No. ", "Tax ID No. ", "Registration Number "];
+
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn parse(contents: &str) -> Result<Vec<SanctionedEntity>, SanctionsError> {
+        let mut entities = Vec::new();
+
+        // Remarks may be quoted across several lines, so records aren't lines
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(contents.as_bytes());
+
+        for record in reader.records() {
+            let record = record.map_err(|e| SanctionsError::Parse {
+                line: e.position().map_or(0, |p| p.line() as usize),
+                reason: e.to_string(),
+            })?;
+            let line = record.position().map_or(0, |p| p.line() as usize);
+            // The file ends with a lone control character
+            if record.iter().all(|field| field.is_empty() || field == "\u{1a}") {
+                continue;
+            }
+
+            let fields: Vec<String> = record.iter().map(str::to_string).collect();
+            if fields.len() < 12 {
+                return Err(SanctionsError::Parse {
+                    line,
+                    reason: format!("expected 12 fields, found {}", fields.len()),
+                });
+            }
+
+            let remarks = null_field(&fields[11]);
+            let (addresses, identifiers) = remarks
+                .map(Self::parse_remarks)
+                .unwrap_or_default();
+
+            entities.push(SanctionedEntity {
+                source: "OFAC SDN".to_string(),
+                uid: fields[0].clone(),
+                name: fields[1].clone(),
+                programs: null_field(&fields[3]).map(Self::parse_programs).unwrap_or_default(),
+                addresses,
+                identifiers,
+            });
+        }
+
+        Ok(entities)
+    }
+
+    // Multiple programs are written as "SDGT] [IRGC"
+    fn parse_programs(programs: &str) -> Vec<String> {
+        programs
+            .split(']')
+            .map(|p| p.trim_matches(|c| c == '[' || c == ' ').to_string())
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    // Remarks are `;`-separated, e.g. "Digital Currency Address - XBT 1A1z...; Passport X123 (Country)"
+    fn parse_remarks(remarks: &str) -> (Vec<String>, Vec<String>) {
+        let mut addresses = Vec::new();
+        let mut identifiers = Vec::new();
+
+        for part in remarks.split(';').map(str::trim) {
+            if let Some(rest) = part.strip_prefix(Self::DIGITAL_CURRENCY_PREFIX) {
+                // "<ticker> <address>"
+                if let Some(address) = rest.split_whitespace().nth(1) {
+                    addresses.push(address.trim_end_matches('.').to_string());
+                }
+            } else if let Some(rest) = Self::ID_MARKERS.iter().find_map(|m| part.strip_prefix(m)) {
+                if let Some(id) = rest.split_whitespace().next() {
+                    identifiers.push(id.trim_end_matches(|c| c == ',' || c == '.').to_string());
+                }
+            }
+        }
+
+        (addresses, identifiers)
+    }
+}
+
+#[async_trait]
+impl SanctionsProvider for OfacSdnProvider {
+    fn name(&self) -> &str {
+        "OFAC SDN"
+    }
+
+    async fn fetch(&self) -> Result<Vec<SanctionedEntity>, SanctionsError> {
+        let contents = tokio::fs::read_to_string(&self.path).await?;
+        Self::parse(&contents)
+    }
+}
+
+fn null_field(field: &str) -> Option<&str> {
+    // OFAC uses -0- for empty fields
+    let field = field.trim();
+    (!field.is_empty() && field != "-0-").then_some(field)
+}
+
+fn normalize(value: &str) -> String {
+    value.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+#[derive(Default)]
+struct SanctionsIndex {
+    entities: Vec<SanctionedEntity>,
+    // Entities can share a name or identifier, so each key lists every match
+    by_address: HashMap<String, Vec<usize>>,
+    by_identifier: HashMap<String, Vec<usize>>,
+    by_name: HashMap<String, Vec<usize>>,
+    refreshed_at: Option<DateTime<Utc>>,
+}
+
+impl SanctionsIndex {
+    fn build(entities: Vec<SanctionedEntity>) -> Self {
+        let mut index = Self::default();
+        for (i, entity) in entities.iter().enumerate() {
+            // Addresses are case-sensitive in some encodings, so keep them exact
+            for address in &entity.addresses {
+                index.by_address.entry(address.clone()).or_default().push(i);
+            }
+            for id in &entity.identifiers {
+                index.by_identifier.entry(normalize(id)).or_default().push(i);
+            }
+            index.by_name.entry(normalize(&entity.name)).or_default().push(i);
+        }
+        index.entities = entities;
+        index.refreshed_at = Some(Utc::now());
+        index
+    }
+
+    fn screen(&self, entity: &DisclosedEntity) -> Vec<SanctionsMatch> {
+        let mut matches = Vec::new();
+        let mut push = |i: usize, field: MatchField, value: &str| {
+            let hit = &self.entities[i];
+            matches.push(SanctionsMatch {
+                source: hit.source.clone(),
+                uid: hit.uid.clone(),
+                name: hit.name.clone(),
+                programs: hit.programs.clone(),
+                field,
+                matched_value: value.to_string(),
+            });
+        };
+
+        for address in &entity.addresses {
+            for &i in self.by_address.get(address).into_iter().flatten() {
+                push(i, MatchField::Address, address);
+            }
+        }
+        for id in &entity.identifiers {
+            for &i in self.by_identifier.get(&normalize(id)).into_iter().flatten() {
+                push(i, MatchField::Identifier, id);
+            }
+        }
+        if let Some(name) = &entity.name {
+            for &i in self.by_name.get(&normalize(name)).into_iter().flatten() {
+                push(i, MatchField::Name, name);
+            }
+        }
+        matches
+    }
+}
+
+pub struct SanctionsScreener {
+    providers: Vec<Box<dyn SanctionsProvider>>,
+    index: RwLock<SanctionsIndex>,
+    refresh_interval: Duration,
+}
+
+impl SanctionsScreener {
+    pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+    pub fn new(providers: Vec<Box<dyn SanctionsProvider>>) -> Self {
+        Self {
+            providers,
+            index: RwLock::new(SanctionsIndex::default()),
+            refresh_interval: Self::DEFAULT_REFRESH_INTERVAL,
+        }
+    }
+
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    // Swaps in a fresh index only when every provider loaded, so a failed
+    // download never leaves screening with a partial list
+    pub async fn refresh(&self) -> Result<usize, SanctionsError> {
+        let mut entities = Vec::new();
+        for provider in &self.providers {
+            let fetched = provider.fetch().await?;
+            if fetched.is_empty() {
+                return Err(SanctionsError::EmptyList(provider.name().to_string()));
+            }
+            entities.extend(fetched);
+        }
+
+        let count = entities.len();
+        *self.index.write().await = SanctionsIndex::build(entities);
+        Ok(count)
+    }
+
+    pub fn spawn_refresh(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.refresh_interval);
+            loop {
+                interval.tick().await;
+                match self.refresh().await {
+                    Ok(count) => tracing::info!("Sanctions lists refreshed: {} entries", count),
+                    Err(e) => tracing::error!("Sanctions list refresh failed: {}", e),
+                }
+            }
+        })
+    }
+
+    pub async fn last_refresh(&self) -> Option<DateTime<Utc>> {
+        self.index.read().await.refreshed_at
+    }
+
+    pub async fn screen(&self, entities: &[DisclosedEntity]) -> Vec<SanctionsMatch> {
+        let index = self.index.read().await;
+        entities.iter().flat_map(|entity| index.screen(entity)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDN: &str = concat!(
+        "36,\"AEROCARIBBEAN AIRLINES\",-0- ,\"CUBA\",-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,\"Passport A123 (Cuba).\"\n",
+        "7001,\"SMITH, John\",\"individual\",\"SDGT] [IRGC\",-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,",
+        "\"Digital Currency Address - XBT 1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa;\n Passport X99, (Iran).\"\n",
+        "7002,\"SMITH, John\",\"individual\",\"SDGT\",-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,-0- ,\"Passport A123 (UK).\"\n",
+        "\u{1a}\n",
+    );
+
+    #[test]
+    fn test_parse_quoted_multiline_remarks() {
+        let entities = OfacSdnProvider::parse(SDN).unwrap();
+        assert_eq!(entities.len(), 3);
+
+        let smith = &entities[1];
+        assert_eq!(smith.name, "SMITH, John");
+        assert_eq!(smith.programs, vec!["SDGT", "IRGC"]);
+        assert_eq!(smith.addresses, vec!["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]);
+        assert_eq!(smith.identifiers, vec!["X99"]);
+        assert_eq!(entities[0].programs, vec!["CUBA"]);
+    }
+
+    #[test]
+    fn test_rejects_short_record() {
+        assert!(matches!(
+            OfacSdnProvider::parse("1,\"NAME\",-0-\n"),
+            Err(SanctionsError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_shared_keys_match_every_entity() {
+        let index = SanctionsIndex::build(OfacSdnProvider::parse(SDN).unwrap());
+
+        let by_name = index.screen(&DisclosedEntity {
+            name: Some("smith john".to_string()),
+            ..Default::default()
+        });
+        let uids: Vec<&str> = by_name.iter().map(|m| m.uid.as_str()).collect();
+        assert_eq!(uids, vec!["7001", "7002"]);
+
+        let by_id = index.screen(&DisclosedEntity {
+            identifiers: vec!["a-123".to_string()],
+            ..Default::default()
+        });
+        let uids: Vec<&str> = by_id.iter().map(|m| m.uid.as_str()).collect();
+        assert_eq!(uids, vec!["36", "7002"]);
+
+        let clean = index.screen(&DisclosedEntity {
+            addresses: vec!["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNA".to_string()],
+            ..Default::default()
+        });
+        assert!(clean.is_empty());
+    }
+}
@@ -0,0 +1,286 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+use super::cases::CaseBundle;
+use super::checks::{CheckResult, ComplianceCheckType, ComplianceChecker, TransactionCheck};
+use super::patterns::PatternKind;
+use super::reporter::AlertType;
+use super::sanctions::DisclosedEntity;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SarError {
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("No failed or flagged checks to report")]
+    NothingToReport,
+}
+
+// The filing institution as registered with the FIU
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportingEntity {
+    // Registration number assigned by the FIU (goAML `rentity_id`)
+    pub id: String,
+    pub name: String,
+    pub contact_first_name: String,
+    pub contact_last_name: String,
+    pub contact_email: String,
+    // ISO 4217 code amounts are reported in
+    pub currency_code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SarTransaction {
+    pub transaction_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub indicators: Vec<String>,
+    pub description: String,
+}
+
+// Generic SAR template; `to_goaml_xml` renders the same data for goAML portals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuspiciousActivityReport {
+    pub reference: String,
+    pub generated_at: DateTime<Utc>,
+    pub reporting_entity: ReportingEntity,
+    pub reason: String,
+    pub action: String,
+    pub indicators: Vec<String>,
+    pub subjects: Vec<DisclosedEntity>,
+    pub transactions: Vec<SarTransaction>,
+}
+
+impl SuspiciousActivityReport {
+    const GOAML_DATE_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S";
+
+    pub fn from_case(bundle: &CaseBundle, entity: ReportingEntity) -> Result<Self, SarError> {
+        let case = &bundle.case;
+        if bundle.alerts.is_empty() && bundle.transactions.is_empty() {
+            return Err(SarError::NothingToReport);
+        }
+
+        let transactions: Vec<SarTransaction> = bundle
+            .transactions
+            .iter()
+            .map(|evidence| {
+                let alerts: Vec<_> = bundle
+                    .alerts
+                    .iter()
+                    .filter(|a| a.transaction_id.as_ref() == Some(&evidence.transaction_id))
+                    .collect();
+                SarTransaction {
+                    transaction_id: evidence.transaction_id.clone(),
+                    // The first alert is the closest thing to a detection time
+                    timestamp: alerts.iter().map(|a| a.timestamp).min().unwrap_or(case.created_at),
+                    indicators: dedup(alerts.iter().map(|a| alert_indicator(a.alert_type).to_string())),
+                    description: alerts.iter().map(|a| a.description.as_str()).collect::<Vec<_>>().join("; "),
+                }
+            })
+            .collect();
+
+        let mut reason = case.title.clone();
+        for alert in &bundle.alerts {
+            let _ = write!(reason, "\n- {}", alert.description);
+        }
+
+        Ok(Self {
+            reference: format!("CASE-{}", case.id),
+            generated_at: Utc::now(),
+            reporting_entity: entity,
+            reason,
+            action: case.notes.iter().map(|n| n.text.as_str()).collect::<Vec<_>>().join("\n"),
+            indicators: dedup(bundle.alerts.iter().map(|a| alert_indicator(a.alert_type).to_string())),
+            subjects: bundle
+                .transactions
+                .iter()
+                .flat_map(|t| t.disclosed_parties.iter().cloned())
+                .collect(),
+            transactions,
+        })
+    }
+
+    // Builds a report straight from check results, skipping transactions that passed everything
+    pub async fn from_checks(
+        checks: &[TransactionCheck],
+        checker: &ComplianceChecker,
+        entity: ReportingEntity,
+    ) -> Result<Self, SarError> {
+        let mut transactions = Vec::new();
+        let mut subjects = Vec::new();
+        let mut reasons = Vec::new();
+
+        for check in checks {
+            let mut indicators = Vec::new();
+            let mut details = Vec::new();
+            for result in &check.checks {
+                let reason = match &result.result {
+                    CheckResult::Pass => continue,
+                    CheckResult::Fail(reason) | CheckResult::Warning(reason) => reason,
+                    CheckResult::RequiresReview => &result.details,
+                };
+                if result.findings.is_empty() {
+                    indicators.push(check_indicator(&result.check_type).to_string());
+                }
+                indicators.extend(result.findings.iter().map(|f| pattern_indicator(&f.pattern).to_string()));
+                details.push(format!("{:?}: {}", result.check_type, reason));
+            }
+            if details.is_empty() {
+                continue;
+            }
+
+            subjects.extend(checker.disclosures_for(&check.transaction_id).await);
+            details.push(format!("risk score {:.0} ({:?})", check.risk.score, check.risk.decision));
+            reasons.push(format!("{}: {}", check.transaction_id, details.join("; ")));
+            transactions.push(SarTransaction {
+                transaction_id: check.transaction_id.clone(),
+                timestamp: check.timestamp,
+                indicators: dedup(indicators.into_iter()),
+                description: details.join("; "),
+            });
+        }
+        if transactions.is_empty() {
+            return Err(SarError::NothingToReport);
+        }
+
+        let generated_at = Utc::now();
+        Ok(Self {
+            reference: format!("CHECKS-{}", generated_at.format("%Y%m%d%H%M%S")),
+            generated_at,
+            reporting_entity: entity,
+            reason: reasons.join("\n"),
+            action: String::new(),
+            indicators: dedup(transactions.iter().flat_map(|t| t.indicators.iter().cloned())),
+            subjects,
+            transactions,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, SarError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    // goAML report of type STR. Amounts stay hidden behind commitments, so
+    // transactions are described rather than given `amount_local` values the
+    // officer would have to fill in from the disclosure.
+    pub fn to_goaml_xml(&self) -> String {
+        let entity = &self.reporting_entity;
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<report>\n");
+
+        element(&mut xml, 1, "rentity_id", &entity.id);
+        element(&mut xml, 1, "submission_code", "E");
+        element(&mut xml, 1, "report_code", "STR");
+        element(&mut xml, 1, "entity_reference", &self.reference);
+        element(&mut xml, 1, "submission_date", &self.generated_at.format(Self::GOAML_DATE_FORMAT).to_string());
+        element(&mut xml, 1, "currency_code_local", &entity.currency_code);
+
+        xml.push_str("  <reporting_person>\n");
+        element(&mut xml, 2, "first_name", &entity.contact_first_name);
+        element(&mut xml, 2, "last_name", &entity.contact_last_name);
+        element(&mut xml, 2, "email", &entity.contact_email);
+        xml.push_str("  </reporting_person>\n");
+
+        element(&mut xml, 1, "reason", &self.reason);
+        if !self.action.is_empty() {
+            element(&mut xml, 1, "action", &self.action);
+        }
+
+        for tx in &self.transactions {
+            xml.push_str("  <transaction>\n");
+            element(&mut xml, 2, "transactionnumber", &tx.transaction_id);
+            element(&mut xml, 2, "transaction_description", &tx.description);
+            element(&mut xml, 2, "date_transaction", &tx.timestamp.format(Self::GOAML_DATE_FORMAT).to_string());
+            element(&mut xml, 2, "transmode_code", "VA");
+            xml.push_str("  </transaction>\n");
+        }
+
+        if !self.subjects.is_empty() {
+            xml.push_str("  <activity>\n    <report_parties>\n");
+            for subject in &self.subjects {
+                xml.push_str("      <report_party>\n        <person>\n");
+                if let Some(name) = &subject.name {
+                    element(&mut xml, 5, "last_name", name);
+                }
+                if let Some(country) = &subject.country {
+                    element(&mut xml, 5, "residence", country);
+                }
+                for identifier in &subject.identifiers {
+                    xml.push_str("          <identification>\n");
+                    element(&mut xml, 6, "number", identifier);
+                    xml.push_str("          </identification>\n");
+                }
+                xml.push_str("        </person>\n");
+                if !subject.addresses.is_empty() {
+                    element(&mut xml, 4, "comments", &format!("Wallet addresses: {}", subject.addresses.join(", ")));
+                }
+                xml.push_str("      </report_party>\n");
+            }
+            xml.push_str("    </report_parties>\n  </activity>\n");
+        }
+
+        xml.push_str("  <report_indicators>\n");
+        for indicator in &self.indicators {
+            element(&mut xml, 2, "indicator", indicator);
+        }
+        xml.push_str("  </report_indicators>\n</report>\n");
+        xml
+    }
+}
+
+fn check_indicator(check_type: &ComplianceCheckType) -> &'static str {
+    match check_type {
+        ComplianceCheckType::SanctionsList | ComplianceCheckType::KnownParticipantCheck => "SANCTIONS",
+        ComplianceCheckType::GeographicRestrictions => "HIGH_RISK_JURISDICTION",
+        ComplianceCheckType::AmountRange | ComplianceCheckType::VolumeLimit => "LARGE_VALUE",
+        ComplianceCheckType::PatternAnalysis => "UNUSUAL_PATTERN",
+        ComplianceCheckType::TimeBasedRestrictions => "UNUSUAL_TIMING",
+        ComplianceCheckType::TransactionSize
+        | ComplianceCheckType::RingSignatureValidation
+        | ComplianceCheckType::StealthAddressFormat => "PROTOCOL_ANOMALY",
+    }
+}
+
+fn pattern_indicator(pattern: &PatternKind) -> &'static str {
+    match pattern {
+        PatternKind::Structuring => "STRUCTURING",
+        PatternKind::RapidCycling => "RAPID_MOVEMENT",
+        PatternKind::VelocitySpike => "UNUSUAL_VELOCITY",
+    }
+}
+
+fn alert_indicator(alert_type: AlertType) -> &'static str {
+    match alert_type {
+        AlertType::LargeTransaction => "LARGE_VALUE",
+        AlertType::AnomalousPattern => "UNUSUAL_PATTERN",
+        AlertType::PrivacyFeatureFailure => "PROTOCOL_ANOMALY",
+        AlertType::ComplianceCheckFailure => "COMPLIANCE_FAILURE",
+    }
+}
+
+fn dedup(items: impl Iterator<Item = String>) -> Vec<String> {
+    let mut unique = Vec::new();
+    for item in items {
+        if !unique.contains(&item) {
+            unique.push(item);
+        }
+    }
+    unique
+}
+
+fn element(xml: &mut String, depth: usize, name: &str, value: &str) {
+    let _ = writeln!(xml, "{}<{}>{}</{}>", "  ".repeat(depth), name, escape(value), name);
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
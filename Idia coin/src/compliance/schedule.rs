@@ -0,0 +1,152 @@
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid schedule {expression}: {reason}")]
+pub struct ScheduleError {
+    expression: String,
+    reason: String,
+}
+
+// Standard five-field cron expression evaluated in UTC:
+// minute hour day-of-month month day-of-week. Fields accept `*`, values,
+// ranges `a-b`, steps `*/n` or `a-b/n`, and comma-separated lists.
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    // Cron matches either day field when both are restricted
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    // Looking further ahead than this means the expression can never fire (e.g. Feb 30)
+    const MAX_LOOKAHEAD_DAYS: i64 = 366 * 4;
+
+    pub fn parse(expression: &str) -> Result<Self, ScheduleError> {
+        let error = |reason: String| ScheduleError {
+            expression: expression.to_string(),
+            reason,
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(error(format!("expected 5 fields, found {}", fields.len())));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7).map_err(&error)?;
+        // Both 0 and 7 mean Sunday
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        weekdays.truncate(7);
+
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: parse_field(fields[0], 0, 59).map_err(&error)?,
+            hours: parse_field(fields[1], 0, 23).map_err(&error)?,
+            days: parse_field(fields[2], 1, 31).map_err(&error)?,
+            months: parse_field(fields[3], 1, 12).map_err(&error)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.duration_trunc(Duration::minutes(1)).ok()? + Duration::minutes(1);
+        let limit = after + Duration::days(Self::MAX_LOOKAHEAD_DAYS);
+
+        while t <= limit {
+            if !self.months[t.month() as usize] || !self.day_matches(&t) {
+                // Skip to the start of the next day
+                t = t.duration_trunc(Duration::days(1)).ok()? + Duration::days(1);
+                continue;
+            }
+            if !self.hours[t.hour() as usize] {
+                t = t.duration_trunc(Duration::hours(1)).ok()? + Duration::hours(1);
+                continue;
+            }
+            if !self.minutes[t.minute() as usize] {
+                t += Duration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+        None
+    }
+
+    fn day_matches(&self, t: &DateTime<Utc>) -> bool {
+        let day = self.days[t.day() as usize];
+        let weekday = self.weekdays[t.weekday().num_days_from_sunday() as usize];
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, String> {
+    let mut allowed = vec![false; max as usize + 1];
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step {}", step))?;
+                if step == 0 {
+                    return Err("step must be positive".to_string());
+                }
+                (range, step)
+            }
+            None => (item, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (parse_value(a, min, max)?, parse_value(b, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // `a/n` runs from a to the end of the field
+            (value, if item.contains('/') { max } else { value })
+        };
+        if start > end {
+            return Err(format!("range {} is reversed", range));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    let parsed: u32 = value.parse().map_err(|_| format!("invalid value {}", value))?;
+    if parsed < min || parsed > max {
+        return Err(format!("{} outside {}-{}", parsed, min, max));
+    }
+    Ok(parsed)
+}
+
+impl Serialize for CronSchedule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.expression)
+    }
+}
+
+impl<'de> Deserialize<'de> for CronSchedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let expression = String::deserialize(deserializer)?;
+        CronSchedule::parse(&expression).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,112 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::Scalar;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SealingError {
+    #[error("Invalid public key")]
+    InvalidKey,
+    #[error("Encryption failed")]
+    Encryption,
+    #[error("Decryption failed")]
+    Decryption,
+}
+
+// Data encrypted to a Ristretto public key: an ephemeral Diffie-Hellman
+// exchange derives an AES-256-GCM key. The domain separates uses of the same
+// recipient key and is bound in as associated data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedBox {
+    pub ephemeral_key: [u8; 32],
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+impl SealedBox {
+    pub fn seal(plaintext: &[u8], domain: &[u8], recipient: &RistrettoPoint) -> Result<Self, SealingError> {
+        Self::seal_on(&RISTRETTO_BASEPOINT_POINT, plaintext, domain, recipient)
+    }
+
+    // For a recipient key on another base, such as a diversified address key
+    // s·D: the ephemeral key is taken on the same base so `open` with s agrees
+    pub fn seal_on(base: &RistrettoPoint, plaintext: &[u8], domain: &[u8], recipient: &RistrettoPoint) -> Result<Self, SealingError> {
+        let mut rng = OsRng;
+        let ephemeral_secret = Scalar::random(&mut rng);
+        let ephemeral_key = (base * ephemeral_secret).compress();
+        let key = derive_key(domain, &(ephemeral_secret * recipient), &ephemeral_key);
+
+        let mut nonce = [0u8; 12];
+        rng.fill_bytes(&mut nonce);
+        let ciphertext = Aes256Gcm::new(key.as_slice().into())
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: domain })
+            .map_err(|_| SealingError::Encryption)?;
+
+        Ok(Self {
+            ephemeral_key: ephemeral_key.to_bytes(),
+            nonce,
+            ciphertext,
+        })
+    }
+
+    pub fn open(&self, domain: &[u8], secret: &Scalar) -> Result<Vec<u8>, SealingError> {
+        let ephemeral_key = CompressedRistretto(self.ephemeral_key);
+        let ephemeral_point = ephemeral_key.decompress().ok_or(SealingError::InvalidKey)?;
+        let key = derive_key(domain, &(secret * ephemeral_point), &ephemeral_key);
+
+        Aes256Gcm::new(key.as_slice().into())
+            .decrypt(Nonce::from_slice(&self.nonce), Payload { msg: &self.ciphertext, aad: domain })
+            .map_err(|_| SealingError::Decryption)
+    }
+}
+
+fn derive_key(domain: &[u8], shared_secret: &RistrettoPoint, ephemeral_key: &CompressedRistretto) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(domain);
+    hasher.update(shared_secret.compress().as_bytes());
+    hasher.update(ephemeral_key.as_bytes());
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_round_trip() {
+        let secret = Scalar::random(&mut OsRng);
+        let sealed = SealedBox::seal(b"share", b"domain", &(RISTRETTO_BASEPOINT_POINT * secret)).unwrap();
+        assert_eq!(sealed.open(b"domain", &secret).unwrap(), b"share");
+
+        // Sealed on another base, opened with the same secret
+        let base = RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng);
+        let sealed = SealedBox::seal_on(&base, b"note", b"domain", &(base * secret)).unwrap();
+        assert_eq!(sealed.open(b"domain", &secret).unwrap(), b"note");
+    }
+
+    #[test]
+    fn test_open_rejects_tampering() {
+        let secret = Scalar::random(&mut OsRng);
+        let sealed = SealedBox::seal(b"share", b"domain", &(RISTRETTO_BASEPOINT_POINT * secret)).unwrap();
+
+        assert!(matches!(sealed.open(b"other", &secret), Err(SealingError::Decryption)));
+        assert!(matches!(
+            sealed.open(b"domain", &Scalar::random(&mut OsRng)),
+            Err(SealingError::Decryption)
+        ));
+
+        let mut tampered = sealed.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(matches!(tampered.open(b"domain", &secret), Err(SealingError::Decryption)));
+
+        let mut tampered = sealed;
+        tampered.ephemeral_key = [0xff; 32];
+        assert!(matches!(tampered.open(b"domain", &secret), Err(SealingError::InvalidKey)));
+    }
+}
@@ -0,0 +1,367 @@
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::Scalar;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::sealing::{SealedBox, SealingError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TravelRuleError {
+    #[error("Invalid IVMS101 payload: {0}")]
+    InvalidPayload(String),
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    #[error("Sealing error: {0}")]
+    Sealing(#[from] SealingError),
+    #[error("Malformed tx_extra field")]
+    MalformedExtra,
+    #[error("Out-of-band payload does not match its reference hash")]
+    ReferenceMismatch,
+    #[error("Payload must be fetched out of band from {0}")]
+    MissingPayload(String),
+}
+
+// IVMS101 data model, serialized with the standard's camelCase field names
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameIdentifierType {
+    #[serde(rename = "LEGL")]
+    Legal,
+    #[serde(rename = "ALIA")]
+    Alias,
+    #[serde(rename = "BIRT")]
+    Birth,
+    #[serde(rename = "MAID")]
+    Maiden,
+    #[serde(rename = "TRAD")]
+    Trading,
+    #[serde(rename = "SHRT")]
+    Short,
+    #[serde(rename = "MISC")]
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddressType {
+    #[serde(rename = "HOME")]
+    Residential,
+    #[serde(rename = "BIZZ")]
+    Business,
+    #[serde(rename = "GEOG")]
+    Geographic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NationalIdentifierType {
+    #[serde(rename = "ARNU")]
+    AlienRegistration,
+    #[serde(rename = "CCPT")]
+    Passport,
+    #[serde(rename = "RAID")]
+    RegistrationAuthority,
+    #[serde(rename = "DRLC")]
+    DriversLicense,
+    #[serde(rename = "FIIN")]
+    ForeignInvestment,
+    #[serde(rename = "TXID")]
+    TaxId,
+    #[serde(rename = "SOCS")]
+    SocialSecurity,
+    #[serde(rename = "IDCD")]
+    IdentityCard,
+    #[serde(rename = "LEIX")]
+    LegalEntityIdentifier,
+    #[serde(rename = "MISC")]
+    Unspecified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NaturalPersonNameId {
+    pub primary_identifier: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_identifier: Option<String>,
+    pub name_identifier_type: NameIdentifierType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NaturalPersonName {
+    pub name_identifier: Vec<NaturalPersonNameId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegalPersonNameId {
+    pub legal_person_name: String,
+    pub legal_person_name_identifier_type: NameIdentifierType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegalPersonName {
+    pub name_identifier: Vec<LegalPersonNameId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeographicAddress {
+    pub address_type: AddressType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub street_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub building_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_code: Option<String>,
+    pub town_name: String,
+    pub country: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NationalIdentification {
+    pub national_identifier: String,
+    pub national_identifier_type: NationalIdentifierType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_of_issue: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateAndPlaceOfBirth {
+    pub date_of_birth: String,
+    pub place_of_birth: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NaturalPerson {
+    pub name: NaturalPersonName,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub geographic_address: Vec<GeographicAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub national_identification: Option<NationalIdentification>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_identification: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_and_place_of_birth: Option<DateAndPlaceOfBirth>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_of_residence: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegalPerson {
+    pub name: LegalPersonName,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub geographic_address: Vec<GeographicAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub national_identification: Option<NationalIdentification>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_of_registration: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Person {
+    NaturalPerson(NaturalPerson),
+    LegalPerson(LegalPerson),
+}
+
+impl Person {
+    fn validate(&self) -> Result<(), TravelRuleError> {
+        let (has_legal_name, addresses, countries) = match self {
+            Person::NaturalPerson(p) => (
+                p.name.name_identifier.iter().any(|n| {
+                    n.name_identifier_type == NameIdentifierType::Legal && !n.primary_identifier.trim().is_empty()
+                }),
+                &p.geographic_address,
+                vec![p.country_of_residence.as_ref()],
+            ),
+            Person::LegalPerson(p) => (
+                p.name.name_identifier.iter().any(|n| {
+                    n.legal_person_name_identifier_type == NameIdentifierType::Legal
+                        && !n.legal_person_name.trim().is_empty()
+                }),
+                &p.geographic_address,
+                vec![p.country_of_registration.as_ref()],
+            ),
+        };
+
+        // IVMS101 constraint C6: every person carries a legal name
+        if !has_legal_name {
+            return Err(TravelRuleError::InvalidPayload("person has no legal name".to_string()));
+        }
+        for country in countries.into_iter().flatten().chain(addresses.iter().map(|a| &a.country)) {
+            if !is_country_code(country) {
+                return Err(TravelRuleError::InvalidPayload(format!("invalid country code {}", country)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Originator {
+    pub originator_persons: Vec<Person>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub account_number: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Beneficiary {
+    pub beneficiary_persons: Vec<Person>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub account_number: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OriginatingVasp {
+    #[serde(rename = "originatingVASP")]
+    pub originating_vasp: Person,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeneficiaryVasp {
+    #[serde(rename = "beneficiaryVASP")]
+    pub beneficiary_vasp: Person,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityPayload {
+    pub originator: Originator,
+    pub beneficiary: Beneficiary,
+    #[serde(rename = "originatingVASP")]
+    pub originating_vasp: OriginatingVasp,
+    #[serde(rename = "beneficiaryVASP", default, skip_serializing_if = "Option::is_none")]
+    pub beneficiary_vasp: Option<BeneficiaryVasp>,
+}
+
+impl IdentityPayload {
+    pub fn validate(&self) -> Result<(), TravelRuleError> {
+        if self.originator.originator_persons.is_empty() {
+            return Err(TravelRuleError::InvalidPayload("no originator persons".to_string()));
+        }
+        if self.beneficiary.beneficiary_persons.is_empty() {
+            return Err(TravelRuleError::InvalidPayload("no beneficiary persons".to_string()));
+        }
+
+        let persons = self.originator.originator_persons.iter()
+            .chain(&self.beneficiary.beneficiary_persons)
+            .chain(std::iter::once(&self.originating_vasp.originating_vasp))
+            .chain(self.beneficiary_vasp.iter().map(|v| &v.beneficiary_vasp));
+        for person in persons {
+            person.validate()?;
+        }
+        Ok(())
+    }
+}
+
+fn is_country_code(code: &str) -> bool {
+    code.len() == 2 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+// IVMS101 JSON sealed to the beneficiary VASP's Ristretto key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedTravelRulePayload(SealedBox);
+
+impl EncryptedTravelRulePayload {
+    const DOMAIN: &'static [u8] = b"idia-travel-rule-v1";
+
+    pub fn seal(payload: &IdentityPayload, vasp_public: &RistrettoPoint) -> Result<Self, TravelRuleError> {
+        payload.validate()?;
+        let plaintext = serde_json::to_vec(payload).map_err(|e| TravelRuleError::Serialization(e.to_string()))?;
+        Ok(Self(SealedBox::seal(&plaintext, Self::DOMAIN, vasp_public)?))
+    }
+
+    pub fn open(&self, vasp_secret: &Scalar) -> Result<IdentityPayload, TravelRuleError> {
+        let plaintext = self.0.open(Self::DOMAIN, vasp_secret)?;
+        let payload: IdentityPayload =
+            serde_json::from_slice(&plaintext).map_err(|e| TravelRuleError::Serialization(e.to_string()))?;
+        payload.validate()?;
+        Ok(payload)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, TravelRuleError> {
+        bincode::serialize(self).map_err(|e| TravelRuleError::Serialization(e.to_string()))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TravelRuleError> {
+        bincode::deserialize(bytes).map_err(|e| TravelRuleError::Serialization(e.to_string()))
+    }
+
+    pub fn digest(&self) -> Result<[u8; 32], TravelRuleError> {
+        Ok(Sha256::digest(self.to_bytes()?).into())
+    }
+}
+
+// How a transaction points at its travel-rule data: the sealed payload itself,
+// or a hash commitment to one exchanged between VASPs out of band
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TravelRuleAttachment {
+    Inline(EncryptedTravelRulePayload),
+    Reference { uri: String, sha256: [u8; 32] },
+}
+
+impl TravelRuleAttachment {
+    // tx_extra is a sequence of (tag: u8, length: u32 LE, value) fields
+    pub const EXTRA_TAG: u8 = 0x54;
+
+    pub fn reference(uri: &str, payload: &EncryptedTravelRulePayload) -> Result<Self, TravelRuleError> {
+        Ok(TravelRuleAttachment::Reference {
+            uri: uri.to_string(),
+            sha256: payload.digest()?,
+        })
+    }
+
+    pub fn to_tx_extra(&self) -> Result<Vec<u8>, TravelRuleError> {
+        let value = bincode::serialize(self).map_err(|e| TravelRuleError::Serialization(e.to_string()))?;
+        if value.len() > idia_core::MAX_TX_EXTRA - 5 {
+            return Err(TravelRuleError::Serialization(
+                "payload too large for tx_extra, attach by reference".to_string(),
+            ));
+        }
+        let mut extra = vec![Self::EXTRA_TAG];
+        extra.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        extra.extend_from_slice(&value);
+        Ok(extra)
+    }
+
+    pub fn from_tx_extra(extra: &[u8]) -> Result<Option<Self>, TravelRuleError> {
+        let mut rest = extra;
+        while !rest.is_empty() {
+            if rest.len() < 5 {
+                return Err(TravelRuleError::MalformedExtra);
+            }
+            let tag = rest[0];
+            let len = u32::from_le_bytes(rest[1..5].try_into().unwrap()) as usize;
+            let value = rest.get(5..5 + len).ok_or(TravelRuleError::MalformedExtra)?;
+            if tag == Self::EXTRA_TAG {
+                return bincode::deserialize(value)
+                    .map(Some)
+                    .map_err(|_| TravelRuleError::MalformedExtra);
+            }
+            rest = &rest[5 + len..];
+        }
+        Ok(None)
+    }
+
+    // Resolves to the sealed payload, checking out-of-band data against its commitment
+    pub fn resolve(&self, fetched: Option<&[u8]>) -> Result<EncryptedTravelRulePayload, TravelRuleError> {
+        match (self, fetched) {
+            (TravelRuleAttachment::Inline(payload), _) => Ok(payload.clone()),
+            (TravelRuleAttachment::Reference { sha256, .. }, Some(bytes)) => {
+                let payload = EncryptedTravelRulePayload::from_bytes(bytes)?;
+                if payload.digest()? != *sha256 {
+                    return Err(TravelRuleError::ReferenceMismatch);
+                }
+                Ok(payload)
+            }
+            (TravelRuleAttachment::Reference { uri, .. }, None) => Err(TravelRuleError::MissingPayload(uri.clone())),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VolumeError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VolumeEntry {
+    transaction_id: String,
+    timestamp: DateTime<Utc>,
+    amount: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EntityVolume {
+    pub daily: f64,
+    pub monthly: f64,
+}
+
+// Rolling per-entity volume over the last 30 days, persisted so limits keep
+// applying across restarts
+pub struct VolumeTracker {
+    path: Option<PathBuf>,
+    entries: HashMap<String, VecDeque<VolumeEntry>>,
+}
+
+impl VolumeTracker {
+    const WINDOW_DAYS: i64 = 30;
+
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn open(path: PathBuf) -> Result<Self, VolumeError> {
+        let entries = match std::fs::read(&path) {
+            Ok(bytes) => bincode::deserialize(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut tracker = Self {
+            path: Some(path),
+            entries,
+        };
+        tracker.prune(Utc::now());
+        Ok(tracker)
+    }
+
+    pub async fn record(
+        &mut self,
+        entity: &str,
+        transaction_id: &str,
+        amount: f64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), VolumeError> {
+        let history = self.entries.entry(entity.to_string()).or_default();
+        // The same transaction may be disclosed more than once
+        if history.iter().any(|e| e.transaction_id == transaction_id) {
+            return Ok(());
+        }
+        let position = history.iter().rposition(|e| e.timestamp <= timestamp).map_or(0, |i| i + 1);
+        history.insert(
+            position,
+            VolumeEntry {
+                transaction_id: transaction_id.to_string(),
+                timestamp,
+                amount,
+            },
+        );
+
+        self.prune(Utc::now());
+        self.save().await
+    }
+
+    pub fn volume(&self, entity: &str, now: DateTime<Utc>) -> EntityVolume {
+        let Some(history) = self.entries.get(entity) else {
+            return EntityVolume { daily: 0.0, monthly: 0.0 };
+        };
+        let day_ago = now - Duration::days(1);
+        let month_ago = now - Duration::days(Self::WINDOW_DAYS);
+
+        EntityVolume {
+            daily: history.iter().filter(|e| e.timestamp > day_ago).map(|e| e.amount).sum(),
+            monthly: history.iter().filter(|e| e.timestamp > month_ago).map(|e| e.amount).sum(),
+        }
+    }
+
+    pub fn entities_for(&self, transaction_id: &str) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|(_, history)| history.iter().any(|e| e.transaction_id == transaction_id))
+            .map(|(entity, _)| entity.clone())
+            .collect()
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - Duration::days(Self::WINDOW_DAYS);
+        for history in self.entries.values_mut() {
+            while history.front().map_or(false, |e| e.timestamp <= cutoff) {
+                history.pop_front();
+            }
+        }
+        self.entries.retain(|_, history| !history.is_empty());
+    }
+
+    async fn save(&self) -> Result<(), VolumeError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        // Write then rename, so a crash never leaves a truncated file
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, bincode::serialize(&self.entries)?).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+}
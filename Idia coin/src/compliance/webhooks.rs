@@ -0,0 +1,264 @@
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Endpoint responded with status {0}")]
+    Status(u16),
+    #[error("Invalid webhook {id}: {reason}")]
+    InvalidConfig { id: String, reason: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    AlertRaised,
+    CheckFailed,
+    ViewKeyDisclosed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub id: String,
+    pub url: String,
+    // Shared secret for the X-Idia-Signature HMAC; never serialized back out
+    #[serde(skip_serializing)]
+    pub secret: String,
+    // Subscribed events; empty subscribes to everything
+    #[serde(default)]
+    pub events: Vec<WebhookEventKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryState {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub id: u64,
+    pub webhook_id: String,
+    pub event: WebhookEventKind,
+    pub state: DeliveryState,
+    pub attempts: u32,
+    pub created_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    #[serde(skip)]
+    body: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    delivery_id: u64,
+    event: WebhookEventKind,
+    timestamp: DateTime<Utc>,
+    data: &'a serde_json::Value,
+}
+
+// Queues compliance events per subscribed endpoint and delivers them in the
+// background, retrying failures with exponential backoff
+pub struct WebhookDispatcher {
+    webhooks: Vec<WebhookConfig>,
+    client: reqwest::Client,
+    deliveries: RwLock<VecDeque<Delivery>>,
+    next_id: AtomicU64,
+}
+
+impl WebhookDispatcher {
+    const MAX_ATTEMPTS: u32 = 8;
+    const INITIAL_BACKOFF_SECS: i64 = 5;
+    const REQUEST_TIMEOUT_SECS: u64 = 10;
+    // Finished deliveries kept for the status API
+    const HISTORY_LIMIT: usize = 1000;
+
+    pub fn new(webhooks: Vec<WebhookConfig>) -> Result<Self, WebhookError> {
+        for webhook in &webhooks {
+            let invalid = |reason: &str| WebhookError::InvalidConfig {
+                id: webhook.id.clone(),
+                reason: reason.to_string(),
+            };
+            if !webhook.url.starts_with("https://") && !webhook.url.starts_with("http://") {
+                return Err(invalid("url must be http(s)"));
+            }
+            if webhook.secret.is_empty() {
+                return Err(invalid("secret must not be empty"));
+            }
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(Self::REQUEST_TIMEOUT_SECS))
+            .build()?;
+        Ok(Self {
+            webhooks,
+            client,
+            deliveries: RwLock::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    pub fn webhooks(&self) -> &[WebhookConfig] {
+        &self.webhooks
+    }
+
+    pub async fn notify(&self, event: WebhookEventKind, data: serde_json::Value) {
+        let now = Utc::now();
+        let mut deliveries = self.deliveries.write().await;
+
+        for webhook in &self.webhooks {
+            if !webhook.events.is_empty() && !webhook.events.contains(&event) {
+                continue;
+            }
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+            let envelope = Envelope {
+                delivery_id: id,
+                event,
+                timestamp: now,
+                data: &data,
+            };
+            // Each envelope carries its own delivery ID, so one failing doesn't stop the rest
+            let body = match serde_json::to_vec(&envelope) {
+                Ok(body) => body,
+                Err(e) => {
+                    tracing::error!("Failed to serialize {:?} webhook for {}: {}", event, webhook.id, e);
+                    continue;
+                }
+            };
+            deliveries.push_back(Delivery {
+                id,
+                webhook_id: webhook.id.clone(),
+                event,
+                state: DeliveryState::Pending,
+                attempts: 0,
+                created_at: now,
+                last_attempt_at: None,
+                next_attempt_at: Some(now),
+                last_error: None,
+                body,
+            });
+        }
+    }
+
+    // Most recent first
+    pub async fn deliveries(&self, webhook_id: Option<&str>, state: Option<DeliveryState>) -> Vec<Delivery> {
+        self.deliveries
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|d| webhook_id.map_or(true, |id| d.webhook_id == id))
+            .filter(|d| state.map_or(true, |s| d.state == s))
+            .cloned()
+            .collect()
+    }
+
+    pub fn spawn_delivery(self: Arc<Self>, poll_interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(poll_interval);
+            loop {
+                interval.tick().await;
+                self.deliver_due().await;
+            }
+        })
+    }
+
+    async fn deliver_due(&self) {
+        let now = Utc::now();
+        let due: Vec<(u64, String, Vec<u8>)> = self
+            .deliveries
+            .read()
+            .await
+            .iter()
+            .filter(|d| d.state == DeliveryState::Pending && d.next_attempt_at.map_or(false, |t| t <= now))
+            .map(|d| (d.id, d.webhook_id.clone(), d.body.clone()))
+            .collect();
+
+        for (id, webhook_id, body) in due {
+            let Some(webhook) = self.webhooks.iter().find(|w| w.id == webhook_id) else {
+                continue;
+            };
+            let result = self.send(webhook, &body).await;
+
+            let mut deliveries = self.deliveries.write().await;
+            let Some(delivery) = deliveries.iter_mut().find(|d| d.id == id) else {
+                continue;
+            };
+            let attempted_at = Utc::now();
+            delivery.attempts += 1;
+            delivery.last_attempt_at = Some(attempted_at);
+            match result {
+                Ok(()) => {
+                    delivery.state = DeliveryState::Delivered;
+                    delivery.next_attempt_at = None;
+                    delivery.last_error = None;
+                }
+                Err(e) => {
+                    delivery.last_error = Some(e.to_string());
+                    if delivery.attempts >= Self::MAX_ATTEMPTS {
+                        tracing::error!("Giving up on webhook {} delivery {}: {}", webhook_id, id, e);
+                        delivery.state = DeliveryState::Failed;
+                        delivery.next_attempt_at = None;
+                    } else {
+                        let backoff = Self::INITIAL_BACKOFF_SECS << (delivery.attempts - 1);
+                        delivery.next_attempt_at = Some(attempted_at + Duration::seconds(backoff));
+                    }
+                }
+            }
+        }
+
+        self.trim_history().await;
+    }
+
+    async fn send(&self, webhook: &WebhookConfig, body: &[u8]) -> Result<(), WebhookError> {
+        let timestamp = Utc::now().timestamp().to_string();
+        let response = self
+            .client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Idia-Timestamp", &timestamp)
+            .header("X-Idia-Signature", format!("sha256={}", sign(&webhook.secret, &timestamp, body)))
+            .body(body.to_vec())
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(WebhookError::Status(response.status().as_u16()))
+        }
+    }
+
+    async fn trim_history(&self) {
+        let mut deliveries = self.deliveries.write().await;
+        let mut finished = deliveries.iter().filter(|d| d.state != DeliveryState::Pending).count();
+        // Pending deliveries are never dropped
+        deliveries.retain(|d| {
+            if finished > Self::HISTORY_LIMIT && d.state != DeliveryState::Pending {
+                finished -= 1;
+                return false;
+            }
+            true
+        });
+    }
+}
+
+// HMAC-SHA256 over "<timestamp>.<body>", so receivers can reject replays
+fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
@@ -0,0 +1,287 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::ballot::VoteChoice;
+use super::parameters::Parameter;
+use super::threshold::{GovernanceProposal, ProposalId, ProposalState, ProposedChange, ThresholdGovernance};
+
+#[derive(Clone)]
+pub struct GovernanceApiState {
+    governance: Arc<RwLock<ThresholdGovernance>>,
+}
+
+impl GovernanceApiState {
+    pub fn new(governance: Arc<RwLock<ThresholdGovernance>>) -> Self {
+        Self { governance }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProposalFilter {
+    state: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProposalSummary {
+    id: String,
+    title: String,
+    kind: String,
+    state: ProposalState,
+    submitted_height: u64,
+    voting_ends_at: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProposalDetail {
+    #[serde(flatten)]
+    summary: ProposalSummary,
+    description: String,
+    change: serde_json::Value,
+    metadata_uri: Option<String>,
+    metadata_hash: Option<String>,
+    threshold: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VoteTallyResponse {
+    id: String,
+    state: ProposalState,
+    threshold: u32,
+    signers: Vec<u32>,
+    delegated_votes: Vec<(u32, u32)>,
+    veto_signers: Vec<u32>,
+    required_veto_signatures: u32,
+    anonymous: Option<AnonymousTally>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnonymousTally {
+    yes: u64,
+    no: u64,
+    abstain: u64,
+    ballots: usize,
+    double_votes: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParameterView {
+    name: &'static str,
+    value: u64,
+    min: u64,
+    max: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParameterChange {
+    name: &'static str,
+    value: u64,
+    height: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParametersResponse {
+    height: u64,
+    current: Vec<ParameterView>,
+    scheduled: Vec<ParameterChange>,
+    history: Vec<ParameterChange>,
+}
+
+// Mounted on the node's RPC server with `Node::with_rpc_routes`
+pub fn create_governance_routes(state: GovernanceApiState) -> Router {
+    Router::new()
+        .route("/governance/proposals", get(list_proposals))
+        .route("/governance/proposals/:id", get(proposal_detail))
+        .route("/governance/proposals/:id/tally", get(vote_tally))
+        .route("/governance/parameters", get(parameters))
+        .with_state(state)
+}
+
+async fn list_proposals(
+    State(state): State<GovernanceApiState>,
+    Query(filter): Query<ProposalFilter>,
+) -> Result<Json<Vec<ProposalSummary>>, StatusCode> {
+    let wanted = filter.state.as_deref().map(parse_state).transpose()?;
+    let governance = state.governance.read().await;
+
+    let mut proposals: Vec<&GovernanceProposal> = governance
+        .proposals()
+        .filter(|p| wanted.map_or(true, |s| p.state == s))
+        .collect();
+    // Newest first, ID as a stable tie-breaker
+    proposals.sort_by(|a, b| b.submitted_height.cmp(&a.submitted_height).then(a.id.cmp(&b.id)));
+
+    Ok(Json(
+        proposals
+            .into_iter()
+            .take(filter.limit.unwrap_or(100).min(1000))
+            .map(summarize)
+            .collect(),
+    ))
+}
+
+async fn proposal_detail(
+    State(state): State<GovernanceApiState>,
+    Path(id): Path<String>,
+) -> Result<Json<ProposalDetail>, StatusCode> {
+    let id = parse_id(&id)?;
+    let governance = state.governance.read().await;
+    let proposal = governance.proposal(id).ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(ProposalDetail {
+        summary: summarize(proposal),
+        description: proposal.description.clone(),
+        change: describe_change(&proposal.proposed_change),
+        metadata_uri: proposal.metadata.as_ref().map(|m| m.uri.clone()),
+        metadata_hash: proposal.metadata.as_ref().map(|m| hex::encode(m.content_hash)),
+        threshold: proposal.threshold,
+    }))
+}
+
+async fn vote_tally(
+    State(state): State<GovernanceApiState>,
+    Path(id): Path<String>,
+) -> Result<Json<VoteTallyResponse>, StatusCode> {
+    let id = parse_id(&id)?;
+    let governance = state.governance.read().await;
+    let proposal = governance.proposal(id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut signers: Vec<u32> = proposal.signatures.keys().copied().collect();
+    signers.sort_unstable();
+    let mut delegated_votes: Vec<(u32, u32)> = proposal.delegated_votes.iter().map(|(a, b)| (*a, *b)).collect();
+    delegated_votes.sort_unstable();
+    let mut veto_signers: Vec<u32> = proposal.veto_signatures.keys().copied().collect();
+    veto_signers.sort_unstable();
+
+    let anonymous = governance.vote_tally(id).map(|tally| AnonymousTally {
+        yes: tally.weight_for(VoteChoice::Yes),
+        no: tally.weight_for(VoteChoice::No),
+        abstain: tally.weight_for(VoteChoice::Abstain),
+        ballots: tally.ballots,
+        double_votes: tally.double_votes,
+    });
+
+    Ok(Json(VoteTallyResponse {
+        id: hex::encode(proposal.id),
+        state: proposal.state,
+        threshold: proposal.threshold,
+        signers,
+        delegated_votes,
+        veto_signers,
+        required_veto_signatures: governance.required_veto_signatures(),
+        anonymous,
+    }))
+}
+
+async fn parameters(State(state): State<GovernanceApiState>) -> Json<ParametersResponse> {
+    let governance = state.governance.read().await;
+    let registry = governance.parameters();
+
+    let current = Parameter::ALL
+        .into_iter()
+        .map(|p| {
+            let (min, max) = p.bounds();
+            ParameterView {
+                name: p.name(),
+                value: registry.get(p),
+                min,
+                max,
+            }
+        })
+        .collect();
+    let scheduled = registry
+        .scheduled_changes()
+        .map(|(height, p, value)| ParameterChange { name: p.name(), value, height })
+        .collect();
+    let history = registry
+        .history()
+        .iter()
+        .map(|(height, p, value)| ParameterChange { name: p.name(), value: *value, height: *height })
+        .collect();
+
+    Json(ParametersResponse {
+        height: governance.current_height(),
+        current,
+        scheduled,
+        history,
+    })
+}
+
+fn summarize(proposal: &GovernanceProposal) -> ProposalSummary {
+    ProposalSummary {
+        id: hex::encode(proposal.id),
+        title: proposal.title.clone(),
+        kind: change_kind(&proposal.proposed_change).to_string(),
+        state: proposal.state,
+        submitted_height: proposal.submitted_height,
+        voting_ends_at: proposal.voting_ends_at(),
+    }
+}
+
+fn change_kind(change: &ProposedChange) -> &'static str {
+    match change {
+        ProposedChange::ParameterUpdate { .. } => "parameter_update",
+        ProposedChange::ProtocolUpgrade { .. } => "protocol_upgrade",
+        ProposedChange::TreasurySpend { .. } => "treasury_spend",
+        ProposedChange::PrivacyFeatureToggle { .. } => "privacy_feature_toggle",
+        ProposedChange::BridgePause { .. } => "bridge_pause",
+    }
+}
+
+fn describe_change(change: &ProposedChange) -> serde_json::Value {
+    match change {
+        ProposedChange::ParameterUpdate { parameter, new_value } => serde_json::json!({
+            "parameter": parameter,
+            "new_value": new_value,
+        }),
+        ProposedChange::ProtocolUpgrade { version, activation_height } => serde_json::json!({
+            "version": version,
+            "activation_height": activation_height,
+        }),
+        ProposedChange::TreasurySpend { amount, recipient, purpose, payout } => serde_json::json!({
+            "amount": amount,
+            "recipient": recipient,
+            "purpose": purpose,
+            "payout": payout,
+        }),
+        ProposedChange::PrivacyFeatureToggle { feature, enabled } => serde_json::json!({
+            "feature": feature,
+            "enabled": enabled,
+        }),
+        ProposedChange::BridgePause { chain, paused, reason } => serde_json::json!({
+            "chain": chain,
+            "paused": paused,
+            "reason": reason,
+        }),
+    }
+}
+
+fn parse_state(state: &str) -> Result<ProposalState, StatusCode> {
+    match state.to_ascii_lowercase().as_str() {
+        "pending" => Ok(ProposalState::Pending),
+        "active" => Ok(ProposalState::Active),
+        "approved" => Ok(ProposalState::Approved),
+        "rejected" => Ok(ProposalState::Rejected),
+        "scheduled" => Ok(ProposalState::Scheduled),
+        "in_progress" => Ok(ProposalState::InProgress),
+        "executed" => Ok(ProposalState::Executed),
+        "execution_failed" => Ok(ProposalState::ExecutionFailed),
+        "vetoed" => Ok(ProposalState::Vetoed),
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+fn parse_id(id: &str) -> Result<ProposalId, StatusCode> {
+    hex::decode(id)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(StatusCode::BAD_REQUEST)
+}
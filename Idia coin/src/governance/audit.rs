@@ -0,0 +1,213 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::threshold::{GovernanceEvent, ProposalId};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+    #[error("Audit chain broken at entry {0}")]
+    BrokenChain(u64),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    pub height: u64,
+    pub event: GovernanceEvent,
+    pub prev_hash: [u8; 32],
+    pub hash: [u8; 32],
+}
+
+impl AuditEntry {
+    fn compute_hash(sequence: u64, height: u64, event: &GovernanceEvent, prev_hash: &[u8; 32]) -> Result<[u8; 32], AuditError> {
+        let mut hasher = Sha256::new();
+        hasher.update(prev_hash);
+        hasher.update(sequence.to_le_bytes());
+        hasher.update(height.to_le_bytes());
+        hasher.update(bincode::serialize(event)?);
+        Ok(hasher.finalize().into())
+    }
+
+    pub fn proposal_id(&self) -> ProposalId {
+        match &self.event {
+            GovernanceEvent::ProposalSubmitted { proposal_id, .. }
+            | GovernanceEvent::SignatureAdded { proposal_id, .. }
+            | GovernanceEvent::StateChanged { proposal_id, .. }
+            | GovernanceEvent::ProposalExecuted { proposal_id, .. }
+            | GovernanceEvent::ExecutionFailed { proposal_id, .. }
+            | GovernanceEvent::MilestoneReleased { proposal_id, .. }
+            | GovernanceEvent::ProposalVetoed { proposal_id, .. }
+            | GovernanceEvent::BridgePauseChanged { proposal_id, .. } => *proposal_id,
+        }
+    }
+}
+
+// Append-only log of governance events. Each entry hashes its predecessor, so
+// anyone holding the head hash can detect a rewritten or truncated history.
+pub struct GovernanceAuditLog {
+    path: PathBuf,
+    entries: Vec<AuditEntry>,
+}
+
+impl GovernanceAuditLog {
+    pub fn open(path: PathBuf) -> Result<Self, AuditError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Entries are stored as length-prefixed bincode records
+        let mut entries = Vec::new();
+        if path.exists() {
+            let mut bytes = Vec::new();
+            File::open(&path)?.read_to_end(&mut bytes)?;
+
+            let mut valid_len = 0;
+            let mut rest = bytes.as_slice();
+            while rest.len() >= 4 {
+                let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+                if rest.len() < 4 + len {
+                    break;
+                }
+                entries.push(bincode::deserialize(&rest[4..4 + len])?);
+                rest = &rest[4 + len..];
+                valid_len += 4 + len;
+            }
+
+            // A crash mid-append leaves a torn record; cut it off so the next
+            // append lands right after the last whole entry
+            if valid_len < bytes.len() {
+                tracing::warn!(
+                    path = %path.display(),
+                    bytes = bytes.len() - valid_len,
+                    "Truncating torn record at end of governance audit log"
+                );
+                let file = OpenOptions::new().write(true).open(&path)?;
+                file.set_len(valid_len as u64)?;
+                file.sync_data()?;
+            }
+        }
+
+        let log = Self { path, entries };
+        log.verify()?;
+        Ok(log)
+    }
+
+    pub fn head_hash(&self) -> [u8; 32] {
+        self.entries.last().map_or([0u8; 32], |entry| entry.hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn append(&mut self, height: u64, event: GovernanceEvent) -> Result<&AuditEntry, AuditError> {
+        let sequence = self.entries.len() as u64;
+        let prev_hash = self.head_hash();
+        let hash = AuditEntry::compute_hash(sequence, height, &event, &prev_hash)?;
+        let entry = AuditEntry {
+            sequence,
+            height,
+            event,
+            prev_hash,
+            hash,
+        };
+
+        let bytes = bincode::serialize(&entry)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        file.write_all(&bytes)?;
+        file.sync_data()?;
+
+        self.entries.push(entry);
+        Ok(self.entries.last().unwrap())
+    }
+
+    // Feed with `ThresholdGovernance::drain_events` after each block
+    pub fn record_events(&mut self, height: u64, events: Vec<GovernanceEvent>) -> Result<(), AuditError> {
+        for event in events {
+            self.append(height, event)?;
+        }
+        Ok(())
+    }
+
+    pub fn verify(&self) -> Result<(), AuditError> {
+        let mut prev_hash = [0u8; 32];
+        for (index, entry) in self.entries.iter().enumerate() {
+            let expected = AuditEntry::compute_hash(entry.sequence, entry.height, &entry.event, &prev_hash)?;
+            if entry.sequence != index as u64 || entry.prev_hash != prev_hash || entry.hash != expected {
+                return Err(AuditError::BrokenChain(index as u64));
+            }
+            prev_hash = entry.hash;
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    pub fn entries_for(&self, proposal_id: ProposalId) -> Vec<&AuditEntry> {
+        self.entries.iter().filter(|entry| entry.proposal_id() == proposal_id).collect()
+    }
+
+    pub fn entries_between(&self, from_height: u64, to_height: u64) -> Vec<&AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.height >= from_height && entry.height <= to_height)
+            .collect()
+    }
+
+    pub fn entries_since(&self, sequence: u64) -> &[AuditEntry] {
+        let start = (sequence as usize).min(self.entries.len());
+        &self.entries[start..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(node_index: u32) -> GovernanceEvent {
+        GovernanceEvent::SignatureAdded {
+            proposal_id: [7; 32],
+            node_index,
+        }
+    }
+
+    #[test]
+    fn test_reopen_after_partial_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("governance.log");
+        {
+            let mut log = GovernanceAuditLog::open(path.clone()).unwrap();
+            log.append(1, event(0)).unwrap();
+            log.append(2, event(1)).unwrap();
+        }
+
+        // Simulate a crash partway through writing a third record
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&100u32.to_le_bytes()).unwrap();
+        file.write_all(&[0xab; 10]).unwrap();
+        drop(file);
+
+        let mut log = GovernanceAuditLog::open(path.clone()).unwrap();
+        assert_eq!(log.len(), 2);
+        log.append(3, event(2)).unwrap();
+
+        let log = GovernanceAuditLog::open(path).unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.entries()[2].height, 3);
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::{HashMap, HashSet};
+
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::Scalar;
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+use super::threshold::ProposalId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VoteChoice {
+    Yes,
+    No,
+    Abstain,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BallotError {
+    #[error("Key is not in the eligibility snapshot")]
+    NotEligible,
+    #[error("No eligible keys hold weight {0}")]
+    EmptyRing(u64),
+    #[error("Ballot is for a different proposal")]
+    WrongProposal,
+    #[error("Invalid ballot signature")]
+    InvalidSignature,
+    #[error("Key image already voted")]
+    DoubleVote,
+    #[error("No anonymous vote open for this proposal")]
+    NoVoteOpen,
+}
+
+// Balances of eligible keys, frozen at a height before voting opens
+#[derive(Debug, Clone)]
+pub struct EligibilitySnapshot {
+    pub proposal_id: ProposalId,
+    pub height: u64,
+    entries: Vec<(RistrettoPoint, u64)>,
+}
+
+impl EligibilitySnapshot {
+    pub fn new(proposal_id: ProposalId, height: u64, mut entries: Vec<(RistrettoPoint, u64)>) -> Self {
+        // Canonical order so every node derives identical rings
+        entries.sort_by_key(|(key, _)| key.compress().to_bytes());
+        entries.dedup_by_key(|(key, _)| key.compress());
+        Self {
+            proposal_id,
+            height,
+            entries,
+        }
+    }
+
+    // A ballot claiming `weight` is signed over every key holding at least that much,
+    // so it proves the weight without saying which key it came from
+    pub fn ring_for(&self, weight: u64) -> Vec<RistrettoPoint> {
+        self.entries
+            .iter()
+            .filter(|(_, balance)| *balance >= weight)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    pub fn weight_of(&self, key: &RistrettoPoint) -> Option<u64> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, weight)| *weight)
+    }
+}
+
+// LSAG ballot. The key image is bound to the proposal, so a voter's ballots on
+// different proposals cannot be linked, but two ballots on the same one can.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ballot {
+    pub proposal_id: ProposalId,
+    pub choice: VoteChoice,
+    pub weight: u64,
+    pub key_image: CompressedRistretto,
+    challenge: Scalar,
+    responses: Vec<Scalar>,
+}
+
+impl Ballot {
+    pub fn sign(
+        snapshot: &EligibilitySnapshot,
+        secret_key: &Scalar,
+        choice: VoteChoice,
+        weight: u64,
+    ) -> Result<Self, BallotError> {
+        let public_key = RISTRETTO_BASEPOINT_POINT * secret_key;
+        let own_weight = snapshot.weight_of(&public_key).ok_or(BallotError::NotEligible)?;
+        if weight > own_weight {
+            return Err(BallotError::NotEligible);
+        }
+
+        let ring = snapshot.ring_for(weight);
+        let n = ring.len();
+        let real_index = ring.iter().position(|key| *key == public_key)
+            .ok_or(BallotError::NotEligible)?;
+
+        let proposal_id = snapshot.proposal_id;
+        let key_image = secret_key * key_base(&proposal_id, &public_key);
+        let message = ballot_message(&proposal_id, choice, weight, &ring);
+
+        let mut rng = OsRng;
+        let alpha = Scalar::random(&mut rng);
+        let mut challenges = vec![Scalar::ZERO; n];
+        let mut responses = vec![Scalar::ZERO; n];
+
+        challenges[(real_index + 1) % n] = challenge(
+            &message,
+            &key_image,
+            &(RISTRETTO_BASEPOINT_POINT * alpha),
+            &(key_base(&proposal_id, &public_key) * alpha),
+        );
+
+        for offset in 1..n {
+            let i = (real_index + offset) % n;
+            responses[i] = Scalar::random(&mut rng);
+            let (l, r) = ring_terms(&proposal_id, &ring[i], &key_image, &responses[i], &challenges[i]);
+            challenges[(i + 1) % n] = challenge(&message, &key_image, &l, &r);
+        }
+
+        // Close the ring at the real signer
+        responses[real_index] = alpha - challenges[real_index] * secret_key;
+
+        Ok(Self {
+            proposal_id,
+            choice,
+            weight,
+            key_image: key_image.compress(),
+            challenge: challenges[0],
+            responses,
+        })
+    }
+
+    pub fn verify(&self, snapshot: &EligibilitySnapshot) -> Result<(), BallotError> {
+        if self.proposal_id != snapshot.proposal_id {
+            return Err(BallotError::WrongProposal);
+        }
+
+        let ring = snapshot.ring_for(self.weight);
+        if ring.is_empty() {
+            return Err(BallotError::EmptyRing(self.weight));
+        }
+        if ring.len() != self.responses.len() {
+            return Err(BallotError::InvalidSignature);
+        }
+        let key_image = self.key_image.decompress().ok_or(BallotError::InvalidSignature)?;
+
+        let message = ballot_message(&self.proposal_id, self.choice, self.weight, &ring);
+        let mut c = self.challenge;
+        for (key, response) in ring.iter().zip(&self.responses) {
+            let (l, r) = ring_terms(&self.proposal_id, key, &key_image, response, &c);
+            c = challenge(&message, &key_image, &l, &r);
+        }
+
+        if c == self.challenge {
+            Ok(())
+        } else {
+            Err(BallotError::InvalidSignature)
+        }
+    }
+}
+
+fn key_base(proposal_id: &ProposalId, key: &RistrettoPoint) -> RistrettoPoint {
+    let mut bytes = b"idia-ballot-key-image".to_vec();
+    bytes.extend_from_slice(proposal_id);
+    bytes.extend_from_slice(key.compress().as_bytes());
+    RistrettoPoint::hash_from_bytes::<Sha512>(&bytes)
+}
+
+fn ring_terms(
+    proposal_id: &ProposalId,
+    key: &RistrettoPoint,
+    key_image: &RistrettoPoint,
+    response: &Scalar,
+    challenge: &Scalar,
+) -> (RistrettoPoint, RistrettoPoint) {
+    (
+        RISTRETTO_BASEPOINT_POINT * response + key * challenge,
+        key_base(proposal_id, key) * response + key_image * challenge,
+    )
+}
+
+fn ballot_message(proposal_id: &ProposalId, choice: VoteChoice, weight: u64, ring: &[RistrettoPoint]) -> Vec<u8> {
+    let mut message = b"idia-ballot-v1".to_vec();
+    message.extend_from_slice(proposal_id);
+    message.push(choice as u8);
+    message.extend_from_slice(&weight.to_le_bytes());
+    for key in ring {
+        message.extend_from_slice(key.compress().as_bytes());
+    }
+    message
+}
+
+fn challenge(message: &[u8], key_image: &RistrettoPoint, l: &RistrettoPoint, r: &RistrettoPoint) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(message);
+    hasher.update(key_image.compress().as_bytes());
+    hasher.update(l.compress().as_bytes());
+    hasher.update(r.compress().as_bytes());
+    Scalar::from_hash(hasher)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VoteTally {
+    pub totals: HashMap<VoteChoice, u64>,
+    pub ballots: usize,
+    pub double_votes: usize,
+}
+
+impl VoteTally {
+    pub fn weight_for(&self, choice: VoteChoice) -> u64 {
+        self.totals.get(&choice).copied().unwrap_or(0)
+    }
+}
+
+pub struct BallotBox {
+    snapshot: EligibilitySnapshot,
+    key_images: HashSet<CompressedRistretto>,
+    tally: VoteTally,
+}
+
+impl BallotBox {
+    pub fn new(snapshot: EligibilitySnapshot) -> Self {
+        Self {
+            snapshot,
+            key_images: HashSet::new(),
+            tally: VoteTally::default(),
+        }
+    }
+
+    pub fn snapshot(&self) -> &EligibilitySnapshot {
+        &self.snapshot
+    }
+
+    pub fn cast(&mut self, ballot: &Ballot) -> Result<(), BallotError> {
+        ballot.verify(&self.snapshot)?;
+
+        // The first ballot per key image counts; later ones are recorded as double votes
+        if !self.key_images.insert(ballot.key_image) {
+            self.tally.double_votes += 1;
+            return Err(BallotError::DoubleVote);
+        }
+
+        *self.tally.totals.entry(ballot.choice).or_default() += ballot.weight;
+        self.tally.ballots += 1;
+        Ok(())
+    }
+
+    pub fn tally(&self) -> &VoteTally {
+        &self.tally
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Four voters holding 10, 20, 30 and 40
+    fn setup(proposal_id: ProposalId) -> (EligibilitySnapshot, Vec<Scalar>) {
+        let keys: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut OsRng)).collect();
+        let entries = keys
+            .iter()
+            .zip([10, 20, 30, 40])
+            .map(|(key, balance)| (RISTRETTO_BASEPOINT_POINT * key, balance))
+            .collect();
+        (EligibilitySnapshot::new(proposal_id, 100, entries), keys)
+    }
+
+    #[test]
+    fn test_ballot_round_trip() {
+        let (snapshot, keys) = setup([1u8; 32]);
+        let mut ballot_box = BallotBox::new(snapshot.clone());
+
+        for (key, weight) in keys.iter().zip([10, 15, 30, 40]) {
+            let ballot = Ballot::sign(&snapshot, key, VoteChoice::Yes, weight).unwrap();
+            ballot.verify(&snapshot).unwrap();
+            ballot_box.cast(&ballot).unwrap();
+        }
+        assert_eq!(ballot_box.tally().weight_for(VoteChoice::Yes), 95);
+        assert_eq!(ballot_box.tally().ballots, 4);
+    }
+
+    #[test]
+    fn test_ballot_rejects_tampering() {
+        let (snapshot, keys) = setup([1u8; 32]);
+        let ballot = Ballot::sign(&snapshot, &keys[2], VoteChoice::No, 25).unwrap();
+
+        let mut tampered = ballot.clone();
+        tampered.choice = VoteChoice::Yes;
+        assert!(matches!(tampered.verify(&snapshot), Err(BallotError::InvalidSignature)));
+
+        // A different weight selects a different ring
+        let mut tampered = ballot.clone();
+        tampered.weight = 30;
+        assert!(matches!(tampered.verify(&snapshot), Err(BallotError::InvalidSignature)));
+
+        let mut tampered = ballot.clone();
+        tampered.responses[0] += Scalar::ONE;
+        assert!(matches!(tampered.verify(&snapshot), Err(BallotError::InvalidSignature)));
+
+        let mut tampered = ballot.clone();
+        tampered.key_image = (RISTRETTO_BASEPOINT_POINT * keys[2]).compress();
+        assert!(matches!(tampered.verify(&snapshot), Err(BallotError::InvalidSignature)));
+
+        let (other, _) = setup([2u8; 32]);
+        assert!(matches!(ballot.verify(&other), Err(BallotError::WrongProposal)));
+    }
+
+    #[test]
+    fn test_ballot_rejects_ineligible_weight() {
+        let (snapshot, keys) = setup([1u8; 32]);
+        assert!(matches!(
+            Ballot::sign(&snapshot, &keys[0], VoteChoice::Yes, 11),
+            Err(BallotError::NotEligible)
+        ));
+        assert!(matches!(
+            Ballot::sign(&snapshot, &Scalar::random(&mut OsRng), VoteChoice::Yes, 1),
+            Err(BallotError::NotEligible)
+        ));
+    }
+
+    #[test]
+    fn test_double_vote_is_linked() {
+        let (snapshot, keys) = setup([1u8; 32]);
+        let mut ballot_box = BallotBox::new(snapshot.clone());
+        ballot_box.cast(&Ballot::sign(&snapshot, &keys[3], VoteChoice::Yes, 40).unwrap()).unwrap();
+
+        // Another choice and a smaller ring still carry the same key image
+        let again = Ballot::sign(&snapshot, &keys[3], VoteChoice::No, 5).unwrap();
+        assert!(matches!(ballot_box.cast(&again), Err(BallotError::DoubleVote)));
+        assert_eq!(ballot_box.tally().double_votes, 1);
+        assert_eq!(ballot_box.tally().weight_for(VoteChoice::No), 0);
+
+        // The same key voting on another proposal cannot be linked to it
+        let (mut other, _) = setup([2u8; 32]);
+        other.entries.push((RISTRETTO_BASEPOINT_POINT * keys[3], 40));
+        let elsewhere = Ballot::sign(&other, &keys[3], VoteChoice::Yes, 40).unwrap();
+        assert_ne!(elsewhere.key_image, again.key_image);
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+
+use idia_core::GovernanceRecord;
+use threshold_crypto::{Signature, SignatureShare};
+
+use super::threshold::{
+    GovernanceError, GovernanceProposal, ProposalId, ProposalState, ThresholdGovernance,
+};
+
+impl ThresholdGovernance {
+    // How far behind the including block a submission's declared height may lag
+    pub const MAX_SUBMISSION_DELAY: u64 = 100;
+
+    pub fn submission_record(proposal: &GovernanceProposal) -> GovernanceRecord {
+        GovernanceRecord::ProposalSubmission {
+            proposal_id: proposal.id,
+            payload: proposal.canonical_bytes(),
+        }
+    }
+
+    pub fn share_record(proposal_id: ProposalId, node_index: u32, share: &SignatureShare) -> GovernanceRecord {
+        GovernanceRecord::SignatureShare {
+            proposal_id,
+            node_index,
+            share: share.to_bytes().to_vec(),
+        }
+    }
+
+    pub fn veto_share_record(proposal_id: ProposalId, node_index: u32, share: &SignatureShare) -> GovernanceRecord {
+        GovernanceRecord::VetoShare {
+            proposal_id,
+            node_index,
+            share: share.to_bytes().to_vec(),
+        }
+    }
+
+    pub fn guardian_veto_record(proposal_id: ProposalId, signature: &Signature) -> GovernanceRecord {
+        GovernanceRecord::GuardianVeto {
+            proposal_id,
+            signature: signature.to_bytes().to_vec(),
+        }
+    }
+
+    pub fn validate_record(&self, record: &GovernanceRecord, height: u64) -> Result<(), GovernanceError> {
+        if !record.verify() {
+            return Err(GovernanceError::InvalidRecord("malformed record".into()));
+        }
+
+        match record {
+            GovernanceRecord::ProposalSubmission { proposal_id, payload } => {
+                let proposal = GovernanceProposal::from_canonical_bytes(payload)?;
+                if proposal.id != *proposal_id {
+                    return Err(GovernanceError::InvalidRecord("proposal ID mismatch".into()));
+                }
+                if proposal.submitted_height > height
+                    || height - proposal.submitted_height > Self::MAX_SUBMISSION_DELAY
+                {
+                    return Err(GovernanceError::InvalidRecord("submission height out of range".into()));
+                }
+                // Every node must agree on the threshold, so it has to match local policy
+                if proposal.threshold != self.required_signatures(&proposal.proposed_change) {
+                    return Err(GovernanceError::InvalidRecord("threshold does not match policy".into()));
+                }
+                self.validate_change(&proposal.proposed_change)?;
+                proposal.validate_contents()?;
+                if self.proposal(proposal.id).is_some() {
+                    return Err(GovernanceError::DuplicateProposal);
+                }
+            }
+            GovernanceRecord::SignatureShare { proposal_id, node_index, share } => {
+                let proposal = self.proposal(*proposal_id)
+                    .ok_or(GovernanceError::ProposalNotFound)?;
+                if proposal.state != ProposalState::Active {
+                    return Err(GovernanceError::InvalidProposalState);
+                }
+                if proposal.signatures.contains_key(node_index) {
+                    return Err(GovernanceError::InvalidRecord("duplicate signature share".into()));
+                }
+
+                let share = decode_share(share)?;
+                if !self.verify_share(*proposal_id, *node_index, &share) {
+                    return Err(GovernanceError::InvalidSignatureShare(*node_index));
+                }
+            }
+            GovernanceRecord::VetoShare { proposal_id, node_index, share } => {
+                let proposal = self.proposal(*proposal_id)
+                    .ok_or(GovernanceError::ProposalNotFound)?;
+                if !proposal.state.is_vetoable() {
+                    return Err(GovernanceError::InvalidProposalState);
+                }
+                if proposal.veto_signatures.contains_key(node_index) {
+                    return Err(GovernanceError::InvalidRecord("duplicate veto share".into()));
+                }
+
+                let share = decode_share(share)?;
+                if !self.verify_veto_share(*proposal_id, *node_index, &share) {
+                    return Err(GovernanceError::InvalidSignatureShare(*node_index));
+                }
+            }
+            GovernanceRecord::GuardianVeto { proposal_id, signature } => {
+                let proposal = self.proposal(*proposal_id)
+                    .ok_or(GovernanceError::ProposalNotFound)?;
+                if !proposal.state.is_vetoable() {
+                    return Err(GovernanceError::InvalidProposalState);
+                }
+                self.check_guardian_signature(*proposal_id, &decode_signature(signature)?)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Validates every record before touching state so a bad block leaves governance untouched.
+    // Only shares in the block are applied. Returns shares this node signed automatically as a
+    // delegator; submit them in a transaction (`Transaction::with_governance`) through the
+    // mempool, and they count once a later block includes them.
+    pub fn apply_block_records(
+        &mut self,
+        height: u64,
+        records: &[GovernanceRecord],
+    ) -> Result<Vec<GovernanceRecord>, GovernanceError> {
+        let mut submitted = HashSet::new();
+        let mut signed = HashSet::new();
+        let mut vetoed = HashSet::new();
+        for record in records {
+            self.validate_record(record, height)?;
+
+            let fresh = match record {
+                GovernanceRecord::ProposalSubmission { proposal_id, .. } => submitted.insert(*proposal_id),
+                GovernanceRecord::SignatureShare { proposal_id, node_index, .. } => {
+                    signed.insert((*proposal_id, *node_index))
+                }
+                GovernanceRecord::VetoShare { proposal_id, node_index, .. } => {
+                    vetoed.insert((*proposal_id, Some(*node_index)))
+                }
+                GovernanceRecord::GuardianVeto { proposal_id, .. } => vetoed.insert((*proposal_id, None)),
+            };
+            if !fresh {
+                return Err(GovernanceError::InvalidRecord("duplicate record in block".into()));
+            }
+        }
+
+        let mut published = Vec::new();
+        for record in records {
+            match record {
+                GovernanceRecord::ProposalSubmission { payload, .. } => {
+                    self.insert_proposal(GovernanceProposal::from_canonical_bytes(payload)?)?;
+                }
+                GovernanceRecord::SignatureShare { proposal_id, node_index, share } => {
+                    // An earlier share in this block may already have approved the proposal
+                    if !self.is_active(*proposal_id) {
+                        continue;
+                    }
+                    let share = decode_share(share)?;
+                    if let Some(own_share) = self.add_signature_share(*proposal_id, *node_index, share)? {
+                        published.push(Self::share_record(*proposal_id, self.node_index(), &own_share));
+                    }
+                }
+                GovernanceRecord::VetoShare { proposal_id, node_index, share } => {
+                    // Likewise a veto may already have gone through earlier in the block
+                    if !self.proposal(*proposal_id).map_or(false, |p| p.state.is_vetoable()) {
+                        continue;
+                    }
+                    self.add_veto_share(*proposal_id, *node_index, decode_share(share)?)?;
+                }
+                GovernanceRecord::GuardianVeto { proposal_id, signature } => {
+                    if !self.proposal(*proposal_id).map_or(false, |p| p.state.is_vetoable()) {
+                        continue;
+                    }
+                    self.guardian_veto(*proposal_id, &decode_signature(signature)?)?;
+                }
+            }
+        }
+
+        Ok(published)
+    }
+}
+
+fn decode_share(bytes: &[u8]) -> Result<SignatureShare, GovernanceError> {
+    let bytes: [u8; 96] = bytes.try_into()
+        .map_err(|_| GovernanceError::InvalidRecord("bad signature share length".into()))?;
+    SignatureShare::from_bytes(bytes)
+        .map_err(|_| GovernanceError::InvalidRecord("bad signature share encoding".into()))
+}
+
+fn decode_signature(bytes: &[u8]) -> Result<Signature, GovernanceError> {
+    let bytes: [u8; 96] = bytes.try_into()
+        .map_err(|_| GovernanceError::InvalidRecord("bad signature length".into()))?;
+    Signature::from_bytes(bytes)
+        .map_err(|_| GovernanceError::InvalidRecord("bad signature encoding".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::governance::delegation::DelegationScope;
+    use crate::governance::threshold::ProposedChange;
+    use threshold_crypto::SecretKeySet;
+
+    #[test]
+    fn test_apply_own_delegated_share() {
+        let keys = SecretKeySet::random(1, &mut rand::thread_rng());
+        let mut node = ThresholdGovernance::new(keys.public_keys(), keys.secret_key_share(0), 0, 4);
+        node.delegate_vote(0, 1, DelegationScope::AllProposals).unwrap();
+
+        let proposal = node.build_proposal(
+            "Upgrade".into(),
+            "Activate v2".into(),
+            ProposedChange::ProtocolUpgrade { version: "2.0.0".into(), activation_height: 500 },
+            100,
+            None,
+        );
+        let proposal_id = proposal.id;
+        node.apply_block_records(1, &[ThresholdGovernance::submission_record(&proposal)]).unwrap();
+        node.on_new_block(1);
+
+        // Our delegate signs; our own share comes back for publishing but isn't applied yet
+        let delegate_share = keys.secret_key_share(1).sign(proposal_id);
+        let published = node
+            .apply_block_records(2, &[ThresholdGovernance::share_record(proposal_id, 1, &delegate_share)])
+            .unwrap();
+        assert_eq!(published.len(), 1);
+        assert_eq!(node.proposal(proposal_id).unwrap().signatures.len(), 1);
+
+        // The block carrying it applies cleanly, like on every other node
+        assert!(node.apply_block_records(3, &published).unwrap().is_empty());
+        let proposal = node.proposal(proposal_id).unwrap();
+        assert_eq!(proposal.signatures.len(), 2);
+        assert!(proposal.signatures.contains_key(&0));
+    }
+}
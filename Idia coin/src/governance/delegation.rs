@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use super::threshold::{GovernanceError, ProposalId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DelegationScope {
+    AllProposals,
+    Proposal(ProposalId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoteDelegation {
+    pub delegator: u32,
+    pub delegate: u32,
+    pub scope: DelegationScope,
+}
+
+// Delegations are public committee state: a delegator's node contributes its own
+// signature share as soon as its resolved delegate signs, so the threshold
+// signature still combines real shares.
+pub struct VoteDelegations {
+    delegations: HashMap<(u32, DelegationScope), u32>,
+    max_chain_depth: usize,
+}
+
+impl VoteDelegations {
+    pub const DEFAULT_MAX_CHAIN_DEPTH: usize = 3;
+
+    pub fn new() -> Self {
+        Self {
+            delegations: HashMap::new(),
+            max_chain_depth: Self::DEFAULT_MAX_CHAIN_DEPTH,
+        }
+    }
+
+    pub fn with_max_chain_depth(mut self, depth: usize) -> Self {
+        self.max_chain_depth = depth;
+        self
+    }
+
+    pub fn delegate(
+        &mut self,
+        delegator: u32,
+        delegate: u32,
+        scope: DelegationScope,
+    ) -> Result<(), GovernanceError> {
+        if delegator == delegate {
+            return Err(GovernanceError::InvalidDelegation("cannot delegate to self".into()));
+        }
+
+        let previous = self.delegations.insert((delegator, scope), delegate);
+
+        // Validate the resulting chain against every proposal this scope can affect
+        let proposal = match scope {
+            DelegationScope::Proposal(id) => Some(id),
+            DelegationScope::AllProposals => None,
+        };
+        if let Err(e) = self.chain_from(delegator, proposal) {
+            match previous {
+                Some(old) => self.delegations.insert((delegator, scope), old),
+                None => self.delegations.remove(&(delegator, scope)),
+            };
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub fn revoke(&mut self, delegator: u32, scope: DelegationScope) -> Option<u32> {
+        self.delegations.remove(&(delegator, scope))
+    }
+
+    pub fn delegate_of(&self, delegator: u32, proposal: Option<ProposalId>) -> Option<u32> {
+        // A proposal-specific delegation overrides the blanket one
+        proposal
+            .and_then(|id| self.delegations.get(&(delegator, DelegationScope::Proposal(id))))
+            .or_else(|| self.delegations.get(&(delegator, DelegationScope::AllProposals)))
+            .copied()
+    }
+
+    pub fn resolve(&self, delegator: u32, proposal: ProposalId) -> Option<u32> {
+        self.chain_from(delegator, Some(proposal)).ok()?.last().copied()
+    }
+
+    pub fn delegators_of(&self, delegate: u32, proposal: ProposalId) -> Vec<u32> {
+        let mut delegators: Vec<u32> = self.delegations
+            .keys()
+            .map(|(delegator, _)| *delegator)
+            .filter(|delegator| self.resolve(*delegator, proposal) == Some(delegate))
+            .collect();
+        delegators.sort_unstable();
+        delegators.dedup();
+        delegators
+    }
+
+    pub fn voting_power(&self, participant: u32, proposal: ProposalId) -> usize {
+        1 + self.delegators_of(participant, proposal).len()
+    }
+
+    fn chain_from(&self, delegator: u32, proposal: Option<ProposalId>) -> Result<Vec<u32>, GovernanceError> {
+        let mut chain = Vec::new();
+        let mut current = delegator;
+
+        while let Some(next) = self.delegate_of(current, proposal) {
+            if next == delegator || chain.contains(&next) {
+                return Err(GovernanceError::InvalidDelegation("delegation cycle".into()));
+            }
+            chain.push(next);
+            if chain.len() > self.max_chain_depth {
+                return Err(GovernanceError::InvalidDelegation("delegation chain too long".into()));
+            }
+            current = next;
+        }
+
+        Ok(chain)
+    }
+}
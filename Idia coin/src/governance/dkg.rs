@@ -0,0 +1,592 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use threshold_crypto::ff::{Field, PrimeField};
+use threshold_crypto::group::CurveAffine;
+use threshold_crypto::poly::{Commitment, Poly};
+use threshold_crypto::serde_impl::{FieldWrap, SerdeSecret};
+use threshold_crypto::{Ciphertext, Fr, G1Affine, IntoFr, PublicKey, PublicKeySet, SecretKey, SecretKeyShare};
+
+use super::threshold::ThresholdGovernance;
+
+// Joint-Feldman DKG: every participant deals a random polynomial, shares are
+// checked against the dealer's public commitment, and the group key is the sum
+// over all dealers that were not disqualified. No single party learns the
+// group secret.
+//
+// The same protocol reshares an existing key: old members deal polynomials
+// whose constant term is their current share, and new members combine the
+// deals with Lagrange weights, so the group public key survives a change of
+// committee or a proactive refresh.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DkgMessage {
+    // Broadcast by each dealer; share j is encrypted to participant j's transport key
+    Deal {
+        session: u64,
+        dealer: u32,
+        commitment: Commitment,
+        encrypted_shares: Vec<Ciphertext>,
+    },
+    // Broadcast when the share received from `dealer` fails verification
+    Complaint {
+        session: u64,
+        complainer: u32,
+        dealer: u32,
+    },
+    // The dealer answers a complaint by revealing the disputed share publicly
+    Justification {
+        session: u64,
+        dealer: u32,
+        complainer: u32,
+        share: FieldWrap<Fr>,
+    },
+}
+
+impl DkgMessage {
+    pub fn session(&self) -> u64 {
+        match self {
+            DkgMessage::Deal { session, .. }
+            | DkgMessage::Complaint { session, .. }
+            | DkgMessage::Justification { session, .. } => *session,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, DkgError> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DkgError> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DkgError {
+    #[error("Unknown participant {0}")]
+    UnknownParticipant(u32),
+    #[error("Message for session {got}, expected {expected}")]
+    WrongSession { expected: u64, got: u64 },
+    #[error("Malformed deal from participant {0}")]
+    MalformedDeal(u32),
+    #[error("Duplicate deal from participant {0}")]
+    DuplicateDeal(u32),
+    #[error("Invalid justification from participant {0}")]
+    InvalidJustification(u32),
+    #[error("Only {qualified} qualified dealers, need more than {threshold}")]
+    NotEnoughDealers { qualified: usize, threshold: usize },
+    #[error("DKG already finished")]
+    AlreadyFinished,
+    #[error("This node does not deal in session {0}")]
+    NotADealer(u64),
+    #[error("This node receives no share in session {0}")]
+    NotARecipient(u64),
+    #[error("No verified share from qualified dealer {0}")]
+    MissingShare(u32),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DkgPhase {
+    Dealing,
+    Finished,
+}
+
+pub struct DkgOutput {
+    pub public_key_set: PublicKeySet,
+    pub secret_key_share: SecretKeyShare,
+    pub qualified: BTreeSet<u32>,
+}
+
+impl DkgOutput {
+    pub fn into_governance(self, node_index: u32, committee_size: u32) -> ThresholdGovernance {
+        ThresholdGovernance::new(self.public_key_set, self.secret_key_share, node_index, committee_size)
+    }
+}
+
+// Committees reshare once per epoch, using the epoch number as the session ID
+pub const RESHARING_EPOCH_BLOCKS: u64 = 20_160;
+
+pub fn resharing_epoch_at(height: u64) -> Option<u64> {
+    (height > 0 && height % RESHARING_EPOCH_BLOCKS == 0).then(|| height / RESHARING_EPOCH_BLOCKS)
+}
+
+pub struct DkgSession {
+    session: u64,
+    // Our index among share recipients and among dealers; they differ when resharing
+    our_index: Option<u32>,
+    our_dealer_index: Option<u32>,
+    threshold: usize,
+    transport_key: SecretKey,
+    participants: Vec<PublicKey>,
+    dealer_count: usize,
+    // Key being reshared, if this is not a fresh DKG
+    previous: Option<PublicKeySet>,
+    our_poly: Option<Poly>,
+    commitments: BTreeMap<u32, Commitment>,
+    // Verified shares dealt to us, by dealer
+    shares: BTreeMap<u32, Fr>,
+    // Outstanding complaints: dealer -> complainers still waiting on a justification
+    complaints: BTreeMap<u32, BTreeSet<u32>>,
+    disqualified: BTreeSet<u32>,
+    phase: DkgPhase,
+}
+
+impl DkgSession {
+    // `threshold` is the polynomial degree: threshold + 1 shares sign
+    pub fn new(
+        session: u64,
+        our_index: u32,
+        threshold: usize,
+        transport_key: SecretKey,
+        participants: Vec<PublicKey>,
+    ) -> Self {
+        let dealer_count = participants.len();
+        Self {
+            session,
+            our_index: Some(our_index),
+            our_dealer_index: Some(our_index),
+            threshold,
+            transport_key,
+            participants,
+            dealer_count,
+            previous: None,
+            our_poly: None,
+            commitments: BTreeMap::new(),
+            shares: BTreeMap::new(),
+            complaints: BTreeMap::new(),
+            disqualified: BTreeSet::new(),
+            phase: DkgPhase::Dealing,
+        }
+    }
+
+    // `old_index` is our index in the outgoing committee, `new_index` in the incoming one;
+    // members joining only receive, members leaving only deal
+    pub fn resharing(
+        session: u64,
+        previous: PublicKeySet,
+        old_committee_size: usize,
+        old_index: Option<u32>,
+        new_index: Option<u32>,
+        threshold: usize,
+        transport_key: SecretKey,
+        participants: Vec<PublicKey>,
+    ) -> Self {
+        Self {
+            session,
+            our_index: new_index,
+            our_dealer_index: old_index,
+            threshold,
+            transport_key,
+            participants,
+            dealer_count: old_committee_size,
+            previous: Some(previous),
+            our_poly: None,
+            commitments: BTreeMap::new(),
+            shares: BTreeMap::new(),
+            complaints: BTreeMap::new(),
+            disqualified: BTreeSet::new(),
+            phase: DkgPhase::Dealing,
+        }
+    }
+
+    pub fn phase(&self) -> DkgPhase {
+        self.phase
+    }
+
+    pub fn session(&self) -> u64 {
+        self.session
+    }
+
+    pub fn participant_count(&self) -> usize {
+        self.participants.len()
+    }
+
+    // Produces our deal; the caller broadcasts it. Our own share is applied immediately.
+    pub fn start(&mut self) -> Result<DkgMessage, DkgError> {
+        if self.previous.is_some() {
+            return Err(DkgError::NotADealer(self.session));
+        }
+        let poly = Poly::random(self.threshold, &mut rand::thread_rng());
+        self.deal(poly)
+    }
+
+    // Deals a fresh polynomial whose constant term is our current share of the old key
+    pub fn start_resharing(&mut self, current_share: &SecretKeyShare) -> Result<DkgMessage, DkgError> {
+        if self.previous.is_none() || self.our_dealer_index.is_none() {
+            return Err(DkgError::NotADealer(self.session));
+        }
+
+        let mut poly = Poly::random(self.threshold, &mut rand::thread_rng());
+        let mut offset = share_scalar(current_share)?;
+        offset.sub_assign(&poly.evaluate(0));
+        poly += Poly::constant(offset);
+        self.deal(poly)
+    }
+
+    fn deal(&mut self, poly: Poly) -> Result<DkgMessage, DkgError> {
+        if self.phase != DkgPhase::Dealing {
+            return Err(DkgError::AlreadyFinished);
+        }
+        let dealer = self.our_dealer_index.ok_or(DkgError::NotADealer(self.session))?;
+
+        let commitment = poly.commitment();
+        let encrypted_shares = self.participants
+            .iter()
+            .enumerate()
+            .map(|(j, key)| {
+                let share = poly.evaluate(j + 1);
+                Ok(key.encrypt(bincode::serialize(&FieldWrap(share))?))
+            })
+            .collect::<Result<Vec<_>, DkgError>>()?;
+
+        self.our_poly = Some(poly);
+        let deal = DkgMessage::Deal {
+            session: self.session,
+            dealer,
+            commitment,
+            encrypted_shares,
+        };
+        self.handle_message(deal.clone())?;
+        Ok(deal)
+    }
+
+    // Returns a message to broadcast in response, if any
+    pub fn handle_message(&mut self, message: DkgMessage) -> Result<Option<DkgMessage>, DkgError> {
+        if message.session() != self.session {
+            return Err(DkgError::WrongSession {
+                expected: self.session,
+                got: message.session(),
+            });
+        }
+        if self.phase != DkgPhase::Dealing {
+            return Err(DkgError::AlreadyFinished);
+        }
+
+        match message {
+            DkgMessage::Deal { dealer, commitment, encrypted_shares, .. } => {
+                self.handle_deal(dealer, commitment, encrypted_shares)
+            }
+            DkgMessage::Complaint { complainer, dealer, .. } => {
+                self.handle_complaint(complainer, dealer)
+            }
+            DkgMessage::Justification { dealer, complainer, share, .. } => {
+                self.handle_justification(dealer, complainer, share.0)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn handle_deal(
+        &mut self,
+        dealer: u32,
+        commitment: Commitment,
+        encrypted_shares: Vec<Ciphertext>,
+    ) -> Result<Option<DkgMessage>, DkgError> {
+        self.check_dealer(dealer)?;
+        if self.commitments.contains_key(&dealer) {
+            return Err(DkgError::DuplicateDeal(dealer));
+        }
+
+        // When resharing, the deal must commit to the dealer's existing public key share
+        let keeps_share = self.previous.as_ref().map_or(true, |previous| {
+            commitment.evaluate(0) == previous.commitment().evaluate(dealer as usize + 1)
+        });
+        if !keeps_share
+            || commitment.degree() != self.threshold
+            || encrypted_shares.len() != self.participants.len()
+        {
+            self.disqualified.insert(dealer);
+            return Err(DkgError::MalformedDeal(dealer));
+        }
+        self.commitments.insert(dealer, commitment);
+
+        let Some(our_index) = self.our_index else {
+            return Ok(None);
+        };
+
+        // Decrypt and check our share; a bad one is disputed publicly rather than dropped
+        let ciphertext = &encrypted_shares[our_index as usize];
+        let share = ciphertext.verify()
+            .then(|| self.transport_key.decrypt(ciphertext))
+            .flatten()
+            .and_then(|bytes| bincode::deserialize::<FieldWrap<Fr>>(&bytes).ok())
+            .map(|wrapped| wrapped.0)
+            .filter(|share| self.share_matches(dealer, our_index, share));
+
+        match share {
+            Some(share) => {
+                self.shares.insert(dealer, share);
+                Ok(None)
+            }
+            None => {
+                self.complaints.entry(dealer).or_default().insert(our_index);
+                Ok(Some(DkgMessage::Complaint {
+                    session: self.session,
+                    complainer: our_index,
+                    dealer,
+                }))
+            }
+        }
+    }
+
+    fn handle_complaint(&mut self, complainer: u32, dealer: u32) -> Result<Option<DkgMessage>, DkgError> {
+        self.check_recipient(complainer)?;
+        self.check_dealer(dealer)?;
+        self.complaints.entry(dealer).or_default().insert(complainer);
+
+        if Some(dealer) != self.our_dealer_index {
+            return Ok(None);
+        }
+        let Some(poly) = &self.our_poly else {
+            return Ok(None);
+        };
+
+        // We broadcast the justification, so we apply it as every other node will
+        let share = poly.evaluate(complainer as usize + 1);
+        self.handle_justification(dealer, complainer, share)?;
+        Ok(Some(DkgMessage::Justification {
+            session: self.session,
+            dealer,
+            complainer,
+            share: FieldWrap(share),
+        }))
+    }
+
+    fn handle_justification(&mut self, dealer: u32, complainer: u32, share: Fr) -> Result<(), DkgError> {
+        self.check_dealer(dealer)?;
+        self.check_recipient(complainer)?;
+
+        if !self.share_matches(dealer, complainer, &share) {
+            self.disqualified.insert(dealer);
+            return Err(DkgError::InvalidJustification(dealer));
+        }
+
+        if let Some(complainers) = self.complaints.get_mut(&dealer) {
+            complainers.remove(&complainer);
+        }
+        if Some(complainer) == self.our_index {
+            self.shares.insert(dealer, share);
+        }
+        Ok(())
+    }
+
+    fn share_matches(&self, dealer: u32, recipient: u32, share: &Fr) -> bool {
+        // Feldman check: g^share must equal the committed polynomial at the recipient's point
+        self.commitments
+            .get(&dealer)
+            .map_or(false, |c| c.evaluate(recipient as usize + 1) == G1Affine::one().mul(*share))
+    }
+
+    fn check_recipient(&self, index: u32) -> Result<(), DkgError> {
+        if (index as usize) < self.participants.len() {
+            Ok(())
+        } else {
+            Err(DkgError::UnknownParticipant(index))
+        }
+    }
+
+    fn check_dealer(&self, index: u32) -> Result<(), DkgError> {
+        if (index as usize) < self.dealer_count {
+            Ok(())
+        } else {
+            Err(DkgError::UnknownParticipant(index))
+        }
+    }
+
+    pub fn qualified(&self) -> BTreeSet<u32> {
+        // Only broadcast messages count, so every node derives the same set: dealers
+        // that never dealt, dealt malformed or left a complaint unanswered are excluded.
+        // A bad share we received ourselves is excluded through our own complaint.
+        self.commitments
+            .keys()
+            .copied()
+            .filter(|dealer| !self.disqualified.contains(dealer))
+            .filter(|dealer| self.complaints.get(dealer).map_or(true, |c| c.is_empty()))
+            .collect()
+    }
+
+    fn share_from(&self, dealer: u32) -> Result<Fr, DkgError> {
+        self.shares.get(&dealer).copied().ok_or(DkgError::MissingShare(dealer))
+    }
+
+    // Called once the dealing window has closed
+    pub fn finalize(&mut self) -> Result<DkgOutput, DkgError> {
+        if self.phase != DkgPhase::Dealing {
+            return Err(DkgError::AlreadyFinished);
+        }
+
+        if self.our_index.is_none() {
+            return Err(DkgError::NotARecipient(self.session));
+        }
+
+        let mut qualified = self.qualified();
+        let threshold = self.previous.as_ref().map_or(self.threshold, |p| p.threshold());
+        if qualified.len() <= threshold {
+            return Err(DkgError::NotEnoughDealers {
+                qualified: qualified.len(),
+                threshold,
+            });
+        }
+
+        let mut commitment = Poly::zero().commitment();
+        let mut secret = Fr::zero();
+        if self.previous.is_some() {
+            // Any threshold + 1 old shares determine the old secret; every node picks the same ones
+            qualified = qualified.into_iter().take(threshold + 1).collect();
+            for dealer in &qualified {
+                let weight = lagrange_at_zero(*dealer, &qualified);
+                commitment += &scale_commitment(&self.commitments[dealer], &weight);
+
+                let mut share = self.share_from(*dealer)?;
+                share.mul_assign(&weight);
+                secret.add_assign(&share);
+            }
+        } else {
+            for dealer in &qualified {
+                commitment += &self.commitments[dealer];
+                secret.add_assign(&self.share_from(*dealer)?);
+            }
+        }
+
+        self.phase = DkgPhase::Finished;
+        self.our_poly = None;
+        Ok(DkgOutput {
+            public_key_set: PublicKeySet::from(commitment),
+            secret_key_share: SecretKeyShare::from_mut(&mut secret),
+            qualified,
+        })
+    }
+}
+
+// SecretKeyShare does not expose its scalar; its secret serialization is the field element
+fn share_scalar(share: &SecretKeyShare) -> Result<Fr, DkgError> {
+    let bytes = bincode::serialize(&SerdeSecret(share))?;
+    Ok(bincode::deserialize::<FieldWrap<Fr>>(&bytes)?.0)
+}
+
+// Lagrange coefficient for `index` when interpolating the set at x = 0 (points are index + 1)
+fn lagrange_at_zero(index: u32, set: &BTreeSet<u32>) -> Fr {
+    let xi = (index as u64 + 1).into_fr();
+    let mut numerator = Fr::one();
+    let mut denominator = Fr::one();
+    for other in set.iter().filter(|other| **other != index) {
+        let xj = (*other as u64 + 1).into_fr();
+        numerator.mul_assign(&xj);
+        let mut diff = xj;
+        diff.sub_assign(&xi);
+        denominator.mul_assign(&diff);
+    }
+    // Distinct points, so the denominator is never zero
+    numerator.mul_assign(&denominator.inverse().expect("distinct interpolation points"));
+    numerator
+}
+
+// Commitments only support addition, so scale by double-and-add over the scalar's bits
+fn scale_commitment(commitment: &Commitment, scalar: &Fr) -> Commitment {
+    let mut result = Poly::zero().commitment();
+    let mut addend = commitment.clone();
+    for limb in scalar.into_repr().as_ref() {
+        for bit in 0..64 {
+            if (limb >> bit) & 1 == 1 {
+                result += &addend;
+            }
+            let doubled = addend.clone();
+            addend += &doubled;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMITTEE: usize = 4;
+
+    fn sessions() -> Vec<DkgSession> {
+        let keys: Vec<SecretKey> = (0..COMMITTEE).map(|_| SecretKey::random()).collect();
+        let participants: Vec<PublicKey> = keys.iter().map(SecretKey::public_key).collect();
+        keys.into_iter()
+            .enumerate()
+            .map(|(i, key)| DkgSession::new(7, i as u32, 1, key, participants.clone()))
+            .collect()
+    }
+
+    // Delivers `message` to everyone but its sender, collecting their responses by sender
+    fn broadcast(sessions: &mut [DkgSession], from: usize, message: &DkgMessage) -> Vec<(usize, DkgMessage)> {
+        let mut responses = Vec::new();
+        for (i, session) in sessions.iter_mut().enumerate().filter(|(i, _)| *i != from) {
+            if let Some(response) = session.handle_message(message.clone()).unwrap() {
+                responses.push((i, response));
+            }
+        }
+        responses
+    }
+
+    // Every dealer deals, but dealer 2 sends participant 3 a share off its polynomial
+    fn deal_with_bad_share(sessions: &mut [DkgSession]) -> Vec<(usize, DkgMessage)> {
+        let mut complaints = Vec::new();
+        for dealer in 0..COMMITTEE {
+            let mut deal = sessions[dealer].start().unwrap();
+            if dealer == 2 {
+                if let DkgMessage::Deal { encrypted_shares, .. } = &mut deal {
+                    let bad = bincode::serialize(&FieldWrap(Fr::one())).unwrap();
+                    encrypted_shares[3] = sessions[3].participants[3].encrypt(bad);
+                }
+            }
+            complaints.extend(broadcast(sessions, dealer, &deal));
+        }
+        complaints
+    }
+
+    #[test]
+    fn test_complaint_answered() {
+        let mut sessions = sessions();
+        let complaints = deal_with_bad_share(&mut sessions);
+        assert_eq!(complaints.len(), 1);
+        assert!(matches!(complaints[0].1, DkgMessage::Complaint { complainer: 3, dealer: 2, .. }));
+
+        let (from, complaint) = &complaints[0];
+        let justifications = broadcast(&mut sessions, *from, complaint);
+        assert_eq!(justifications.len(), 1);
+        let (from, justification) = &justifications[0];
+        assert!(broadcast(&mut sessions, *from, justification).is_empty());
+
+        let outputs: Vec<DkgOutput> = sessions.iter_mut().map(|s| s.finalize().unwrap()).collect();
+        let group_key = outputs[0].public_key_set.public_key();
+        for (i, output) in outputs.iter().enumerate() {
+            assert_eq!(output.qualified, (0..COMMITTEE as u32).collect());
+            assert_eq!(output.public_key_set.public_key(), group_key);
+            assert_eq!(output.public_key_set.public_key_share(i as u64), output.secret_key_share.public_key_share());
+        }
+    }
+
+    #[test]
+    fn test_unanswered_complaint_disqualifies_everywhere() {
+        let mut sessions = sessions();
+        let complaints = deal_with_bad_share(&mut sessions);
+
+        // The complaint reaches everyone but the dealer, who never answers
+        let (from, complaint) = &complaints[0];
+        for (i, session) in sessions.iter_mut().enumerate().filter(|(i, _)| *i != *from && *i != 2) {
+            assert!(session.handle_message(complaint.clone()).unwrap().is_none(), "node {}", i);
+        }
+
+        // Nodes 0 and 1 hold valid shares from dealer 2, yet exclude it like node 3 does
+        let honest: Vec<DkgOutput> = [0, 1, 3].iter().map(|i| sessions[*i].finalize().unwrap()).collect();
+        for output in &honest {
+            assert_eq!(output.qualified, [0, 1, 3].into_iter().collect());
+            assert_eq!(output.public_key_set.public_key(), honest[0].public_key_set.public_key());
+        }
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let mut sessions = sessions();
+        let deal = sessions[0].start().unwrap();
+        let decoded = DkgMessage::from_bytes(&deal.to_bytes().unwrap()).unwrap();
+        assert!(broadcast(&mut sessions, 0, &decoded).is_empty());
+        assert!(matches!(sessions[1].handle_message(decoded), Err(DkgError::DuplicateDeal(0))));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, HashMap};
+
+use idia_node::mempool::Mempool;
+
+use crate::compliance::checks::ComplianceConfig;
+use crate::tokenomics::economics::FeeMechanism;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Parameter {
+    MinBaseFee,
+    FeeAdjustmentDenominator,
+    MaxBlockSize,
+    TargetFullnessPercent,
+    MinRingSize,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 5] = [
+        Parameter::MinBaseFee,
+        Parameter::FeeAdjustmentDenominator,
+        Parameter::MaxBlockSize,
+        Parameter::TargetFullnessPercent,
+        Parameter::MinRingSize,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Parameter::MinBaseFee => "min_base_fee",
+            Parameter::FeeAdjustmentDenominator => "fee_adjustment_denominator",
+            Parameter::MaxBlockSize => "max_block_size",
+            Parameter::TargetFullnessPercent => "target_fullness_percent",
+            Parameter::MinRingSize => "min_ring_size",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    // Inclusive bounds; anything outside them would stall or destabilise consensus
+    pub fn bounds(&self) -> (u64, u64) {
+        match self {
+            Parameter::MinBaseFee => (1, 1_000_000),
+            Parameter::FeeAdjustmentDenominator => (2, 64),
+            Parameter::MaxBlockSize => (100_000, 32_000_000),
+            Parameter::TargetFullnessPercent => (10, 90),
+            Parameter::MinRingSize => (2, 128),
+        }
+    }
+
+    pub fn default_value(&self) -> u64 {
+        match self {
+            Parameter::MinBaseFee => 1,
+            Parameter::FeeAdjustmentDenominator => 8,
+            Parameter::MaxBlockSize => 2_000_000,
+            Parameter::TargetFullnessPercent => 50,
+            Parameter::MinRingSize => 11,
+        }
+    }
+
+    pub fn validate(&self, value: u64) -> Result<u64, ParameterError> {
+        let (min, max) = self.bounds();
+        if value < min || value > max {
+            return Err(ParameterError::OutOfRange {
+                parameter: self.name(),
+                value,
+                min,
+                max,
+            });
+        }
+        Ok(value)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParameterError {
+    #[error("Unknown parameter: {0}")]
+    UnknownParameter(String),
+    #[error("Invalid value for {parameter}: {value}")]
+    InvalidValue { parameter: &'static str, value: String },
+    #[error("{parameter} = {value} outside allowed range {min}..={max}")]
+    OutOfRange { parameter: &'static str, value: u64, min: u64, max: u64 },
+    #[error("Activation height {activation} not after current height {current}")]
+    ActivationInPast { activation: u64, current: u64 },
+}
+
+// Values governance has decided on, keyed by the height they take effect at.
+// After `activate` each block, the `apply_to_*` methods push the active values
+// to their consumers so every node switches on the same block.
+pub struct ParameterRegistry {
+    active: HashMap<Parameter, u64>,
+    scheduled: BTreeMap<u64, Vec<(Parameter, u64)>>,
+    history: Vec<(u64, Parameter, u64)>,
+    height: u64,
+}
+
+impl ParameterRegistry {
+    // Gives nodes time to see an approved change before it binds
+    pub const ACTIVATION_DELAY: u64 = 720;
+
+    pub fn new() -> Self {
+        Self {
+            active: Parameter::ALL.into_iter().map(|p| (p, p.default_value())).collect(),
+            scheduled: BTreeMap::new(),
+            history: Vec::new(),
+            height: 0,
+        }
+    }
+
+    // Seeds the genesis values from node configuration
+    pub fn with_value(mut self, parameter: Parameter, value: u64) -> Result<Self, ParameterError> {
+        self.active.insert(parameter, parameter.validate(value)?);
+        Ok(self)
+    }
+
+    pub fn parse(&self, name: &str, value: &str) -> Result<(Parameter, u64), ParameterError> {
+        let parameter = Parameter::from_name(name)
+            .ok_or_else(|| ParameterError::UnknownParameter(name.to_string()))?;
+        let value = value.trim().parse::<u64>()
+            .map_err(|_| ParameterError::InvalidValue {
+                parameter: parameter.name(),
+                value: value.to_string(),
+            })?;
+        Ok((parameter, parameter.validate(value)?))
+    }
+
+    pub fn schedule(&mut self, parameter: Parameter, value: u64, activation_height: u64) -> Result<(), ParameterError> {
+        if activation_height <= self.height {
+            return Err(ParameterError::ActivationInPast {
+                activation: activation_height,
+                current: self.height,
+            });
+        }
+        let value = parameter.validate(value)?;
+        self.scheduled.entry(activation_height).or_default().push((parameter, value));
+        Ok(())
+    }
+
+    pub fn get(&self, parameter: Parameter) -> u64 {
+        self.active.get(&parameter).copied().unwrap_or_else(|| parameter.default_value())
+    }
+
+    pub fn value_at(&self, parameter: Parameter, height: u64) -> u64 {
+        // Latest scheduled change at or below the height wins over the active value
+        self.scheduled
+            .range(..=height)
+            .rev()
+            .flat_map(|(_, changes)| changes.iter().rev())
+            .find(|(p, _)| *p == parameter)
+            .map(|(_, value)| *value)
+            .unwrap_or_else(|| self.get(parameter))
+    }
+
+    // Every activated change, oldest first
+    pub fn history(&self) -> &[(u64, Parameter, u64)] {
+        &self.history
+    }
+
+    pub fn scheduled_changes(&self) -> impl Iterator<Item = (u64, Parameter, u64)> + '_ {
+        self.scheduled
+            .iter()
+            .flat_map(|(height, changes)| changes.iter().map(move |(p, v)| (*height, *p, *v)))
+    }
+
+    // Promotes every change whose activation height has been reached
+    pub fn activate(&mut self, height: u64) -> Vec<(Parameter, u64)> {
+        self.height = height;
+
+        let pending = self.scheduled.split_off(&(height + 1));
+        let due = std::mem::replace(&mut self.scheduled, pending);
+
+        let mut activated = Vec::new();
+        for (activation_height, changes) in due {
+            for (parameter, value) in changes {
+                self.active.insert(parameter, value);
+                self.history.push((activation_height, parameter, value));
+                activated.push((parameter, value));
+            }
+        }
+        activated
+    }
+
+    pub fn apply_to_fees(&self, fees: &mut FeeMechanism) {
+        fees.min_base_fee = self.get(Parameter::MinBaseFee);
+        fees.adjustment_denominator = self.get(Parameter::FeeAdjustmentDenominator);
+        fees.max_block_size = self.get(Parameter::MaxBlockSize);
+        fees.target_fullness_percent = self.get(Parameter::TargetFullnessPercent);
+        fees.base_fee = fees.base_fee.max(fees.min_base_fee);
+    }
+
+    pub fn apply_to_compliance(&self, config: &mut ComplianceConfig) {
+        config.min_ring_size = self.get(Parameter::MinRingSize) as u32;
+    }
+
+    // Call after `apply_to_fees` and the block's base fee update, so the pool
+    // orders by the base fee the next block burns
+    pub fn apply_to_mempool(&self, fees: &FeeMechanism, mempool: &mut Mempool) {
+        mempool.set_min_ring_size(self.get(Parameter::MinRingSize) as usize);
+        mempool.set_max_block_size(self.get(Parameter::MaxBlockSize) as usize);
+        mempool.set_base_fee(fees.base_fee);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activated_values_reach_fees() {
+        let mut registry = ParameterRegistry::new();
+        registry.schedule(Parameter::MaxBlockSize, 100_000, 10).unwrap();
+        registry.schedule(Parameter::MinBaseFee, 500, 10).unwrap();
+
+        let mut fees = FeeMechanism::new();
+        registry.activate(9);
+        registry.apply_to_fees(&mut fees);
+        assert_eq!(fees.max_block_size, 2_000_000);
+
+        registry.activate(10);
+        registry.apply_to_fees(&mut fees);
+        assert_eq!(fees.max_block_size, 100_000);
+        assert_eq!(fees.base_fee, 500);
+        assert_eq!(registry.history().len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_out_of_range() {
+        let registry = ParameterRegistry::new();
+        assert!(registry.parse("min_ring_size", "1").is_err());
+        assert!(registry.parse("no_such_parameter", "1").is_err());
+        assert_eq!(registry.parse("min_ring_size", " 16 ").unwrap(), (Parameter::MinRingSize, 16));
+    }
+}
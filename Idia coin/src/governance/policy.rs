@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use super::threshold::ProposedChange;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProposalKind {
+    ParameterUpdate,
+    ProtocolUpgrade,
+    TreasurySpend,
+    PrivacyFeatureToggle,
+    BridgePause,
+}
+
+impl From<&ProposedChange> for ProposalKind {
+    fn from(change: &ProposedChange) -> Self {
+        match change {
+            ProposedChange::ParameterUpdate { .. } => ProposalKind::ParameterUpdate,
+            ProposedChange::ProtocolUpgrade { .. } => ProposalKind::ProtocolUpgrade,
+            ProposedChange::TreasurySpend { .. } => ProposalKind::TreasurySpend,
+            ProposedChange::PrivacyFeatureToggle { .. } => ProposalKind::PrivacyFeatureToggle,
+            ProposedChange::BridgePause { .. } => ProposalKind::BridgePause,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdRule {
+    SimpleMajority,
+    Supermajority { numerator: u32, denominator: u32 },
+    Unanimous,
+}
+
+impl ThresholdRule {
+    pub fn required_signatures(&self, committee_size: u32) -> u32 {
+        match *self {
+            ThresholdRule::SimpleMajority => committee_size / 2 + 1,
+            ThresholdRule::Supermajority { numerator, denominator } => {
+                (committee_size * numerator).div_ceil(denominator)
+            }
+            ThresholdRule::Unanimous => committee_size,
+        }
+    }
+}
+
+pub struct GovernancePolicy {
+    rules: HashMap<ProposalKind, ThresholdRule>,
+    default_rule: ThresholdRule,
+    veto_rule: ThresholdRule,
+}
+
+impl Default for GovernancePolicy {
+    fn default() -> Self {
+        let two_thirds = ThresholdRule::Supermajority { numerator: 2, denominator: 3 };
+
+        let mut rules = HashMap::new();
+        rules.insert(ProposalKind::ParameterUpdate, ThresholdRule::SimpleMajority);
+        rules.insert(ProposalKind::ProtocolUpgrade, two_thirds);
+        rules.insert(ProposalKind::TreasurySpend, two_thirds);
+        rules.insert(ProposalKind::PrivacyFeatureToggle, two_thirds);
+        // A halt during an exploit has to pass quickly
+        rules.insert(ProposalKind::BridgePause, ThresholdRule::SimpleMajority);
+
+        Self {
+            rules,
+            default_rule: two_thirds,
+            // Overturning an approval takes more than approving it did
+            veto_rule: ThresholdRule::Supermajority { numerator: 3, denominator: 4 },
+        }
+    }
+}
+
+impl GovernancePolicy {
+    pub fn with_rule(mut self, kind: ProposalKind, rule: ThresholdRule) -> Self {
+        self.rules.insert(kind, rule);
+        self
+    }
+
+    pub fn with_veto_rule(mut self, rule: ThresholdRule) -> Self {
+        self.veto_rule = rule;
+        self
+    }
+
+    pub fn rule_for(&self, kind: ProposalKind) -> ThresholdRule {
+        self.rules.get(&kind).copied().unwrap_or(self.default_rule)
+    }
+
+    // Never below what the key set needs to combine a signature at all
+    pub fn required_signatures(&self, change: &ProposedChange, committee_size: u32, crypto_minimum: u32) -> u32 {
+        self.rule_for(ProposalKind::from(change))
+            .required_signatures(committee_size)
+            .max(crypto_minimum)
+            .min(committee_size)
+    }
+
+    pub fn required_veto_signatures(&self, committee_size: u32, crypto_minimum: u32) -> u32 {
+        self.veto_rule
+            .required_signatures(committee_size)
+            .max(crypto_minimum)
+            .min(committee_size)
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+use super::threshold::ProposalId;
+
+#[derive(Debug, Clone)]
+pub struct ScheduledExecution {
+    pub proposal_id: ProposalId,
+    pub activation_height: u64,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+// Approved changes that only take effect at a future height on the canonical chain
+pub struct ExecutionScheduler {
+    queue: BTreeMap<u64, Vec<ScheduledExecution>>,
+    failed: Vec<ScheduledExecution>,
+}
+
+impl ExecutionScheduler {
+    pub const MAX_ATTEMPTS: u32 = 3;
+    pub const RETRY_INTERVAL_BLOCKS: u64 = 10;
+
+    pub fn new() -> Self {
+        Self {
+            queue: BTreeMap::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    pub fn schedule(&mut self, proposal_id: ProposalId, activation_height: u64) {
+        self.queue.entry(activation_height).or_default().push(ScheduledExecution {
+            proposal_id,
+            activation_height,
+            attempts: 0,
+            last_error: None,
+        });
+    }
+
+    pub fn take_due(&mut self, height: u64) -> Vec<ScheduledExecution> {
+        let pending = self.queue.split_off(&(height + 1));
+        std::mem::replace(&mut self.queue, pending)
+            .into_values()
+            .flatten()
+            .collect()
+    }
+
+    // Requeues a failed attempt; returns false once it has been flagged as permanently failed
+    pub fn retry(&mut self, mut execution: ScheduledExecution, height: u64, error: String) -> bool {
+        execution.attempts += 1;
+        execution.last_error = Some(error);
+
+        if execution.attempts >= Self::MAX_ATTEMPTS {
+            self.failed.push(execution);
+            return false;
+        }
+        self.queue
+            .entry(height + Self::RETRY_INTERVAL_BLOCKS)
+            .or_default()
+            .push(execution);
+        true
+    }
+
+    pub fn is_scheduled(&self, proposal_id: ProposalId) -> bool {
+        self.queue.values().flatten().any(|e| e.proposal_id == proposal_id)
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = (u64, &ScheduledExecution)> {
+        self.queue
+            .iter()
+            .flat_map(|(height, executions)| executions.iter().map(move |e| (*height, e)))
+    }
+
+    pub fn failed(&self) -> &[ScheduledExecution] {
+        &self.failed
+    }
+}
@@ -1,8 +1,56 @@
-use threshold_crypto::{PublicKeySet, SecretKeyShare, SignatureShare};
+use threshold_crypto::{PublicKey, PublicKeySet, SecretKeyShare, Signature, SignatureShare};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
+use crate::bridge::pause::ALL_CHAINS;
+use crate::bridge::types::ChainId;
+use crate::tokenomics::economics::{Milestone, PayoutMode, PayoutRecord, Treasury, TreasuryError};
+
+use super::ballot::{Ballot, BallotBox, BallotError, EligibilitySnapshot, VoteTally};
+use super::delegation::{DelegationScope, VoteDelegations};
+use super::parameters::{ParameterError, ParameterRegistry};
+use super::policy::GovernancePolicy;
+use super::scheduler::ExecutionScheduler;
+
+pub type ProposalId = [u8; 32];
+
+pub const MAX_MILESTONES: usize = 32;
+
+// Points at the full proposal text kept off-chain; only the hash is signed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataRef {
+    pub uri: String,
+    pub content_hash: [u8; 32],
+}
+
+impl MetadataRef {
+    pub const MAX_URI_LEN: usize = 256;
+    const ALLOWED_SCHEMES: [&'static str; 2] = ["ipfs://", "https://"];
+
+    pub fn new(uri: impl Into<String>, content: &[u8]) -> Self {
+        Self {
+            uri: uri.into(),
+            content_hash: Sha256::digest(content).into(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GovernanceError> {
+        if self.uri.len() > Self::MAX_URI_LEN
+            || !Self::ALLOWED_SCHEMES.iter().any(|scheme| self.uri.starts_with(scheme) && self.uri.len() > scheme.len())
+        {
+            return Err(GovernanceError::InvalidMetadata(self.uri.clone()));
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, content: &[u8]) -> bool {
+        Sha256::digest(content).as_slice() == self.content_hash
+    }
+}
+
 pub struct GovernanceProposal {
-    pub id: u64,
+    pub id: ProposalId,
     pub title: String,
     pub description: String,
     pub proposed_change: ProposedChange,
@@ -10,8 +58,327 @@ pub struct GovernanceProposal {
     pub threshold: u32,
     pub signatures: HashMap<u32, SignatureShare>,
     pub state: ProposalState,
+    pub submitted_height: u64,
+    pub metadata: Option<MetadataRef>,
+    pub combined_signature: Option<Signature>,
+    pub delegated_votes: HashMap<u32, u32>,
+    pub veto_signatures: HashMap<u32, SignatureShare>,
+    // Attestation shares per milestone index, for milestone-paid treasury spends
+    pub milestone_attestations: HashMap<u32, HashMap<u32, SignatureShare>>,
+    pub milestones_released: Vec<u32>,
 }
 
+impl GovernanceProposal {
+    const DOMAIN_TAG: &'static [u8] = b"idia-governance-proposal-v1";
+
+    // Longer texts belong off-chain behind a metadata reference
+    pub const MAX_INLINE_DESCRIPTION: usize = 4096;
+
+    pub fn new(
+        title: String,
+        description: String,
+        proposed_change: ProposedChange,
+        voting_period_blocks: u64,
+        threshold: u32,
+        submitted_height: u64,
+    ) -> Self {
+        let mut proposal = Self {
+            id: [0u8; 32],
+            title,
+            description,
+            proposed_change,
+            voting_period_blocks,
+            threshold,
+            signatures: HashMap::new(),
+            state: ProposalState::Pending,
+            submitted_height,
+            metadata: None,
+            combined_signature: None,
+            delegated_votes: HashMap::new(),
+            veto_signatures: HashMap::new(),
+            milestone_attestations: HashMap::new(),
+            milestones_released: Vec::new(),
+        };
+
+        // The ID commits to the proposal contents, so signatures over it bind to them too
+        proposal.id = proposal.compute_id();
+        proposal
+    }
+
+    pub fn with_metadata(mut self, metadata: MetadataRef) -> Self {
+        self.metadata = Some(metadata);
+        self.id = self.compute_id();
+        self
+    }
+
+    pub fn validate_contents(&self) -> Result<(), GovernanceError> {
+        if self.description.len() > Self::MAX_INLINE_DESCRIPTION {
+            return Err(GovernanceError::DescriptionTooLong(self.description.len()));
+        }
+        if let Some(metadata) = &self.metadata {
+            metadata.validate()?;
+        }
+        Ok(())
+    }
+
+    // Checks fetched off-chain content against the signed hash
+    pub fn verify_metadata(&self, content: &[u8]) -> Result<(), GovernanceError> {
+        match &self.metadata {
+            Some(metadata) if metadata.matches(content) => Ok(()),
+            Some(_) => Err(GovernanceError::MetadataMismatch),
+            None => Err(GovernanceError::InvalidMetadata("proposal has no metadata reference".into())),
+        }
+    }
+
+    pub fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, GovernanceError> {
+        let mut reader = CanonicalReader { bytes };
+        if reader.bytes()? != Self::DOMAIN_TAG {
+            return Err(GovernanceError::MalformedProposal);
+        }
+
+        let title = reader.string()?;
+        let description = reader.string()?;
+        let proposed_change = ProposedChange::decode(&mut reader)?;
+        let voting_period_blocks = reader.u64()?;
+        let threshold = reader.u32()?;
+        let submitted_height = reader.u64()?;
+        let metadata = match reader.u8()? {
+            0 => None,
+            1 => Some(MetadataRef {
+                uri: reader.string()?,
+                content_hash: reader.take(32)?.try_into().unwrap(),
+            }),
+            _ => return Err(GovernanceError::MalformedProposal),
+        };
+        if !reader.bytes.is_empty() {
+            return Err(GovernanceError::MalformedProposal);
+        }
+
+        let proposal = Self::new(
+            title,
+            description,
+            proposed_change,
+            voting_period_blocks,
+            threshold,
+            submitted_height,
+        );
+        Ok(match metadata {
+            Some(metadata) => proposal.with_metadata(metadata),
+            None => proposal,
+        })
+    }
+
+    pub fn voting_ends_at(&self) -> u64 {
+        self.submitted_height + self.voting_period_blocks
+    }
+
+    // Deterministic encoding of everything the committee signs off on: fixed field
+    // order, little-endian integers and length-prefixed strings
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_bytes(&mut buf, Self::DOMAIN_TAG);
+        write_bytes(&mut buf, self.title.as_bytes());
+        write_bytes(&mut buf, self.description.as_bytes());
+        self.proposed_change.encode(&mut buf);
+        buf.extend_from_slice(&self.voting_period_blocks.to_le_bytes());
+        buf.extend_from_slice(&self.threshold.to_le_bytes());
+        buf.extend_from_slice(&self.submitted_height.to_le_bytes());
+        match &self.metadata {
+            Some(metadata) => {
+                buf.push(1);
+                write_bytes(&mut buf, metadata.uri.as_bytes());
+                buf.extend_from_slice(&metadata.content_hash);
+            }
+            None => buf.push(0),
+        }
+        buf
+    }
+
+    pub fn compute_id(&self) -> ProposalId {
+        Sha256::digest(self.canonical_bytes()).into()
+    }
+
+    // Vetoes sign a separate message so an approval share can never be replayed as one
+    pub fn veto_message(proposal_id: ProposalId) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"idia-governance-veto-v1");
+        hasher.update(proposal_id);
+        hasher.finalize().to_vec()
+    }
+
+    pub fn milestone_message(proposal_id: ProposalId, milestone: u32) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(b"idia-governance-milestone-v1");
+        hasher.update(proposal_id);
+        hasher.update(milestone.to_le_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    pub fn milestones(&self) -> &[Milestone] {
+        match &self.proposed_change {
+            ProposedChange::TreasurySpend { payout: PayoutMode::Milestones { milestones }, .. } => milestones,
+            _ => &[],
+        }
+    }
+}
+
+impl ProposedChange {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            ProposedChange::ParameterUpdate { parameter, new_value } => {
+                buf.push(0);
+                write_bytes(buf, parameter.as_bytes());
+                write_bytes(buf, new_value.as_bytes());
+            }
+            ProposedChange::ProtocolUpgrade { version, activation_height } => {
+                buf.push(1);
+                write_bytes(buf, version.as_bytes());
+                buf.extend_from_slice(&activation_height.to_le_bytes());
+            }
+            ProposedChange::TreasurySpend { amount, recipient, purpose, payout } => {
+                buf.push(2);
+                buf.extend_from_slice(&amount.to_le_bytes());
+                write_bytes(buf, recipient.as_bytes());
+                write_bytes(buf, purpose.as_bytes());
+                match payout {
+                    PayoutMode::Installments { installments, interval_blocks } => {
+                        buf.push(0);
+                        buf.extend_from_slice(&installments.to_le_bytes());
+                        buf.extend_from_slice(&interval_blocks.to_le_bytes());
+                    }
+                    PayoutMode::Vested { cliff_blocks, duration_blocks } => {
+                        buf.push(1);
+                        buf.extend_from_slice(&cliff_blocks.to_le_bytes());
+                        buf.extend_from_slice(&duration_blocks.to_le_bytes());
+                    }
+                    PayoutMode::Milestones { milestones } => {
+                        buf.push(2);
+                        buf.extend_from_slice(&(milestones.len() as u32).to_le_bytes());
+                        for milestone in milestones {
+                            write_bytes(buf, milestone.description.as_bytes());
+                            buf.extend_from_slice(&milestone.amount.to_le_bytes());
+                        }
+                    }
+                }
+            }
+            ProposedChange::PrivacyFeatureToggle { feature, enabled } => {
+                buf.push(3);
+                write_bytes(buf, feature.as_bytes());
+                buf.push(*enabled as u8);
+            }
+            ProposedChange::BridgePause { chain, paused, reason } => {
+                buf.push(4);
+                write_bytes(buf, chain.as_bytes());
+                buf.push(*paused as u8);
+                write_bytes(buf, reason.as_bytes());
+            }
+        }
+    }
+
+    fn decode(reader: &mut CanonicalReader) -> Result<Self, GovernanceError> {
+        let change = match reader.u8()? {
+            0 => ProposedChange::ParameterUpdate {
+                parameter: reader.string()?,
+                new_value: reader.string()?,
+            },
+            1 => ProposedChange::ProtocolUpgrade {
+                version: reader.string()?,
+                activation_height: reader.u64()?,
+            },
+            2 => ProposedChange::TreasurySpend {
+                amount: reader.u64()?,
+                recipient: reader.string()?,
+                purpose: reader.string()?,
+                payout: match reader.u8()? {
+                    0 => PayoutMode::Installments {
+                        installments: reader.u32()?,
+                        interval_blocks: reader.u64()?,
+                    },
+                    1 => PayoutMode::Vested {
+                        cliff_blocks: reader.u64()?,
+                        duration_blocks: reader.u64()?,
+                    },
+                    2 => {
+                        let count = reader.u32()?;
+                        if count as usize > MAX_MILESTONES {
+                            return Err(GovernanceError::MalformedProposal);
+                        }
+                        let milestones = (0..count)
+                            .map(|_| Ok(Milestone {
+                                description: reader.string()?,
+                                amount: reader.u64()?,
+                            }))
+                            .collect::<Result<Vec<_>, GovernanceError>>()?;
+                        PayoutMode::Milestones { milestones }
+                    }
+                    _ => return Err(GovernanceError::MalformedProposal),
+                },
+            },
+            3 => ProposedChange::PrivacyFeatureToggle {
+                feature: reader.string()?,
+                enabled: match reader.u8()? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(GovernanceError::MalformedProposal),
+                },
+            },
+            4 => ProposedChange::BridgePause {
+                chain: reader.string()?,
+                paused: match reader.u8()? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(GovernanceError::MalformedProposal),
+                },
+                reason: reader.string()?,
+            },
+            _ => return Err(GovernanceError::MalformedProposal),
+        };
+        Ok(change)
+    }
+}
+
+fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+struct CanonicalReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CanonicalReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], GovernanceError> {
+        if self.bytes.len() < len {
+            return Err(GovernanceError::MalformedProposal);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, GovernanceError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, GovernanceError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, GovernanceError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], GovernanceError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String, GovernanceError> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| GovernanceError::MalformedProposal)
+    }
+}
+
+#[derive(Clone)]
 pub enum ProposedChange {
     ParameterUpdate {
         parameter: String,
@@ -25,28 +392,154 @@ pub enum ProposedChange {
         amount: u64,
         recipient: String,
         purpose: String,
+        payout: PayoutMode,
     },
     PrivacyFeatureToggle {
         feature: String,
         enabled: bool,
     },
+    // Halts or resumes transfers on one bridge chain; takes effect on execution
+    BridgePause {
+        chain: String,
+        paused: bool,
+        reason: String,
+    },
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProposalState {
     Pending,
     Active,
     Approved,
     Rejected,
+    Scheduled,
+    // Milestone spends stay here until every tranche has been attested and paid
+    InProgress,
     Executed,
+    ExecutionFailed,
+    Vetoed,
+}
+
+impl ProposalState {
+    // Approved changes can be cancelled until they have actually run
+    pub fn is_vetoable(&self) -> bool {
+        matches!(self, ProposalState::Approved | ProposalState::Scheduled)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VetoAuthority {
+    Committee,
+    Guardian,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GovernanceEvent {
+    ProposalSubmitted {
+        proposal_id: ProposalId,
+        title: String,
+        submitted_height: u64,
+    },
+    SignatureAdded {
+        proposal_id: ProposalId,
+        node_index: u32,
+    },
+    StateChanged {
+        proposal_id: ProposalId,
+        from: ProposalState,
+        to: ProposalState,
+    },
+    ProposalExecuted {
+        proposal_id: ProposalId,
+        height: u64,
+    },
+    ExecutionFailed {
+        proposal_id: ProposalId,
+        attempts: u32,
+        error: String,
+        height: u64,
+    },
+    MilestoneReleased {
+        proposal_id: ProposalId,
+        milestone: u32,
+        amount: u64,
+        height: u64,
+    },
+    ProposalVetoed {
+        proposal_id: ProposalId,
+        authority: VetoAuthority,
+        height: u64,
+    },
+    BridgePauseChanged {
+        proposal_id: ProposalId,
+        chain: String,
+        paused: bool,
+        reason: String,
+        height: u64,
+    },
+}
+
+// Bootstrap-only key that can veto on its own until the committee is established
+#[derive(Debug, Clone)]
+pub struct Guardian {
+    pub public_key: PublicKey,
+    pub expires_at_height: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GovernanceError {
+    #[error("Proposal not found")]
+    ProposalNotFound,
+    #[error("Invalid proposal state")]
+    InvalidProposalState,
+    #[error("Proposal already exists")]
+    DuplicateProposal,
+    #[error("Invalid signature share from node {0}")]
+    InvalidSignatureShare(u32),
+    #[error("Invalid delegation: {0}")]
+    InvalidDelegation(String),
+    #[error("Malformed proposal encoding")]
+    MalformedProposal,
+    #[error("Invalid governance record: {0}")]
+    InvalidRecord(String),
+    #[error("Treasury error: {0}")]
+    Treasury(#[from] TreasuryError),
+    #[error("Parameter error: {0}")]
+    Parameter(#[from] ParameterError),
+    #[error("Ballot error: {0}")]
+    Ballot(#[from] BallotError),
+    #[error("Invalid milestone: {0}")]
+    InvalidMilestone(String),
+    #[error("Invalid veto: {0}")]
+    InvalidVeto(String),
+    #[error("Key rotation rejected: {0}")]
+    KeyRotation(String),
+    #[error("Invalid metadata reference: {0}")]
+    InvalidMetadata(String),
+    #[error("Off-chain content does not match the metadata hash")]
+    MetadataMismatch,
+    #[error("Description of {0} bytes too long to store inline")]
+    DescriptionTooLong(usize),
+    #[error("Invalid bridge chain name: {0}")]
+    InvalidBridgeChain(String),
 }
 
 pub struct ThresholdGovernance {
     public_key_set: PublicKeySet,
     secret_key_share: SecretKeyShare,
     node_index: u32,
-    proposals: HashMap<u64, GovernanceProposal>,
+    proposals: HashMap<ProposalId, GovernanceProposal>,
     current_height: u64,
+    delegations: VoteDelegations,
+    policy: GovernancePolicy,
+    committee_size: u32,
+    parameters: ParameterRegistry,
+    guardian: Option<Guardian>,
+    events: Vec<GovernanceEvent>,
+    scheduler: ExecutionScheduler,
+    protocol_upgrades: Vec<(u64, String)>,
+    ballot_boxes: HashMap<ProposalId, BallotBox>,
+    bridge_pauses: HashMap<String, String>,
 }
 
 impl ThresholdGovernance {
@@ -54,6 +547,7 @@ impl ThresholdGovernance {
         public_key_set: PublicKeySet,
         secret_key_share: SecretKeyShare,
         node_index: u32,
+        committee_size: u32,
     ) -> Self {
         Self {
             public_key_set,
@@ -61,66 +555,477 @@ impl ThresholdGovernance {
             node_index,
             proposals: HashMap::new(),
             current_height: 0,
+            delegations: VoteDelegations::new(),
+            policy: GovernancePolicy::default(),
+            committee_size,
+            parameters: ParameterRegistry::new(),
+            guardian: None,
+            events: Vec::new(),
+            scheduler: ExecutionScheduler::new(),
+            protocol_upgrades: Vec::new(),
+            ballot_boxes: HashMap::new(),
+            bridge_pauses: HashMap::new(),
         }
     }
 
+    pub fn with_policy(mut self, policy: GovernancePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_parameters(mut self, parameters: ParameterRegistry) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    pub fn parameters(&self) -> &ParameterRegistry {
+        &self.parameters
+    }
+
+    pub fn with_guardian(mut self, public_key: PublicKey, expires_at_height: u64) -> Self {
+        self.guardian = Some(Guardian {
+            public_key,
+            expires_at_height,
+        });
+        self
+    }
+
+    pub fn scheduler(&self) -> &ExecutionScheduler {
+        &self.scheduler
+    }
+
+    // Paused bridge chains and the reason given, for nodes restoring their switch
+    pub fn bridge_pauses(&self) -> &HashMap<String, String> {
+        &self.bridge_pauses
+    }
+
+    pub fn protocol_upgrades(&self) -> &[(u64, String)] {
+        &self.protocol_upgrades
+    }
+
+    pub fn drain_events(&mut self) -> Vec<GovernanceEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn required_signatures(&self, change: &ProposedChange) -> u32 {
+        // combine_signatures needs at least threshold + 1 shares
+        let crypto_minimum = self.public_key_set.threshold() as u32 + 1;
+        self.policy.required_signatures(change, self.committee_size, crypto_minimum)
+    }
+
+    pub fn delegate_vote(
+        &mut self,
+        delegator: u32,
+        delegate: u32,
+        scope: DelegationScope,
+    ) -> Result<(), GovernanceError> {
+        self.delegations.delegate(delegator, delegate, scope)
+    }
+
+    pub fn revoke_delegation(&mut self, delegator: u32, scope: DelegationScope) -> Option<u32> {
+        self.delegations.revoke(delegator, scope)
+    }
+
+    pub fn voting_power(&self, participant: u32, proposal_id: ProposalId) -> usize {
+        self.delegations.voting_power(participant, proposal_id)
+    }
+
     pub fn create_proposal(
         &mut self,
         title: String,
         description: String,
         proposed_change: ProposedChange,
         voting_period_blocks: u64,
-        threshold: u32,
-    ) -> u64 {
-        let proposal_id = self.next_proposal_id();
-        
-        let proposal = GovernanceProposal {
-            id: proposal_id,
+        metadata: Option<MetadataRef>,
+    ) -> Result<ProposalId, GovernanceError> {
+        self.validate_change(&proposed_change)?;
+        let proposal = self.build_proposal(title, description, proposed_change, voting_period_blocks, metadata);
+        proposal.validate_contents()?;
+
+        let proposal_id = proposal.id;
+        self.insert_proposal(proposal)?;
+        Ok(proposal_id)
+    }
+
+    pub fn build_proposal(
+        &self,
+        title: String,
+        description: String,
+        proposed_change: ProposedChange,
+        voting_period_blocks: u64,
+        metadata: Option<MetadataRef>,
+    ) -> GovernanceProposal {
+        // The threshold comes from policy for the kind of change, never from the proposer
+        let threshold = self.required_signatures(&proposed_change);
+
+        let proposal = GovernanceProposal::new(
             title,
             description,
             proposed_change,
             voting_period_blocks,
             threshold,
-            signatures: HashMap::new(),
-            state: ProposalState::Pending,
-        };
+            self.current_height,
+        );
+        match metadata {
+            Some(metadata) => proposal.with_metadata(metadata),
+            None => proposal,
+        }
+    }
+
+    // Rejects changes that could never execute, before anyone signs them
+    pub fn validate_change(&self, change: &ProposedChange) -> Result<(), GovernanceError> {
+        match change {
+            ProposedChange::ParameterUpdate { parameter, new_value } => {
+                self.parameters.parse(parameter, new_value)?;
+            }
+            ProposedChange::TreasurySpend { amount, payout: PayoutMode::Milestones { milestones }, .. } => {
+                if milestones.is_empty() || milestones.len() > MAX_MILESTONES {
+                    return Err(GovernanceError::InvalidMilestone("bad milestone count".into()));
+                }
+                if milestones.iter().any(|m| m.amount == 0)
+                    || milestones.iter().try_fold(0u64, |sum, m| sum.checked_add(m.amount)) != Some(*amount)
+                {
+                    return Err(GovernanceError::InvalidMilestone("tranches must sum to the spend".into()));
+                }
+            }
+            // Bridge chain names as the bridge spells them; `*` pauses every chain
+            ProposedChange::BridgePause { chain, .. } => {
+                if chain != ALL_CHAINS && chain.parse::<ChainId>().is_err() {
+                    return Err(GovernanceError::InvalidBridgeChain(chain.clone()));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn on_new_block(&mut self, height: u64) -> Vec<(ProposalId, ProposalState)> {
+        self.current_height = height;
+
+        // Due changes go first so parameter updates land in this block's activation
+        self.run_scheduled_executions(height);
+        self.parameters.activate(height);
+
+        let mut transitions = Vec::new();
+        for proposal in self.proposals.values_mut() {
+            let next_state = match proposal.state {
+                // Voting opens once the chain reaches the submission height
+                ProposalState::Pending if height >= proposal.submitted_height => {
+                    Some(ProposalState::Active)
+                }
+                // Anything still active after the voting period never reached threshold
+                ProposalState::Active if height >= proposal.voting_ends_at() => {
+                    Some(ProposalState::Rejected)
+                }
+                _ => None,
+            };
+
+            if let Some(state) = next_state {
+                transitions.push((proposal.id, proposal.state, state));
+                proposal.state = state;
+            }
+        }
+
+        transitions.sort_by_key(|(id, _, _)| *id);
+        for (proposal_id, from, to) in &transitions {
+            self.events.push(GovernanceEvent::StateChanged {
+                proposal_id: *proposal_id,
+                from: *from,
+                to: *to,
+            });
+        }
+        transitions.into_iter().map(|(id, _, to)| (id, to)).collect()
+    }
+
+    // Token-weighted signalling alongside the committee vote; ballots don't reveal the voter
+    pub fn open_anonymous_vote(&mut self, snapshot: EligibilitySnapshot) -> Result<(), GovernanceError> {
+        let proposal_id = snapshot.proposal_id;
+        if !self.proposals.contains_key(&proposal_id) {
+            return Err(GovernanceError::ProposalNotFound);
+        }
+        if snapshot.height > self.current_height {
+            return Err(GovernanceError::InvalidProposalState);
+        }
+        self.ballot_boxes.entry(proposal_id).or_insert_with(|| BallotBox::new(snapshot));
+        Ok(())
+    }
+
+    pub fn cast_ballot(&mut self, ballot: &Ballot) -> Result<(), GovernanceError> {
+        if !self.is_active(ballot.proposal_id) {
+            return Err(GovernanceError::InvalidProposalState);
+        }
+        let ballot_box = self.ballot_boxes.get_mut(&ballot.proposal_id)
+            .ok_or(BallotError::NoVoteOpen)?;
+        ballot_box.cast(ballot)?;
+        Ok(())
+    }
+
+    pub fn vote_tally(&self, proposal_id: ProposalId) -> Option<&VoteTally> {
+        self.ballot_boxes.get(&proposal_id).map(|b| b.tally())
+    }
+
+    pub fn required_veto_signatures(&self) -> u32 {
+        let crypto_minimum = self.public_key_set.threshold() as u32 + 1;
+        self.policy.required_veto_signatures(self.committee_size, crypto_minimum)
+    }
+
+    // The share only counts once it lands in a block; publish it with `veto_share_record`
+    pub fn sign_veto(&self, proposal_id: ProposalId) -> Result<SignatureShare, GovernanceError> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if !proposal.state.is_vetoable() {
+            return Err(GovernanceError::InvalidProposalState);
+        }
+        Ok(self.secret_key_share.sign(GovernanceProposal::veto_message(proposal_id)))
+    }
+
+    pub fn add_veto_share(
+        &mut self,
+        proposal_id: ProposalId,
+        node_index: u32,
+        share: SignatureShare,
+    ) -> Result<(), GovernanceError> {
+        let required = self.required_veto_signatures() as usize;
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
 
-        self.proposals.insert(proposal_id, proposal);
-        proposal_id
+        // Only approved changes that haven't run yet can be cancelled
+        if !proposal.state.is_vetoable() {
+            return Err(GovernanceError::InvalidProposalState);
+        }
+
+        let message = GovernanceProposal::veto_message(proposal_id);
+        if node_index >= self.committee_size
+            || !self.public_key_set.public_key_share(node_index as u64).verify(&share, &message)
+        {
+            return Err(GovernanceError::InvalidSignatureShare(node_index));
+        }
+        proposal.veto_signatures.insert(node_index, share);
+
+        if proposal.veto_signatures.len() >= required {
+            let sigs: Vec<_> = proposal.veto_signatures.iter()
+                .map(|(&i, s)| (i as u64, s))
+                .collect();
+
+            if let Ok(signature) = self.public_key_set.combine_signatures(sigs) {
+                if self.public_key_set.public_key().verify(&signature, &message) {
+                    proposal.state = ProposalState::Vetoed;
+                    self.events.push(GovernanceEvent::ProposalVetoed {
+                        proposal_id,
+                        authority: VetoAuthority::Committee,
+                        height: self.current_height,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn verify_veto_share(&self, proposal_id: ProposalId, node_index: u32, share: &SignatureShare) -> bool {
+        node_index < self.committee_size
+            && self.public_key_set
+                .public_key_share(node_index as u64)
+                .verify(share, GovernanceProposal::veto_message(proposal_id))
+    }
+
+    pub(crate) fn check_guardian_signature(
+        &self,
+        proposal_id: ProposalId,
+        signature: &Signature,
+    ) -> Result<(), GovernanceError> {
+        let guardian = self.guardian.as_ref()
+            .ok_or_else(|| GovernanceError::InvalidVeto("no guardian configured".into()))?;
+        if self.current_height >= guardian.expires_at_height {
+            return Err(GovernanceError::InvalidVeto("guardian key has expired".into()));
+        }
+        if !guardian.public_key.verify(signature, GovernanceProposal::veto_message(proposal_id)) {
+            return Err(GovernanceError::InvalidVeto("bad guardian signature".into()));
+        }
+        Ok(())
     }
 
-    pub fn sign_proposal(&mut self, proposal_id: u64) -> Result<(), GovernanceError> {
+    pub fn guardian_veto(&mut self, proposal_id: ProposalId, signature: &Signature) -> Result<(), GovernanceError> {
+        self.check_guardian_signature(proposal_id, signature)?;
+
         let proposal = self.proposals.get_mut(&proposal_id)
             .ok_or(GovernanceError::ProposalNotFound)?;
+        if !proposal.state.is_vetoable() {
+            return Err(GovernanceError::InvalidProposalState);
+        }
 
+        proposal.state = ProposalState::Vetoed;
+        self.events.push(GovernanceEvent::ProposalVetoed {
+            proposal_id,
+            authority: VetoAuthority::Guardian,
+            height: self.current_height,
+        });
+        Ok(())
+    }
+
+    // Like every share, ours only counts once it lands in a block; publish it with `share_record`
+    pub fn sign_proposal(&self, proposal_id: ProposalId) -> Result<SignatureShare, GovernanceError> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
         if proposal.state != ProposalState::Active {
             return Err(GovernanceError::InvalidProposalState);
         }
 
-        // Create signature share
+        // Sign the content hash, recomputed so a tampered proposal can't be signed
         let msg = self.serialize_proposal(proposal);
-        let signature_share = self.secret_key_share.sign(msg);
+        Ok(self.secret_key_share.sign(msg))
+    }
+
+    // Returns our own share when the signer is our resolved delegate. It is not
+    // applied here: the caller submits it, and it counts once it lands in a block
+    pub fn add_signature_share(
+        &mut self,
+        proposal_id: ProposalId,
+        node_index: u32,
+        share: SignatureShare,
+    ) -> Result<Option<SignatureShare>, GovernanceError> {
+        self.insert_signature_share(proposal_id, node_index, share)?;
+
+        let follows_signer = node_index != self.node_index
+            && self.delegations.resolve(self.node_index, proposal_id) == Some(node_index);
+        let already_signed = self.proposals.get(&proposal_id)
+            .map_or(true, |p| p.signatures.contains_key(&self.node_index));
+
+        if follows_signer && !already_signed && self.is_active(proposal_id) {
+            let msg = proposal_id.to_vec();
+            let own_share = self.secret_key_share.sign(msg);
+            if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                proposal.delegated_votes.insert(self.node_index, node_index);
+            }
+            return Ok(Some(own_share));
+        }
+
+        Ok(None)
+    }
 
-        // Add signature to proposal
-        proposal.signatures.insert(self.node_index, signature_share);
+    pub fn node_index(&self) -> u32 {
+        self.node_index
+    }
+
+    // Installs a share produced by committee resharing; the group key itself must not change
+    pub fn rotate_key_share(
+        &mut self,
+        public_key_set: PublicKeySet,
+        secret_key_share: SecretKeyShare,
+        node_index: u32,
+        committee_size: u32,
+    ) -> Result<(), GovernanceError> {
+        if public_key_set.public_key() != self.public_key_set.public_key() {
+            return Err(GovernanceError::KeyRotation("group public key changed".into()));
+        }
+        if public_key_set.public_key_share(node_index as u64) != secret_key_share.public_key_share() {
+            return Err(GovernanceError::KeyRotation("share does not match key set".into()));
+        }
+
+        self.public_key_set = public_key_set;
+        self.secret_key_share = secret_key_share;
+        self.node_index = node_index;
+        self.committee_size = committee_size;
+
+        // Shares from the old polynomial can't combine with new ones, so open votes restart
+        for proposal in self.proposals.values_mut() {
+            if proposal.state == ProposalState::Active {
+                proposal.signatures.clear();
+                proposal.delegated_votes.clear();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn proposals(&self) -> impl Iterator<Item = &GovernanceProposal> {
+        self.proposals.values()
+    }
+
+    pub fn current_height(&self) -> u64 {
+        self.current_height
+    }
+
+    pub fn proposal(&self, proposal_id: ProposalId) -> Option<&GovernanceProposal> {
+        self.proposals.get(&proposal_id)
+    }
+
+    pub(crate) fn insert_proposal(&mut self, proposal: GovernanceProposal) -> Result<(), GovernanceError> {
+        if self.proposals.contains_key(&proposal.id) {
+            return Err(GovernanceError::DuplicateProposal);
+        }
+        self.events.push(GovernanceEvent::ProposalSubmitted {
+            proposal_id: proposal.id,
+            title: proposal.title.clone(),
+            submitted_height: proposal.submitted_height,
+        });
+        self.proposals.insert(proposal.id, proposal);
+        Ok(())
+    }
+
+    pub(crate) fn verify_share(&self, proposal_id: ProposalId, node_index: u32, share: &SignatureShare) -> bool {
+        node_index < self.committee_size
+            && self.public_key_set.public_key_share(node_index as u64).verify(share, proposal_id)
+    }
+
+    pub(crate) fn is_active(&self, proposal_id: ProposalId) -> bool {
+        self.proposals.get(&proposal_id)
+            .map_or(false, |p| p.state == ProposalState::Active)
+    }
+
+    pub(crate) fn insert_signature_share(
+        &mut self,
+        proposal_id: ProposalId,
+        node_index: u32,
+        share: SignatureShare,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+
+        if proposal.state != ProposalState::Active {
+            return Err(GovernanceError::InvalidProposalState);
+        }
+
+        // Every share must verify against the signer's public key share over the proposal ID
+        if node_index >= self.committee_size
+            || !self.public_key_set.public_key_share(node_index as u64).verify(&share, proposal_id)
+        {
+            return Err(GovernanceError::InvalidSignatureShare(node_index));
+        }
+        proposal.signatures.insert(node_index, share);
+        self.events.push(GovernanceEvent::SignatureAdded {
+            proposal_id,
+            node_index,
+        });
 
         // Check if we have enough signatures
         if proposal.signatures.len() >= proposal.threshold as usize {
             // Combine signatures
             let sigs: Vec<_> = proposal.signatures.iter()
-                .map(|(&i, s)| (i, s))
+                .map(|(&i, s)| (i as u64, s))
                 .collect();
             
-            if let Ok(_) = self.public_key_set.combine_signatures(&sigs) {
-                proposal.state = ProposalState::Approved;
+            if let Ok(signature) = self.public_key_set.combine_signatures(sigs) {
+                if self.public_key_set.public_key().verify(&signature, proposal_id) {
+                    proposal.combined_signature = Some(signature);
+                    proposal.state = ProposalState::Approved;
+                    self.events.push(GovernanceEvent::StateChanged {
+                        proposal_id,
+                        from: ProposalState::Active,
+                        to: ProposalState::Approved,
+                    });
+                }
             }
         }
 
         Ok(())
     }
 
-    pub fn execute_proposal(&mut self, proposal_id: u64) -> Result<(), GovernanceError> {
-        let proposal = self.proposals.get_mut(&proposal_id)
+    pub fn execute_proposal(
+        &mut self,
+        proposal_id: ProposalId,
+        treasury: &mut Treasury,
+    ) -> Result<(), GovernanceError> {
+        let proposal = self.proposals.get(&proposal_id)
             .ok_or(GovernanceError::ProposalNotFound)?;
 
         if proposal.state != ProposalState::Approved {
@@ -128,42 +1033,234 @@ impl ThresholdGovernance {
         }
 
         // Execute the proposed change
-        match &proposal.proposed_change {
+        match proposal.proposed_change.clone() {
             ProposedChange::ParameterUpdate { parameter, new_value } => {
-                self.update_parameter(parameter, new_value)?;
+                // Reject bad values now rather than when they fall due
+                self.parameters.parse(&parameter, &new_value)?;
+                let activation_height = self.current_height + ParameterRegistry::ACTIVATION_DELAY;
+                self.defer_execution(proposal_id, activation_height);
+                return Ok(());
             }
-            ProposedChange::ProtocolUpgrade { version, activation_height } => {
-                self.schedule_upgrade(version, *activation_height)?;
+            ProposedChange::ProtocolUpgrade { activation_height, .. } => {
+                let activation_height = activation_height.max(self.current_height + 1);
+                self.defer_execution(proposal_id, activation_height);
+                return Ok(());
             }
-            ProposedChange::TreasurySpend { amount, recipient, purpose } => {
-                self.process_treasury_spend(*amount, recipient, purpose)?;
+            ProposedChange::TreasurySpend { amount, recipient, purpose, payout } => {
+                self.process_treasury_spend(
+                    treasury,
+                    proposal_id,
+                    amount,
+                    &recipient,
+                    &purpose,
+                    &payout,
+                )?;
+
+                // Tranches are only reserved so far; each waits for its attestation
+                if let PayoutMode::Milestones { .. } = payout {
+                    if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                        proposal.state = ProposalState::InProgress;
+                    }
+                    self.events.push(GovernanceEvent::StateChanged {
+                        proposal_id,
+                        from: ProposalState::Approved,
+                        to: ProposalState::InProgress,
+                    });
+                    return Ok(());
+                }
             }
             ProposedChange::PrivacyFeatureToggle { feature, enabled } => {
-                self.toggle_privacy_feature(feature, *enabled)?;
+                self.toggle_privacy_feature(&feature, enabled)?;
+            }
+            // Emergency halts run at once rather than waiting out an activation delay
+            ProposedChange::BridgePause { chain, paused, reason } => {
+                if paused {
+                    self.bridge_pauses.insert(chain.clone(), reason.clone());
+                } else {
+                    self.bridge_pauses.remove(&chain);
+                }
+                self.events.push(GovernanceEvent::BridgePauseChanged {
+                    proposal_id,
+                    chain,
+                    paused,
+                    reason,
+                    height: self.current_height,
+                });
             }
         }
 
-        proposal.state = ProposalState::Executed;
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.state = ProposalState::Executed;
+        }
+        self.events.push(GovernanceEvent::ProposalExecuted {
+            proposal_id,
+            height: self.current_height,
+        });
         Ok(())
     }
 
-    fn update_parameter(&self, parameter: &str, value: &str) -> Result<(), GovernanceError> {
-        // Implement parameter update logic
+    pub fn sign_milestone(
+        &mut self,
+        proposal_id: ProposalId,
+        milestone: u32,
+        treasury: &mut Treasury,
+    ) -> Result<SignatureShare, GovernanceError> {
+        let share = self.secret_key_share.sign(GovernanceProposal::milestone_message(proposal_id, milestone));
+        self.add_milestone_share(proposal_id, milestone, self.node_index, share.clone(), treasury)?;
+        Ok(share)
+    }
+
+    // Releases the tranche once enough members attest that the milestone was delivered
+    pub fn add_milestone_share(
+        &mut self,
+        proposal_id: ProposalId,
+        milestone: u32,
+        node_index: u32,
+        share: SignatureShare,
+        treasury: &mut Treasury,
+    ) -> Result<Option<PayoutRecord>, GovernanceError> {
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or(GovernanceError::ProposalNotFound)?;
+        if proposal.state != ProposalState::InProgress {
+            return Err(GovernanceError::InvalidProposalState);
+        }
+        let milestone_count = proposal.milestones().len();
+        if milestone as usize >= milestone_count {
+            return Err(GovernanceError::InvalidMilestone(format!("no milestone {}", milestone)));
+        }
+        if proposal.milestones_released.contains(&milestone) {
+            return Err(GovernanceError::InvalidMilestone(format!("milestone {} already released", milestone)));
+        }
+
+        let message = GovernanceProposal::milestone_message(proposal_id, milestone);
+        if node_index >= self.committee_size
+            || !self.public_key_set.public_key_share(node_index as u64).verify(&share, &message)
+        {
+            return Err(GovernanceError::InvalidSignatureShare(node_index));
+        }
+
+        let attestations = proposal.milestone_attestations.entry(milestone).or_default();
+        attestations.insert(node_index, share);
+        if attestations.len() < proposal.threshold as usize {
+            return Ok(None);
+        }
+
+        let sigs: Vec<_> = attestations.iter().map(|(&i, s)| (i as u64, s)).collect();
+        let attested = self.public_key_set
+            .combine_signatures(sigs)
+            .map_or(false, |signature| self.public_key_set.public_key().verify(&signature, &message));
+        if !attested {
+            return Ok(None);
+        }
+
+        let record = treasury.release_milestone(proposal_id, milestone as usize, self.current_height)?;
+        proposal.milestones_released.push(milestone);
+        proposal.milestone_attestations.remove(&milestone);
+        self.events.push(GovernanceEvent::MilestoneReleased {
+            proposal_id,
+            milestone,
+            amount: record.amount,
+            height: self.current_height,
+        });
+
+        if proposal.milestones_released.len() == milestone_count {
+            proposal.state = ProposalState::Executed;
+            self.events.push(GovernanceEvent::ProposalExecuted {
+                proposal_id,
+                height: self.current_height,
+            });
+        }
+        Ok(Some(record))
+    }
+
+    fn defer_execution(&mut self, proposal_id: ProposalId, activation_height: u64) {
+        self.scheduler.schedule(proposal_id, activation_height);
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.state = ProposalState::Scheduled;
+        }
+        self.events.push(GovernanceEvent::StateChanged {
+            proposal_id,
+            from: ProposalState::Approved,
+            to: ProposalState::Scheduled,
+        });
+    }
+
+    fn run_scheduled_executions(&mut self, height: u64) {
+        for execution in self.scheduler.take_due(height) {
+            let proposal_id = execution.proposal_id;
+            let Some(change) = self.proposals.get(&proposal_id)
+                .filter(|p| p.state == ProposalState::Scheduled)
+                .map(|p| p.proposed_change.clone())
+            else {
+                continue;
+            };
+
+            let result = match &change {
+                ProposedChange::ParameterUpdate { parameter, new_value } => {
+                    self.update_parameter(parameter, new_value, height)
+                }
+                ProposedChange::ProtocolUpgrade { version, .. } => self.schedule_upgrade(version, height),
+                _ => Err(GovernanceError::InvalidProposalState),
+            };
+
+            match result {
+                Ok(()) => {
+                    if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                        proposal.state = ProposalState::Executed;
+                    }
+                    self.events.push(GovernanceEvent::ProposalExecuted { proposal_id, height });
+                }
+                Err(e) => {
+                    let attempts = execution.attempts + 1;
+                    let error = e.to_string();
+                    if !self.scheduler.retry(execution, height, error.clone()) {
+                        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+                            proposal.state = ProposalState::ExecutionFailed;
+                        }
+                    }
+                    self.events.push(GovernanceEvent::ExecutionFailed {
+                        proposal_id,
+                        attempts,
+                        error,
+                        height,
+                    });
+                }
+            }
+        }
+    }
+
+    fn update_parameter(&mut self, parameter: &str, value: &str, height: u64) -> Result<(), GovernanceError> {
+        let (parameter, value) = self.parameters.parse(parameter, value)?;
+        self.parameters.schedule(parameter, value, height)?;
         Ok(())
     }
 
-    fn schedule_upgrade(&self, version: &str, height: u64) -> Result<(), GovernanceError> {
-        // Implement upgrade scheduling logic
+    fn schedule_upgrade(&mut self, version: &str, height: u64) -> Result<(), GovernanceError> {
+        if self.protocol_upgrades.iter().any(|(_, v)| v == version) {
+            return Err(GovernanceError::InvalidProposalState);
+        }
+        self.protocol_upgrades.push((height, version.to_string()));
         Ok(())
     }
 
     fn process_treasury_spend(
         &self,
+        treasury: &mut Treasury,
+        proposal_id: ProposalId,
         amount: u64,
         recipient: &str,
         purpose: &str,
+        payout: &PayoutMode,
     ) -> Result<(), GovernanceError> {
-        // Implement treasury spend logic
+        // Funds are reserved now and released as installments fall due or grants vest
+        treasury.spend(
+            proposal_id,
+            amount,
+            recipient,
+            purpose,
+            payout,
+            self.current_height,
+        )?;
         Ok(())
     }
 
@@ -176,12 +1273,7 @@ impl ThresholdGovernance {
         Ok(())
     }
 
-    fn next_proposal_id(&self) -> u64 {
-        self.proposals.keys().max().unwrap_or(&0) + 1
-    }
-
     fn serialize_proposal(&self, proposal: &GovernanceProposal) -> Vec<u8> {
-        // Implement proposal serialization
-        Vec::new() // Placeholder
+        proposal.compute_id().to_vec()
     }
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+use prometheus::{
+    IntCounterVec, IntGauge, IntGaugeVec,
+    register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+};
+
+lazy_static! {
+    // Operation Metrics
+    pub static ref BRIDGE_OPERATION_TRANSITIONS: IntCounterVec = register_int_counter_vec!(
+        "idia_bridge_operation_transitions_total",
+        "Bridge operations entering each state, by route",
+        &["from_chain", "to_chain", "state"]
+    ).unwrap();
+
+    pub static ref BRIDGE_PENDING_OPERATIONS: IntGaugeVec = register_int_gauge_vec!(
+        "idia_bridge_pending_operations",
+        "Bridge operations not yet in a terminal state, by destination chain",
+        &["chain"]
+    ).unwrap();
+
+    pub static ref BRIDGE_FAILED_OPERATIONS: IntGaugeVec = register_int_gauge_vec!(
+        "idia_bridge_failed_operations",
+        "Bridge operations that failed or are refunding, by destination chain",
+        &["chain"]
+    ).unwrap();
+
+    // Adapter Metrics
+    pub static ref BRIDGE_RPC_UP: IntGaugeVec = register_int_gauge_vec!(
+        "idia_bridge_rpc_up",
+        "Whether the chain's RPC endpoint answered the last health probe",
+        &["chain"]
+    ).unwrap();
+
+    pub static ref BRIDGE_SYNCED_HEAD: IntGaugeVec = register_int_gauge_vec!(
+        "idia_bridge_synced_head",
+        "Last head height the bridge saw on each chain",
+        &["chain"]
+    ).unwrap();
+
+    pub static ref BRIDGE_PAUSED: IntGaugeVec = register_int_gauge_vec!(
+        "idia_bridge_paused",
+        "Whether transfers on the chain are paused",
+        &["chain"]
+    ).unwrap();
+
+    // Safety Metrics
+    pub static ref BRIDGE_VELOCITY_ALARMS: IntCounterVec = register_int_counter_vec!(
+        "idia_bridge_velocity_alarms_total",
+        "Velocity alarms raised, by destination chain",
+        &["chain"]
+    ).unwrap();
+
+    pub static ref BRIDGE_REPLAYS_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "idia_bridge_replays_rejected_total",
+        "Releases refused because the proof was already processed",
+        &["chain"]
+    ).unwrap();
+
+    // Federation Metrics
+    pub static ref BRIDGE_SIGNERS_LIVE: IntGauge = register_int_gauge!(
+        "idia_bridge_signers_live",
+        "Federation operators heard from within the liveness window"
+    ).unwrap();
+
+    pub static ref BRIDGE_SIGNER_QUORUM: IntGauge = register_int_gauge!(
+        "idia_bridge_signer_quorum",
+        "Signature shares a release needs"
+    ).unwrap();
+}
@@ -85,14 +85,9 @@ pub fn record_transaction_metrics(tx: &Transaction) {
 pub fn record_compliance_check(check: &ComplianceCheck) {
     COMPLIANCE_CHECKS_TOTAL.inc();
     
-    match check.result {
-        CheckResult::Fail(_) => {
-            COMPLIANCE_CHECK_FAILURES.inc();
-        },
-        CheckResult::Warning(_) => {
-            HIGH_RISK_TRANSACTIONS.inc();
-        },
-        _ => {}
+    // HIGH_RISK_TRANSACTIONS tracks open alerts and is maintained by the alert store
+    if let CheckResult::Fail(_) = check.result {
+        COMPLIANCE_CHECK_FAILURES.inc();
     }
 }
 
@@ -0,0 +1,24 @@
+use prometheus::{
+    IntCounter, IntGaugeVec,
+    register_int_counter, register_int_gauge_vec,
+};
+
+lazy_static! {
+    // Anonymity Set Metrics
+    pub static ref SPARK_EPOCH_NOTES: IntGaugeVec = register_int_gauge_vec!(
+        "idia_spark_epoch_notes",
+        "Notes minted into each Spark epoch, the anonymity set its spends prove against",
+        &["epoch"]
+    ).unwrap();
+
+    pub static ref SPARK_EPOCH_UNSPENT: IntGaugeVec = register_int_gauge_vec!(
+        "idia_spark_epoch_unspent",
+        "Notes in each Spark epoch not yet spent",
+        &["epoch"]
+    ).unwrap();
+
+    pub static ref SPARK_EPOCHS_PRUNED: IntCounter = register_int_counter!(
+        "idia_spark_epochs_pruned_total",
+        "Fully spent Spark epochs whose commitments were dropped"
+    ).unwrap();
+}
@@ -0,0 +1,355 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bech32::{FromBase32, ToBase32, Variant};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use idia_core::NetworkType;
+use rand_core::OsRng;
+use sha2::{Digest, Sha256, Sha512};
+
+const MAINNET_HRP: &str = "sp";
+const TESTNET_HRP: &str = "tsp";
+const ADDRESS_VERSION: u8 = 1;
+// version | diversifier | Q1 | Q2
+const ADDRESS_LEN: usize = 1 + 8 + 32 + 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AddressError {
+    #[error("Invalid encoding: {0}")]
+    Encoding(#[from] bech32::Error),
+    #[error("Unknown address prefix {0}")]
+    UnknownPrefix(String),
+    #[error("Address must use bech32m")]
+    WrongVariant,
+    #[error("Address is {0} bytes, expected {ADDRESS_LEN}")]
+    WrongLength(usize),
+    #[error("Unsupported address version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Address key is not a valid point")]
+    InvalidPoint,
+    #[error("Malformed view key")]
+    InvalidViewKey,
+}
+
+// Spark keys form a hierarchy: the spend key derives the full view key,
+// which derives the incoming view key, which derives addresses. With
+// generator G:
+//   spend key       (s1, s2, r)
+//   full view key   (s1, s2, D = r·G, P2 = s2·G + D)
+//   incoming view   (s1, P2)
+//   address i       (d_i, Q1 = s1·H_div(d_i), Q2 = H_Q2(s1, i)·G + P2)
+// Any number of unlinkable addresses come from one key, and only the
+// spend key knows the discrete log of Q2.
+#[derive(Clone)]
+pub struct SpendKey {
+    s1: Scalar,
+    s2: Scalar,
+    r: Scalar,
+}
+
+#[derive(Clone)]
+pub struct FullViewKey {
+    s1: Scalar,
+    s2: Scalar,
+    pub d: RistrettoPoint,
+    pub p2: RistrettoPoint,
+}
+
+#[derive(Clone)]
+pub struct IncomingViewKey {
+    s1: Scalar,
+    pub p2: RistrettoPoint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SparkAddress {
+    pub network: NetworkType,
+    pub diversifier: [u8; 8],
+    pub q1: RistrettoPoint,
+    pub q2: RistrettoPoint,
+}
+
+pub(crate) fn diversifier_point(diversifier: &[u8; 8]) -> RistrettoPoint {
+    let mut input = b"idia-spark-diversifier".to_vec();
+    input.extend_from_slice(diversifier);
+    RistrettoPoint::hash_from_bytes::<Sha512>(&input)
+}
+
+fn q2_offset(s1: &Scalar, index: u64) -> Scalar {
+    let mut input = b"idia-spark-q2".to_vec();
+    input.extend_from_slice(s1.as_bytes());
+    input.extend_from_slice(&index.to_le_bytes());
+    Scalar::hash_from_bytes::<Sha512>(&input)
+}
+
+// Diversifiers hide the index from everyone without the incoming view key,
+// who can recover it by XORing the mask back off
+fn diversifier_mask(s1: &Scalar) -> [u8; 8] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"idia-spark-diversifier-mask");
+    hasher.update(s1.as_bytes());
+    hasher.finalize()[..8].try_into().unwrap()
+}
+
+impl SpendKey {
+    pub fn generate() -> Self {
+        let mut rng = OsRng;
+        Self {
+            s1: Scalar::random(&mut rng),
+            s2: Scalar::random(&mut rng),
+            r: Scalar::random(&mut rng),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8; 96]) -> Option<Self> {
+        Some(Self {
+            s1: scalar_at(bytes, 0)?,
+            s2: scalar_at(bytes, 1)?,
+            r: scalar_at(bytes, 2)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut bytes = [0u8; 96];
+        bytes[..32].copy_from_slice(self.s1.as_bytes());
+        bytes[32..64].copy_from_slice(self.s2.as_bytes());
+        bytes[64..].copy_from_slice(self.r.as_bytes());
+        bytes
+    }
+
+    pub fn full_view_key(&self) -> FullViewKey {
+        let d = RISTRETTO_BASEPOINT_POINT * self.r;
+        FullViewKey {
+            s1: self.s1,
+            s2: self.s2,
+            d,
+            p2: RISTRETTO_BASEPOINT_POINT * self.s2 + d,
+        }
+    }
+
+    pub fn incoming_view_key(&self) -> IncomingViewKey {
+        self.full_view_key().incoming_view_key()
+    }
+
+    pub fn address(&self, network: NetworkType, index: u64) -> SparkAddress {
+        self.incoming_view_key().address(network, index)
+    }
+
+    // Discrete log of Q2 for address `index`, which authorises spends of
+    // notes sent to it
+    pub fn address_secret(&self, index: u64) -> Scalar {
+        q2_offset(&self.s1, index) + self.s2 + self.r
+    }
+}
+
+fn scalar_at(bytes: &[u8], i: usize) -> Option<Scalar> {
+    Option::<Scalar>::from(Scalar::from_canonical_bytes(bytes[i * 32..(i + 1) * 32].try_into().unwrap()))
+}
+
+impl FullViewKey {
+    pub fn incoming_view_key(&self) -> IncomingViewKey {
+        IncomingViewKey { s1: self.s1, p2: self.p2 }
+    }
+
+    // P2 follows from s2 and D, so only (s1, s2, D) are stored
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut bytes = [0u8; 96];
+        bytes[..32].copy_from_slice(self.s1.as_bytes());
+        bytes[32..64].copy_from_slice(self.s2.as_bytes());
+        bytes[64..].copy_from_slice(self.d.compress().as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; 96]) -> Option<Self> {
+        let (s1, s2) = (scalar_at(bytes, 0)?, scalar_at(bytes, 1)?);
+        let d = CompressedRistretto::from_slice(&bytes[64..]).ok()?.decompress()?;
+        Some(Self {
+            s1,
+            s2,
+            d,
+            p2: RISTRETTO_BASEPOINT_POINT * s2 + d,
+        })
+    }
+}
+
+impl IncomingViewKey {
+    pub fn address(&self, network: NetworkType, index: u64) -> SparkAddress {
+        let mask = diversifier_mask(&self.s1);
+        let mut diversifier = index.to_le_bytes();
+        diversifier.iter_mut().zip(mask).for_each(|(byte, m)| *byte ^= m);
+        SparkAddress {
+            network,
+            diversifier,
+            q1: diversifier_point(&diversifier) * self.s1,
+            q2: RISTRETTO_BASEPOINT_POINT * q2_offset(&self.s1, index) + self.p2,
+        }
+    }
+
+    // Opens what was sealed to any of our Q1 keys
+    pub(crate) fn diversifier_secret(&self) -> &Scalar {
+        &self.s1
+    }
+
+    pub fn diversifier_index(&self, diversifier: &[u8; 8]) -> u64 {
+        let mask = diversifier_mask(&self.s1);
+        let mut index = *diversifier;
+        index.iter_mut().zip(mask).for_each(|(byte, m)| *byte ^= m);
+        u64::from_le_bytes(index)
+    }
+
+    // The index of `address` if it is one of ours
+    pub fn owns(&self, address: &SparkAddress) -> Option<u64> {
+        let index = self.diversifier_index(&address.diversifier);
+        (*address == self.address(address.network, index)).then_some(index)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.s1.as_bytes());
+        bytes[32..].copy_from_slice(self.p2.compress().as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; 64]) -> Option<Self> {
+        Some(Self {
+            s1: scalar_at(bytes, 0)?,
+            p2: CompressedRistretto::from_slice(&bytes[32..]).ok()?.decompress()?,
+        })
+    }
+}
+
+impl SparkAddress {
+    pub fn to_bytes(&self) -> [u8; ADDRESS_LEN] {
+        let mut bytes = [0u8; ADDRESS_LEN];
+        bytes[0] = ADDRESS_VERSION;
+        bytes[1..9].copy_from_slice(&self.diversifier);
+        bytes[9..41].copy_from_slice(self.q1.compress().as_bytes());
+        bytes[41..].copy_from_slice(self.q2.compress().as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(network: NetworkType, bytes: &[u8]) -> Result<Self, AddressError> {
+        if bytes.len() != ADDRESS_LEN {
+            return Err(AddressError::WrongLength(bytes.len()));
+        }
+        if bytes[0] != ADDRESS_VERSION {
+            return Err(AddressError::UnsupportedVersion(bytes[0]));
+        }
+        let point = |range: std::ops::Range<usize>| {
+            CompressedRistretto::from_slice(&bytes[range])
+                .ok()
+                .and_then(|c| c.decompress())
+                .ok_or(AddressError::InvalidPoint)
+        };
+        Ok(Self {
+            network,
+            diversifier: bytes[1..9].try_into().unwrap(),
+            q1: point(9..41)?,
+            q2: point(41..73)?,
+        })
+    }
+}
+
+impl fmt::Display for SparkAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hrp = match self.network {
+            NetworkType::Mainnet => MAINNET_HRP,
+            NetworkType::Testnet => TESTNET_HRP,
+        };
+        let encoded = bech32::encode(hrp, self.to_bytes().to_base32(), Variant::Bech32m).map_err(|_| fmt::Error)?;
+        f.write_str(&encoded)
+    }
+}
+
+impl FromStr for SparkAddress {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hrp, data, variant) = bech32::decode(s)?;
+        if variant != Variant::Bech32m {
+            return Err(AddressError::WrongVariant);
+        }
+        let network = match hrp.as_str() {
+            MAINNET_HRP => NetworkType::Mainnet,
+            TESTNET_HRP => NetworkType::Testnet,
+            _ => return Err(AddressError::UnknownPrefix(hrp)),
+        };
+        Self::from_bytes(network, &Vec::<u8>::from_base32(&data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_round_trip() {
+        let key = SpendKey::generate();
+        let address = key.address(NetworkType::Mainnet, 7);
+        let encoded = address.to_string();
+        assert!(encoded.starts_with("sp1"));
+        assert_eq!(encoded.parse::<SparkAddress>().unwrap(), address);
+
+        let testnet = key.address(NetworkType::Testnet, 7);
+        assert!(testnet.to_string().starts_with("tsp1"));
+        assert_eq!(testnet.to_string().parse::<SparkAddress>().unwrap(), testnet);
+    }
+
+    #[test]
+    fn test_key_round_trip() {
+        let key = SpendKey::generate();
+        let restored = SpendKey::from_bytes(&key.to_bytes()).unwrap();
+        assert_eq!(restored.address(NetworkType::Mainnet, 3), key.address(NetworkType::Mainnet, 3));
+
+        let fvk = FullViewKey::from_bytes(&key.full_view_key().to_bytes()).unwrap();
+        assert_eq!(fvk.p2, key.full_view_key().p2);
+        let ivk = IncomingViewKey::from_bytes(&key.incoming_view_key().to_bytes()).unwrap();
+        assert_eq!(ivk.address(NetworkType::Mainnet, 3), key.address(NetworkType::Mainnet, 3));
+    }
+
+    #[test]
+    fn test_addresses_are_owned_and_spendable() {
+        let key = SpendKey::generate();
+        let ivk = key.incoming_view_key();
+        for index in [0, 1, u64::MAX] {
+            let address = key.address(NetworkType::Mainnet, index);
+            assert_eq!(ivk.owns(&address), Some(index));
+            assert_eq!(RISTRETTO_BASEPOINT_POINT * key.address_secret(index), address.q2);
+        }
+        // Diversified addresses share no visible key material
+        let (a, b) = (key.address(NetworkType::Mainnet, 0), key.address(NetworkType::Mainnet, 1));
+        assert_ne!(a.q1, b.q1);
+        assert_ne!(a.q2, b.q2);
+
+        let other = SpendKey::generate().address(NetworkType::Mainnet, 0);
+        assert_eq!(ivk.owns(&other), None);
+    }
+
+    #[test]
+    fn test_rejects_malformed_addresses() {
+        let address = SpendKey::generate().address(NetworkType::Mainnet, 0);
+        let mut bytes = address.to_bytes();
+
+        bytes[0] = 2;
+        assert!(matches!(
+            SparkAddress::from_bytes(NetworkType::Mainnet, &bytes),
+            Err(AddressError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            SparkAddress::from_bytes(NetworkType::Mainnet, &bytes[..40]),
+            Err(AddressError::WrongLength(40))
+        ));
+
+        let bech32 = bech32::encode("sp", address.to_bytes().to_base32(), Variant::Bech32).unwrap();
+        assert!(matches!(bech32.parse::<SparkAddress>(), Err(AddressError::WrongVariant)));
+        let foreign = bech32::encode("xx", address.to_bytes().to_base32(), Variant::Bech32m).unwrap();
+        assert!(matches!(foreign.parse::<SparkAddress>(), Err(AddressError::UnknownPrefix(_))));
+
+        let mut encoded = address.to_string().into_bytes();
+        let last = encoded.len() - 1;
+        encoded[last] = if encoded[last] == b'q' { b'p' } else { b'q' };
+        assert!(String::from_utf8(encoded).unwrap().parse::<SparkAddress>().is_err());
+    }
+}
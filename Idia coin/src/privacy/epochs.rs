@@ -0,0 +1,179 @@
+use curve25519_dalek::RistrettoPoint;
+use serde::Serialize;
+
+use crate::metrics::privacy::{SPARK_EPOCHS_PRUNED, SPARK_EPOCH_NOTES, SPARK_EPOCH_UNSPENT};
+
+// A fully spent epoch is kept this many blocks before pruning, so a reorg
+// that unspends one of its notes still finds the commitments
+pub const PRUNE_DEPTH: u64 = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EpochError {
+    #[error("Unknown Spark epoch {0}")]
+    UnknownEpoch(u64),
+    #[error("Spark epoch {0} is pruned")]
+    Pruned(u64),
+    #[error("Spark epoch {0} has more spends than notes")]
+    Overspent(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EpochStats {
+    pub epoch: u64,
+    pub notes: u64,
+    pub spent: u64,
+    pub closed: bool,
+    pub pruned: bool,
+}
+
+#[derive(Debug, Default)]
+struct Epoch {
+    commitments: Vec<RistrettoPoint>,
+    notes: u64,
+    spent: u64,
+    // Height of the spend that used up a closed epoch
+    fully_spent_at: Option<u64>,
+    pruned: bool,
+}
+
+// Note commitments grouped into epochs of `epoch_length` in mint order. A
+// spend proves membership in its note's epoch only, which bounds proof size
+// and verification time however large the pool grows. Spends name their
+// epoch, so once every note of a closed epoch is spent nothing can spend
+// from it again and its commitments can go.
+pub struct EpochPool {
+    epoch_length: u64,
+    epochs: Vec<Epoch>,
+}
+
+impl EpochPool {
+    pub fn new(epoch_length: u64) -> Self {
+        Self {
+            epoch_length: epoch_length.max(1),
+            epochs: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.epochs.iter().map(|epoch| epoch.notes).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn publish(&self, index: usize) {
+        let epoch = &self.epochs[index];
+        let label = index.to_string();
+        SPARK_EPOCH_NOTES.with_label_values(&[&label]).set(epoch.notes as i64);
+        SPARK_EPOCH_UNSPENT
+            .with_label_values(&[&label])
+            .set((epoch.notes - epoch.spent.min(epoch.notes)) as i64);
+    }
+
+    pub fn push(&mut self, commitments: &[RistrettoPoint]) {
+        let first = self.epochs.len().saturating_sub(1);
+        for commitment in commitments {
+            if self.epochs.last().map_or(true, |epoch| epoch.notes == self.epoch_length) {
+                self.epochs.push(Epoch::default());
+            }
+            let epoch = self.epochs.last_mut().unwrap();
+            epoch.commitments.push(*commitment);
+            epoch.notes += 1;
+        }
+        for index in first..self.epochs.len() {
+            self.publish(index);
+        }
+    }
+
+    // Drops notes from global index `len` on. Only the newest epochs are
+    // affected, which are never pruned.
+    pub fn truncate(&mut self, len: u64) {
+        let keep = len.div_ceil(self.epoch_length) as usize;
+        for index in keep..self.epochs.len() {
+            let label = index.to_string();
+            let _ = SPARK_EPOCH_NOTES.remove_label_values(&[&label]);
+            let _ = SPARK_EPOCH_UNSPENT.remove_label_values(&[&label]);
+        }
+        self.epochs.truncate(keep);
+        if let Some(last) = self.epochs.last_mut() {
+            let notes = len - (keep as u64 - 1) * self.epoch_length;
+            last.commitments.truncate(notes as usize);
+            last.notes = notes;
+            self.publish(keep - 1);
+        }
+    }
+
+    pub fn set(&self, epoch: u64) -> Result<&[RistrettoPoint], EpochError> {
+        let entry = self.epochs.get(epoch as usize).ok_or(EpochError::UnknownEpoch(epoch))?;
+        if entry.pruned {
+            return Err(EpochError::Pruned(epoch));
+        }
+        Ok(&entry.commitments)
+    }
+
+    // The epoch and position within it of a commitment still in the pool
+    pub fn find(&self, commitment: &RistrettoPoint) -> Option<(u64, usize)> {
+        self.epochs.iter().enumerate().find_map(|(epoch, entry)| {
+            let position = entry.commitments.iter().position(|c| c == commitment)?;
+            Some((epoch as u64, position))
+        })
+    }
+
+    pub fn record_spend(&mut self, epoch: u64, height: u64) -> Result<(), EpochError> {
+        let epoch_length = self.epoch_length;
+        let entry = self.epochs.get_mut(epoch as usize).ok_or(EpochError::UnknownEpoch(epoch))?;
+        if entry.spent == entry.notes {
+            return Err(EpochError::Overspent(epoch));
+        }
+        entry.spent += 1;
+        if entry.notes == epoch_length && entry.spent == entry.notes {
+            entry.fully_spent_at = Some(height);
+        }
+        self.publish(epoch as usize);
+        Ok(())
+    }
+
+    pub fn unrecord_spend(&mut self, epoch: u64) {
+        if let Some(entry) = self.epochs.get_mut(epoch as usize) {
+            entry.spent = entry.spent.saturating_sub(1);
+            entry.fully_spent_at = None;
+            self.publish(epoch as usize);
+        }
+    }
+
+    // Drops the commitments of epochs fully spent at least `PRUNE_DEPTH`
+    // blocks before `height`, returning the epochs pruned
+    pub fn prune(&mut self, height: u64) -> Vec<u64> {
+        let mut pruned = Vec::new();
+        for (index, entry) in self.epochs.iter_mut().enumerate() {
+            let deep = entry
+                .fully_spent_at
+                .is_some_and(|at| height.saturating_sub(at) >= PRUNE_DEPTH);
+            if deep && !entry.pruned {
+                entry.commitments = Vec::new();
+                entry.pruned = true;
+                pruned.push(index as u64);
+            }
+        }
+        if !pruned.is_empty() {
+            tracing::info!("Pruned fully spent Spark epochs {:?}", pruned);
+            SPARK_EPOCHS_PRUNED.inc_by(pruned.len() as u64);
+        }
+        pruned
+    }
+
+    pub fn stats(&self) -> Vec<EpochStats> {
+        self.epochs
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| EpochStats {
+                epoch: index as u64,
+                notes: entry.notes,
+                spent: entry.spent,
+                closed: entry.notes == self.epoch_length,
+                pruned: entry.pruned,
+            })
+            .collect()
+    }
+}
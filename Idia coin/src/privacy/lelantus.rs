@@ -1,6 +1,13 @@
 use curve25519_dalek::{Scalar, RistrettoPoint};
+use idia_core::crypto::OneOfManyProof;
 use merlin::Transcript;
 use rand_core::{RngCore, OsRng};
+use sha2::Sha512;
+
+use super::address::SparkAddress;
+use super::epochs::{EpochError, EpochPool, EpochStats};
+use super::merkle::{IncrementalMerkleTree, MerkleError};
+use super::nullifiers::{NullifierError, NullifierSet};
 
 pub struct LelantusParameters {
     pub generators: Vec<RistrettoPoint>,
@@ -8,6 +15,10 @@ pub struct LelantusParameters {
     pub epoch_length: u64,
 }
 
+// The nullifier is the note's serial number, committed to alongside the
+// value: C = s·S + v·G + r·H. Revealing s at spend time says nothing about
+// which commitment it belongs to.
+#[derive(Debug, Clone)]
 pub struct SparkNote {
     pub value: u64,
     pub randomness: Scalar,
@@ -17,10 +28,74 @@ pub struct SparkNote {
 
 pub struct SpendProof {
     pub nullifier: Scalar,
+    // Anonymity set the membership proof is against
+    pub epoch: u64,
+    // Fresh commitment to the spent value, v·G + r'·H, for balancing
+    pub value_commitment: RistrettoPoint,
+    // The spent note is among the anonymity set, without saying which
+    pub membership: OneOfManyProof,
     pub proof: BulletproofRangeProof,
     pub signature: SchnorrSignature,
 }
 
+impl SpendProof {
+    // What the proof shifts the anonymity set by: every C_i - s·S - C' is a
+    // commitment to zero exactly when C_i is the spent note
+    pub(crate) fn set_offset(nullifier: Scalar, value_commitment: RistrettoPoint) -> RistrettoPoint {
+        serial_generator() * nullifier + value_commitment
+    }
+
+    pub fn verify(
+        &self,
+        params: &LelantusParameters,
+        anonymity_set: &[RistrettoPoint],
+        recipient: &SparkAddress,
+    ) -> Result<bool, PrivacyError> {
+        Self::verify_batch(params, anonymity_set, &[(self, recipient)])
+    }
+
+    // Verifies spends from one epoch against its anonymity set. Membership proofs
+    // share a single multiscalar multiplication, which dominates the cost;
+    // range proofs are still checked one at a time.
+    pub fn verify_batch(
+        params: &LelantusParameters,
+        anonymity_set: &[RistrettoPoint],
+        spends: &[(&SpendProof, &SparkAddress)],
+    ) -> Result<bool, PrivacyError> {
+        let membership = spends.iter().map(|(spend, recipient)| {
+            (
+                &spend.membership,
+                spend_transcript(&spend.nullifier, &spend.value_commitment, &recipient.to_bytes()),
+                Self::set_offset(spend.nullifier, spend.value_commitment),
+            )
+        });
+        if !OneOfManyProof::verify_batch(membership, anonymity_set, &params.generators[0], &params.h) {
+            return Ok(false);
+        }
+        Ok(spends.iter().all(|(spend, _)| {
+            let mut transcript = Transcript::new(b"lelantus-range-proof");
+            spend
+                .proof
+                .verify_single(&mut transcript, &spend.value_commitment.compress(), 64, &params.generators, params.h)
+                .is_ok()
+        }))
+    }
+}
+
+pub(crate) fn serial_generator() -> RistrettoPoint {
+    RistrettoPoint::hash_from_bytes::<Sha512>(b"idia-spark-serial")
+}
+
+// Binds the membership proof to the spend it belongs to; `recipient` is an
+// encoded Spark address, or the one-time key of a redeem output
+pub(crate) fn spend_transcript(nullifier: &Scalar, value_commitment: &RistrettoPoint, recipient: &[u8]) -> Transcript {
+    let mut transcript = Transcript::new(b"lelantus-spend");
+    transcript.append_message(b"nullifier", nullifier.as_bytes());
+    transcript.append_message(b"value", value_commitment.compress().as_bytes());
+    transcript.append_message(b"recipient", recipient);
+    transcript
+}
+
 pub struct MintProof {
     pub commitment: RistrettoPoint,
     pub range_proof: BulletproofRangeProof,
@@ -28,20 +103,27 @@ pub struct MintProof {
 
 pub struct LelantusProtocol {
     params: LelantusParameters,
-    merkle_tree: SparseMerkleTree,
-    note_commitments: Vec<RistrettoPoint>,
-    nullifier_set: HashSet<Scalar>,
+    // Same commitments as `pool`, persisted, for membership
+    // witnesses against recent roots
+    merkle_tree: IncrementalMerkleTree,
+    pool: EpochPool,
+    // Nullifiers confirmed on chain, persisted per block
+    nullifiers: NullifierSet,
+    // Spent by us but not yet in a connected block
+    pending_nullifiers: HashSet<Scalar>,
 }
 
 impl LelantusProtocol {
-    pub fn mint(&mut self, value: u64) -> Result<(SparkNote, MintProof), PrivacyError> {
+    // The note joins the pool once its mint is mined, in `connect_spark_block`
+    pub fn mint(&self, value: u64) -> Result<(SparkNote, MintProof), PrivacyError> {
         let mut rng = OsRng;
         
         // Generate randomness
         let randomness = Scalar::random(&mut rng);
+        let serial = Scalar::random(&mut rng);
         
         // Create commitment
-        let commitment = self.commit_value(value, randomness)?;
+        let commitment = self.commit_value(value, randomness)? + serial_generator() * serial;
         
         // Generate range proof
         let (range_proof, _) = self.prove_range(value, randomness)?;
@@ -51,7 +133,7 @@ impl LelantusProtocol {
             value,
             randomness,
             commitment,
-            nullifier: self.derive_nullifier(&commitment),
+            nullifier: serial,
         };
         
         // Create proof
@@ -60,30 +142,19 @@ impl LelantusProtocol {
             range_proof,
         };
         
-        // Update state
-        self.note_commitments.push(commitment);
-        self.merkle_tree.insert(commitment.compress().to_bytes());
-        
         Ok((note, proof))
     }
     
     pub fn spend(
         &mut self,
         note: SparkNote,
-        recipient: PublicKey,
+        recipient: SparkAddress,
     ) -> Result<SpendProof, PrivacyError> {
-        // Verify note exists
-        if !self.merkle_tree.contains(&note.commitment.compress().to_bytes()) {
-            return Err(PrivacyError::NoteNotFound);
-        }
-        
-        // Check nullifier not already spent
-        if self.nullifier_set.contains(&note.nullifier) {
-            return Err(PrivacyError::NullifierAlreadySpent);
-        }
-        
-        // Generate range proof
-        let (range_proof, _) = self.prove_range(note.value, note.randomness)?;
+        // Re-commit to the value under fresh randomness
+        let value_randomness = Scalar::random(&mut OsRng);
+        let (range_proof, _) = self.prove_range(note.value, value_randomness)?;
+        let (epoch, value_commitment, membership) =
+            self.prove_membership(&note, value_randomness, &recipient.to_bytes())?;
         
         // Generate signature
         let signature = self.sign_spend(&note, &recipient)?;
@@ -91,16 +162,140 @@ impl LelantusProtocol {
         // Create proof
         let proof = SpendProof {
             nullifier: note.nullifier,
+            epoch,
+            value_commitment,
+            membership,
             proof: range_proof,
             signature,
         };
         
-        // Update nullifier set
-        self.nullifier_set.insert(note.nullifier);
+        // Held until the spend is mined
+        self.pending_nullifiers.insert(note.nullifier);
         
         Ok(proof)
     }
     
+    // Commits to the note's value under `value_randomness` and proves the
+    // note is in its epoch, bound to `recipient`
+    pub(crate) fn prove_membership(
+        &self,
+        note: &SparkNote,
+        value_randomness: Scalar,
+        recipient: &[u8],
+    ) -> Result<(u64, RistrettoPoint, OneOfManyProof), PrivacyError> {
+        // Only the spender learns where the note sits; the proof hides it
+        let (epoch, index) = self.pool.find(&note.commitment).ok_or(PrivacyError::NoteNotFound)?;
+        let anonymity_set = self.pool.set(epoch).map_err(|_| PrivacyError::NoteNotFound)?;
+        
+        // Check nullifier not already spent
+        if self.nullifiers.contains(note.nullifier.as_bytes())
+            || self.pending_nullifiers.contains(&note.nullifier)
+        {
+            return Err(PrivacyError::NullifierAlreadySpent);
+        }
+        
+        let value_commitment = self.commit_value(note.value, value_randomness)?;
+        
+        // C_l - s·S - C' = (r - r')·H
+        let offset = SpendProof::set_offset(note.nullifier, value_commitment);
+        let mut transcript = spend_transcript(&note.nullifier, &value_commitment, recipient);
+        let membership = OneOfManyProof::prove(
+            &mut transcript,
+            anonymity_set,
+            &offset,
+            index,
+            note.randomness - value_randomness,
+            &self.params.generators[0],
+            &self.params.h,
+        )
+        .ok_or(PrivacyError::NoteNotFound)?;
+        Ok((epoch, value_commitment, membership))
+    }
+    
+    // Verifies spends from any epochs, batching those that share one
+    pub fn verify_spends(&self, spends: &[(&SpendProof, &SparkAddress)]) -> Result<bool, PrivacyError> {
+        let mut by_epoch: HashMap<u64, Vec<(&SpendProof, &SparkAddress)>> = HashMap::new();
+        for (spend, recipient) in spends {
+            by_epoch.entry(spend.epoch).or_default().push((spend, recipient));
+        }
+        for (epoch, spends) in by_epoch {
+            let Ok(anonymity_set) = self.pool.set(epoch) else {
+                return Ok(false);
+            };
+            if !SpendProof::verify_batch(&self.params, anonymity_set, &spends)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+    
+    pub(crate) fn params(&self) -> &LelantusParameters {
+        &self.params
+    }
+    
+    pub(crate) fn anonymity_set(&self, epoch: u64) -> Result<&[RistrettoPoint], EpochError> {
+        self.pool.set(epoch)
+    }
+    
+    pub(crate) fn pool_mut(&mut self) -> &mut EpochPool {
+        &mut self.pool
+    }
+    
+    pub fn pool_len(&self) -> u64 {
+        self.pool.len()
+    }
+    
+    pub fn epoch_stats(&self) -> Vec<EpochStats> {
+        self.pool.stats()
+    }
+    
+    pub(crate) fn insert_note_commitments(&mut self, commitments: &[RistrettoPoint]) -> Result<(), MerkleError> {
+        let leaves: Vec<[u8; 32]> = commitments.iter().map(|c| c.compress().to_bytes()).collect();
+        self.merkle_tree.insert_batch(&leaves)?;
+        self.pool.push(commitments);
+        Ok(())
+    }
+    
+    // Drops notes minted in disconnected blocks
+    pub(crate) fn truncate_note_commitments(&mut self, len: u64) -> Result<(), MerkleError> {
+        self.merkle_tree.truncate(len)?;
+        self.pool.truncate(len);
+        Ok(())
+    }
+    
+    pub fn merkle_tree(&self) -> &IncrementalMerkleTree {
+        &self.merkle_tree
+    }
+    
+    pub(crate) fn mark_pending(&mut self, nullifier: Scalar) {
+        self.pending_nullifiers.insert(nullifier);
+    }
+    
+    // Marks a block's spends as final; fails without changes if any of them
+    // was already spent
+    pub fn connect_block(
+        &mut self,
+        height: u64,
+        block_hash: [u8; 32],
+        nullifiers: &[[u8; 32]],
+    ) -> Result<[u8; 32], NullifierError> {
+        let digest = self.nullifiers.connect_block(height, block_hash, nullifiers)?;
+        self.pending_nullifiers.retain(|n| !nullifiers.contains(&n.to_bytes()));
+        Ok(digest)
+    }
+    
+    pub fn disconnect_block(&mut self, height: u64) -> Result<(), NullifierError> {
+        self.nullifiers.disconnect_block(height).map(|_| ())
+    }
+    
+    pub fn nullifiers(&self) -> &NullifierSet {
+        &self.nullifiers
+    }
+    
+    pub fn nullifiers_mut(&mut self) -> &mut NullifierSet {
+        &mut self.nullifiers
+    }
+    
     fn commit_value(&self, value: u64, randomness: Scalar) -> Result<RistrettoPoint, PrivacyError> {
         let value_scalar = Scalar::from(value);
         let commitment = self.params.h * randomness + 
@@ -128,16 +323,10 @@ impl LelantusProtocol {
         Ok((proof, commitments))
     }
     
-    fn derive_nullifier(&self, commitment: &RistrettoPoint) -> Scalar {
-        let mut hasher = Blake2b::new();
-        hasher.update(&commitment.compress().to_bytes());
-        Scalar::from_hash(hasher)
-    }
-    
     fn sign_spend(
         &self,
         note: &SparkNote,
-        recipient: &PublicKey,
+        recipient: &SparkAddress,
     ) -> Result<SchnorrSignature, PrivacyError> {
         let mut rng = OsRng;
         let keypair = KeyPair::generate(&mut rng);
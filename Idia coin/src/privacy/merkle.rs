@@ -0,0 +1,308 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const TREE_DEPTH: usize = 32;
+// Recent roots a membership witness may be made against, so a proof built
+// a few blocks ago is still accepted
+pub const ROOT_HISTORY: usize = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MerkleError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Tree is full")]
+    Full,
+    #[error("Leaf file is corrupt: {0} trailing bytes")]
+    Corrupt(usize),
+}
+
+fn hash_leaf(leaf: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(leaf);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+// Roots of all-empty subtrees, by height
+fn empty_roots() -> [[u8; 32]; TREE_DEPTH + 1] {
+    let mut empty = [[0u8; 32]; TREE_DEPTH + 1];
+    for height in 1..=TREE_DEPTH {
+        empty[height] = hash_node(&empty[height - 1], &empty[height - 1]);
+    }
+    empty
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleWitness {
+    pub index: u64,
+    // Bottom-up, one per level
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl MerkleWitness {
+    pub fn root(&self, leaf: &[u8; 32]) -> [u8; 32] {
+        let mut hash = hash_leaf(leaf);
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            hash = if (self.index >> height) & 1 == 0 {
+                hash_node(&hash, sibling)
+            } else {
+                hash_node(sibling, &hash)
+            };
+        }
+        hash
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct RootRecord {
+    leaves: u64,
+    root: [u8; 32],
+}
+
+// Append-only Merkle tree of fixed depth over note commitments. Leaves are
+// appended to a flat file of 32-byte records; interior nodes are rebuilt in
+// memory on open, and recent roots are kept alongside in JSON.
+pub struct IncrementalMerkleTree {
+    path: PathBuf,
+    empty: [[u8; 32]; TREE_DEPTH + 1],
+    // levels[0] holds leaf hashes, levels[TREE_DEPTH] the root once non-empty
+    levels: Vec<Vec<[u8; 32]>>,
+    history: VecDeque<RootRecord>,
+}
+
+impl IncrementalMerkleTree {
+    pub fn open(path: PathBuf) -> Result<Self, MerkleError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut tree = Self {
+            path,
+            empty: empty_roots(),
+            levels: vec![Vec::new(); TREE_DEPTH + 1],
+            history: VecDeque::new(),
+        };
+
+        if tree.path.exists() {
+            let mut bytes = Vec::new();
+            File::open(&tree.path)?.read_to_end(&mut bytes)?;
+            if bytes.len() % 32 != 0 {
+                return Err(MerkleError::Corrupt(bytes.len() % 32));
+            }
+            tree.levels[0] = bytes
+                .chunks_exact(32)
+                .map(|chunk| hash_leaf(chunk.try_into().unwrap()))
+                .collect();
+            tree.rehash_from(0);
+        }
+
+        let history_path = tree.history_path();
+        if history_path.exists() {
+            let records: Vec<RootRecord> = serde_json::from_slice(&fs::read(history_path)?)?;
+            // Roots past our leaves come from writes that never landed
+            tree.history = records.into_iter().filter(|r| r.leaves <= tree.len()).collect();
+        }
+        if tree.history.back().map(|r| r.leaves) != Some(tree.len()) {
+            tree.push_root();
+        }
+        Ok(tree)
+    }
+
+    fn history_path(&self) -> PathBuf {
+        self.path.with_extension("roots.json")
+    }
+
+    fn save_history(&self) -> Result<(), MerkleError> {
+        let path = self.history_path();
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(&self.history)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn push_root(&mut self) {
+        let record = RootRecord {
+            leaves: self.len(),
+            root: self.root(),
+        };
+        self.history.push_back(record);
+        while self.history.len() > ROOT_HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.levels[0].len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels[TREE_DEPTH].first().copied().unwrap_or(self.empty[TREE_DEPTH])
+    }
+
+    pub fn is_known_root(&self, root: &[u8; 32]) -> bool {
+        self.history.iter().any(|record| record.root == *root)
+    }
+
+    // Rebuilds every level above `leaf` onwards, after an append or truncate
+    fn rehash_from(&mut self, leaf: usize) {
+        let mut start = leaf;
+        for height in 1..=TREE_DEPTH {
+            start >>= 1;
+            let (below, above) = self.levels.split_at_mut(height);
+            let (children, level) = (&below[height - 1], &mut above[0]);
+            level.truncate(start);
+            for index in start..children.len().div_ceil(2) {
+                let left = &children[2 * index];
+                let right = children.get(2 * index + 1).unwrap_or(&self.empty[height - 1]);
+                level.push(hash_node(left, right));
+            }
+        }
+    }
+
+    pub fn insert(&mut self, leaf: [u8; 32]) -> Result<u64, MerkleError> {
+        self.insert_batch(&[leaf])
+    }
+
+    // Appends all leaves with one write and one rehash of each level,
+    // returning the index of the first
+    pub fn insert_batch(&mut self, leaves: &[[u8; 32]]) -> Result<u64, MerkleError> {
+        let first = self.len();
+        if first + leaves.len() as u64 > 1u64 << TREE_DEPTH {
+            return Err(MerkleError::Full);
+        }
+        if leaves.is_empty() {
+            return Ok(first);
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&leaves.concat())?;
+        file.sync_data()?;
+
+        self.levels[0].extend(leaves.iter().map(hash_leaf));
+        self.rehash_from(first as usize);
+        self.push_root();
+        self.save_history()?;
+        Ok(first)
+    }
+
+    // Drops leaves from `len` on, when the blocks that added them are
+    // disconnected
+    pub fn truncate(&mut self, len: u64) -> Result<(), MerkleError> {
+        if len >= self.len() {
+            return Ok(());
+        }
+        OpenOptions::new().write(true).open(&self.path)?.set_len(len * 32)?;
+        self.levels[0].truncate(len as usize);
+        self.rehash_from(len as usize);
+        self.history.retain(|record| record.leaves <= len);
+        if self.history.back().map(|r| r.leaves) != Some(len) {
+            self.push_root();
+        }
+        self.save_history()
+    }
+
+    pub fn witness(&self, index: u64) -> Option<MerkleWitness> {
+        if index >= self.len() {
+            return None;
+        }
+        let siblings = (0..TREE_DEPTH)
+            .map(|height| {
+                let sibling = ((index >> height) ^ 1) as usize;
+                self.levels[height].get(sibling).copied().unwrap_or(self.empty[height])
+            })
+            .collect();
+        Some(MerkleWitness { index, siblings })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(i: u8) -> [u8; 32] {
+        [i; 32]
+    }
+
+    #[test]
+    fn test_witness_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tree = IncrementalMerkleTree::open(dir.path().join("leaves")).unwrap();
+        let empty_root = tree.root();
+        assert_eq!(tree.insert(leaf(0)).unwrap(), 0);
+        assert_eq!(tree.insert_batch(&[leaf(1), leaf(2), leaf(3), leaf(4)]).unwrap(), 1);
+
+        for i in 0..5 {
+            let witness = tree.witness(i).unwrap();
+            assert_eq!(witness.siblings.len(), TREE_DEPTH);
+            assert_eq!(witness.root(&leaf(i as u8)), tree.root());
+        }
+        assert!(tree.witness(5).is_none());
+        assert!(tree.is_known_root(&empty_root));
+        assert!(tree.is_known_root(&tree.root()));
+    }
+
+    #[test]
+    fn test_witness_rejects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tree = IncrementalMerkleTree::open(dir.path().join("leaves")).unwrap();
+        tree.insert_batch(&[leaf(1), leaf(2), leaf(3)]).unwrap();
+        let witness = tree.witness(1).unwrap();
+
+        assert_ne!(witness.root(&leaf(9)), tree.root());
+        let mut tampered = witness.clone();
+        tampered.index = 0;
+        assert_ne!(tampered.root(&leaf(2)), tree.root());
+        let mut tampered = witness;
+        tampered.siblings[0][0] ^= 1;
+        assert_ne!(tampered.root(&leaf(2)), tree.root());
+    }
+
+    #[test]
+    fn test_tree_survives_restart_and_truncate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("leaves");
+        let mut tree = IncrementalMerkleTree::open(path.clone()).unwrap();
+        tree.insert_batch(&[leaf(1), leaf(2)]).unwrap();
+        let two_leaves = tree.root();
+        tree.insert(leaf(3)).unwrap();
+        let root = tree.root();
+        drop(tree);
+
+        let mut tree = IncrementalMerkleTree::open(path.clone()).unwrap();
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.root(), root);
+        assert!(tree.is_known_root(&two_leaves));
+
+        tree.truncate(2).unwrap();
+        assert_eq!(tree.root(), two_leaves);
+        assert!(!tree.is_known_root(&root));
+        drop(tree);
+        assert_eq!(IncrementalMerkleTree::open(path).unwrap().root(), two_leaves);
+    }
+
+    #[test]
+    fn test_rejects_corrupt_leaf_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("leaves");
+        fs::write(&path, [0u8; 40]).unwrap();
+        assert!(matches!(IncrementalMerkleTree::open(path), Err(MerkleError::Corrupt(8))));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use idia_core::crypto::OneOfManyProof;
+use idia_core::{Block, Output, SparkMint, SparkRedeem, Transaction};
+use merlin::Transcript;
+use rand_core::OsRng;
+
+use super::lelantus::{serial_generator, spend_transcript, LelantusProtocol, SparkNote, SpendProof};
+
+use super::address::SparkAddress;
+use super::epochs::EpochError;
+use super::merkle::MerkleError;
+use super::note::{NoteError, NotePlaintext};
+use super::nullifiers::NullifierError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("Invalid Spark record: {0}")]
+    InvalidRecord(&'static str),
+    #[error("Spark note already redeemed")]
+    AlreadyRedeemed,
+    #[error("Privacy error: {0}")]
+    Privacy(#[from] PrivacyError),
+    #[error("Nullifier error: {0}")]
+    Nullifier(#[from] NullifierError),
+    #[error("Merkle tree error: {0}")]
+    Merkle(#[from] MerkleError),
+    #[error("Note error: {0}")]
+    Note(#[from] NoteError),
+    #[error("Epoch error: {0}")]
+    Epoch(#[from] EpochError),
+}
+
+// Shows a mint's commitment holds exactly its public value: knowledge of
+// (s, r) with C - v·G = s·S + r·H
+struct OpeningProof {
+    nonce: RistrettoPoint,
+    z_serial: Scalar,
+    z_blinding: Scalar,
+}
+
+impl OpeningProof {
+    fn challenge(commitment: &RistrettoPoint, value: u64, nonce: &RistrettoPoint) -> Scalar {
+        let mut transcript = Transcript::new(b"spark-mint");
+        transcript.append_message(b"commitment", commitment.compress().as_bytes());
+        transcript.append_u64(b"value", value);
+        transcript.append_message(b"nonce", nonce.compress().as_bytes());
+        let mut bytes = [0u8; 64];
+        transcript.challenge_bytes(b"c", &mut bytes);
+        Scalar::from_bytes_mod_order_wide(&bytes)
+    }
+
+    fn prove(note: &SparkNote, h: &RistrettoPoint) -> Self {
+        let mut rng = OsRng;
+        let (k_serial, k_blinding) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let nonce = serial_generator() * k_serial + h * k_blinding;
+        let c = Self::challenge(&note.commitment, note.value, &nonce);
+        Self {
+            nonce,
+            z_serial: k_serial + c * note.nullifier,
+            z_blinding: k_blinding + c * note.randomness,
+        }
+    }
+
+    fn verify(&self, commitment: &RistrettoPoint, value: u64, g: &RistrettoPoint, h: &RistrettoPoint) -> bool {
+        let c = Self::challenge(commitment, value, &self.nonce);
+        serial_generator() * self.z_serial + h * self.z_blinding
+            == self.nonce + (commitment - g * Scalar::from(value)) * c
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(96);
+        bytes.extend_from_slice(self.nonce.compress().as_bytes());
+        bytes.extend_from_slice(self.z_serial.as_bytes());
+        bytes.extend_from_slice(self.z_blinding.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 96 {
+            return None;
+        }
+        let scalar = |range: std::ops::Range<usize>| {
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(bytes[range].try_into().unwrap()))
+        };
+        Some(Self {
+            nonce: CompressedRistretto::from_slice(&bytes[..32]).ok()?.decompress()?,
+            z_serial: scalar(32..64)?,
+            z_blinding: scalar(64..96)?,
+        })
+    }
+}
+
+fn decompress(bytes: &[u8; 32]) -> Result<RistrettoPoint, MigrationError> {
+    CompressedRistretto(*bytes)
+        .decompress()
+        .ok_or(MigrationError::InvalidRecord("commitment is not a valid point"))
+}
+
+// What a redeem's membership proof is bound to
+fn output_binding(output: &Output) -> [u8; 32] {
+    output.stealth_pubkey.compress().to_bytes()
+}
+
+impl LelantusProtocol {
+    // A note for `value` paid in from ring-CT inputs, encrypted to
+    // `recipient`. The note only joins the pool once the mint is mined, via
+    // `connect_spark_block`.
+    pub fn mint_record(&self, value: u64, recipient: &SparkAddress, memo: &[u8]) -> Result<(SparkNote, SparkMint), MigrationError> {
+        let mut rng = OsRng;
+        let (randomness, serial) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let params = self.params();
+        let commitment = params.generators[0] * Scalar::from(value) + params.h * randomness + serial_generator() * serial;
+        let note = SparkNote {
+            value,
+            randomness,
+            commitment,
+            nullifier: serial,
+        };
+        let commitment = commitment.compress().to_bytes();
+        let plaintext = NotePlaintext {
+            diversifier: recipient.diversifier,
+            value,
+            randomness,
+            serial,
+            memo: memo.to_vec(),
+        };
+        let mint = SparkMint {
+            value,
+            commitment,
+            proof: OpeningProof::prove(&note, &params.h).to_bytes(),
+            encrypted_note: plaintext.encrypt(recipient, &commitment)?,
+        };
+        Ok((note, mint))
+    }
+
+    // Spends `note` into `output`, which the transaction must carry at
+    // `output_index`. The value is public, so it is re-committed without
+    // randomness and consensus checks v·G directly.
+    pub fn redeem_record(&mut self, note: &SparkNote, output: &Output, output_index: u32) -> Result<SparkRedeem, MigrationError> {
+        let (epoch, _, membership) = self.prove_membership(note, Scalar::ZERO, &output_binding(output))?;
+        self.mark_pending(note.nullifier);
+        Ok(SparkRedeem {
+            nullifier: note.nullifier.to_bytes(),
+            epoch,
+            value: note.value,
+            output_index,
+            proof: membership.to_bytes(),
+        })
+    }
+
+    pub fn validate_spark_transaction(&self, tx: &Transaction) -> Result<(), MigrationError> {
+        let params = self.params();
+        let g = params.generators[0];
+
+        for mint in &tx.spark_mints {
+            if !mint.verify() {
+                return Err(MigrationError::InvalidRecord("malformed mint"));
+            }
+            let commitment = decompress(&mint.commitment)?;
+            let valid = OpeningProof::from_bytes(&mint.proof)
+                .is_some_and(|proof| proof.verify(&commitment, mint.value, &g, &params.h));
+            if !valid {
+                return Err(MigrationError::InvalidRecord("mint does not open to its value"));
+            }
+        }
+
+        let mut by_epoch: HashMap<u64, Vec<_>> = HashMap::new();
+        for redeem in &tx.spark_redeems {
+            if !redeem.verify(tx.outputs.len()) {
+                return Err(MigrationError::InvalidRecord("malformed redeem"));
+            }
+            if self.nullifiers().contains(&redeem.nullifier) {
+                return Err(MigrationError::AlreadyRedeemed);
+            }
+            let nullifier = Option::<Scalar>::from(Scalar::from_canonical_bytes(redeem.nullifier))
+                .ok_or(MigrationError::InvalidRecord("non-canonical nullifier"))?;
+            let proof = OneOfManyProof::from_bytes(&redeem.proof)
+                .ok_or(MigrationError::InvalidRecord("malformed membership proof"))?;
+
+            let value_commitment = g * Scalar::from(redeem.value);
+            let output = &tx.outputs[redeem.output_index as usize];
+            let transcript = spend_transcript(&nullifier, &value_commitment, &output_binding(output));
+            let offset = SpendProof::set_offset(nullifier, value_commitment);
+            by_epoch.entry(redeem.epoch).or_default().push((proof, transcript, offset));
+        }
+        for (epoch, membership) in by_epoch {
+            let anonymity_set = self.anonymity_set(epoch)?;
+            let proofs = membership.iter().map(|(proof, transcript, offset)| (proof, transcript.clone(), *offset));
+            if !OneOfManyProof::verify_batch(proofs, anonymity_set, &g, &params.h) {
+                return Err(MigrationError::InvalidRecord("note is not in its epoch"));
+            }
+        }
+        Ok(())
+    }
+
+    // Applies a validated block: minted notes join the pool, redeemed
+    // nullifiers become final and epochs spent out long enough are pruned
+    pub fn connect_spark_block(&mut self, block: &Block) -> Result<[u8; 32], MigrationError> {
+        let minted = block
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.spark_mints)
+            .map(|mint| decompress(&mint.commitment))
+            .collect::<Result<Vec<_>, _>>()?;
+        let nullifiers: Vec<[u8; 32]> = block.spark_nullifiers().copied().collect();
+        let height = block.header.height;
+        let pool_len = self.pool_len();
+        self.insert_note_commitments(&minted)?;
+        let digest = match self.connect_block(height, block.hash(), &nullifiers) {
+            Ok(digest) => digest,
+            Err(e) => {
+                self.truncate_note_commitments(pool_len)?;
+                return Err(e.into());
+            }
+        };
+
+        let pool = self.pool_mut();
+        for redeem in block.transactions.iter().flat_map(|tx| &tx.spark_redeems) {
+            pool.record_spend(redeem.epoch, height)?;
+        }
+        pool.prune(height);
+        Ok(digest)
+    }
+
+    pub fn disconnect_spark_block(&mut self, block: &Block) -> Result<(), MigrationError> {
+        self.disconnect_block(block.header.height)?;
+        for redeem in block.transactions.iter().flat_map(|tx| &tx.spark_redeems) {
+            self.pool_mut().unrecord_spend(redeem.epoch);
+        }
+        let minted = block.transactions.iter().map(|tx| tx.spark_mints.len() as u64).sum::<u64>();
+        let len = self.pool_len().saturating_sub(minted);
+        self.truncate_note_commitments(len)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use idia_core::{Block, SparkMint};
+
+use super::address::{diversifier_point, IncomingViewKey, SparkAddress};
+use super::lelantus::{serial_generator, LelantusParameters, SparkNote};
+use crate::compliance::sealing::{SealedBox, SealingError};
+
+pub const MAX_MEMO: usize = 64;
+// diversifier | value | randomness | serial | memo length | memo, padded so
+// every ciphertext is the same size whatever the memo
+const PLAINTEXT_LEN: usize = 8 + 8 + 32 + 32 + 1 + MAX_MEMO;
+const NOTE_DOMAIN: &[u8] = b"idia-spark-note";
+
+#[derive(Debug, thiserror::Error)]
+pub enum NoteError {
+    #[error("Memo is {0} bytes, at most {MAX_MEMO} allowed")]
+    MemoTooLong(usize),
+    #[error("Malformed encrypted note")]
+    Malformed,
+    #[error("Sealing error: {0}")]
+    Sealing(#[from] SealingError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotePlaintext {
+    pub diversifier: [u8; 8],
+    pub value: u64,
+    pub randomness: Scalar,
+    pub serial: Scalar,
+    pub memo: Vec<u8>,
+}
+
+// Bound to the commitment so a ciphertext can't be replayed onto another note
+fn domain(commitment: &[u8; 32]) -> Vec<u8> {
+    [NOTE_DOMAIN, commitment.as_slice()].concat()
+}
+
+impl NotePlaintext {
+    fn to_bytes(&self) -> [u8; PLAINTEXT_LEN] {
+        let mut bytes = [0u8; PLAINTEXT_LEN];
+        bytes[..8].copy_from_slice(&self.diversifier);
+        bytes[8..16].copy_from_slice(&self.value.to_le_bytes());
+        bytes[16..48].copy_from_slice(self.randomness.as_bytes());
+        bytes[48..80].copy_from_slice(self.serial.as_bytes());
+        bytes[80] = self.memo.len() as u8;
+        bytes[81..81 + self.memo.len()].copy_from_slice(&self.memo);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != PLAINTEXT_LEN || bytes[80] as usize > MAX_MEMO {
+            return None;
+        }
+        let scalar = |range: std::ops::Range<usize>| {
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(bytes[range].try_into().unwrap()))
+        };
+        Some(Self {
+            diversifier: bytes[..8].try_into().unwrap(),
+            value: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            randomness: scalar(16..48)?,
+            serial: scalar(48..80)?,
+            memo: bytes[81..81 + bytes[80] as usize].to_vec(),
+        })
+    }
+
+    // Sealed to the address's Q1 on its diversified base, so only the
+    // incoming view key that produced the address can open it
+    pub fn encrypt(&self, recipient: &SparkAddress, commitment: &[u8; 32]) -> Result<Vec<u8>, NoteError> {
+        if self.memo.len() > MAX_MEMO {
+            return Err(NoteError::MemoTooLong(self.memo.len()));
+        }
+        let sealed = SealedBox::seal_on(
+            &diversifier_point(&recipient.diversifier),
+            &self.to_bytes(),
+            &domain(commitment),
+            &recipient.q1,
+        )?;
+        let mut bytes = Vec::with_capacity(32 + 12 + sealed.ciphertext.len());
+        bytes.extend_from_slice(&sealed.ephemeral_key);
+        bytes.extend_from_slice(&sealed.nonce);
+        bytes.extend_from_slice(&sealed.ciphertext);
+        Ok(bytes)
+    }
+
+    pub fn decrypt(ivk: &IncomingViewKey, encrypted: &[u8], commitment: &[u8; 32]) -> Result<Self, NoteError> {
+        if encrypted.len() < 44 {
+            return Err(NoteError::Malformed);
+        }
+        let sealed = SealedBox {
+            ephemeral_key: encrypted[..32].try_into().unwrap(),
+            nonce: encrypted[32..44].try_into().unwrap(),
+            ciphertext: encrypted[44..].to_vec(),
+        };
+        let plaintext = sealed.open(&domain(commitment), ivk.diversifier_secret())?;
+        Self::from_bytes(&plaintext).ok_or(NoteError::Malformed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReceivedNote {
+    pub note: SparkNote,
+    pub address_index: u64,
+    pub memo: Vec<u8>,
+    pub height: u64,
+}
+
+// Finds notes minted to any of our addresses by trial-decrypting every
+// mint in each block. A note is only reported if its opening reproduces the
+// on-chain commitment, so a sender can't claim a value the note doesn't hold.
+pub struct NoteScanner {
+    ivk: IncomingViewKey,
+    value_generator: RistrettoPoint,
+    blinding_generator: RistrettoPoint,
+    scanned_height: Option<u64>,
+}
+
+impl NoteScanner {
+    pub fn new(ivk: IncomingViewKey, params: &LelantusParameters) -> Self {
+        Self {
+            ivk,
+            value_generator: params.generators[0],
+            blinding_generator: params.h,
+            scanned_height: None,
+        }
+    }
+
+    pub fn scanned_height(&self) -> Option<u64> {
+        self.scanned_height
+    }
+
+    pub fn scan_mint(&self, mint: &SparkMint, height: u64) -> Option<ReceivedNote> {
+        if mint.encrypted_note.is_empty() {
+            return None;
+        }
+        let plaintext = NotePlaintext::decrypt(&self.ivk, &mint.encrypted_note, &mint.commitment).ok()?;
+        let commitment = self.value_generator * Scalar::from(plaintext.value)
+            + self.blinding_generator * plaintext.randomness
+            + serial_generator() * plaintext.serial;
+        if commitment.compress().to_bytes() != mint.commitment || plaintext.value != mint.value {
+            tracing::warn!("Spark note at height {} decrypts but does not match its commitment", height);
+            return None;
+        }
+
+        Some(ReceivedNote {
+            note: SparkNote {
+                value: plaintext.value,
+                randomness: plaintext.randomness,
+                commitment,
+                nullifier: plaintext.serial,
+            },
+            address_index: self.ivk.diversifier_index(&plaintext.diversifier),
+            memo: plaintext.memo,
+            height,
+        })
+    }
+
+    pub fn scan_block(&mut self, block: &Block) -> Vec<ReceivedNote> {
+        let height = block.header.height;
+        let found: Vec<ReceivedNote> = block
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.spark_mints)
+            .filter_map(|mint| self.scan_mint(mint, height))
+            .collect();
+        if !found.is_empty() {
+            tracing::info!("Found {} Spark notes at height {}", found.len(), height);
+        }
+        self.scanned_height = Some(height);
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::privacy::address::SpendKey;
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use idia_core::NetworkType;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+
+    fn params() -> LelantusParameters {
+        LelantusParameters {
+            generators: vec![RISTRETTO_BASEPOINT_POINT],
+            h: RistrettoPoint::hash_from_bytes::<Sha512>(b"note-test-h"),
+            epoch_length: 16,
+        }
+    }
+
+    fn plaintext(diversifier: [u8; 8]) -> NotePlaintext {
+        NotePlaintext {
+            diversifier,
+            value: 500,
+            randomness: Scalar::random(&mut OsRng),
+            serial: Scalar::random(&mut OsRng),
+            memo: b"invoice 42".to_vec(),
+        }
+    }
+
+    // A mint of `plaintext` as a sender would publish it
+    fn mint(params: &LelantusParameters, plaintext: &NotePlaintext, recipient: &SparkAddress) -> SparkMint {
+        let commitment = (params.generators[0] * Scalar::from(plaintext.value)
+            + params.h * plaintext.randomness
+            + serial_generator() * plaintext.serial)
+            .compress()
+            .to_bytes();
+        SparkMint {
+            value: plaintext.value,
+            commitment,
+            proof: Vec::new(),
+            encrypted_note: plaintext.encrypt(recipient, &commitment).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_note_round_trip() {
+        let key = SpendKey::generate();
+        let address = key.address(NetworkType::Mainnet, 5);
+        let note = plaintext(address.diversifier);
+
+        let encrypted = note.encrypt(&address, &[7u8; 32]).unwrap();
+        let decrypted = NotePlaintext::decrypt(&key.incoming_view_key(), &encrypted, &[7u8; 32]).unwrap();
+        assert_eq!(decrypted, note);
+
+        // Ciphertexts do not reveal the memo length
+        let mut short = note.clone();
+        short.memo.clear();
+        assert_eq!(short.encrypt(&address, &[7u8; 32]).unwrap().len(), encrypted.len());
+    }
+
+    #[test]
+    fn test_note_rejects_tampering() {
+        let key = SpendKey::generate();
+        let address = key.address(NetworkType::Mainnet, 0);
+        let note = plaintext(address.diversifier);
+        let encrypted = note.encrypt(&address, &[7u8; 32]).unwrap();
+        let ivk = key.incoming_view_key();
+
+        // Replayed onto another commitment
+        assert!(NotePlaintext::decrypt(&ivk, &encrypted, &[8u8; 32]).is_err());
+        assert!(NotePlaintext::decrypt(&SpendKey::generate().incoming_view_key(), &encrypted, &[7u8; 32]).is_err());
+        let mut tampered = encrypted.clone();
+        tampered[50] ^= 1;
+        assert!(NotePlaintext::decrypt(&ivk, &tampered, &[7u8; 32]).is_err());
+        assert!(matches!(
+            NotePlaintext::decrypt(&ivk, &encrypted[..40], &[7u8; 32]),
+            Err(NoteError::Malformed)
+        ));
+
+        let mut long = note;
+        long.memo = vec![0; MAX_MEMO + 1];
+        assert!(matches!(long.encrypt(&address, &[7u8; 32]), Err(NoteError::MemoTooLong(_))));
+    }
+
+    #[test]
+    fn test_scanner_checks_commitment() {
+        let params = params();
+        let key = SpendKey::generate();
+        let address = key.address(NetworkType::Mainnet, 9);
+        let scanner = NoteScanner::new(key.incoming_view_key(), &params);
+
+        let note = plaintext(address.diversifier);
+        let received = scanner.scan_mint(&mint(&params, &note, &address), 10).unwrap();
+        assert_eq!(received.address_index, 9);
+        assert_eq!(received.note.value, 500);
+        assert_eq!(received.memo, note.memo);
+
+        // The sender claims more than the commitment holds
+        let mut inflated = note.clone();
+        inflated.value = 5_000;
+        let mut lying = mint(&params, &note, &address);
+        lying.encrypted_note = inflated.encrypt(&address, &lying.commitment).unwrap();
+        assert!(scanner.scan_mint(&lying, 10).is_none());
+
+        let elsewhere = SpendKey::generate().address(NetworkType::Mainnet, 0);
+        assert!(scanner.scan_mint(&mint(&params, &plaintext(elsewhere.diversifier), &elsewhere), 10).is_none());
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const SYNC_VERSION: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum NullifierError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+    #[error("Nullifier already spent at height {0}")]
+    AlreadySpent(u64),
+    #[error("Block {got} does not extend tip {tip:?}")]
+    NotTip { got: u64, tip: Option<u64> },
+    #[error("Nullifier sync data malformed: {0}")]
+    MalformedSync(&'static str),
+    #[error("Nullifier digest mismatch at height {0}")]
+    DigestMismatch(u64),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockNullifiers {
+    pub height: u64,
+    pub block_hash: [u8; 32],
+    // Sorted, so the block digest does not depend on transaction order
+    pub nullifiers: Vec<[u8; 32]>,
+    // Chains every block's nullifiers since genesis; two nodes with the same
+    // digest at a height hold the same set
+    pub digest: [u8; 32],
+}
+
+impl BlockNullifiers {
+    fn compute_digest(prev: &[u8; 32], height: u64, block_hash: &[u8; 32], nullifiers: &[[u8; 32]]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(prev);
+        hasher.update(height.to_le_bytes());
+        hasher.update(block_hash);
+        for nullifier in nullifiers {
+            hasher.update(nullifier);
+        }
+        hasher.finalize().into()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum JournalRecord {
+    Connect(BlockNullifiers),
+    Disconnect(u64),
+}
+
+// Spent Spark serial numbers, kept in step with the chain. Blocks are
+// connected and disconnected at the tip and journalled to disk as
+// length-prefixed bincode records, replayed on open.
+pub struct NullifierSet {
+    path: PathBuf,
+    spent: HashMap<[u8; 32], u64>,
+    blocks: BTreeMap<u64, BlockNullifiers>,
+}
+
+impl NullifierSet {
+    pub fn open(path: PathBuf) -> Result<Self, NullifierError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut set = Self {
+            path,
+            spent: HashMap::new(),
+            blocks: BTreeMap::new(),
+        };
+
+        if set.path.exists() {
+            let mut bytes = Vec::new();
+            File::open(&set.path)?.read_to_end(&mut bytes)?;
+            let mut rest = bytes.as_slice();
+            while rest.len() >= 4 {
+                let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+                if rest.len() < 4 + len {
+                    // Torn final write; the block will be connected again
+                    break;
+                }
+                match bincode::deserialize(&rest[4..4 + len])? {
+                    JournalRecord::Connect(block) => set.apply_connect(block),
+                    JournalRecord::Disconnect(height) => {
+                        set.apply_disconnect(height);
+                    }
+                }
+                rest = &rest[4 + len..];
+            }
+        }
+        Ok(set)
+    }
+
+    pub fn contains(&self, nullifier: &[u8; 32]) -> bool {
+        self.spent.contains_key(nullifier)
+    }
+
+    pub fn len(&self) -> usize {
+        self.spent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spent.is_empty()
+    }
+
+    pub fn tip(&self) -> Option<(u64, [u8; 32])> {
+        self.blocks.values().next_back().map(|block| (block.height, block.digest))
+    }
+
+    pub fn digest_at(&self, height: u64) -> Option<[u8; 32]> {
+        self.blocks.get(&height).map(|block| block.digest)
+    }
+
+    fn append(&self, record: &JournalRecord) -> Result<(), NullifierError> {
+        let bytes = bincode::serialize(record)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        file.write_all(&bytes)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    fn apply_connect(&mut self, block: BlockNullifiers) {
+        for nullifier in &block.nullifiers {
+            self.spent.insert(*nullifier, block.height);
+        }
+        self.blocks.insert(block.height, block);
+    }
+
+    fn apply_disconnect(&mut self, height: u64) -> Option<BlockNullifiers> {
+        let block = self.blocks.remove(&height)?;
+        for nullifier in &block.nullifiers {
+            self.spent.remove(nullifier);
+        }
+        Some(block)
+    }
+
+    // Rejects the whole block if any nullifier is already spent, on chain or
+    // earlier in the same block
+    pub fn connect_block(&mut self, height: u64, block_hash: [u8; 32], nullifiers: &[[u8; 32]]) -> Result<[u8; 32], NullifierError> {
+        let tip = self.blocks.keys().next_back().copied();
+        if tip.is_some_and(|tip| height != tip + 1) {
+            return Err(NullifierError::NotTip { got: height, tip });
+        }
+        let mut sorted = nullifiers.to_vec();
+        sorted.sort_unstable();
+        if sorted.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(NullifierError::AlreadySpent(height));
+        }
+        if let Some(spent_at) = sorted.iter().find_map(|n| self.spent.get(n)) {
+            return Err(NullifierError::AlreadySpent(*spent_at));
+        }
+
+        let prev = self.tip().map_or([0u8; 32], |(_, digest)| digest);
+        let block = BlockNullifiers {
+            height,
+            block_hash,
+            digest: BlockNullifiers::compute_digest(&prev, height, &block_hash, &sorted),
+            nullifiers: sorted,
+        };
+        let digest = block.digest;
+        self.append(&JournalRecord::Connect(block.clone()))?;
+        self.apply_connect(block);
+        Ok(digest)
+    }
+
+    // Undoes the tip block on a reorg, returning its nullifiers to the pool
+    pub fn disconnect_block(&mut self, height: u64) -> Result<Vec<[u8; 32]>, NullifierError> {
+        let tip = self.blocks.keys().next_back().copied();
+        if tip != Some(height) {
+            return Err(NullifierError::NotTip { got: height, tip });
+        }
+        self.append(&JournalRecord::Disconnect(height))?;
+        Ok(self.apply_disconnect(height).map(|block| block.nullifiers).unwrap_or_default())
+    }
+
+    // Compact sync encoding of blocks `from..=to`, all integers little-endian:
+    //   version u8 | block count u32 |
+    //   per block: height u64 | block hash 32 | digest 32 | count u32 | nullifiers 32 each
+    pub fn encode_range(&self, from: u64, to: u64) -> Vec<u8> {
+        let blocks: Vec<_> = self.blocks.range(from..=to).map(|(_, block)| block).collect();
+        let mut bytes = vec![SYNC_VERSION];
+        bytes.extend((blocks.len() as u32).to_le_bytes());
+        for block in blocks {
+            bytes.extend(block.height.to_le_bytes());
+            bytes.extend(block.block_hash);
+            bytes.extend(block.digest);
+            bytes.extend((block.nullifiers.len() as u32).to_le_bytes());
+            for nullifier in &block.nullifiers {
+                bytes.extend(nullifier);
+            }
+        }
+        bytes
+    }
+
+    // Connects blocks received from a peer, recomputing each digest against
+    // our own chain of them. `block_hash` must match what our own chain sync
+    // accepted at that height, so a peer cannot slip in a different history.
+    pub fn apply_sync(
+        &mut self,
+        bytes: &[u8],
+        block_hash: impl Fn(u64) -> Option<[u8; 32]>,
+    ) -> Result<u64, NullifierError> {
+        let mut reader = SyncReader { bytes };
+        if reader.take(1)?[0] != SYNC_VERSION {
+            return Err(NullifierError::MalformedSync("unsupported version"));
+        }
+        let count = reader.u32()?;
+        let mut connected = 0;
+        for _ in 0..count {
+            let height = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+            let hash: [u8; 32] = reader.take(32)?.try_into().unwrap();
+            let digest: [u8; 32] = reader.take(32)?.try_into().unwrap();
+            let len = reader.u32()? as usize;
+            let mut nullifiers = Vec::with_capacity(len.min(reader.bytes.len() / 32));
+            for _ in 0..len {
+                nullifiers.push(reader.take(32)?.try_into().unwrap());
+            }
+
+            if self.blocks.contains_key(&height) {
+                if self.digest_at(height) != Some(digest) {
+                    return Err(NullifierError::DigestMismatch(height));
+                }
+                continue;
+            }
+            if block_hash(height) != Some(hash) {
+                return Err(NullifierError::MalformedSync("block hash differs from our chain"));
+            }
+            if self.connect_block(height, hash, &nullifiers)? != digest {
+                self.disconnect_block(height)?;
+                return Err(NullifierError::DigestMismatch(height));
+            }
+            connected += 1;
+        }
+        if !reader.bytes.is_empty() {
+            return Err(NullifierError::MalformedSync("trailing bytes"));
+        }
+        Ok(connected)
+    }
+}
+
+struct SyncReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SyncReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NullifierError> {
+        let (head, rest) = self
+            .bytes
+            .split_at_checked(len)
+            .ok_or(NullifierError::MalformedSync("truncated"))?;
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, NullifierError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nullifier(byte: u8) -> [u8; 32] {
+        [byte; 32]
+    }
+
+    fn block_hash(height: u64) -> Option<[u8; 32]> {
+        let mut hash = [0u8; 32];
+        hash[..8].copy_from_slice(&height.to_le_bytes());
+        Some(hash)
+    }
+
+    #[test]
+    fn test_rejects_double_spends() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut set = NullifierSet::open(dir.path().join("nullifiers")).unwrap();
+
+        set.connect_block(1, block_hash(1).unwrap(), &[nullifier(1), nullifier(2)]).unwrap();
+        assert!(set.contains(&nullifier(1)));
+        assert_eq!(set.len(), 2);
+
+        assert!(matches!(
+            set.connect_block(2, block_hash(2).unwrap(), &[nullifier(3), nullifier(1)]),
+            Err(NullifierError::AlreadySpent(1))
+        ));
+        assert!(matches!(
+            set.connect_block(2, block_hash(2).unwrap(), &[nullifier(4), nullifier(4)]),
+            Err(NullifierError::AlreadySpent(2))
+        ));
+        assert!(matches!(
+            set.connect_block(3, block_hash(3).unwrap(), &[nullifier(5)]),
+            Err(NullifierError::NotTip { got: 3, tip: Some(1) })
+        ));
+        // A rejected block leaves nothing behind
+        assert!(!set.contains(&nullifier(3)));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_disconnect_and_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nullifiers");
+        let mut set = NullifierSet::open(path.clone()).unwrap();
+        set.connect_block(1, block_hash(1).unwrap(), &[nullifier(1)]).unwrap();
+        let digest = set.connect_block(2, block_hash(2).unwrap(), &[nullifier(2)]).unwrap();
+        set.connect_block(3, block_hash(3).unwrap(), &[nullifier(3)]).unwrap();
+
+        assert!(matches!(set.disconnect_block(2), Err(NullifierError::NotTip { .. })));
+        assert_eq!(set.disconnect_block(3).unwrap(), vec![nullifier(3)]);
+        assert!(!set.contains(&nullifier(3)));
+        drop(set);
+
+        let mut set = NullifierSet::open(path).unwrap();
+        assert_eq!(set.tip(), Some((2, digest)));
+        assert!(set.contains(&nullifier(1)) && set.contains(&nullifier(2)));
+        assert!(!set.contains(&nullifier(3)));
+        // The reorged-out nullifier can be spent again
+        set.connect_block(3, block_hash(3).unwrap(), &[nullifier(3)]).unwrap();
+    }
+
+    #[test]
+    fn test_digest_ignores_transaction_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut a = NullifierSet::open(dir.path().join("a")).unwrap();
+        let mut b = NullifierSet::open(dir.path().join("b")).unwrap();
+        let hash = block_hash(1).unwrap();
+        assert_eq!(
+            a.connect_block(1, hash, &[nullifier(1), nullifier(2)]).unwrap(),
+            b.connect_block(1, hash, &[nullifier(2), nullifier(1)]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sync_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = NullifierSet::open(dir.path().join("source")).unwrap();
+        for height in 1..=3 {
+            source.connect_block(height, block_hash(height).unwrap(), &[nullifier(height as u8)]).unwrap();
+        }
+
+        let mut target = NullifierSet::open(dir.path().join("target")).unwrap();
+        target.connect_block(1, block_hash(1).unwrap(), &[nullifier(1)]).unwrap();
+        // Blocks we already hold are checked and skipped
+        assert_eq!(target.apply_sync(&source.encode_range(1, 3), block_hash).unwrap(), 2);
+        assert_eq!(target.tip(), source.tip());
+        assert_eq!(target.len(), 3);
+    }
+
+    #[test]
+    fn test_sync_rejects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = NullifierSet::open(dir.path().join("source")).unwrap();
+        source.connect_block(1, block_hash(1).unwrap(), &[nullifier(1)]).unwrap();
+        let bytes = source.encode_range(1, 1);
+
+        let mut target = NullifierSet::open(dir.path().join("target")).unwrap();
+
+        // Nullifier swapped out: the recomputed digest no longer matches
+        let mut tampered = bytes.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(matches!(target.apply_sync(&tampered, block_hash), Err(NullifierError::DigestMismatch(1))));
+        assert!(target.is_empty());
+        assert_eq!(target.tip(), None);
+
+        // A block hash our own chain never accepted
+        assert!(matches!(
+            target.apply_sync(&bytes, |_| Some([9u8; 32])),
+            Err(NullifierError::MalformedSync(_))
+        ));
+        assert!(matches!(
+            target.apply_sync(&bytes[..bytes.len() - 1], block_hash),
+            Err(NullifierError::MalformedSync("truncated"))
+        ));
+        assert!(target.is_empty());
+        assert!(matches!(
+            target.apply_sync(&[bytes.as_slice(), &[0]].concat(), block_hash),
+            Err(NullifierError::MalformedSync("trailing bytes"))
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+
+use bech32::{FromBase32, ToBase32, Variant};
+use idia_core::explorer::{SparkViewer, ViewedNote};
+use idia_core::{NetworkType, SparkMint};
+use sha2::{Digest, Sha256};
+
+use super::address::{AddressError, FullViewKey, IncomingViewKey};
+use super::lelantus::LelantusParameters;
+use super::note::NoteScanner;
+
+const MAINNET_HRP: &str = "spview";
+const TESTNET_HRP: &str = "tspview";
+const INCOMING_KIND: u8 = 0;
+const FULL_KIND: u8 = 1;
+
+// What a wallet owner can hand an auditor. An incoming view key shows the
+// notes paid to the wallet; a full view key also shows which of them were
+// spent. Neither can spend: that needs the spend key's r.
+//
+// The serial sits in the note plaintext, so spend visibility is a matter of
+// what the explorer reports rather than of what the key could compute.
+#[derive(Clone)]
+pub enum SparkViewKey {
+    Incoming(IncomingViewKey),
+    Full(FullViewKey),
+}
+
+impl SparkViewKey {
+    pub fn incoming_view_key(&self) -> IncomingViewKey {
+        match self {
+            Self::Incoming(ivk) => ivk.clone(),
+            Self::Full(fvk) => fvk.incoming_view_key(),
+        }
+    }
+
+    pub fn sees_spends(&self) -> bool {
+        matches!(self, Self::Full(_))
+    }
+
+    // The same for both kinds of key from one wallet, so an auditor upgraded
+    // to a full view key keeps its handle
+    pub fn fingerprint(&self) -> String {
+        let digest = Sha256::new()
+            .chain_update(b"idia-spark-view-fingerprint")
+            .chain_update(self.incoming_view_key().to_bytes())
+            .finalize();
+        hex::encode(&digest[..8])
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Incoming(ivk) => [&[INCOMING_KIND][..], &ivk.to_bytes()].concat(),
+            Self::Full(fvk) => [&[FULL_KIND][..], &fvk.to_bytes()].concat(),
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, AddressError> {
+        let key = match bytes.split_first() {
+            Some((&INCOMING_KIND, key)) => key
+                .try_into()
+                .ok()
+                .and_then(IncomingViewKey::from_bytes)
+                .map(Self::Incoming),
+            Some((&FULL_KIND, key)) => key.try_into().ok().and_then(FullViewKey::from_bytes).map(Self::Full),
+            _ => None,
+        };
+        key.ok_or(AddressError::InvalidViewKey)
+    }
+
+    pub fn encode(&self, network: NetworkType) -> String {
+        let hrp = match network {
+            NetworkType::Mainnet => MAINNET_HRP,
+            NetworkType::Testnet => TESTNET_HRP,
+        };
+        bech32::encode(hrp, self.to_bytes().to_base32(), Variant::Bech32m).expect("view key HRP is valid")
+    }
+
+    pub fn decode(s: &str) -> Result<(NetworkType, Self), AddressError> {
+        let (hrp, data, variant) = bech32::decode(s)?;
+        if variant != Variant::Bech32m {
+            return Err(AddressError::WrongVariant);
+        }
+        let network = match hrp.as_str() {
+            MAINNET_HRP => NetworkType::Mainnet,
+            TESTNET_HRP => NetworkType::Testnet,
+            _ => return Err(AddressError::UnknownPrefix(hrp)),
+        };
+        Ok((network, Self::from_bytes(&Vec::<u8>::from_base32(&data)?)?))
+    }
+}
+
+impl fmt::Debug for SparkViewKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.sees_spends() { "Full" } else { "Incoming" };
+        write!(f, "SparkViewKey::{}({})", kind, self.fingerprint())
+    }
+}
+
+// Opens notes for the explorer's view-key flow
+pub struct SparkAuditor {
+    key: SparkViewKey,
+    scanner: NoteScanner,
+}
+
+impl SparkAuditor {
+    pub fn new(key: SparkViewKey, params: &LelantusParameters) -> Self {
+        let scanner = NoteScanner::new(key.incoming_view_key(), params);
+        Self { key, scanner }
+    }
+}
+
+impl SparkViewer for SparkAuditor {
+    fn fingerprint(&self) -> String {
+        self.key.fingerprint()
+    }
+
+    fn view_mint(&self, mint: &SparkMint) -> Option<ViewedNote> {
+        // Height is only used for logging here
+        let received = self.scanner.scan_mint(mint, 0)?;
+        Some(ViewedNote {
+            value: received.note.value,
+            address_index: received.address_index,
+            memo: received.memo,
+            nullifier: self.key.sees_spends().then(|| received.note.nullifier.to_bytes()),
+        })
+    }
+}
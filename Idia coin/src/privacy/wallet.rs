@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use idia_core::{Block, NetworkType, Output, PoolNote, SparkBackend, SparkMint, SparkRedeem, WalletError};
+
+use super::address::{SparkAddress, SpendKey};
+use super::lelantus::{LelantusProtocol, SparkNote};
+use super::note::NoteScanner;
+
+fn spark_error(e: impl std::fmt::Display) -> WalletError {
+    WalletError::SparkError(e.to_string())
+}
+
+// The wallet's side of the Spark pool: finds notes paid to our addresses and
+// proves spends of them against the node's pool. The protocol is shared
+// with block processing, and the wallet only ever needs it briefly, so a
+// plain lock is enough.
+pub struct SparkWallet {
+    protocol: Arc<RwLock<LelantusProtocol>>,
+    scanner: NoteScanner,
+    // Change and self-mints go to address 0
+    change_address: SparkAddress,
+    // Openings of our unspent notes, by nullifier
+    notes: HashMap<[u8; 32], SparkNote>,
+}
+
+impl SparkWallet {
+    pub fn new(protocol: Arc<RwLock<LelantusProtocol>>, spend_key: &SpendKey, network: NetworkType) -> Self {
+        let scanner = {
+            let protocol = protocol.read().expect("Spark protocol lock poisoned");
+            NoteScanner::new(spend_key.incoming_view_key(), protocol.params())
+        };
+        Self {
+            protocol,
+            scanner,
+            change_address: spend_key.address(network, 0),
+            notes: HashMap::new(),
+        }
+    }
+
+    pub fn scanned_height(&self) -> Option<u64> {
+        self.scanner.scanned_height()
+    }
+}
+
+impl SparkBackend for SparkWallet {
+    fn mint(&mut self, value: u64) -> Result<SparkMint, WalletError> {
+        let protocol = self.protocol.read().map_err(spark_error)?;
+        // The note comes back to us through scanning once the mint is mined
+        let (_, mint) = protocol
+            .mint_record(value, &self.change_address, &[])
+            .map_err(spark_error)?;
+        Ok(mint)
+    }
+
+    fn redeem(&mut self, nullifier: &[u8; 32], output: &Output, output_index: u32) -> Result<SparkRedeem, WalletError> {
+        let note = self
+            .notes
+            .get(nullifier)
+            .ok_or_else(|| WalletError::SparkError("unknown Spark note".into()))?;
+        let mut protocol = self.protocol.write().map_err(spark_error)?;
+        protocol.redeem_record(note, output, output_index).map_err(spark_error)
+    }
+
+    fn scan_block(&mut self, block: &Block) -> Vec<PoolNote> {
+        let received: Vec<PoolNote> = self
+            .scanner
+            .scan_block(block)
+            .into_iter()
+            .map(|received| {
+                let nullifier = received.note.nullifier.to_bytes();
+                let note = PoolNote {
+                    nullifier,
+                    value: received.note.value,
+                    height: received.height,
+                };
+                self.notes.insert(nullifier, received.note);
+                note
+            })
+            .collect();
+        for nullifier in block.spark_nullifiers() {
+            self.notes.remove(nullifier);
+        }
+        received
+    }
+}
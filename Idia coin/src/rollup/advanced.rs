@@ -1,112 +1,441 @@
-use bellman::{Circuit, ConstraintSystem, SynthesisError};
-use ff::PrimeField;
-use recursive_snark::{RecursiveProof, RecursiveCircuit};
+use bellpepper_core::num::AllocatedNum;
+use bellpepper_core::{Circuit, ConstraintSystem, SynthesisError};
+use blstrs::Scalar as Fr;
+use chrono::{DateTime, Duration, Utc};
+use ff::{PrimeField, PrimeFieldBits};
+use std::collections::{BTreeMap, HashMap};
 
+use super::aggregation::{AggregatedBatches, ProofAggregator, SnarkPackAggregator};
+use super::availability::{AvailabilitySampler, DataAvailabilityCommitment, SamplingReport};
+use super::fraud::{apply_transaction, FraudProof};
+use super::hash::{mimc_hash, mimc_hash_gadget};
+use super::processor::{compute_batch_root, RollupBatch, RollupError, TransactionCircuit};
+use super::state::SparseMerkleTree;
+
+// Chains a batch onto the previous root: the new root is the MiMC hash of
+// the previous root and every transaction's nullifier and output commitment,
+// so it cannot be claimed apart from the transactions proven here. Proofs of
+// consecutive batches are compressed afterwards by a `ProofAggregator` rather
+// than verified inside this circuit.
 pub struct RecursiveRollupCircuit<F: PrimeField> {
     pub previous_state_root: Option<F>,
     pub new_state_root: Option<F>,
-    pub previous_proof: Option<RecursiveProof>,
     pub transactions: Vec<TransactionCircuit<F>>,
 }
 
-impl<F: PrimeField> Circuit<F> for RecursiveRollupCircuit<F> {
+impl<F: PrimeField> RecursiveRollupCircuit<F> {
+    // The root the circuit accepts after `transactions`, computed outside it
+    pub fn transition_root(previous_state_root: F, transactions: &[TransactionCircuit<F>]) -> Option<F> {
+        let mut preimage = vec![previous_state_root];
+        for tx in transactions {
+            preimage.push(tx.input_nullifier?);
+            preimage.push(tx.output_commitment?);
+        }
+        Some(mimc_hash(&preimage))
+    }
+}
+
+impl<F: PrimeFieldBits> Circuit<F> for RecursiveRollupCircuit<F> {
     fn synthesize<CS: ConstraintSystem<F>>(
         self,
         cs: &mut CS,
     ) -> Result<(), SynthesisError> {
-        // Verify previous proof if it exists
-        if let Some(prev_proof) = self.previous_proof {
-            prev_proof.verify(cs)?;
-        }
-
-        // Allocate state roots
-        let prev_root = cs.alloc(
-            || "previous state root",
-            || self.previous_state_root.ok_or(SynthesisError::AssignmentMissing),
-        )?;
-
-        let new_root = cs.alloc(
-            || "new state root",
-            || self.new_state_root.ok_or(SynthesisError::AssignmentMissing),
-        )?;
+        // Both roots are public inputs the verifier supplies
+        let prev_root = AllocatedNum::alloc(cs.namespace(|| "previous state root"), || {
+            self.previous_state_root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        prev_root.inputize(cs.namespace(|| "previous state root input"))?;
+        let new_root = AllocatedNum::alloc(cs.namespace(|| "new state root"), || {
+            self.new_state_root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        new_root.inputize(cs.namespace(|| "new state root input"))?;
 
-        // Process all transactions
-        for tx in self.transactions.iter() {
-            tx.synthesize(cs)?;
+        let mut preimage = vec![prev_root];
+        for (i, tx) in self.transactions.iter().enumerate() {
+            let (nullifier, commitment) = tx.allocate(&mut cs.namespace(|| format!("transaction {}", i)))?;
+            preimage.push(nullifier);
+            preimage.push(commitment);
         }
+        let transition = mimc_hash_gadget(cs.namespace(|| "transition"), &preimage)?;
 
-        // Verify state transition
         cs.enforce(
-            || "state transition validity",
-            |lc| lc + prev_root,
+            || "new root is the transition of the previous root",
+            |lc| lc + transition.get_variable(),
             |lc| lc + CS::one(),
-            |lc| lc + new_root,
+            |lc| lc + new_root.get_variable(),
         );
 
         Ok(())
     }
 }
 
+pub struct OptimisticBatch {
+    pub id: u64,
+    pub batch: RollupBatch,
+    pub validator: String,
+    pub bond: u64,
+    pub pre_state_root: Fr,
+    // State root after each transaction; the last is the batch's post-state
+    pub trace: Vec<Fr>,
+    pub submitted_at: DateTime<Utc>,
+    pub challenge_deadline: DateTime<Utc>,
+}
+
+impl OptimisticBatch {
+    pub fn post_state_root(&self) -> Fr {
+        self.trace.last().copied().unwrap_or(self.pre_state_root)
+    }
+
+    pub fn root_before(&self, tx_index: usize) -> Fr {
+        match tx_index {
+            0 => self.pre_state_root,
+            i => self.trace[i - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatorBond {
+    pub total: u64,
+    // Backing batches still inside their challenge period
+    pub locked: u64,
+}
+
+impl ValidatorBond {
+    pub fn available(&self) -> u64 {
+        self.total.saturating_sub(self.locked)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChallengeOutcome {
+    pub batch_id: u64,
+    pub validator: String,
+    pub slashed: u64,
+    pub challenger_reward: u64,
+    pub burned: u64,
+    // The disputed batch and every pending batch built on top of it
+    pub reverted: Vec<u64>,
+}
+
+// Batches are accepted on a bonded validator's word, with the state root after
+// every transaction. Anyone replaying a batch can point at the first wrong root
+// with a fraud proof; the batch and its successors are reverted and the
+// validator's bond slashed.
 pub struct OptimisticRollup {
-    pub state_root: [u8; 32],
-    pub pending_batches: Vec<RollupBatch>,
+    // Last finalized state
+    pub state_root: Fr,
+    // In id order, each building on the post-state of the one before
+    pub pending_batches: BTreeMap<u64, OptimisticBatch>,
     pub challenge_period: u64,
-    pub validators: Vec<PublicKey>,
+    pub validators: HashMap<String, ValidatorBond>,
+    // Bond locked behind each batch until it finalizes
+    pub batch_bond: u64,
+    // Share of a slashed bond paid to the challenger; the rest is burned
+    pub challenger_reward_bps: u32,
+    next_batch_id: u64,
 }
 
 impl OptimisticRollup {
-    pub async fn submit_batch(&mut self, batch: RollupBatch) -> Result<(), RollupError> {
-        // Optimistic submission without immediate proof verification
-        self.pending_batches.push(batch);
-        Ok(())
+    pub fn new(state_root: Fr, challenge_period: u64, batch_bond: u64) -> Self {
+        Self {
+            state_root,
+            pending_batches: BTreeMap::new(),
+            challenge_period,
+            validators: HashMap::new(),
+            batch_bond,
+            challenger_reward_bps: 5_000,
+            next_batch_id: 0,
+        }
+    }
+
+    pub fn with_challenger_reward(mut self, bps: u32) -> Self {
+        self.challenger_reward_bps = bps.min(10_000);
+        self
     }
 
-    pub async fn challenge_batch(&self, batch_id: u64, fraud_proof: FraudProof) -> Result<(), RollupError> {
-        // Verify fraud proof and slash if valid
-        if self.verify_fraud_proof(&fraud_proof).await? {
-            self.slash_validator(fraud_proof.validator).await?;
+    // Root the next batch must start from
+    pub fn tip_root(&self) -> Fr {
+        self.pending_batches
+            .values()
+            .next_back()
+            .map_or(self.state_root, OptimisticBatch::post_state_root)
+    }
+
+    pub fn deposit_bond(&mut self, validator: &str, amount: u64) -> ValidatorBond {
+        let bond = self.validators.entry(validator.to_string()).or_default();
+        bond.total += amount;
+        *bond
+    }
+
+    pub fn withdraw_bond(&mut self, validator: &str, amount: u64) -> Result<ValidatorBond, RollupError> {
+        let bond = self.validators.entry(validator.to_string()).or_default();
+        if amount > bond.available() {
+            return Err(RollupError::InsufficientBond {
+                validator: validator.to_string(),
+                available: bond.available(),
+                required: amount,
+            });
         }
-        Ok(())
+        bond.total -= amount;
+        Ok(*bond)
     }
 
-    pub async fn finalize_batch(&mut self, batch_id: u64) -> Result<(), RollupError> {
-        // Finalize batch after challenge period
-        if self.challenge_period_expired(batch_id) {
-            self.commit_batch(batch_id).await?;
+    pub async fn submit_batch(&mut self, validator: &str, batch: RollupBatch, trace: Vec<Fr>) -> Result<u64, RollupError> {
+        // Optimistic submission without immediate proof verification, but the
+        // trace must cover every transaction for fraud proofs to address them
+        if trace.len() != batch.transactions.len() {
+            return Err(RollupError::InvalidTrace(format!(
+                "{} roots for {} transactions",
+                trace.len(),
+                batch.transactions.len()
+            )));
         }
-        Ok(())
+        if compute_batch_root(&batch.transactions) != batch.merkle_root {
+            return Err(RollupError::InvalidTrace("batch root does not match its transactions".to_string()));
+        }
+
+        let required = self.batch_bond;
+        let bond = self.validators.entry(validator.to_string()).or_default();
+        if bond.available() < required {
+            return Err(RollupError::InsufficientBond {
+                validator: validator.to_string(),
+                available: bond.available(),
+                required,
+            });
+        }
+        bond.locked += required;
+
+        let id = self.next_batch_id;
+        self.next_batch_id += 1;
+        let now = Utc::now();
+        let pre_state_root = self.tip_root();
+        self.pending_batches.insert(
+            id,
+            OptimisticBatch {
+                id,
+                batch,
+                validator: validator.to_string(),
+                bond: required,
+                pre_state_root,
+                trace,
+                submitted_at: now,
+                challenge_deadline: now + Duration::seconds(self.challenge_period as i64),
+            },
+        );
+        tracing::info!("Validator {} submitted optimistic batch {}", validator, id);
+        Ok(id)
+    }
+
+    // Replays a pending batch from `state`, the rollup state at its pre-state
+    // root, and builds a fraud proof at the first step whose root is wrong
+    pub fn find_fraud(
+        &self,
+        batch_id: u64,
+        state: &SparseMerkleTree,
+        challenger: &str,
+    ) -> Result<Option<FraudProof>, RollupError> {
+        let pending = self
+            .pending_batches
+            .get(&batch_id)
+            .ok_or(RollupError::UnknownBatch(batch_id))?;
+        if state.root() != pending.pre_state_root {
+            return Err(RollupError::InvalidTrace("replay state is not the batch's pre-state".to_string()));
+        }
+
+        let mut state = state.clone();
+        for (index, (tx, claimed)) in pending.batch.transactions.iter().zip(&pending.trace).enumerate() {
+            let before = state.clone();
+            apply_transaction(&mut state, tx);
+            if state.root() != *claimed {
+                return FraudProof::construct(&before, batch_id, index, tx, challenger).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn challenge_batch(&mut self, fraud_proof: FraudProof) -> Result<ChallengeOutcome, RollupError> {
+        let batch_id = fraud_proof.batch_id;
+        let pending = self
+            .pending_batches
+            .get(&batch_id)
+            .ok_or(RollupError::UnknownBatch(batch_id))?;
+        if Utc::now() >= pending.challenge_deadline {
+            return Err(RollupError::ChallengePeriodOver(batch_id));
+        }
+
+        // Verify fraud proof by re-executing the disputed transaction
+        let tx = pending.batch.transactions.get(fraud_proof.tx_index).ok_or_else(|| {
+            RollupError::FraudProofRejected(format!("batch has no transaction {}", fraud_proof.tx_index))
+        })?;
+        let correct = fraud_proof.re_execute(tx, pending.root_before(fraud_proof.tx_index))?;
+        if correct == pending.trace[fraud_proof.tx_index] {
+            return Err(RollupError::FraudProofRejected("transaction executes to the claimed root".to_string()));
+        }
+
+        // Later batches started from a state that never existed
+        let reverted: Vec<OptimisticBatch> = self
+            .pending_batches
+            .split_off(&batch_id)
+            .into_values()
+            .collect();
+        let mut slashed = 0;
+        let mut validator = String::new();
+        for batch in &reverted {
+            let bond = self.validators.entry(batch.validator.clone()).or_default();
+            bond.locked = bond.locked.saturating_sub(batch.bond);
+            if batch.id == batch_id {
+                bond.total = bond.total.saturating_sub(batch.bond);
+                slashed = batch.bond;
+                validator = batch.validator.clone();
+            }
+        }
+
+        let challenger_reward = (slashed as u128 * self.challenger_reward_bps as u128 / 10_000) as u64;
+        self.validators.entry(fraud_proof.challenger.clone()).or_default().total += challenger_reward;
+        tracing::warn!(
+            "Optimistic batch {} by {} proven fraudulent at transaction {} by {}; slashed {}",
+            batch_id,
+            validator,
+            fraud_proof.tx_index,
+            fraud_proof.challenger,
+            slashed
+        );
+        Ok(ChallengeOutcome {
+            batch_id,
+            validator,
+            slashed,
+            challenger_reward,
+            burned: slashed - challenger_reward,
+            reverted: reverted.iter().map(|batch| batch.id).collect(),
+        })
+    }
+
+    // Finalizes pending batches in order while their challenge period has passed
+    pub async fn finalize_due(&mut self, now: DateTime<Utc>) -> Vec<u64> {
+        let mut finalized = Vec::new();
+        while let Some(entry) = self.pending_batches.first_entry() {
+            if entry.get().challenge_deadline > now {
+                break;
+            }
+            let batch = entry.remove();
+            self.state_root = batch.post_state_root();
+            if let Some(bond) = self.validators.get_mut(&batch.validator) {
+                bond.locked = bond.locked.saturating_sub(batch.bond);
+            }
+            finalized.push(batch.id);
+        }
+        finalized
     }
 }
 
 pub struct ValidiumProof {
-    pub data_availability_proof: DataAvailabilityProof,
-    pub state_validity_proof: RecursiveProof,
+    pub data_availability_proof: SamplingReport,
+    pub state_validity_proof: AggregatedBatches,
 }
 
 pub struct ValidiumRollup {
     pub state_root: [u8; 32],
     pub data_availability_committee: Vec<PublicKey>,
-    pub proof_aggregator: ProofAggregator,
+    pub proof_aggregator: SnarkPackAggregator,
+    // Samples the committee for the batch's erasure-coded data
+    pub availability_sampler: AvailabilitySampler,
+    // Highest false-accept probability a batch may be admitted with
+    pub max_false_accept: f64,
 }
 
 impl ValidiumRollup {
     pub async fn process_batch(
         &mut self,
         batch: RollupBatch,
-        da_signatures: Vec<Signature>,
+        da_commitment: DataAvailabilityCommitment,
     ) -> Result<ValidiumProof, RollupError> {
-        // Verify data availability committee signatures
-        self.verify_da_signatures(&da_signatures)?;
-
-        // Generate data availability proof
-        let da_proof = self.generate_da_proof(&batch, &da_signatures)?;
+        // Committee signatures only say the data was received; sampling checks
+        // it can actually be retrieved
+        let da_proof = self
+            .availability_sampler
+            .sample(&da_commitment)
+            .await
+            .map_err(|e| RollupError::DataUnavailable(e.to_string()))?;
+        if da_proof.false_accept_bound > self.max_false_accept {
+            return Err(RollupError::DataUnavailable(format!(
+                "{} samples leave a false-accept bound of {}",
+                da_proof.samples.len(),
+                da_proof.false_accept_bound
+            )));
+        }
 
         // Generate and aggregate validity proofs
-        let validity_proof = self.proof_aggregator.aggregate_proofs(&batch)?;
+        let validity_proof = self.proof_aggregator.aggregate(std::slice::from_ref(&batch))?;
 
         Ok(ValidiumProof {
             data_availability_proof: da_proof,
             state_validity_proof: validity_proof,
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellpepper_core::test_cs::TestConstraintSystem;
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    fn transactions() -> Vec<TransactionCircuit<Fr>> {
+        (0..2)
+            .map(|_| {
+                TransactionCircuit::new(
+                    1000,
+                    Fr::random(&mut OsRng),
+                    Fr::random(&mut OsRng),
+                    Fr::random(&mut OsRng),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recursive_circuit_accepts_transition_root() {
+        let previous = Fr::random(&mut OsRng);
+        let transactions = transactions();
+        let circuit = RecursiveRollupCircuit {
+            previous_state_root: Some(previous),
+            new_state_root: RecursiveRollupCircuit::transition_root(previous, &transactions),
+            transactions,
+        };
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_recursive_circuit_rejects_unrelated_root() {
+        let previous = Fr::random(&mut OsRng);
+        let circuit = RecursiveRollupCircuit {
+            previous_state_root: Some(previous),
+            new_state_root: Some(Fr::random(&mut OsRng)),
+            transactions: transactions(),
+        };
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_recursive_circuit_rejects_replayed_previous_root() {
+        // Claiming the previous root as the new one no longer satisfies the circuit
+        let previous = Fr::random(&mut OsRng);
+        let circuit = RecursiveRollupCircuit {
+            previous_state_root: Some(previous),
+            new_state_root: Some(previous),
+            transactions: transactions(),
+        };
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,203 @@
+use bellperson::groth16::aggregate::{
+    aggregate_proofs, setup_fake_srs, verify_aggregate_proof, AggregateProof, AggregateVersion, GenericSRS,
+    ProverSRS, VerifierSRS,
+};
+use blstrs::{Bls12, Scalar as Fr};
+use rand::rngs::OsRng;
+
+use super::processor::{compute_batch_root, BatchVerifier, RollupBatch, RollupError};
+
+// Binds aggregates to this protocol so they cannot be replayed elsewhere
+const TRANSCRIPT_TAG: &[u8] = b"idia-rollup-aggregate-v1";
+
+// Compresses many batch proofs into one that verifies in a single check.
+// Backends differ in how (pairing-based aggregation, folding); callers only
+// see the aggregate and the batches' public data.
+pub trait ProofAggregator: Send + Sync {
+    type Aggregate: Send + Sync;
+
+    fn aggregate(&self, batches: &[RollupBatch]) -> Result<Self::Aggregate, RollupError>;
+
+    // `batches` need only their public data; proofs are not consulted
+    fn verify(&self, aggregate: &Self::Aggregate, batches: &[RollupBatch]) -> Result<bool, RollupError>;
+}
+
+pub struct AggregatedBatches {
+    pub proof: AggregateProof<Bls12>,
+    // Batches covered, before padding to a power of two
+    pub count: usize,
+    pub batch_roots: Vec<Fr>,
+}
+
+// SnarkPack: aggregates n Groth16 proofs into one of O(log n) size with
+// O(log n) verification, so aggregation depth is log2 of the batch count
+pub struct SnarkPackAggregator {
+    verifier: BatchVerifier,
+    prover_srs: ProverSRS<Bls12>,
+    verifier_srs: VerifierSRS<Bls12>,
+    max_batches: usize,
+}
+
+impl SnarkPackAggregator {
+    // The SRS must come from a trusted ceremony in production; `max_batches`
+    // bounds what one aggregate can cover
+    pub fn new(verifier: BatchVerifier, srs: &GenericSRS<Bls12>, max_batches: usize) -> Result<Self, RollupError> {
+        let max_batches = max_batches.next_power_of_two().max(2);
+        let (prover_srs, verifier_srs) = srs.specialize(max_batches);
+        Ok(Self {
+            verifier,
+            prover_srs,
+            verifier_srs,
+            max_batches,
+        })
+    }
+
+    // For tests and benchmarks only: the setup randomness is not discarded
+    pub fn with_insecure_srs(verifier: BatchVerifier, max_batches: usize) -> Result<Self, RollupError> {
+        let srs = setup_fake_srs::<Bls12, _>(&mut OsRng, max_batches.next_power_of_two().max(2));
+        Self::new(verifier, &srs, max_batches)
+    }
+
+    pub fn max_batches(&self) -> usize {
+        self.max_batches
+    }
+
+    // SnarkPack wants a power of two of at least two proofs; the last batch
+    // is repeated to fill up, which proves nothing new
+    fn padded_inputs(&self, batches: &[RollupBatch]) -> Result<Vec<Vec<Fr>>, RollupError> {
+        if batches.is_empty() || batches.len() > self.max_batches {
+            return Err(RollupError::AggregationSize {
+                len: batches.len(),
+                max: self.max_batches,
+            });
+        }
+        let mut inputs = batches
+            .iter()
+            .map(|batch| self.verifier.public_inputs(batch))
+            .collect::<Result<Vec<_>, _>>()?;
+        let target = batches.len().next_power_of_two().max(2);
+        let last = inputs.last().cloned().expect("at least one batch");
+        inputs.resize(target, last);
+        Ok(inputs)
+    }
+}
+
+impl ProofAggregator for SnarkPackAggregator {
+    type Aggregate = AggregatedBatches;
+
+    fn aggregate(&self, batches: &[RollupBatch]) -> Result<AggregatedBatches, RollupError> {
+        let target = self.padded_inputs(batches)?.len();
+        let mut proofs: Vec<_> = batches.iter().map(|batch| batch.batch_proof.clone()).collect();
+        let last = proofs.last().cloned().expect("at least one batch");
+        proofs.resize(target, last);
+
+        let proof = aggregate_proofs(&self.prover_srs, TRANSCRIPT_TAG, &proofs, AggregateVersion::V2)?;
+        Ok(AggregatedBatches {
+            proof,
+            count: batches.len(),
+            batch_roots: batches.iter().map(|batch| batch.merkle_root).collect(),
+        })
+    }
+
+    fn verify(&self, aggregate: &AggregatedBatches, batches: &[RollupBatch]) -> Result<bool, RollupError> {
+        if aggregate.count != batches.len() {
+            return Ok(false);
+        }
+        // Each root must still match its transactions, as for a single batch
+        let roots_match = batches.iter().zip(&aggregate.batch_roots).all(|(batch, root)| {
+            batch.merkle_root == *root && compute_batch_root(&batch.transactions) == *root
+        });
+        if !roots_match {
+            return Ok(false);
+        }
+
+        let inputs = self.padded_inputs(batches)?;
+        Ok(verify_aggregate_proof(
+            &self.verifier_srs,
+            self.verifier.prepared(),
+            &mut OsRng,
+            &inputs,
+            &aggregate.proof,
+            TRANSCRIPT_TAG,
+            AggregateVersion::V2,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollup::processor::{RollupProcessor, TransactionCircuit};
+    use ff::Field;
+    use std::time::Instant;
+
+    const BATCH_SIZE: usize = 4;
+
+    fn random_batch(processor: &RollupProcessor) -> RollupBatch {
+        let circuits = (0..BATCH_SIZE)
+            .map(|_| {
+                TransactionCircuit::new(
+                    1000,
+                    Fr::random(&mut OsRng),
+                    Fr::random(&mut OsRng),
+                    Fr::random(&mut OsRng),
+                )
+            })
+            .collect();
+        processor.prove(circuits).unwrap()
+    }
+
+    fn aggregator(processor: &RollupProcessor, max_batches: usize) -> SnarkPackAggregator {
+        let verifier = BatchVerifier::from_bytes(&processor.verifier().to_bytes().unwrap()).unwrap();
+        SnarkPackAggregator::with_insecure_srs(verifier, max_batches).unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_round_trip() {
+        let processor = RollupProcessor::new(BATCH_SIZE);
+        let batches: Vec<RollupBatch> = (0..2).map(|_| random_batch(&processor)).collect();
+        let aggregator = aggregator(&processor, 2);
+
+        let aggregate = aggregator.aggregate(&batches).unwrap();
+        assert!(aggregator.verify(&aggregate, &batches).unwrap());
+
+        // The aggregate is bound to the batches it was built from
+        let other: Vec<RollupBatch> = (0..2).map(|_| random_batch(&processor)).collect();
+        assert!(!aggregator.verify(&aggregate, &other).unwrap_or(false));
+    }
+
+    // Aggregation depth is log2 of the batch count: 1 through 6. Run with
+    // `cargo test --release -- --ignored --nocapture` to compare the cost of
+    // one aggregate check against verifying each batch proof on its own.
+    #[test]
+    #[ignore]
+    fn test_aggregation_depth_timings() {
+        const BATCH_COUNTS: [usize; 6] = [2, 4, 8, 16, 32, 64];
+
+        let processor = RollupProcessor::new(BATCH_SIZE);
+        let max = *BATCH_COUNTS.last().unwrap();
+        let batches: Vec<RollupBatch> = (0..max).map(|_| random_batch(&processor)).collect();
+        let aggregator = aggregator(&processor, max);
+
+        for count in BATCH_COUNTS {
+            let started = Instant::now();
+            let aggregate = aggregator.aggregate(&batches[..count]).unwrap();
+            let aggregated = started.elapsed();
+
+            let started = Instant::now();
+            assert!(aggregator.verify(&aggregate, &batches[..count]).unwrap());
+            let verified = started.elapsed();
+
+            let started = Instant::now();
+            for batch in &batches[..count] {
+                assert!(processor.verify_batch(batch).unwrap());
+            }
+            let individually = started.elapsed();
+
+            println!(
+                "{:>2} batches: aggregate {:?}, verify aggregate {:?}, verify individually {:?}",
+                count, aggregated, verified, individually
+            );
+        }
+    }
+}
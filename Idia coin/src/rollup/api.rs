@@ -0,0 +1,38 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use std::sync::Arc;
+
+use super::prover::{ProofJobProgress, ProverPool};
+
+#[derive(Clone)]
+pub struct RollupApiState {
+    prover: Arc<ProverPool>,
+}
+
+impl RollupApiState {
+    pub fn new(prover: Arc<ProverPool>) -> Self {
+        Self { prover }
+    }
+}
+
+pub fn create_rollup_routes(state: RollupApiState) -> Router {
+    Router::new()
+        .route("/rollup/proofs", get(proof_jobs))
+        .route("/rollup/proofs/:id", get(proof_job))
+        .with_state(state)
+}
+
+async fn proof_jobs(State(state): State<RollupApiState>) -> Json<Vec<ProofJobProgress>> {
+    Json(state.prover.jobs().await)
+}
+
+async fn proof_job(
+    State(state): State<RollupApiState>,
+    Path(id): Path<u64>,
+) -> Result<Json<ProofJobProgress>, StatusCode> {
+    state.prover.progress(id).await.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
@@ -0,0 +1,285 @@
+use async_trait::async_trait;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AvailabilityError {
+    #[error("Erasure coding failed: {0}")]
+    Coding(String),
+    #[error("Unknown batch data {0}")]
+    UnknownData(String),
+    #[error("Chunk {0} out of range")]
+    ChunkOutOfRange(usize),
+    #[error("Chunk {index} from committee member {member} does not match the commitment")]
+    InvalidChunk { index: usize, member: usize },
+    #[error("Committee member {member} failed to serve chunk {index}: {reason}")]
+    Unavailable { index: usize, member: usize, reason: String },
+    #[error("No committee members to sample from")]
+    NoCommittee,
+}
+
+// What a Validium batch commits to instead of publishing its data: the Merkle
+// root over the erasure-coded chunks and the coding parameters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataAvailabilityCommitment {
+    pub root: [u8; 32],
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    pub shard_len: usize,
+    pub data_len: usize,
+}
+
+impl DataAvailabilityCommitment {
+    pub fn total_chunks(&self) -> usize {
+        self.data_shards + self.parity_shards
+    }
+
+    pub fn id(&self) -> String {
+        hex::encode(self.root)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkProof {
+    pub index: usize,
+    pub chunk: Vec<u8>,
+    // Leaf level first
+    pub branch: Vec<[u8; 32]>,
+}
+
+impl ChunkProof {
+    pub fn verify(&self, commitment: &DataAvailabilityCommitment) -> bool {
+        if self.index >= commitment.total_chunks() || self.chunk.len() != commitment.shard_len {
+            return false;
+        }
+        let mut node = leaf_hash(self.index, &self.chunk);
+        let mut index = self.index;
+        for sibling in &self.branch {
+            node = if index & 1 == 0 {
+                node_hash(&node, sibling)
+            } else {
+                node_hash(sibling, &node)
+            };
+            index >>= 1;
+        }
+        node == commitment.root
+    }
+}
+
+// Batch data split into data shards plus Reed-Solomon parity. Any
+// `data_shards` of the chunks rebuild the data, so withholding it means
+// withholding more than `parity_shards` chunks, which sampling detects.
+#[derive(Debug, Clone)]
+pub struct EncodedData {
+    pub commitment: DataAvailabilityCommitment,
+    chunks: Vec<Vec<u8>>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl EncodedData {
+    pub fn encode(data: &[u8], data_shards: usize, parity_shards: usize) -> Result<Self, AvailabilityError> {
+        let coder = ReedSolomon::new(data_shards, parity_shards).map_err(|e| AvailabilityError::Coding(e.to_string()))?;
+        let shard_len = data.len().div_ceil(data_shards).max(1);
+
+        let mut chunks: Vec<Vec<u8>> = (0..data_shards)
+            .map(|i| {
+                let start = (i * shard_len).min(data.len());
+                let end = ((i + 1) * shard_len).min(data.len());
+                let mut shard = data[start..end].to_vec();
+                shard.resize(shard_len, 0);
+                shard
+            })
+            .collect();
+        chunks.extend((0..parity_shards).map(|_| vec![0u8; shard_len]));
+        coder.encode(&mut chunks).map_err(|e| AvailabilityError::Coding(e.to_string()))?;
+
+        let levels = merkle_levels(&chunks);
+        let root = levels.last().expect("at least one level")[0];
+        Ok(Self {
+            commitment: DataAvailabilityCommitment {
+                root,
+                data_shards,
+                parity_shards,
+                shard_len,
+                data_len: data.len(),
+            },
+            chunks,
+            levels,
+        })
+    }
+
+    pub fn chunk_proof(&self, index: usize) -> Result<ChunkProof, AvailabilityError> {
+        let chunk = self.chunks.get(index).ok_or(AvailabilityError::ChunkOutOfRange(index))?;
+        let mut branch = Vec::with_capacity(self.levels.len() - 1);
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            // Odd levels are padded by repeating the last node
+            let sibling = level.get(position ^ 1).copied().unwrap_or(level[position]);
+            branch.push(sibling);
+            position >>= 1;
+        }
+        Ok(ChunkProof {
+            index,
+            chunk: chunk.clone(),
+            branch,
+        })
+    }
+}
+
+// Rebuilds batch data from at least `data_shards` verified chunks
+pub fn reconstruct(commitment: &DataAvailabilityCommitment, proofs: &[ChunkProof]) -> Result<Vec<u8>, AvailabilityError> {
+    let coder = ReedSolomon::new(commitment.data_shards, commitment.parity_shards)
+        .map_err(|e| AvailabilityError::Coding(e.to_string()))?;
+    let mut shards: Vec<Option<Vec<u8>>> = vec![None; commitment.total_chunks()];
+    // Chunks that do not match the commitment are dropped, not trusted
+    for proof in proofs.iter().filter(|p| p.verify(commitment)) {
+        shards[proof.index] = Some(proof.chunk.clone());
+    }
+    coder.reconstruct_data(&mut shards).map_err(|e| AvailabilityError::Coding(e.to_string()))?;
+
+    let mut data: Vec<u8> = shards
+        .into_iter()
+        .take(commitment.data_shards)
+        .flat_map(|shard| shard.unwrap_or_default())
+        .collect();
+    data.truncate(commitment.data_len);
+    Ok(data)
+}
+
+// A DA committee member, reached over whatever transport the node uses
+#[async_trait]
+pub trait ChunkProvider: Send + Sync {
+    async fn fetch_chunk(&self, commitment: &DataAvailabilityCommitment, index: usize) -> Result<ChunkProof, AvailabilityError>;
+}
+
+// What a committee member keeps: every batch it attested to, encoded
+#[derive(Default)]
+pub struct ChunkStore {
+    batches: RwLock<HashMap<[u8; 32], EncodedData>>,
+}
+
+impl ChunkStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn store(&self, encoded: EncodedData) {
+        self.batches.write().await.insert(encoded.commitment.root, encoded);
+    }
+
+    pub async fn forget(&self, root: &[u8; 32]) {
+        self.batches.write().await.remove(root);
+    }
+}
+
+#[async_trait]
+impl ChunkProvider for ChunkStore {
+    async fn fetch_chunk(&self, commitment: &DataAvailabilityCommitment, index: usize) -> Result<ChunkProof, AvailabilityError> {
+        let batches = self.batches.read().await;
+        let encoded = batches
+            .get(&commitment.root)
+            .ok_or_else(|| AvailabilityError::UnknownData(commitment.id()))?;
+        encoded.chunk_proof(index)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingReport {
+    pub commitment: DataAvailabilityCommitment,
+    pub samples: Vec<ChunkProof>,
+    // Chance that data too sparse to reconstruct would have passed every sample
+    pub false_accept_bound: f64,
+}
+
+// Light-client check that a batch's data can be rebuilt: request random chunks
+// from random committee members and verify each against the commitment. If
+// fewer than `data_shards` chunks are retrievable, each sample fails with
+// probability above parity / total, so k samples pass with at most
+// (data / total)^k.
+pub struct AvailabilitySampler {
+    committee: Vec<Arc<dyn ChunkProvider>>,
+    samples: usize,
+}
+
+impl AvailabilitySampler {
+    pub fn new(committee: Vec<Arc<dyn ChunkProvider>>, samples: usize) -> Self {
+        Self { committee, samples }
+    }
+
+    pub async fn sample(&self, commitment: &DataAvailabilityCommitment) -> Result<SamplingReport, AvailabilityError> {
+        if self.committee.is_empty() {
+            return Err(AvailabilityError::NoCommittee);
+        }
+        let total = commitment.total_chunks();
+        let count = self.samples.min(total);
+
+        let (indices, members): (Vec<usize>, Vec<usize>) = {
+            let mut rng = rand::thread_rng();
+            let mut all: Vec<usize> = (0..total).collect();
+            all.shuffle(&mut rng);
+            let indices: Vec<usize> = all.into_iter().take(count).collect();
+            let members = indices.iter().map(|_| rng.gen_range(0..self.committee.len())).collect();
+            (indices, members)
+        };
+
+        let mut samples = Vec::with_capacity(count);
+        let mut seen = HashSet::new();
+        for (index, member) in indices.into_iter().zip(members) {
+            let proof = self.committee[member]
+                .fetch_chunk(commitment, index)
+                .await
+                .map_err(|e| AvailabilityError::Unavailable {
+                    index,
+                    member,
+                    reason: e.to_string(),
+                })?;
+            if proof.index != index || !proof.verify(commitment) {
+                return Err(AvailabilityError::InvalidChunk { index, member });
+            }
+            seen.insert(index);
+            samples.push(proof);
+        }
+
+        let pass_rate = commitment.data_shards as f64 / total as f64;
+        Ok(SamplingReport {
+            commitment: commitment.clone(),
+            samples,
+            false_accept_bound: pass_rate.powi(seen.len() as i32),
+        })
+    }
+}
+
+fn leaf_hash(index: usize, chunk: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update((index as u64).to_le_bytes());
+    hasher.update(chunk);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn merkle_levels(chunks: &[Vec<u8>]) -> Vec<Vec<[u8; 32]>> {
+    let mut levels = vec![chunks.iter().enumerate().map(|(i, c)| leaf_hash(i, c)).collect::<Vec<_>>()];
+    while levels.last().expect("at least one level").len() > 1 {
+        let level = levels.last().expect("at least one level");
+        let next = level
+            .chunks(2)
+            .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
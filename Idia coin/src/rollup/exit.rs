@@ -0,0 +1,481 @@
+use async_trait::async_trait;
+use blstrs::Scalar as Fr;
+use chrono::{DateTime, Duration, Utc};
+use ff::PrimeField;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use super::fraud::state_key;
+use super::hash::hash_pair;
+use super::pedersen::commitment_value;
+use super::processor::RollupError;
+use super::state::{MerkleWitness, STATE_TREE_DEPTH};
+
+// Same derivation the transaction circuit enforces when a note is spent
+pub fn note_nullifier<F: PrimeField>(spend_key: F, commitment: F) -> F {
+    hash_pair(spend_key, commitment)
+}
+
+// Scalars are journaled as their 32 little-endian bytes
+mod scalar_bytes {
+    use super::*;
+    use serde::de::Error;
+
+    pub fn serialize<S: Serializer>(scalar: &Fr, serializer: S) -> Result<S::Ok, S::Error> {
+        scalar.to_bytes_le().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Fr, D::Error> {
+        let bytes = <[u8; 32]>::deserialize(deserializer)?;
+        Option::from(Fr::from_bytes_le(&bytes)).ok_or_else(|| D::Error::custom("scalar out of range"))
+    }
+}
+
+// Everything needed to open a rollup note. Knowing the spend key is what
+// authorizes the exit, and `owner` is the main-chain address paid. Exiting
+// reveals the spend key, so keys must be per note.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteOpening {
+    pub amount: u64,
+    pub owner: String,
+    #[serde(with = "scalar_bytes")]
+    pub blinding: Fr,
+    #[serde(with = "scalar_bytes")]
+    pub spend_key: Fr,
+}
+
+impl NoteOpening {
+    pub fn commitment(&self) -> Fr {
+        commitment_value(self.amount, self.blinding)
+    }
+
+    pub fn nullifier(&self) -> Fr {
+        note_nullifier(self.spend_key, self.commitment())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    Queued,
+    // Written before the payout is sent; if the node dies before the outcome
+    // is known the exit stays blocked until an operator clears it
+    Paying,
+    Released,
+    // The note was spent on the rollup before the exit matured
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitRequest {
+    pub id: u64,
+    pub note: NoteOpening,
+    #[serde(with = "scalar_bytes")]
+    pub state_root: Fr,
+    pub requested_at: DateTime<Utc>,
+    pub available_at: DateTime<Utc>,
+    pub status: ExitStatus,
+    pub release_tx: Option<String>,
+}
+
+// Pays a matured exit out on the main chain
+#[async_trait]
+pub trait ExitPayout: Send + Sync {
+    async fn pay(&self, exit: &ExitRequest) -> Result<String, RollupError>;
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum ExitRecord {
+    Finalized {
+        #[serde(with = "scalar_bytes")]
+        root: Fr,
+    },
+    Exit(ExitRequest),
+}
+
+#[derive(Default)]
+struct ExitState {
+    next_id: u64,
+    finalized_root: Fr,
+    exits: BTreeMap<u64, ExitRequest>,
+    // Nullifiers of every note that has exited or is exiting
+    nullifiers: HashSet<[u8; 32]>,
+}
+
+impl ExitState {
+    fn apply(&mut self, record: ExitRecord) {
+        match record {
+            ExitRecord::Finalized { root } => self.finalized_root = root,
+            ExitRecord::Exit(exit) => {
+                self.next_id = self.next_id.max(exit.id + 1);
+                self.nullifiers.insert(exit.note.nullifier().to_bytes_le());
+                self.exits.insert(exit.id, exit);
+            }
+        }
+    }
+}
+
+// User-initiated exits. A note exits by proving it is in the last finalized
+// state root and not yet spent there; funds are paid after `delay`, which gives
+// an in-flight rollup spend of the same note time to finalize and cancel the
+// exit. Queued nullifiers must be recorded by the rollup so the note cannot
+// be spent once it has left.
+//
+// Every change is appended to a JSON-lines journal and synced before it takes
+// effect, so a restart neither forgets an exit nor lets its note exit twice.
+pub struct ExitQueue {
+    delay: Duration,
+    path: PathBuf,
+    state: RwLock<ExitState>,
+    payout: Arc<dyn ExitPayout>,
+}
+
+impl ExitQueue {
+    // `finalized_root` seeds a new journal; once `finalize` has recorded a
+    // root, the recorded one wins
+    pub async fn open(
+        path: PathBuf,
+        finalized_root: Fr,
+        delay: Duration,
+        payout: Arc<dyn ExitPayout>,
+    ) -> Result<Self, RollupError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut state = ExitState {
+            finalized_root,
+            ..ExitState::default()
+        };
+        let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(record) => state.apply(record),
+                // A crash mid-append leaves a partial last line; anything earlier is real corruption
+                Err(e) if index + 1 == lines.len() => {
+                    tracing::warn!("Dropping torn last record of {}: {}", path.display(), e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let paying = state.exits.values().filter(|exit| exit.status == ExitStatus::Paying).count();
+        if paying > 0 {
+            tracing::warn!("{} exit payouts have an unknown outcome and stay blocked until cleared", paying);
+        }
+        let queue = Self {
+            delay,
+            path,
+            state: RwLock::new(state),
+            payout,
+        };
+        queue.compact(&*queue.state.read().await).await?;
+        Ok(queue)
+    }
+
+    pub async fn exit(&self, id: u64) -> Option<ExitRequest> {
+        self.state.read().await.exits.get(&id).cloned()
+    }
+
+    // Nullifiers of queued exits, for the sequencer to record in the state
+    pub async fn pending_nullifiers(&self) -> Vec<Fr> {
+        self.state
+            .read()
+            .await
+            .exits
+            .values()
+            .filter(|exit| exit.status == ExitStatus::Queued)
+            .map(|exit| exit.note.nullifier())
+            .collect()
+    }
+
+    // Called as rollup batches finalize, with the nullifiers they published
+    pub async fn finalize(&self, root: Fr, spent: impl IntoIterator<Item = Fr>) -> Result<(), RollupError> {
+        let spent: HashSet<[u8; 32]> = spent.into_iter().map(|n| n.to_bytes_le()).collect();
+        let mut state = self.state.write().await;
+        let mut records = Vec::new();
+        for exit in state.exits.values() {
+            if exit.status == ExitStatus::Queued && spent.contains(&exit.note.nullifier().to_bytes_le()) {
+                tracing::warn!("Exit {} cancelled: note spent on the rollup first", exit.id);
+                records.push(ExitRecord::Exit(ExitRequest {
+                    status: ExitStatus::Cancelled,
+                    ..exit.clone()
+                }));
+            }
+        }
+        records.push(ExitRecord::Finalized { root });
+        self.record(&mut state, records).await
+    }
+
+    // For an operator who has confirmed on the main chain that a payout
+    // never landed; the exit is paid again on the next sweep
+    pub async fn clear(&self, id: u64) -> Result<(), RollupError> {
+        let mut state = self.state.write().await;
+        match state.exits.get(&id) {
+            Some(exit) if exit.status == ExitStatus::Paying => {
+                let exit = ExitRequest {
+                    status: ExitStatus::Queued,
+                    ..exit.clone()
+                };
+                self.record(&mut state, vec![ExitRecord::Exit(exit)]).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // `commitment_witness` shows the note in the finalized state and
+    // `nullifier_witness` that it is unspent there
+    pub async fn request_exit(
+        &self,
+        note: NoteOpening,
+        commitment_witness: &MerkleWitness,
+        nullifier_witness: &MerkleWitness,
+    ) -> Result<u64, RollupError> {
+        if note.amount == 0 {
+            return Err(RollupError::InvalidExit("nothing to exit".to_string()));
+        }
+        let (commitment, nullifier) = (note.commitment(), note.nullifier());
+
+        let mut state = self.state.write().await;
+        let root = state.finalized_root;
+        let included = commitment_witness.key == state_key(commitment)
+            && commitment_witness.value == Some(commitment)
+            && commitment_witness.siblings.len() == STATE_TREE_DEPTH
+            && commitment_witness.verify(root);
+        if !included {
+            return Err(RollupError::InvalidExit("note is not in the finalized state".to_string()));
+        }
+        let unspent = nullifier_witness.key == state_key(nullifier)
+            && nullifier_witness.value.is_none()
+            && nullifier_witness.siblings.len() == STATE_TREE_DEPTH
+            && nullifier_witness.verify(root);
+        if !unspent {
+            return Err(RollupError::InvalidExit("note is spent in the finalized state".to_string()));
+        }
+        if state.nullifiers.contains(&nullifier.to_bytes_le()) {
+            return Err(RollupError::InvalidExit("note is already exiting".to_string()));
+        }
+
+        let id = state.next_id;
+        let now = Utc::now();
+        let (amount, owner) = (note.amount, note.owner.clone());
+        let exit = ExitRequest {
+            id,
+            note,
+            state_root: root,
+            requested_at: now,
+            available_at: now + self.delay,
+            status: ExitStatus::Queued,
+            release_tx: None,
+        };
+        self.record(&mut state, vec![ExitRecord::Exit(exit)]).await?;
+        tracing::info!("Exit {} of {} to {} queued", id, amount, owner);
+        Ok(id)
+    }
+
+    // Pays out every exit whose delay has passed
+    pub async fn release_due(&self, now: DateTime<Utc>) -> Result<Vec<u64>, RollupError> {
+        let due: Vec<ExitRequest> = self
+            .state
+            .read()
+            .await
+            .exits
+            .values()
+            .filter(|exit| exit.status == ExitStatus::Queued && exit.available_at <= now)
+            .cloned()
+            .collect();
+
+        let mut released = Vec::new();
+        for exit in due {
+            // A finalized spend may have cancelled the exit, or another sweep
+            // claimed it, since the snapshot above
+            let exit = {
+                let mut state = self.state.write().await;
+                let exit = match state.exits.get(&exit.id) {
+                    Some(stored) if stored.status == ExitStatus::Queued => ExitRequest {
+                        status: ExitStatus::Paying,
+                        ..stored.clone()
+                    },
+                    _ => continue,
+                };
+                self.record(&mut state, vec![ExitRecord::Exit(exit.clone())]).await?;
+                exit
+            };
+
+            let outcome = self.payout.pay(&exit).await;
+            let mut state = self.state.write().await;
+            let settled = match outcome {
+                Ok(release_tx) => ExitRequest {
+                    status: ExitStatus::Released,
+                    release_tx: Some(release_tx),
+                    ..exit
+                },
+                Err(e) => {
+                    tracing::warn!("Payout of exit {} failed, retrying: {}", exit.id, e);
+                    ExitRequest {
+                        status: ExitStatus::Queued,
+                        ..exit
+                    }
+                }
+            };
+            let id = settled.id;
+            let paid = settled.status == ExitStatus::Released;
+            self.record(&mut state, vec![ExitRecord::Exit(settled)]).await?;
+            if paid {
+                released.push(id);
+            }
+        }
+        Ok(released)
+    }
+
+    // Syncs the records to the journal, then applies them; callers hold the
+    // write lock so the journal order matches the in-memory order
+    async fn record(&self, state: &mut ExitState, records: Vec<ExitRecord>) -> Result<(), RollupError> {
+        let mut lines = Vec::new();
+        for record in &records {
+            lines.extend(serde_json::to_vec(record)?);
+            lines.push(b'\n');
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&lines).await?;
+        file.sync_data().await?;
+        for record in records {
+            state.apply(record);
+        }
+        Ok(())
+    }
+
+    async fn compact(&self, state: &ExitState) -> Result<(), RollupError> {
+        let mut contents = serde_json::to_vec(&ExitRecord::Finalized {
+            root: state.finalized_root,
+        })?;
+        contents.push(b'\n');
+        for exit in state.exits.values() {
+            contents.extend(serde_json::to_vec(&ExitRecord::Exit(exit.clone()))?);
+            contents.push(b'\n');
+        }
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, contents).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+
+    pub fn spawn(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                match self.release_due(Utc::now()).await {
+                    Ok(released) => {
+                        for id in released {
+                            tracing::info!("Exit {} released on the main chain", id);
+                        }
+                    }
+                    Err(e) => tracing::error!("Exit release sweep failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollup::state::SparseMerkleTree;
+    use ff::Field;
+    use rand::rngs::OsRng;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingPayout {
+        paid: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ExitPayout for CountingPayout {
+        async fn pay(&self, exit: &ExitRequest) -> Result<String, RollupError> {
+            self.paid.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("release-{}", exit.id))
+        }
+    }
+
+    fn note() -> NoteOpening {
+        NoteOpening {
+            amount: 500,
+            owner: "owner".to_string(),
+            blinding: Fr::random(&mut OsRng),
+            spend_key: Fr::random(&mut OsRng),
+        }
+    }
+
+    fn witnesses(state: &SparseMerkleTree, note: &NoteOpening) -> (MerkleWitness, MerkleWitness) {
+        (
+            state.witness(state_key(note.commitment())).unwrap(),
+            state.witness(state_key(note.nullifier())).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_exits_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("exits.jsonl");
+        let note = note();
+        let mut state = SparseMerkleTree::new(STATE_TREE_DEPTH);
+        state.insert(state_key(note.commitment()), note.commitment()).unwrap();
+        let (commitment_witness, nullifier_witness) = witnesses(&state, &note);
+        let payout = Arc::new(CountingPayout::default());
+
+        let id = {
+            let queue = ExitQueue::open(path.clone(), state.root(), Duration::zero(), payout.clone())
+                .await
+                .unwrap();
+            queue.request_exit(note.clone(), &commitment_witness, &nullifier_witness).await.unwrap()
+        };
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(&path).await.unwrap();
+        file.write_all(br#"{"record":"exi"#).await.unwrap();
+        drop(file);
+
+        // Opened without the root: the recorded one still verifies the note
+        let queue = ExitQueue::open(path, Fr::ZERO, Duration::zero(), payout.clone()).await.unwrap();
+        assert_eq!(queue.exit(id).await.unwrap().status, ExitStatus::Queued);
+        assert!(queue.request_exit(note, &commitment_witness, &nullifier_witness).await.is_err());
+
+        assert_eq!(queue.release_due(Utc::now()).await.unwrap(), vec![id]);
+        assert!(queue.release_due(Utc::now()).await.unwrap().is_empty());
+        assert_eq!(payout.paid.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_exit_is_not_paid() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = note();
+        let mut state = SparseMerkleTree::new(STATE_TREE_DEPTH);
+        state.insert(state_key(note.commitment()), note.commitment()).unwrap();
+        let (commitment_witness, nullifier_witness) = witnesses(&state, &note);
+        let payout = Arc::new(CountingPayout::default());
+
+        let queue = ExitQueue::open(dir.path().join("exits.jsonl"), state.root(), Duration::zero(), payout.clone())
+            .await
+            .unwrap();
+        let id = queue.request_exit(note.clone(), &commitment_witness, &nullifier_witness).await.unwrap();
+        queue.finalize(state.root(), [note.nullifier()]).await.unwrap();
+
+        assert!(queue.release_due(Utc::now()).await.unwrap().is_empty());
+        assert_eq!(queue.exit(id).await.unwrap().status, ExitStatus::Cancelled);
+        assert_eq!(payout.paid.load(Ordering::SeqCst), 0);
+    }
+}
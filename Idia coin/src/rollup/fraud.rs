@@ -0,0 +1,189 @@
+use blstrs::Scalar as Fr;
+use ff::{Field, PrimeField};
+
+use super::processor::{RollupError, TransactionCircuit};
+use super::state::{MerkleWitness, SparseMerkleTree, STATE_TREE_DEPTH};
+
+// Where a nullifier or commitment lives in the state tree
+pub fn state_key(value: Fr) -> u64 {
+    let repr = value.to_repr();
+    u64::from_le_bytes(repr.as_ref()[..8].try_into().expect("8 bytes"))
+}
+
+// The rollup's state transition. A transaction records its nullifier and its
+// output commitment; one that would reuse either slot (a double spend or a
+// repeated note) executes as a no-op rather than failing the batch, so every
+// batch has exactly one correct post-state.
+pub fn apply_transaction(state: &mut SparseMerkleTree, tx: &TransactionCircuit<Fr>) -> bool {
+    let Some([nullifier, commitment]) = tx.public_inputs() else {
+        return false;
+    };
+    if nullifier == Fr::ZERO || commitment == Fr::ZERO {
+        return false;
+    }
+    let (nullifier_key, commitment_key) = (state_key(nullifier), state_key(commitment));
+    if nullifier_key == commitment_key || state.get(nullifier_key).is_some() || state.get(commitment_key).is_some() {
+        return false;
+    }
+    state.insert(nullifier_key, nullifier).expect("slot checked empty");
+    state.insert(commitment_key, commitment).expect("slot checked empty");
+    true
+}
+
+// State root after each transaction, in batch order
+pub fn execute_batch(state: &mut SparseMerkleTree, transactions: &[TransactionCircuit<Fr>]) -> Vec<Fr> {
+    transactions
+        .iter()
+        .map(|tx| {
+            apply_transaction(state, tx);
+            state.root()
+        })
+        .collect()
+}
+
+// Shows that one step of a batch's claimed trace is wrong. The witnesses let
+// any node re-execute the disputed transaction from the root before it without
+// holding the rollup state: the nullifier slot against that root, then the
+// commitment slot against the root once the nullifier is recorded.
+#[derive(Debug, Clone)]
+pub struct FraudProof {
+    pub batch_id: u64,
+    pub tx_index: usize,
+    pub challenger: String,
+    pub nullifier_witness: MerkleWitness,
+    pub commitment_witness: MerkleWitness,
+}
+
+impl FraudProof {
+    // `state` must be the rollup state just before the disputed transaction
+    pub fn construct(
+        state: &SparseMerkleTree,
+        batch_id: u64,
+        tx_index: usize,
+        tx: &TransactionCircuit<Fr>,
+        challenger: &str,
+    ) -> Result<Self, RollupError> {
+        let [nullifier, commitment] = tx.public_inputs().unwrap_or([Fr::ZERO; 2]);
+        let nullifier_witness = state.witness(state_key(nullifier))?;
+
+        let mut mid = state.clone();
+        if nullifier != Fr::ZERO && nullifier_witness.value.is_none() {
+            mid.insert(nullifier_witness.key, nullifier)?;
+        }
+        let commitment_witness = mid.witness(state_key(commitment))?;
+
+        Ok(Self {
+            batch_id,
+            tx_index,
+            challenger: challenger.to_string(),
+            nullifier_witness,
+            commitment_witness,
+        })
+    }
+
+    // Re-executes the transaction from `pre_root` and returns the correct
+    // post-state root; the proof holds when it differs from the claimed one
+    pub fn re_execute(&self, tx: &TransactionCircuit<Fr>, pre_root: Fr) -> Result<Fr, RollupError> {
+        let Some([nullifier, commitment]) = tx.public_inputs() else {
+            return Ok(pre_root);
+        };
+        if nullifier == Fr::ZERO || commitment == Fr::ZERO {
+            return Ok(pre_root);
+        }
+
+        let nullifier_slot = &self.nullifier_witness;
+        if nullifier_slot.key != state_key(nullifier)
+            || nullifier_slot.siblings.len() != STATE_TREE_DEPTH
+            || !nullifier_slot.verify(pre_root)
+        {
+            return Err(RollupError::FraudProofRejected("nullifier witness does not match the pre-state".to_string()));
+        }
+        if nullifier_slot.value.is_some() {
+            return Ok(pre_root);
+        }
+        let mid_root = MerkleWitness {
+            value: Some(nullifier),
+            ..nullifier_slot.clone()
+        }
+        .root();
+
+        let commitment_slot = &self.commitment_witness;
+        if commitment_slot.key != state_key(commitment)
+            || commitment_slot.siblings.len() != STATE_TREE_DEPTH
+            || !commitment_slot.verify(mid_root)
+        {
+            return Err(RollupError::FraudProofRejected(
+                "commitment witness does not match the intermediate state".to_string(),
+            ));
+        }
+        // Taken by this very nullifier, or by an earlier note
+        if commitment_slot.value.is_some() {
+            return Ok(pre_root);
+        }
+        Ok(MerkleWitness {
+            value: Some(commitment),
+            ..commitment_slot.clone()
+        }
+        .root())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn transaction() -> TransactionCircuit<Fr> {
+        TransactionCircuit::new(1000, Fr::random(&mut OsRng), Fr::random(&mut OsRng), Fr::random(&mut OsRng))
+    }
+
+    fn state() -> SparseMerkleTree {
+        let mut state = SparseMerkleTree::default();
+        execute_batch(&mut state, &[transaction(), transaction()]);
+        state
+    }
+
+    #[test]
+    fn test_fraud_proof_re_executes_transaction() {
+        let state = state();
+        let tx = transaction();
+        let proof = FraudProof::construct(&state, 1, 0, &tx, "challenger").unwrap();
+
+        let mut post = state.clone();
+        assert!(apply_transaction(&mut post, &tx));
+        assert_eq!(proof.re_execute(&tx, state.root()).unwrap(), post.root());
+        assert_ne!(post.root(), state.root());
+    }
+
+    #[test]
+    fn test_double_spend_executes_as_no_op() {
+        let mut state = state();
+        let tx = transaction();
+        assert!(apply_transaction(&mut state, &tx));
+        let root = state.root();
+        assert!(!apply_transaction(&mut state, &tx));
+        assert_eq!(state.root(), root);
+
+        let proof = FraudProof::construct(&state, 1, 1, &tx, "challenger").unwrap();
+        assert_eq!(proof.re_execute(&tx, root).unwrap(), root);
+    }
+
+    #[test]
+    fn test_rejects_witness_for_other_state() {
+        let state = state();
+        let tx = transaction();
+        let proof = FraudProof::construct(&state, 1, 0, &tx, "challenger").unwrap();
+
+        assert!(matches!(
+            proof.re_execute(&tx, Fr::random(&mut OsRng)),
+            Err(RollupError::FraudProofRejected(_))
+        ));
+
+        // Witnesses built for a different transaction
+        assert!(proof.re_execute(&transaction(), state.root()).is_err());
+
+        let mut tampered = proof;
+        tampered.commitment_witness.siblings[0] += Fr::ONE;
+        assert!(tampered.re_execute(&tx, state.root()).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use bellpepper_core::num::AllocatedNum;
+use bellpepper_core::{ConstraintSystem, SynthesisError};
+use ff::PrimeField;
+use sha2::{Digest, Sha256};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+// MiMC with x^5 (x^3 is not a permutation of the BLS12-381 scalar field),
+// chained Miyaguchi-Preneel style. Cheap in-circuit: three constraints a round.
+// Generic over the field so folding backends on other curves hash the same way.
+const MIMC_ROUNDS: usize = 110;
+
+// Derived once per field and kept for the life of the process. A static in a
+// generic fn is shared by every F, so the cache is keyed by the field's type.
+fn mimc_constants<F: PrimeField>() -> &'static [F] {
+    type Cache = RwLock<HashMap<TypeId, &'static (dyn Any + Send + Sync)>>;
+    static CACHE: OnceLock<Cache> = OnceLock::new();
+
+    let cache = CACHE.get_or_init(Cache::default);
+    let key = TypeId::of::<F>();
+    let cached = cache.read().unwrap().get(&key).copied();
+    let constants = match cached {
+        Some(constants) => constants,
+        None => *cache.write().unwrap().entry(key).or_insert_with(|| {
+            let constants: Vec<F> = (0..MIMC_ROUNDS)
+                .map(|i| hash_bytes(format!("idia-rollup-mimc-{}", i).as_bytes()))
+                .collect();
+            Box::leak(Box::new(constants))
+        }),
+    };
+    constants.downcast_ref::<Vec<F>>().expect("cached under its own type")
+}
+
+fn mimc_encrypt<F: PrimeField>(mut x: F, key: F, constants: &[F]) -> F {
+    for c in constants {
+        let t = x + key + c;
+        let t2 = t.square();
+        x = t2.square() * t;
+    }
+    x + key
+}
+
+pub fn mimc_hash<F: PrimeField>(inputs: &[F]) -> F {
+    let constants = mimc_constants::<F>();
+    inputs
+        .iter()
+        .fold(F::ZERO, |h, m| mimc_encrypt(*m, h, constants) + m + h)
+}
+
+pub fn hash_pair<F: PrimeField>(left: F, right: F) -> F {
+    mimc_hash(&[left, right])
+}
+
+// Maps arbitrary bytes into the field, for data that is not already a field element
+pub fn hash_bytes<F: PrimeField>(bytes: &[u8]) -> F {
+    let digest = Sha256::digest(bytes);
+    let hi = u128::from_be_bytes(digest[..16].try_into().expect("16 bytes"));
+    let lo = u128::from_be_bytes(digest[16..].try_into().expect("16 bytes"));
+    let shift = F::from_u128(1 << 64).square();
+    F::from_u128(hi) * shift + F::from_u128(lo)
+}
+
+fn mimc_encrypt_gadget<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    x: &AllocatedNum<F>,
+    key: &AllocatedNum<F>,
+    constants: &[F],
+) -> Result<AllocatedNum<F>, SynthesisError> {
+    let mut x = x.clone();
+    for (i, c) in constants.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", i));
+        let t_value = x.get_value().zip(key.get_value()).map(|(x, k)| x + k + c);
+
+        let t2 = AllocatedNum::alloc(cs.namespace(|| "t^2"), || {
+            t_value.map(|t| t.square()).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "t^2 = t * t",
+            |lc| lc + x.get_variable() + key.get_variable() + (*c, CS::one()),
+            |lc| lc + x.get_variable() + key.get_variable() + (*c, CS::one()),
+            |lc| lc + t2.get_variable(),
+        );
+        let t4 = t2.square(cs.namespace(|| "t^4"))?;
+        let t5 = AllocatedNum::alloc(cs.namespace(|| "t^5"), || {
+            t4.get_value()
+                .zip(t_value)
+                .map(|(t4, t)| t4 * t)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "t^5 = t^4 * t",
+            |lc| lc + t4.get_variable(),
+            |lc| lc + x.get_variable() + key.get_variable() + (*c, CS::one()),
+            |lc| lc + t5.get_variable(),
+        );
+        x = t5;
+    }
+
+    let out = AllocatedNum::alloc(cs.namespace(|| "x + key"), || {
+        x.get_value()
+            .zip(key.get_value())
+            .map(|(x, k)| x + k)
+            .ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    cs.enforce(
+        || "out = x + key",
+        |lc| lc + x.get_variable() + key.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + out.get_variable(),
+    );
+    Ok(out)
+}
+
+// In-circuit twin of `mimc_hash`
+pub fn mimc_hash_gadget<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    inputs: &[AllocatedNum<F>],
+) -> Result<AllocatedNum<F>, SynthesisError> {
+    let constants = mimc_constants::<F>();
+    let mut h = AllocatedNum::alloc(cs.namespace(|| "iv"), || Ok(F::ZERO))?;
+    cs.enforce(|| "iv = 0", |lc| lc + h.get_variable(), |lc| lc + CS::one(), |lc| lc);
+
+    for (i, m) in inputs.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("block {}", i));
+        let e = mimc_encrypt_gadget(cs.namespace(|| "encrypt"), m, &h, constants)?;
+        let next = AllocatedNum::alloc(cs.namespace(|| "chain"), || {
+            e.get_value()
+                .zip(m.get_value())
+                .zip(h.get_value())
+                .map(|((e, m), h)| e + m + h)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "chain = e + m + h",
+            |lc| lc + e.get_variable() + m.get_variable() + h.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + next.get_variable(),
+        );
+        h = next;
+    }
+    Ok(h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellpepper_core::test_cs::TestConstraintSystem;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_gadget_matches_native_hash() {
+        let inputs = [Fr::random(&mut OsRng), Fr::random(&mut OsRng)];
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let allocated: Vec<_> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*input)).unwrap())
+            .collect();
+        let hash = mimc_hash_gadget(cs.namespace(|| "hash"), &allocated).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(hash.get_value(), Some(hash_pair(inputs[0], inputs[1])));
+    }
+
+    #[test]
+    fn test_hash_is_order_sensitive() {
+        let (a, b) = (Fr::random(&mut OsRng), Fr::random(&mut OsRng));
+        assert_ne!(hash_pair(a, b), hash_pair(b, a));
+        assert_ne!(mimc_hash(&[a]), mimc_hash(&[a, Fr::ZERO]));
+        assert_eq!(hash_bytes::<Fr>(b"idia"), hash_bytes::<Fr>(b"idia"));
+    }
+}
@@ -0,0 +1,287 @@
+use bellpepper_core::boolean::AllocatedBit;
+use bellpepper_core::num::AllocatedNum;
+use bellpepper_core::{ConstraintSystem, LinearCombination, SynthesisError};
+use ff::{PrimeField, PrimeFieldBits};
+
+use super::hash::hash_bytes;
+
+pub const AMOUNT_BITS: usize = 64;
+
+// Pedersen commitments on Jubjub, the twisted Edwards curve
+// -x^2 + y^2 = 1 + d x^2 y^2 embedded in the BLS12-381 scalar field, so
+// commitments open in-circuit at a few constraints per scalar bit. Edwards
+// addition is complete here (d is a non-square), so no bit pattern hits an
+// exceptional case.
+fn edwards_d<F: PrimeField>() -> F {
+    -(F::from(10240) * F::from(10241).invert().expect("10241 is non-zero"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdwardsPoint<F: PrimeField> {
+    pub x: F,
+    pub y: F,
+}
+
+impl<F: PrimeField> EdwardsPoint<F> {
+    pub fn identity() -> Self {
+        Self { x: F::ZERO, y: F::ONE }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let t = edwards_d::<F>() * self.x * other.x * self.y * other.y;
+        Self {
+            x: (self.x * other.y + self.y * other.x) * (F::ONE + t).invert().expect("complete addition"),
+            y: (self.y * other.y + self.x * other.x) * (F::ONE - t).invert().expect("complete addition"),
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        self.add(self)
+    }
+
+    // Little-endian scalar bits
+    pub fn mul_bits(&self, bits: impl IntoIterator<Item = bool>) -> Self {
+        let mut acc = Self::identity();
+        let mut base = *self;
+        for bit in bits {
+            if bit {
+                acc = acc.add(&base);
+            }
+            base = base.double();
+        }
+        acc
+    }
+
+    // Try-and-increment onto the curve, then clear the cofactor of 8 so the
+    // point lies in the prime-order subgroup. Nobody knows the discrete log
+    // between two such points.
+    fn hash_to_curve(tag: &str) -> Self {
+        let d = edwards_d::<F>();
+        for i in 0u32.. {
+            let y: F = hash_bytes(format!("{}-{}", tag, i).as_bytes());
+            let x2 = (y.square() - F::ONE) * (F::ONE + d * y.square()).invert().expect("d is a non-square");
+            let Some(x) = Option::<F>::from(x2.sqrt()) else {
+                continue;
+            };
+            let point = Self { x, y }.double().double().double();
+            if point != Self::identity() {
+                return point;
+            }
+        }
+        unreachable!("half of all y values are on the curve")
+    }
+}
+
+// Value and blinding bases
+pub fn generators<F: PrimeField>() -> (EdwardsPoint<F>, EdwardsPoint<F>) {
+    (
+        EdwardsPoint::hash_to_curve("idia-rollup-pedersen-value"),
+        EdwardsPoint::hash_to_curve("idia-rollup-pedersen-blinding"),
+    )
+}
+
+pub fn pedersen_commit<F: PrimeFieldBits>(amount: u64, blinding: F) -> EdwardsPoint<F> {
+    let (g, h) = generators::<F>();
+    let value = g.mul_bits((0..AMOUNT_BITS).map(|i| (amount >> i) & 1 == 1));
+    let blind = h.mul_bits(blinding.to_le_bits().iter().by_vals().take(F::NUM_BITS as usize));
+    value.add(&blind)
+}
+
+// What goes on chain: the x-coordinate alone identifies a subgroup point,
+// since its only other point with that x is off the subgroup
+pub fn commitment_value<F: PrimeFieldBits>(amount: u64, blinding: F) -> F {
+    pedersen_commit(amount, blinding).x
+}
+
+pub struct AllocatedPoint<F: PrimeField> {
+    pub x: AllocatedNum<F>,
+    pub y: AllocatedNum<F>,
+}
+
+// Decomposes `num` into `n` little-endian bits and constrains them to pack
+// back to it. With n below the field size this is also a range proof: `num`
+// must be less than 2^n.
+pub fn alloc_bits<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    num: &AllocatedNum<F>,
+    n: usize,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    let values: Vec<Option<bool>> = match num.get_value() {
+        Some(value) => value.to_le_bits().iter().by_vals().take(n).map(Some).collect(),
+        None => vec![None; n],
+    };
+    let bits = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), value))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = F::ONE;
+    for bit in &bits {
+        packed = packed + (coeff, bit.get_variable());
+        coeff = coeff.double();
+    }
+    cs.enforce(|| "packing", |_| packed, |lc| lc + CS::one(), |lc| lc + num.get_variable());
+    Ok(bits)
+}
+
+// acc + (bit ? base : identity), six constraints. The selected point is linear
+// in the bit: (bit * base.x, 1 + bit * (base.y - 1)).
+fn add_fixed_conditional<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    acc: &AllocatedPoint<F>,
+    bit: &AllocatedBit,
+    base: &EdwardsPoint<F>,
+) -> Result<AllocatedPoint<F>, SynthesisError> {
+    let d = edwards_d::<F>();
+    let b = bit.get_variable();
+    let selected = bit
+        .get_value()
+        .map(|set| if set { *base } else { EdwardsPoint::identity() });
+    let (x1, y1) = (acc.x.get_value(), acc.y.get_value());
+    let (x2, y2) = (selected.map(|p| p.x), selected.map(|p| p.y));
+    let value = |v: Option<F>| v.ok_or(SynthesisError::AssignmentMissing);
+
+    let a = AllocatedNum::alloc(cs.namespace(|| "x1x2"), || Ok(value(x1)? * value(x2)?))?;
+    cs.enforce(
+        || "a = x1 x2",
+        |lc| lc + acc.x.get_variable(),
+        |lc| lc + (base.x, b),
+        |lc| lc + a.get_variable(),
+    );
+    let bb = AllocatedNum::alloc(cs.namespace(|| "y1y2"), || Ok(value(y1)? * value(y2)?))?;
+    cs.enforce(
+        || "b = y1 y2",
+        |lc| lc + acc.y.get_variable(),
+        |lc| lc + CS::one() + (base.y - F::ONE, b),
+        |lc| lc + bb.get_variable(),
+    );
+    let t = AllocatedNum::alloc(cs.namespace(|| "(x1+y1)(x2+y2)"), || {
+        Ok((value(x1)? + value(y1)?) * (value(x2)? + value(y2)?))
+    })?;
+    cs.enforce(
+        || "t = (x1 + y1)(x2 + y2)",
+        |lc| lc + acc.x.get_variable() + acc.y.get_variable(),
+        |lc| lc + CS::one() + (base.x + base.y - F::ONE, b),
+        |lc| lc + t.get_variable(),
+    );
+    let c = a.mul(cs.namespace(|| "x1x2y1y2"), &bb)?;
+
+    let x3 = AllocatedNum::alloc(cs.namespace(|| "x3"), || {
+        let denominator = F::ONE + d * value(c.get_value())?;
+        let numerator = value(t.get_value())? - value(a.get_value())? - value(bb.get_value())?;
+        Ok(numerator * Option::<F>::from(denominator.invert()).ok_or(SynthesisError::DivisionByZero)?)
+    })?;
+    cs.enforce(
+        || "x3 (1 + d c) = t - a - b",
+        |lc| lc + CS::one() + (d, c.get_variable()),
+        |lc| lc + x3.get_variable(),
+        |lc| lc + t.get_variable() - a.get_variable() - bb.get_variable(),
+    );
+    let y3 = AllocatedNum::alloc(cs.namespace(|| "y3"), || {
+        let denominator = F::ONE - d * value(c.get_value())?;
+        let numerator = value(a.get_value())? + value(bb.get_value())?;
+        Ok(numerator * Option::<F>::from(denominator.invert()).ok_or(SynthesisError::DivisionByZero)?)
+    })?;
+    cs.enforce(
+        || "y3 (1 - d c) = a + b",
+        |lc| lc + CS::one() - (d, c.get_variable()),
+        |lc| lc + y3.get_variable(),
+        |lc| lc + a.get_variable() + bb.get_variable(),
+    );
+    Ok(AllocatedPoint { x: x3, y: y3 })
+}
+
+// Sum of bits_i * 2^i * base over little-endian bits
+fn fixed_base_mul<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    acc: AllocatedPoint<F>,
+    bits: &[AllocatedBit],
+    base: EdwardsPoint<F>,
+) -> Result<AllocatedPoint<F>, SynthesisError> {
+    let mut acc = acc;
+    let mut power = base;
+    for (i, bit) in bits.iter().enumerate() {
+        acc = add_fixed_conditional(cs.namespace(|| format!("bit {}", i)), &acc, bit, &power)?;
+        power = power.double();
+    }
+    Ok(acc)
+}
+
+// In-circuit twin of `pedersen_commit`, from bits already constrained
+pub fn pedersen_commit_gadget<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    amount_bits: &[AllocatedBit],
+    blinding_bits: &[AllocatedBit],
+) -> Result<AllocatedPoint<F>, SynthesisError> {
+    let (g, h) = generators::<F>();
+    let x = AllocatedNum::alloc(cs.namespace(|| "identity x"), || Ok(F::ZERO))?;
+    cs.enforce(|| "identity x = 0", |lc| lc + x.get_variable(), |lc| lc + CS::one(), |lc| lc);
+    let y = AllocatedNum::alloc(cs.namespace(|| "identity y"), || Ok(F::ONE))?;
+    cs.enforce(
+        || "identity y = 1",
+        |lc| lc + y.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + CS::one(),
+    );
+
+    let acc = fixed_base_mul(cs.namespace(|| "value"), AllocatedPoint { x, y }, amount_bits, g)?;
+    fixed_base_mul(cs.namespace(|| "blinding"), acc, blinding_bits, h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellpepper_core::test_cs::TestConstraintSystem;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    fn alloc(cs: &mut TestConstraintSystem<Fr>, name: &str, value: Fr) -> AllocatedNum<Fr> {
+        AllocatedNum::alloc(cs.namespace(|| name), || Ok(value)).unwrap()
+    }
+
+    #[test]
+    fn test_gadget_matches_native_commitment() {
+        let (amount, blinding) = (1_000_000u64, Fr::random(&mut OsRng));
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let amount_num = alloc(&mut cs, "amount", Fr::from(amount));
+        let blinding_num = alloc(&mut cs, "blinding", blinding);
+        let amount_bits = alloc_bits(cs.namespace(|| "amount bits"), &amount_num, AMOUNT_BITS).unwrap();
+        let blinding_bits =
+            alloc_bits(cs.namespace(|| "blinding bits"), &blinding_num, Fr::NUM_BITS as usize).unwrap();
+        let point = pedersen_commit_gadget(cs.namespace(|| "commit"), &amount_bits, &blinding_bits).unwrap();
+
+        assert!(cs.is_satisfied());
+        let native = pedersen_commit(amount, blinding);
+        assert_eq!(point.x.get_value(), Some(native.x));
+        assert_eq!(point.y.get_value(), Some(native.y));
+        assert_eq!(native.x, commitment_value(amount, blinding));
+    }
+
+    #[test]
+    fn test_alloc_bits_is_a_range_check() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let num = alloc(&mut cs, "in range", Fr::from(u64::MAX));
+        alloc_bits(cs.namespace(|| "bits"), &num, AMOUNT_BITS).unwrap();
+        assert!(cs.is_satisfied());
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let num = alloc(&mut cs, "out of range", Fr::from(u64::MAX) + Fr::ONE);
+        alloc_bits(cs.namespace(|| "bits"), &num, AMOUNT_BITS).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_commitments_bind_and_add() {
+        let (r1, r2) = (Fr::from(17u64), Fr::from(29u64));
+        assert_ne!(commitment_value(5, r1), commitment_value(6, r1));
+        assert_ne!(commitment_value(5, r1), commitment_value(5, r2));
+        assert_eq!(
+            pedersen_commit(5, r1).add(&pedersen_commit(7, r2)),
+            pedersen_commit(12, r1 + r2)
+        );
+        assert_eq!(pedersen_commit(0, Fr::ZERO), EdwardsPoint::identity());
+    }
+}
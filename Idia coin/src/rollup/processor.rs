@@ -1,106 +1,331 @@
-use bellman::{Circuit, ConstraintSystem, SynthesisError};
-use ff::PrimeField;
+use bellpepper_core::num::AllocatedNum;
+use bellpepper_core::{Circuit, ConstraintSystem, SynthesisError};
+use bellperson::groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof, Parameters,
+    PreparedVerifyingKey, Proof, VerifyingKey,
+};
+use blstrs::{Bls12, Scalar as Fr};
+use ff::{PrimeField, PrimeFieldBits};
+use rand::rngs::OsRng;
 
+use super::exit::note_nullifier;
+use super::hash::{hash_pair, mimc_hash_gadget};
+use super::pedersen::{alloc_bits, commitment_value, pedersen_commit_gadget, AMOUNT_BITS};
+use super::state::{MerkleWitness, SparseMerkleTree, StateTreeError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RollupError {
+    #[error("Circuit synthesis failed: {0}")]
+    Synthesis(#[from] SynthesisError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Batch of {len} transactions exceeds the batch size of {max}")]
+    BatchTooLarge { len: usize, max: usize },
+    #[error("Transaction is missing its public inputs")]
+    MissingPublicInputs,
+    #[error("Cannot aggregate {len} batches, between 1 and {max} allowed")]
+    AggregationSize { len: usize, max: usize },
+    #[error("Batch data unavailable: {0}")]
+    DataUnavailable(String),
+    #[error("State tree error: {0}")]
+    State(#[from] StateTreeError),
+    #[error("Unknown batch {0}")]
+    UnknownBatch(u64),
+    #[error("Challenge period of batch {0} has ended")]
+    ChallengePeriodOver(u64),
+    #[error("Invalid execution trace: {0}")]
+    InvalidTrace(String),
+    #[error("Fraud proof rejected: {0}")]
+    FraudProofRejected(String),
+    #[error("Validator {validator} has {available} unbonded, {required} required")]
+    InsufficientBond { validator: String, available: u64, required: u64 },
+    #[error("Invalid exit: {0}")]
+    InvalidExit(String),
+    #[error("Exit payout failed: {0}")]
+    Payout(String),
+    #[error("Unknown proof job {0}")]
+    UnknownProofJob(u64),
+    #[error("Proof job failed: {0}")]
+    ProofJobFailed(String),
+}
+
+// Spends the note `input_commitment` and creates a note for `amount`.
+// Only the nullifier and the new commitment are public.
+#[derive(Clone)]
 pub struct TransactionCircuit<F: PrimeField> {
     pub amount: Option<F>,
+    pub blinding: Option<F>,
+    pub spend_key: Option<F>,
+    pub input_commitment: Option<F>,
     pub input_nullifier: Option<F>,
     pub output_commitment: Option<F>,
 }
 
-impl<F: PrimeField> Circuit<F> for TransactionCircuit<F> {
+impl<F: PrimeFieldBits> Circuit<F> for TransactionCircuit<F> {
     fn synthesize<CS: ConstraintSystem<F>>(
         self,
         cs: &mut CS,
     ) -> Result<(), SynthesisError> {
-        // Amount range proof
-        let amount = cs.alloc(
-            || "transaction amount",
-            || self.amount.ok_or(SynthesisError::AssignmentMissing),
-        )?;
+        // Nullifiers and commitments are published with the batch, so they are
+        // public inputs the verifier supplies
+        let (nullifier, commitment) = self.allocate(cs)?;
+        nullifier.inputize(cs.namespace(|| "input nullifier"))?;
+        commitment.inputize(cs.namespace(|| "output commitment"))?;
+        Ok(())
+    }
+}
 
-        // Input nullifier
-        let nullifier = cs.alloc(
-            || "input nullifier",
-            || self.input_nullifier.ok_or(SynthesisError::AssignmentMissing),
-        )?;
+impl<F: PrimeFieldBits> TransactionCircuit<F> {
+    // Public values are derived from the witness, so they always match it
+    pub fn new(amount: u64, blinding: F, spend_key: F, input_commitment: F) -> Self {
+        Self {
+            amount: Some(F::from(amount)),
+            blinding: Some(blinding),
+            spend_key: Some(spend_key),
+            input_commitment: Some(input_commitment),
+            input_nullifier: Some(note_nullifier(spend_key, input_commitment)),
+            output_commitment: Some(commitment_value(amount, blinding)),
+        }
+    }
 
-        // Output commitment
-        let commitment = cs.alloc(
-            || "output commitment",
-            || self.output_commitment.ok_or(SynthesisError::AssignmentMissing),
-        )?;
+    // The transaction's constraints with nullifier and commitment left private,
+    // so folding backends can consume them without a public input per step
+    pub fn allocate<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+    ) -> Result<(AllocatedNum<F>, AllocatedNum<F>), SynthesisError> {
+        let amount = AllocatedNum::alloc(cs.namespace(|| "transaction amount"), || {
+            self.amount.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let blinding = AllocatedNum::alloc(cs.namespace(|| "blinding"), || {
+            self.blinding.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let spend_key = AllocatedNum::alloc(cs.namespace(|| "spend key"), || {
+            self.spend_key.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let input_commitment = AllocatedNum::alloc(cs.namespace(|| "input commitment"), || {
+            self.input_commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
 
-        // Enforce that amount is positive and within range
-        cs.enforce(
-            || "amount range check",
-            |lc| lc + amount,
-            |lc| lc + CS::one(),
-            |lc| lc + amount,
-        );
+        // Amount range proof: 64 bits that pack back to the amount, which
+        // cannot wrap since the field is far larger than 2^64
+        let amount_bits = alloc_bits(cs.namespace(|| "amount bits"), &amount, AMOUNT_BITS)?;
+        let blinding_bits = alloc_bits(cs.namespace(|| "blinding bits"), &blinding, F::NUM_BITS as usize)?;
 
-        // Enforce nullifier is unique
-        cs.enforce(
-            || "nullifier uniqueness",
-            |lc| lc + nullifier,
-            |lc| lc + CS::one(),
-            |lc| lc + nullifier,
-        );
+        // The output commitment is the Pedersen commitment to exactly this
+        // amount and blinding, as it appears on chain
+        let commitment = pedersen_commit_gadget(cs.namespace(|| "output commitment"), &amount_bits, &blinding_bits)?;
 
-        // Enforce commitment correctness
-        cs.enforce(
-            || "commitment correctness",
-            |lc| lc + commitment,
-            |lc| lc + CS::one(),
-            |lc| lc + commitment,
-        );
+        // Only the spend key's holder can derive the input note's nullifier
+        let nullifier = mimc_hash_gadget(cs.namespace(|| "input nullifier"), &[spend_key, input_commitment])?;
 
+        Ok((nullifier, commitment.x))
+    }
+
+    // Fills unused slots so every batch proof has the same circuit shape. It
+    // spends nothing real and commits to zero.
+    pub fn padding() -> Self {
+        Self::new(0, F::ZERO, F::ZERO, F::ZERO)
+    }
+
+    pub fn blank() -> Self {
+        Self {
+            amount: None,
+            blinding: None,
+            spend_key: None,
+            input_commitment: None,
+            input_nullifier: None,
+            output_commitment: None,
+        }
+    }
+}
+
+impl<F: PrimeField> TransactionCircuit<F> {
+    pub fn public_inputs(&self) -> Option<[F; 2]> {
+        Some([self.input_nullifier?, self.output_commitment?])
+    }
+}
+
+// A full batch of `batch_size` transactions proven at once
+pub struct BatchCircuit<F: PrimeField> {
+    pub transactions: Vec<TransactionCircuit<F>>,
+}
+
+impl<F: PrimeFieldBits> Circuit<F> for BatchCircuit<F> {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        for (i, tx) in self.transactions.into_iter().enumerate() {
+            tx.synthesize(&mut cs.namespace(|| format!("transaction {}", i)))?;
+        }
         Ok(())
     }
 }
 
+// Verifiers only ever see nullifiers and commitments; amounts stay with the prover
 pub struct RollupBatch {
     pub transactions: Vec<TransactionCircuit<Fr>>,
     pub merkle_root: Fr,
     pub batch_proof: Proof<Bls12>,
 }
 
-pub struct RollupProcessor {
+// Checks batch proofs with the verifying key alone, so nodes without the
+// proving key (or L1 consensus) can validate batches
+pub struct BatchVerifier {
     batch_size: usize,
-    proving_key: ProvingKey<Bls12>,
     verifying_key: VerifyingKey<Bls12>,
+    prepared: PreparedVerifyingKey<Bls12>,
+}
+
+impl BatchVerifier {
+    pub fn new(verifying_key: VerifyingKey<Bls12>, batch_size: usize) -> Self {
+        Self {
+            batch_size,
+            prepared: prepare_verifying_key(&verifying_key),
+            verifying_key,
+        }
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    // Batch size as little-endian u32, then the verifying key
+    pub fn to_bytes(&self) -> Result<Vec<u8>, RollupError> {
+        let mut bytes = (self.batch_size as u32).to_le_bytes().to_vec();
+        self.verifying_key.write(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RollupError> {
+        let (size, key) = bytes.split_at_checked(4).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "verifier bytes too short")
+        })?;
+        let batch_size = u32::from_le_bytes(size.try_into().expect("4 bytes")) as usize;
+        Ok(Self::new(VerifyingKey::read(key)?, batch_size))
+    }
+
+    pub fn prepared(&self) -> &PreparedVerifyingKey<Bls12> {
+        &self.prepared
+    }
+
+    // What the batch proof is checked against, padded like the circuit
+    pub fn public_inputs(&self, batch: &RollupBatch) -> Result<Vec<Fr>, RollupError> {
+        if batch.transactions.len() > self.batch_size {
+            return Err(RollupError::BatchTooLarge {
+                len: batch.transactions.len(),
+                max: self.batch_size,
+            });
+        }
+        let mut inputs = Vec::with_capacity(self.batch_size * 2);
+        for tx in &batch.transactions {
+            inputs.extend(tx.public_inputs().ok_or(RollupError::MissingPublicInputs)?);
+        }
+        let padding = TransactionCircuit::<Fr>::padding()
+            .public_inputs()
+            .expect("padding has public inputs");
+        while inputs.len() < self.batch_size * 2 {
+            inputs.extend(padding);
+        }
+        Ok(inputs)
+    }
+
+    pub fn verify(&self, batch: &RollupBatch) -> Result<bool, RollupError> {
+        let inputs = self.public_inputs(batch)?;
+        if compute_batch_root(&batch.transactions) != batch.merkle_root {
+            return Ok(false);
+        }
+        Ok(verify_proof(&self.prepared, &batch.batch_proof, &inputs)?)
+    }
+}
+
+pub fn encode_proof(proof: &Proof<Bls12>) -> Result<Vec<u8>, RollupError> {
+    let mut bytes = Vec::new();
+    proof.write(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn decode_proof(bytes: &[u8]) -> Result<Proof<Bls12>, RollupError> {
+    Ok(Proof::read(bytes)?)
+}
+
+pub struct RollupProcessor {
+    batch_size: usize,
+    proving_key: Parameters<Bls12>,
+    verifier: BatchVerifier,
+    // Account and note state the batches transition
+    state: SparseMerkleTree,
 }
 
 impl RollupProcessor {
     pub fn new(batch_size: usize) -> Self {
         // Generate circuit parameters
         let params = generate_random_parameters::<Bls12, _, _>(
-            TransactionCircuit { 
-                amount: None,
-                input_nullifier: None,
-                output_commitment: None,
+            BatchCircuit {
+                transactions: (0..batch_size).map(|_| TransactionCircuit::blank()).collect(),
             },
             &mut OsRng,
         ).unwrap();
 
         Self {
             batch_size,
-            proving_key: params.0,
-            verifying_key: params.1,
+            verifier: BatchVerifier::new(params.vk.clone(), batch_size),
+            proving_key: params,
+            state: SparseMerkleTree::default(),
         }
     }
 
-    pub async fn process_batch(&self, transactions: Vec<Transaction>) -> Result<RollupBatch, Error> {
-        let circuits: Vec<TransactionCircuit<Fr>> = transactions
-            .iter()
-            .map(|tx| self.create_circuit(tx))
-            .collect();
+    pub fn state_root(&self) -> Fr {
+        self.state.root()
+    }
+
+    pub fn state(&self) -> &SparseMerkleTree {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut SparseMerkleTree {
+        &mut self.state
+    }
+
+    // Membership witness for a set key, non-membership for an absent one
+    pub fn state_witness(&self, key: u64) -> Result<MerkleWitness, StateTreeError> {
+        self.state.witness(key)
+    }
+
+    pub fn verifier(&self) -> &BatchVerifier {
+        &self.verifier
+    }
+
+    pub fn verify_batch(&self, batch: &RollupBatch) -> Result<bool, RollupError> {
+        self.verifier.verify(batch)
+    }
+
+    // Proves inline on the caller's thread; `ProverPool` spreads larger
+    // workloads across cores
+    pub async fn process_batch(&self, transactions: Vec<Transaction>) -> Result<RollupBatch, RollupError> {
+        self.prove(self.circuits(&transactions))
+    }
+
+    pub fn circuits(&self, transactions: &[Transaction]) -> Vec<TransactionCircuit<Fr>> {
+        transactions.iter().map(|tx| self.create_circuit(tx)).collect()
+    }
+
+    pub fn prove(&self, circuits: Vec<TransactionCircuit<Fr>>) -> Result<RollupBatch, RollupError> {
+        if circuits.len() > self.batch_size {
+            return Err(RollupError::BatchTooLarge {
+                len: circuits.len(),
+                max: self.batch_size,
+            });
+        }
 
         // Create batch Merkle tree
-        let merkle_root = self.compute_batch_root(&circuits);
+        let merkle_root = compute_batch_root(&circuits);
 
-        // Generate ZK proof for the batch
+        // Generate ZK proof for the batch, padded to the fixed circuit shape
+        let mut padded = circuits.clone();
+        padded.resize_with(self.batch_size, TransactionCircuit::padding);
         let proof = create_random_proof(
-            circuits,
+            BatchCircuit { transactions: padded },
             &self.proving_key,
             &mut OsRng,
         )?;
@@ -114,20 +339,28 @@ impl RollupProcessor {
 
     fn create_circuit(&self, tx: &Transaction) -> TransactionCircuit<Fr> {
         // Convert transaction data to circuit inputs
-        TransactionCircuit {
-            amount: Some(Fr::from_str(&tx.amount.to_string()).unwrap()),
-            input_nullifier: Some(hash_to_field(tx.inputs)),
-            output_commitment: Some(hash_to_field(tx.outputs)),
-        }
+        TransactionCircuit::new(
+            tx.amount,
+            hash_to_field(tx.blinding),
+            hash_to_field(tx.spend_key),
+            hash_to_field(tx.inputs),
+        )
     }
+}
 
-    fn compute_batch_root(&self, circuits: &[TransactionCircuit<Fr>]) -> Fr {
-        // Implement Merkle tree computation for the batch
-        let leaves: Vec<Fr> = circuits
-            .iter()
-            .map(|circuit| hash_to_field(&circuit))
-            .collect();
-
-        compute_merkle_root(&leaves)
+// Transactions keyed by position, committing to their public values
+pub fn compute_batch_root(circuits: &[TransactionCircuit<Fr>]) -> Fr {
+    let depth = (usize::BITS - circuits.len().max(1).leading_zeros()) as usize;
+    let mut tree = SparseMerkleTree::new(depth.max(1));
+    for (index, circuit) in circuits.iter().enumerate() {
+        let leaf = hash_pair(
+            circuit.input_nullifier.unwrap_or_default(),
+            circuit.output_commitment.unwrap_or_default(),
+        );
+        // Zero marks an absent leaf; a real hash hitting it is negligible
+        if leaf != Fr::ZERO {
+            tree.insert(index as u64, leaf).expect("batch index fits the tree");
+        }
     }
-}
\ No newline at end of file
+    tree.root()
+}
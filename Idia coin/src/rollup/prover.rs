@@ -0,0 +1,190 @@
+use blstrs::Scalar as Fr;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{watch, RwLock, Semaphore};
+
+use super::processor::{RollupBatch, RollupError, RollupProcessor, TransactionCircuit};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProofJobProgress {
+    pub id: u64,
+    pub shards: usize,
+    pub proven: usize,
+    pub failed: usize,
+    pub started_at: DateTime<Utc>,
+    // Estimated seconds left, once a shard has finished to time against
+    pub eta_secs: Option<f64>,
+    pub finished: bool,
+}
+
+struct ProofJob {
+    shards: usize,
+    started_at: DateTime<Utc>,
+    shard_secs: Vec<f64>,
+    results: Vec<Option<RollupBatch>>,
+    errors: Vec<String>,
+    done: watch::Sender<bool>,
+}
+
+impl ProofJob {
+    fn finished(&self) -> bool {
+        self.shard_secs.len() + self.errors.len() == self.shards
+    }
+}
+
+// Batch proving is the rollup's throughput bottleneck. Jobs larger than the
+// circuit's batch size are sharded into full batches, and shards from every
+// job are proven concurrently on the blocking thread pool, at most `workers`
+// at a time.
+pub struct ProverPool {
+    processor: Arc<RollupProcessor>,
+    workers: usize,
+    permits: Arc<Semaphore>,
+    jobs: RwLock<HashMap<u64, ProofJob>>,
+    next_id: AtomicU64,
+}
+
+impl ProverPool {
+    // Zero workers means one per available core
+    pub fn new(processor: Arc<RollupProcessor>, workers: usize) -> Self {
+        let workers = match workers {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        Self {
+            processor,
+            workers,
+            permits: Arc::new(Semaphore::new(workers)),
+            jobs: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub async fn submit(self: &Arc<Self>, circuits: Vec<TransactionCircuit<Fr>>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let batch_size = self.processor.verifier().batch_size().max(1);
+        let shards: Vec<Vec<TransactionCircuit<Fr>>> = circuits.chunks(batch_size).map(<[_]>::to_vec).collect();
+
+        let (done, _) = watch::channel(shards.is_empty());
+        self.jobs.write().await.insert(
+            id,
+            ProofJob {
+                shards: shards.len(),
+                started_at: Utc::now(),
+                shard_secs: Vec::new(),
+                results: (0..shards.len()).map(|_| None).collect(),
+                errors: Vec::new(),
+                done,
+            },
+        );
+
+        for (index, shard) in shards.into_iter().enumerate() {
+            let pool = Arc::clone(self);
+            tokio::spawn(async move {
+                let _permit = pool.permits.clone().acquire_owned().await.expect("prover semaphore closed");
+                let processor = Arc::clone(&pool.processor);
+                let started = Instant::now();
+                let result = tokio::task::spawn_blocking(move || processor.prove(shard))
+                    .await
+                    .unwrap_or_else(|e| Err(RollupError::ProofJobFailed(e.to_string())));
+                pool.record(id, index, result, started.elapsed().as_secs_f64()).await;
+            });
+        }
+        tracing::info!("Proof job {} queued", id);
+        id
+    }
+
+    async fn record(&self, id: u64, index: usize, result: Result<RollupBatch, RollupError>, secs: f64) {
+        let mut jobs = self.jobs.write().await;
+        let Some(job) = jobs.get_mut(&id) else {
+            return;
+        };
+        match result {
+            Ok(batch) => {
+                job.results[index] = Some(batch);
+                job.shard_secs.push(secs);
+            }
+            Err(e) => {
+                tracing::error!("Proof job {} shard {} failed: {}", id, index, e);
+                job.errors.push(format!("shard {}: {}", index, e));
+            }
+        }
+        if job.finished() {
+            job.done.send_replace(true);
+        }
+    }
+
+    pub async fn progress(&self, id: u64) -> Option<ProofJobProgress> {
+        self.jobs.read().await.get(&id).map(|job| self.progress_of(id, job))
+    }
+
+    pub async fn jobs(&self) -> Vec<ProofJobProgress> {
+        let mut jobs: Vec<_> = self
+            .jobs
+            .read()
+            .await
+            .iter()
+            .map(|(id, job)| self.progress_of(*id, job))
+            .collect();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+
+    fn progress_of(&self, id: u64, job: &ProofJob) -> ProofJobProgress {
+        let proven = job.shard_secs.len();
+        let remaining = job.shards - proven - job.errors.len();
+        // Remaining shards run `workers` at a time at the observed mean pace
+        let eta_secs = (proven > 0).then(|| {
+            let mean = job.shard_secs.iter().sum::<f64>() / proven as f64;
+            remaining.div_ceil(self.workers) as f64 * mean
+        });
+        ProofJobProgress {
+            id,
+            shards: job.shards,
+            proven,
+            failed: job.errors.len(),
+            started_at: job.started_at,
+            eta_secs,
+            finished: job.finished(),
+        }
+    }
+
+    // Waits for a job and hands over its batches, in shard order. The job is
+    // forgotten afterwards.
+    pub async fn wait(&self, id: u64) -> Result<Vec<RollupBatch>, RollupError> {
+        let mut done = self
+            .jobs
+            .read()
+            .await
+            .get(&id)
+            .map(|job| job.done.subscribe())
+            .ok_or(RollupError::UnknownProofJob(id))?;
+        done.wait_for(|finished| *finished)
+            .await
+            .map_err(|e| RollupError::ProofJobFailed(e.to_string()))?;
+
+        let job = self
+            .jobs
+            .write()
+            .await
+            .remove(&id)
+            .ok_or(RollupError::UnknownProofJob(id))?;
+        if !job.errors.is_empty() {
+            return Err(RollupError::ProofJobFailed(job.errors.join("; ")));
+        }
+        Ok(job.results.into_iter().flatten().collect())
+    }
+
+    pub async fn prove(self: &Arc<Self>, circuits: Vec<TransactionCircuit<Fr>>) -> Result<Vec<RollupBatch>, RollupError> {
+        let id = self.submit(circuits).await;
+        self.wait(id).await
+    }
+}
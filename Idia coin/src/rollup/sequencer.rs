@@ -0,0 +1,256 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+use super::processor::{RollupBatch, RollupError, RollupProcessor};
+use super::prover::ProverPool;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SequencerError {
+    #[error("Transaction already pending")]
+    Duplicate,
+    #[error("Fee {fee} below the minimum of {min}")]
+    FeeTooLow { fee: u64, min: u64 },
+    #[error("Rollup mempool is full")]
+    MempoolFull,
+    #[error("Batch failed: {0}")]
+    BatchFailed(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequencerConfig {
+    pub batch_size: usize,
+    // A partial batch is sealed once its oldest transaction waited this long
+    pub batch_interval: Duration,
+    pub max_pending: usize,
+    pub min_fee: u64,
+}
+
+impl Default for SequencerConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 64,
+            batch_interval: Duration::from_secs(10),
+            max_pending: 10_000,
+            min_fee: 0,
+        }
+    }
+}
+
+// Handed back to the submitter once its transaction is in a proven batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchReceipt {
+    pub tx_id: [u8; 32],
+    pub batch_number: u64,
+    pub position: usize,
+    pub batch_root: [u8; 32],
+    pub sealed_at: DateTime<Utc>,
+}
+
+struct Pending {
+    id: [u8; 32],
+    fee: u64,
+    seq: u64,
+    received_at: DateTime<Utc>,
+    tx: Transaction,
+    receipt: oneshot::Sender<Result<BatchReceipt, SequencerError>>,
+}
+
+// Highest fee first; equal fees keep arrival order
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.fee, Reverse(self.seq)).cmp(&(other.fee, Reverse(other.seq)))
+    }
+}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.seq == other.seq
+    }
+}
+
+impl Eq for Pending {}
+
+#[derive(Default)]
+struct Mempool {
+    queue: BinaryHeap<Pending>,
+    ids: HashSet<[u8; 32]>,
+    next_seq: u64,
+    next_batch: u64,
+}
+
+// Orders rollup transactions into batches for the `RollupProcessor`. A batch
+// is sealed when `batch_size` transactions are waiting or the oldest has
+// waited `batch_interval`, whichever comes first.
+pub struct Sequencer {
+    config: SequencerConfig,
+    processor: Arc<RollupProcessor>,
+    mempool: Mutex<Mempool>,
+    output: Option<mpsc::Sender<RollupBatch>>,
+    pool: Option<Arc<ProverPool>>,
+    // Wakes the batching loop early when a full batch is waiting
+    full: tokio::sync::Notify,
+}
+
+impl Sequencer {
+    pub fn new(config: SequencerConfig, processor: Arc<RollupProcessor>) -> Self {
+        Self {
+            config,
+            processor,
+            mempool: Mutex::new(Mempool::default()),
+            output: None,
+            pool: None,
+            full: tokio::sync::Notify::new(),
+        }
+    }
+
+    // Where sealed batches go next, e.g. L1 submission or gossip
+    pub fn with_output(mut self, output: mpsc::Sender<RollupBatch>) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    // Proves on the pool instead of inline; a sequencer batch larger than the
+    // circuit is then sealed as several proven shards
+    pub fn with_pool(mut self, pool: Arc<ProverPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    pub async fn pending(&self) -> usize {
+        self.mempool.lock().await.queue.len()
+    }
+
+    // Queues a transaction; the receiver resolves once it is in a proven batch
+    pub async fn submit(
+        &self,
+        tx: Transaction,
+    ) -> Result<oneshot::Receiver<Result<BatchReceipt, SequencerError>>, SequencerError> {
+        let id = tx.hash();
+        let fee = tx.fee;
+        if fee < self.config.min_fee {
+            return Err(SequencerError::FeeTooLow {
+                fee,
+                min: self.config.min_fee,
+            });
+        }
+
+        let mut mempool = self.mempool.lock().await;
+        if mempool.ids.contains(&id) {
+            return Err(SequencerError::Duplicate);
+        }
+        if mempool.queue.len() >= self.config.max_pending {
+            return Err(SequencerError::MempoolFull);
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let seq = mempool.next_seq;
+        mempool.next_seq += 1;
+        mempool.ids.insert(id);
+        mempool.queue.push(Pending {
+            id,
+            fee,
+            seq,
+            received_at: Utc::now(),
+            tx,
+            receipt: sender,
+        });
+        if mempool.queue.len() >= self.config.batch_size {
+            self.full.notify_one();
+        }
+        Ok(receiver)
+    }
+
+    // Seals one batch if it is due; returns its number
+    pub async fn seal_due(&self, now: DateTime<Utc>) -> Option<u64> {
+        let (number, pending) = {
+            let mut mempool = self.mempool.lock().await;
+            let oldest = mempool.queue.iter().map(|p| p.received_at).min()?;
+            let waited = (now - oldest).to_std().unwrap_or_default();
+            if mempool.queue.len() < self.config.batch_size && waited < self.config.batch_interval {
+                return None;
+            }
+
+            let mut pending = Vec::with_capacity(self.config.batch_size);
+            while pending.len() < self.config.batch_size {
+                let Some(next) = mempool.queue.pop() else {
+                    break;
+                };
+                mempool.ids.remove(&next.id);
+                pending.push(next);
+            }
+            let number = mempool.next_batch;
+            mempool.next_batch += 1;
+            (number, pending)
+        };
+
+        let (txs, waiters): (Vec<Transaction>, Vec<_>) = pending
+            .into_iter()
+            .map(|p| (p.tx, (p.id, p.receipt)))
+            .unzip();
+
+        match self.prove(txs).await {
+            Ok(batches) => {
+                let sealed_at = Utc::now();
+                tracing::info!("Sealed rollup batch {} with {} transactions", number, waiters.len());
+                let mut waiters = waiters.into_iter().enumerate();
+                for batch in batches {
+                    let batch_root = batch.merkle_root.to_bytes_le();
+                    for (position, (tx_id, receipt)) in waiters.by_ref().take(batch.transactions.len()) {
+                        // A submitter that stopped listening does not hold up the batch
+                        let _ = receipt.send(Ok(BatchReceipt {
+                            tx_id,
+                            batch_number: number,
+                            position,
+                            batch_root,
+                            sealed_at,
+                        }));
+                    }
+                    if let Some(output) = &self.output {
+                        if output.send(batch).await.is_err() {
+                            tracing::error!("Rollup batch {} has nowhere to go; output channel closed", number);
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!("Rollup batch {} failed: {}", number, e);
+                for (_, receipt) in waiters {
+                    let _ = receipt.send(Err(SequencerError::BatchFailed(e.to_string())));
+                }
+            }
+        }
+        Some(number)
+    }
+
+    async fn prove(&self, txs: Vec<Transaction>) -> Result<Vec<RollupBatch>, RollupError> {
+        match &self.pool {
+            Some(pool) => pool.prove(self.processor.circuits(&txs)).await,
+            None => Ok(vec![self.processor.process_batch(txs).await?]),
+        }
+    }
+
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.config.batch_interval);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = self.full.notified() => {}
+                }
+                // Drain every batch that is due, not just one per wakeup
+                while self.seal_due(Utc::now()).await.is_some() {}
+            }
+        })
+    }
+}
@@ -0,0 +1,265 @@
+use blstrs::Scalar as Fr;
+use ff::Field;
+use std::collections::HashMap;
+
+use super::hash::hash_pair;
+
+pub const STATE_TREE_DEPTH: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StateTreeError {
+    #[error("Key {0} is already set")]
+    KeyExists(u64),
+    #[error("Key {0} is not set")]
+    KeyMissing(u64),
+    #[error("Key {key} does not fit a tree of depth {depth}")]
+    KeyOutOfRange { key: u64, depth: usize },
+    #[error("Empty leaf value is reserved for absent keys")]
+    EmptyValue,
+}
+
+// Path from a leaf to the root: one sibling per level, leaf level first.
+// `value` is None when the witness shows the key is absent.
+#[derive(Debug, Clone)]
+pub struct MerkleWitness {
+    pub key: u64,
+    pub value: Option<Fr>,
+    pub siblings: Vec<Fr>,
+}
+
+impl MerkleWitness {
+    pub fn leaf(&self) -> Fr {
+        self.value.map_or(Fr::ZERO, |value| leaf_hash(self.key, value))
+    }
+
+    pub fn root(&self) -> Fr {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(self.leaf(), |node, (level, sibling)| {
+                if (self.key >> level) & 1 == 0 {
+                    hash_pair(node, *sibling)
+                } else {
+                    hash_pair(*sibling, node)
+                }
+            })
+    }
+
+    pub fn verify(&self, root: Fr) -> bool {
+        self.root() == root
+    }
+
+    // Private inputs for a Merkle path gadget: direction bits (true when the
+    // node is the right child), siblings and the leaf, all leaf level first
+    pub fn circuit_inputs(&self) -> (Vec<bool>, Vec<Fr>, Fr) {
+        let bits = (0..self.siblings.len()).map(|level| (self.key >> level) & 1 == 1).collect();
+        (bits, self.siblings.clone(), self.leaf())
+    }
+}
+
+// Binding the key into the leaf stops a witness for one key passing for another
+pub fn leaf_hash(key: u64, value: Fr) -> Fr {
+    hash_pair(Fr::from(key), value)
+}
+
+// Sparse Merkle tree over account and note state. Absent leaves are zero, so
+// the same witness shape proves both membership and non-membership. Only
+// non-default nodes are stored.
+#[derive(Debug, Clone)]
+pub struct SparseMerkleTree {
+    depth: usize,
+    values: HashMap<u64, Fr>,
+    // (level, index) -> node, level 0 being the leaves
+    nodes: HashMap<(usize, u64), Fr>,
+    defaults: Vec<Fr>,
+}
+
+impl SparseMerkleTree {
+    pub fn new(depth: usize) -> Self {
+        assert!(depth > 0 && depth <= 64, "state tree depth must be 1..=64");
+        let mut defaults = vec![Fr::ZERO];
+        for level in 0..depth {
+            defaults.push(hash_pair(defaults[level], defaults[level]));
+        }
+        Self {
+            depth,
+            values: HashMap::new(),
+            nodes: HashMap::new(),
+            defaults,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn root(&self) -> Fr {
+        self.node(self.depth, 0)
+    }
+
+    pub fn get(&self, key: u64) -> Option<Fr> {
+        self.values.get(&key).copied()
+    }
+
+    pub fn insert(&mut self, key: u64, value: Fr) -> Result<Fr, StateTreeError> {
+        if self.values.contains_key(&key) {
+            return Err(StateTreeError::KeyExists(key));
+        }
+        self.set(key, Some(value))
+    }
+
+    pub fn update(&mut self, key: u64, value: Fr) -> Result<Fr, StateTreeError> {
+        if !self.values.contains_key(&key) {
+            return Err(StateTreeError::KeyMissing(key));
+        }
+        self.set(key, Some(value))
+    }
+
+    pub fn remove(&mut self, key: u64) -> Result<Fr, StateTreeError> {
+        if !self.values.contains_key(&key) {
+            return Err(StateTreeError::KeyMissing(key));
+        }
+        self.set(key, None)
+    }
+
+    pub fn witness(&self, key: u64) -> Result<MerkleWitness, StateTreeError> {
+        self.check_key(key)?;
+        let siblings = (0..self.depth)
+            .map(|level| self.node(level, (key >> level) ^ 1))
+            .collect();
+        Ok(MerkleWitness {
+            key,
+            value: self.get(key),
+            siblings,
+        })
+    }
+
+    // Writes the leaf and rehashes its path; returns the new root
+    fn set(&mut self, key: u64, value: Option<Fr>) -> Result<Fr, StateTreeError> {
+        self.check_key(key)?;
+        if value == Some(Fr::ZERO) {
+            return Err(StateTreeError::EmptyValue);
+        }
+        match value {
+            Some(value) => self.values.insert(key, value),
+            None => self.values.remove(&key),
+        };
+
+        let mut node = value.map_or(Fr::ZERO, |value| leaf_hash(key, value));
+        for level in 0..=self.depth {
+            let index = key.checked_shr(level as u32).unwrap_or(0);
+            if node == self.defaults[level] {
+                self.nodes.remove(&(level, index));
+            } else {
+                self.nodes.insert((level, index), node);
+            }
+            if level == self.depth {
+                break;
+            }
+            let sibling = self.node(level, index ^ 1);
+            node = if index & 1 == 0 {
+                hash_pair(node, sibling)
+            } else {
+                hash_pair(sibling, node)
+            };
+        }
+        Ok(self.root())
+    }
+
+    fn node(&self, level: usize, index: u64) -> Fr {
+        self.nodes.get(&(level, index)).copied().unwrap_or(self.defaults[level])
+    }
+
+    fn check_key(&self, key: u64) -> Result<(), StateTreeError> {
+        if self.depth < 64 && key >> self.depth != 0 {
+            return Err(StateTreeError::KeyOutOfRange { key, depth: self.depth });
+        }
+        Ok(())
+    }
+}
+
+impl Default for SparseMerkleTree {
+    fn default() -> Self {
+        Self::new(STATE_TREE_DEPTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_membership_round_trip() {
+        let mut tree = SparseMerkleTree::new(16);
+        let empty_root = tree.root();
+        let value = Fr::random(&mut OsRng);
+        tree.insert(42, value).unwrap();
+        tree.insert(7, Fr::random(&mut OsRng)).unwrap();
+        let root = tree.update(7, Fr::random(&mut OsRng)).unwrap();
+        assert_eq!(root, tree.root());
+
+        let witness = tree.witness(42).unwrap();
+        assert_eq!(witness.value, Some(value));
+        assert!(witness.verify(root));
+
+        // Absent keys get a witness of the same shape
+        let absent = tree.witness(43).unwrap();
+        assert_eq!(absent.value, None);
+        assert!(absent.verify(root));
+
+        tree.remove(7).unwrap();
+        tree.remove(42).unwrap();
+        assert_eq!(tree.root(), empty_root);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_witness_rejects_tampering() {
+        let mut tree = SparseMerkleTree::new(16);
+        tree.insert(42, Fr::random(&mut OsRng)).unwrap();
+        let root = tree.insert(9, Fr::random(&mut OsRng)).unwrap();
+        let witness = tree.witness(42).unwrap();
+
+        let mut tampered = witness.clone();
+        tampered.value = Some(Fr::random(&mut OsRng));
+        assert!(!tampered.verify(root));
+
+        // Claiming the key is absent
+        let mut tampered = witness.clone();
+        tampered.value = None;
+        assert!(!tampered.verify(root));
+
+        // Same path, different key
+        let mut tampered = witness.clone();
+        tampered.key = 42 + (1 << 16);
+        assert!(!tampered.verify(root));
+
+        let mut tampered = witness;
+        tampered.siblings[3] += Fr::ONE;
+        assert!(!tampered.verify(root));
+    }
+
+    #[test]
+    fn test_rejects_invalid_writes() {
+        let mut tree = SparseMerkleTree::new(8);
+        tree.insert(1, Fr::ONE).unwrap();
+        assert!(matches!(tree.insert(1, Fr::ONE), Err(StateTreeError::KeyExists(1))));
+        assert!(matches!(tree.update(2, Fr::ONE), Err(StateTreeError::KeyMissing(2))));
+        assert!(matches!(tree.remove(2), Err(StateTreeError::KeyMissing(2))));
+        assert!(matches!(tree.insert(3, Fr::ZERO), Err(StateTreeError::EmptyValue)));
+        assert!(matches!(
+            tree.insert(256, Fr::ONE),
+            Err(StateTreeError::KeyOutOfRange { key: 256, depth: 8 })
+        ));
+        assert_eq!(tree.len(), 1);
+    }
+}
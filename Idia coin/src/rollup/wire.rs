@@ -0,0 +1,181 @@
+use blstrs::Scalar as Fr;
+use ff::PrimeField;
+use idia_core::{GossipAcceptance, GossipValidator, NetworkCommand};
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+use super::processor::{decode_proof, encode_proof, BatchVerifier, RollupBatch, RollupError, TransactionCircuit};
+
+pub const WIRE_VERSION: u8 = 1;
+// Compressed Groth16 proof over BLS12-381: G1, G2, G1
+const PROOF_LEN: usize = 192;
+const FIELD_LEN: usize = 32;
+// Gossip payloads already validated, for dropping re-broadcasts cheaply
+const SEEN_CAPACITY: usize = 4_096;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WireError {
+    #[error("Unsupported batch wire version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Batch encoding truncated")]
+    Truncated,
+    #[error("{0} trailing bytes after batch")]
+    TrailingBytes(usize),
+    #[error("Non-canonical field element in {0}")]
+    NonCanonical(&'static str),
+    #[error("Batch of {len} transactions exceeds the limit of {max}")]
+    TooManyTransactions { len: usize, max: usize },
+    #[error("Batch proof does not verify")]
+    InvalidProof,
+    #[error("Batch proof: {0}")]
+    Proof(#[from] RollupError),
+}
+
+// Canonical encoding, all integers little-endian:
+//   version u8 | transaction count u32 | batch root 32 | proof 192 |
+//   per transaction: nullifier 32 | commitment 32
+// Only public values travel; amounts and keys stay with the prover, so a
+// decoded batch has none.
+pub fn encode_batch(batch: &RollupBatch) -> Result<Vec<u8>, WireError> {
+    let mut bytes = Vec::with_capacity(1 + 4 + FIELD_LEN + PROOF_LEN + batch.transactions.len() * 2 * FIELD_LEN);
+    bytes.push(WIRE_VERSION);
+    bytes.extend((batch.transactions.len() as u32).to_le_bytes());
+    bytes.extend(batch.merkle_root.to_repr().as_ref());
+    bytes.extend(encode_proof(&batch.batch_proof)?);
+    for tx in &batch.transactions {
+        let [nullifier, commitment] = tx.public_inputs().ok_or(RollupError::MissingPublicInputs)?;
+        bytes.extend(nullifier.to_repr().as_ref());
+        bytes.extend(commitment.to_repr().as_ref());
+    }
+    Ok(bytes)
+}
+
+// Rejects anything `encode_batch` would not have produced, so each batch has
+// exactly one encoding and gossip deduplication by payload hash is sound
+pub fn decode_batch(bytes: &[u8], max_transactions: usize) -> Result<RollupBatch, WireError> {
+    let mut reader = Reader { bytes };
+    let version = reader.take(1)?[0];
+    if version != WIRE_VERSION {
+        return Err(WireError::UnsupportedVersion(version));
+    }
+    let count = u32::from_le_bytes(reader.take(4)?.try_into().expect("4 bytes")) as usize;
+    if count > max_transactions {
+        return Err(WireError::TooManyTransactions {
+            len: count,
+            max: max_transactions,
+        });
+    }
+    let merkle_root = reader.field("batch root")?;
+    let batch_proof = decode_proof(reader.take(PROOF_LEN)?)?;
+
+    let mut transactions = Vec::with_capacity(count);
+    for _ in 0..count {
+        transactions.push(TransactionCircuit {
+            input_nullifier: Some(reader.field("nullifier")?),
+            output_commitment: Some(reader.field("commitment")?),
+            ..TransactionCircuit::blank()
+        });
+    }
+    if !reader.bytes.is_empty() {
+        return Err(WireError::TrailingBytes(reader.bytes.len()));
+    }
+
+    Ok(RollupBatch {
+        transactions,
+        merkle_root,
+        batch_proof,
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], WireError> {
+        let (head, rest) = self.bytes.split_at_checked(len).ok_or(WireError::Truncated)?;
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn field(&mut self, what: &'static str) -> Result<Fr, WireError> {
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.as_mut().copy_from_slice(self.take(FIELD_LEN)?);
+        Option::from(Fr::from_repr(repr)).ok_or(WireError::NonCanonical(what))
+    }
+}
+
+// Gossip validation for the rollup batch topic: a batch is relayed only if it
+// decodes canonically and its proof verifies, so invalid batches die at the
+// first honest hop
+pub struct RollupBatchValidator {
+    verifier: BatchVerifier,
+    seen: Mutex<(HashSet<[u8; 32]>, VecDeque<[u8; 32]>)>,
+}
+
+impl RollupBatchValidator {
+    pub fn new(verifier: BatchVerifier) -> Self {
+        Self {
+            verifier,
+            seen: Mutex::new((HashSet::new(), VecDeque::new())),
+        }
+    }
+
+    pub fn check(&self, payload: &[u8]) -> Result<RollupBatch, WireError> {
+        let batch = decode_batch(payload, self.verifier.batch_size())?;
+        if !self.verifier.verify(&batch)? {
+            return Err(WireError::InvalidProof);
+        }
+        Ok(batch)
+    }
+
+    // Records the payload; false when it was already seen
+    fn first_sighting(&self, payload: &[u8]) -> bool {
+        let id: [u8; 32] = Sha256::digest(payload).into();
+        let mut guard = self.seen.lock().expect("seen set poisoned");
+        let (set, order) = &mut *guard;
+        if !set.insert(id) {
+            return false;
+        }
+        order.push_back(id);
+        if order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = order.pop_front() {
+                set.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+impl GossipValidator for RollupBatchValidator {
+    fn validate(&self, payload: &[u8]) -> GossipAcceptance {
+        if !self.first_sighting(payload) {
+            return GossipAcceptance::Ignore;
+        }
+        match self.check(payload) {
+            Ok(_) => GossipAcceptance::Accept,
+            Err(e) => {
+                tracing::debug!("Rollup batch failed gossip validation: {}", e);
+                GossipAcceptance::Reject
+            }
+        }
+    }
+}
+
+// Gossips each batch the sequencer seals; give it the receiving end of
+// `Sequencer::with_output` and `NodeContext::network`
+pub async fn gossip_batches(mut batches: mpsc::Receiver<RollupBatch>, network: mpsc::Sender<NetworkCommand>) {
+    while let Some(batch) = batches.recv().await {
+        match encode_batch(&batch) {
+            Ok(payload) => {
+                if network.send(NetworkCommand::BroadcastRollupBatch(payload)).await.is_err() {
+                    tracing::warn!("Network is down, no longer gossiping rollup batches");
+                    return;
+                }
+            }
+            Err(e) => tracing::error!("Failed to encode rollup batch for gossip: {}", e),
+        }
+    }
+}
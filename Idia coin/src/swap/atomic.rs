@@ -1,7 +1,63 @@
+use bitcoin::{absolute, PublicKey};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use idia_core::{
+    cross_group_secret, secp_nonce, to_ristretto_scalar, to_secp_scalar, AdaptorSignature, Bip340AdaptorSignature,
+    Bip340JointKey, Bip340Signature, CrossGroupDleq, JointKey, SchnorrSignature, SecpPoint, SecpScalar, WalletError,
+};
 use sha2::{Sha256, Digest};
+use rand::rngs::OsRng;
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use super::btc::{BitcoinHtlc, BitcoinHtlcError};
+
+// The external leg expires this long before the Idia leg, leaving whoever
+// learns the preimage from an Idia claim time to claim the external side
+const EXTERNAL_TIMEOUT_HOURS: u64 = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SwapError {
+    #[error("Secret does not match the swap lock")]
+    InvalidSecret,
+    #[error("Swap has expired")]
+    SwapExpired,
+    #[error("Swap has not expired yet")]
+    SwapNotExpired,
+    #[error("Counterparty signature does not verify")]
+    InvalidSignature,
+    #[error("Counterparty adaptor signature does not verify")]
+    InvalidAdaptorSignature,
+    #[error("Adaptor points do not share a secret")]
+    InvalidAdaptorProof,
+    #[error("Counterparty has not sent their nonces yet")]
+    MissingNonces,
+    #[error("Signing nonce already used")]
+    NonceUsed,
+    #[error("Counterparty has not supplied the {0} signature yet")]
+    MissingSignature(&'static str),
+    #[error("Adaptor secret is not known yet")]
+    SecretUnknown,
+    #[error("Not available to the {0:?} of a swap")]
+    WrongRole(SwapRole),
+    #[error("Unsupported external chain {0}")]
+    UnsupportedChain(String),
+    #[error("Bitcoin HTLC error: {0}")]
+    Bitcoin(#[from] BitcoinHtlcError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Keystore error: {0}")]
+    Keystore(#[from] WalletError),
+    #[error("No ledger configured for chain {0}")]
+    NoLedger(String),
+    #[error("Chain error: {0}")]
+    Chain(String),
+}
+
 pub struct AtomicSwap {
     pub hash_lock: [u8; 32],
     pub time_lock: u64,
@@ -79,13 +135,76 @@ impl AtomicSwap {
     }
 }
 
+// Counterparty chain's half of a hashlock swap, locked to the same hash as
+// the Idia leg
+pub struct ExternalChainSwap {
+    pub chain: String,
+    pub counterparty_address: String,
+    // Locked on the external chain, including the claim fee
+    pub amount: u64,
+    pub hash_lock: [u8; 32],
+    pub time_lock: u64,
+    pub bitcoin: Option<BitcoinHtlc>,
+}
+
+impl ExternalChainSwap {
+    pub fn new(chain: &str, counterparty_address: &str, preimage: &[u8; 32]) -> Result<Self, SwapError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Ok(Self {
+            chain: chain.to_string(),
+            counterparty_address: counterparty_address.to_string(),
+            amount: 0,
+            hash_lock: Sha256::digest(preimage).into(),
+            time_lock: now + (EXTERNAL_TIMEOUT_HOURS * 3600),
+            bitcoin: None,
+        })
+    }
+
+    // Builds the Bitcoin HTLC for this leg, refundable at `time_lock`
+    pub fn with_bitcoin(mut self, recipient: PublicKey, refund: PublicKey) -> Result<Self, SwapError> {
+        if !matches!(self.chain.as_str(), "btc" | "bitcoin") {
+            return Err(SwapError::UnsupportedChain(self.chain));
+        }
+        let lock_time = u32::try_from(self.time_lock)
+            .ok()
+            .and_then(|t| absolute::LockTime::from_time(t).ok())
+            .ok_or_else(|| BitcoinHtlcError::InvalidScript(format!("lock time {} out of range", self.time_lock)))?;
+        self.bitcoin = Some(BitcoinHtlc {
+            payment_hash: self.hash_lock,
+            recipient,
+            refund,
+            lock_time,
+        });
+        Ok(self)
+    }
+
+    pub async fn is_expired(&self) -> Result<bool, SwapError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Ok(now > self.time_lock)
+    }
+}
+
 pub struct CrossChainSwap {
     pub idia_swap: AtomicSwap,
     pub external_swap: ExternalChainSwap,
     pub state: SwapState,
+    pub role: SwapRole,
+    // Known to the initiator from the start, to the other side once revealed
+    pub preimage: Option<[u8; 32]>,
+    pub idia_lock_tx: Option<String>,
+    pub external_lock_tx: Option<String>,
+    // Our claim or refund, whichever settled the swap
+    pub settle_tx: Option<String>,
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SwapState {
     Initialized,
     IdiaLocked,
@@ -119,9 +238,48 @@ impl CrossChainSwap {
             idia_swap,
             external_swap,
             state: SwapState::Initialized,
+            role: SwapRole::Initiator,
+            preimage: Some(preimage),
+            idia_lock_tx: None,
+            external_lock_tx: None,
+            settle_tx: None,
         })
     }
 
+    // The counterparty's side of a swap the other party initiated: their Idia
+    // lock pays `recipient`, ours on the external chain expires first, and the
+    // preimage is learned from their claim of it
+    pub fn join(
+        idia_swap: AtomicSwap,
+        external_chain: &str,
+        external_address: &str,
+        external_time_lock: u64,
+    ) -> Self {
+        let external_swap = ExternalChainSwap {
+            chain: external_chain.to_string(),
+            counterparty_address: external_address.to_string(),
+            amount: 0,
+            hash_lock: idia_swap.hash_lock,
+            time_lock: external_time_lock,
+            bitcoin: None,
+        };
+        Self {
+            idia_swap,
+            external_swap,
+            state: SwapState::Initialized,
+            role: SwapRole::Participant,
+            preimage: None,
+            idia_lock_tx: None,
+            external_lock_tx: None,
+            settle_tx: None,
+        }
+    }
+
+    // Both legs share the hash lock, so it names the swap on either chain
+    pub fn id(&self) -> String {
+        hex::encode(self.idia_swap.hash_lock)
+    }
+
     pub async fn lock_idia(&mut self) -> Result<(), SwapError> {
         // Lock IDIA tokens in the swap contract
         self.state = SwapState::IdiaLocked;
@@ -157,4 +315,562 @@ impl CrossChainSwap {
         self.state = SwapState::Refunded;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapRole {
+    // Picks the adaptor secret, locks first with the longer time lock and
+    // claims first
+    #[default]
+    Initiator,
+    // Locks second with a shorter time lock and learns the secret from the
+    // initiator's published claim
+    Participant,
+}
+
+// Scriptless mode, between Idia and Bitcoin. Each leg is locked to the
+// parties' joint key on that chain's curve: a MuSig key on Idia, a taproot
+// key-path output on Bitcoin. Neither lock looks like a swap or a multisig,
+// and every spend carries one signature assembled from both parties'
+// partial signatures:
+//
+//   Idia leg, locked by the initiator: the participant's claim, pre-signed
+//   under T = t·G, and the initiator's time-locked refund.
+//   Bitcoin leg, locked by the participant: the initiator's claim, pre-signed
+//   under T' = t·G', and the participant's earlier time-locked refund.
+//
+// A cross-group DLEQ proof shows T and T' hide the same t. The initiator's
+// Bitcoin claim reveals t to the participant, which completes the Idia
+// claim. Ethereum cannot verify either signature, so it keeps the HTLC mode.
+pub struct ScriptlessSwap {
+    pub role: SwapRole,
+    pub amount: u64,
+    // When our own lock can be refunded
+    pub time_lock: u64,
+    pub adaptor_point: RistrettoPoint,
+    pub external_adaptor_point: SecpPoint,
+    pub idia_joint_key: JointKey,
+    pub external_joint_key: Bip340JointKey,
+    pub messages: ScriptlessMessages,
+    idia_key: Scalar,
+    external_key: SecpScalar,
+    their_idia_key: RistrettoPoint,
+    their_external_key: SecpPoint,
+    secret: Option<Scalar>,
+    adaptor_proof: Option<CrossGroupDleq>,
+    // Each secret nonce signs once, so they are taken when used
+    secret_nonces: SecretNonces,
+    our_nonces: ScriptlessNonces,
+    their_nonces: Option<ScriptlessNonces>,
+    our_partials: Option<ScriptlessPartials>,
+    idia_claim_presignature: Option<AdaptorSignature>,
+    external_claim_presignature: Option<Bip340AdaptorSignature>,
+    idia_refund_signature: Option<SchnorrSignature>,
+    external_refund_signature: Option<Bip340Signature>,
+}
+
+// Sighashes of the four spends, agreed on by both parties
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptlessMessages {
+    pub idia_claim: Vec<u8>,
+    pub idia_refund: Vec<u8>,
+    pub external_claim: Vec<u8>,
+    pub external_refund: Vec<u8>,
+}
+
+// Public nonces for the four spends, exchanged before any partial signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptlessNonces {
+    pub idia_claim: RistrettoPoint,
+    pub idia_refund: RistrettoPoint,
+    pub external_claim: SecpPoint,
+    pub external_refund: SecpPoint,
+}
+
+struct SecretNonces {
+    idia_claim: Option<Scalar>,
+    idia_refund: Option<Scalar>,
+    external_claim: Option<SecpScalar>,
+    external_refund: Option<SecpScalar>,
+}
+
+// What each party hands the other: shares of both claims, which only
+// pre-sign, and of the other party's refund
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptlessPartials {
+    pub idia_claim: Scalar,
+    pub external_claim: SecpScalar,
+    pub refund: ScriptlessRefundPartial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptlessRefundPartial {
+    // From the participant, for the initiator's Idia refund
+    Idia(Scalar),
+    // From the initiator, for the participant's Bitcoin refund
+    External(SecpScalar),
+}
+
+impl ScriptlessSwap {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        role: SwapRole,
+        amount: u64,
+        time_lock: u64,
+        adaptor_point: RistrettoPoint,
+        external_adaptor_point: SecpPoint,
+        idia_key: Scalar,
+        external_key: SecpScalar,
+        their_idia_key: RistrettoPoint,
+        their_external_key: SecpPoint,
+        messages: ScriptlessMessages,
+    ) -> Self {
+        let idia_nonces = [Scalar::random(&mut OsRng), Scalar::random(&mut OsRng)];
+        let (external_claim, external_claim_point) = secp_nonce();
+        let (external_refund, external_refund_point) = secp_nonce();
+        Self {
+            role,
+            amount,
+            time_lock,
+            adaptor_point,
+            external_adaptor_point,
+            idia_joint_key: JointKey::new(&(RISTRETTO_BASEPOINT_POINT * idia_key), &their_idia_key),
+            external_joint_key: Bip340JointKey::new(&(SecpPoint::GENERATOR * external_key), &their_external_key),
+            messages,
+            idia_key,
+            external_key,
+            their_idia_key,
+            their_external_key,
+            secret: None,
+            adaptor_proof: None,
+            secret_nonces: SecretNonces {
+                idia_claim: Some(idia_nonces[0]),
+                idia_refund: Some(idia_nonces[1]),
+                external_claim: Some(external_claim),
+                external_refund: Some(external_refund),
+            },
+            our_nonces: ScriptlessNonces {
+                idia_claim: RISTRETTO_BASEPOINT_POINT * idia_nonces[0],
+                idia_refund: RISTRETTO_BASEPOINT_POINT * idia_nonces[1],
+                external_claim: external_claim_point,
+                external_refund: external_refund_point,
+            },
+            their_nonces: None,
+            our_partials: None,
+            idia_claim_presignature: None,
+            external_claim_presignature: None,
+            idia_refund_signature: None,
+            external_refund_signature: None,
+        }
+    }
+
+    pub fn initiate(
+        amount: u64,
+        timeout_hours: u64,
+        idia_key: Scalar,
+        external_key: SecpScalar,
+        their_idia_key: RistrettoPoint,
+        their_external_key: SecpPoint,
+        messages: ScriptlessMessages,
+    ) -> Self {
+        let secret = cross_group_secret();
+        let (adaptor_point, external_adaptor_point, proof) =
+            CrossGroupDleq::prove(&secret).expect("cross-group secrets are below 2^252");
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut swap = Self::new(
+            SwapRole::Initiator,
+            amount,
+            now + (timeout_hours * 3600),
+            adaptor_point,
+            external_adaptor_point,
+            idia_key,
+            external_key,
+            their_idia_key,
+            their_external_key,
+            messages,
+        );
+        swap.secret = Some(secret);
+        swap.adaptor_proof = Some(proof);
+        swap
+    }
+
+    // `time_lock` must fall well before the initiator's, so the participant
+    // can still claim after learning the secret
+    #[allow(clippy::too_many_arguments)]
+    pub fn join(
+        amount: u64,
+        time_lock: u64,
+        adaptor_point: RistrettoPoint,
+        external_adaptor_point: SecpPoint,
+        adaptor_proof: &CrossGroupDleq,
+        idia_key: Scalar,
+        external_key: SecpScalar,
+        their_idia_key: RistrettoPoint,
+        their_external_key: SecpPoint,
+        messages: ScriptlessMessages,
+    ) -> Result<Self, SwapError> {
+        if !adaptor_proof.verify(&adaptor_point, &external_adaptor_point) {
+            return Err(SwapError::InvalidAdaptorProof);
+        }
+        Ok(Self::new(
+            SwapRole::Participant,
+            amount,
+            time_lock,
+            adaptor_point,
+            external_adaptor_point,
+            idia_key,
+            external_key,
+            their_idia_key,
+            their_external_key,
+            messages,
+        ))
+    }
+
+    // Sent to the participant with both adaptor points
+    pub fn adaptor_proof(&self) -> Option<&CrossGroupDleq> {
+        self.adaptor_proof.as_ref()
+    }
+
+    pub fn our_idia_public_key(&self) -> RistrettoPoint {
+        RISTRETTO_BASEPOINT_POINT * self.idia_key
+    }
+
+    pub fn our_external_public_key(&self) -> SecpPoint {
+        SecpPoint::GENERATOR * self.external_key
+    }
+
+    pub fn secret(&self) -> Option<Scalar> {
+        self.secret
+    }
+
+    pub fn nonces(&self) -> ScriptlessNonces {
+        self.our_nonces
+    }
+
+    pub fn accept_nonces(&mut self, nonces: ScriptlessNonces) {
+        self.their_nonces = Some(nonces);
+    }
+
+    fn nonce_points(&self) -> Result<ScriptlessNonces, SwapError> {
+        let theirs = self.their_nonces.ok_or(SwapError::MissingNonces)?;
+        let ours = self.our_nonces;
+        Ok(ScriptlessNonces {
+            idia_claim: ours.idia_claim + theirs.idia_claim + self.adaptor_point,
+            idia_refund: ours.idia_refund + theirs.idia_refund,
+            external_claim: ours.external_claim + theirs.external_claim + self.external_adaptor_point,
+            external_refund: ours.external_refund + theirs.external_refund,
+        })
+    }
+
+    // Our shares for the counterparty, computed once since each consumes a
+    // nonce; handing them over is what lets the counterparty lock safely
+    pub fn partial_signatures(&mut self) -> Result<ScriptlessPartials, SwapError> {
+        if let Some(partials) = self.our_partials {
+            return Ok(partials);
+        }
+        let nonces = self.nonce_points()?;
+        let idia_claim = self.secret_nonces.idia_claim.take().ok_or(SwapError::NonceUsed)?;
+        let external_claim = self.secret_nonces.external_claim.take().ok_or(SwapError::NonceUsed)?;
+        let refund = match self.role {
+            SwapRole::Initiator => {
+                let k = self.secret_nonces.external_refund.take().ok_or(SwapError::NonceUsed)?;
+                ScriptlessRefundPartial::External(self.external_partial(&k, &nonces.external_refund, &self.messages.external_refund))
+            }
+            SwapRole::Participant => {
+                let k = self.secret_nonces.idia_refund.take().ok_or(SwapError::NonceUsed)?;
+                ScriptlessRefundPartial::Idia(self.idia_partial(&k, &nonces.idia_refund, &self.messages.idia_refund))
+            }
+        };
+        let partials = ScriptlessPartials {
+            idia_claim: self.idia_partial(&idia_claim, &nonces.idia_claim, &self.messages.idia_claim),
+            external_claim: self.external_partial(&external_claim, &nonces.external_claim, &self.messages.external_claim),
+            refund,
+        };
+        self.our_partials = Some(partials);
+        Ok(partials)
+    }
+
+    fn idia_partial(&self, secret_nonce: &Scalar, nonce: &RistrettoPoint, message: &[u8]) -> Scalar {
+        self.idia_joint_key
+            .partial_sign(&self.idia_key, secret_nonce, nonce, message)
+            .expect("our key is part of the joint key")
+    }
+
+    fn external_partial(&self, secret_nonce: &SecpScalar, nonce: &SecpPoint, message: &[u8]) -> SecpScalar {
+        self.external_joint_key
+            .partial_sign(&self.external_key, secret_nonce, nonce, message)
+            .expect("our key is part of the joint key")
+    }
+
+    // Checks the counterparty's shares and assembles both claim
+    // pre-signatures and our own refund
+    pub fn accept_partials(&mut self, theirs: ScriptlessPartials) -> Result<(), SwapError> {
+        let ours = self.partial_signatures()?;
+        let nonces = self.nonce_points()?;
+        let their_nonces = self.their_nonces.ok_or(SwapError::MissingNonces)?;
+        let messages = &self.messages;
+
+        if !self.idia_joint_key.verify_partial(
+            &self.their_idia_key,
+            &their_nonces.idia_claim,
+            &nonces.idia_claim,
+            &messages.idia_claim,
+            &theirs.idia_claim,
+        ) || !self.external_joint_key.verify_partial(
+            &self.their_external_key,
+            &their_nonces.external_claim,
+            &nonces.external_claim,
+            &messages.external_claim,
+            &theirs.external_claim,
+        ) {
+            return Err(SwapError::InvalidSignature);
+        }
+        let idia_claim = AdaptorSignature {
+            nonce: nonces.idia_claim.compress(),
+            s_hat: ours.idia_claim + theirs.idia_claim,
+        };
+        let external_claim = self.external_joint_key.combine(
+            &nonces.external_claim,
+            &messages.external_claim,
+            [ours.external_claim, theirs.external_claim],
+        );
+        if !idia_claim.verify(&self.idia_joint_key.public_key, &messages.idia_claim, &self.adaptor_point)
+            || !external_claim.verify(
+                &self.external_joint_key.output_key_x(),
+                &messages.external_claim,
+                &self.external_adaptor_point,
+            )
+        {
+            return Err(SwapError::InvalidAdaptorSignature);
+        }
+
+        match (self.role, theirs.refund) {
+            (SwapRole::Initiator, ScriptlessRefundPartial::Idia(partial)) => {
+                if !self.idia_joint_key.verify_partial(
+                    &self.their_idia_key,
+                    &their_nonces.idia_refund,
+                    &nonces.idia_refund,
+                    &messages.idia_refund,
+                    &partial,
+                ) {
+                    return Err(SwapError::InvalidSignature);
+                }
+                let k = self.secret_nonces.idia_refund.take().ok_or(SwapError::NonceUsed)?;
+                let signature = SchnorrSignature {
+                    nonce: nonces.idia_refund.compress(),
+                    s: self.idia_partial(&k, &nonces.idia_refund, &self.messages.idia_refund) + partial,
+                };
+                if !signature.verify(&self.idia_joint_key.public_key, &self.messages.idia_refund) {
+                    return Err(SwapError::InvalidSignature);
+                }
+                self.idia_refund_signature = Some(signature);
+            }
+            (SwapRole::Participant, ScriptlessRefundPartial::External(partial)) => {
+                if !self.external_joint_key.verify_partial(
+                    &self.their_external_key,
+                    &their_nonces.external_refund,
+                    &nonces.external_refund,
+                    &messages.external_refund,
+                    &partial,
+                ) {
+                    return Err(SwapError::InvalidSignature);
+                }
+                let k = self.secret_nonces.external_refund.take().ok_or(SwapError::NonceUsed)?;
+                let ours = self.external_partial(&k, &nonces.external_refund, &self.messages.external_refund);
+                let signature = self
+                    .external_joint_key
+                    .combine(&nonces.external_refund, &self.messages.external_refund, [ours, partial])
+                    .adapt(&SecpScalar::ZERO);
+                if !signature.verify(&self.external_joint_key.output_key_x(), &self.messages.external_refund) {
+                    return Err(SwapError::InvalidSignature);
+                }
+                self.external_refund_signature = Some(signature);
+            }
+            _ => return Err(SwapError::MissingSignature("refund")),
+        }
+        self.idia_claim_presignature = Some(idia_claim);
+        self.external_claim_presignature = Some(external_claim);
+        Ok(())
+    }
+
+    // Never lock without a way back out and a way to claim
+    pub fn ready_to_lock(&self) -> bool {
+        self.idia_claim_presignature.is_some()
+            && self.external_claim_presignature.is_some()
+            && (self.idia_refund_signature.is_some() || self.external_refund_signature.is_some())
+    }
+
+    // The initiator's claim of the Bitcoin lock; publishing it reveals t
+    pub fn external_claim_signature(&self) -> Result<Bip340Signature, SwapError> {
+        if self.role != SwapRole::Initiator {
+            return Err(SwapError::WrongRole(self.role));
+        }
+        let secret = self.secret.ok_or(SwapError::SecretUnknown)?;
+        let presignature = self
+            .external_claim_presignature
+            .ok_or(SwapError::MissingSignature("claim"))?;
+        Ok(presignature.adapt(&to_secp_scalar(&secret).ok_or(SwapError::InvalidSecret)?))
+    }
+
+    // Reads t off the initiator's published Bitcoin claim
+    pub fn learn_secret(&mut self, published: &Bip340Signature) -> Result<Scalar, SwapError> {
+        let presignature = self
+            .external_claim_presignature
+            .ok_or(SwapError::MissingSignature("claim"))?;
+        let secret = presignature
+            .extract(published, &self.external_adaptor_point)
+            .and_then(|secret| to_ristretto_scalar(&secret))
+            .filter(|secret| RISTRETTO_BASEPOINT_POINT * secret == self.adaptor_point)
+            .ok_or(SwapError::InvalidSecret)?;
+        self.secret = Some(secret);
+        Ok(secret)
+    }
+
+    // The participant's claim of the Idia lock, once t is known
+    pub fn idia_claim_signature(&self) -> Result<SchnorrSignature, SwapError> {
+        if self.role != SwapRole::Participant {
+            return Err(SwapError::WrongRole(self.role));
+        }
+        let secret = self.secret.ok_or(SwapError::SecretUnknown)?;
+        let presignature = self
+            .idia_claim_presignature
+            .ok_or(SwapError::MissingSignature("claim"))?;
+        Ok(presignature.adapt(&secret))
+    }
+
+    pub fn idia_refund_signature(&self) -> Result<SchnorrSignature, SwapError> {
+        if !self.is_expired() {
+            return Err(SwapError::SwapNotExpired);
+        }
+        self.idia_refund_signature.ok_or(SwapError::WrongRole(self.role))
+    }
+
+    pub fn external_refund_signature(&self) -> Result<Bip340Signature, SwapError> {
+        if !self.is_expired() {
+            return Err(SwapError::SwapNotExpired);
+        }
+        self.external_refund_signature.ok_or(SwapError::WrongRole(self.role))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        now > self.time_lock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> ScriptlessMessages {
+        ScriptlessMessages {
+            idia_claim: b"idia claim".to_vec(),
+            idia_refund: b"idia refund".to_vec(),
+            external_claim: b"bitcoin claim".to_vec(),
+            external_refund: b"bitcoin refund".to_vec(),
+        }
+    }
+
+    // Runs the exchange up to both parties being ready to lock; the
+    // participant's refund is already open
+    fn negotiate() -> (ScriptlessSwap, ScriptlessSwap) {
+        let (alice_idia, alice_btc) = (Scalar::random(&mut OsRng), secp_nonce().0);
+        let (bob_idia, bob_btc) = (Scalar::random(&mut OsRng), secp_nonce().0);
+        let mut alice = ScriptlessSwap::initiate(
+            1_000,
+            24,
+            alice_idia,
+            alice_btc,
+            RISTRETTO_BASEPOINT_POINT * bob_idia,
+            SecpPoint::GENERATOR * bob_btc,
+            messages(),
+        );
+        let mut bob = ScriptlessSwap::join(
+            1_000,
+            0,
+            alice.adaptor_point,
+            alice.external_adaptor_point,
+            alice.adaptor_proof().unwrap(),
+            bob_idia,
+            bob_btc,
+            alice.our_idia_public_key(),
+            alice.our_external_public_key(),
+            messages(),
+        )
+        .unwrap();
+        assert_eq!(alice.idia_joint_key, bob.idia_joint_key);
+        assert_eq!(alice.external_joint_key, bob.external_joint_key);
+
+        alice.accept_nonces(bob.nonces());
+        bob.accept_nonces(alice.nonces());
+        let (from_alice, from_bob) = (alice.partial_signatures().unwrap(), bob.partial_signatures().unwrap());
+        alice.accept_partials(from_bob).unwrap();
+        bob.accept_partials(from_alice).unwrap();
+        assert!(alice.ready_to_lock() && bob.ready_to_lock());
+        (alice, bob)
+    }
+
+    #[test]
+    fn test_scriptless_swap_settles() {
+        let (alice, mut bob) = negotiate();
+
+        let bitcoin_claim = alice.external_claim_signature().unwrap();
+        assert!(bitcoin_claim.verify(&alice.external_joint_key.output_key_x(), &alice.messages.external_claim));
+        assert!(bob.idia_claim_signature().is_err());
+
+        assert_eq!(bob.learn_secret(&bitcoin_claim).unwrap(), alice.secret().unwrap());
+        let idia_claim = bob.idia_claim_signature().unwrap();
+        assert!(idia_claim.verify(&bob.idia_joint_key.public_key, &bob.messages.idia_claim));
+    }
+
+    #[test]
+    fn test_scriptless_refunds() {
+        let (alice, bob) = negotiate();
+        assert!(matches!(alice.idia_refund_signature(), Err(SwapError::SwapNotExpired)));
+        let refund = bob.external_refund_signature().unwrap();
+        assert!(refund.verify(&bob.external_joint_key.output_key_x(), &bob.messages.external_refund));
+        assert!(bob.idia_refund_signature().is_err());
+    }
+
+    #[test]
+    fn test_scriptless_rejects_bad_counterparty() {
+        let mut alice = ScriptlessSwap::initiate(
+            1_000,
+            24,
+            Scalar::random(&mut OsRng),
+            secp_nonce().0,
+            RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut OsRng),
+            secp_nonce().1,
+            messages(),
+        );
+        // Adaptor points that do not share a secret
+        let (_, other_point, _) = CrossGroupDleq::prove(&cross_group_secret()).unwrap();
+        let joined = ScriptlessSwap::join(
+            1_000,
+            0,
+            alice.adaptor_point,
+            other_point,
+            alice.adaptor_proof().unwrap(),
+            Scalar::random(&mut OsRng),
+            secp_nonce().0,
+            alice.our_idia_public_key(),
+            alice.our_external_public_key(),
+            messages(),
+        );
+        assert!(matches!(joined, Err(SwapError::InvalidAdaptorProof)));
+
+        // Shares from someone other than the counterparty
+        let (mut bob, _) = negotiate();
+        alice.accept_nonces(bob.nonces());
+        let from_bob = bob.partial_signatures().unwrap();
+        assert!(matches!(alice.accept_partials(from_bob), Err(SwapError::InvalidSignature)));
+        assert!(!alice.ready_to_lock());
+    }
+}
@@ -0,0 +1,373 @@
+use bitcoin::blockdata::opcodes::all::{
+    OP_CHECKSIG, OP_CLTV, OP_DROP, OP_ELSE, OP_ENDIF, OP_EQUALVERIFY, OP_IF, OP_SHA256,
+};
+use bitcoin::blockdata::script::{Builder, Instruction, Script, ScriptBuf};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::key::TweakedPublicKey;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey, XOnlyPublicKey};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::{
+    absolute, transaction, Address, Amount, Network, OutPoint, PublicKey, Sequence, Transaction, TxIn, TxOut,
+    Witness,
+};
+use idia_core::{Bip340JointKey, Bip340Signature};
+
+// Upper bound on the size of a one-in one-out P2WSH HTLC spend, used for fees
+pub const HTLC_SPEND_VSIZE: u64 = 200;
+// Same for a taproot key-path spend
+pub const KEY_PATH_SPEND_VSIZE: u64 = 111;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BitcoinHtlcError {
+    #[error("Not a swap HTLC script: {0}")]
+    InvalidScript(String),
+    #[error("{amount} sat does not cover the {fee} sat fee")]
+    InsufficientFunds { amount: u64, fee: u64 },
+    #[error("Preimage does not match the payment hash")]
+    InvalidPreimage,
+    #[error("Sighash error: {0}")]
+    Sighash(String),
+    #[error("Invalid taproot output key")]
+    InvalidOutputKey,
+}
+
+// Unsigned one-in one-out spend of `outpoint` to `destination`, less the fee
+fn spend_template(
+    outpoint: OutPoint,
+    amount: u64,
+    destination: &Address,
+    fee: u64,
+    lock_time: absolute::LockTime,
+    sequence: Sequence,
+) -> Result<Transaction, BitcoinHtlcError> {
+    if amount <= fee {
+        return Err(BitcoinHtlcError::InsufficientFunds { amount, fee });
+    }
+    Ok(Transaction {
+        version: transaction::Version::TWO,
+        lock_time,
+        input: vec![TxIn {
+            previous_output: outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::from_sat(amount - fee),
+            script_pubkey: destination.script_pubkey(),
+        }],
+    })
+}
+
+// Bitcoin leg of a swap:
+//   OP_IF OP_SHA256 <payment_hash> OP_EQUALVERIFY <recipient>
+//   OP_ELSE <lock_time> OP_CHECKLOCKTIMEVERIFY OP_DROP <refund>
+//   OP_ENDIF OP_CHECKSIG
+// Unlike the bridge's relative-timeout HTLC, the refund opens at an absolute
+// time, so both legs of a swap can be set against one clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitcoinHtlc {
+    pub payment_hash: [u8; 32],
+    pub recipient: PublicKey,
+    pub refund: PublicKey,
+    pub lock_time: absolute::LockTime,
+}
+
+impl BitcoinHtlc {
+    pub fn witness_script(&self) -> ScriptBuf {
+        Builder::new()
+            .push_opcode(OP_IF)
+            .push_opcode(OP_SHA256)
+            .push_slice(self.payment_hash)
+            .push_opcode(OP_EQUALVERIFY)
+            .push_key(&self.recipient)
+            .push_opcode(OP_ELSE)
+            .push_lock_time(self.lock_time)
+            .push_opcode(OP_CLTV)
+            .push_opcode(OP_DROP)
+            .push_key(&self.refund)
+            .push_opcode(OP_ENDIF)
+            .push_opcode(OP_CHECKSIG)
+            .into_script()
+    }
+
+    // Accepts exactly the script `witness_script` builds, so a counterparty's
+    // HTLC can be checked before funds are committed against it
+    pub fn parse(script: &Script) -> Result<Self, BitcoinHtlcError> {
+        let invalid = |what: &str| BitcoinHtlcError::InvalidScript(what.to_string());
+        let instructions = script
+            .instructions_minimal()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| BitcoinHtlcError::InvalidScript(e.to_string()))?;
+        let [
+            Instruction::Op(OP_IF),
+            Instruction::Op(OP_SHA256),
+            Instruction::PushBytes(hash),
+            Instruction::Op(OP_EQUALVERIFY),
+            Instruction::PushBytes(recipient),
+            Instruction::Op(OP_ELSE),
+            lock_time,
+            Instruction::Op(OP_CLTV),
+            Instruction::Op(OP_DROP),
+            Instruction::PushBytes(refund),
+            Instruction::Op(OP_ENDIF),
+            Instruction::Op(OP_CHECKSIG),
+        ] = instructions.as_slice()
+        else {
+            return Err(invalid("unexpected template"));
+        };
+
+        let payment_hash = hash.as_bytes().try_into().map_err(|_| invalid("payment hash is not 32 bytes"))?;
+        let recipient = PublicKey::from_slice(recipient.as_bytes()).map_err(|_| invalid("bad recipient key"))?;
+        let refund = PublicKey::from_slice(refund.as_bytes()).map_err(|_| invalid("bad refund key"))?;
+        let lock_time = lock_time
+            .script_num()
+            .and_then(|n| u32::try_from(n).ok())
+            .map(absolute::LockTime::from_consensus)
+            .ok_or_else(|| invalid("bad lock time"))?;
+
+        let htlc = Self {
+            payment_hash,
+            recipient,
+            refund,
+            lock_time,
+        };
+        // Rules out non-minimal variants that parse to the same fields
+        if htlc.witness_script().as_script() != script {
+            return Err(invalid("non-canonical encoding"));
+        }
+        Ok(htlc)
+    }
+
+    pub fn script_pubkey(&self) -> ScriptBuf {
+        ScriptBuf::new_p2wsh(&self.witness_script().wscript_hash())
+    }
+
+    pub fn address(&self, network: Network) -> Address {
+        Address::p2wsh(&self.witness_script(), network)
+    }
+
+    pub fn claim_template(
+        &self,
+        outpoint: OutPoint,
+        amount: u64,
+        destination: &Address,
+        fee_rate: u64,
+    ) -> Result<Transaction, BitcoinHtlcError> {
+        spend_template(
+            outpoint,
+            amount,
+            destination,
+            fee_rate * HTLC_SPEND_VSIZE,
+            absolute::LockTime::ZERO,
+            Sequence::MAX,
+        )
+    }
+
+    // nLockTime must reach the script's lock time and the input must not be
+    // final, or OP_CHECKLOCKTIMEVERIFY fails
+    pub fn refund_template(
+        &self,
+        outpoint: OutPoint,
+        amount: u64,
+        destination: &Address,
+        fee_rate: u64,
+    ) -> Result<Transaction, BitcoinHtlcError> {
+        spend_template(
+            outpoint,
+            amount,
+            destination,
+            fee_rate * HTLC_SPEND_VSIZE,
+            self.lock_time,
+            Sequence::ENABLE_LOCKTIME_NO_RBF,
+        )
+    }
+
+    fn sign(&self, tx: &Transaction, amount: u64, secret_key: &SecretKey) -> Result<Vec<u8>, BitcoinHtlcError> {
+        let sighash = SighashCache::new(tx)
+            .p2wsh_signature_hash(0, &self.witness_script(), Amount::from_sat(amount), EcdsaSighashType::All)
+            .map_err(|e| BitcoinHtlcError::Sighash(e.to_string()))?;
+        let signature = Secp256k1::signing_only().sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), secret_key);
+        Ok(bitcoin::ecdsa::Signature::sighash_all(signature).to_vec())
+    }
+
+    // Signs a claim template in place; `amount` is the HTLC output's value
+    pub fn sign_claim(
+        &self,
+        tx: &mut Transaction,
+        amount: u64,
+        secret_key: &SecretKey,
+        preimage: [u8; 32],
+    ) -> Result<(), BitcoinHtlcError> {
+        if sha256::Hash::hash(&preimage).to_byte_array() != self.payment_hash {
+            return Err(BitcoinHtlcError::InvalidPreimage);
+        }
+        let signature = self.sign(tx, amount, secret_key)?;
+        // Claim path selects OP_IF with the preimage
+        let mut witness = Witness::new();
+        witness.push(signature);
+        witness.push(preimage);
+        witness.push([1u8]);
+        witness.push(self.witness_script().as_bytes());
+        tx.input[0].witness = witness;
+        Ok(())
+    }
+
+    pub fn sign_refund(&self, tx: &mut Transaction, amount: u64, secret_key: &SecretKey) -> Result<(), BitcoinHtlcError> {
+        let signature = self.sign(tx, amount, secret_key)?;
+        // Refund path selects OP_ELSE with an empty push
+        let mut witness = Witness::new();
+        witness.push(signature);
+        witness.push([]);
+        witness.push(self.witness_script().as_bytes());
+        tx.input[0].witness = witness;
+        Ok(())
+    }
+}
+
+// The preimage from a claim of `htlc` seen on chain, if `tx` is one
+pub fn extract_preimage(tx: &Transaction, htlc: &BitcoinHtlc) -> Option<[u8; 32]> {
+    let script = htlc.witness_script();
+    tx.input.iter().find_map(|input| {
+        let items: Vec<&[u8]> = input.witness.iter().collect();
+        match items.as_slice() {
+            [_, preimage, [1u8], witness_script] if *witness_script == script.as_bytes() => {
+                let preimage: [u8; 32] = (*preimage).try_into().ok()?;
+                (sha256::Hash::hash(&preimage).to_byte_array() == htlc.payment_hash).then_some(preimage)
+            }
+            _ => None,
+        }
+    })
+}
+
+// Bitcoin leg of a scriptless swap: a key-path-only taproot output to the
+// parties' joint key, which on chain looks like any single-key wallet's.
+// Claim and refund are both spent with one BIP340 signature; the refund is
+// held back by its nLockTime alone, so it is fully signed before locking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptlessLock {
+    pub output_key: [u8; 32],
+}
+
+impl ScriptlessLock {
+    pub fn new(joint_key: &Bip340JointKey) -> Self {
+        Self {
+            output_key: joint_key.output_key_x(),
+        }
+    }
+
+    fn tweaked_key(&self) -> Result<TweakedPublicKey, BitcoinHtlcError> {
+        let key = XOnlyPublicKey::from_slice(&self.output_key).map_err(|_| BitcoinHtlcError::InvalidOutputKey)?;
+        Ok(TweakedPublicKey::dangerous_assume_tweaked(key))
+    }
+
+    pub fn script_pubkey(&self) -> Result<ScriptBuf, BitcoinHtlcError> {
+        Ok(ScriptBuf::new_p2tr_tweaked(self.tweaked_key()?))
+    }
+
+    pub fn address(&self, network: Network) -> Result<Address, BitcoinHtlcError> {
+        Ok(Address::p2tr_tweaked(self.tweaked_key()?, network))
+    }
+
+    pub fn claim_template(
+        &self,
+        outpoint: OutPoint,
+        amount: u64,
+        destination: &Address,
+        fee_rate: u64,
+    ) -> Result<Transaction, BitcoinHtlcError> {
+        spend_template(
+            outpoint,
+            amount,
+            destination,
+            fee_rate * KEY_PATH_SPEND_VSIZE,
+            absolute::LockTime::ZERO,
+            Sequence::MAX,
+        )
+    }
+
+    pub fn refund_template(
+        &self,
+        outpoint: OutPoint,
+        amount: u64,
+        destination: &Address,
+        fee_rate: u64,
+        lock_time: absolute::LockTime,
+    ) -> Result<Transaction, BitcoinHtlcError> {
+        spend_template(
+            outpoint,
+            amount,
+            destination,
+            fee_rate * KEY_PATH_SPEND_VSIZE,
+            lock_time,
+            Sequence::ENABLE_LOCKTIME_NO_RBF,
+        )
+    }
+
+    // The message both parties sign for a spend template; `amount` is the
+    // locked output's value
+    pub fn sighash(&self, tx: &Transaction, amount: u64) -> Result<[u8; 32], BitcoinHtlcError> {
+        let prevout = TxOut {
+            value: Amount::from_sat(amount),
+            script_pubkey: self.script_pubkey()?,
+        };
+        let sighash = SighashCache::new(tx)
+            .taproot_key_spend_signature_hash(0, &Prevouts::All(&[prevout]), TapSighashType::Default)
+            .map_err(|e| BitcoinHtlcError::Sighash(e.to_string()))?;
+        Ok(sighash.to_byte_array())
+    }
+
+    // Completes a template with the joint signature over its sighash
+    pub fn finalize(&self, tx: &mut Transaction, amount: u64, signature: &Bip340Signature) -> Result<(), BitcoinHtlcError> {
+        if !signature.verify(&self.output_key, &self.sighash(tx, amount)?) {
+            return Err(BitcoinHtlcError::Sighash("signature does not cover this spend".to_string()));
+        }
+        let mut witness = Witness::new();
+        witness.push(signature.to_bytes());
+        tx.input[0].witness = witness;
+        Ok(())
+    }
+}
+
+// The joint signature from a key-path spend of `outpoint` seen on chain,
+// which is how the participant learns the adaptor secret
+pub fn extract_key_path_signature(tx: &Transaction, outpoint: &OutPoint) -> Option<Bip340Signature> {
+    let input = tx.input.iter().find(|input| input.previous_output == *outpoint)?;
+    let items: Vec<&[u8]> = input.witness.iter().collect();
+    match items.as_slice() {
+        [signature] => Bip340Signature::from_bytes((*signature).try_into().ok()?).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Txid;
+    use idia_core::{secp_nonce, SecpPoint, SecpScalar};
+
+    #[test]
+    fn test_scriptless_lock_key_path_spend() {
+        let keys = [secp_nonce().0, secp_nonce().0];
+        let publics = keys.map(|key| SecpPoint::GENERATOR * key);
+        let joint = Bip340JointKey::new(&publics[0], &publics[1]);
+        let lock = ScriptlessLock::new(&joint);
+        assert!(lock.script_pubkey().unwrap().is_p2tr());
+
+        let outpoint = OutPoint::new(Txid::all_zeros(), 0);
+        let destination = lock.address(Network::Regtest).unwrap();
+        let mut tx = lock.claim_template(outpoint, 50_000, &destination, 2).unwrap();
+        let sighash = lock.sighash(&tx, 50_000).unwrap();
+
+        let nonces = [secp_nonce(), secp_nonce()];
+        let nonce = nonces[0].1 + nonces[1].1;
+        let partials = [0, 1].map(|i| joint.partial_sign(&keys[i], &nonces[i].0, &nonce, &sighash).unwrap());
+        let signature = joint.combine(&nonce, &sighash, partials).adapt(&SecpScalar::ZERO);
+
+        // Signed for another amount, the sighash differs
+        assert!(lock.finalize(&mut tx.clone(), 40_000, &signature).is_err());
+        lock.finalize(&mut tx, 50_000, &signature).unwrap();
+        assert_eq!(extract_key_path_signature(&tx, &outpoint), Some(signature));
+        assert_eq!(extract_key_path_signature(&tx, &OutPoint::new(Txid::all_zeros(), 1)), None);
+    }
+}
@@ -0,0 +1,314 @@
+use async_trait::async_trait;
+use ethers::abi::{self, ParamType, Token};
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::atomic::SwapError;
+use super::store::{SwapLedger, SwapRecord};
+
+// Events of the reference HashedTimelock contracts; contract ids are indexed
+const NEW_EVENT: &str = "LogHTLCNew(bytes32,address,address,uint256,bytes32,uint256)";
+const NEW_ERC20_EVENT: &str = "LogHTLCERC20New(bytes32,address,address,address,uint256,bytes32,uint256)";
+const WITHDRAW_EVENT: &str = "LogHTLCWithdraw(bytes32)";
+const REFUND_EVENT: &str = "LogHTLCRefund(bytes32)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EthAsset {
+    Ether,
+    Erc20(Address),
+}
+
+// A contract as `getContract` reports it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthHtlcContract {
+    pub sender: Address,
+    pub receiver: Address,
+    pub amount: U256,
+    pub hash_lock: [u8; 32],
+    pub time_lock: u64,
+    pub withdrawn: bool,
+    pub refunded: bool,
+    // Zero until withdrawn
+    pub preimage: [u8; 32],
+}
+
+fn chain_error(e: impl std::fmt::Display) -> SwapError {
+    SwapError::Chain(e.to_string())
+}
+
+fn call_data(signature: &str, tokens: &[Token]) -> Bytes {
+    let mut data = keccak256(signature)[..4].to_vec();
+    data.extend(abi::encode(tokens));
+    data.into()
+}
+
+// Client for the HashedTimelock (ether) or HashedTimelockERC20 contract. Both
+// lock with sha256, so one hash lock covers this leg and the Idia leg, and
+// both take an absolute unix-time lock like `ExternalChainSwap::time_lock`.
+pub struct EthereumHtlc<M> {
+    client: Arc<M>,
+    contract: Address,
+    asset: EthAsset,
+    // First block searched for swap events
+    from_block: u64,
+}
+
+impl<M: Middleware + 'static> EthereumHtlc<M> {
+    pub fn new(client: Arc<M>, contract: Address, asset: EthAsset, from_block: u64) -> Self {
+        Self {
+            client,
+            contract,
+            asset,
+            from_block,
+        }
+    }
+
+    async fn send(&self, to: Address, data: Bytes, value: U256) -> Result<TransactionReceipt, SwapError> {
+        let tx = TransactionRequest::new().to(to).data(data).value(value);
+        let receipt = self
+            .client
+            .send_transaction(tx, None)
+            .await
+            .map_err(chain_error)?
+            .await
+            .map_err(chain_error)?
+            .ok_or_else(|| SwapError::Chain("transaction dropped from the mempool".to_string()))?;
+        if receipt.status != Some(U64::one()) {
+            return Err(SwapError::Chain(format!("{:?} reverted", receipt.transaction_hash)));
+        }
+        Ok(receipt)
+    }
+
+    // Locks `amount` to `receiver`, approving the token transfer first for an
+    // ERC-20 leg. Returns the contract id and the locking transaction.
+    pub async fn new_contract(
+        &self,
+        receiver: Address,
+        hash_lock: [u8; 32],
+        time_lock: u64,
+        amount: U256,
+    ) -> Result<([u8; 32], H256), SwapError> {
+        let receipt = match self.asset {
+            EthAsset::Ether => {
+                let data = call_data(
+                    "newContract(address,bytes32,uint256)",
+                    &[
+                        Token::Address(receiver),
+                        Token::FixedBytes(hash_lock.to_vec()),
+                        Token::Uint(time_lock.into()),
+                    ],
+                );
+                self.send(self.contract, data, amount).await?
+            }
+            EthAsset::Erc20(token) => {
+                let approve = call_data(
+                    "approve(address,uint256)",
+                    &[Token::Address(self.contract), Token::Uint(amount)],
+                );
+                self.send(token, approve, U256::zero()).await?;
+                let data = call_data(
+                    "newContract(address,bytes32,uint256,address,uint256)",
+                    &[
+                        Token::Address(receiver),
+                        Token::FixedBytes(hash_lock.to_vec()),
+                        Token::Uint(time_lock.into()),
+                        Token::Address(token),
+                        Token::Uint(amount),
+                    ],
+                );
+                self.send(self.contract, data, U256::zero()).await?
+            }
+        };
+
+        let new_event = H256::from(keccak256(self.new_event()));
+        let contract_id = receipt
+            .logs
+            .iter()
+            .find(|log| log.address == self.contract && log.topics.first() == Some(&new_event))
+            .and_then(|log| log.topics.get(1))
+            .ok_or_else(|| SwapError::Chain("newContract emitted no contract id".to_string()))?;
+        Ok((contract_id.0, receipt.transaction_hash))
+    }
+
+    pub async fn withdraw(&self, contract_id: [u8; 32], preimage: [u8; 32]) -> Result<H256, SwapError> {
+        let data = call_data(
+            "withdraw(bytes32,bytes32)",
+            &[Token::FixedBytes(contract_id.to_vec()), Token::FixedBytes(preimage.to_vec())],
+        );
+        Ok(self.send(self.contract, data, U256::zero()).await?.transaction_hash)
+    }
+
+    pub async fn refund(&self, contract_id: [u8; 32]) -> Result<H256, SwapError> {
+        let data = call_data("refund(bytes32)", &[Token::FixedBytes(contract_id.to_vec())]);
+        Ok(self.send(self.contract, data, U256::zero()).await?.transaction_hash)
+    }
+
+    pub async fn get_contract(&self, contract_id: [u8; 32]) -> Result<EthHtlcContract, SwapError> {
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(self.contract)
+            .data(call_data("getContract(bytes32)", &[Token::FixedBytes(contract_id.to_vec())]))
+            .into();
+        let output = self.client.call(&tx, None).await.map_err(chain_error)?;
+
+        let mut types = vec![ParamType::Address, ParamType::Address];
+        if matches!(self.asset, EthAsset::Erc20(_)) {
+            types.push(ParamType::Address);
+        }
+        types.extend([
+            ParamType::Uint(256),
+            ParamType::FixedBytes(32),
+            ParamType::Uint(256),
+            ParamType::Bool,
+            ParamType::Bool,
+            ParamType::FixedBytes(32),
+        ]);
+        let mut tokens = abi::decode(&types, &output).map_err(chain_error)?;
+        if matches!(self.asset, EthAsset::Erc20(_)) {
+            tokens.remove(2);
+        }
+        let [Token::Address(sender), Token::Address(receiver), Token::Uint(amount), Token::FixedBytes(hash_lock), Token::Uint(time_lock), Token::Bool(withdrawn), Token::Bool(refunded), Token::FixedBytes(preimage)] =
+            tokens.as_slice()
+        else {
+            return Err(SwapError::Chain("unexpected getContract output".to_string()));
+        };
+        let bytes32 = |b: &Vec<u8>| <[u8; 32]>::try_from(b.as_slice()).map_err(chain_error);
+        Ok(EthHtlcContract {
+            sender: *sender,
+            receiver: *receiver,
+            amount: *amount,
+            hash_lock: bytes32(hash_lock)?,
+            time_lock: time_lock.low_u64(),
+            withdrawn: *withdrawn,
+            refunded: *refunded,
+            preimage: bytes32(preimage)?,
+        })
+    }
+
+    fn new_event(&self) -> &'static str {
+        match self.asset {
+            EthAsset::Ether => NEW_EVENT,
+            EthAsset::Erc20(_) => NEW_ERC20_EVENT,
+        }
+    }
+
+    // The contract locked to `hash_lock`, with its locking transaction. The
+    // hash lock is not indexed, so matching is done on the decoded log data.
+    pub async fn find_contract(&self, hash_lock: [u8; 32]) -> Result<Option<([u8; 32], H256)>, SwapError> {
+        let filter = Filter::new()
+            .address(self.contract)
+            .topic0(H256::from(keccak256(self.new_event())))
+            .from_block(self.from_block);
+        let logs = self.client.get_logs(&filter).await.map_err(chain_error)?;
+
+        // Data holds amount, hash lock, time lock, after the token for ERC-20
+        let hash_offset = match self.asset {
+            EthAsset::Ether => 32,
+            EthAsset::Erc20(_) => 64,
+        };
+        Ok(logs.into_iter().find_map(|log| {
+            let matches = log.data.get(hash_offset..hash_offset + 32) == Some(&hash_lock[..]);
+            let id = log.topics.get(1)?;
+            (matches && log.removed != Some(true)).then_some((id.0, log.transaction_hash?))
+        }))
+    }
+
+    // The withdrawal of `contract_id`, with the preimage it revealed. The
+    // event carries only the id, so the preimage is read back from storage
+    // and checked against the hash lock.
+    pub async fn find_withdrawal(&self, contract_id: [u8; 32]) -> Result<Option<(H256, [u8; 32])>, SwapError> {
+        let filter = Filter::new()
+            .address(self.contract)
+            .topic0(H256::from(keccak256(WITHDRAW_EVENT)))
+            .topic1(H256::from(contract_id))
+            .from_block(self.from_block);
+        let Some(tx) = self
+            .client
+            .get_logs(&filter)
+            .await
+            .map_err(chain_error)?
+            .into_iter()
+            .find(|log| log.removed != Some(true))
+            .and_then(|log| log.transaction_hash)
+        else {
+            return Ok(None);
+        };
+
+        let contract = self.get_contract(contract_id).await?;
+        if <[u8; 32]>::from(Sha256::digest(contract.preimage)) != contract.hash_lock {
+            return Err(SwapError::InvalidSecret);
+        }
+        Ok(Some((tx, contract.preimage)))
+    }
+
+    // Polls until the counterparty withdraws `contract_id`, for swaps driven
+    // outside the swap monitor
+    pub async fn wait_for_preimage(&self, contract_id: [u8; 32], poll: Duration) -> Result<[u8; 32], SwapError> {
+        let mut interval = tokio::time::interval(poll);
+        loop {
+            interval.tick().await;
+            if let Some((tx, preimage)) = self.find_withdrawal(contract_id).await? {
+                tracing::info!("HTLC withdrawn in {:?}, preimage learned", tx);
+                return Ok(preimage);
+            }
+            if self.is_refunded(contract_id).await? {
+                return Err(SwapError::SwapExpired);
+            }
+        }
+    }
+
+    pub async fn is_refunded(&self, contract_id: [u8; 32]) -> Result<bool, SwapError> {
+        let filter = Filter::new()
+            .address(self.contract)
+            .topic0(H256::from(keccak256(REFUND_EVENT)))
+            .topic1(H256::from(contract_id))
+            .from_block(self.from_block);
+        let logs = self.client.get_logs(&filter).await.map_err(chain_error)?;
+        Ok(logs.iter().any(|log| log.removed != Some(true)))
+    }
+
+    async fn contract_for(&self, swap: &SwapRecord) -> Result<[u8; 32], SwapError> {
+        self.find_contract(swap.hash_lock)
+            .await?
+            .map(|(id, _)| id)
+            .ok_or_else(|| SwapError::Chain(format!("no HTLC locked to swap {}", swap.id)))
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> SwapLedger for EthereumHtlc<M> {
+    async fn is_confirmed(&self, txid: &str) -> Result<bool, SwapError> {
+        let hash: H256 = txid.parse().map_err(chain_error)?;
+        let receipt = self.client.get_transaction_receipt(hash).await.map_err(chain_error)?;
+        Ok(receipt.is_some_and(|r| r.status == Some(U64::one())))
+    }
+
+    async fn claim(&self, swap: &SwapRecord, preimage: [u8; 32]) -> Result<String, SwapError> {
+        let contract_id = self.contract_for(swap).await?;
+        Ok(format!("{:?}", self.withdraw(contract_id, preimage).await?))
+    }
+
+    async fn refund(&self, swap: &SwapRecord) -> Result<String, SwapError> {
+        let contract_id = self.contract_for(swap).await?;
+        Ok(format!("{:?}", EthereumHtlc::refund(self, contract_id).await?))
+    }
+
+    async fn find_lock(&self, swap: &SwapRecord) -> Result<Option<String>, SwapError> {
+        Ok(self
+            .find_contract(swap.hash_lock)
+            .await?
+            .map(|(_, tx)| format!("{:?}", tx)))
+    }
+
+    async fn find_claim(&self, swap: &SwapRecord) -> Result<Option<(String, [u8; 32])>, SwapError> {
+        let Some((contract_id, _)) = self.find_contract(swap.hash_lock).await? else {
+            return Ok(None);
+        };
+        Ok(self
+            .find_withdrawal(contract_id)
+            .await?
+            .map(|(tx, preimage)| (format!("{:?}", tx), preimage)))
+    }
+}
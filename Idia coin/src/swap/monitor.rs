@@ -0,0 +1,161 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+use super::atomic::{SwapError, SwapState};
+use super::store::{ResumeAction, SwapLedger, SwapRecord, SwapStore};
+
+const EVENT_CAPACITY: usize = 1_024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapLeg {
+    Idia,
+    External,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SwapEvent {
+    Locked { id: String, leg: SwapLeg, txid: String },
+    Claimed { id: String, leg: SwapLeg, txid: String },
+    Expired { id: String, leg: SwapLeg },
+    StateChanged { id: String, state: SwapState },
+    Completed { id: String, txid: String },
+    Refunded { id: String, txid: String },
+    Failed { id: String, error: String },
+}
+
+// Drives every unfinished swap in the store: watches both chains for the
+// lock and claim transactions, records what it sees, and lets the store take
+// the next step, which includes refunding our leg once its time lock passes.
+// Subscribers get each lifecycle change as an event.
+pub struct SwapMonitor {
+    store: Arc<SwapStore>,
+    events: broadcast::Sender<SwapEvent>,
+    last_check: AtomicU64,
+}
+
+impl SwapMonitor {
+    pub fn new(store: Arc<SwapStore>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            store,
+            events,
+            last_check: AtomicU64::new(0),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SwapEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: SwapEvent) {
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    pub async fn tick(&self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        for record in self.store.pending().await {
+            let id = record.id.clone();
+            if let Err(e) = self.check(record).await {
+                tracing::warn!("Swap {} check failed: {}", id, e);
+                self.emit(SwapEvent::Failed { id, error: e.to_string() });
+            }
+        }
+        self.last_check.store(now, Ordering::Relaxed);
+    }
+
+    async fn check(&self, mut record: SwapRecord) -> Result<(), SwapError> {
+        let (idia, external) = self.store.ledgers(&record)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let mut changed = self.watch_lock(&mut record, SwapLeg::Idia, idia.as_ref()).await?;
+        changed |= self.watch_lock(&mut record, SwapLeg::External, external.as_ref()).await?;
+        changed |= self.watch_claim(&mut record, SwapLeg::Idia, idia.as_ref()).await?;
+        changed |= self.watch_claim(&mut record, SwapLeg::External, external.as_ref()).await?;
+        if changed {
+            self.store.update(record.clone()).await?;
+        }
+
+        // Reported once, on the first check past each deadline
+        let last_check = self.last_check.load(Ordering::Relaxed);
+        for (leg, time_lock) in [
+            (SwapLeg::External, record.external_time_lock),
+            (SwapLeg::Idia, record.idia_time_lock),
+        ] {
+            if now > time_lock && last_check <= time_lock {
+                self.emit(SwapEvent::Expired { id: record.id.clone(), leg });
+            }
+        }
+
+        let id = record.id.clone();
+        match self.store.resume_swap(record).await? {
+            ResumeAction::Waiting => {}
+            ResumeAction::Advanced(state) => self.emit(SwapEvent::StateChanged { id, state }),
+            ResumeAction::Claimed(txid) => self.emit(SwapEvent::Completed { id, txid }),
+            ResumeAction::Refunded(txid) => self.emit(SwapEvent::Refunded { id, txid }),
+        }
+        Ok(())
+    }
+
+    async fn watch_lock(&self, record: &mut SwapRecord, leg: SwapLeg, ledger: &dyn SwapLedger) -> Result<bool, SwapError> {
+        if record.lock_tx(leg).is_some() {
+            return Ok(false);
+        }
+        let Some(txid) = ledger.find_lock(record).await? else {
+            return Ok(false);
+        };
+        match leg {
+            SwapLeg::Idia => record.idia_lock_tx = Some(txid.clone()),
+            SwapLeg::External => record.external_lock_tx = Some(txid.clone()),
+        }
+        self.emit(SwapEvent::Locked { id: record.id.clone(), leg, txid });
+        Ok(true)
+    }
+
+    // A claim on either leg reveals the preimage, which is all the other side
+    // needs to claim theirs. For a participant this is how the secret arrives:
+    // sealed here, the Idia claim follows in the same check.
+    async fn watch_claim(&self, record: &mut SwapRecord, leg: SwapLeg, ledger: &dyn SwapLedger) -> Result<bool, SwapError> {
+        if record.lock_tx(leg).is_none() || record.claim_tx(leg).is_some() {
+            return Ok(false);
+        }
+        let Some((txid, preimage)) = ledger.find_claim(record).await? else {
+            return Ok(false);
+        };
+        if <[u8; 32]>::from(Sha256::digest(preimage)) != record.hash_lock {
+            return Err(SwapError::InvalidSecret);
+        }
+        match leg {
+            SwapLeg::Idia => record.idia_claim_tx = Some(txid.clone()),
+            SwapLeg::External => record.external_claim_tx = Some(txid.clone()),
+        }
+        self.emit(SwapEvent::Claimed { id: record.id.clone(), leg, txid });
+        if record.sealed_preimage.is_none() {
+            tracing::info!("Swap {} preimage revealed on the {:?} leg", record.id, leg);
+            record.sealed_preimage = Some(self.store.seal(preimage)?);
+        }
+        Ok(true)
+    }
+
+    pub fn spawn(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                self.tick().await;
+            }
+        })
+    }
+}
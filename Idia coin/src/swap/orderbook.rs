@@ -0,0 +1,691 @@
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+use idia_core::{GossipAcceptance, GossipValidator, NetworkCommand, SchnorrSignature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, RwLock};
+
+use super::atomic::{AtomicSwap, CrossChainSwap, SwapError};
+
+// The participant needs this long between learning the preimage from the
+// external claim and the Idia leg expiring
+const MIN_TIMELOCK_GAP_HOURS: u64 = 6;
+// Clock skew tolerated when checking the initiator's time locks
+const TIMELOCK_SLACK_SECS: u64 = 600;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OrderBookError {
+    #[error("Malformed order message: {0}")]
+    Malformed(#[from] serde_json::Error),
+    #[error("Bad signature on {0}")]
+    InvalidSignature(&'static str),
+    #[error("Unknown offer {0}")]
+    UnknownOffer(String),
+    #[error("Offer {0} has expired")]
+    OfferExpired(String),
+    #[error("Offer {0} is fully filled")]
+    OfferFilled(String),
+    #[error("Amount {amount} outside the offer's range {min}..={max}")]
+    AmountOutOfRange { amount: u64, min: u64, max: u64 },
+    #[error("Fill of {0} does not cover its fees or rounds to nothing")]
+    UnfillableAmount(u64),
+    #[error("Lock amounts differ from the offer's terms")]
+    TermsMismatch,
+    #[error("Invalid offer: {0}")]
+    InvalidOffer(&'static str),
+    #[error("Invalid time lock policy: {0}")]
+    InvalidPolicy(String),
+    #[error("Take {take} is {state:?}, not awaiting that message")]
+    UnexpectedState { take: String, state: NegotiationState },
+    #[error("Swap error: {0}")]
+    Swap(#[from] SwapError),
+    #[error("Network is down")]
+    NetworkDown,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+// Domain-separated digest of a message body, which is what gets signed and
+// what names offers and takes
+fn digest<T: Serialize>(domain: &[u8], body: &T) -> Result<[u8; 32], OrderBookError> {
+    let mut hasher = Sha256::new();
+    hasher.update(domain);
+    hasher.update(serde_json::to_vec(body)?);
+    Ok(hasher.finalize().into())
+}
+
+fn sign<T: Serialize>(domain: &[u8], body: &T, key: &Scalar) -> Result<Vec<u8>, OrderBookError> {
+    Ok(SchnorrSignature::sign(key, &digest(domain, body)?).to_bytes().to_vec())
+}
+
+fn verify<T: Serialize>(domain: &[u8], body: &T, public_key: &[u8; 32], signature: &[u8]) -> bool {
+    let Some(public_key) = CompressedRistretto(*public_key).decompress() else {
+        return false;
+    };
+    let Ok(bytes) = <[u8; 64]>::try_from(signature) else {
+        return false;
+    };
+    let (Ok(signature), Ok(message)) = (SchnorrSignature::from_bytes(&bytes), digest(domain, body)) else {
+        return false;
+    };
+    signature.verify(&public_key, &message)
+}
+
+fn public_key(key: &Scalar) -> [u8; 32] {
+    (RISTRETTO_BASEPOINT_POINT * key).compress().to_bytes()
+}
+
+// Price of one IDIA base unit, as a ratio to avoid rounding in the rate itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rate {
+    pub quote: u64,
+    pub base: u64,
+}
+
+impl Rate {
+    // External-chain amount owed for `amount` IDIA, rounded down
+    pub fn quote_amount(&self, amount: u64) -> Option<u64> {
+        u64::try_from(amount as u128 * self.quote as u128 / self.base.max(1) as u128).ok()
+    }
+}
+
+// Fee of the claim spending each leg's HTLC, in that chain's units. Whoever
+// funds a leg adds its claim fee on top, so the side claiming it nets the
+// agreed amount instead of absorbing the fee.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapFees {
+    pub idia_claim: u64,
+    pub external_claim: u64,
+}
+
+// What one fill locks on each leg
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FillTerms {
+    // IDIA the taker receives, and what the maker is paid for on the external chain
+    pub amount: u64,
+    pub quote: u64,
+    // Funded by the maker on Idia and by the taker externally, fees included
+    pub idia_lock: u64,
+    pub external_lock: u64,
+}
+
+// Hours each leg stays locked. The maker's Idia leg must outlast the taker's
+// external leg by enough to claim safely after the preimage is revealed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelockPolicy {
+    pub idia_hours: u64,
+    pub external_hours: u64,
+}
+
+impl TimelockPolicy {
+    pub fn validate(&self) -> Result<(), OrderBookError> {
+        if self.external_hours == 0 {
+            return Err(OrderBookError::InvalidPolicy("external leg needs a time lock".to_string()));
+        }
+        if self.idia_hours < self.external_hours + MIN_TIMELOCK_GAP_HOURS {
+            return Err(OrderBookError::InvalidPolicy(format!(
+                "Idia leg must outlast the external leg by {} hours",
+                MIN_TIMELOCK_GAP_HOURS
+            )));
+        }
+        Ok(())
+    }
+}
+
+// A maker selling IDIA for `chain`'s native asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapOffer {
+    pub maker: [u8; 32],
+    pub chain: String,
+    // Maker's address on `chain`, paid by the taker's lock
+    pub external_address: String,
+    pub min_amount: u64,
+    pub max_amount: u64,
+    pub rate: Rate,
+    pub fees: SwapFees,
+    pub timelocks: TimelockPolicy,
+    pub expires_at: u64,
+    pub nonce: u64,
+}
+
+impl SwapOffer {
+    pub fn fill_terms(&self, amount: u64) -> Result<FillTerms, OrderBookError> {
+        let quote = self
+            .rate
+            .quote_amount(amount)
+            .filter(|quote| *quote > 0)
+            .ok_or(OrderBookError::UnfillableAmount(amount))?;
+        let idia_lock = amount
+            .checked_add(self.fees.idia_claim)
+            .ok_or(OrderBookError::UnfillableAmount(amount))?;
+        let external_lock = quote
+            .checked_add(self.fees.external_claim)
+            .ok_or(OrderBookError::UnfillableAmount(amount))?;
+        Ok(FillTerms {
+            amount,
+            quote,
+            idia_lock,
+            external_lock,
+        })
+    }
+
+    // Offers are filled by any number of takes; the last may be smaller than
+    // `min_amount` if that is all that is left
+    fn check_fill(&self, offer_id: &str, amount: u64, filled: u64) -> Result<FillTerms, OrderBookError> {
+        if self.expires_at <= now() {
+            return Err(OrderBookError::OfferExpired(offer_id.to_string()));
+        }
+        let remaining = self.max_amount.saturating_sub(filled);
+        if remaining == 0 {
+            return Err(OrderBookError::OfferFilled(offer_id.to_string()));
+        }
+        let min = self.min_amount.min(remaining);
+        if amount < min || amount > remaining {
+            return Err(OrderBookError::AmountOutOfRange {
+                amount,
+                min,
+                max: remaining,
+            });
+        }
+        self.fill_terms(amount)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedOffer {
+    pub offer: SwapOffer,
+    pub signature: Vec<u8>,
+}
+
+impl SignedOffer {
+    pub fn id(&self) -> Result<String, OrderBookError> {
+        Ok(hex::encode(digest(b"idia-swap-offer", &self.offer)?))
+    }
+
+    pub fn verify(&self) -> bool {
+        verify(b"idia-swap-offer", &self.offer, &self.offer.maker, &self.signature)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TakeRequest {
+    pub offer_id: String,
+    pub taker: [u8; 32],
+    pub amount: u64,
+    // Where the taker receives IDIA, and funds the external lock from
+    pub idia_address: [u8; 32],
+    pub external_address: String,
+    pub nonce: u64,
+}
+
+// The maker's answer to a take: the terms both legs will be locked with.
+// `filled` is the offer's running total, so every node can track what is
+// left of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TakeAcceptance {
+    pub take_id: String,
+    pub offer_id: String,
+    pub terms: FillTerms,
+    pub filled: u64,
+    pub hash_lock: [u8; 32],
+    pub idia_time_lock: u64,
+    pub external_time_lock: u64,
+    pub refund_address: [u8; 32],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderMessage {
+    Offer(SignedOffer),
+    Cancel { offer_id: String, signature: Vec<u8> },
+    Take { take: TakeRequest, signature: Vec<u8> },
+    Accept { acceptance: TakeAcceptance, signature: Vec<u8> },
+    Reject { take_id: String, reason: String, signature: Vec<u8> },
+}
+
+impl OrderMessage {
+    pub fn encode(&self) -> Result<Vec<u8>, OrderBookError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, OrderBookError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NegotiationState {
+    // Take sent or received, no answer yet
+    Proposed,
+    // Terms agreed and the swap handed off
+    Initiated,
+    Rejected,
+}
+
+#[derive(Debug, Clone)]
+pub struct Negotiation {
+    pub take_id: String,
+    pub offer_id: String,
+    pub take: TakeRequest,
+    pub state: NegotiationState,
+    pub is_maker: bool,
+}
+
+// What handling a gossiped message asks of the caller
+pub enum OrderAction {
+    // Broadcast this reply on the order topic
+    Reply(OrderMessage),
+    // Terms are agreed; drive the swap, and broadcast the reply if any
+    Start { swap: CrossChainSwap, reply: Option<OrderMessage> },
+}
+
+// Local view of the swap-orders topic: open offers from everyone, plus the
+// negotiations this node takes part in, as maker or taker
+pub struct OrderBook {
+    key: Scalar,
+    idia_address: [u8; 32],
+    offers: RwLock<HashMap<String, SignedOffer>>,
+    // Amount filled so far, by offer
+    filled: RwLock<HashMap<String, u64>>,
+    negotiations: RwLock<HashMap<String, Negotiation>>,
+}
+
+impl OrderBook {
+    pub fn new(key: Scalar, idia_address: [u8; 32]) -> Self {
+        Self {
+            key,
+            idia_address,
+            offers: RwLock::new(HashMap::new()),
+            filled: RwLock::new(HashMap::new()),
+            negotiations: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        public_key(&self.key)
+    }
+
+    // Open, unexpired offers for `chain` with the amount left on each,
+    // cheapest first
+    pub async fn offers(&self, chain: &str) -> Vec<(String, SignedOffer, u64)> {
+        let now = now();
+        let filled = self.filled.read().await;
+        let mut offers: Vec<_> = self
+            .offers
+            .read()
+            .await
+            .iter()
+            .filter(|(_, o)| o.offer.chain == chain && o.offer.expires_at > now)
+            .map(|(id, o)| {
+                let remaining = o.offer.max_amount.saturating_sub(filled.get(id).copied().unwrap_or(0));
+                (id.clone(), o.clone(), remaining)
+            })
+            .filter(|(_, _, remaining)| *remaining > 0)
+            .collect();
+        offers.sort_by(|(_, a, _), (_, b, _)| {
+            let (a, b) = (a.offer.rate, b.offer.rate);
+            (a.quote as u128 * b.base as u128).cmp(&(b.quote as u128 * a.base as u128))
+        });
+        offers
+    }
+
+    pub async fn negotiation(&self, take_id: &str) -> Option<Negotiation> {
+        self.negotiations.read().await.get(take_id).cloned()
+    }
+
+    pub async fn post_offer(
+        &self,
+        chain: &str,
+        external_address: &str,
+        min_amount: u64,
+        max_amount: u64,
+        rate: Rate,
+        fees: SwapFees,
+        timelocks: TimelockPolicy,
+        ttl_secs: u64,
+    ) -> Result<OrderMessage, OrderBookError> {
+        timelocks.validate()?;
+        if min_amount == 0 || min_amount > max_amount {
+            return Err(OrderBookError::InvalidOffer("empty amount range"));
+        }
+        if rate.quote == 0 || rate.base == 0 {
+            return Err(OrderBookError::InvalidOffer("zero rate"));
+        }
+        let offer = SwapOffer {
+            maker: self.public_key(),
+            chain: chain.to_string(),
+            external_address: external_address.to_string(),
+            min_amount,
+            max_amount,
+            rate,
+            fees,
+            timelocks,
+            expires_at: now() + ttl_secs,
+            nonce: rand::random(),
+        };
+        let signed = SignedOffer {
+            signature: sign(b"idia-swap-offer", &offer, &self.key)?,
+            offer,
+        };
+        // The smallest fill must still be worth something after fees
+        signed.offer.fill_terms(min_amount)?;
+        self.offers.write().await.insert(signed.id()?, signed.clone());
+        Ok(OrderMessage::Offer(signed))
+    }
+
+    pub async fn cancel_offer(&self, offer_id: &str) -> Result<OrderMessage, OrderBookError> {
+        let mut offers = self.offers.write().await;
+        match offers.get(offer_id) {
+            Some(offer) if offer.offer.maker == self.public_key() => {}
+            _ => return Err(OrderBookError::UnknownOffer(offer_id.to_string())),
+        }
+        offers.remove(offer_id);
+        Ok(OrderMessage::Cancel {
+            offer_id: offer_id.to_string(),
+            signature: sign(b"idia-swap-cancel", &offer_id, &self.key)?,
+        })
+    }
+
+    pub async fn take(&self, offer_id: &str, amount: u64, external_address: &str) -> Result<OrderMessage, OrderBookError> {
+        let offer = self
+            .offers
+            .read()
+            .await
+            .get(offer_id)
+            .cloned()
+            .ok_or_else(|| OrderBookError::UnknownOffer(offer_id.to_string()))?;
+        offer.offer.check_fill(offer_id, amount, self.filled_of(offer_id).await)?;
+
+        let take = TakeRequest {
+            offer_id: offer_id.to_string(),
+            taker: self.public_key(),
+            amount,
+            idia_address: self.idia_address,
+            external_address: external_address.to_string(),
+            nonce: rand::random(),
+        };
+        let take_id = hex::encode(digest(b"idia-swap-take", &take)?);
+        self.negotiations.write().await.insert(
+            take_id.clone(),
+            Negotiation {
+                take_id,
+                offer_id: offer_id.to_string(),
+                take: take.clone(),
+                state: NegotiationState::Proposed,
+                is_maker: false,
+            },
+        );
+        Ok(OrderMessage::Take {
+            signature: sign(b"idia-swap-take", &take, &self.key)?,
+            take,
+        })
+    }
+
+    // Applies a message from the topic. Offers and cancels update the book;
+    // takes of our offers and answers to our takes advance negotiations.
+    pub async fn handle(&self, message: OrderMessage) -> Result<Option<OrderAction>, OrderBookError> {
+        match message {
+            OrderMessage::Offer(offer) => {
+                if !offer.verify() {
+                    return Err(OrderBookError::InvalidSignature("offer"));
+                }
+                offer.offer.timelocks.validate()?;
+                if offer.offer.expires_at > now() {
+                    self.offers.write().await.insert(offer.id()?, offer);
+                }
+                Ok(None)
+            }
+            OrderMessage::Cancel { offer_id, signature } => {
+                let mut offers = self.offers.write().await;
+                if let Some(offer) = offers.get(&offer_id) {
+                    if !verify(b"idia-swap-cancel", &offer_id, &offer.offer.maker, &signature) {
+                        return Err(OrderBookError::InvalidSignature("cancel"));
+                    }
+                    offers.remove(&offer_id);
+                    self.filled.write().await.remove(&offer_id);
+                }
+                Ok(None)
+            }
+            OrderMessage::Take { take, signature } => self.handle_take(take, signature).await,
+            OrderMessage::Accept { acceptance, signature } => self.handle_accept(acceptance, signature).await,
+            OrderMessage::Reject { take_id, reason, signature } => {
+                let mut negotiations = self.negotiations.write().await;
+                let Some(negotiation) = negotiations.get_mut(&take_id).filter(|n| !n.is_maker) else {
+                    return Ok(None);
+                };
+                let maker = self.offers.read().await.get(&negotiation.offer_id).map(|o| o.offer.maker);
+                if !maker.is_some_and(|maker| verify(b"idia-swap-reject", &(&take_id, &reason), &maker, &signature)) {
+                    return Err(OrderBookError::InvalidSignature("reject"));
+                }
+                tracing::info!("Take {} rejected: {}", take_id, reason);
+                negotiation.state = NegotiationState::Rejected;
+                Ok(None)
+            }
+        }
+    }
+
+    // Maker side: accept a valid take of one of our offers and start the swap
+    // as its initiator, or turn it down
+    async fn handle_take(&self, take: TakeRequest, signature: Vec<u8>) -> Result<Option<OrderAction>, OrderBookError> {
+        let Some(offer) = self.offers.read().await.get(&take.offer_id).cloned() else {
+            return Ok(None);
+        };
+        if offer.offer.maker != self.public_key() {
+            return Ok(None);
+        }
+        if !verify(b"idia-swap-take", &take, &take.taker, &signature) {
+            return Err(OrderBookError::InvalidSignature("take"));
+        }
+        let take_id = hex::encode(digest(b"idia-swap-take", &take)?);
+        if self.negotiations.read().await.contains_key(&take_id) {
+            return Ok(None);
+        }
+
+        // Checked and counted under one lock, so concurrent takes cannot
+        // overfill the offer
+        let mut filled = self.filled.write().await;
+        let already = filled.get(&take.offer_id).copied().unwrap_or(0);
+        let terms = match offer.offer.check_fill(&take.offer_id, take.amount, already) {
+            Ok(terms) => terms,
+            Err(e) => {
+                drop(filled);
+                let reason = e.to_string();
+                let signature = sign(b"idia-swap-reject", &(&take_id, &reason), &self.key)?;
+                self.record(take_id.clone(), take, NegotiationState::Rejected, true).await;
+                return Ok(Some(OrderAction::Reply(OrderMessage::Reject { take_id, reason, signature })));
+            }
+        };
+        filled.insert(take.offer_id.clone(), already + terms.amount);
+        drop(filled);
+
+        // Each fill is its own swap with its own hash lock
+        let policy = offer.offer.timelocks;
+        let mut swap = CrossChainSwap::initiate(terms.idia_lock, &offer.offer.chain, &take.external_address).await?;
+        let now = now();
+        swap.idia_swap.recipient_address = take.idia_address;
+        swap.idia_swap.refund_address = self.idia_address;
+        swap.idia_swap.time_lock = now + policy.idia_hours * 3600;
+        swap.external_swap.time_lock = now + policy.external_hours * 3600;
+        swap.external_swap.amount = terms.external_lock;
+
+        let acceptance = TakeAcceptance {
+            take_id: take_id.clone(),
+            offer_id: take.offer_id.clone(),
+            terms,
+            filled: already + terms.amount,
+            hash_lock: swap.idia_swap.hash_lock,
+            idia_time_lock: swap.idia_swap.time_lock,
+            external_time_lock: swap.external_swap.time_lock,
+            refund_address: self.idia_address,
+        };
+        let signature = sign(b"idia-swap-accept", &acceptance, &self.key)?;
+        self.record(take_id.clone(), take, NegotiationState::Initiated, true).await;
+        tracing::info!(
+            "Accepted take {} for {} of offer {}, swap {} initiated",
+            take_id,
+            terms.amount,
+            acceptance.offer_id,
+            swap.id()
+        );
+        Ok(Some(OrderAction::Start {
+            swap,
+            reply: Some(OrderMessage::Accept { acceptance, signature }),
+        }))
+    }
+
+    // Every node counts the fill against the offer; the taker also checks
+    // the maker's terms against it and joins the swap
+    async fn handle_accept(&self, acceptance: TakeAcceptance, signature: Vec<u8>) -> Result<Option<OrderAction>, OrderBookError> {
+        let Some(offer) = self.offers.read().await.get(&acceptance.offer_id).cloned() else {
+            return Ok(None);
+        };
+        if !verify(b"idia-swap-accept", &acceptance, &offer.offer.maker, &signature) {
+            return Err(OrderBookError::InvalidSignature("accept"));
+        }
+        {
+            let mut filled = self.filled.write().await;
+            let total = filled.entry(acceptance.offer_id.clone()).or_default();
+            *total = (*total).max(acceptance.filled);
+        }
+
+        let Some(negotiation) = self.negotiation(&acceptance.take_id).await.filter(|n| !n.is_maker) else {
+            return Ok(None);
+        };
+        if negotiation.state != NegotiationState::Proposed {
+            return Err(OrderBookError::UnexpectedState {
+                take: acceptance.take_id,
+                state: negotiation.state,
+            });
+        }
+        if negotiation.offer_id != acceptance.offer_id
+            || acceptance.terms != offer.offer.fill_terms(negotiation.take.amount)?
+        {
+            return Err(OrderBookError::TermsMismatch);
+        }
+
+        // The legs must be locked the way the offer promised
+        let policy = offer.offer.timelocks;
+        let now = now();
+        let within = |time_lock: u64, hours: u64| time_lock.abs_diff(now + hours * 3600) <= TIMELOCK_SLACK_SECS;
+        if !within(acceptance.idia_time_lock, policy.idia_hours)
+            || !within(acceptance.external_time_lock, policy.external_hours)
+        {
+            return Err(OrderBookError::InvalidPolicy("time locks differ from the offer".to_string()));
+        }
+
+        let idia_swap = AtomicSwap {
+            hash_lock: acceptance.hash_lock,
+            time_lock: acceptance.idia_time_lock,
+            amount: acceptance.terms.idia_lock,
+            recipient_address: negotiation.take.idia_address,
+            refund_address: acceptance.refund_address,
+        };
+        let mut swap = CrossChainSwap::join(
+            idia_swap,
+            &offer.offer.chain,
+            &offer.offer.external_address,
+            acceptance.external_time_lock,
+        );
+        swap.external_swap.amount = acceptance.terms.external_lock;
+        self.record(acceptance.take_id.clone(), negotiation.take, NegotiationState::Initiated, false).await;
+        Ok(Some(OrderAction::Start { swap, reply: None }))
+    }
+
+    async fn filled_of(&self, offer_id: &str) -> u64 {
+        self.filled.read().await.get(offer_id).copied().unwrap_or(0)
+    }
+
+    async fn record(&self, take_id: String, take: TakeRequest, state: NegotiationState, is_maker: bool) {
+        self.negotiations.write().await.insert(
+            take_id.clone(),
+            Negotiation {
+                take_id,
+                offer_id: take.offer_id.clone(),
+                take,
+                state,
+                is_maker,
+            },
+        );
+    }
+}
+
+// Sends a message on the swap-orders topic, such as one returned by
+// `post_offer`, `cancel_offer` or `take`
+pub async fn broadcast(network: &mpsc::Sender<NetworkCommand>, message: &OrderMessage) -> Result<(), OrderBookError> {
+    network
+        .send(NetworkCommand::BroadcastSwapOrder(message.encode()?))
+        .await
+        .map_err(|_| OrderBookError::NetworkDown)
+}
+
+// Applies gossiped order messages to `book`, broadcasting its replies and
+// handing agreed swaps to `swaps`. `inbound` is the handler registered for
+// the swap-orders topic with `Node::with_gossip`.
+pub async fn serve_gossip(
+    book: Arc<OrderBook>,
+    mut inbound: mpsc::Receiver<Vec<u8>>,
+    network: mpsc::Sender<NetworkCommand>,
+    swaps: mpsc::Sender<CrossChainSwap>,
+) {
+    while let Some(payload) = inbound.recv().await {
+        let action = match OrderMessage::decode(&payload) {
+            Ok(message) => book.handle(message).await,
+            Err(e) => Err(e),
+        };
+        let reply = match action {
+            Ok(None) => None,
+            Ok(Some(OrderAction::Reply(reply))) => Some(reply),
+            Ok(Some(OrderAction::Start { swap, reply })) => {
+                if swaps.send(swap).await.is_err() {
+                    tracing::error!("Swap driver is down, dropping an agreed swap");
+                }
+                reply
+            }
+            Err(e) => {
+                tracing::debug!("Ignoring order message: {}", e);
+                None
+            }
+        };
+        if let Some(reply) = reply {
+            if let Err(e) = broadcast(&network, &reply).await {
+                tracing::warn!("Failed to broadcast order reply: {}", e);
+            }
+        }
+    }
+}
+
+// Gossip validation for the swap-orders topic: only well-formed messages
+// with good signatures on their own body propagate. Whether a take or answer
+// fits an offer is left to the parties involved.
+pub struct OrderMessageValidator;
+
+impl GossipValidator for OrderMessageValidator {
+    fn validate(&self, payload: &[u8]) -> GossipAcceptance {
+        let Ok(message) = OrderMessage::decode(payload) else {
+            return GossipAcceptance::Reject;
+        };
+        let valid = match &message {
+            OrderMessage::Offer(offer) => {
+                offer.verify() && offer.offer.timelocks.validate().is_ok() && offer.offer.expires_at > now()
+            }
+            OrderMessage::Take { take, signature } => verify(b"idia-swap-take", take, &take.taker, signature),
+            // Signed by the offer's maker, which only holders of the offer can check
+            OrderMessage::Cancel { .. } | OrderMessage::Accept { .. } | OrderMessage::Reject { .. } => true,
+        };
+        if valid {
+            GossipAcceptance::Accept
+        } else {
+            GossipAcceptance::Reject
+        }
+    }
+}
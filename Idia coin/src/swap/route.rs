@@ -0,0 +1,295 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::atomic::{SwapError, SwapRole, SwapState};
+use super::orderbook::Rate;
+use super::store::{SwapLedger, SwapRecord};
+
+pub const MAX_HOPS: usize = 3;
+// Each leg must expire at least this long after the one it pays for, so a
+// hop that learns the preimage downstream can still claim upstream
+const MIN_STEP_HOURS: u64 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RouteError {
+    #[error("No route from {from} to {to} for {amount}")]
+    NoRoute { from: String, to: String, amount: u64 },
+    #[error("Time lock step of {0} hours is below the minimum")]
+    StepTooShort(u64),
+    #[error("No ledger configured for chain {0}")]
+    NoLedger(String),
+    #[error("Swap error: {0}")]
+    Swap(#[from] SwapError),
+}
+
+// A counterparty quote to convert `from` into `to`: they claim the leg
+// locked to them on `from` and fund the next leg on `to`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteHop {
+    pub counterparty: String,
+    pub from: String,
+    pub to: String,
+    pub rate: Rate,
+    // Claim fee of the leg they fund, added on top of what it carries
+    pub claim_fee: u64,
+    pub min_amount: u64,
+    pub max_amount: u64,
+    // Where they receive on `from`
+    pub receive_address: String,
+}
+
+impl RouteHop {
+    // What the next leg pays its receiver for `amount` in, before the claim
+    // fee the hop adds on top
+    fn convert(&self, amount: u64) -> Option<u64> {
+        if amount < self.min_amount || amount > self.max_amount {
+            return None;
+        }
+        self.rate.quote_amount(amount).filter(|out| *out > 0)
+    }
+}
+
+// Best output for `amount` of `from`, over paths of at most `MAX_HOPS`
+// counterparties that visit no chain twice
+pub fn find_route(hops: &[RouteHop], from: &str, to: &str, amount: u64) -> Option<(Vec<RouteHop>, u64)> {
+    fn search<'a>(
+        hops: &'a [RouteHop],
+        at: &str,
+        to: &str,
+        amount: u64,
+        path: &mut Vec<&'a RouteHop>,
+        best: &mut Option<(Vec<RouteHop>, u64)>,
+    ) {
+        if at == to && !path.is_empty() {
+            if best.as_ref().map_or(true, |(_, out)| amount > *out) {
+                *best = Some((path.iter().map(|hop| (*hop).clone()).collect(), amount));
+            }
+            return;
+        }
+        if path.len() == MAX_HOPS {
+            return;
+        }
+        for hop in hops.iter().filter(|hop| hop.from == at) {
+            let revisits = path.iter().any(|h| h.from == hop.to);
+            let Some(out) = hop.convert(amount).filter(|_| !revisits) else {
+                continue;
+            };
+            path.push(hop);
+            search(hops, &hop.to, to, out, path, best);
+            path.pop();
+        }
+    }
+
+    let mut best = None;
+    search(hops, from, to, amount, &mut Vec::new(), &mut best);
+    best
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegState {
+    Pending,
+    Locked,
+    Claimed,
+    Refunded,
+}
+
+// One lock along the route. Leg 0 is ours on the source chain; the last
+// leg pays us on the destination chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLeg {
+    pub chain: String,
+    // Carried by the lock, claim fee included
+    pub amount: u64,
+    pub time_lock: u64,
+    pub receiver: String,
+    pub lock_tx: Option<String>,
+    pub state: LegState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteState {
+    // Waiting for every leg to be locked, in order
+    Locking,
+    Completed,
+    // A leg was not locked in time; the preimage is never revealed and our
+    // leg is refunded once it expires
+    Unwinding,
+    Unwound,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteProgress {
+    Waiting,
+    LegLocked(usize),
+    Claimed(String),
+    Unwinding,
+    Refunded(String),
+}
+
+// A swap through intermediate counterparties, e.g. IDIA→BTC→ETH. Every leg
+// is locked to one hash; time locks shrink by `step_hours` along the route.
+// We lock first with the longest, and claim the last leg only once all legs
+// are locked, which reveals the preimage for each hop to claim upstream in
+// turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiHopSwap {
+    pub hash_lock: [u8; 32],
+    preimage: [u8; 32],
+    pub legs: Vec<RouteLeg>,
+    pub state: RouteState,
+    // Legs still unlocked after this unwind the route
+    pub lock_deadline: u64,
+}
+
+impl MultiHopSwap {
+    pub fn plan(
+        route: &[RouteHop],
+        amount: u64,
+        our_address: &str,
+        final_hours: u64,
+        step_hours: u64,
+    ) -> Result<Self, RouteError> {
+        if step_hours < MIN_STEP_HOURS {
+            return Err(RouteError::StepTooShort(step_hours));
+        }
+        let no_route = || RouteError::NoRoute {
+            from: route.first().map(|h| h.from.clone()).unwrap_or_default(),
+            to: route.last().map(|h| h.to.clone()).unwrap_or_default(),
+            amount,
+        };
+        if route.is_empty() || route.len() > MAX_HOPS {
+            return Err(no_route());
+        }
+
+        let mut preimage = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut preimage);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let time_lock = |leg: usize| now + (final_hours + (route.len() - leg) as u64 * step_hours) * 3600;
+
+        let mut legs = Vec::with_capacity(route.len() + 1);
+        let mut carried = amount;
+        for (index, hop) in route.iter().enumerate() {
+            legs.push(RouteLeg {
+                chain: hop.from.clone(),
+                amount: carried,
+                time_lock: time_lock(index),
+                receiver: hop.receive_address.clone(),
+                lock_tx: None,
+                state: LegState::Pending,
+            });
+            let out = hop.convert(carried).ok_or_else(no_route)?;
+            carried = out.checked_add(hop.claim_fee).ok_or_else(no_route)?;
+        }
+        let last = route.last().expect("route is not empty");
+        legs.push(RouteLeg {
+            chain: last.to.clone(),
+            amount: carried,
+            time_lock: time_lock(route.len()),
+            receiver: our_address.to_string(),
+            lock_tx: None,
+            state: LegState::Pending,
+        });
+
+        Ok(Self {
+            hash_lock: Sha256::digest(preimage).into(),
+            preimage,
+            lock_deadline: time_lock(route.len()) - step_hours * 3600,
+            legs,
+            state: RouteState::Locking,
+        })
+    }
+
+    // The view of one leg the chain ledgers work from
+    fn leg_record(&self, index: usize) -> SwapRecord {
+        let leg = &self.legs[index];
+        let id = hex::encode(self.hash_lock);
+        SwapRecord {
+            id: format!("{}-{}", id, index),
+            state: SwapState::ExternalLocked,
+            role: if index == 0 { SwapRole::Initiator } else { SwapRole::Participant },
+            amount: leg.amount,
+            hash_lock: self.hash_lock,
+            idia_time_lock: leg.time_lock,
+            recipient_address: [0u8; 32],
+            refund_address: [0u8; 32],
+            external_chain: leg.chain.clone(),
+            counterparty_address: leg.receiver.clone(),
+            external_amount: leg.amount,
+            external_time_lock: leg.time_lock,
+            bitcoin_script: None,
+            sealed_preimage: None,
+            idia_lock_tx: None,
+            external_lock_tx: leg.lock_tx.clone(),
+            settle_tx: None,
+            idia_claim_tx: None,
+            external_claim_tx: None,
+            updated_at: 0,
+        }
+    }
+
+    // Drives the route one step against each leg's chain
+    pub async fn advance(&mut self, ledgers: &HashMap<String, Arc<dyn SwapLedger>>) -> Result<RouteProgress, RouteError> {
+        let ledger = |chain: &str| {
+            ledgers
+                .get(chain)
+                .cloned()
+                .ok_or_else(|| RouteError::NoLedger(chain.to_string()))
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        match self.state {
+            RouteState::Locking => {
+                // Each hop only locks after seeing the leg before it
+                if let Some(index) = self.legs.iter().position(|leg| leg.state == LegState::Pending) {
+                    if now > self.lock_deadline {
+                        tracing::warn!("Route {:02x?} not locked in time, unwinding", &self.hash_lock[..4]);
+                        self.state = RouteState::Unwinding;
+                        return Ok(RouteProgress::Unwinding);
+                    }
+                    let record = self.leg_record(index);
+                    let Some(txid) = ledger(&self.legs[index].chain)?.find_lock(&record).await? else {
+                        return Ok(RouteProgress::Waiting);
+                    };
+                    self.legs[index].lock_tx = Some(txid);
+                    self.legs[index].state = LegState::Locked;
+                    return Ok(RouteProgress::LegLocked(index));
+                }
+
+                let last = self.legs.len() - 1;
+                let record = self.leg_record(last);
+                let txid = ledger(&self.legs[last].chain)?.claim(&record, self.preimage).await?;
+                self.legs[last].state = LegState::Claimed;
+                self.state = RouteState::Completed;
+                Ok(RouteProgress::Claimed(txid))
+            }
+            RouteState::Unwinding => {
+                let first = &self.legs[0];
+                if first.state != LegState::Locked {
+                    // Nothing of ours was ever locked
+                    self.state = RouteState::Unwound;
+                    return Ok(RouteProgress::Unwinding);
+                }
+                if now <= first.time_lock {
+                    return Ok(RouteProgress::Waiting);
+                }
+                let txid = ledger(&first.chain)?.refund(&self.leg_record(0)).await?;
+                self.legs[0].state = LegState::Refunded;
+                self.state = RouteState::Unwound;
+                Ok(RouteProgress::Refunded(txid))
+            }
+            RouteState::Completed | RouteState::Unwound => Ok(RouteProgress::Waiting),
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use async_trait::async_trait;
+use bitcoin::ScriptBuf;
+use idia_core::KeyStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+
+use super::atomic::{AtomicSwap, CrossChainSwap, ExternalChainSwap, SwapError, SwapRole, SwapState};
+use super::btc::BitcoinHtlc;
+use super::monitor::SwapLeg;
+
+// Everything needed to pick a swap back up after a restart. The preimage is
+// the only secret and is sealed with the wallet keystore; timelocks and txids
+// are public on chain anyway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapRecord {
+    pub id: String,
+    pub state: SwapState,
+    #[serde(default)]
+    pub role: SwapRole,
+    pub amount: u64,
+    pub hash_lock: [u8; 32],
+    pub idia_time_lock: u64,
+    pub recipient_address: [u8; 32],
+    pub refund_address: [u8; 32],
+    pub external_chain: String,
+    pub counterparty_address: String,
+    #[serde(default)]
+    pub external_amount: u64,
+    pub external_time_lock: u64,
+    // Witness script of the Bitcoin HTLC, re-parsed on load
+    pub bitcoin_script: Option<Vec<u8>>,
+    pub sealed_preimage: Option<Vec<u8>>,
+    pub idia_lock_tx: Option<String>,
+    pub external_lock_tx: Option<String>,
+    pub settle_tx: Option<String>,
+    // Claims seen on chain by the swap monitor, ours or the counterparty's
+    #[serde(default)]
+    pub idia_claim_tx: Option<String>,
+    #[serde(default)]
+    pub external_claim_tx: Option<String>,
+    pub updated_at: u64,
+}
+
+impl SwapRecord {
+    pub fn is_pending(&self) -> bool {
+        !matches!(self.state, SwapState::Completed | SwapState::Refunded)
+    }
+
+    pub fn lock_tx(&self, leg: SwapLeg) -> Option<&String> {
+        match leg {
+            SwapLeg::Idia => self.idia_lock_tx.as_ref(),
+            SwapLeg::External => self.external_lock_tx.as_ref(),
+        }
+    }
+
+    pub fn claim_tx(&self, leg: SwapLeg) -> Option<&String> {
+        match leg {
+            SwapLeg::Idia => self.idia_claim_tx.as_ref(),
+            SwapLeg::External => self.external_claim_tx.as_ref(),
+        }
+    }
+}
+
+// One chain's view of a swap leg, as far as resuming needs it
+#[async_trait]
+pub trait SwapLedger: Send + Sync {
+    async fn is_confirmed(&self, txid: &str) -> Result<bool, SwapError>;
+    // Spends the leg locked to us with the preimage, returning the txid
+    async fn claim(&self, swap: &SwapRecord, preimage: [u8; 32]) -> Result<String, SwapError>;
+    // Spends our own expired leg back to us, returning the txid
+    async fn refund(&self, swap: &SwapRecord) -> Result<String, SwapError>;
+    // Transaction locking funds to the swap's hash lock on this chain
+    async fn find_lock(&self, swap: &SwapRecord) -> Result<Option<String>, SwapError>;
+    // Transaction claiming that lock, with the preimage it revealed
+    async fn find_claim(&self, swap: &SwapRecord) -> Result<Option<(String, [u8; 32])>, SwapError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResumeAction {
+    Waiting,
+    Advanced(SwapState),
+    Claimed(String),
+    Refunded(String),
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SwapStoreState {
+    swaps: BTreeMap<String, SwapRecord>,
+}
+
+pub struct SwapStore {
+    path: PathBuf,
+    keystore: Arc<KeyStore>,
+    state: RwLock<SwapStoreState>,
+    idia_ledger: Option<Arc<dyn SwapLedger>>,
+    external_ledgers: HashMap<String, Arc<dyn SwapLedger>>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+impl SwapStore {
+    pub async fn open(path: PathBuf, keystore: Arc<KeyStore>) -> Result<Self, SwapError> {
+        let state = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SwapStoreState::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            keystore,
+            state: RwLock::new(state),
+            idia_ledger: None,
+            external_ledgers: HashMap::new(),
+        })
+    }
+
+    pub fn with_idia_ledger(mut self, ledger: Arc<dyn SwapLedger>) -> Self {
+        self.idia_ledger = Some(ledger);
+        self
+    }
+
+    pub fn with_external_ledger(mut self, chain: &str, ledger: Arc<dyn SwapLedger>) -> Self {
+        self.external_ledgers.insert(chain.to_string(), ledger);
+        self
+    }
+
+    // Call after every state change, before acting on it on chain
+    pub async fn save(&self, swap: &CrossChainSwap) -> Result<(), SwapError> {
+        let sealed_preimage = swap.preimage.map(|preimage| self.seal(preimage)).transpose()?;
+        // Claims are only ever observed, so carry over what the monitor saw
+        let (idia_claim_tx, external_claim_tx) = match self.state.read().await.swaps.get(&swap.id()) {
+            Some(record) => (record.idia_claim_tx.clone(), record.external_claim_tx.clone()),
+            None => (None, None),
+        };
+        let record = SwapRecord {
+            id: swap.id(),
+            state: swap.state,
+            role: swap.role,
+            amount: swap.idia_swap.amount,
+            hash_lock: swap.idia_swap.hash_lock,
+            idia_time_lock: swap.idia_swap.time_lock,
+            recipient_address: swap.idia_swap.recipient_address,
+            refund_address: swap.idia_swap.refund_address,
+            external_chain: swap.external_swap.chain.clone(),
+            counterparty_address: swap.external_swap.counterparty_address.clone(),
+            external_amount: swap.external_swap.amount,
+            external_time_lock: swap.external_swap.time_lock,
+            bitcoin_script: swap
+                .external_swap
+                .bitcoin
+                .as_ref()
+                .map(|htlc| htlc.witness_script().to_bytes()),
+            sealed_preimage,
+            idia_lock_tx: swap.idia_lock_tx.clone(),
+            external_lock_tx: swap.external_lock_tx.clone(),
+            settle_tx: swap.settle_tx.clone(),
+            idia_claim_tx,
+            external_claim_tx,
+            updated_at: now(),
+        };
+
+        self.update(record).await
+    }
+
+    pub async fn update(&self, mut record: SwapRecord) -> Result<(), SwapError> {
+        record.updated_at = now();
+        let mut state = self.state.write().await;
+        state.swaps.insert(record.id.clone(), record);
+        self.persist(&state).await
+    }
+
+    pub fn seal(&self, preimage: [u8; 32]) -> Result<Vec<u8>, SwapError> {
+        Ok(self.keystore.encrypt(&preimage)?)
+    }
+
+    // Idia and external ledgers a swap settles on
+    pub fn ledgers(&self, swap: &SwapRecord) -> Result<(Arc<dyn SwapLedger>, Arc<dyn SwapLedger>), SwapError> {
+        let idia = self
+            .idia_ledger
+            .clone()
+            .ok_or_else(|| SwapError::NoLedger("idia".to_string()))?;
+        let external = self
+            .external_ledgers
+            .get(&swap.external_chain)
+            .cloned()
+            .ok_or_else(|| SwapError::NoLedger(swap.external_chain.clone()))?;
+        Ok((idia, external))
+    }
+
+    pub async fn load(&self, id: &str) -> Result<Option<CrossChainSwap>, SwapError> {
+        match self.state.read().await.swaps.get(id) {
+            Some(record) => self.restore(record).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn pending(&self) -> Vec<SwapRecord> {
+        self.state
+            .read()
+            .await
+            .swaps
+            .values()
+            .filter(|record| record.is_pending())
+            .cloned()
+            .collect()
+    }
+
+    fn restore(&self, record: &SwapRecord) -> Result<CrossChainSwap, SwapError> {
+        let preimage = self.unseal(record)?;
+        let bitcoin = record
+            .bitcoin_script
+            .as_ref()
+            .map(|script| BitcoinHtlc::parse(&ScriptBuf::from_bytes(script.clone())))
+            .transpose()?;
+        Ok(CrossChainSwap {
+            idia_swap: AtomicSwap {
+                hash_lock: record.hash_lock,
+                time_lock: record.idia_time_lock,
+                amount: record.amount,
+                recipient_address: record.recipient_address,
+                refund_address: record.refund_address,
+            },
+            external_swap: ExternalChainSwap {
+                chain: record.external_chain.clone(),
+                counterparty_address: record.counterparty_address.clone(),
+                amount: record.external_amount,
+                hash_lock: record.hash_lock,
+                time_lock: record.external_time_lock,
+                bitcoin,
+            },
+            state: record.state,
+            role: record.role,
+            preimage,
+            idia_lock_tx: record.idia_lock_tx.clone(),
+            external_lock_tx: record.external_lock_tx.clone(),
+            settle_tx: record.settle_tx.clone(),
+        })
+    }
+
+    pub fn unseal(&self, record: &SwapRecord) -> Result<Option<[u8; 32]>, SwapError> {
+        let Some(sealed) = &record.sealed_preimage else {
+            return Ok(None);
+        };
+        let preimage: [u8; 32] = self
+            .keystore
+            .decrypt(sealed)?
+            .try_into()
+            .map_err(|_| SwapError::InvalidSecret)?;
+        Ok(Some(preimage))
+    }
+
+    // Re-checks every unfinished swap against its chains after a restart and
+    // takes the step it was waiting for: advance once a lock confirms, claim
+    // the counterparty's leg while it is still open, refund our leg once it
+    // has expired. Swaps whose chains are unreachable are left for the next
+    // run.
+    pub async fn resume_pending_swaps(&self) -> Vec<(String, Result<ResumeAction, SwapError>)> {
+        let mut results = Vec::new();
+        for record in self.pending().await {
+            let id = record.id.clone();
+            let result = self.resume_swap(record).await;
+            match &result {
+                Ok(ResumeAction::Waiting) => {}
+                Ok(action) => tracing::info!("Swap {} resumed: {:?}", id, action),
+                Err(e) => tracing::warn!("Swap {} could not be resumed: {}", id, e),
+            }
+            results.push((id, result));
+        }
+        results
+    }
+
+    pub async fn resume_swap(&self, mut record: SwapRecord) -> Result<ResumeAction, SwapError> {
+        let now = now();
+        let (idia, external) = self.ledgers(&record)?;
+
+        let action = match record.state {
+            // Crashed between broadcasting our lock and recording it
+            SwapState::Initialized => match &record.idia_lock_tx {
+                Some(txid) if idia.is_confirmed(txid).await? => {
+                    record.state = SwapState::IdiaLocked;
+                    ResumeAction::Advanced(SwapState::IdiaLocked)
+                }
+                _ => ResumeAction::Waiting,
+            },
+            SwapState::IdiaLocked => match &record.external_lock_tx {
+                Some(txid) if external.is_confirmed(txid).await? => {
+                    record.state = SwapState::ExternalLocked;
+                    ResumeAction::Advanced(SwapState::ExternalLocked)
+                }
+                // The participant has nothing locked yet; the Idia leg is
+                // the initiator's to refund
+                _ if record.role == SwapRole::Initiator && now > record.idia_time_lock => {
+                    let txid = idia.refund(&record).await?;
+                    record.state = SwapState::Refunded;
+                    record.settle_tx = Some(txid.clone());
+                    ResumeAction::Refunded(txid)
+                }
+                _ => ResumeAction::Waiting,
+            },
+            SwapState::ExternalLocked if record.role == SwapRole::Participant => match self.unseal(&record)? {
+                // Preimage learned from the initiator's claim of our leg
+                Some(preimage) if now <= record.idia_time_lock => {
+                    let txid = idia.claim(&record, preimage).await?;
+                    record.state = SwapState::Completed;
+                    record.settle_tx = Some(txid.clone());
+                    ResumeAction::Claimed(txid)
+                }
+                None if now > record.external_time_lock => {
+                    let txid = external.refund(&record).await?;
+                    record.state = SwapState::Refunded;
+                    record.settle_tx = Some(txid.clone());
+                    ResumeAction::Refunded(txid)
+                }
+                _ => ResumeAction::Waiting,
+            },
+            SwapState::ExternalLocked => match self.unseal(&record)? {
+                // The external leg expires first, so claiming is only safe
+                // while it is still open
+                Some(preimage) if now <= record.external_time_lock => {
+                    let txid = external.claim(&record, preimage).await?;
+                    record.state = SwapState::Completed;
+                    record.settle_tx = Some(txid.clone());
+                    ResumeAction::Claimed(txid)
+                }
+                _ if now > record.idia_time_lock => {
+                    let txid = idia.refund(&record).await?;
+                    record.state = SwapState::Refunded;
+                    record.settle_tx = Some(txid.clone());
+                    ResumeAction::Refunded(txid)
+                }
+                _ => ResumeAction::Waiting,
+            },
+            SwapState::Completed | SwapState::Refunded => ResumeAction::Waiting,
+        };
+
+        if action != ResumeAction::Waiting {
+            self.update(record).await?;
+        }
+        Ok(action)
+    }
+
+    async fn persist(&self, state: &SwapStoreState) -> Result<(), SwapError> {
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(state)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use idia_core::{Bip340Signature, SchnorrSignature};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::atomic::{ScriptlessSwap, SwapError, SwapRole};
+
+// Bitcoin side of a scriptless swap, where the initiator claims our taproot
+// lock. Since the lock is a plain key-path output, the source is asked about
+// spends of that specific output.
+#[async_trait]
+pub trait ExternalClaims: Send + Sync {
+    // The joint signature from the initiator's published claim, once it is
+    // on chain; `extract_key_path_signature` reads it off the spend
+    async fn published_claim(&self, swap: &ScriptlessSwap) -> Result<Option<Bip340Signature>, SwapError>;
+}
+
+// Idia side of a scriptless swap, where we claim the initiator's lock
+#[async_trait]
+pub trait IdiaClaims: Send + Sync {
+    // Broadcasts our claim of the joint-key lock with the completed signature
+    async fn claim(&self, swap: &ScriptlessSwap, signature: SchnorrSignature) -> Result<String, SwapError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdaptorClaim {
+    // Compressed adaptor point, shared by both legs
+    pub id: String,
+    pub claim_tx: String,
+}
+
+// Completes scriptless swaps for the participant. The initiator's claim of
+// our Bitcoin lock carries the joint signature adapted from the claim's
+// pre-signature; the difference is the adaptor secret, which in turn
+// completes the pre-signature on our Idia claim. No secret is ever entered
+// by hand.
+pub struct AdaptorClaimWatcher {
+    external: Arc<dyn ExternalClaims>,
+    idia: Arc<dyn IdiaClaims>,
+    swaps: Mutex<Vec<ScriptlessSwap>>,
+}
+
+fn swap_id(swap: &ScriptlessSwap) -> String {
+    hex::encode(swap.adaptor_point.compress().as_bytes())
+}
+
+impl AdaptorClaimWatcher {
+    pub fn new(external: Arc<dyn ExternalClaims>, idia: Arc<dyn IdiaClaims>) -> Self {
+        Self {
+            external,
+            idia,
+            swaps: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Only a participant learns the secret from a claim; the initiator
+    // already holds it
+    pub async fn watch(&self, swap: ScriptlessSwap) -> Result<(), SwapError> {
+        if swap.role != SwapRole::Participant {
+            return Err(SwapError::WrongRole(swap.role));
+        }
+        if !swap.ready_to_lock() {
+            return Err(SwapError::MissingSignature("claim"));
+        }
+        self.swaps.lock().await.push(swap);
+        Ok(())
+    }
+
+    pub async fn watching(&self) -> usize {
+        self.swaps.lock().await.len()
+    }
+
+    // Swaps claimed this pass leave the watch list; expired ones stay so the
+    // refund path can take them back with `take_expired`
+    pub async fn tick(&self) -> Vec<AdaptorClaim> {
+        let mut swaps = self.swaps.lock().await;
+        let mut claimed = Vec::new();
+        let mut index = 0;
+        while index < swaps.len() {
+            let id = swap_id(&swaps[index]);
+            match self.try_claim(&mut swaps[index]).await {
+                Ok(Some(claim_tx)) => {
+                    tracing::info!("Scriptless swap {} claimed on Idia in {}", id, claim_tx);
+                    swaps.swap_remove(index);
+                    claimed.push(AdaptorClaim { id, claim_tx });
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Scriptless swap {} claim failed: {}", id, e),
+            }
+            index += 1;
+        }
+        claimed
+    }
+
+    async fn try_claim(&self, swap: &mut ScriptlessSwap) -> Result<Option<String>, SwapError> {
+        if swap.secret().is_none() {
+            let Some(published) = self.external.published_claim(swap).await? else {
+                return Ok(None);
+            };
+            swap.learn_secret(&published)?;
+            tracing::info!("Scriptless swap {} adaptor secret extracted", swap_id(swap));
+        }
+        let signature = swap.idia_claim_signature()?;
+        self.idia.claim(swap, signature).await.map(Some)
+    }
+
+    pub async fn take_expired(&self) -> Vec<ScriptlessSwap> {
+        let mut swaps = self.swaps.lock().await;
+        let (expired, live) = swaps.drain(..).partition(|swap: &ScriptlessSwap| swap.is_expired() && swap.secret().is_none());
+        *swaps = live;
+        expired
+    }
+
+    pub fn spawn(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                self.tick().await;
+            }
+        })
+    }
+}
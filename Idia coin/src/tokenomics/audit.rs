@@ -0,0 +1,181 @@
+use super::economics::TokenEconomics;
+
+pub trait ChainView {
+    fn tip_height(&self) -> u64;
+    fn emission_at(&self, height: u64) -> u64;
+    fn burned_at(&self, height: u64) -> u64;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupplyDiscrepancy {
+    TotalSupply { expected: u64, tracked: u64 },
+    Emitted { expected: u64, tracked: u64 },
+    Burned { expected: u64, tracked: u64 },
+    // Everything minted must still be in supply or provably destroyed
+    Unbalanced { minted: u64, supply: u64, burned: u64, slashed: u64 },
+    ExceedsMaxSupply { supply: u64 },
+    CirculatingExceedsTotal { circulating: u64, total: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct SupplyAudit {
+    pub height: u64,
+    pub expected_supply: u64,
+    pub tracked_supply: u64,
+    pub discrepancies: Vec<SupplyDiscrepancy>,
+}
+
+impl SupplyAudit {
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+impl TokenEconomics {
+    pub fn audit(&self, chain: &dyn ChainView) -> SupplyAudit {
+        let height = chain.tip_height();
+
+        // Recompute issuance and burns from the chain itself
+        let (emitted, burned) = (0..=height).fold((0u64, 0u64), |(emitted, burned), h| {
+            (emitted + chain.emission_at(h), burned + chain.burned_at(h))
+        });
+        let mut discrepancies = self.check_invariants();
+
+        // Slashes never appear on chain, so the tracked total stands in for them
+        let minted = Self::INITIAL_SUPPLY + emitted;
+        let expected_supply = minted.checked_sub(burned + self.total_slashed).unwrap_or_else(|| {
+            discrepancies.push(SupplyDiscrepancy::Unbalanced {
+                minted,
+                supply: self.total_supply,
+                burned,
+                slashed: self.total_slashed,
+            });
+            0
+        });
+        if emitted != self.total_emitted {
+            discrepancies.push(SupplyDiscrepancy::Emitted {
+                expected: emitted,
+                tracked: self.total_emitted,
+            });
+        }
+        if burned != self.total_burned {
+            discrepancies.push(SupplyDiscrepancy::Burned {
+                expected: burned,
+                tracked: self.total_burned,
+            });
+        }
+        if expected_supply != self.total_supply {
+            discrepancies.push(SupplyDiscrepancy::TotalSupply {
+                expected: expected_supply,
+                tracked: self.total_supply,
+            });
+        }
+        discrepancies.dedup();
+
+        SupplyAudit {
+            height,
+            expected_supply,
+            tracked_supply: self.total_supply,
+            discrepancies,
+        }
+    }
+
+    // Checks that only need the tracked counters, cheap enough to run on every block
+    pub fn check_invariants(&self) -> Vec<SupplyDiscrepancy> {
+        let mut discrepancies = Vec::new();
+
+        let minted = Self::INITIAL_SUPPLY + self.total_emitted;
+        let accounted = self.total_supply as u128 + self.total_burned as u128 + self.total_slashed as u128;
+        if minted as u128 != accounted {
+            discrepancies.push(SupplyDiscrepancy::Unbalanced {
+                minted,
+                supply: self.total_supply,
+                burned: self.total_burned,
+                slashed: self.total_slashed,
+            });
+        }
+        if self.total_supply > Self::MAX_SUPPLY {
+            discrepancies.push(SupplyDiscrepancy::ExceedsMaxSupply {
+                supply: self.total_supply,
+            });
+        }
+        if self.circulating_supply > self.total_supply {
+            discrepancies.push(SupplyDiscrepancy::CirculatingExceedsTotal {
+                circulating: self.circulating_supply,
+                total: self.total_supply,
+            });
+        }
+
+        discrepancies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use idia_core::NetworkType;
+
+    struct Chain {
+        emissions: Vec<u64>,
+        burns: Vec<u64>,
+    }
+
+    impl ChainView for Chain {
+        fn tip_height(&self) -> u64 {
+            self.emissions.len() as u64 - 1
+        }
+
+        fn emission_at(&self, height: u64) -> u64 {
+            self.emissions[height as usize]
+        }
+
+        fn burned_at(&self, height: u64) -> u64 {
+            self.burns[height as usize]
+        }
+    }
+
+    #[test]
+    fn test_slash_then_audit() {
+        let mut economics = TokenEconomics::new().with_invariant_checks(true);
+        economics.on_block_connected(1000, 500, 0);
+        economics.staking_pool.stake("validator".to_string(), 10_000, 30).unwrap();
+
+        let slashed = economics.slash_staker("validator", 0.5).unwrap();
+        assert_eq!(slashed, 5_000);
+        economics.on_block_connected(1000, 500, 100);
+
+        let chain = Chain {
+            emissions: vec![500, 500],
+            burns: vec![0, 100],
+        };
+        let audit = economics.audit(&chain);
+        assert!(audit.is_consistent(), "{:?}", audit.discrepancies);
+        assert_eq!(audit.expected_supply, TokenEconomics::INITIAL_SUPPLY + 1000 - 100 - slashed);
+        assert_eq!(economics.total_burned, 100);
+    }
+
+    #[test]
+    fn test_checks_follow_network() {
+        assert!(TokenEconomics::for_network(NetworkType::Testnet).invariant_checks);
+        assert!(!TokenEconomics::for_network(NetworkType::Mainnet).invariant_checks);
+    }
+
+    #[test]
+    fn test_untracked_burn_unbalances() {
+        let mut economics = TokenEconomics::new();
+        economics.on_block_connected(1000, 500, 200);
+        assert!(economics.check_invariants().is_empty());
+
+        // Supply destroyed without a burn record no longer balances against minted
+        economics.total_supply -= 50;
+        assert_eq!(
+            economics.check_invariants(),
+            vec![SupplyDiscrepancy::Unbalanced {
+                minted: TokenEconomics::INITIAL_SUPPLY + 500,
+                supply: TokenEconomics::INITIAL_SUPPLY + 250,
+                burned: 200,
+                slashed: 0,
+            }]
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::economics::{proportional_share, StakingError, StakingPool};
+
+#[derive(Serialize, Deserialize)]
+pub struct Operator {
+    pub address: String,
+    pub commission: f64,
+    pub total_delegated: u64,
+    pub accumulated_commission: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Delegation {
+    pub id: u64,
+    pub delegator: String,
+    pub operator: String,
+    pub amount: u64,
+    pub accumulated_rewards: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UnbondingEntry {
+    pub delegation_id: u64,
+    pub delegator: String,
+    pub operator: String,
+    pub amount: u64,
+    pub rewards: u64,
+    pub release_time: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DelegationBook {
+    pub operators: HashMap<String, Operator>,
+    pub delegations: HashMap<String, Vec<Delegation>>,
+    pub unbonding: Vec<UnbondingEntry>,
+    pub unbonding_period_days: u64,
+    pub max_commission: f64,
+}
+
+impl DelegationBook {
+    pub fn new() -> Self {
+        Self {
+            operators: HashMap::new(),
+            delegations: HashMap::new(),
+            unbonding: Vec::new(),
+            unbonding_period_days: 21, // 21-day unbonding period
+            max_commission: 0.25,      // Operators may keep at most 25% of rewards
+        }
+    }
+
+    pub fn total_delegated(&self) -> u64 {
+        self.operators.values().map(|o| o.total_delegated).sum()
+    }
+}
+
+impl StakingPool {
+    pub fn register_operator(&mut self, address: String, commission: f64) -> Result<(), StakingError> {
+        if !(0.0..=self.delegation.max_commission).contains(&commission) {
+            return Err(StakingError::InvalidCommission);
+        }
+        if self.delegation.operators.contains_key(&address) {
+            return Err(StakingError::OperatorExists);
+        }
+
+        self.delegation.operators.insert(address.clone(), Operator {
+            address,
+            commission,
+            total_delegated: 0,
+            accumulated_commission: 0,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_commission(&mut self, operator: &str, commission: f64) -> Result<(), StakingError> {
+        if !(0.0..=self.delegation.max_commission).contains(&commission) {
+            return Err(StakingError::InvalidCommission);
+        }
+
+        let operator = self.delegation.operators.get_mut(operator)
+            .ok_or(StakingError::OperatorNotFound)?;
+        operator.commission = commission;
+
+        Ok(())
+    }
+
+    pub fn delegate(
+        &mut self,
+        delegator: String,
+        operator: &str,
+        amount: u64,
+    ) -> Result<u64, StakingError> {
+        if amount == 0 {
+            return Err(StakingError::InsufficientStake);
+        }
+
+        let operator_info = self.delegation.operators.get_mut(operator)
+            .ok_or(StakingError::OperatorNotFound)?;
+        operator_info.total_delegated += amount;
+
+        let delegation_id = self.next_id();
+        self.delegation.delegations.entry(delegator.clone()).or_default().push(Delegation {
+            id: delegation_id,
+            delegator,
+            operator: operator.to_string(),
+            amount,
+            accumulated_rewards: 0,
+        });
+        self.total_staked += amount;
+
+        Ok(delegation_id)
+    }
+
+    pub fn undelegate(
+        &mut self,
+        delegator: &str,
+        delegation_id: u64,
+    ) -> Result<DateTime<Utc>, StakingError> {
+        let delegations = self.delegation.delegations.get_mut(delegator)
+            .ok_or(StakingError::StakeNotFound)?;
+        let index = delegations.iter()
+            .position(|d| d.id == delegation_id)
+            .ok_or(StakingError::StakeNotFound)?;
+
+        let delegation = delegations.remove(index);
+        if delegations.is_empty() {
+            self.delegation.delegations.remove(delegator);
+        }
+
+        if let Some(operator) = self.delegation.operators.get_mut(&delegation.operator) {
+            operator.total_delegated -= delegation.amount;
+        }
+        // Unbonding stake stops earning rewards immediately
+        self.total_staked -= delegation.amount;
+
+        let release_time = Utc::now() + Duration::days(self.delegation.unbonding_period_days as i64);
+        self.delegation.unbonding.push(UnbondingEntry {
+            delegation_id,
+            delegator: delegation.delegator,
+            operator: delegation.operator,
+            amount: delegation.amount,
+            rewards: delegation.accumulated_rewards,
+            release_time,
+        });
+
+        Ok(release_time)
+    }
+
+    pub fn complete_unbonding(&mut self, now: DateTime<Utc>) -> Vec<UnbondingEntry> {
+        let (released, pending) = self.delegation.unbonding
+            .drain(..)
+            .partition(|entry| entry.release_time <= now);
+        self.delegation.unbonding = pending;
+        released
+    }
+
+    pub fn delegations_of(&self, delegator: &str) -> &[Delegation] {
+        self.delegation.delegations.get(delegator).map_or(&[], |d| d.as_slice())
+    }
+
+    // Stake still unbonding from the operator is slashed too, so leaving
+    // just ahead of a slash does not escape it
+    pub(crate) fn slash_delegations(&mut self, operator: &str, fraction: f64) -> u64 {
+        let mut bonded = 0;
+        for delegation in self.delegation.delegations.values_mut().flatten() {
+            if delegation.operator == operator {
+                let amount = (delegation.amount as f64 * fraction) as u64;
+                delegation.amount -= amount;
+                bonded += amount;
+            }
+        }
+        if let Some(operator) = self.delegation.operators.get_mut(operator) {
+            operator.total_delegated -= bonded;
+        }
+        self.total_staked -= bonded;
+
+        let mut unbonding = 0;
+        for entry in self.delegation.unbonding.iter_mut().filter(|e| e.operator == operator) {
+            let amount = (entry.amount as f64 * fraction) as u64;
+            entry.amount -= amount;
+            unbonding += amount;
+        }
+        bonded + unbonding
+    }
+
+    pub(crate) fn distribute_delegation_rewards(&mut self, amount: u64, total_staked: u64) -> u64 {
+        let book = &mut self.delegation;
+        let mut distributed = 0;
+        for delegation in book.delegations.values_mut().flatten() {
+            let share = proportional_share(amount, delegation.amount, total_staked);
+
+            // Operator commission is taken before the delegator's share
+            let commission = match book.operators.get_mut(&delegation.operator) {
+                Some(operator) => {
+                    let commission = (share as f64 * operator.commission) as u64;
+                    operator.accumulated_commission += commission;
+                    commission
+                }
+                None => 0,
+            };
+            delegation.accumulated_rewards += share - commission;
+            distributed += share;
+        }
+        distributed
+    }
+
+    pub(crate) fn claim_delegation_rewards(&mut self, delegator: &str, compound: bool) -> u64 {
+        let Some(delegations) = self.delegation.delegations.get_mut(delegator) else {
+            return 0;
+        };
+
+        let mut claimed = 0;
+        for delegation in delegations.iter_mut() {
+            let rewards = std::mem::take(&mut delegation.accumulated_rewards);
+            if compound {
+                delegation.amount += rewards;
+                if let Some(operator) = self.delegation.operators.get_mut(&delegation.operator) {
+                    operator.total_delegated += rewards;
+                }
+                self.total_staked += rewards;
+            }
+            claimed += rewards;
+        }
+        claimed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slash_reaches_delegators() {
+        let mut pool = StakingPool::new();
+        pool.stake("operator".to_string(), 10_000, 30).unwrap();
+        pool.register_operator("operator".to_string(), 0.1).unwrap();
+        pool.register_operator("other".to_string(), 0.1).unwrap();
+        pool.delegate("alice".to_string(), "operator", 4_000).unwrap();
+        let leaving = pool.delegate("bob".to_string(), "operator", 2_000).unwrap();
+        pool.delegate("carol".to_string(), "other", 5_000).unwrap();
+        pool.undelegate("bob", leaving).unwrap();
+
+        let slashed = pool.slash("operator", 0.5).unwrap();
+        assert_eq!(slashed, 5_000 + 2_000 + 1_000);
+        assert_eq!(pool.stakes_of("operator")[0].amount, 5_000);
+        assert_eq!(pool.delegations_of("alice")[0].amount, 2_000);
+        assert_eq!(pool.delegation.unbonding[0].amount, 1_000);
+        assert_eq!(pool.delegation.operators["operator"].total_delegated, 2_000);
+        assert_eq!(pool.total_staked, 5_000 + 2_000 + 5_000);
+
+        // Delegators of other operators are untouched
+        assert_eq!(pool.delegations_of("carol")[0].amount, 5_000);
+    }
+
+    #[test]
+    fn test_slash_operator_without_own_stake() {
+        let mut pool = StakingPool::new();
+        pool.register_operator("operator".to_string(), 0.1).unwrap();
+        pool.delegate("alice".to_string(), "operator", 1_000).unwrap();
+
+        assert_eq!(pool.slash("operator", 0.1).unwrap(), 100);
+        assert_eq!(pool.delegations_of("alice")[0].amount, 900);
+        assert!(matches!(pool.slash("nobody", 0.1), Err(StakingError::StakeNotFound)));
+    }
+}
@@ -1,47 +1,146 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use idia_core::explorer::{Explorer, ExplorerError, IssuanceTotals, TreasuryActivity};
+use idia_core::NetworkType;
 
+// The mempool orders by the same key, so both sides agree on what gets mined next
+pub use idia_node::mempool::FeePriority;
+
+use super::delegation::DelegationBook;
+use super::vesting::VestingLedger;
+
+#[derive(Serialize, Deserialize)]
 pub struct TokenEconomics {
     pub total_supply: u64,
     pub circulating_supply: u64,
+    pub total_emitted: u64,
+    pub total_burned: u64,
+    // Slashed stake leaves supply off-chain, so the chain's burn outputs never show it
+    #[serde(default)]
+    pub total_slashed: u64,
     pub staking_pool: StakingPool,
     pub treasury: Treasury,
     pub fee_mechanism: FeeMechanism,
+    #[serde(skip)]
+    pub invariant_checks: bool,
 }
 
 impl TokenEconomics {
     pub const INITIAL_SUPPLY: u64 = 100_000_000; // 100 million tokens
     pub const MAX_SUPPLY: u64 = 200_000_000;     // 200 million tokens
     pub const EMISSION_RATE: f64 = 0.02;         // 2% annual inflation
-    pub const BURN_RATE: f64 = 0.03;            // 3% of transaction fees
     
     pub fn new() -> Self {
         Self {
             total_supply: Self::INITIAL_SUPPLY,
             circulating_supply: 0,
+            total_emitted: 0,
+            total_burned: 0,
+            total_slashed: 0,
             staking_pool: StakingPool::new(),
             treasury: Treasury::new(),
             fee_mechanism: FeeMechanism::new(),
+            invariant_checks: false,
         }
     }
 
+    // Regtest and testnet nodes enable this to fail fast on any supply accounting bug
+    pub fn with_invariant_checks(mut self, enabled: bool) -> Self {
+        self.invariant_checks = enabled;
+        self
+    }
+
+    // Regtest runs under the testnet network type, so both get the checks
+    pub fn for_network(network: NetworkType) -> Self {
+        Self::new().with_invariant_checks(matches!(network, NetworkType::Testnet))
+    }
+
     pub fn calculate_emission(&self) -> u64 {
         let annual_emission = (self.total_supply as f64 * Self::EMISSION_RATE) as u64;
         annual_emission / (365 * 24 * 60 * 60) // Per second emission
     }
 
-    pub fn process_transaction_fee(&mut self, transaction_amount: u64) -> u64 {
-        let fee = self.fee_mechanism.calculate_fee(transaction_amount);
-        let burn_amount = (fee as f64 * Self::BURN_RATE) as u64;
+    pub fn process_transaction_fee(
+        &mut self,
+        tx_size: u64,
+        max_fee_per_byte: u64,
+        max_priority_fee_per_byte: u64,
+    ) -> Result<FeeBreakdown, FeeError> {
+        let fee = self.fee_mechanism.calculate_fee(tx_size, max_fee_per_byte, max_priority_fee_per_byte)?;
         
-        // Burn portion of fees
-        self.total_supply -= burn_amount;
-        self.circulating_supply -= burn_amount;
+        // The base fee portion must be destroyed by a burn output in the block;
+        // supply only changes when that output is connected. Only the tip is distributed
+        self.distribute_fees(fee.tip);
         
-        // Distribute remaining fees
-        self.distribute_fees(fee - burn_amount);
-        
-        fee
+        Ok(fee)
+    }
+
+    pub fn on_block_connected(&mut self, block_size: u64, emitted: u64, burned: u64) {
+        self.fee_mechanism.update_base_fee(block_size);
+        self.record_emission(emitted);
+        self.record_burn(burned);
+
+        if self.invariant_checks {
+            let discrepancies = self.check_invariants();
+            assert!(discrepancies.is_empty(), "supply invariant violated: {:?}", discrepancies);
+        }
+    }
+
+    pub fn record_emission(&mut self, amount: u64) {
+        self.total_supply += amount;
+        self.circulating_supply += amount;
+        self.total_emitted += amount;
+    }
+
+    pub fn record_burn(&mut self, amount: u64) {
+        self.total_supply -= amount;
+        self.circulating_supply = self.circulating_supply.saturating_sub(amount);
+        self.total_burned += amount;
+    }
+
+    pub fn record_slash(&mut self, amount: u64) {
+        self.total_supply -= amount;
+        self.circulating_supply = self.circulating_supply.saturating_sub(amount);
+        self.total_slashed += amount;
+    }
+
+    pub fn unstake(
+        &mut self,
+        address: &str,
+        stake_id: u64,
+        early_exit: bool,
+    ) -> Result<UnstakeResult, StakingError> {
+        let result = self.staking_pool.unstake(address, stake_id, early_exit)?;
+
+        // Early-exit penalties are forfeited to the treasury
+        self.treasury.add_funds(result.penalty);
+
+        Ok(result)
+    }
+
+    // Call after each connected block so the explorer shows the full supply and treasury activity
+    pub async fn publish_to_explorer(&mut self, height: u64, explorer: &Explorer) -> Result<(), ExplorerError> {
+        for event in self.treasury.drain_events() {
+            explorer.record_treasury_activity(height, event.into()).await?;
+        }
+        explorer
+            .record_issuance(IssuanceTotals {
+                initial_supply: Self::INITIAL_SUPPLY,
+                total_emitted: self.total_emitted,
+                total_slashed: self.total_slashed,
+            })
+            .await
+    }
+
+    pub fn slash_staker(&mut self, address: &str, fraction: f64) -> Result<u64, StakingError> {
+        let slashed = self.staking_pool.slash(address, fraction)?;
+
+        // Slashed stake is destroyed, but tracked apart from on-chain burns
+        self.record_slash(slashed);
+
+        Ok(slashed)
     }
 
     fn distribute_fees(&mut self, fee_amount: u64) {
@@ -59,21 +158,65 @@ impl TokenEconomics {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct StakingPool {
     pub total_staked: u64,
-    pub stakers: HashMap<String, StakeInfo>,
+    pub stakers: HashMap<String, Vec<StakeInfo>>,
     pub annual_return: f64,
     pub minimum_stake: u64,
     pub lock_periods: Vec<LockPeriod>,
+    pub early_exit_penalty: f64,
+    pub delegation: DelegationBook,
+    pub current_epoch: u64,
+    pub pending_rewards: u64,
+    next_stake_id: u64,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct StakeInfo {
+    pub id: u64,
     pub amount: u64,
     pub start_time: DateTime<Utc>,
     pub lock_period: u64,
     pub accumulated_rewards: u64,
 }
 
+impl StakeInfo {
+    pub fn unlock_time(&self) -> DateTime<Utc> {
+        self.start_time + Duration::days(self.lock_period as i64)
+    }
+
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        now < self.unlock_time()
+    }
+}
+
+pub struct UnstakeResult {
+    pub stake_id: u64,
+    pub amount: u64,
+    pub penalty: u64,
+    pub rewards: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StakingError {
+    #[error("Stake below minimum")]
+    InsufficientStake,
+    #[error("Stake not found")]
+    StakeNotFound,
+    #[error("Stake locked until {0}")]
+    StakeLocked(DateTime<Utc>),
+    #[error("Invalid slash fraction")]
+    InvalidSlashFraction,
+    #[error("Commission outside allowed range")]
+    InvalidCommission,
+    #[error("Operator already registered")]
+    OperatorExists,
+    #[error("Operator not found")]
+    OperatorNotFound,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct LockPeriod {
     pub duration_days: u64,
     pub bonus_multiplier: f64,
@@ -104,25 +247,105 @@ impl StakingPool {
                     bonus_multiplier: 3.0,
                 },
             ],
+            early_exit_penalty: 0.1, // 10% of principal forfeited on early exit
+            delegation: DelegationBook::new(),
+            current_epoch: 0,
+            pending_rewards: 0,
+            next_stake_id: 1,
         }
     }
 
-    pub fn stake(&mut self, address: String, amount: u64, lock_period: u64) -> Result<(), StakingError> {
+    pub fn stake(&mut self, address: String, amount: u64, lock_period: u64) -> Result<u64, StakingError> {
         if amount < self.minimum_stake {
             return Err(StakingError::InsufficientStake);
         }
 
+        let stake_id = self.next_id();
+
         let stake_info = StakeInfo {
+            id: stake_id,
             amount,
             start_time: Utc::now(),
             lock_period,
             accumulated_rewards: 0,
         };
 
-        self.stakers.insert(address, stake_info);
+        self.stakers.entry(address).or_default().push(stake_info);
         self.total_staked += amount;
         
-        Ok(())
+        Ok(stake_id)
+    }
+
+    pub fn unstake(
+        &mut self,
+        address: &str,
+        stake_id: u64,
+        early_exit: bool,
+    ) -> Result<UnstakeResult, StakingError> {
+        let stakes = self.stakers.get_mut(address)
+            .ok_or(StakingError::StakeNotFound)?;
+        let index = stakes.iter()
+            .position(|s| s.id == stake_id)
+            .ok_or(StakingError::StakeNotFound)?;
+
+        // Lock enforcement: only an explicit early exit may break the lock
+        let stake_info = &stakes[index];
+        let penalty = if stake_info.is_locked(Utc::now()) {
+            if !early_exit {
+                return Err(StakingError::StakeLocked(stake_info.unlock_time()));
+            }
+            (stake_info.amount as f64 * self.early_exit_penalty) as u64
+        } else {
+            0
+        };
+
+        let stake_info = stakes.remove(index);
+        if stakes.is_empty() {
+            self.stakers.remove(address);
+        }
+        self.total_staked -= stake_info.amount;
+
+        Ok(UnstakeResult {
+            stake_id,
+            amount: stake_info.amount - penalty,
+            penalty,
+            rewards: stake_info.accumulated_rewards,
+        })
+    }
+
+    pub fn slash(&mut self, address: &str, fraction: f64) -> Result<u64, StakingError> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(StakingError::InvalidSlashFraction);
+        }
+
+        if !self.stakers.contains_key(address) && !self.delegation.operators.contains_key(address) {
+            return Err(StakingError::StakeNotFound);
+        }
+
+        // Slash every stake held by the misbehaving address
+        let mut slashed = 0;
+        for stake_info in self.stakers.get_mut(address).into_iter().flatten() {
+            let amount = (stake_info.amount as f64 * fraction) as u64;
+            stake_info.amount -= amount;
+            slashed += amount;
+        }
+        self.total_staked -= slashed;
+
+        // Delegators share their operator's risk at the same fraction
+        slashed += self.slash_delegations(address, fraction);
+
+        Ok(slashed)
+    }
+
+    pub fn stakes_of(&self, address: &str) -> &[StakeInfo] {
+        self.stakers.get(address).map_or(&[], |s| s.as_slice())
+    }
+
+    pub(crate) fn next_id(&mut self) -> u64 {
+        // Stakes and delegations share one ID space
+        let id = self.next_stake_id;
+        self.next_stake_id += 1;
+        id
     }
 
     pub fn calculate_rewards(&self, stake_info: &StakeInfo) -> u64 {
@@ -139,19 +362,203 @@ impl StakingPool {
     }
 
     pub fn add_rewards(&mut self, amount: u64) {
-        // Distribute rewards proportionally to stakers
+        // Rewards accrue to the current epoch and are distributed when it ends
+        self.pending_rewards += amount;
+    }
+
+    pub fn end_epoch(&mut self) -> u64 {
+        self.current_epoch += 1;
+
         let total_staked = self.total_staked;
-        for stake_info in self.stakers.values_mut() {
-            let share = (amount as f64 * stake_info.amount as f64 / total_staked as f64) as u64;
+        if total_staked == 0 {
+            return 0;
+        }
+
+        // Distribute proportionally using integer math so every node computes
+        // identical shares; rounding dust carries over to the next epoch
+        let amount = self.pending_rewards;
+        let mut distributed = 0;
+        for stake_info in self.stakers.values_mut().flatten() {
+            let share = proportional_share(amount, stake_info.amount, total_staked);
             stake_info.accumulated_rewards += share;
+            distributed += share;
+        }
+        distributed += self.distribute_delegation_rewards(amount, total_staked);
+
+        self.pending_rewards -= distributed;
+        distributed
+    }
+
+    pub fn claimable_rewards(&self, address: &str) -> u64 {
+        let staked: u64 = self.stakes_of(address).iter().map(|s| s.accumulated_rewards).sum();
+        let delegated: u64 = self.delegations_of(address).iter().map(|d| d.accumulated_rewards).sum();
+        staked + delegated
+    }
+
+    pub fn claim_rewards(&mut self, address: &str, compound: bool) -> Result<u64, StakingError> {
+        if !self.stakers.contains_key(address) && self.delegations_of(address).is_empty() {
+            return Err(StakingError::StakeNotFound);
+        }
+
+        let mut claimed = 0;
+        if let Some(stakes) = self.stakers.get_mut(address) {
+            for stake_info in stakes.iter_mut() {
+                let rewards = std::mem::take(&mut stake_info.accumulated_rewards);
+                if compound {
+                    stake_info.amount += rewards;
+                    self.total_staked += rewards;
+                }
+                claimed += rewards;
+            }
         }
+        claimed += self.claim_delegation_rewards(address, compound);
+
+        Ok(claimed)
     }
 }
 
+pub(crate) fn proportional_share(amount: u64, part: u64, total: u64) -> u64 {
+    (amount as u128 * part as u128 / total as u128) as u64
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Treasury {
     pub balance: u64,
     pub privacy_pool: u64,
+    #[serde(skip)]
     pub governance_proposals: Vec<GovernanceProposal>,
+    pub reserved: u64,
+    pub payouts: Vec<PayoutRecord>,
+    pub schedules: Vec<DisbursementSchedule>,
+    pub vesting: VestingLedger,
+    pub milestone_payouts: Vec<MilestonePayout>,
+    #[serde(skip)]
+    events: Vec<TreasuryEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PayoutMode {
+    Installments {
+        installments: u32,
+        interval_blocks: u64,
+    },
+    Vested {
+        cliff_blocks: u64,
+        duration_blocks: u64,
+    },
+    Milestones {
+        milestones: Vec<Milestone>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Milestone {
+    pub description: String,
+    pub amount: u64,
+}
+
+// Each tranche stays reserved until the committee attests the milestone was met
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MilestonePayout {
+    pub proposal_id: [u8; 32],
+    pub recipient: String,
+    pub purpose: String,
+    pub milestones: Vec<Milestone>,
+    pub released: Vec<bool>,
+}
+
+impl MilestonePayout {
+    pub fn outstanding(&self) -> u64 {
+        self.milestones
+            .iter()
+            .zip(&self.released)
+            .filter(|(_, released)| !**released)
+            .map(|(m, _)| m.amount)
+            .sum()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.released.iter().all(|r| *r)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutRecord {
+    pub proposal_id: [u8; 32],
+    pub recipient: String,
+    pub amount: u64,
+    pub purpose: String,
+    pub height: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisbursementSchedule {
+    pub proposal_id: [u8; 32],
+    pub recipient: String,
+    pub purpose: String,
+    pub total_amount: u64,
+    pub disbursed: u64,
+    pub remaining_installments: u32,
+    pub interval_blocks: u64,
+    pub next_height: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum TreasuryEvent {
+    SpendScheduled {
+        proposal_id: [u8; 32],
+        total_amount: u64,
+        installments: u32,
+    },
+    GrantCreated {
+        proposal_id: [u8; 32],
+        grant_id: u64,
+        total_amount: u64,
+    },
+    MilestonesReserved {
+        proposal_id: [u8; 32],
+        total_amount: u64,
+        milestones: usize,
+    },
+    Disbursed(PayoutRecord),
+}
+
+impl From<TreasuryEvent> for TreasuryActivity {
+    fn from(event: TreasuryEvent) -> Self {
+        match event {
+            TreasuryEvent::SpendScheduled { proposal_id, total_amount, installments } => {
+                TreasuryActivity::SpendScheduled { proposal_id, total_amount, installments }
+            }
+            TreasuryEvent::GrantCreated { proposal_id, grant_id, total_amount } => {
+                TreasuryActivity::GrantCreated { proposal_id, grant_id, total_amount }
+            }
+            TreasuryEvent::MilestonesReserved { proposal_id, total_amount, milestones } => {
+                TreasuryActivity::MilestonesReserved {
+                    proposal_id,
+                    total_amount,
+                    milestones: milestones as u64,
+                }
+            }
+            TreasuryEvent::Disbursed(record) => TreasuryActivity::Disbursed {
+                proposal_id: record.proposal_id,
+                recipient: record.recipient,
+                amount: record.amount,
+                purpose: record.purpose,
+            },
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TreasuryError {
+    #[error("Insufficient treasury funds: requested {requested}, available {available}")]
+    InsufficientFunds { requested: u64, available: u64 },
+    #[error("Invalid disbursement schedule")]
+    InvalidSchedule,
+    #[error("Unknown milestone {index} for proposal")]
+    UnknownMilestone { index: usize },
+    #[error("Milestone {index} already released")]
+    MilestoneAlreadyReleased { index: usize },
 }
 
 impl Treasury {
@@ -160,6 +567,12 @@ impl Treasury {
             balance: 0,
             privacy_pool: 0,
             governance_proposals: Vec::new(),
+            reserved: 0,
+            payouts: Vec::new(),
+            schedules: Vec::new(),
+            vesting: VestingLedger::new(),
+            milestone_payouts: Vec::new(),
+            events: Vec::new(),
         }
     }
 
@@ -167,36 +580,345 @@ impl Treasury {
         self.balance += amount;
     }
 
+    pub fn available(&self) -> u64 {
+        self.balance - self.reserved
+    }
+
+    pub fn spend(
+        &mut self,
+        proposal_id: [u8; 32],
+        amount: u64,
+        recipient: &str,
+        purpose: &str,
+        mode: &PayoutMode,
+        start_height: u64,
+    ) -> Result<(), TreasuryError> {
+        match mode {
+            PayoutMode::Installments { installments, interval_blocks } => self.schedule_spend(
+                proposal_id,
+                amount,
+                recipient,
+                purpose,
+                *installments,
+                *interval_blocks,
+                start_height,
+            ),
+            PayoutMode::Vested { cliff_blocks, duration_blocks } => self.create_vesting_grant(
+                proposal_id,
+                amount,
+                recipient,
+                purpose,
+                start_height,
+                *cliff_blocks,
+                *duration_blocks,
+            ).map(|_| ()),
+            PayoutMode::Milestones { milestones } => self.reserve_milestones(
+                proposal_id,
+                amount,
+                recipient,
+                purpose,
+                milestones,
+            ),
+        }
+    }
+
+    pub fn reserve_milestones(
+        &mut self,
+        proposal_id: [u8; 32],
+        amount: u64,
+        recipient: &str,
+        purpose: &str,
+        milestones: &[Milestone],
+    ) -> Result<(), TreasuryError> {
+        let total = milestones.iter().try_fold(0u64, |sum, m| {
+            (m.amount > 0).then_some(()).and_then(|_| sum.checked_add(m.amount))
+        });
+        if milestones.is_empty() || total != Some(amount) {
+            return Err(TreasuryError::InvalidSchedule);
+        }
+        self.reserve(amount)?;
+
+        self.milestone_payouts.push(MilestonePayout {
+            proposal_id,
+            recipient: recipient.to_string(),
+            purpose: purpose.to_string(),
+            milestones: milestones.to_vec(),
+            released: vec![false; milestones.len()],
+        });
+        self.events.push(TreasuryEvent::MilestonesReserved {
+            proposal_id,
+            total_amount: amount,
+            milestones: milestones.len(),
+        });
+        Ok(())
+    }
+
+    // Callers must have verified the committee's attestation for this milestone
+    pub fn release_milestone(
+        &mut self,
+        proposal_id: [u8; 32],
+        index: usize,
+        height: u64,
+    ) -> Result<PayoutRecord, TreasuryError> {
+        let payout = self.milestone_payouts
+            .iter_mut()
+            .find(|p| p.proposal_id == proposal_id)
+            .ok_or(TreasuryError::UnknownMilestone { index })?;
+        let milestone = payout.milestones.get(index)
+            .ok_or(TreasuryError::UnknownMilestone { index })?;
+        if payout.released[index] {
+            return Err(TreasuryError::MilestoneAlreadyReleased { index });
+        }
+        payout.released[index] = true;
+
+        let record = PayoutRecord {
+            proposal_id,
+            recipient: payout.recipient.clone(),
+            amount: milestone.amount,
+            purpose: format!("{}: {}", payout.purpose, milestone.description),
+            height,
+        };
+        self.milestone_payouts.retain(|p| !p.is_complete());
+
+        self.balance -= record.amount;
+        self.reserved -= record.amount;
+        self.payouts.push(record.clone());
+        self.events.push(TreasuryEvent::Disbursed(record.clone()));
+        Ok(record)
+    }
+
+    pub fn schedule_spend(
+        &mut self,
+        proposal_id: [u8; 32],
+        amount: u64,
+        recipient: &str,
+        purpose: &str,
+        installments: u32,
+        interval_blocks: u64,
+        start_height: u64,
+    ) -> Result<(), TreasuryError> {
+        if amount == 0 || installments == 0 || (installments > 1 && interval_blocks == 0) {
+            return Err(TreasuryError::InvalidSchedule);
+        }
+        self.reserve(amount)?;
+
+        self.schedules.push(DisbursementSchedule {
+            proposal_id,
+            recipient: recipient.to_string(),
+            purpose: purpose.to_string(),
+            total_amount: amount,
+            disbursed: 0,
+            remaining_installments: installments,
+            interval_blocks,
+            next_height: start_height,
+        });
+        self.events.push(TreasuryEvent::SpendScheduled {
+            proposal_id,
+            total_amount: amount,
+            installments,
+        });
+
+        self.process_disbursements(start_height);
+        Ok(())
+    }
+
+    pub fn create_vesting_grant(
+        &mut self,
+        proposal_id: [u8; 32],
+        amount: u64,
+        beneficiary: &str,
+        purpose: &str,
+        start_height: u64,
+        cliff_blocks: u64,
+        duration_blocks: u64,
+    ) -> Result<u64, TreasuryError> {
+        if amount == 0 || duration_blocks == 0 || cliff_blocks > duration_blocks {
+            return Err(TreasuryError::InvalidSchedule);
+        }
+        self.reserve(amount)?;
+
+        let grant_id = self.vesting.add_grant(
+            proposal_id,
+            beneficiary,
+            purpose,
+            amount,
+            start_height,
+            cliff_blocks,
+            duration_blocks,
+        );
+        self.events.push(TreasuryEvent::GrantCreated {
+            proposal_id,
+            grant_id,
+            total_amount: amount,
+        });
+
+        Ok(grant_id)
+    }
+
+    pub fn claim_vested(&mut self, beneficiary: &str, height: u64) -> u64 {
+        let mut claimed = 0;
+        for (grant, amount) in self.vesting.claim(beneficiary, height) {
+            let payout = PayoutRecord {
+                proposal_id: grant.proposal_id,
+                recipient: grant.beneficiary,
+                amount,
+                purpose: grant.purpose,
+                height,
+            };
+            self.balance -= amount;
+            self.reserved -= amount;
+            self.payouts.push(payout.clone());
+            self.events.push(TreasuryEvent::Disbursed(payout));
+            claimed += amount;
+        }
+        claimed
+    }
+
+    fn reserve(&mut self, amount: u64) -> Result<(), TreasuryError> {
+        if amount > self.available() {
+            return Err(TreasuryError::InsufficientFunds {
+                requested: amount,
+                available: self.available(),
+            });
+        }
+
+        // Reserve the full amount up front so later proposals can't overdraw it
+        self.reserved += amount;
+        Ok(())
+    }
+
+    pub fn process_disbursements(&mut self, height: u64) -> Vec<PayoutRecord> {
+        let mut released = Vec::new();
+
+        for schedule in self.schedules.iter_mut() {
+            while schedule.remaining_installments > 0 && schedule.next_height <= height {
+                // Splitting what remains means the final installment absorbs rounding dust
+                let remaining = schedule.total_amount - schedule.disbursed;
+                let amount = remaining / schedule.remaining_installments as u64;
+                schedule.disbursed += amount;
+                schedule.remaining_installments -= 1;
+                schedule.next_height += schedule.interval_blocks;
+
+                released.push(PayoutRecord {
+                    proposal_id: schedule.proposal_id,
+                    recipient: schedule.recipient.clone(),
+                    amount,
+                    purpose: schedule.purpose.clone(),
+                    height,
+                });
+            }
+        }
+        self.schedules.retain(|s| s.remaining_installments > 0);
+
+        for payout in &released {
+            self.balance -= payout.amount;
+            self.reserved -= payout.amount;
+            self.payouts.push(payout.clone());
+            self.events.push(TreasuryEvent::Disbursed(payout.clone()));
+        }
+
+        released
+    }
+
+    pub fn drain_events(&mut self) -> Vec<TreasuryEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     pub fn add_to_privacy_pool(&mut self, amount: u64) {
         self.privacy_pool += amount;
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct FeeMechanism {
     pub base_fee: u64,
-    pub privacy_multiplier: f64,
-    pub congestion_multiplier: f64,
+    pub min_base_fee: u64,
+    pub max_block_size: u64,
+    pub target_fullness_percent: u64,
+    pub adjustment_denominator: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeBreakdown {
+    pub burned: u64,
+    pub tip: u64,
+}
+
+impl FeeBreakdown {
+    pub fn total(&self) -> u64 {
+        self.burned + self.tip
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FeeError {
+    #[error("Max fee {max_fee} per byte below base fee {base_fee}")]
+    BelowBaseFee { max_fee: u64, base_fee: u64 },
+    #[error("Fee for {tx_size} bytes overflows")]
+    Overflow { tx_size: u64 },
 }
 
 impl FeeMechanism {
     pub fn new() -> Self {
         Self {
-            base_fee: 100,           // Base fee in smallest units
-            privacy_multiplier: 1.5,  // 50% premium for privacy features
-            congestion_multiplier: 1.0, // Dynamic based on network usage
+            base_fee: 100,                // Base fee per byte in smallest units
+            min_base_fee: 1,
+            max_block_size: 2_000_000,    // 2 MB blocks
+            target_fullness_percent: 50,  // Target half-full blocks
+            adjustment_denominator: 8,    // Max 12.5% change per block
         }
     }
 
-    pub fn calculate_fee(&self, amount: u64) -> u64 {
-        let base = self.base_fee;
-        let privacy_premium = (base as f64 * self.privacy_multiplier) as u64;
-        let congestion_fee = (privacy_premium as f64 * self.congestion_multiplier) as u64;
-        
-        congestion_fee
+    pub fn calculate_fee(
+        &self,
+        tx_size: u64,
+        max_fee_per_byte: u64,
+        max_priority_fee_per_byte: u64,
+    ) -> Result<FeeBreakdown, FeeError> {
+        let tip_per_byte = self.effective_tip(max_fee_per_byte, max_priority_fee_per_byte)
+            .ok_or(FeeError::BelowBaseFee {
+                max_fee: max_fee_per_byte,
+                base_fee: self.base_fee,
+            })?;
+
+        let overflow = || FeeError::Overflow { tx_size };
+        let burned = self.base_fee.checked_mul(tx_size).ok_or_else(overflow)?;
+        let tip = tip_per_byte.checked_mul(tx_size).ok_or_else(overflow)?;
+        // The sender pays both, so their sum has to fit as well
+        burned.checked_add(tip).ok_or_else(overflow)?;
+
+        Ok(FeeBreakdown { burned, tip })
+    }
+
+    pub fn effective_tip(&self, max_fee_per_byte: u64, max_priority_fee_per_byte: u64) -> Option<u64> {
+        self.priority(max_fee_per_byte, max_priority_fee_per_byte)
+            .map(|priority| priority.tip_per_byte)
+    }
+
+    pub fn priority(&self, max_fee_per_byte: u64, max_priority_fee_per_byte: u64) -> Option<FeePriority> {
+        // Transactions that can't cover the current base fee aren't eligible for the next block
+        FeePriority::new(self.base_fee, max_fee_per_byte, max_priority_fee_per_byte)
+    }
+
+    pub fn target_block_size(&self) -> u64 {
+        self.max_block_size * self.target_fullness_percent / 100
     }
 
-    pub fn update_congestion_multiplier(&mut self, network_load: f64) {
-        // Dynamic fee adjustment based on network load
-        self.congestion_multiplier = 1.0 + (network_load * 0.5); // Max 50% increase
+    pub fn update_base_fee(&mut self, block_size: u64) {
+        let target = self.target_block_size();
+        let block_size = block_size.min(self.max_block_size);
+
+        // Move the base fee proportionally to the distance from the target fullness
+        match block_size.cmp(&target) {
+            Ordering::Greater => {
+                let delta = self.base_fee * (block_size - target) / target / self.adjustment_denominator;
+                self.base_fee += delta.max(1);
+            }
+            Ordering::Less => {
+                let delta = self.base_fee * (target - block_size) / target / self.adjustment_denominator;
+                self.base_fee = self.base_fee.saturating_sub(delta).max(self.min_base_fee);
+            }
+            Ordering::Equal => {}
+        }
     }
 }
\ No newline at end of file
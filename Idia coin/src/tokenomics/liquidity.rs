@@ -1,5 +1,5 @@
-use std::collections::HashMap;
-use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use chrono::{DateTime, Duration, Utc};
 
 pub struct LiquidityPool {
     pub total_liquidity: u64,
@@ -23,6 +23,217 @@ pub struct TradingPair {
     pub quote_reserve: u64,
     pub last_price: f64,
     pub volume_24h: u64,
+    pub total_shares: u64,
+    pub positions: HashMap<String, LpPosition>,
+    pub price_cumulative: u128,
+    pub last_oracle_update: DateTime<Utc>,
+    pub observations: VecDeque<PriceObservation>,
+    pub volume_buckets: VecDeque<VolumeBucket>,
+}
+
+// One hour of trading activity for a pair
+#[derive(Debug, Clone)]
+pub struct VolumeBucket {
+    pub start: DateTime<Utc>,
+    pub volume: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub trades: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PairStats {
+    pub pair_id: String,
+    pub last_price: f64,
+    pub volume_24h: u64,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: Option<f64>,
+    pub trades_24h: u64,
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+}
+
+// Cumulative quote-per-base price, as a 32.32 fixed-point value multiplied by seconds
+#[derive(Debug, Clone, Copy)]
+pub struct PriceObservation {
+    pub timestamp: DateTime<Utc>,
+    pub price_cumulative: u128,
+}
+
+pub struct LpPosition {
+    pub shares: u64,
+    pub last_deposit: DateTime<Utc>,
+    pub lock_period: Option<u64>,
+}
+
+impl LpPosition {
+    pub fn unlock_time(&self) -> DateTime<Utc> {
+        self.last_deposit + Duration::days(self.lock_period.unwrap_or(0) as i64)
+    }
+}
+
+impl TradingPair {
+    pub const MAX_OBSERVATIONS: usize = 1024;
+    const PRICE_FRACTION_BITS: u32 = 32;
+
+    pub fn new(base_token: String, quote_token: String) -> Self {
+        Self {
+            base_token,
+            quote_token,
+            base_reserve: 0,
+            quote_reserve: 0,
+            last_price: 0.0,
+            volume_24h: 0,
+            total_shares: 0,
+            positions: HashMap::new(),
+            price_cumulative: 0,
+            last_oracle_update: Utc::now(),
+            observations: VecDeque::new(),
+            volume_buckets: VecDeque::new(),
+        }
+    }
+
+    pub fn record_trade(&mut self, now: DateTime<Utc>, volume: u64, price: f64) {
+        let bucket_start = now - Duration::seconds(now.timestamp() % 3600);
+
+        match self.volume_buckets.back_mut() {
+            Some(bucket) if bucket.start == bucket_start => {
+                bucket.volume += volume;
+                bucket.high = bucket.high.max(price);
+                bucket.low = bucket.low.min(price);
+                bucket.close = price;
+                bucket.trades += 1;
+            }
+            _ => self.volume_buckets.push_back(VolumeBucket {
+                start: bucket_start,
+                volume,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                trades: 1,
+            }),
+        }
+
+        self.last_price = price;
+        self.roll_window(now);
+    }
+
+    // Drop buckets that have aged out of the 24h window and recompute the rolling volume
+    pub fn roll_window(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - Duration::hours(24);
+        while self.volume_buckets.front().map_or(false, |b| b.start + Duration::hours(1) <= cutoff) {
+            self.volume_buckets.pop_front();
+        }
+        self.volume_24h = self.volume_buckets.iter().map(|b| b.volume).sum();
+    }
+
+    pub fn stats(&self, now: DateTime<Utc>) -> PairStats {
+        let cutoff = now - Duration::hours(24);
+        let window: Vec<&VolumeBucket> = self.volume_buckets
+            .iter()
+            .filter(|b| b.start + Duration::hours(1) > cutoff)
+            .collect();
+
+        PairStats {
+            pair_id: self.pair_id(),
+            last_price: self.last_price,
+            volume_24h: window.iter().map(|b| b.volume).sum(),
+            open: window.first().map(|b| b.open),
+            high: window.iter().map(|b| b.high).reduce(f64::max),
+            low: window.iter().map(|b| b.low).reduce(f64::min),
+            close: window.last().map(|b| b.close),
+            trades_24h: window.iter().map(|b| b.trades).sum(),
+            base_reserve: self.base_reserve,
+            quote_reserve: self.quote_reserve,
+        }
+    }
+
+    fn spot_price_fixed(&self) -> u128 {
+        if self.base_reserve == 0 {
+            return 0;
+        }
+        ((self.quote_reserve as u128) << Self::PRICE_FRACTION_BITS) / self.base_reserve as u128
+    }
+
+    fn cumulative_at(&self, now: DateTime<Utc>) -> u128 {
+        let elapsed = (now - self.last_oracle_update).num_seconds().max(0) as u128;
+        self.price_cumulative + self.spot_price_fixed() * elapsed
+    }
+
+    // Must run before reserves change so the elapsed interval is priced at the old reserves
+    pub fn update_oracle(&mut self, now: DateTime<Utc>) {
+        if now <= self.last_oracle_update {
+            return;
+        }
+
+        self.price_cumulative = self.cumulative_at(now);
+        self.last_oracle_update = now;
+
+        self.observations.push_back(PriceObservation {
+            timestamp: now,
+            price_cumulative: self.price_cumulative,
+        });
+        if self.observations.len() > Self::MAX_OBSERVATIONS {
+            self.observations.pop_front();
+        }
+    }
+
+    pub fn twap(&self, window: Duration, now: DateTime<Utc>) -> Option<f64> {
+        let window_start = now - window;
+
+        // Use the most recent observation at or before the start of the window
+        let start = self.observations
+            .iter()
+            .rev()
+            .find(|o| o.timestamp <= window_start)?;
+
+        let elapsed = (now - start.timestamp).num_seconds();
+        if elapsed <= 0 {
+            return None;
+        }
+
+        let average = (self.cumulative_at(now) - start.price_cumulative) / elapsed as u128;
+        Some(average as f64 / (1u128 << Self::PRICE_FRACTION_BITS) as f64)
+    }
+
+    pub fn pair_id(&self) -> String {
+        format!("{}-{}", self.base_token, self.quote_token)
+    }
+
+    pub fn shares_of(&self, provider: &str) -> u64 {
+        self.positions.get(provider).map_or(0, |p| p.shares)
+    }
+
+    fn shares_for_deposit(&self, base_amount: u64, quote_amount: u64) -> u64 {
+        if self.total_shares == 0 {
+            // Initial shares are the geometric mean of the deposit
+            return ((base_amount as u128 * quote_amount as u128) as f64).sqrt() as u64;
+        }
+
+        // Mint against the scarcer side so unbalanced deposits can't dilute existing LPs
+        let by_base = base_amount as u128 * self.total_shares as u128 / self.base_reserve as u128;
+        let by_quote = quote_amount as u128 * self.total_shares as u128 / self.quote_reserve as u128;
+        by_base.min(by_quote) as u64
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LiquidityError {
+    #[error("Trading pair not found")]
+    PairNotFound,
+    #[error("Trading pair already exists")]
+    PairExists,
+    #[error("Deposit too small to mint shares")]
+    InsufficientLiquidity,
+    #[error("Insufficient LP shares")]
+    InsufficientShares,
+    #[error("Liquidity locked until {0}")]
+    PositionLocked(DateTime<Utc>),
 }
 
 pub struct LiquidityIncentives {
@@ -49,24 +260,114 @@ impl LiquidityPool {
         }
     }
 
+    pub fn create_pair(&mut self, base_token: String, quote_token: String) -> Result<String, LiquidityError> {
+        let pair = TradingPair::new(base_token, quote_token);
+        let pair_id = pair.pair_id();
+        if self.pairs.contains_key(&pair_id) {
+            return Err(LiquidityError::PairExists);
+        }
+
+        self.pairs.insert(pair_id.clone(), pair);
+        Ok(pair_id)
+    }
+
     pub fn add_liquidity(
         &mut self,
         provider: String,
-        amount: u64,
+        pair_id: &str,
+        base_amount: u64,
+        quote_amount: u64,
         lock_period: Option<u64>,
-    ) -> Result<(), LiquidityError> {
-        let provider_info = LiquidityProvider {
-            address: provider.clone(),
-            liquidity_provided: amount,
-            rewards_earned: 0,
-            last_deposit: Utc::now(),
-            lock_period,
-        };
+    ) -> Result<u64, LiquidityError> {
+        let pair = self.pairs.get_mut(pair_id)
+            .ok_or(LiquidityError::PairNotFound)?;
+
+        let shares = pair.shares_for_deposit(base_amount, quote_amount);
+        if shares == 0 {
+            return Err(LiquidityError::InsufficientLiquidity);
+        }
+
+        let now = Utc::now();
+        pair.update_oracle(now);
+        pair.base_reserve += base_amount;
+        pair.quote_reserve += quote_amount;
+        pair.total_shares += shares;
 
-        self.providers.insert(provider, provider_info);
+        // A new deposit restarts the lock, keeping the longer of the two periods
+        let position = pair.positions.entry(provider.clone()).or_insert(LpPosition {
+            shares: 0,
+            last_deposit: now,
+            lock_period: None,
+        });
+        position.shares += shares;
+        position.last_deposit = now;
+        position.lock_period = position.lock_period.max(lock_period);
+
+        let amount = base_amount + quote_amount;
+        let provider_info = self.providers.entry(provider.clone()).or_insert(LiquidityProvider {
+            address: provider,
+            liquidity_provided: 0,
+            rewards_earned: 0,
+            last_deposit: now,
+            lock_period: None,
+        });
+        provider_info.liquidity_provided += amount;
+        provider_info.last_deposit = now;
+        provider_info.lock_period = provider_info.lock_period.max(lock_period);
         self.total_liquidity += amount;
 
-        Ok(())
+        Ok(shares)
+    }
+
+    pub fn remove_liquidity(
+        &mut self,
+        provider: &str,
+        pair_id: &str,
+        shares: u64,
+    ) -> Result<(u64, u64), LiquidityError> {
+        let pair = self.pairs.get_mut(pair_id)
+            .ok_or(LiquidityError::PairNotFound)?;
+        let position = pair.positions.get_mut(provider)
+            .ok_or(LiquidityError::InsufficientShares)?;
+
+        if shares == 0 || shares > position.shares {
+            return Err(LiquidityError::InsufficientShares);
+        }
+        if Utc::now() < position.unlock_time() {
+            return Err(LiquidityError::PositionLocked(position.unlock_time()));
+        }
+
+        // Redeem a pro-rata slice of both reserves
+        let base_out = (shares as u128 * pair.base_reserve as u128 / pair.total_shares as u128) as u64;
+        let quote_out = (shares as u128 * pair.quote_reserve as u128 / pair.total_shares as u128) as u64;
+
+        position.shares -= shares;
+        if position.shares == 0 {
+            pair.positions.remove(provider);
+        }
+        pair.update_oracle(Utc::now());
+        pair.total_shares -= shares;
+        pair.base_reserve -= base_out;
+        pair.quote_reserve -= quote_out;
+
+        let amount = base_out + quote_out;
+        if let Some(provider_info) = self.providers.get_mut(provider) {
+            provider_info.liquidity_provided = provider_info.liquidity_provided.saturating_sub(amount);
+            if provider_info.liquidity_provided == 0 {
+                self.providers.remove(provider);
+            }
+        }
+        self.total_liquidity = self.total_liquidity.saturating_sub(amount);
+
+        Ok((base_out, quote_out))
+    }
+
+    pub fn get_twap(&self, pair_id: &str, window: Duration) -> Option<f64> {
+        self.pairs.get(pair_id)?.twap(window, Utc::now())
+    }
+
+    pub fn get_pair_stats(&self, pair_id: &str) -> Option<PairStats> {
+        Some(self.pairs.get(pair_id)?.stats(Utc::now()))
     }
 
     pub fn calculate_rewards(&self, provider: &LiquidityProvider) -> u64 {
@@ -181,13 +482,14 @@ impl MarketMaker {
             return Err(SwapError::ExcessivePriceImpact);
         }
 
-        // Update reserves
+        // Accumulate the pre-swap price, then update reserves
+        let now = Utc::now();
+        pair.update_oracle(now);
         pair.base_reserve += amount;
         pair.quote_reserve -= output_amount;
 
-        // Update price and volume
-        pair.last_price = output_amount as f64 / amount as f64;
-        pair.volume_24h += amount;
+        // Update price and rolling volume
+        pair.record_trade(now, amount, output_amount as f64 / amount as f64);
 
         Ok(SwapResult {
             input_amount: amount,
@@ -196,6 +498,27 @@ impl MarketMaker {
             fee_paid: amount * self.amm_params.fee_tier / 10000,
         })
     }
+
+    pub fn get_twap(&self, pair_id: &str, window: Duration) -> Option<f64> {
+        self.pools
+            .get(pair_id)?
+            .get_twap(pair_id, window)
+    }
+
+    pub fn get_pair_stats(&self, pair_id: &str) -> Option<PairStats> {
+        self.pools
+            .get(pair_id)?
+            .get_pair_stats(pair_id)
+    }
+
+    pub fn all_pair_stats(&self) -> Vec<PairStats> {
+        let now = Utc::now();
+        self.pools
+            .values()
+            .flat_map(|pool| pool.pairs.values())
+            .map(|pair| pair.stats(now))
+            .collect()
+    }
 }
 
 pub struct SwapResult {
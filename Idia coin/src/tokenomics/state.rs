@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use super::economics::TokenEconomics;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StateError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] bincode::Error),
+    #[error("No tokenomics snapshot at height {0}")]
+    MissingSnapshot(u64),
+    #[error("Block {got} does not extend tip {tip}")]
+    NonContiguousHeight { tip: u64, got: u64 },
+}
+
+pub struct TokenomicsStore {
+    data_dir: PathBuf,
+    snapshots: BTreeMap<u64, Vec<u8>>,
+    max_reorg_depth: u64,
+}
+
+impl TokenomicsStore {
+    pub const DEFAULT_MAX_REORG_DEPTH: u64 = 100;
+
+    pub fn open(data_dir: PathBuf) -> Result<Self, StateError> {
+        fs::create_dir_all(&data_dir)?;
+
+        // Load every snapshot left on disk by a previous run
+        let mut snapshots = BTreeMap::new();
+        for entry in fs::read_dir(&data_dir)? {
+            let path = entry?.path();
+            let height = path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("tokenomics_"))
+                .and_then(|name| name.strip_suffix(".bin"))
+                .and_then(|height| height.parse::<u64>().ok());
+            if let Some(height) = height {
+                snapshots.insert(height, fs::read(&path)?);
+            }
+        }
+
+        Ok(Self {
+            data_dir,
+            snapshots,
+            max_reorg_depth: Self::DEFAULT_MAX_REORG_DEPTH,
+        })
+    }
+
+    pub fn tip_height(&self) -> Option<u64> {
+        self.snapshots.keys().next_back().copied()
+    }
+
+    pub fn load_tip(&self) -> Result<Option<(u64, TokenEconomics)>, StateError> {
+        match self.tip_height() {
+            Some(height) => Ok(Some((height, self.load(height)?))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn load(&self, height: u64) -> Result<TokenEconomics, StateError> {
+        let bytes = self.snapshots.get(&height)
+            .ok_or(StateError::MissingSnapshot(height))?;
+        Ok(bincode::deserialize(bytes)?)
+    }
+
+    pub fn connect_block(&mut self, height: u64, economics: &TokenEconomics) -> Result<(), StateError> {
+        if let Some(tip) = self.tip_height() {
+            if height != tip + 1 {
+                return Err(StateError::NonContiguousHeight { tip, got: height });
+            }
+        }
+
+        let bytes = bincode::serialize(economics)?;
+        fs::write(self.snapshot_path(height), &bytes)?;
+        self.snapshots.insert(height, bytes);
+
+        // Snapshots deeper than the maximum reorg depth can never be reverted to
+        let keep_from = height.saturating_sub(self.max_reorg_depth);
+        let stale: Vec<u64> = self.snapshots.range(..keep_from).map(|(h, _)| *h).collect();
+        for height in stale {
+            self.snapshots.remove(&height);
+            fs::remove_file(self.snapshot_path(height))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn disconnect_block(&mut self, height: u64) -> Result<TokenEconomics, StateError> {
+        let tip = self.tip_height().ok_or(StateError::MissingSnapshot(height))?;
+        if height != tip {
+            return Err(StateError::NonContiguousHeight { tip, got: height });
+        }
+
+        // Restore the state as of the parent block before dropping this one
+        let parent = height.checked_sub(1).ok_or(StateError::MissingSnapshot(0))?;
+        let economics = self.load(parent)?;
+
+        self.snapshots.remove(&height);
+        fs::remove_file(self.snapshot_path(height))?;
+
+        Ok(economics)
+    }
+
+    fn snapshot_path(&self, height: u64) -> PathBuf {
+        self.data_dir.join(format!("tokenomics_{}.bin", height))
+    }
+}
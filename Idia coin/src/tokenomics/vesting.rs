@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VestingGrant {
+    pub id: u64,
+    pub proposal_id: [u8; 32],
+    pub beneficiary: String,
+    pub purpose: String,
+    pub total_amount: u64,
+    pub claimed: u64,
+    pub start_height: u64,
+    pub cliff_blocks: u64,
+    pub duration_blocks: u64,
+}
+
+impl VestingGrant {
+    pub fn vested_at(&self, height: u64) -> u64 {
+        let elapsed = height.saturating_sub(self.start_height);
+
+        // Nothing vests before the cliff; afterwards release is linear from the start height
+        if elapsed < self.cliff_blocks {
+            0
+        } else if elapsed >= self.duration_blocks {
+            self.total_amount
+        } else {
+            (self.total_amount as u128 * elapsed as u128 / self.duration_blocks as u128) as u64
+        }
+    }
+
+    pub fn claimable_at(&self, height: u64) -> u64 {
+        self.vested_at(height) - self.claimed
+    }
+
+    pub fn is_fully_claimed(&self) -> bool {
+        self.claimed == self.total_amount
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct VestingLedger {
+    grants: HashMap<u64, VestingGrant>,
+    by_beneficiary: HashMap<String, Vec<u64>>,
+    next_grant_id: u64,
+}
+
+impl VestingLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_grant(
+        &mut self,
+        proposal_id: [u8; 32],
+        beneficiary: &str,
+        purpose: &str,
+        total_amount: u64,
+        start_height: u64,
+        cliff_blocks: u64,
+        duration_blocks: u64,
+    ) -> u64 {
+        self.next_grant_id += 1;
+        let id = self.next_grant_id;
+
+        self.grants.insert(id, VestingGrant {
+            id,
+            proposal_id,
+            beneficiary: beneficiary.to_string(),
+            purpose: purpose.to_string(),
+            total_amount,
+            claimed: 0,
+            start_height,
+            cliff_blocks,
+            duration_blocks,
+        });
+        self.by_beneficiary.entry(beneficiary.to_string()).or_default().push(id);
+
+        id
+    }
+
+    pub fn grant(&self, id: u64) -> Option<&VestingGrant> {
+        self.grants.get(&id)
+    }
+
+    pub fn grants_of(&self, beneficiary: &str) -> Vec<&VestingGrant> {
+        self.by_beneficiary
+            .get(beneficiary)
+            .map(|ids| ids.iter().filter_map(|id| self.grants.get(id)).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn claimable(&self, beneficiary: &str, height: u64) -> u64 {
+        self.grants_of(beneficiary).iter().map(|g| g.claimable_at(height)).sum()
+    }
+
+    pub(crate) fn claim(&mut self, beneficiary: &str, height: u64) -> Vec<(VestingGrant, u64)> {
+        let Some(ids) = self.by_beneficiary.get(beneficiary) else {
+            return Vec::new();
+        };
+
+        let mut claims = Vec::new();
+        for id in ids {
+            if let Some(grant) = self.grants.get_mut(id) {
+                let amount = grant.claimable_at(height);
+                if amount > 0 {
+                    grant.claimed += amount;
+                    claims.push((grant.clone(), amount));
+                }
+            }
+        }
+        claims
+    }
+}